@@ -0,0 +1,297 @@
+//! The forward kinematics kernel of optima, as a `#![no_std]` crate that only depends on `core`,
+//! `alloc`, `nalgebra`, and `serde`, so that it can run on an embedded real-time controller.
+//!
+//! optima's `RobotKinematicsModule` computes every joint transform with
+//! `CoreJointComposition::joint_transform`, so FK on the host and on the target share one
+//! implementation.  A `CoreKinematicsModel` is built on the host from a `RobotKinematicsModule` (see
+//! `RobotKinematicsModule::spawn_core_kinematics_model`), serialized, and then evaluated on the
+//! target.  The model supports everything `RobotKinematicsModule::compute_fk` does (standard axis,
+//! arbitrary axis, and ball joints, as well as fixed and coupled joint axes) except TCP frames.  FK
+//! performs no heap allocation after its output buffers are created.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use nalgebra::{Isometry3, Quaternion, Translation3, Unit, UnitQuaternion, Vector3};
+use serde::{Serialize, Deserialize};
+
+/// A compact, `std`-free kinematic model of a robot configuration.  Links are stored in the order in
+/// which they should be visited during FK (parents before children).  full_state_axis_values\[i\]
+/// says where the value of full state axis i comes from.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CoreKinematicsModel {
+    links: Vec<CoreLink>,
+    traversal_order: Vec<usize>,
+    full_state_axis_values: Vec<CoreAxisValue>,
+    num_dofs: usize
+}
+impl CoreKinematicsModel {
+    pub fn new(links: Vec<CoreLink>, traversal_order: Vec<usize>, full_state_axis_values: Vec<CoreAxisValue>) -> Result<Self, CoreKinematicsError> {
+        let num_axes = full_state_axis_values.len();
+        for idx in &traversal_order {
+            if *idx >= links.len() { return Err(CoreKinematicsError::InconsistentModel); }
+        }
+        for link in &links {
+            if let Some(parent) = link.parent_link_idx {
+                if parent >= links.len() { return Err(CoreKinematicsError::InconsistentModel); }
+            }
+            for axis in &link.joint_axes {
+                if axis.full_state_idx >= num_axes { return Err(CoreKinematicsError::InconsistentModel); }
+            }
+            let num_quaternion_components = link.joint_axes.iter().filter(|a| a.axis_type == CoreJointAxisType::QuaternionComponent).count();
+            let expected_num_quaternion_components = if link.joint_composition == CoreJointComposition::Ball { 4 } else { 0 };
+            if num_quaternion_components != expected_num_quaternion_components { return Err(CoreKinematicsError::InconsistentModel); }
+        }
+
+        let mut num_dofs = 0;
+        for axis_value in &full_state_axis_values {
+            match axis_value {
+                CoreAxisValue::Dof(dof_idx) => {
+                    if *dof_idx != num_dofs { return Err(CoreKinematicsError::InconsistentModel); }
+                    num_dofs += 1;
+                }
+                CoreAxisValue::Fixed(_) => {}
+                CoreAxisValue::Coupled { terms, .. } => {
+                    // Coupling constraints cannot be chained.
+                    for (term_full_state_idx, _) in terms {
+                        match full_state_axis_values.get(*term_full_state_idx) {
+                            None | Some(CoreAxisValue::Coupled { .. }) => { return Err(CoreKinematicsError::InconsistentModel); }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            links,
+            traversal_order,
+            full_state_axis_values,
+            num_dofs
+        })
+    }
+    /// Allocates the buffers used by `compute_fk`.  Create this once and reuse it across calls.
+    pub fn spawn_fk_result(&self) -> CoreFKResult {
+        CoreFKResult {
+            full_state: alloc::vec![0.0; self.full_state_axis_values.len()],
+            link_poses: alloc::vec![None; self.links.len()]
+        }
+    }
+    /// Computes the pose of every present link for the given DOF state, writing into output.  Performs
+    /// no heap allocation.
+    pub fn compute_fk(&self, dof_state: &[f64], output: &mut CoreFKResult) -> Result<(), CoreKinematicsError> {
+        if dof_state.len() != self.num_dofs {
+            return Err(CoreKinematicsError::WrongStateLength { given: dof_state.len(), expected: self.num_dofs });
+        }
+        if output.link_poses.len() != self.links.len() || output.full_state.len() != self.full_state_axis_values.len() {
+            return Err(CoreKinematicsError::InconsistentModel);
+        }
+
+        for (i, axis_value) in self.full_state_axis_values.iter().enumerate() {
+            output.full_state[i] = match axis_value {
+                CoreAxisValue::Dof(dof_idx) => { dof_state[*dof_idx] }
+                CoreAxisValue::Fixed(value) => { *value }
+                CoreAxisValue::Coupled { .. } => { 0.0 }
+            };
+        }
+        // Coupled axes only refer to non-coupled axes, which are all set above.
+        for (i, axis_value) in self.full_state_axis_values.iter().enumerate() {
+            if let CoreAxisValue::Coupled { offset, terms } = axis_value {
+                let mut value = *offset;
+                for (term_full_state_idx, coefficient) in terms { value += coefficient * output.full_state[*term_full_state_idx]; }
+                output.full_state[i] = value;
+            }
+        }
+        for pose in output.link_poses.iter_mut() { *pose = None; }
+
+        for link_idx in &self.traversal_order {
+            let link = &self.links[*link_idx];
+            let parent_pose = match link.parent_link_idx {
+                None => { Isometry3::identity() }
+                Some(parent) => {
+                    match &output.link_poses[parent] {
+                        None => { continue; }
+                        Some(parent_pose) => { *parent_pose }
+                    }
+                }
+            };
+
+            let full_state = &output.full_state;
+            let joint_transform = link.joint_composition.joint_transform(link.joint_axes.iter().map(|axis| (axis.axis_type, axis.axis, full_state[axis.full_state_idx])))?;
+            let pose = parent_pose * link.origin_offset * joint_transform;
+
+            output.link_poses[*link_idx] = Some(pose);
+        }
+
+        Ok(())
+    }
+    pub fn num_dofs(&self) -> usize {
+        self.num_dofs
+    }
+    pub fn num_links(&self) -> usize {
+        self.links.len()
+    }
+    pub fn num_axes(&self) -> usize {
+        self.full_state_axis_values.len()
+    }
+}
+
+/// Where the value of a full state joint axis comes from: a DOF of the input state, a fixed value,
+/// or a coupling offset + sum(coefficient * value) over (full state idx, coefficient) terms, which
+/// must refer to non-coupled axes.  DOF indices must be given in increasing order, starting at 0.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CoreAxisValue {
+    Dof(usize),
+    Fixed(f64),
+    Coupled { offset: f64, terms: Vec<(usize, f64)> }
+}
+
+/// A single link along with the joint that precedes it.  A link without a parent is a root link and
+/// its origin_offset is its pose in the world frame.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CoreLink {
+    parent_link_idx: Option<usize>,
+    origin_offset: Isometry3<f64>,
+    joint_axes: Vec<CoreJointAxis>,
+    joint_composition: CoreJointComposition
+}
+impl CoreLink {
+    pub fn new(parent_link_idx: Option<usize>, origin_offset: Isometry3<f64>, joint_axes: Vec<CoreJointAxis>, joint_composition: CoreJointComposition) -> Self {
+        Self {
+            parent_link_idx,
+            origin_offset,
+            joint_axes,
+            joint_composition
+        }
+    }
+}
+
+/// How the joint axes of a `CoreLink` combine into the joint transform.
+/// - `EulerAngles`: all axes are standard axes (positive or negative x, y, or z), and the transform
+///   is a single translation followed by an XYZ Euler angle rotation.
+/// - `AxisSequence`: rotation and translation axes are applied one after another.
+/// - `Ball`: a translation along the translation axes (if any) followed by the rotation given by
+///   exactly four quaternion component axes (w, x, y, z, in that order).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CoreJointComposition {
+    EulerAngles,
+    AxisSequence,
+    Ball
+}
+impl CoreJointComposition {
+    /// The transform of a joint with the given (axis type, axis, value) triples, given in the order
+    /// of the joint's axes.  Joints without axes give the identity.
+    pub fn joint_transform<I: Iterator<Item = (CoreJointAxisType, Unit<Vector3<f64>>, f64)>>(&self, joint_axes: I) -> Result<Isometry3<f64>, CoreKinematicsError> {
+        match self {
+            CoreJointComposition::EulerAngles => {
+                let mut rr = [0.0; 3];
+                let mut tt = [0.0; 3];
+                let mut has_axes = false;
+                for (axis_type, axis, value) in joint_axes {
+                    has_axes = true;
+                    let slot = if axis[0] != 0.0 { 0 } else if axis[1] != 0.0 { 1 } else { 2 };
+                    let signed_value = if axis[slot] < 0.0 { -value } else { value };
+                    match axis_type {
+                        CoreJointAxisType::Rotation => { rr[slot] = signed_value; }
+                        CoreJointAxisType::Translation => { tt[slot] = signed_value; }
+                        CoreJointAxisType::QuaternionComponent => { return Err(CoreKinematicsError::InconsistentModel); }
+                    }
+                }
+                if !has_axes { return Ok(Isometry3::identity()); }
+                Ok(Isometry3::from_parts(Translation3::new(tt[0], tt[1], tt[2]), UnitQuaternion::from_euler_angles(rr[0], rr[1], rr[2])))
+            }
+            CoreJointComposition::AxisSequence => {
+                let mut transform = Isometry3::identity();
+                for (axis_type, axis, value) in joint_axes {
+                    let axis_transform = match axis_type {
+                        CoreJointAxisType::Rotation => { Isometry3::from_parts(Translation3::identity(), UnitQuaternion::from_axis_angle(&axis, value)) }
+                        CoreJointAxisType::Translation => {
+                            let t = axis.into_inner() * value;
+                            Isometry3::from_parts(Translation3::new(t[0], t[1], t[2]), UnitQuaternion::identity())
+                        }
+                        CoreJointAxisType::QuaternionComponent => { return Err(CoreKinematicsError::InconsistentModel); }
+                    };
+                    transform *= axis_transform;
+                }
+                Ok(transform)
+            }
+            CoreJointComposition::Ball => {
+                // Translate along the translation axes, then rotate by the normalized (w, x, y, z)
+                // quaternion given by the quaternion component axes in order.
+                let mut t = Vector3::zeros();
+                let mut q = [0.0; 4];
+                let mut q_idx = 0;
+                for (axis_type, axis, value) in joint_axes {
+                    match axis_type {
+                        CoreJointAxisType::Translation => { t += axis.into_inner() * value; }
+                        CoreJointAxisType::QuaternionComponent => {
+                            if q_idx >= 4 { return Err(CoreKinematicsError::InconsistentModel); }
+                            q[q_idx] = value;
+                            q_idx += 1;
+                        }
+                        CoreJointAxisType::Rotation => { /* Ball joints do not have rotation axes. */ }
+                    }
+                }
+                Ok(Isometry3::from_parts(Translation3::new(t[0], t[1], t[2]), Self::unit_quaternion_from_joint_values(q[0], q[1], q[2], q[3])))
+            }
+        }
+    }
+    /// The rotation of a ball joint with the given (w, x, y, z) joint values: the normalized
+    /// quaternion, or the identity if all values are zero.
+    pub fn unit_quaternion_from_joint_values(w: f64, x: f64, y: f64, z: f64) -> UnitQuaternion<f64> {
+        let q = Quaternion::new(w, x, y, z);
+        if q.norm() == 0.0 { return UnitQuaternion::identity(); }
+        UnitQuaternion::from_quaternion(q)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CoreJointAxis {
+    full_state_idx: usize,
+    axis: Unit<Vector3<f64>>,
+    axis_type: CoreJointAxisType
+}
+impl CoreJointAxis {
+    pub fn new(full_state_idx: usize, axis: Unit<Vector3<f64>>, axis_type: CoreJointAxisType) -> Self {
+        Self {
+            full_state_idx,
+            axis,
+            axis_type
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CoreJointAxisType {
+    Rotation,
+    Translation,
+    QuaternionComponent
+}
+
+/// Preallocated FK buffers.  link_poses\[i\] is None if link i is not present in the configuration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CoreFKResult {
+    full_state: Vec<f64>,
+    link_poses: Vec<Option<Isometry3<f64>>>
+}
+impl CoreFKResult {
+    pub fn link_poses(&self) -> &Vec<Option<Isometry3<f64>>> {
+        &self.link_poses
+    }
+    pub fn link_pose(&self, link_idx: usize) -> Option<&Isometry3<f64>> {
+        match self.link_poses.get(link_idx) {
+            None => { None }
+            Some(pose) => { pose.as_ref() }
+        }
+    }
+}
+
+/// Errors for the core kinematics module.  These do not carry heap-allocated messages so that they
+/// can be constructed without `std`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CoreKinematicsError {
+    WrongStateLength { given: usize, expected: usize },
+    InconsistentModel
+}
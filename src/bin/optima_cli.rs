@@ -0,0 +1,433 @@
+//! Command line tool for preprocessing and inspecting Optima robot assets.  Build with
+//! `cargo build --release --features cli --bin optima`.  Running `optima preprocess <robot>` ahead of
+//! time (e.g., in a Docker build) avoids the lazy preprocessing that otherwise happens the first time a
+//! robot is used by the library.
+
+use std::path::PathBuf;
+use std::process::exit;
+use nalgebra::{DVector, Quaternion, UnitQuaternion, Vector3};
+use serde_json::{json, Value};
+use optima::robot_modules::robot_configuration_module::RobotConfigurationModule;
+use optima::robot_modules::robot_geometric_shape_module::{PreprocessingConfig, RobotLinkShapeRepresentation, SkipDecisionThresholds};
+use optima::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateType};
+use optima::robot_modules::robot_kinematics_module::{JacobianMode, RobotKinematicsModule};
+use optima::robot_modules::robot_preprocessing_module::RobotPreprocessingModule;
+use optima::robot_modules::robot_shape_distance_lookup::DistanceLookupTableConfig;
+use optima::robot_set_modules::robot_set::RobotSet;
+use optima::robot_set_modules::robot_set_joint_state_module::RobotSetJointStateType;
+use optima::scenes::robot_geometric_shape_scene::{RobotGeometricShapeScene, RobotGeometricShapeSceneQuery};
+use optima::utils::utils_console::{optima_print, PrintColor, PrintMode};
+use optima::utils::utils_errors::OptimaError;
+use optima::utils::utils_files::optima_path::{OptimaAssetLocation, OptimaPath, OptimaStemCellPath};
+use optima::utils::utils_robot::robot_ids::LinkId;
+use optima::utils::utils_robot::robot_module_utils::RobotNames;
+use optima::utils::utils_robot::urdf_validation::URDFValidationReport;
+use optima::utils::utils_se3::optima_se3_pose::{OptimaSE3Pose, OptimaSE3PoseType};
+use optima::utils::utils_shape_geometry::geometric_shape::{GeometricShapeSignature, LogCondition, StopCondition};
+use optima::utils::utils_traits::ToAndFromRonString;
+
+const USAGE: &str = "\
+Usage: optima <command> [arguments]
+
+Commands:
+    preprocess <robot | --all> [--replace-model-module] [--replace-convex-shapes] [--replace-convex-subcomponents]
+               [--distance-lookup-tables] [--seed <seed> [--num-samples <n>]]
+               [--always-colliding-ratio <r | off>] [--never-colliding-min-samples <n | off>]
+        Preprocesses robot shape geometry and module files.  --distance-lookup-tables also builds per-pair
+        distance lookup tables for fast approximate distance queries.  --seed makes preprocessing deterministic,
+        drawing exactly --num-samples (default 5000) samples per shape representation.  Pairs colliding in more
+        than --always-colliding-ratio (default 0.99) of samples, or in none of at least --never-colliding-min-samples
+        (default 1000) samples, are skipped; off disables the rule.
+    tree <robot> [--configuration <name>]
+        Prints the robot's link/joint tree.
+    validate <robot | --file <path to urdf>>
+        Checks a URDF for structural problems.  Exits with status 1 if any errors are found.
+    cache-status [robot]
+        Reports which preprocessed assets exist for the given robot (or all robots).
+    fk --robot <robot> --state <values> [--configuration <name>] [--link <name or idx>]
+        Prints link poses as JSON.
+    ik --robot <robot> --link <name or idx> --pose <x,y,z,rx,ry,rz | x,y,z,qw,qx,qy,qz> [--state <initial values>]
+       [--configuration <name>] [--position-only] [--max-iterations <n>] [--tolerance <t>] [--damping <d>]
+        Solves for a DOF joint state that puts the link at the given pose and prints the result as JSON.
+        Exits with status 1 if the solver does not converge.
+    check-collision --robot <robot> --state <values> [--configuration <name>] [--shape-representation <representation>]
+        Checks the state for self collision and prints the result as JSON.
+    help
+        Prints this message.
+
+Joint states may be given as DOF or full states, e.g. --state 0,0.5,0,0,0,0 or --state \"[0, 0.5, 0, 0, 0, 0]\".";
+
+/// Flags that are followed by a value.
+const VALUE_FLAGS: [&str; 14] = ["--configuration", "--file", "--robot", "--state", "--pose", "--link", "--max-iterations", "--tolerance", "--damping", "--shape-representation", "--seed", "--num-samples", "--always-colliding-ratio", "--never-colliding-min-samples"];
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        println!("{}", USAGE);
+        exit(1);
+    }
+
+    let res = match args[0].as_str() {
+        "preprocess" => { preprocess(&args[1..]) }
+        "tree" => { tree(&args[1..]) }
+        "validate" => { validate(&args[1..]) }
+        "cache-status" => { cache_status(&args[1..]) }
+        "fk" => { fk(&args[1..]) }
+        "ik" => { ik(&args[1..]) }
+        "check-collision" => { check_collision(&args[1..]) }
+        "help" | "--help" | "-h" => { println!("{}", USAGE); Ok(true) }
+        other => {
+            optima_print(&format!("Unknown command {:?}.", other), PrintMode::Println, PrintColor::Red, true);
+            println!("{}", USAGE);
+            Ok(false)
+        }
+    };
+
+    match res {
+        Ok(true) => { }
+        Ok(false) => { exit(1); }
+        Err(e) => {
+            optima_print(&format!("{:?}", e), PrintMode::Println, PrintColor::Red, true);
+            exit(1);
+        }
+    }
+}
+
+fn preprocess(args: &[String]) -> Result<bool, OptimaError> {
+    let robot_names = match positional_arg(args) {
+        None => {
+            if has_flag(args, "--all") { all_robot_names()? } else { return Ok(usage_error("preprocess requires a robot name or --all.")); }
+        }
+        Some(robot_name) => { vec![robot_name] }
+    };
+
+    let mut preprocessing_config = match flag_value(args, "--seed") {
+        None => { PreprocessingConfig::default() }
+        Some(seed) => {
+            let seed = match seed.parse::<u64>() {
+                Ok(seed) => { seed }
+                Err(_) => { return Ok(usage_error(&format!("Could not parse seed {:?}.", seed))); }
+            };
+            PreprocessingConfig::new_deterministic(seed, parse_number_flag(args, "--num-samples", 5000.0)? as usize)?
+        }
+    };
+    if has_flag(args, "--distance-lookup-tables") { preprocessing_config.set_distance_lookup_table_config(Some(DistanceLookupTableConfig::default())); }
+    let default_thresholds = SkipDecisionThresholds::default();
+    let always_colliding_ratio = match parse_optional_number_flag(args, "--always-colliding-ratio")? {
+        None => { default_thresholds.always_colliding_ratio() }
+        Some(always_colliding_ratio) => { always_colliding_ratio }
+    };
+    let never_colliding_min_samples = match parse_optional_number_flag(args, "--never-colliding-min-samples")? {
+        None => { default_thresholds.never_colliding_min_samples() }
+        Some(never_colliding_min_samples) => { never_colliding_min_samples.map(|n| n as usize) }
+    };
+    preprocessing_config.set_skip_decision_thresholds(SkipDecisionThresholds::new(always_colliding_ratio, default_thresholds.always_colliding_min_samples(), never_colliding_min_samples)?);
+
+    let preprocessing_module = RobotPreprocessingModule {
+        replace_robot_model_module_json: has_flag(args, "--replace-model-module"),
+        replace_robot_link_convex_shapes: has_flag(args, "--replace-convex-shapes"),
+        replace_robot_link_convex_shape_subcomponents: has_flag(args, "--replace-convex-subcomponents"),
+        preprocessing_config
+    };
+
+    let mut all_succeeded = true;
+    for robot_name in &robot_names {
+        optima_print(&format!("Preprocessing robot {:?}", robot_name), PrintMode::Println, PrintColor::Blue, true);
+        if let Err(e) = preprocessing_module.preprocess_robot(robot_name) {
+            optima_print(&format!("Could not successfully preprocess robot {:?}.  Encountered error {:?}", robot_name, e), PrintMode::Println, PrintColor::Red, true);
+            all_succeeded = false;
+        }
+    }
+
+    Ok(all_succeeded)
+}
+
+fn tree(args: &[String]) -> Result<bool, OptimaError> {
+    let robot_name = match positional_arg(args) {
+        None => { return Ok(usage_error("tree requires a robot name.")); }
+        Some(robot_name) => { robot_name }
+    };
+    let configuration_name = flag_value(args, "--configuration");
+
+    let robot_configuration_module = RobotConfigurationModule::new_from_names(RobotNames::new(&robot_name, configuration_name.as_ref().map(|s| s.as_str())))?;
+    robot_configuration_module.robot_model_module().print_link_joint_tree();
+
+    Ok(true)
+}
+
+fn validate(args: &[String]) -> Result<bool, OptimaError> {
+    let report = match flag_value(args, "--file") {
+        Some(file) => { URDFValidationReport::new_from_path(&OptimaPath::Path(PathBuf::from(file)))? }
+        None => {
+            match positional_arg(args) {
+                None => { return Ok(usage_error("validate requires a robot name or --file <path>.")); }
+                Some(robot_name) => { URDFValidationReport::new_from_robot_name(&robot_name)? }
+            }
+        }
+    };
+
+    report.print_summary();
+    Ok(report.is_valid())
+}
+
+fn cache_status(args: &[String]) -> Result<bool, OptimaError> {
+    let robot_names = match positional_arg(args) {
+        None => { all_robot_names()? }
+        Some(robot_name) => { vec![robot_name] }
+    };
+
+    for robot_name in &robot_names {
+        RobotPreprocessingModule::preprocessing_status(robot_name)?.print_summary();
+    }
+
+    Ok(true)
+}
+
+fn fk(args: &[String]) -> Result<bool, OptimaError> {
+    let robot_kinematics_module = match robot_kinematics_module_from_args(args)? {
+        None => { return Ok(usage_error("fk requires --robot <robot>.")); }
+        Some(robot_kinematics_module) => { robot_kinematics_module }
+    };
+    let joint_state = match flag_value(args, "--state") {
+        None => { return Ok(usage_error("fk requires --state <values>.")); }
+        Some(state) => { parse_joint_state(&robot_kinematics_module, &state)? }
+    };
+    let link_id = match flag_value(args, "--link") {
+        None => { None }
+        Some(link) => { Some(parse_link_id(&robot_kinematics_module, &link)?) }
+    };
+
+    let fk_res = robot_kinematics_module.compute_fk(&joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+    let mut links = vec![];
+    for entry in fk_res.link_entries() {
+        if let Some(link_id) = link_id { if entry.link_idx() != link_id.idx() { continue; } }
+        links.push(json!({
+            "link_idx": entry.link_idx(),
+            "link_name": entry.link_name(),
+            "pose": entry.pose().as_ref().map(|p| pose_to_json(p))
+        }));
+    }
+
+    print_json(&json!({
+        "robot": robot_kinematics_module.robot_name(),
+        "joint_state": joint_state.joint_state().as_slice(),
+        "joint_state_type": format!("{:?}", joint_state.robot_joint_state_type()),
+        "links": links
+    }));
+    Ok(true)
+}
+
+fn ik(args: &[String]) -> Result<bool, OptimaError> {
+    let robot_kinematics_module = match robot_kinematics_module_from_args(args)? {
+        None => { return Ok(usage_error("ik requires --robot <robot>.")); }
+        Some(robot_kinematics_module) => { robot_kinematics_module }
+    };
+    let link_id = match flag_value(args, "--link") {
+        None => { return Ok(usage_error("ik requires --link <name or idx>.")); }
+        Some(link) => { parse_link_id(&robot_kinematics_module, &link)? }
+    };
+    let target_pose = match flag_value(args, "--pose") {
+        None => { return Ok(usage_error("ik requires --pose <x,y,z,rx,ry,rz | x,y,z,qw,qx,qy,qz>.")); }
+        Some(pose) => { parse_pose(&pose)? }
+    };
+    let initial_joint_state = match flag_value(args, "--state") {
+        None => { robot_kinematics_module.robot_joint_state_module().spawn_zeros_robot_joint_state(RobotJointStateType::DOF) }
+        Some(state) => { parse_joint_state(&robot_kinematics_module, &state)? }
+    };
+    let jacobian_mode = if has_flag(args, "--position-only") { JacobianMode::Translational } else { JacobianMode::Full };
+    let max_iterations = parse_number_flag(args, "--max-iterations", 200.0)? as usize;
+    let tolerance = parse_number_flag(args, "--tolerance", 1e-4)?;
+    let damping = parse_number_flag(args, "--damping", 0.05)?;
+
+    let res = robot_kinematics_module.compute_ik_damped_least_squares(&initial_joint_state, link_id, &target_pose, jacobian_mode, max_iterations, tolerance, damping)?;
+
+    print_json(&json!({
+        "robot": robot_kinematics_module.robot_name(),
+        "link_idx": link_id.idx(),
+        "converged": res.converged(),
+        "joint_state": res.joint_state().joint_state().as_slice(),
+        "joint_state_type": "DOF",
+        "position_error": res.position_error(),
+        "rotation_error": res.rotation_error(),
+        "iterations": res.iterations()
+    }));
+    Ok(res.converged())
+}
+
+fn check_collision(args: &[String]) -> Result<bool, OptimaError> {
+    let robot_name = match flag_value(args, "--robot") {
+        None => { return Ok(usage_error("check-collision requires --robot <robot>.")); }
+        Some(robot_name) => { robot_name }
+    };
+    let configuration_name = flag_value(args, "--configuration");
+    let robot_names = RobotNames::new(&robot_name, configuration_name.as_ref().map(|s| s.as_str()));
+    let robot_kinematics_module = RobotKinematicsModule::new_from_names(robot_names.clone())?;
+    let joint_state = match flag_value(args, "--state") {
+        None => { return Ok(usage_error("check-collision requires --state <values>.")); }
+        Some(state) => { parse_joint_state(&robot_kinematics_module, &state)? }
+    };
+    let shape_representation = match flag_value(args, "--shape-representation") {
+        None => { RobotLinkShapeRepresentation::ConvexShapes }
+        Some(s) => { RobotLinkShapeRepresentation::from_ron_string(&s)? }
+    };
+
+    let robot_set = RobotSet::new_from_robot_names(vec![robot_names]);
+    let robot_set_joint_state_type = match joint_state.robot_joint_state_type() {
+        RobotJointStateType::DOF => { RobotSetJointStateType::DOF }
+        RobotJointStateType::Full => { RobotSetJointStateType::Full }
+    };
+    let robot_set_joint_state = robot_set.robot_set_joint_state_module().spawn_robot_set_joint_state(joint_state.joint_state().clone(), robot_set_joint_state_type)?;
+    let scene = RobotGeometricShapeScene::new(robot_set, shape_representation.clone(), vec![])?;
+
+    let input = RobotGeometricShapeSceneQuery::IntersectionTest {
+        robot_set_joint_state: &robot_set_joint_state,
+        env_obj_pose_constraint_group_input: None,
+        inclusion_list: &None
+    };
+    let res = scene.shape_collection_query(&input, StopCondition::None, LogCondition::Intersection, false)?;
+
+    let links = robot_kinematics_module.robot_configuration_module().robot_model_module().links();
+    let link_name = |signature: &GeometricShapeSignature| -> Value {
+        return match signature {
+            GeometricShapeSignature::RobotSetLink { link_idx_in_robot, .. } | GeometricShapeSignature::RobotLink { link_idx: link_idx_in_robot, .. } => { json!(links[*link_idx_in_robot].name()) }
+            other => { json!(format!("{:?}", other)) }
+        }
+    };
+    let mut colliding_pairs = vec![];
+    for output in res.outputs() {
+        let names: Vec<Value> = output.signatures().iter().map(|s| link_name(s)).collect();
+        colliding_pairs.push(Value::Array(names));
+    }
+
+    print_json(&json!({
+        "robot": robot_name,
+        "joint_state": joint_state.joint_state().as_slice(),
+        "shape_representation": format!("{:?}", shape_representation),
+        "in_collision": res.intersection_found(),
+        "num_queries": res.num_queries(),
+        "colliding_link_pairs": colliding_pairs
+    }));
+    Ok(true)
+}
+
+fn robot_kinematics_module_from_args(args: &[String]) -> Result<Option<RobotKinematicsModule>, OptimaError> {
+    let robot_name = match flag_value(args, "--robot") {
+        None => { return Ok(None); }
+        Some(robot_name) => { robot_name }
+    };
+    let configuration_name = flag_value(args, "--configuration");
+    let robot_kinematics_module = RobotKinematicsModule::new_from_names(RobotNames::new(&robot_name, configuration_name.as_ref().map(|s| s.as_str())))?;
+    Ok(Some(robot_kinematics_module))
+}
+
+fn parse_numbers(s: &str) -> Result<Vec<f64>, OptimaError> {
+    let trimmed = s.trim().trim_start_matches('[').trim_end_matches(']');
+    let mut out_vec = vec![];
+    for component in trimmed.split(|c: char| c == ',' || c.is_whitespace()).filter(|c| !c.is_empty()) {
+        match component.parse::<f64>() {
+            Ok(value) => { out_vec.push(value); }
+            Err(_) => { return Err(OptimaError::new_generic_error_str(&format!("Could not parse {:?} as a number.", component), file!(), line!())); }
+        }
+    }
+    Ok(out_vec)
+}
+
+fn parse_number_flag(args: &[String], flag: &str, default: f64) -> Result<f64, OptimaError> {
+    return match flag_value(args, flag) {
+        None => { Ok(default) }
+        Some(value) => {
+            match value.parse::<f64>() {
+                Ok(value) => { Ok(value) }
+                Err(_) => { Err(OptimaError::new_generic_error_str(&format!("Could not parse value {:?} for {}.", value, flag), file!(), line!())) }
+            }
+        }
+    }
+}
+
+fn parse_joint_state(robot_kinematics_module: &RobotKinematicsModule, s: &str) -> Result<RobotJointState, OptimaError> {
+    let values = parse_numbers(s)?;
+    return robot_kinematics_module.robot_joint_state_module().spawn_robot_joint_state_try_auto_type(DVector::from_vec(values));
+}
+
+fn parse_link_id(robot_kinematics_module: &RobotKinematicsModule, s: &str) -> Result<LinkId, OptimaError> {
+    let robot_model_module = robot_kinematics_module.robot_configuration_module().robot_model_module();
+    if let Ok(link_idx) = s.parse::<usize>() {
+        OptimaError::new_check_for_idx_out_of_bound_error(link_idx, robot_model_module.links().len(), file!(), line!())?;
+        return Ok(LinkId::new(link_idx));
+    }
+    return match robot_model_module.get_link_id_from_name(s) {
+        None => { Err(OptimaError::new_generic_error_str(&format!("Robot does not have a link named {:?}.", s), file!(), line!())) }
+        Some(link_id) => { Ok(link_id) }
+    }
+}
+
+/// Parses x,y,z,rx,ry,rz (Euler angles) or x,y,z,qw,qx,qy,qz (quaternion).
+fn parse_pose(s: &str) -> Result<OptimaSE3Pose, OptimaError> {
+    let v = parse_numbers(s)?;
+    return match v.len() {
+        6 => { Ok(OptimaSE3Pose::new_from_euler_angles(v[3], v[4], v[5], v[0], v[1], v[2], &OptimaSE3PoseType::ImplicitDualQuaternion)) }
+        7 => {
+            let q = UnitQuaternion::from_quaternion(Quaternion::new(v[3], v[4], v[5], v[6]));
+            Ok(OptimaSE3Pose::new_unit_quaternion_and_translation(q, Vector3::new(v[0], v[1], v[2])))
+        }
+        n => { Err(OptimaError::new_generic_error_str(&format!("A pose must have 6 (x,y,z,rx,ry,rz) or 7 (x,y,z,qw,qx,qy,qz) values (given {}).", n), file!(), line!())) }
+    }
+}
+
+fn pose_to_json(pose: &OptimaSE3Pose) -> Value {
+    let isometry = pose.to_nalgebra_isometry();
+    let q = isometry.rotation;
+    let (euler_angles, translation) = pose.to_euler_angles_and_translation();
+    return json!({
+        "translation": [translation[0], translation[1], translation[2]],
+        "quaternion_wxyz": [q.w, q.i, q.j, q.k],
+        "euler_angles": [euler_angles[0], euler_angles[1], euler_angles[2]]
+    });
+}
+
+fn print_json(value: &Value) {
+    println!("{}", serde_json::to_string_pretty(value).expect("error"));
+}
+
+fn all_robot_names() -> Result<Vec<String>, OptimaError> {
+    let mut path = OptimaStemCellPath::new_asset_path()?;
+    path.append_file_location(&OptimaAssetLocation::Robots);
+    let mut out_vec = path.get_all_directories_in_directory();
+    out_vec.sort();
+    Ok(out_vec)
+}
+
+/// Returns the first argument that is neither a flag nor the value of a flag that takes a value.
+fn positional_arg(args: &[String]) -> Option<String> {
+    let mut i = 0;
+    while i < args.len() {
+        if VALUE_FLAGS.contains(&args[i].as_str()) { i += 2; continue; }
+        if !args[i].starts_with("--") { return Some(args[i].clone()); }
+        i += 1;
+    }
+    None
+}
+
+/// Returns None if the flag is not given, and Some(None) if its value is "off".
+fn parse_optional_number_flag(args: &[String], flag: &str) -> Result<Option<Option<f64>>, OptimaError> {
+    return match flag_value(args, flag) {
+        Some(value) if value == "off" => { Ok(Some(None)) }
+        Some(_) => { Ok(Some(Some(parse_number_flag(args, flag, 0.0)?))) }
+        None => { Ok(None) }
+    }
+}
+
+fn has_flag(args: &[String], flag: &str) -> bool {
+    args.iter().any(|a| a == flag)
+}
+
+fn flag_value(args: &[String], flag: &str) -> Option<String> {
+    let idx = args.iter().position(|a| a == flag)?;
+    args.get(idx + 1).cloned()
+}
+
+fn usage_error(message: &str) -> bool {
+    optima_print(message, PrintMode::Println, PrintColor::Red, true);
+    println!("{}", USAGE);
+    false
+}
@@ -7,12 +7,17 @@
 
 extern crate core;
 
+pub use optima_core_kinematics as core_kinematics;
+
+pub mod motion_planning;
 pub mod nonlinear_optimization;
 pub mod optima_tensor_function;
 pub mod robot_modules;
 pub mod robot_set_modules;
 pub mod scenes;
 pub mod utils;
+#[cfg(all(feature = "viz", not(target_arch = "wasm32")))]
+pub mod viz;
 
 #[cfg(not(target_arch = "wasm32"))]
 use pyo3::prelude::*;
@@ -21,6 +26,9 @@ use pyo3::prelude::*;
 #[pymodule]
 fn optima(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<scenes::robot_geometric_shape_scene::RobotGeometricShapeScenePy>()?;
+    m.add_class::<scenes::gltf_export::GltfSceneExporter>()?;
+    m.add_class::<utils::utils_shape_geometry::collision_visualization::CollisionVisualization>()?;
+    m.add_class::<utils::utils_shape_geometry::query_output_summary::QueryOutputSummary>()?;
 
     m.add_class::<robot_set_modules::robot_set::RobotSetPy>()?;
     m.add_class::<robot_set_modules::robot_set_configuration_module::RobotSetConfigurationModule>()?;
@@ -33,12 +41,29 @@ fn optima(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<robot_modules::robot_model_module::RobotModelModule>()?;
     m.add_class::<robot_modules::robot_configuration_module::RobotConfigurationModulePy>()?;
     m.add_class::<robot_modules::robot_joint_state_module::RobotJointStateModule>()?;
+    m.add_class::<robot_modules::robot_joint_state_module::RobotJointStateRemapper>()?;
     m.add_class::<robot_modules::robot_kinematics_module::RobotKinematicsModule>()?;
+    m.add_class::<robot_modules::robot_cartesian_jogger::CartesianJogger>()?;
+    m.add_class::<robot_modules::robot_self_motion::SelfMotionExplorer>()?;
+    m.add_class::<robot_modules::robot_poe_model::ProductOfExponentialsModel>()?;
     m.add_class::<robot_modules::robot_geometric_shape_module::RobotGeometricShapeModule>()?;
+    m.add_class::<robot_modules::robot_geometric_shape_module::RobotGeometricShapeModuleSnapshot>()?;
     m.add_class::<robot_modules::robot_mesh_file_manager_module::RobotMeshFileManagerModule>()?;
 
     m.add_class::<utils::utils_se3::optima_se3_pose::OptimaSE3PosePy>()?;
     m.add_class::<utils::utils_se3::optima_rotation::OptimaRotationPy>()?;
+    m.add_class::<utils::utils_files::optima_bundle::OptimaBundle>()?;
+    m.add_class::<utils::utils_files::mesh_uri_resolver::MeshUriResolver>()?;
+    m.add_class::<utils::utils_files::optima_bundle::OptimaBundleManifest>()?;
+    m.add_class::<utils::utils_robot::robot_joint_trajectory::RobotJointTrajectory>()?;
+    m.add_class::<utils::utils_robot::robot_state_recorder::RobotStateRecorder>()?;
+    m.add_class::<utils::utils_robot::robot_state_recorder::RobotStateLog>()?;
+    m.add_class::<utils::utils_robot::robot_joint_state_normalizer::RobotJointStateNormalizer>()?;
+    m.add_class::<utils::utils_robot::robot_dataset_generator::RobotDatasetGenerator>()?;
+    m.add_class::<utils::utils_robot::robot_dataset_generator::RobotDatasetSample>()?;
+    m.add_class::<utils::utils_robot::robot_dataset_generator::RobotDatasetInfo>()?;
+    #[cfg(feature = "viz")]
+    m.add_class::<viz::meshcat::MeshcatVisualizer>()?;
     Ok(())
 }
 
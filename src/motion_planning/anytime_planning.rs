@@ -0,0 +1,315 @@
+use instant::{Duration, Instant};
+use nalgebra::DVector;
+use crate::motion_planning::collision_checking::RobotSetSceneCollisionChecker;
+use crate::motion_planning::goal_set::{GoalSetPath, JointStateGoalSet};
+use crate::motion_planning::rrt_connect::RRTConnectPlanner;
+use crate::robot_set_modules::robot_set_joint_state_module::{RobotSetJointState, RobotSetJointStateType};
+use crate::utils::utils_cancellation::CancellationToken;
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_sampling::SimpleSamplers;
+
+/// Anytime joint space planning in the scene of a `RobotSetSceneCollisionChecker`.  The first
+/// feasible path is found with RRT-Connect and reported right away; the planner then keeps trying to
+/// lower the path's cost (its Euclidean joint space length) until its `CancellationToken` is
+/// cancelled or its deadline passes, and returns the best path along with the history of
+/// improvements.
+///
+/// Each improvement round runs a batch of random partial shortcuts on the best path (two random
+/// points along the path are connected directly if the segment is valid and shorter) and, every
+/// restart_interval rounds, plans a new RRT-Connect path from scratch, which can escape the homotopy
+/// class of the current best path.  Every strict improvement is passed to the callback given to
+/// `plan`, e.g., so a robot can start executing a good enough path early.
+pub struct AnytimePlanner<'a> {
+    collision_checker: &'a RobotSetSceneCollisionChecker<'a>,
+    step_size: f64,
+    rrt_connect_max_iterations: usize,
+    shortcuts_per_round: usize,
+    restart_interval: Option<usize>,
+    max_rounds: Option<usize>
+}
+impl <'a> AnytimePlanner<'a> {
+    /// step_size is the RRT-Connect step size.
+    pub fn new(collision_checker: &'a RobotSetSceneCollisionChecker<'a>, step_size: f64) -> Result<Self, OptimaError> {
+        if !(step_size > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Step size must be positive (given {}).", step_size), file!(), line!()));
+        }
+
+        Ok(Self {
+            collision_checker,
+            step_size,
+            rrt_connect_max_iterations: 5000,
+            shortcuts_per_round: 20,
+            restart_interval: Some(10),
+            max_rounds: None
+        })
+    }
+    pub fn set_rrt_connect_max_iterations(&mut self, rrt_connect_max_iterations: usize) {
+        self.rrt_connect_max_iterations = rrt_connect_max_iterations;
+    }
+    pub fn set_shortcuts_per_round(&mut self, shortcuts_per_round: usize) {
+        self.shortcuts_per_round = shortcuts_per_round;
+    }
+    /// Plans a new path from scratch every restart_interval rounds (None never restarts).
+    pub fn set_restart_interval(&mut self, restart_interval: Option<usize>) -> Result<(), OptimaError> {
+        if restart_interval == Some(0) {
+            return Err(OptimaError::new_generic_error_str("Restart interval must be positive.", file!(), line!()));
+        }
+        self.restart_interval = restart_interval;
+        Ok(())
+    }
+    /// Stops improving after the given number of rounds, even if the token is not cancelled yet.
+    pub fn set_max_rounds(&mut self, max_rounds: Option<usize>) {
+        self.max_rounds = max_rounds;
+    }
+    /// Plans from start to goal, calling on_improvement with every new best solution (starting with
+    /// the first feasible one), until the token is cancelled or max_rounds rounds have run.  Since an
+    /// anytime planner only stops when told to, the token must have a deadline (see
+    /// `CancellationToken::new_with_time_budget`) unless max_rounds is set.  Returns an error if start
+    /// or goal is in collision.
+    pub fn plan<F: FnMut(&AnytimeSolution)>(&self, start: &RobotSetJointState, goal: &RobotSetJointState, cancellation_token: &CancellationToken, on_improvement: F) -> Result<AnytimePlanningOutput, OptimaError> {
+        return self.plan_with_rrt_connect(cancellation_token, on_improvement, |rrt_connect_planner| {
+            let res = rrt_connect_planner.plan_with_cancellation(start, goal, cancellation_token)?;
+            Ok(res.into_output().map(|path| GoalSetPath::new(path, 0)))
+        });
+    }
+    /// Same as `plan`, but plans to any goal of the goal set (see
+    /// `RRTConnectPlanner::plan_to_goal_set`).  Every restart may reach a different goal, so each
+    /// solution reports the goal its path ends at, and shortcuts keep a path's goal.  Returns an
+    /// error if start is in collision or if no goal is collision free.
+    pub fn plan_to_goal_set<F: FnMut(&AnytimeSolution)>(&self, start: &RobotSetJointState, goal_set: &JointStateGoalSet, cancellation_token: &CancellationToken, on_improvement: F) -> Result<AnytimePlanningOutput, OptimaError> {
+        return self.plan_with_rrt_connect(cancellation_token, on_improvement, |rrt_connect_planner| {
+            let res = rrt_connect_planner.plan_to_goal_set_with_cancellation(start, goal_set, cancellation_token)?;
+            Ok(res.into_output())
+        });
+    }
+    pub fn step_size(&self) -> f64 {
+        self.step_size
+    }
+    pub fn rrt_connect_max_iterations(&self) -> usize {
+        self.rrt_connect_max_iterations
+    }
+    pub fn shortcuts_per_round(&self) -> usize {
+        self.shortcuts_per_round
+    }
+    pub fn restart_interval(&self) -> Option<usize> {
+        self.restart_interval
+    }
+    pub fn max_rounds(&self) -> Option<usize> {
+        self.max_rounds
+    }
+    /// The improvement loop shared by `plan` and `plan_to_goal_set`.  plan_from_scratch runs a
+    /// (cancellable) RRT-Connect query with the given planner.
+    fn plan_with_rrt_connect<F: FnMut(&AnytimeSolution), P: Fn(&RRTConnectPlanner) -> Result<Option<GoalSetPath<RobotSetJointState>>, OptimaError>>(&self, cancellation_token: &CancellationToken, mut on_improvement: F, plan_from_scratch: P) -> Result<AnytimePlanningOutput, OptimaError> {
+        if cancellation_token.remaining_time().is_none() && self.max_rounds.is_none() {
+            return Err(OptimaError::new_generic_error_str("Anytime planning needs a cancellation token with a deadline or a maximum number of rounds.", file!(), line!()));
+        }
+        let start_time = Instant::now();
+
+        let mut rrt_connect_planner = RRTConnectPlanner::new(self.collision_checker, self.step_size)?;
+        rrt_connect_planner.set_max_iterations(self.rrt_connect_max_iterations);
+
+        let mut best_path: Option<(Vec<DVector<f64>>, usize)> = None;
+        let mut history = vec![];
+        let mut round = 0;
+        loop {
+            if cancellation_token.is_cancelled() { break; }
+            if let Some(max_rounds) = self.max_rounds { if round >= max_rounds { break; } }
+
+            let restart = match (&best_path, self.restart_interval) {
+                (None, _) => { true }
+                (Some(_), Some(restart_interval)) => { round > 0 && round % restart_interval == 0 }
+                (Some(_), None) => { false }
+            };
+
+            if restart {
+                if let Some(goal_set_path) = plan_from_scratch(&rrt_connect_planner)? {
+                    let goal_idx = goal_set_path.goal_idx();
+                    let path: Vec<DVector<f64>> = goal_set_path.path().iter().map(|s| s.concatenated_state().clone()).collect();
+                    let source = if best_path.is_none() { AnytimeImprovementSource::InitialSolution } else { AnytimeImprovementSource::Restart };
+                    self.consider_path(path, goal_idx, source, round, start_time, &mut best_path, &mut history, &mut on_improvement)?;
+                }
+            }
+
+            if let Some((path, goal_idx)) = &best_path {
+                let mut path = path.clone();
+                let goal_idx = *goal_idx;
+                let mut improved = false;
+                for _ in 0..self.shortcuts_per_round {
+                    if cancellation_token.is_cancelled() { break; }
+                    if self.try_shortcut(&mut path)? { improved = true; }
+                }
+                if improved {
+                    self.consider_path(path, goal_idx, AnytimeImprovementSource::Shortcut, round, start_time, &mut best_path, &mut history, &mut on_improvement)?;
+                }
+            }
+
+            round += 1;
+        }
+
+        let (best_path, best_goal_idx) = match best_path {
+            None => { (None, None) }
+            Some((path, goal_idx)) => { (Some(self.spawn_path(&path)?), Some(goal_idx)) }
+        };
+
+        Ok(AnytimePlanningOutput {
+            best_path,
+            best_goal_idx,
+            history,
+            num_rounds: round,
+            elapsed: Instant::now() - start_time
+        })
+    }
+    /// Adopts the given path (which ends at the goal with the given index) if it is cheaper than the
+    /// current best one.
+    fn consider_path<F: FnMut(&AnytimeSolution)>(&self, path: Vec<DVector<f64>>, goal_idx: usize, source: AnytimeImprovementSource, round: usize, start_time: Instant, best_path: &mut Option<(Vec<DVector<f64>>, usize)>, history: &mut Vec<AnytimeImprovement>, on_improvement: &mut F) -> Result<(), OptimaError> {
+        let cost = Self::path_cost(&path);
+        if let Some((best_path, _)) = best_path {
+            if cost >= Self::path_cost(best_path) { return Ok(()); }
+        }
+
+        let improvement = AnytimeImprovement {
+            cost,
+            elapsed: Instant::now() - start_time,
+            round,
+            source
+        };
+        on_improvement(&AnytimeSolution {
+            path: self.spawn_path(&path)?,
+            goal_idx,
+            improvement: improvement.clone()
+        });
+        history.push(improvement);
+        *best_path = Some((path, goal_idx));
+        Ok(())
+    }
+    /// Connects two random points along the path directly if that is valid and shorter.  Returns
+    /// true if the path was changed.
+    fn try_shortcut(&self, path: &mut Vec<DVector<f64>>) -> Result<bool, OptimaError> {
+        if path.len() < 3 { return Ok(false); }
+        let max_s = (path.len() - 1) as f64;
+        let s1 = SimpleSamplers::uniform_sample((0.0, max_s));
+        let s2 = SimpleSamplers::uniform_sample((0.0, max_s));
+        let (s1, s2) = if s1 < s2 { (s1, s2) } else { (s2, s1) };
+        let (i1, i2) = (s1.floor() as usize, s2.floor() as usize);
+        // Points on the same segment cannot be shortcut.
+        if i1 == i2 { return Ok(false); }
+
+        let p1 = Self::point_along_path(path, s1);
+        let p2 = Self::point_along_path(path, s2);
+        let mut sub_path_cost = (&path[i1 + 1] - &p1).norm() + (&p2 - &path[i2]).norm();
+        for i in (i1 + 1)..i2 { sub_path_cost += (&path[i + 1] - &path[i]).norm(); }
+        if (&p2 - &p1).norm() >= sub_path_cost { return Ok(false); }
+
+        if !self.collision_checker.segment_is_valid(&self.spawn_dof_state(&p1)?, &self.spawn_dof_state(&p2)?)? { return Ok(false); }
+
+        let mut new_path: Vec<DVector<f64>> = path[..=i1].to_vec();
+        new_path.push(p1);
+        new_path.push(p2);
+        new_path.extend(path[(i2 + 1)..].iter().cloned());
+        *path = new_path;
+        Ok(true)
+    }
+    /// The point at parameter s along the path, where waypoint i is at s = i.
+    fn point_along_path(path: &Vec<DVector<f64>>, s: f64) -> DVector<f64> {
+        let i = (s.floor() as usize).min(path.len() - 2);
+        let t = s - i as f64;
+        return &path[i] + (&path[i + 1] - &path[i]) * t;
+    }
+    fn path_cost(path: &Vec<DVector<f64>>) -> f64 {
+        return path.windows(2).map(|w| (&w[1] - &w[0]).norm()).sum();
+    }
+    fn spawn_path(&self, path: &Vec<DVector<f64>>) -> Result<Vec<RobotSetJointState>, OptimaError> {
+        let mut out_vec = vec![];
+        for v in path { out_vec.push(self.spawn_dof_state(v)?); }
+        Ok(out_vec)
+    }
+    fn spawn_dof_state(&self, v: &DVector<f64>) -> Result<RobotSetJointState, OptimaError> {
+        let robot_set_joint_state_module = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        return robot_set_joint_state_module.spawn_robot_set_joint_state(v.clone(), RobotSetJointStateType::DOF);
+    }
+}
+
+/// A solution reported by an `AnytimePlanner` when it improves on its best path.
+#[derive(Clone, Debug)]
+pub struct AnytimeSolution {
+    path: Vec<RobotSetJointState>,
+    goal_idx: usize,
+    improvement: AnytimeImprovement
+}
+impl AnytimeSolution {
+    pub fn path(&self) -> &Vec<RobotSetJointState> {
+        &self.path
+    }
+    /// The index of the goal the path ends at (always 0 for `AnytimePlanner::plan`).
+    pub fn goal_idx(&self) -> usize {
+        self.goal_idx
+    }
+    pub fn improvement(&self) -> &AnytimeImprovement {
+        &self.improvement
+    }
+}
+
+/// One entry of an `AnytimePlanner`'s improvement history.
+#[derive(Clone, Debug)]
+pub struct AnytimeImprovement {
+    cost: f64,
+    elapsed: Duration,
+    round: usize,
+    source: AnytimeImprovementSource
+}
+impl AnytimeImprovement {
+    /// The Euclidean joint space length of the new best path.
+    pub fn cost(&self) -> f64 {
+        self.cost
+    }
+    /// Time since planning started.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+    pub fn round(&self) -> usize {
+        self.round
+    }
+    pub fn source(&self) -> &AnytimeImprovementSource {
+        &self.source
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnytimeImprovementSource {
+    InitialSolution,
+    Restart,
+    Shortcut
+}
+
+/// The output of `AnytimePlanner::plan`.
+#[derive(Clone, Debug)]
+pub struct AnytimePlanningOutput {
+    best_path: Option<Vec<RobotSetJointState>>,
+    best_goal_idx: Option<usize>,
+    history: Vec<AnytimeImprovement>,
+    num_rounds: usize,
+    elapsed: Duration
+}
+impl AnytimePlanningOutput {
+    /// The best path found, or None if no feasible path was found in time.
+    pub fn best_path(&self) -> &Option<Vec<RobotSetJointState>> {
+        &self.best_path
+    }
+    /// The index of the goal the best path ends at (always 0 for `AnytimePlanner::plan`).
+    pub fn best_goal_idx(&self) -> Option<usize> {
+        self.best_goal_idx
+    }
+    /// Every improvement of the best path, in order (the first entry is the initial solution).
+    pub fn history(&self) -> &Vec<AnytimeImprovement> {
+        &self.history
+    }
+    pub fn best_cost(&self) -> Option<f64> {
+        return self.history.last().map(|i| i.cost);
+    }
+    pub fn num_rounds(&self) -> usize {
+        self.num_rounds
+    }
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
@@ -0,0 +1,177 @@
+use crate::robot_set_modules::robot_set_joint_state_module::RobotSetJointState;
+use crate::scenes::robot_geometric_shape_scene::{EnvObjPoseConstraintGroupInput, RobotGeometricShapeScene, RobotGeometricShapeSceneQuery};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_shape_geometry::geometric_shape::{GeometricShapeSignature, LogCondition, StopCondition};
+
+/// Checks robot set joint states and straight-line joint space segments between them for collisions
+/// in a `RobotGeometricShapeScene`.  Segments are checked by discretizing them such that consecutive
+/// checked states are at most `resolution` apart (in Euclidean joint space distance).
+///
+/// Environment object poses can be overridden for all checks without changing the scene (see
+/// `set_env_obj_pose_constraint_group_input`), e.g., to make a grasped object follow the gripper,
+/// and contacts between an environment object and a robot can be ignored (see
+/// `add_ignored_env_obj_robot_contact`), e.g., between a grasped object and the robot holding it.
+pub struct RobotSetSceneCollisionChecker<'a> {
+    robot_geometric_shape_scene: &'a RobotGeometricShapeScene,
+    resolution: f64,
+    env_obj_pose_constraint_group_input: Option<EnvObjPoseConstraintGroupInput>,
+    ignored_env_obj_robot_contacts: Vec<(usize, usize)>
+}
+impl <'a> RobotSetSceneCollisionChecker<'a> {
+    pub fn new(robot_geometric_shape_scene: &'a RobotGeometricShapeScene, resolution: f64) -> Result<Self, OptimaError> {
+        if resolution <= 0.0 {
+            return Err(OptimaError::new_generic_error_str(&format!("Collision checking resolution must be positive (given {}).", resolution), file!(), line!()));
+        }
+
+        Ok(Self {
+            robot_geometric_shape_scene,
+            resolution,
+            env_obj_pose_constraint_group_input: None,
+            ignored_env_obj_robot_contacts: vec![]
+        })
+    }
+    /// Overrides the pose constraints of environment objects in all checks (None reverts to the
+    /// constraints saved in the scene).
+    pub fn set_env_obj_pose_constraint_group_input(&mut self, env_obj_pose_constraint_group_input: Option<EnvObjPoseConstraintGroupInput>) {
+        self.env_obj_pose_constraint_group_input = env_obj_pose_constraint_group_input;
+    }
+    /// Ignores contacts between the given environment object and any link of the given robot.
+    pub fn add_ignored_env_obj_robot_contact(&mut self, env_obj_idx: usize, robot_idx_in_set: usize) -> Result<(), OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(env_obj_idx, self.robot_geometric_shape_scene.num_env_objs(), file!(), line!())?;
+        if !self.ignored_env_obj_robot_contacts.contains(&(env_obj_idx, robot_idx_in_set)) {
+            self.ignored_env_obj_robot_contacts.push((env_obj_idx, robot_idx_in_set));
+        }
+        Ok(())
+    }
+    pub fn clear_ignored_env_obj_robot_contacts(&mut self) {
+        self.ignored_env_obj_robot_contacts.clear();
+    }
+    /// Returns true if the given state is collision free.
+    pub fn state_is_valid(&self, robot_set_joint_state: &RobotSetJointState) -> Result<bool, OptimaError> {
+        let input = RobotGeometricShapeSceneQuery::IntersectionTest {
+            robot_set_joint_state,
+            env_obj_pose_constraint_group_input: self.env_obj_pose_constraint_group_input.as_ref(),
+            inclusion_list: &None
+        };
+        // With ignored contacts, all intersections are needed to tell them apart from real ones.
+        let stop_condition = if self.ignored_env_obj_robot_contacts.is_empty() { StopCondition::Intersection } else { StopCondition::None };
+        let res = self.robot_geometric_shape_scene.shape_collection_query(&input, stop_condition, LogCondition::Intersection, false)?;
+        if !res.intersection_found() { return Ok(true); }
+
+        for output in res.outputs() {
+            if !output.raw_output().unwrap_intersection_test()? { continue; }
+            if !self.is_ignored_contact(output.signatures()) { return Ok(false); }
+        }
+        Ok(true)
+    }
+    /// Returns true if all interpolated states strictly between a and b are collision free.  The end
+    /// points themselves are not checked.
+    pub fn segment_is_valid(&self, a: &RobotSetJointState, b: &RobotSetJointState) -> Result<bool, OptimaError> {
+        let a_vec = a.concatenated_state();
+        let b_vec = b.concatenated_state();
+        if a_vec.len() != b_vec.len() {
+            return Err(OptimaError::new_generic_error_str(&format!("Segment end points have different lengths ({} and {}).", a_vec.len(), b_vec.len()), file!(), line!()));
+        }
+
+        let num_steps = ((b_vec - a_vec).norm() / self.resolution).ceil() as usize;
+        for i in 1..num_steps {
+            let t = i as f64 / num_steps as f64;
+            let state = self.interpolate(a, b, t)?;
+            if !self.state_is_valid(&state)? { return Ok(false); }
+        }
+
+        Ok(true)
+    }
+    /// Same as `segment_is_valid`, but if the segment is invalid, the boundary between the valid
+    /// start of the segment and the first invalid interpolated state is refined by bisection until
+    /// the last valid state and first invalid state are at most tolerance apart (in Euclidean joint
+    /// space distance).  Planners can use the last valid state to insert a node as far along the
+    /// segment as possible (e.g., when extending an RRT).
+    ///
+    /// As with `segment_is_valid`, the end points are not checked, so the last valid state is a
+    /// itself (at t = 0) if the segment is invalid right away.
+    pub fn validate_segment(&self, a: &RobotSetJointState, b: &RobotSetJointState, tolerance: f64) -> Result<SegmentValidationOutput, OptimaError> {
+        if tolerance <= 0.0 {
+            return Err(OptimaError::new_generic_error_str(&format!("Tolerance must be positive (given {}).", tolerance), file!(), line!()));
+        }
+        let a_vec = a.concatenated_state();
+        let b_vec = b.concatenated_state();
+        if a_vec.len() != b_vec.len() {
+            return Err(OptimaError::new_generic_error_str(&format!("Segment end points have different lengths ({} and {}).", a_vec.len(), b_vec.len()), file!(), line!()));
+        }
+
+        let length = (b_vec - a_vec).norm();
+        let num_steps = (length / self.resolution).ceil() as usize;
+        let mut first_invalid = None;
+        for i in 1..num_steps {
+            let t = i as f64 / num_steps as f64;
+            let state = self.interpolate(a, b, t)?;
+            if !self.state_is_valid(&state)? {
+                first_invalid = Some((t, state));
+                break;
+            }
+        }
+
+        let (mut first_invalid_t, mut first_invalid_state) = match first_invalid {
+            None => { return Ok(SegmentValidationOutput::Valid); }
+            Some(first_invalid) => { first_invalid }
+        };
+        let mut last_valid_t = first_invalid_t - 1.0 / num_steps as f64;
+        let mut last_valid_state = if last_valid_t <= 0.0 { a.clone() } else { self.interpolate(a, b, last_valid_t)? };
+
+        while (first_invalid_t - last_valid_t) * length > tolerance {
+            let t = 0.5 * (last_valid_t + first_invalid_t);
+            let state = self.interpolate(a, b, t)?;
+            if self.state_is_valid(&state)? {
+                last_valid_t = t;
+                last_valid_state = state;
+            } else {
+                first_invalid_t = t;
+                first_invalid_state = state;
+            }
+        }
+
+        Ok(SegmentValidationOutput::Invalid {
+            last_valid_state,
+            last_valid_t,
+            first_invalid_state,
+            first_invalid_t
+        })
+    }
+    pub fn resolution(&self) -> f64 {
+        self.resolution
+    }
+    pub fn robot_geometric_shape_scene(&self) -> &RobotGeometricShapeScene {
+        self.robot_geometric_shape_scene
+    }
+    fn interpolate(&self, a: &RobotSetJointState, b: &RobotSetJointState, t: f64) -> Result<RobotSetJointState, OptimaError> {
+        return self.robot_geometric_shape_scene.robot_set().robot_set_joint_state_module().interpolate_robot_set_joint_states(a, b, t);
+    }
+    fn is_ignored_contact(&self, signatures: &Vec<GeometricShapeSignature>) -> bool {
+        let ignored = |a: &GeometricShapeSignature, b: &GeometricShapeSignature| {
+            match (a, b) {
+                (GeometricShapeSignature::EnvironmentObject { environment_object_idx, .. }, GeometricShapeSignature::RobotSetLink { robot_idx_in_set, .. }) => {
+                    self.ignored_env_obj_robot_contacts.contains(&(*environment_object_idx, *robot_idx_in_set))
+                }
+                _ => { false }
+            }
+        };
+        return ignored(&signatures[0], &signatures[1]) || ignored(&signatures[1], &signatures[0]);
+    }
+}
+
+/// The output of `RobotSetSceneCollisionChecker::validate_segment`.  t values are the interpolation
+/// parameters along the segment, from 0 (at a) to 1 (at b).
+#[derive(Clone, Debug)]
+pub enum SegmentValidationOutput {
+    Valid,
+    Invalid { last_valid_state: RobotSetJointState, last_valid_t: f64, first_invalid_state: RobotSetJointState, first_invalid_t: f64 }
+}
+impl SegmentValidationOutput {
+    pub fn is_valid(&self) -> bool {
+        return match self {
+            SegmentValidationOutput::Valid => { true }
+            SegmentValidationOutput::Invalid { .. } => { false }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use nalgebra::DVector;
+use crate::robot_modules::robot_geometric_shape_module::{RobotGeometricShapeModule, RobotLinkShapeRepresentation, RobotShapeCollectionQuery};
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateType};
+use crate::robot_modules::robot_kinematics_module::{JacobianEndPoint, JacobianMode};
+use crate::utils::utils_cancellation::{CancellableOutput, CancellationToken};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_robot::robot_ids::LinkId;
+use crate::utils::utils_shape_geometry::geometric_shape::{GeometricShapeSignature, LogCondition, StopCondition};
+
+/// Samples DOF joint states that are already verified to be free of self collision, so callers such
+/// as PRM construction never have to reject samples themselves.
+///
+/// Each attempt draws a uniform sample within the joint bounds.  If the sample is invalid, it is
+/// projected out of collision by repeatedly pushing every offending pair of links apart along their
+/// contact normal (using the translational Jacobians at the contact points), clamping to the joint
+/// bounds after every step.  If the projection does not reach a valid state within
+/// max_projection_iterations steps, a fresh sample is drawn, up to max_attempts times.
+///
+/// With a clearance, a state is only valid if every checked pair of shapes is at least clearance
+/// apart; without one, a state is valid if no pair intersects.  Projection can bias samples toward
+/// the boundary of the free space; set max_projection_iterations to 0 for purely uniform
+/// rejection sampling.
+pub struct SelfCollisionFreeSampler<'a> {
+    robot_geometric_shape_module: &'a RobotGeometricShapeModule,
+    robot_link_shape_representation: RobotLinkShapeRepresentation,
+    clearance: Option<f64>,
+    max_attempts: usize,
+    max_projection_iterations: usize
+}
+impl <'a> SelfCollisionFreeSampler<'a> {
+    pub fn new(robot_geometric_shape_module: &'a RobotGeometricShapeModule, robot_link_shape_representation: RobotLinkShapeRepresentation, clearance: Option<f64>) -> Result<Self, OptimaError> {
+        if let Some(clearance) = clearance {
+            if !(clearance >= 0.0) {
+                return Err(OptimaError::new_generic_error_str(&format!("Clearance must be non-negative (given {}).", clearance), file!(), line!()));
+            }
+        }
+
+        Ok(Self {
+            robot_geometric_shape_module,
+            robot_link_shape_representation,
+            clearance,
+            max_attempts: 1000,
+            max_projection_iterations: 10
+        })
+    }
+    pub fn set_max_attempts(&mut self, max_attempts: usize) -> Result<(), OptimaError> {
+        if max_attempts == 0 {
+            return Err(OptimaError::new_generic_error_str("Max attempts must be at least 1.", file!(), line!()));
+        }
+        self.max_attempts = max_attempts;
+        Ok(())
+    }
+    pub fn set_max_projection_iterations(&mut self, max_projection_iterations: usize) {
+        self.max_projection_iterations = max_projection_iterations;
+    }
+    /// Returns a DOF joint state that satisfies `state_is_valid`.  Returns an error if no such state
+    /// is found within max_attempts attempts.
+    pub fn sample(&self) -> Result<RobotJointState, OptimaError> {
+        let robot_joint_state_module = self.robot_geometric_shape_module.robot_joint_state_module();
+        for _ in 0..self.max_attempts {
+            let mut sample = robot_joint_state_module.sample_joint_state(&RobotJointStateType::DOF);
+            if self.state_is_valid(&sample)? { return Ok(sample); }
+
+            for _ in 0..self.max_projection_iterations {
+                if !self.projection_step(&mut sample)? { break; }
+                if self.state_is_valid(&sample)? { return Ok(sample); }
+            }
+        }
+
+        Err(OptimaError::new_generic_error_str(&format!("Could not find a collision free sample within {} attempts.", self.max_attempts), file!(), line!()))
+    }
+    /// Returns n samples from `sample`.
+    pub fn sample_n(&self, n: usize) -> Result<Vec<RobotJointState>, OptimaError> {
+        let mut out_vec = vec![];
+        for _ in 0..n {
+            out_vec.push(self.sample()?);
+        }
+        Ok(out_vec)
+    }
+    /// Same as `sample_n`, but stops drawing samples once the given token is cancelled and returns
+    /// the samples found so far.
+    pub fn sample_n_with_cancellation(&self, n: usize, cancellation_token: &CancellationToken) -> Result<CancellableOutput<Vec<RobotJointState>>, OptimaError> {
+        let mut out_vec = vec![];
+        for _ in 0..n {
+            if cancellation_token.is_cancelled() { return Ok(CancellableOutput::Cancelled(out_vec)); }
+            out_vec.push(self.sample()?);
+        }
+        Ok(CancellableOutput::Complete(out_vec))
+    }
+    /// Returns true if the given state is free of self collision (and satisfies the clearance, if
+    /// one is set).
+    pub fn state_is_valid(&self, robot_joint_state: &RobotJointState) -> Result<bool, OptimaError> {
+        return match self.clearance {
+            None => {
+                let input = RobotShapeCollectionQuery::IntersectionTest {
+                    robot_joint_state,
+                    inclusion_list: None
+                };
+                let res = self.robot_geometric_shape_module.shape_collection_query(&input, self.robot_link_shape_representation.clone(), StopCondition::Intersection, LogCondition::Intersection, false)?;
+                Ok(!res.intersection_found())
+            }
+            Some(clearance) => {
+                let input = RobotShapeCollectionQuery::Contact {
+                    robot_joint_state,
+                    prediction: clearance,
+                    inclusion_list: &None
+                };
+                let res = self.robot_geometric_shape_module.shape_collection_query(&input, self.robot_link_shape_representation.clone(), StopCondition::BelowMinDistance(clearance), LogCondition::BelowMinDistance(clearance), false)?;
+                Ok(!res.intersection_found() && res.minimum_distance() >= clearance)
+            }
+        };
+    }
+    pub fn robot_link_shape_representation(&self) -> &RobotLinkShapeRepresentation {
+        &self.robot_link_shape_representation
+    }
+    pub fn clearance(&self) -> Option<f64> {
+        self.clearance
+    }
+    pub fn max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+    pub fn max_projection_iterations(&self) -> usize {
+        self.max_projection_iterations
+    }
+    /// Moves the state one step away from all contacts closer than the clearance.  Returns false if
+    /// there was nothing to push against (e.g., a contact between links with no DOFs in between).
+    fn projection_step(&self, robot_joint_state: &mut RobotJointState) -> Result<bool, OptimaError> {
+        // Aim slightly beyond the clearance so the projected state is not left exactly on the
+        // boundary.
+        let target = self.clearance.unwrap_or(0.0) + 0.005;
+
+        let input = RobotShapeCollectionQuery::Contact {
+            robot_joint_state,
+            prediction: target,
+            inclusion_list: &None
+        };
+        let res = self.robot_geometric_shape_module.shape_collection_query(&input, self.robot_link_shape_representation.clone(), StopCondition::None, LogCondition::BelowMinDistance(target), false)?;
+
+        let robot_kinematics_module = self.robot_geometric_shape_module.robot_kinematics_module();
+        let num_dofs = robot_joint_state.len();
+        let mut delta: DVector<f64> = DVector::zeros(num_dofs);
+        let mut moved = false;
+
+        for output in res.outputs() {
+            let contact = match output.raw_output().unwrap_contact()? {
+                None => { continue; }
+                Some(contact) => { contact }
+            };
+            let signatures = output.signatures();
+            let (link_id1, link_id2) = match (&signatures[0], &signatures[1]) {
+                (GeometricShapeSignature::RobotLink { link_idx: l1, .. }, GeometricShapeSignature::RobotLink { link_idx: l2, .. }) => { (LinkId::new(*l1), LinkId::new(*l2)) }
+                _ => { continue; }
+            };
+
+            // The gradient of the signed distance is n . (J2(p2) - J1(p1)), where n points from the
+            // first shape toward the second.
+            let jacobian1 = robot_kinematics_module.compute_jacobian(robot_joint_state, None, link_id1, &JacobianEndPoint::Global(contact.point1), None, JacobianMode::Translational)?;
+            let jacobian2 = robot_kinematics_module.compute_jacobian(robot_joint_state, None, link_id2, &JacobianEndPoint::Global(contact.point2), None, JacobianMode::Translational)?;
+            let gradient: DVector<f64> = (jacobian2 - jacobian1).transpose() * contact.normal1;
+
+            let gradient_norm_squared = gradient.norm_squared();
+            if gradient_norm_squared < 1e-12 { continue; }
+            delta += gradient * ((target - contact.dist) / gradient_norm_squared);
+            moved = true;
+        }
+
+        if !moved { return Ok(false); }
+
+        let bounds = self.robot_geometric_shape_module.robot_joint_state_module().get_joint_state_bounds(&RobotJointStateType::DOF);
+        let mut new_state = robot_joint_state.joint_state() + delta;
+        for (i, (lower, upper)) in bounds.iter().enumerate() {
+            new_state[i] = new_state[i].max(*lower).min(*upper);
+        }
+        *robot_joint_state = self.robot_geometric_shape_module.robot_joint_state_module().spawn_robot_joint_state(new_state, RobotJointStateType::DOF)?;
+
+        Ok(true)
+    }
+}
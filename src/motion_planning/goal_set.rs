@@ -0,0 +1,244 @@
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_joint_state_module::RobotJointStateType;
+use crate::robot_modules::robot_kinematics_module::RobotKinematicsModule;
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_nearest_neighbor::JointStateDistanceMetric;
+use crate::utils::utils_robot::robot_ids::LinkId;
+use crate::utils::utils_se3::optima_se3_pose::OptimaSE3Pose;
+use crate::utils::utils_se3::pose_error_metric::PoseErrorMetric;
+
+/// A planning goal given as a set of goal states, any one of which satisfies the query (e.g., all IK
+/// solutions for a target pose).  The planners' goal set entry points
+/// (`RRTConnectPlanner::plan_to_goal_set`, `PRMPlanner::query_goal_set`,
+/// `LazyPRMPlanner::query_goal_set`, `KinodynamicRRTPlanner::plan_to_goal_set`,
+/// `AnytimePlanner::plan_to_goal_set` and `ExperiencePlanner::plan_to_goal_set`) terminate as soon
+/// as any goal is reached and report the reached goal's index so the caller knows which goal was
+/// achieved.  Goal indices are stable: goals are only ever appended.
+///
+/// Goals can be given up front, added one at a time, or drawn from a `GoalSampler` (see
+/// `add_goals_from_sampler`), so planners that grow the goal set while planning (e.g., by sampling
+/// more IK solutions when progress stalls) can use the same structure.  States are plain vectors,
+/// so the set works for single robot DOF states and concatenated robot set states alike.  Goals
+/// are searched linearly, which is fast for the handful to few hundred goals of a typical query.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JointStateGoalSet {
+    goals: Vec<DVector<f64>>,
+    tolerance: f64,
+    metric: JointStateDistanceMetric
+}
+impl JointStateGoalSet {
+    /// A state reaches a goal if it is within tolerance of it under the given metric.
+    pub fn new(goals: Vec<DVector<f64>>, tolerance: f64, metric: JointStateDistanceMetric) -> Result<Self, OptimaError> {
+        if !(tolerance >= 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Goal tolerance must be non-negative (given {}).", tolerance), file!(), line!()));
+        }
+
+        let mut out_self = Self {
+            goals: vec![],
+            tolerance,
+            metric
+        };
+        for goal in goals { out_self.add_goal(goal)?; }
+
+        Ok(out_self)
+    }
+    pub fn new_single_goal(goal: DVector<f64>, tolerance: f64) -> Result<Self, OptimaError> {
+        return Self::new(vec![goal], tolerance, JointStateDistanceMetric::Euclidean);
+    }
+    /// Adds a goal and returns its index.
+    pub fn add_goal(&mut self, goal: DVector<f64>) -> Result<usize, OptimaError> {
+        if let Some(first) = self.goals.first() {
+            if first.len() != goal.len() {
+                return Err(OptimaError::new_generic_error_str(&format!("Goal has dimension {}, but the goal set has dimension {}.", goal.len(), first.len()), file!(), line!()));
+            }
+        }
+        if let JointStateDistanceMetric::WeightedEuclidean { weights } = &self.metric {
+            if weights.len() != goal.len() {
+                return Err(OptimaError::new_generic_error_str(&format!("Metric has {} weights, but the goal has dimension {}.", weights.len(), goal.len()), file!(), line!()));
+            }
+        }
+
+        self.goals.push(goal);
+        Ok(self.goals.len() - 1)
+    }
+    /// Draws up to num_attempts goals from the sampler and adds the ones that are not within
+    /// min_separation of a goal already in the set, so repeated solutions (e.g., IK solves that land
+    /// in the same branch) do not crowd the set.  Returns the indices of the added goals.
+    pub fn add_goals_from_sampler(&mut self, sampler: &dyn GoalSampler, num_attempts: usize, min_separation: f64) -> Result<Vec<usize>, OptimaError> {
+        let mut out_vec = vec![];
+        for _ in 0..num_attempts {
+            let goal = match sampler.sample_goal()? {
+                None => { continue; }
+                Some(goal) => { goal }
+            };
+            if let Some(nearest) = self.nearest_goal(&goal) {
+                if nearest.distance() <= min_separation { continue; }
+            }
+            out_vec.push(self.add_goal(goal)?);
+        }
+        Ok(out_vec)
+    }
+    /// Returns the nearest goal if the state is within tolerance of it, and None otherwise.
+    pub fn reached_goal(&self, state: &DVector<f64>) -> Option<GoalSetMatch> {
+        let nearest = self.nearest_goal(state)?;
+        return if nearest.distance() <= self.tolerance { Some(nearest) } else { None };
+    }
+    /// Returns the goal nearest to the state (e.g., to bias tree growth toward the closest goal).
+    /// Returns None if the set is empty.
+    pub fn nearest_goal(&self, state: &DVector<f64>) -> Option<GoalSetMatch> {
+        let mut out_match: Option<GoalSetMatch> = None;
+        for (goal_idx, goal) in self.goals.iter().enumerate() {
+            if goal.len() != state.len() { continue; }
+            let distance = self.metric.distance(state, goal);
+            let is_nearer = match &out_match {
+                None => { true }
+                Some(m) => { distance < m.distance }
+            };
+            if is_nearer { out_match = Some(GoalSetMatch { goal_idx, distance }); }
+        }
+        out_match
+    }
+    pub fn goal(&self, goal_idx: usize) -> Result<&DVector<f64>, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(goal_idx, self.goals.len(), file!(), line!())?;
+        Ok(&self.goals[goal_idx])
+    }
+    pub fn goals(&self) -> &Vec<DVector<f64>> {
+        &self.goals
+    }
+    pub fn len(&self) -> usize {
+        self.goals.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.goals.is_empty()
+    }
+    pub fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+    pub fn metric(&self) -> &JointStateDistanceMetric {
+        &self.metric
+    }
+    /// Returns an error if any goal does not have the given dimension.
+    pub(crate) fn check_dimension(&self, function_name: &str, dimension: usize) -> Result<(), OptimaError> {
+        if let Some(goal) = self.goals.iter().find(|goal| goal.len() != dimension) {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error(function_name, goal.len(), dimension, file!(), line!()));
+        }
+        Ok(())
+    }
+    /// The goals that pass the given validity check, as (goal index, goal) pairs.  Returns an error
+    /// if none do.
+    pub(crate) fn valid_goals<F: Fn(&DVector<f64>) -> Result<bool, OptimaError>>(&self, state_is_valid: F) -> Result<Vec<(usize, DVector<f64>)>, OptimaError> {
+        let mut out_vec = vec![];
+        for (goal_idx, goal) in self.goals.iter().enumerate() {
+            if state_is_valid(goal)? { out_vec.push((goal_idx, goal.clone())); }
+        }
+        if out_vec.is_empty() {
+            return Err(OptimaError::new_generic_error_str(&format!("None of the {} goals of the goal set is valid.", self.goals.len()), file!(), line!()));
+        }
+        Ok(out_vec)
+    }
+}
+
+/// The goal of a `JointStateGoalSet` matched by a state, along with the distance between the two.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GoalSetMatch {
+    goal_idx: usize,
+    distance: f64
+}
+impl GoalSetMatch {
+    pub fn goal_idx(&self) -> usize {
+        self.goal_idx
+    }
+    pub fn distance(&self) -> f64 {
+        self.distance
+    }
+}
+
+/// A path to one of the goals of a `JointStateGoalSet`, as returned by the planners' goal set entry
+/// points (e.g., `RRTConnectPlanner::plan_to_goal_set`).  The path ends at (or, for a start that is
+/// already within tolerance of a goal, only consists of) the reached goal.
+#[derive(Clone, Debug)]
+pub struct GoalSetPath<T> {
+    path: Vec<T>,
+    goal_idx: usize
+}
+impl <T> GoalSetPath<T> {
+    pub fn new(path: Vec<T>, goal_idx: usize) -> Self {
+        Self {
+            path,
+            goal_idx
+        }
+    }
+    pub fn path(&self) -> &Vec<T> {
+        &self.path
+    }
+    /// The index of the reached goal in the goal set.
+    pub fn goal_idx(&self) -> usize {
+        self.goal_idx
+    }
+    pub fn into_path(self) -> Vec<T> {
+        self.path
+    }
+}
+
+/// A source of goal states for a `JointStateGoalSet`.  `sample_goal` returns None when an attempt
+/// did not produce a goal (e.g., an IK solve that did not converge), so callers can simply try
+/// again.
+pub trait GoalSampler {
+    fn sample_goal(&self) -> Result<Option<DVector<f64>>, OptimaError>;
+}
+
+/// Samples DOF joint states that place a link at a target pose.  Each attempt runs
+/// `RobotKinematicsModule::compute_ik_damped_least_squares_with_metric` from a uniformly sampled
+/// initial state, so repeated attempts tend to find the different solution branches of the
+/// target.  Attempts that do not converge yield None.
+pub struct IKGoalSampler<'a> {
+    robot_kinematics_module: &'a RobotKinematicsModule,
+    end_link_id: LinkId,
+    target_pose: OptimaSE3Pose,
+    pose_error_metric: PoseErrorMetric,
+    max_iterations: usize,
+    position_tolerance: f64,
+    orientation_tolerance: f64,
+    damping: f64
+}
+impl <'a> IKGoalSampler<'a> {
+    pub fn new(robot_kinematics_module: &'a RobotKinematicsModule, end_link_id: LinkId, target_pose: OptimaSE3Pose, pose_error_metric: PoseErrorMetric) -> Result<Self, OptimaError> {
+        let num_links = robot_kinematics_module.robot_configuration_module().robot_model_module().links().len();
+        OptimaError::new_check_for_idx_out_of_bound_error(end_link_id.idx(), num_links, file!(), line!())?;
+
+        Ok(Self {
+            robot_kinematics_module,
+            end_link_id,
+            target_pose,
+            pose_error_metric,
+            max_iterations: 100,
+            position_tolerance: 0.001,
+            orientation_tolerance: 0.001,
+            damping: 0.05
+        })
+    }
+    pub fn set_max_iterations(&mut self, max_iterations: usize) {
+        self.max_iterations = max_iterations;
+    }
+    pub fn set_tolerances(&mut self, position_tolerance: f64, orientation_tolerance: f64) {
+        self.position_tolerance = position_tolerance;
+        self.orientation_tolerance = orientation_tolerance;
+    }
+    pub fn set_damping(&mut self, damping: f64) {
+        self.damping = damping;
+    }
+    pub fn end_link_id(&self) -> LinkId {
+        self.end_link_id
+    }
+    pub fn target_pose(&self) -> &OptimaSE3Pose {
+        &self.target_pose
+    }
+}
+impl <'a> GoalSampler for IKGoalSampler<'a> {
+    fn sample_goal(&self) -> Result<Option<DVector<f64>>, OptimaError> {
+        let initial_joint_state = self.robot_kinematics_module.robot_joint_state_module().sample_joint_state(&RobotJointStateType::DOF);
+        let res = self.robot_kinematics_module.compute_ik_damped_least_squares_with_metric(&initial_joint_state, self.end_link_id, &self.target_pose, &self.pose_error_metric, self.max_iterations, self.position_tolerance, self.orientation_tolerance, self.damping)?;
+        return if res.converged() { Ok(Some(res.joint_state().joint_state().clone())) } else { Ok(None) };
+    }
+}
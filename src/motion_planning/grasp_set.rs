@@ -0,0 +1,265 @@
+use nalgebra::DVector;
+use rayon::prelude::*;
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateType};
+use crate::robot_set_modules::robot_set_joint_state_module::{RobotSetJointState, RobotSetJointStateType};
+use crate::scenes::robot_geometric_shape_scene::{EnvObjPoseConstraint, EnvObjPoseConstraintGroupInput, RobotGeometricShapeScene, RobotGeometricShapeSceneQuery};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3Pose, OptimaSE3PoseType};
+use crate::utils::utils_se3::pose_error_metric::PoseErrorMetric;
+use crate::utils::utils_shape_geometry::geometric_shape::{GeometricShapeSignature, LogCondition, StopCondition};
+
+/// A set of candidate grasps of an object.  Each grasp is the pose of the end effector frame
+/// relative to the object frame, so a grasp set is computed once per object and applies wherever
+/// the object is placed, with a quality score (higher is better) from whatever grasp planner or
+/// heuristic produced it.  Grasp indices are stable: grasps are only ever appended.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GraspSet {
+    grasps: Vec<Grasp>
+}
+impl GraspSet {
+    pub fn new_empty() -> Self {
+        Self {
+            grasps: vec![]
+        }
+    }
+    /// Adds a grasp and returns its index.
+    pub fn add_grasp(&mut self, object_to_end_effector_pose: OptimaSE3Pose, quality: f64) -> usize {
+        self.grasps.push(Grasp {
+            object_to_end_effector_pose,
+            quality
+        });
+        self.grasps.len() - 1
+    }
+    /// The end effector pose of the given grasp in the world frame, for an object at the given pose.
+    pub fn world_end_effector_pose(&self, grasp_idx: usize, object_pose: &OptimaSE3Pose) -> Result<OptimaSE3Pose, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(grasp_idx, self.grasps.len(), file!(), line!())?;
+        return object_pose.multiply(&self.grasps[grasp_idx].object_to_end_effector_pose, true);
+    }
+    /// Grasp indices sorted from highest to lowest quality.
+    pub fn grasp_idxs_by_quality(&self) -> Vec<usize> {
+        let mut out_vec: Vec<usize> = (0..self.grasps.len()).collect();
+        out_vec.sort_by(|a, b| self.grasps[*b].quality.partial_cmp(&self.grasps[*a].quality).unwrap_or(std::cmp::Ordering::Equal));
+        out_vec
+    }
+    pub fn grasp(&self, grasp_idx: usize) -> Result<&Grasp, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(grasp_idx, self.grasps.len(), file!(), line!())?;
+        Ok(&self.grasps[grasp_idx])
+    }
+    pub fn grasps(&self) -> &Vec<Grasp> {
+        &self.grasps
+    }
+    pub fn len(&self) -> usize {
+        self.grasps.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.grasps.is_empty()
+    }
+}
+
+/// A single grasp of a `GraspSet`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Grasp {
+    object_to_end_effector_pose: OptimaSE3Pose,
+    quality: f64
+}
+impl Grasp {
+    pub fn object_to_end_effector_pose(&self) -> &OptimaSE3Pose {
+        &self.object_to_end_effector_pose
+    }
+    pub fn quality(&self) -> f64 {
+        self.quality
+    }
+}
+
+/// Finds the grasps of a `GraspSet` that one robot of a scene can actually execute for an object at a
+/// given pose.  A grasp is reachable if IK for the end effector frame converges at the grasp pose and
+/// the resulting robot set state is collision free in the scene.  Grasps are evaluated in parallel.
+///
+/// IK (see `RobotKinematicsModule::compute_ik_damped_least_squares_on_frame`) is first seeded from
+/// the robot's state in the base robot set state, then from up to num_ik_restarts uniformly sampled
+/// states.  The other robots of the set stay at their base states.
+///
+/// If the object is an environment object of the scene (see `set_object_env_obj_idx`), it is moved
+/// to the object pose for the collision check, and contacts between the object and the grasping
+/// robot are ignored, since a grasp touches the object by design.
+pub struct GraspReachabilityFilter<'a> {
+    robot_geometric_shape_scene: &'a RobotGeometricShapeScene,
+    robot_idx_in_set: usize,
+    end_effector_frame_name: String,
+    base_robot_set_joint_state: RobotSetJointState,
+    object_env_obj_idx: Option<usize>,
+    pose_error_metric: PoseErrorMetric,
+    num_ik_restarts: usize,
+    max_iterations: usize,
+    position_tolerance: f64,
+    orientation_tolerance: f64,
+    damping: f64
+}
+impl <'a> GraspReachabilityFilter<'a> {
+    pub fn new(robot_geometric_shape_scene: &'a RobotGeometricShapeScene, robot_idx_in_set: usize, end_effector_frame_name: &str, base_robot_set_joint_state: &RobotSetJointState) -> Result<Self, OptimaError> {
+        let robot_set = robot_geometric_shape_scene.robot_set();
+        let robot_kinematics_modules = robot_set.robot_set_kinematics_module().robot_kinematics_modules();
+        OptimaError::new_check_for_idx_out_of_bound_error(robot_idx_in_set, robot_kinematics_modules.len(), file!(), line!())?;
+        robot_kinematics_modules[robot_idx_in_set].resolve_frame(end_effector_frame_name)?;
+        let base_robot_set_joint_state = robot_set.robot_set_joint_state_module().convert_state_to_dof_state(base_robot_set_joint_state)?;
+
+        Ok(Self {
+            robot_geometric_shape_scene,
+            robot_idx_in_set,
+            end_effector_frame_name: end_effector_frame_name.to_string(),
+            base_robot_set_joint_state,
+            object_env_obj_idx: None,
+            pose_error_metric: PoseErrorMetric::default(),
+            num_ik_restarts: 10,
+            max_iterations: 100,
+            position_tolerance: 0.001,
+            orientation_tolerance: 0.001,
+            damping: 0.05
+        })
+    }
+    pub fn set_object_env_obj_idx(&mut self, object_env_obj_idx: Option<usize>) -> Result<(), OptimaError> {
+        if let Some(env_obj_idx) = object_env_obj_idx {
+            OptimaError::new_check_for_idx_out_of_bound_error(env_obj_idx, self.robot_geometric_shape_scene.num_env_objs(), file!(), line!())?;
+        }
+        self.object_env_obj_idx = object_env_obj_idx;
+        Ok(())
+    }
+    pub fn set_pose_error_metric(&mut self, pose_error_metric: PoseErrorMetric) {
+        self.pose_error_metric = pose_error_metric;
+    }
+    pub fn set_num_ik_restarts(&mut self, num_ik_restarts: usize) {
+        self.num_ik_restarts = num_ik_restarts;
+    }
+    pub fn set_max_iterations(&mut self, max_iterations: usize) {
+        self.max_iterations = max_iterations;
+    }
+    pub fn set_tolerances(&mut self, position_tolerance: f64, orientation_tolerance: f64) {
+        self.position_tolerance = position_tolerance;
+        self.orientation_tolerance = orientation_tolerance;
+    }
+    pub fn set_damping(&mut self, damping: f64) {
+        self.damping = damping;
+    }
+    /// Returns the reachable grasps for an object at the given pose, sorted from highest to lowest
+    /// quality.
+    pub fn filter(&self, grasp_set: &GraspSet, object_pose: &OptimaSE3Pose) -> Result<Vec<ReachableGrasp>, OptimaError> {
+        let env_obj_pose_constraint_group_input = match self.object_env_obj_idx {
+            None => { None }
+            Some(env_obj_idx) => {
+                let mut input = EnvObjPoseConstraintGroupInput::new_empty_some(self.robot_geometric_shape_scene);
+                input.inject_new_pose_constraint(env_obj_idx, EnvObjPoseConstraint::Absolute(object_pose.clone()))?;
+                Some(input)
+            }
+        };
+
+        let res: Result<Vec<Option<ReachableGrasp>>, OptimaError> = (0..grasp_set.len()).into_par_iter().map(|grasp_idx| {
+            let target_pose = grasp_set.world_end_effector_pose(grasp_idx, object_pose)?;
+            self.evaluate_grasp(grasp_idx, grasp_set.grasps[grasp_idx].quality, &target_pose, env_obj_pose_constraint_group_input.as_ref())
+        }).collect();
+
+        let mut out_vec: Vec<ReachableGrasp> = res?.into_iter().flatten().collect();
+        out_vec.sort_by(|a, b| b.quality.partial_cmp(&a.quality).unwrap_or(std::cmp::Ordering::Equal));
+        Ok(out_vec)
+    }
+    pub fn robot_idx_in_set(&self) -> usize {
+        self.robot_idx_in_set
+    }
+    pub fn end_effector_frame_name(&self) -> &str {
+        &self.end_effector_frame_name
+    }
+    pub fn object_env_obj_idx(&self) -> Option<usize> {
+        self.object_env_obj_idx
+    }
+    fn evaluate_grasp(&self, grasp_idx: usize, quality: f64, target_pose: &OptimaSE3Pose, env_obj_pose_constraint_group_input: Option<&EnvObjPoseConstraintGroupInput>) -> Result<Option<ReachableGrasp>, OptimaError> {
+        let robot_set = self.robot_geometric_shape_scene.robot_set();
+        let robot_kinematics_module = &robot_set.robot_set_kinematics_module().robot_kinematics_modules()[self.robot_idx_in_set];
+        let robot_joint_states = robot_set.robot_set_joint_state_module().split_robot_set_joint_state_into_robot_joint_states(&self.base_robot_set_joint_state)?;
+
+        for attempt in 0..self.num_ik_restarts + 1 {
+            let initial_joint_state = if attempt == 0 {
+                robot_joint_states[self.robot_idx_in_set].clone()
+            } else {
+                robot_kinematics_module.robot_joint_state_module().sample_joint_state(&RobotJointStateType::DOF)
+            };
+            let ik_res = robot_kinematics_module.compute_ik_damped_least_squares_on_frame(&initial_joint_state, &self.end_effector_frame_name, target_pose, &self.pose_error_metric, self.max_iterations, self.position_tolerance, self.orientation_tolerance, self.damping)?;
+            if !ik_res.converged() { continue; }
+
+            let robot_set_joint_state = self.replace_robot_joint_state(&robot_joint_states, ik_res.joint_state())?;
+            if !self.robot_set_joint_state_is_collision_free(&robot_set_joint_state, env_obj_pose_constraint_group_input)? { continue; }
+
+            return Ok(Some(ReachableGrasp {
+                grasp_idx,
+                quality,
+                robot_joint_state: ik_res.joint_state().clone(),
+                robot_set_joint_state,
+                end_effector_pose: target_pose.convert(&OptimaSE3PoseType::ImplicitDualQuaternion)
+            }));
+        }
+
+        Ok(None)
+    }
+    fn replace_robot_joint_state(&self, robot_joint_states: &Vec<RobotJointState>, robot_joint_state: &RobotJointState) -> Result<RobotSetJointState, OptimaError> {
+        let mut out_vec = vec![];
+        for (i, s) in robot_joint_states.iter().enumerate() {
+            let s = if i == self.robot_idx_in_set { robot_joint_state } else { s };
+            out_vec.extend(s.joint_state().iter());
+        }
+        return self.robot_geometric_shape_scene.robot_set().robot_set_joint_state_module().spawn_robot_set_joint_state(DVector::from_vec(out_vec), RobotSetJointStateType::DOF);
+    }
+    fn robot_set_joint_state_is_collision_free(&self, robot_set_joint_state: &RobotSetJointState, env_obj_pose_constraint_group_input: Option<&EnvObjPoseConstraintGroupInput>) -> Result<bool, OptimaError> {
+        let input = RobotGeometricShapeSceneQuery::IntersectionTest {
+            robot_set_joint_state,
+            env_obj_pose_constraint_group_input,
+            inclusion_list: &None
+        };
+        // All intersections are needed to tell apart contacts with the grasped object.
+        let stop_condition = if self.object_env_obj_idx.is_some() { StopCondition::None } else { StopCondition::Intersection };
+        let res = self.robot_geometric_shape_scene.shape_collection_query(&input, stop_condition, LogCondition::Intersection, false)?;
+        if !res.intersection_found() { return Ok(true); }
+
+        for output in res.outputs() {
+            if !output.raw_output().unwrap_intersection_test()? { continue; }
+            if !self.is_grasp_contact(output.signatures()) { return Ok(false); }
+        }
+        Ok(true)
+    }
+    /// True if the pair is the grasped object and a link of the grasping robot.
+    fn is_grasp_contact(&self, signatures: &Vec<GeometricShapeSignature>) -> bool {
+        let object_env_obj_idx = match self.object_env_obj_idx {
+            None => { return false; }
+            Some(object_env_obj_idx) => { object_env_obj_idx }
+        };
+        let is_object = |s: &GeometricShapeSignature| matches!(s, GeometricShapeSignature::EnvironmentObject { environment_object_idx, .. } if *environment_object_idx == object_env_obj_idx);
+        let is_robot = |s: &GeometricShapeSignature| matches!(s, GeometricShapeSignature::RobotSetLink { robot_idx_in_set, .. } if *robot_idx_in_set == self.robot_idx_in_set);
+        return (is_object(&signatures[0]) && is_robot(&signatures[1])) || (is_robot(&signatures[0]) && is_object(&signatures[1]));
+    }
+}
+
+/// A grasp found reachable by `GraspReachabilityFilter`, with the IK solution that reaches it (as a
+/// DOF state of the grasping robot and of the whole robot set) and the world frame end effector pose.
+#[derive(Clone, Debug)]
+pub struct ReachableGrasp {
+    grasp_idx: usize,
+    quality: f64,
+    robot_joint_state: RobotJointState,
+    robot_set_joint_state: RobotSetJointState,
+    end_effector_pose: OptimaSE3Pose
+}
+impl ReachableGrasp {
+    pub fn grasp_idx(&self) -> usize {
+        self.grasp_idx
+    }
+    pub fn quality(&self) -> f64 {
+        self.quality
+    }
+    pub fn robot_joint_state(&self) -> &RobotJointState {
+        &self.robot_joint_state
+    }
+    pub fn robot_set_joint_state(&self) -> &RobotSetJointState {
+        &self.robot_set_joint_state
+    }
+    pub fn end_effector_pose(&self) -> &OptimaSE3Pose {
+        &self.end_effector_pose
+    }
+}
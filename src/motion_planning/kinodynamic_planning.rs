@@ -0,0 +1,489 @@
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+use crate::motion_planning::collision_checking::RobotSetSceneCollisionChecker;
+use crate::motion_planning::goal_set::JointStateGoalSet;
+use crate::robot_modules::robot_joint_state_module::RobotJointStateType;
+use crate::robot_set_modules::robot_set_joint_state_module::{RobotSetJointState, RobotSetJointStateModule, RobotSetJointStateType};
+use crate::utils::utils_cancellation::{CancellableOutput, CancellationToken};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_nearest_neighbor::{JointStateDistanceMetric, KDTreeNearestNeighbor, NearestNeighborStructure};
+use crate::utils::utils_robot::robot_joint_trajectory::RobotJointTrajectory;
+use crate::utils::utils_sampling::SimpleSamplers;
+
+/// The velocity limit (in units per second) given to DOFs whose joints do not specify a velocity
+/// limit in their URDF.
+pub const KINODYNAMIC_DEFAULT_VELOCITY_LIMIT: f64 = 1.0;
+
+/// Kinodynamic RRT for robot set DOFs modeled as double integrators (the control is the joint
+/// acceleration).  Unlike geometric planners, whose paths have to be time parameterized afterwards,
+/// the output is a `KinodynamicTrajectory` that respects the per DOF velocity and acceleration limits
+/// by construction and can be executed directly.
+///
+/// Tree nodes are (position, velocity) states.  Each iteration draws a random state, picks the
+/// nearest node (weighting velocity differences by velocity_weight), and forward simulates
+/// num_control_samples random bounded accelerations from it for control_duration seconds, keeping
+/// the one that ends closest to the random state.  Every goal_connection_interval iterations, the
+/// node nearest to the goal is steered to the goal exactly: all DOFs brake to rest together, then
+/// move along the straight joint space line to the goal with a synchronized trapezoidal (or
+/// triangular) velocity profile.  Start and goal are at rest.
+///
+/// Every simulated segment is checked for joint limits and collisions at its integration sub
+/// steps, with the straight segments between sub steps checked by the collision checker.
+pub struct KinodynamicRRTPlanner<'a> {
+    collision_checker: &'a RobotSetSceneCollisionChecker<'a>,
+    velocity_limits: Vec<f64>,
+    acceleration_limits: Vec<f64>,
+    control_duration: f64,
+    num_sub_steps: usize,
+    num_control_samples: usize,
+    goal_connection_interval: usize,
+    velocity_weight: f64,
+    max_iterations: usize
+}
+impl <'a> KinodynamicRRTPlanner<'a> {
+    /// Velocity limits are taken from the robots' URDFs (see
+    /// `RobotJointStateModule::get_joint_state_velocity_limits`); DOFs whose joints do not specify a
+    /// velocity limit are given `KINODYNAMIC_DEFAULT_VELOCITY_LIMIT`, which can be overridden with
+    /// `set_velocity_limits`.  Acceleration limits are per DOF of the robot set.
+    pub fn new(collision_checker: &'a RobotSetSceneCollisionChecker<'a>, acceleration_limits: Vec<f64>) -> Result<Self, OptimaError> {
+        let robot_set_joint_state_module = collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        let mut velocity_limits = vec![];
+        for robot_joint_state_module in robot_set_joint_state_module.robot_joint_state_modules() {
+            velocity_limits.extend(robot_joint_state_module.get_joint_state_velocity_limits(&RobotJointStateType::DOF));
+        }
+        for v in &mut velocity_limits {
+            if !v.is_finite() { *v = KINODYNAMIC_DEFAULT_VELOCITY_LIMIT; }
+        }
+
+        let mut out_self = Self {
+            collision_checker,
+            velocity_limits: vec![],
+            acceleration_limits: vec![],
+            control_duration: 0.2,
+            num_sub_steps: 5,
+            num_control_samples: 10,
+            goal_connection_interval: 10,
+            velocity_weight: 0.2,
+            max_iterations: 5000
+        };
+        out_self.set_velocity_limits(velocity_limits)?;
+        out_self.set_acceleration_limits(acceleration_limits)?;
+        Ok(out_self)
+    }
+    pub fn set_velocity_limits(&mut self, velocity_limits: Vec<f64>) -> Result<(), OptimaError> {
+        self.check_limits("velocity", &velocity_limits)?;
+        self.velocity_limits = velocity_limits;
+        Ok(())
+    }
+    pub fn set_acceleration_limits(&mut self, acceleration_limits: Vec<f64>) -> Result<(), OptimaError> {
+        self.check_limits("acceleration", &acceleration_limits)?;
+        self.acceleration_limits = acceleration_limits;
+        Ok(())
+    }
+    /// Sets how long each sampled acceleration is held and the number of integration sub steps it is
+    /// checked at.
+    pub fn set_control_duration(&mut self, control_duration: f64, num_sub_steps: usize) -> Result<(), OptimaError> {
+        if !(control_duration > 0.0) || num_sub_steps == 0 {
+            return Err(OptimaError::new_generic_error_str(&format!("Control duration and number of sub steps must be positive (given {} and {}).", control_duration, num_sub_steps), file!(), line!()));
+        }
+        self.control_duration = control_duration;
+        self.num_sub_steps = num_sub_steps;
+        Ok(())
+    }
+    pub fn set_num_control_samples(&mut self, num_control_samples: usize) {
+        self.num_control_samples = num_control_samples.max(1);
+    }
+    pub fn set_goal_connection_interval(&mut self, goal_connection_interval: usize) {
+        self.goal_connection_interval = goal_connection_interval.max(1);
+    }
+    /// The weight of velocity differences (in seconds) relative to position differences in the state
+    /// space metric.
+    pub fn set_velocity_weight(&mut self, velocity_weight: f64) {
+        self.velocity_weight = velocity_weight;
+    }
+    pub fn set_max_iterations(&mut self, max_iterations: usize) {
+        self.max_iterations = max_iterations;
+    }
+    /// Returns a trajectory from start to goal (both at rest), or None if none is found within
+    /// max_iterations iterations.  Returns an error if start or goal is in collision.
+    pub fn plan(&self, start: &RobotSetJointState, goal: &RobotSetJointState) -> Result<Option<KinodynamicTrajectory>, OptimaError> {
+        let res = self.plan_with_cancellation(start, goal, &CancellationToken::new())?;
+        return Ok(res.into_output());
+    }
+    /// Same as `plan`, but stops once the given token is cancelled, in which case the output is None.
+    pub fn plan_with_cancellation(&self, start: &RobotSetJointState, goal: &RobotSetJointState, cancellation_token: &CancellationToken) -> Result<CancellableOutput<Option<KinodynamicTrajectory>>, OptimaError> {
+        let robot_set_joint_state_module = self.robot_set_joint_state_module();
+        let start = robot_set_joint_state_module.convert_state_to_dof_state(start)?;
+        let goal = robot_set_joint_state_module.convert_state_to_dof_state(goal)?;
+        if !self.collision_checker.state_is_valid(&start)? {
+            return Err(OptimaError::new_generic_error_str("Start state of kinodynamic RRT is in collision.", file!(), line!()));
+        }
+        if !self.collision_checker.state_is_valid(&goal)? {
+            return Err(OptimaError::new_generic_error_str("Goal state of kinodynamic RRT is in collision.", file!(), line!()));
+        }
+
+        let res = self.plan_to_any_goal_with_cancellation(&start, &[(0, goal.concatenated_state().clone())], cancellation_token)?;
+        let was_cancelled = res.was_cancelled();
+        let out = res.into_output().map(|(trajectory, _)| trajectory);
+        return if was_cancelled { Ok(CancellableOutput::Cancelled(out)) } else { Ok(CancellableOutput::Complete(out)) };
+    }
+    /// Returns a trajectory from start to any goal of the goal set (whose goals are concatenated DOF
+    /// vectors of the robot set, all at rest) along with the index of the reached goal, or None if
+    /// none is found within max_iterations iterations.  Every goal_connection_interval iterations,
+    /// the tree is steered toward each goal, and planning stops at the first goal that is reached.
+    /// Goals in collision are skipped.  Returns an error if start is in collision or if no goal is
+    /// collision free.
+    pub fn plan_to_goal_set(&self, start: &RobotSetJointState, goal_set: &JointStateGoalSet) -> Result<Option<(KinodynamicTrajectory, usize)>, OptimaError> {
+        let res = self.plan_to_goal_set_with_cancellation(start, goal_set, &CancellationToken::new())?;
+        return Ok(res.into_output());
+    }
+    /// Same as `plan_to_goal_set`, but stops once the given token is cancelled, in which case the
+    /// output is None.
+    pub fn plan_to_goal_set_with_cancellation(&self, start: &RobotSetJointState, goal_set: &JointStateGoalSet, cancellation_token: &CancellationToken) -> Result<CancellableOutput<Option<(KinodynamicTrajectory, usize)>>, OptimaError> {
+        let robot_set_joint_state_module = self.robot_set_joint_state_module();
+        goal_set.check_dimension("KinodynamicRRTPlanner::plan_to_goal_set", self.num_dofs())?;
+        let start = robot_set_joint_state_module.convert_state_to_dof_state(start)?;
+        if !self.collision_checker.state_is_valid(&start)? {
+            return Err(OptimaError::new_generic_error_str("Start state of kinodynamic RRT is in collision.", file!(), line!()));
+        }
+        if let Some(m) = goal_set.reached_goal(start.concatenated_state()) {
+            let waypoints = vec![KinodynamicWaypoint { time_from_start: 0.0, position: start.concatenated_state().clone(), velocity: DVector::zeros(self.num_dofs()) }];
+            return Ok(CancellableOutput::Complete(Some((KinodynamicTrajectory { waypoints }, m.goal_idx()))));
+        }
+        let goals = goal_set.valid_goals(|goal| {
+            self.collision_checker.state_is_valid(&robot_set_joint_state_module.spawn_robot_set_joint_state(goal.clone(), RobotSetJointStateType::DOF)?)
+        })?;
+
+        return self.plan_to_any_goal_with_cancellation(&start, &goals, cancellation_token);
+    }
+    pub fn velocity_limits(&self) -> &Vec<f64> {
+        &self.velocity_limits
+    }
+    pub fn acceleration_limits(&self) -> &Vec<f64> {
+        &self.acceleration_limits
+    }
+    pub fn control_duration(&self) -> f64 {
+        self.control_duration
+    }
+    pub fn num_sub_steps(&self) -> usize {
+        self.num_sub_steps
+    }
+    pub fn num_control_samples(&self) -> usize {
+        self.num_control_samples
+    }
+    pub fn goal_connection_interval(&self) -> usize {
+        self.goal_connection_interval
+    }
+    pub fn velocity_weight(&self) -> f64 {
+        self.velocity_weight
+    }
+    pub fn max_iterations(&self) -> usize {
+        self.max_iterations
+    }
+    /// The planning loop shared by `plan_with_cancellation` and `plan_to_goal_set_with_cancellation`.
+    /// Start is a collision free DOF state, and goals are given as (goal index, goal position) pairs
+    /// of collision free positions.
+    fn plan_to_any_goal_with_cancellation(&self, start: &RobotSetJointState, goals: &[(usize, DVector<f64>)], cancellation_token: &CancellationToken) -> Result<CancellableOutput<Option<(KinodynamicTrajectory, usize)>>, OptimaError> {
+        let robot_set_joint_state_module = self.robot_set_joint_state_module();
+        let n = self.num_dofs();
+        let goal_velocity = DVector::zeros(n);
+        let bounds = robot_set_joint_state_module.get_joint_state_bounds(&RobotSetJointStateType::DOF);
+        let velocity_bounds: Vec<(f64, f64)> = self.velocity_limits.iter().map(|v| (-*v, *v)).collect();
+        let acceleration_bounds: Vec<(f64, f64)> = self.acceleration_limits.iter().map(|a| (-*a, *a)).collect();
+
+        let mut tree = vec![KinodynamicRRTNode {
+            position: start.concatenated_state().clone(),
+            velocity: DVector::zeros(n),
+            parent_idx: None,
+            segment: vec![]
+        }];
+        // Item i of the index is tree node i, with the weighted state metric as a weighted Euclidean
+        // metric over concatenated (position, velocity) coordinates.
+        let mut weights = vec![1.0; n];
+        weights.extend(vec![self.velocity_weight * self.velocity_weight; n]);
+        let mut nearest_neighbor_index = KDTreeNearestNeighbor::new(JointStateDistanceMetric::WeightedEuclidean { weights });
+        nearest_neighbor_index.insert(Self::nearest_neighbor_coordinates(&tree[0].position, &tree[0].velocity), ())?;
+
+        for iteration in 0..=self.max_iterations {
+            if cancellation_token.is_cancelled() { return Ok(CancellableOutput::Cancelled(None)); }
+
+            if iteration % self.goal_connection_interval == 0 {
+                for (goal_idx, goal_position) in goals {
+                    let nearest_idx = Self::nearest(&nearest_neighbor_index, goal_position, &goal_velocity);
+                    if let Some(segment) = self.steer_to_rest_state(&tree[nearest_idx].position, &tree[nearest_idx].velocity, goal_position)? {
+                        return Ok(CancellableOutput::Complete(Some((Self::extract_trajectory(&tree, nearest_idx, segment), *goal_idx))));
+                    }
+                }
+            }
+            if iteration == self.max_iterations { break; }
+
+            let target_position = DVector::from_vec(SimpleSamplers::uniform_samples(&bounds));
+            let target_velocity = DVector::from_vec(SimpleSamplers::uniform_samples(&velocity_bounds));
+            let nearest_idx = Self::nearest(&nearest_neighbor_index, &target_position, &target_velocity);
+
+            let mut best: Option<(f64, Vec<KinodynamicWaypoint>)> = None;
+            for _ in 0..self.num_control_samples {
+                let acceleration = DVector::from_vec(SimpleSamplers::uniform_samples(&acceleration_bounds));
+                let segment = match self.propagate(&tree[nearest_idx].position, &tree[nearest_idx].velocity, &acceleration)? {
+                    None => { continue; }
+                    Some(segment) => { segment }
+                };
+                let end = segment.last().expect("error");
+                let distance = self.state_distance(&end.position, &end.velocity, &target_position, &target_velocity);
+                if best.as_ref().map_or(true, |(d, _)| distance < *d) { best = Some((distance, segment)); }
+            }
+
+            if let Some((_, segment)) = best {
+                let end = segment.last().expect("error").clone();
+                nearest_neighbor_index.insert(Self::nearest_neighbor_coordinates(&end.position, &end.velocity), ())?;
+                tree.push(KinodynamicRRTNode {
+                    position: end.position,
+                    velocity: end.velocity,
+                    parent_idx: Some(nearest_idx),
+                    segment
+                });
+            }
+        }
+
+        Ok(CancellableOutput::Complete(None))
+    }
+    /// Holds the given acceleration for control_duration, reducing it per DOF where needed so that
+    /// velocities stay within their limits.  Returns the sub step waypoints (with times relative to
+    /// the start), or None if the segment leaves the joint bounds or is in collision.
+    fn propagate(&self, position: &DVector<f64>, velocity: &DVector<f64>, acceleration: &DVector<f64>) -> Result<Option<Vec<KinodynamicWaypoint>>, OptimaError> {
+        let dt = self.control_duration / self.num_sub_steps as f64;
+        let mut out_vec = vec![];
+        let mut q = position.clone();
+        let mut v = velocity.clone();
+        for k in 0..self.num_sub_steps {
+            let mut a = acceleration.clone();
+            for i in 0..a.len() {
+                a[i] = a[i].max((-self.velocity_limits[i] - v[i]) / dt).min((self.velocity_limits[i] - v[i]) / dt);
+            }
+            q = &q + &v * dt + &a * (0.5 * dt * dt);
+            v = &v + &a * dt;
+            out_vec.push(KinodynamicWaypoint { time_from_start: (k + 1) as f64 * dt, position: q.clone(), velocity: v.clone() });
+        }
+
+        return if self.segment_is_valid(position, &out_vec)? { Ok(Some(out_vec)) } else { Ok(None) };
+    }
+    /// Brakes all DOFs to rest together and then moves along the straight line to the given rest
+    /// state.  Returns the waypoints (with times relative to the start), or None if the motion leaves
+    /// the joint bounds or is in collision.
+    fn steer_to_rest_state(&self, position: &DVector<f64>, velocity: &DVector<f64>, target_position: &DVector<f64>) -> Result<Option<Vec<KinodynamicWaypoint>>, OptimaError> {
+        let n = position.len();
+        let mut out_vec = vec![];
+        let mut time = 0.0;
+        let mut stop_position = position.clone();
+
+        let braking_time = (0..n).map(|i| velocity[i].abs() / self.acceleration_limits[i]).fold(0.0, f64::max);
+        if braking_time > 0.0 {
+            let a = -velocity / braking_time;
+            let dt = braking_time / self.num_sub_steps as f64;
+            for k in 1..=self.num_sub_steps {
+                let t = k as f64 * dt;
+                out_vec.push(KinodynamicWaypoint { time_from_start: t, position: position + velocity * t + &a * (0.5 * t * t), velocity: velocity + &a * t });
+            }
+            stop_position = out_vec.last().expect("error").position.clone();
+            out_vec.last_mut().expect("error").velocity = DVector::zeros(n);
+            time = braking_time;
+        }
+
+        // Synchronized trapezoidal profile on the path parameter s in [0, 1].
+        let direction = target_position - &stop_position;
+        let mut s_velocity_limit = f64::INFINITY;
+        let mut s_acceleration_limit = f64::INFINITY;
+        for i in 0..n {
+            if direction[i].abs() < 1e-12 { continue; }
+            s_velocity_limit = s_velocity_limit.min(self.velocity_limits[i] / direction[i].abs());
+            s_acceleration_limit = s_acceleration_limit.min(self.acceleration_limits[i] / direction[i].abs());
+        }
+        if s_acceleration_limit.is_finite() {
+            let (accel_time, cruise_time, peak) = if s_velocity_limit * s_velocity_limit / s_acceleration_limit >= 1.0 {
+                let accel_time = (1.0 / s_acceleration_limit).sqrt();
+                (accel_time, 0.0, s_acceleration_limit * accel_time)
+            } else {
+                let accel_time = s_velocity_limit / s_acceleration_limit;
+                (accel_time, (1.0 - s_velocity_limit * accel_time) / s_velocity_limit, s_velocity_limit)
+            };
+            let s_at = |t: f64| -> (f64, f64) {
+                if t <= accel_time {
+                    (0.5 * s_acceleration_limit * t * t, s_acceleration_limit * t)
+                } else if t <= accel_time + cruise_time {
+                    (0.5 * peak * accel_time + peak * (t - accel_time), peak)
+                } else {
+                    let r = (2.0 * accel_time + cruise_time - t).max(0.0);
+                    (1.0 - 0.5 * s_acceleration_limit * r * r, s_acceleration_limit * r)
+                }
+            };
+
+            let phase_ends = [accel_time, accel_time + cruise_time, 2.0 * accel_time + cruise_time];
+            let mut phase_start = 0.0;
+            for phase_end in phase_ends {
+                if phase_end <= phase_start { continue; }
+                let dt = (phase_end - phase_start) / self.num_sub_steps as f64;
+                for k in 1..=self.num_sub_steps {
+                    let t = phase_start + k as f64 * dt;
+                    let (s, s_dot) = s_at(t);
+                    out_vec.push(KinodynamicWaypoint { time_from_start: time + t, position: &stop_position + &direction * s, velocity: &direction * s_dot });
+                }
+                phase_start = phase_end;
+            }
+            let last = out_vec.last_mut().expect("error");
+            last.position = target_position.clone();
+            last.velocity = DVector::zeros(n);
+        }
+
+        if out_vec.is_empty() { return Ok(Some(out_vec)); }
+        return if self.segment_is_valid(position, &out_vec)? { Ok(Some(out_vec)) } else { Ok(None) };
+    }
+    fn segment_is_valid(&self, start_position: &DVector<f64>, waypoints: &Vec<KinodynamicWaypoint>) -> Result<bool, OptimaError> {
+        let robot_set_joint_state_module = self.robot_set_joint_state_module();
+        let bounds = robot_set_joint_state_module.get_joint_state_bounds(&RobotSetJointStateType::DOF);
+        let mut prev_state = robot_set_joint_state_module.spawn_robot_set_joint_state(start_position.clone(), RobotSetJointStateType::DOF)?;
+        for w in waypoints {
+            for (i, (lower, upper)) in bounds.iter().enumerate() {
+                if w.position[i] < *lower || w.position[i] > *upper { return Ok(false); }
+            }
+            let state = robot_set_joint_state_module.spawn_robot_set_joint_state(w.position.clone(), RobotSetJointStateType::DOF)?;
+            if !self.collision_checker.state_is_valid(&state)? || !self.collision_checker.segment_is_valid(&prev_state, &state)? { return Ok(false); }
+            prev_state = state;
+        }
+        Ok(true)
+    }
+    fn nearest(nearest_neighbor_index: &KDTreeNearestNeighbor<()>, position: &DVector<f64>, velocity: &DVector<f64>) -> usize {
+        return nearest_neighbor_index.nearest(&Self::nearest_neighbor_coordinates(position, velocity)).map_or(0, |res| res.item_idx());
+    }
+    fn nearest_neighbor_coordinates(position: &DVector<f64>, velocity: &DVector<f64>) -> DVector<f64> {
+        return DVector::from_iterator(position.len() + velocity.len(), position.iter().chain(velocity.iter()).cloned());
+    }
+    fn state_distance(&self, position_a: &DVector<f64>, velocity_a: &DVector<f64>, position_b: &DVector<f64>, velocity_b: &DVector<f64>) -> f64 {
+        return ((position_a - position_b).norm_squared() + self.velocity_weight * self.velocity_weight * (velocity_a - velocity_b).norm_squared()).sqrt();
+    }
+    fn extract_trajectory(tree: &Vec<KinodynamicRRTNode>, node_idx: usize, final_segment: Vec<KinodynamicWaypoint>) -> KinodynamicTrajectory {
+        let mut segments = vec![final_segment];
+        let mut curr = node_idx;
+        while let Some(parent_idx) = tree[curr].parent_idx {
+            segments.push(tree[curr].segment.clone());
+            curr = parent_idx;
+        }
+        segments.reverse();
+
+        let mut waypoints = vec![KinodynamicWaypoint { time_from_start: 0.0, position: tree[0].position.clone(), velocity: tree[0].velocity.clone() }];
+        for segment in segments {
+            let segment_start_time = waypoints.last().expect("error").time_from_start;
+            for mut w in segment {
+                w.time_from_start += segment_start_time;
+                waypoints.push(w);
+            }
+        }
+
+        KinodynamicTrajectory { waypoints }
+    }
+    fn check_limits(&self, name: &str, limits: &Vec<f64>) -> Result<(), OptimaError> {
+        if limits.len() != self.num_dofs() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("KinodynamicRRTPlanner", limits.len(), self.num_dofs(), file!(), line!()));
+        }
+        for (i, l) in limits.iter().enumerate() {
+            if !(*l > 0.0) || !l.is_finite() {
+                return Err(OptimaError::new_generic_error_str(&format!("The {} limit for DOF {} must be positive and finite (given {}).", name, i, l), file!(), line!()));
+            }
+        }
+        Ok(())
+    }
+    fn robot_set_joint_state_module(&self) -> &RobotSetJointStateModule {
+        return self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+    }
+    fn num_dofs(&self) -> usize {
+        return self.robot_set_joint_state_module().num_dofs();
+    }
+}
+
+struct KinodynamicRRTNode {
+    position: DVector<f64>,
+    velocity: DVector<f64>,
+    parent_idx: Option<usize>,
+    /// Waypoints from the parent node to this node (the parent's state excluded), with times
+    /// relative to the parent.
+    segment: Vec<KinodynamicWaypoint>
+}
+
+/// A trajectory of robot set DOF states with piecewise constant accelerations between waypoints, as
+/// produced by a `KinodynamicRRTPlanner`.  The first waypoint is at time 0.0.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KinodynamicTrajectory {
+    waypoints: Vec<KinodynamicWaypoint>
+}
+impl KinodynamicTrajectory {
+    pub fn waypoints(&self) -> &Vec<KinodynamicWaypoint> {
+        &self.waypoints
+    }
+    pub fn duration(&self) -> f64 {
+        return self.waypoints.last().map_or(0.0, |w| w.time_from_start);
+    }
+    /// The position and velocity at the given time (clamped to the trajectory's duration).
+    pub fn state_at(&self, time: f64) -> (DVector<f64>, DVector<f64>) {
+        let time = time.max(0.0).min(self.duration());
+        let idx = self.waypoints.iter().position(|w| w.time_from_start >= time).unwrap_or(self.waypoints.len() - 1);
+        if idx == 0 { return (self.waypoints[0].position.clone(), self.waypoints[0].velocity.clone()); }
+
+        let a = &self.waypoints[idx - 1];
+        let b = &self.waypoints[idx];
+        let dt = b.time_from_start - a.time_from_start;
+        if dt <= 0.0 { return (b.position.clone(), b.velocity.clone()); }
+        let acceleration = (&b.velocity - &a.velocity) / dt;
+        let t = time - a.time_from_start;
+        return (&a.position + &a.velocity * t + &acceleration * (0.5 * t * t), &a.velocity + &acceleration * t);
+    }
+    /// Samples the trajectory at the given fixed time step (the final state is always included).
+    pub fn sample(&self, time_step: f64) -> Result<Vec<KinodynamicWaypoint>, OptimaError> {
+        if !(time_step > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Time step must be positive (given {}).", time_step), file!(), line!()));
+        }
+        let num_steps = (self.duration() / time_step).ceil() as usize;
+        let mut out_vec = vec![];
+        for k in 0..=num_steps {
+            let time_from_start = (k as f64 * time_step).min(self.duration());
+            let (position, velocity) = self.state_at(time_from_start);
+            out_vec.push(KinodynamicWaypoint { time_from_start, position, velocity });
+        }
+        Ok(out_vec)
+    }
+    /// Samples the trajectory at the given fixed time step and splits it into one
+    /// `RobotJointTrajectory` per robot of the set.
+    pub fn to_robot_joint_trajectories(&self, robot_set_joint_state_module: &RobotSetJointStateModule, time_step: f64) -> Result<Vec<RobotJointTrajectory>, OptimaError> {
+        let samples = self.sample(time_step)?;
+        let times_from_start: Vec<f64> = samples.iter().map(|w| w.time_from_start).collect();
+        let mut robot_joint_states = vec![vec![]; robot_set_joint_state_module.robot_joint_state_modules().len()];
+        for w in &samples {
+            let state = robot_set_joint_state_module.spawn_robot_set_joint_state(w.position.clone(), RobotSetJointStateType::DOF)?;
+            for (robot_idx_in_set, robot_joint_state) in robot_set_joint_state_module.split_robot_set_joint_state_into_robot_joint_states(&state)?.into_iter().enumerate() {
+                robot_joint_states[robot_idx_in_set].push(robot_joint_state);
+            }
+        }
+
+        let mut out_vec = vec![];
+        for (robot_joint_state_module, states) in robot_set_joint_state_module.robot_joint_state_modules().iter().zip(robot_joint_states.iter()) {
+            out_vec.push(RobotJointTrajectory::new_from_robot_joint_states(robot_joint_state_module, states, &times_from_start)?);
+        }
+        Ok(out_vec)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KinodynamicWaypoint {
+    time_from_start: f64,
+    position: DVector<f64>,
+    velocity: DVector<f64>
+}
+impl KinodynamicWaypoint {
+    pub fn time_from_start(&self) -> f64 {
+        self.time_from_start
+    }
+    pub fn position(&self) -> &DVector<f64> {
+        &self.position
+    }
+    pub fn velocity(&self) -> &DVector<f64> {
+        &self.velocity
+    }
+}
@@ -0,0 +1,393 @@
+use nalgebra::DVector;
+use rayon::prelude::*;
+use serde::{Serialize, Deserialize};
+use crate::motion_planning::collision_checking::RobotSetSceneCollisionChecker;
+use crate::motion_planning::goal_set::{GoalSetPath, JointStateGoalSet};
+use crate::motion_planning::prm::{k_nearest_nodes, shortest_path_to_any_on_graph, update_nearest_neighbor_index};
+use crate::robot_set_modules::robot_set_joint_state_module::{RobotSetJointState, RobotSetJointStateType};
+use crate::utils::utils_cancellation::{CancellableOutput, CancellationToken};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_nearest_neighbor::{NearestNeighborStructure, NearestNeighborStructureObject, NearestNeighborStructureType};
+
+/// Lazy PRM (Bohlin and Kavraki, 2000) with LazySP style edge evaluation (Dellin and Srinivasa,
+/// 2016) in the scene of a `RobotSetSceneCollisionChecker`.  Roadmaps are built without any collision
+/// checks: nodes are uniform samples and each node gets candidate edges to its k nearest neighbors.
+/// Checks are deferred to queries, which repeatedly find the shortest path through the parts of the
+/// roadmap not yet known to be invalid and only check the nodes and edges on that path, until a path
+/// is found whose every node and edge is valid.
+///
+/// This pays off when collision checks dominate planning time (e.g., with
+/// `RobotLinkShapeRepresentation::TriangleMeshes`), since most of the roadmap is never checked.
+/// Check results are cached in the `LazyPRMRoadmap`, so later queries on the same roadmap get faster.
+/// The cache is only meaningful while the scene's static geometry stays the same.
+pub struct LazyPRMPlanner<'a> {
+    collision_checker: &'a RobotSetSceneCollisionChecker<'a>,
+    k: usize,
+    max_edge_length: Option<f64>,
+    edge_selector: LazyEdgeSelector,
+    nearest_neighbor_structure_type: NearestNeighborStructureType
+}
+impl <'a> LazyPRMPlanner<'a> {
+    pub fn new(collision_checker: &'a RobotSetSceneCollisionChecker<'a>, k: usize) -> Result<Self, OptimaError> {
+        if k == 0 {
+            return Err(OptimaError::new_generic_error_str("Lazy PRM must connect each node to at least one neighbor (k must be positive).", file!(), line!()));
+        }
+
+        Ok(Self {
+            collision_checker,
+            k,
+            max_edge_length: None,
+            edge_selector: LazyEdgeSelector::Forward,
+            nearest_neighbor_structure_type: NearestNeighborStructureType::KDTree
+        })
+    }
+    /// Candidate edges longer than the given length are not added (None means no limit).
+    pub fn set_max_edge_length(&mut self, max_edge_length: Option<f64>) -> Result<(), OptimaError> {
+        if let Some(max_edge_length) = max_edge_length {
+            if !(max_edge_length > 0.0) {
+                return Err(OptimaError::new_generic_error_str(&format!("Max edge length must be positive (given {}).", max_edge_length), file!(), line!()));
+            }
+        }
+        self.max_edge_length = max_edge_length;
+        Ok(())
+    }
+    pub fn set_edge_selector(&mut self, edge_selector: LazyEdgeSelector) {
+        self.edge_selector = edge_selector;
+    }
+    /// The structure that indexes roadmap nodes for nearest neighbor queries (KD-tree by default;
+    /// GNAT scales better to robot sets with many DOFs).
+    pub fn set_nearest_neighbor_structure_type(&mut self, nearest_neighbor_structure_type: NearestNeighborStructureType) {
+        self.nearest_neighbor_structure_type = nearest_neighbor_structure_type;
+    }
+    /// Builds a roadmap with num_samples nodes.  No collision checks are done.
+    pub fn build_roadmap(&self, num_samples: usize) -> LazyPRMRoadmap {
+        let mut roadmap = LazyPRMRoadmap::new_empty(self.num_dofs());
+        self.densify(&mut roadmap, num_samples).expect("error");
+        roadmap
+    }
+    /// Adds num_samples nodes to the given roadmap along with their candidate edges.  No collision
+    /// checks are done, and check results already cached in the roadmap are kept.
+    pub fn densify(&self, roadmap: &mut LazyPRMRoadmap, num_samples: usize) -> Result<(), OptimaError> {
+        if roadmap.num_dofs != self.num_dofs() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("LazyPRMPlanner::densify", roadmap.num_dofs, self.num_dofs(), file!(), line!()));
+        }
+
+        let robot_set_joint_state_module = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        let first_new_idx = roadmap.num_nodes();
+        for _ in 0..num_samples {
+            let sample = robot_set_joint_state_module.sample_set_joint_state(&RobotSetJointStateType::DOF);
+            roadmap.add_node(sample.concatenated_state().clone(), None);
+        }
+        roadmap.update_nearest_neighbor_index(&self.nearest_neighbor_structure_type)?;
+
+        for node_idx in 0..roadmap.num_nodes() {
+            for neighbor_idx in k_nearest_nodes(&roadmap.nodes, roadmap.nearest_neighbor_index.as_ref(), &roadmap.nodes[node_idx], self.k, Some(node_idx)) {
+                if node_idx < first_new_idx && neighbor_idx < first_new_idx { continue; }
+                self.add_candidate_edge(roadmap, node_idx, neighbor_idx);
+            }
+        }
+
+        Ok(())
+    }
+    /// Returns a collision free path of DOF states from start to goal (both included) through the
+    /// given roadmap, or None if there is none.  Start and goal are connected to their k nearest
+    /// roadmap nodes for this query only, but the results of all other checks are cached in the
+    /// roadmap.  Returns an error if start or goal is in collision.
+    pub fn query(&self, roadmap: &mut LazyPRMRoadmap, start: &RobotSetJointState, goal: &RobotSetJointState) -> Result<Option<Vec<RobotSetJointState>>, OptimaError> {
+        let res = self.query_with_cancellation(roadmap, start, goal, &CancellationToken::new())?;
+        return Ok(res.into_output());
+    }
+    /// Same as `query`, but stops once the given token is cancelled, in which case the output is None.
+    /// Check results found before cancellation are still cached in the roadmap.
+    pub fn query_with_cancellation(&self, roadmap: &mut LazyPRMRoadmap, start: &RobotSetJointState, goal: &RobotSetJointState, cancellation_token: &CancellationToken) -> Result<CancellableOutput<Option<Vec<RobotSetJointState>>>, OptimaError> {
+        if roadmap.num_dofs != self.num_dofs() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("LazyPRMPlanner::query", roadmap.num_dofs, self.num_dofs(), file!(), line!()));
+        }
+        let robot_set_joint_state_module = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        let start = robot_set_joint_state_module.convert_state_to_dof_state(start)?;
+        let goal = robot_set_joint_state_module.convert_state_to_dof_state(goal)?;
+        if !self.collision_checker.state_is_valid(&start)? {
+            return Err(OptimaError::new_generic_error_str("Start state of Lazy PRM query is in collision.", file!(), line!()));
+        }
+        if !self.collision_checker.state_is_valid(&goal)? {
+            return Err(OptimaError::new_generic_error_str("Goal state of Lazy PRM query is in collision.", file!(), line!()));
+        }
+
+        let res = self.query_to_any_goal_with_cancellation(roadmap, &start, &[(0, goal)], cancellation_token)?;
+        let was_cancelled = res.was_cancelled();
+        let out = res.into_output().map(|goal_set_path| goal_set_path.into_path());
+        return if was_cancelled { Ok(CancellableOutput::Cancelled(out)) } else { Ok(CancellableOutput::Complete(out)) };
+    }
+    /// Same as `query`, but returns a path to any goal of the goal set (whose goals are concatenated
+    /// DOF vectors of the robot set).  All goals are connected to the roadmap at once and each lazy
+    /// shortest path search targets the nearest goal not yet cut off, so the query stops at the first
+    /// goal reached by a fully valid path, which the output reports (see `GoalSetPath`).  Goals in
+    /// collision are skipped.  Returns an error if start is in collision or if no goal is collision
+    /// free.
+    pub fn query_goal_set(&self, roadmap: &mut LazyPRMRoadmap, start: &RobotSetJointState, goal_set: &JointStateGoalSet) -> Result<Option<GoalSetPath<RobotSetJointState>>, OptimaError> {
+        let res = self.query_goal_set_with_cancellation(roadmap, start, goal_set, &CancellationToken::new())?;
+        return Ok(res.into_output());
+    }
+    /// Same as `query_goal_set`, but stops once the given token is cancelled, in which case the output
+    /// is None.  Check results found before cancellation are still cached in the roadmap.
+    pub fn query_goal_set_with_cancellation(&self, roadmap: &mut LazyPRMRoadmap, start: &RobotSetJointState, goal_set: &JointStateGoalSet, cancellation_token: &CancellationToken) -> Result<CancellableOutput<Option<GoalSetPath<RobotSetJointState>>>, OptimaError> {
+        if roadmap.num_dofs != self.num_dofs() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("LazyPRMPlanner::query_goal_set", roadmap.num_dofs, self.num_dofs(), file!(), line!()));
+        }
+        goal_set.check_dimension("LazyPRMPlanner::query_goal_set", self.num_dofs())?;
+        let robot_set_joint_state_module = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        let start = robot_set_joint_state_module.convert_state_to_dof_state(start)?;
+        if !self.collision_checker.state_is_valid(&start)? {
+            return Err(OptimaError::new_generic_error_str("Start state of Lazy PRM query is in collision.", file!(), line!()));
+        }
+        if let Some(m) = goal_set.reached_goal(start.concatenated_state()) {
+            return Ok(CancellableOutput::Complete(Some(GoalSetPath::new(vec![start], m.goal_idx()))));
+        }
+
+        let mut goals = vec![];
+        for (goal_idx, goal) in goal_set.valid_goals(|goal| self.collision_checker.state_is_valid(&self.spawn_dof_state(goal)?))? {
+            goals.push((goal_idx, self.spawn_dof_state(&goal)?));
+        }
+        return self.query_to_any_goal_with_cancellation(roadmap, &start, &goals, cancellation_token);
+    }
+    pub fn k(&self) -> usize {
+        self.k
+    }
+    pub fn max_edge_length(&self) -> Option<f64> {
+        self.max_edge_length
+    }
+    pub fn edge_selector(&self) -> &LazyEdgeSelector {
+        &self.edge_selector
+    }
+    pub fn nearest_neighbor_structure_type(&self) -> &NearestNeighborStructureType {
+        &self.nearest_neighbor_structure_type
+    }
+    /// The query shared by `query_with_cancellation` and `query_goal_set_with_cancellation`.  Start
+    /// and goals are collision free DOF states, and goals are given as (goal index, goal) pairs.
+    fn query_to_any_goal_with_cancellation(&self, roadmap: &mut LazyPRMRoadmap, start: &RobotSetJointState, goals: &[(usize, RobotSetJointState)], cancellation_token: &CancellationToken) -> Result<CancellableOutput<Option<GoalSetPath<RobotSetJointState>>>, OptimaError> {
+        for (goal_idx, goal) in goals {
+            if self.collision_checker.segment_is_valid(start, goal)? {
+                return Ok(CancellableOutput::Complete(Some(GoalSetPath::new(vec![start.clone(), goal.clone()], *goal_idx))));
+            }
+        }
+
+        roadmap.update_nearest_neighbor_index(&self.nearest_neighbor_structure_type)?;
+        let num_roadmap_nodes = roadmap.num_nodes();
+        let start_idx = roadmap.add_node(start.concatenated_state().clone(), Some(true));
+        let goal_node_idxs: Vec<usize> = goals.iter().map(|(_, goal)| roadmap.add_node(goal.concatenated_state().clone(), Some(true))).collect();
+        for node_idx in num_roadmap_nodes..roadmap.num_nodes() {
+            let neighbor_idxs = k_nearest_nodes(&roadmap.nodes[..num_roadmap_nodes], roadmap.nearest_neighbor_index.as_ref(), &roadmap.nodes[node_idx], self.k, None);
+            for neighbor_idx in neighbor_idxs { self.add_candidate_edge(roadmap, node_idx, neighbor_idx); }
+        }
+
+        let res = self.lazy_search(roadmap, start_idx, &goal_node_idxs, cancellation_token);
+        let res = res.map(|res| match res {
+            CancellableOutput::Complete(Some(path_idxs)) => {
+                let last_idx = *path_idxs.last().expect("error");
+                let goal_idx = goals[goal_node_idxs.iter().position(|idx| *idx == last_idx).expect("error")].0;
+                CancellableOutput::Complete(Some((path_idxs.iter().map(|idx| roadmap.nodes[*idx].clone()).collect::<Vec<DVector<f64>>>(), goal_idx)))
+            }
+            CancellableOutput::Complete(None) => { CancellableOutput::Complete(None) }
+            CancellableOutput::Cancelled(_) => { CancellableOutput::Cancelled(None) }
+        });
+        // Start and goals are removed whether or not the search succeeded, so the roadmap stays reusable.
+        roadmap.truncate_nodes(num_roadmap_nodes);
+        let res = res?;
+
+        return match res {
+            CancellableOutput::Complete(Some((path_vecs, goal_idx))) => {
+                let mut out_vec = vec![];
+                for v in path_vecs { out_vec.push(self.spawn_dof_state(&v)?); }
+                Ok(CancellableOutput::Complete(Some(GoalSetPath::new(out_vec, goal_idx))))
+            }
+            CancellableOutput::Complete(None) => { Ok(CancellableOutput::Complete(None)) }
+            CancellableOutput::Cancelled(_) => { Ok(CancellableOutput::Cancelled(None)) }
+        }
+    }
+    /// The LazySP loop.  Returns the node indices of the first shortest path to any of the given goal
+    /// nodes whose nodes and edges are all valid, or None once no path remains through nodes and
+    /// edges not known to be invalid.
+    fn lazy_search(&self, roadmap: &mut LazyPRMRoadmap, start_idx: usize, goal_idxs: &[usize], cancellation_token: &CancellationToken) -> Result<CancellableOutput<Option<Vec<usize>>>, OptimaError> {
+        loop {
+            if cancellation_token.is_cancelled() { return Ok(CancellableOutput::Cancelled(None)); }
+
+            let path_idxs = shortest_path_to_any_on_graph(roadmap.num_nodes(), start_idx, goal_idxs, |idx| {
+                roadmap.adjacency[idx].iter()
+                    .filter(|e| e.validity != Some(false) && roadmap.node_validity[e.neighbor_idx] != Some(false))
+                    .map(|e| (e.neighbor_idx, e.length))
+                    .collect()
+            });
+            let path_idxs = match path_idxs {
+                None => { return Ok(CancellableOutput::Complete(None)); }
+                Some(path_idxs) => { path_idxs }
+            };
+
+            // Nodes are cheap relative to edges, so all unchecked nodes on the path are checked at once.
+            let unchecked_node_idxs: Vec<usize> = path_idxs.iter().filter(|idx| roadmap.node_validity[**idx].is_none()).map(|idx| *idx).collect();
+            let node_results: Result<Vec<bool>, OptimaError> = unchecked_node_idxs.par_iter().map(|idx| {
+                self.collision_checker.state_is_valid(&self.spawn_dof_state(&roadmap.nodes[*idx])?)
+            }).collect();
+            let node_results = node_results?;
+            let mut all_nodes_valid = true;
+            for (idx, valid) in unchecked_node_idxs.iter().zip(node_results) {
+                roadmap.node_validity[*idx] = Some(valid);
+                if !valid { all_nodes_valid = false; }
+            }
+            if !all_nodes_valid { continue; }
+
+            let unchecked_edges: Vec<(usize, usize)> = path_idxs.windows(2).map(|w| (w[0], w[1])).filter(|(a, b)| roadmap.edge_validity(*a, *b).is_none()).collect();
+            if unchecked_edges.is_empty() {
+                return Ok(CancellableOutput::Complete(Some(path_idxs)));
+            }
+
+            let edges_to_check = match self.edge_selector {
+                LazyEdgeSelector::Forward => { vec![unchecked_edges[0]] }
+                LazyEdgeSelector::All => { unchecked_edges }
+            };
+            let edge_results: Result<Vec<bool>, OptimaError> = edges_to_check.par_iter().map(|(a, b)| {
+                let state_a = self.spawn_dof_state(&roadmap.nodes[*a])?;
+                let state_b = self.spawn_dof_state(&roadmap.nodes[*b])?;
+                self.collision_checker.segment_is_valid(&state_a, &state_b)
+            }).collect();
+            for ((a, b), valid) in edges_to_check.iter().zip(edge_results?) {
+                roadmap.set_edge_validity(*a, *b, valid);
+            }
+        }
+    }
+    fn add_candidate_edge(&self, roadmap: &mut LazyPRMRoadmap, a: usize, b: usize) {
+        let length = (&roadmap.nodes[a] - &roadmap.nodes[b]).norm();
+        if let Some(max_edge_length) = self.max_edge_length { if length > max_edge_length { return; } }
+        roadmap.add_edge(a, b, length);
+    }
+    fn spawn_dof_state(&self, v: &DVector<f64>) -> Result<RobotSetJointState, OptimaError> {
+        let robot_set_joint_state_module = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        return robot_set_joint_state_module.spawn_robot_set_joint_state(v.clone(), RobotSetJointStateType::DOF);
+    }
+    fn num_dofs(&self) -> usize {
+        return self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module().num_dofs();
+    }
+}
+
+/// Which unchecked edges of a candidate path a `LazyPRMPlanner` checks before searching again.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LazyEdgeSelector {
+    /// Only the first unchecked edge from the start, which does the fewest checks.
+    Forward,
+    /// All unchecked edges on the path at once (in parallel), which usually does more checks but
+    /// makes better use of multiple threads.
+    All
+}
+
+/// A roadmap built by a `LazyPRMPlanner`.  Nodes and edges carry their cached check results (None
+/// means not checked yet).  Edges are undirected and weighted by their Euclidean joint space length.
+/// Nodes are indexed by a nearest neighbor structure (see
+/// `LazyPRMPlanner::set_nearest_neighbor_structure_type`); the index is not saved with the roadmap
+/// and is rebuilt on its next densification or query.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LazyPRMRoadmap {
+    num_dofs: usize,
+    nodes: Vec<DVector<f64>>,
+    node_validity: Vec<Option<bool>>,
+    adjacency: Vec<Vec<LazyPRMEdge>>,
+    #[serde(skip)]
+    nearest_neighbor_index: Option<NearestNeighborStructureObject<()>>
+}
+impl LazyPRMRoadmap {
+    pub fn new_empty(num_dofs: usize) -> Self {
+        Self {
+            num_dofs,
+            nodes: vec![],
+            node_validity: vec![],
+            adjacency: vec![],
+            nearest_neighbor_index: None
+        }
+    }
+    /// Adds all nodes that are not indexed yet to the roadmap's nearest neighbor index.  If the index
+    /// is not of the given type, all nodes are re-indexed with a new structure of that type.
+    pub fn update_nearest_neighbor_index(&mut self, nearest_neighbor_structure_type: &NearestNeighborStructureType) -> Result<(), OptimaError> {
+        update_nearest_neighbor_index(&mut self.nearest_neighbor_index, &self.nodes, nearest_neighbor_structure_type)
+    }
+    /// Forgets all cached check results, e.g., after the scene's static geometry changed.
+    pub fn clear_validity_cache(&mut self) {
+        for v in self.node_validity.iter_mut() { *v = None; }
+        for edges in self.adjacency.iter_mut() {
+            for e in edges.iter_mut() { e.validity = None; }
+        }
+    }
+    pub fn edge_validity(&self, a: usize, b: usize) -> Option<bool> {
+        if a >= self.adjacency.len() { return None; }
+        return self.adjacency[a].iter().find(|e| e.neighbor_idx == b).and_then(|e| e.validity);
+    }
+    pub fn has_edge(&self, a: usize, b: usize) -> bool {
+        if a >= self.adjacency.len() { return false; }
+        return self.adjacency[a].iter().any(|e| e.neighbor_idx == b);
+    }
+    pub fn neighbors(&self, node_idx: usize) -> Result<&Vec<LazyPRMEdge>, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(node_idx, self.adjacency.len(), file!(), line!())?;
+        Ok(&self.adjacency[node_idx])
+    }
+    pub fn nodes(&self) -> &Vec<DVector<f64>> {
+        &self.nodes
+    }
+    pub fn node_validity(&self) -> &Vec<Option<bool>> {
+        &self.node_validity
+    }
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+    pub fn num_edges(&self) -> usize {
+        return self.adjacency.iter().map(|edges| edges.len()).sum::<usize>() / 2;
+    }
+    /// The number of edges that have been checked so far.
+    pub fn num_checked_edges(&self) -> usize {
+        return self.adjacency.iter().map(|edges| edges.iter().filter(|e| e.validity.is_some()).count()).sum::<usize>() / 2;
+    }
+    pub fn num_dofs(&self) -> usize {
+        self.num_dofs
+    }
+    fn add_node(&mut self, node: DVector<f64>, validity: Option<bool>) -> usize {
+        self.nodes.push(node);
+        self.node_validity.push(validity);
+        self.adjacency.push(vec![]);
+        self.nodes.len() - 1
+    }
+    fn add_edge(&mut self, a: usize, b: usize, length: f64) {
+        if a == b || self.has_edge(a, b) { return; }
+        self.adjacency[a].push(LazyPRMEdge { neighbor_idx: b, length, validity: None });
+        self.adjacency[b].push(LazyPRMEdge { neighbor_idx: a, length, validity: None });
+    }
+    fn set_edge_validity(&mut self, a: usize, b: usize, validity: bool) {
+        for (from, to) in [(a, b), (b, a)] {
+            for e in self.adjacency[from].iter_mut() {
+                if e.neighbor_idx == to { e.validity = Some(validity); }
+            }
+        }
+    }
+    /// Removes all nodes with index num_nodes or higher, along with their edges.
+    fn truncate_nodes(&mut self, num_nodes: usize) {
+        if self.nearest_neighbor_index.as_ref().map_or(false, |index| index.len() > num_nodes) { self.nearest_neighbor_index = None; }
+        self.nodes.truncate(num_nodes);
+        self.node_validity.truncate(num_nodes);
+        self.adjacency.truncate(num_nodes);
+        for edges in self.adjacency.iter_mut() { edges.retain(|e| e.neighbor_idx < num_nodes); }
+    }
+}
+
+/// An edge of a `LazyPRMRoadmap`, as seen from one of its end nodes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LazyPRMEdge {
+    neighbor_idx: usize,
+    length: f64,
+    validity: Option<bool>
+}
+impl LazyPRMEdge {
+    pub fn neighbor_idx(&self) -> usize {
+        self.neighbor_idx
+    }
+    pub fn length(&self) -> f64 {
+        self.length
+    }
+    pub fn validity(&self) -> Option<bool> {
+        self.validity
+    }
+}
@@ -0,0 +1,19 @@
+pub mod collision_checking;
+pub mod path_simplification;
+pub mod orientation_constraints;
+pub mod collision_free_sampling;
+pub mod state_validity;
+pub mod trajectory_certification;
+pub mod goal_set;
+pub mod grasp_set;
+pub mod relative_pose_constraint;
+pub mod state_space;
+pub mod rrt_connect;
+pub mod prm;
+pub mod lazy_prm;
+pub mod path_library;
+pub mod anytime_planning;
+pub mod kinodynamic_planning;
+pub mod reactive_planning;
+pub mod pick_and_place;
+pub mod separation_monitoring;
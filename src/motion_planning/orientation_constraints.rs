@@ -0,0 +1,156 @@
+use nalgebra::{DVector, Matrix3, Unit, UnitQuaternion, Vector3};
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_joint_state_module::RobotJointState;
+use crate::robot_modules::robot_kinematics_module::{JacobianEndPoint, JacobianMode, RobotKinematicsModule};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_robot::robot_ids::LinkId;
+use crate::utils::utils_se3::optima_se3_pose::OptimaSE3PoseType;
+
+/// The region that a link's orientation is restricted to by an `OrientationConstraint`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum OrientationConstraintRegion {
+    /// The given axis of the link (in the link's frame) must lie within half_angle radians of the
+    /// given world direction.  For example, local_axis = z, world_direction = -z, and half_angle =
+    /// 0.2 keeps a gripper pointing roughly downward while leaving rotation about its axis free.
+    Cone { local_axis: Unit<Vector3<f64>>, world_direction: Unit<Vector3<f64>>, half_angle: f64 },
+    /// The roll, pitch, and yaw of the link rotation relative to reference (i.e., of
+    /// reference^-1 * link_rotation, with link_rotation = Rz(yaw) Ry(pitch) Rx(roll)) must lie in the
+    /// given ranges.  Angles are in (-pi, pi], and pitch is in \[-pi/2, pi/2\].
+    RPYRange { reference: UnitQuaternion<f64>, roll_range: (f64, f64), pitch_range: (f64, f64), yaw_range: (f64, f64) }
+}
+
+/// Restricts the orientation of a robot link to an `OrientationConstraintRegion`.
+///
+/// A constraint is evaluated as a scalar value that is less than or equal to zero exactly when the
+/// constraint is satisfied, so it can be used directly as an inequality constraint g(q) <= 0 by
+/// optimization-based planners (via `value_and_gradient`) or as a validity check by sampling-based
+/// planners (via `is_satisfied`).  The gradient is with respect to the DOF joint state.
+///
+/// For a `Cone`, the value is cos(half_angle) - a . d, where a is the link axis in the world frame
+/// and d is the world direction; this is smooth everywhere, unlike the angle itself.  For an
+/// `RPYRange`, the value is the largest violation of the six range bounds (in radians), and the
+/// gradient is that of the active bound.  The RPY gradient is not defined at pitch = +-pi/2 (gimbal
+/// lock), where it is returned as zero.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OrientationConstraint {
+    link_id: LinkId,
+    region: OrientationConstraintRegion
+}
+impl OrientationConstraint {
+    pub fn new_cone(link_id: LinkId, local_axis: Vector3<f64>, world_direction: Vector3<f64>, half_angle: f64) -> Result<Self, OptimaError> {
+        if local_axis.norm() == 0.0 || world_direction.norm() == 0.0 {
+            return Err(OptimaError::new_generic_error_str("Cone axis and direction must be non-zero.", file!(), line!()));
+        }
+        if !(half_angle >= 0.0 && half_angle <= std::f64::consts::PI) {
+            return Err(OptimaError::new_generic_error_str(&format!("Cone half angle must be in [0, pi] (given {}).", half_angle), file!(), line!()));
+        }
+
+        Ok(Self {
+            link_id,
+            region: OrientationConstraintRegion::Cone {
+                local_axis: Unit::new_normalize(local_axis),
+                world_direction: Unit::new_normalize(world_direction),
+                half_angle
+            }
+        })
+    }
+    pub fn new_rpy_range(link_id: LinkId, reference: UnitQuaternion<f64>, roll_range: (f64, f64), pitch_range: (f64, f64), yaw_range: (f64, f64)) -> Result<Self, OptimaError> {
+        for (name, range) in [("Roll", roll_range), ("Pitch", pitch_range), ("Yaw", yaw_range)] {
+            if range.0 > range.1 {
+                return Err(OptimaError::new_generic_error_str(&format!("{} range lower bound is greater than its upper bound ({:?}).", name, range), file!(), line!()));
+            }
+        }
+
+        Ok(Self {
+            link_id,
+            region: OrientationConstraintRegion::RPYRange { reference, roll_range, pitch_range, yaw_range }
+        })
+    }
+    /// The constraint value for a given world frame link rotation (see `OrientationConstraint`).
+    /// Useful when forward kinematics results are already available.
+    pub fn value_from_rotation(&self, link_rotation: &UnitQuaternion<f64>) -> f64 {
+        return match &self.region {
+            OrientationConstraintRegion::Cone { local_axis, world_direction, half_angle } => {
+                let a = link_rotation * local_axis.into_inner();
+                half_angle.cos() - a.dot(&world_direction.into_inner())
+            }
+            OrientationConstraintRegion::RPYRange { reference, roll_range, pitch_range, yaw_range } => {
+                let (roll, pitch, yaw) = (reference.inverse() * link_rotation).euler_angles();
+                let (_, value) = Self::rpy_active_bound(&[roll, pitch, yaw], &[*roll_range, *pitch_range, *yaw_range]);
+                value
+            }
+        };
+    }
+    pub fn value(&self, robot_kinematics_module: &RobotKinematicsModule, joint_state: &RobotJointState) -> Result<f64, OptimaError> {
+        let link_rotation = self.link_rotation(robot_kinematics_module, joint_state)?;
+        return Ok(self.value_from_rotation(&link_rotation));
+    }
+    pub fn is_satisfied(&self, robot_kinematics_module: &RobotKinematicsModule, joint_state: &RobotJointState) -> Result<bool, OptimaError> {
+        return Ok(self.value(robot_kinematics_module, joint_state)? <= 0.0);
+    }
+    /// Returns the constraint value along with its gradient with respect to the DOF joint state.
+    pub fn value_and_gradient(&self, robot_kinematics_module: &RobotKinematicsModule, joint_state: &RobotJointState) -> Result<(f64, DVector<f64>), OptimaError> {
+        let link_rotation = self.link_rotation(robot_kinematics_module, joint_state)?;
+        let value = self.value_from_rotation(&link_rotation);
+
+        // Columns are the world frame angular velocities of the link per unit DOF velocity.
+        let angular_jacobian = robot_kinematics_module.compute_jacobian(joint_state, None, self.link_id, &JacobianEndPoint::Link, None, JacobianMode::Rotational)?;
+        let num_dofs = angular_jacobian.ncols();
+
+        let gradient = match &self.region {
+            OrientationConstraintRegion::Cone { local_axis, world_direction, half_angle: _ } => {
+                // d(a . d)/dq_i = d . (w_i x a) = w_i . (a x d)
+                let a = link_rotation * local_axis.into_inner();
+                let c = a.cross(&world_direction.into_inner());
+                DVector::from_fn(num_dofs, |i, _| -(angular_jacobian[(0, i)] * c[0] + angular_jacobian[(1, i)] * c[1] + angular_jacobian[(2, i)] * c[2]))
+            }
+            OrientationConstraintRegion::RPYRange { reference, roll_range, pitch_range, yaw_range } => {
+                let (roll, pitch, yaw) = (reference.inverse() * link_rotation).euler_angles();
+                let (active, _) = Self::rpy_active_bound(&[roll, pitch, yaw], &[*roll_range, *pitch_range, *yaw_range]);
+
+                // Maps (roll, pitch, yaw) rates to angular velocity in the reference frame.
+                let e = Matrix3::new(yaw.cos() * pitch.cos(), -yaw.sin(), 0.0,
+                                     yaw.sin() * pitch.cos(), yaw.cos(), 0.0,
+                                     -pitch.sin(), 0.0, 1.0);
+                match (active, e.try_inverse()) {
+                    (Some((component, sign)), Some(e_inv)) if pitch.cos().abs() > 1e-9 => {
+                        let row = (e_inv * reference.inverse().to_rotation_matrix().into_inner()).row(component).into_owned();
+                        let rates = row * &angular_jacobian;
+                        DVector::from_fn(num_dofs, |i, _| sign * rates[i])
+                    }
+                    _ => { DVector::zeros(num_dofs) }
+                }
+            }
+        };
+
+        Ok((value, gradient))
+    }
+    pub fn link_id(&self) -> LinkId {
+        self.link_id
+    }
+    pub fn region(&self) -> &OrientationConstraintRegion {
+        &self.region
+    }
+    fn link_rotation(&self, robot_kinematics_module: &RobotKinematicsModule, joint_state: &RobotJointState) -> Result<UnitQuaternion<f64>, OptimaError> {
+        let fk_res = robot_kinematics_module.compute_fk(joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+        let link_idx = self.link_id.idx();
+        OptimaError::new_check_for_idx_out_of_bound_error(link_idx, fk_res.link_entries().len(), file!(), line!())?;
+        return match fk_res.link_entries()[link_idx].pose() {
+            None => { Err(OptimaError::new_generic_error_str(&format!("Link {} is not present in the robot configuration.", link_idx), file!(), line!())) }
+            Some(pose) => { Ok(pose.to_nalgebra_isometry().rotation) }
+        };
+    }
+    /// Returns the most violated (or least satisfied) of the six bounds as
+    /// (Some((component, sign)), value), where the value is sign * angle + constant.
+    fn rpy_active_bound(angles: &[f64; 3], ranges: &[(f64, f64); 3]) -> (Option<(usize, f64)>, f64) {
+        let mut active = None;
+        let mut value = f64::NEG_INFINITY;
+        for i in 0..3 {
+            let lower_violation = ranges[i].0 - angles[i];
+            let upper_violation = angles[i] - ranges[i].1;
+            if lower_violation > value { value = lower_violation; active = Some((i, -1.0)); }
+            if upper_violation > value { value = upper_violation; active = Some((i, 1.0)); }
+        }
+        (active, value)
+    }
+}
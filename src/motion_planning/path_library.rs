@@ -0,0 +1,311 @@
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+use crate::motion_planning::collision_checking::RobotSetSceneCollisionChecker;
+use crate::motion_planning::goal_set::{GoalSetPath, JointStateGoalSet};
+use crate::motion_planning::rrt_connect::RRTConnectPlanner;
+use crate::robot_set_modules::robot_set_joint_state_module::{RobotSetJointState, RobotSetJointStateType};
+use crate::utils::utils_errors::OptimaError;
+
+/// Experience-based planning in the style of Lightning (Berenson et al., 2012): successful joint space
+/// paths are stored in a `PathLibrary`, and a new query first retrieves stored paths whose start and
+/// goal are both within region_radius of the query's start and goal (in Euclidean joint space
+/// distance), closest first.  A retrieved path is extended by the query's start and goal and then
+/// repaired: waypoints that are now in collision are dropped, and every invalid segment that remains
+/// is replaced by a short RRT-Connect query limited to repair_max_iterations iterations.  Only if no
+/// candidate can be repaired is the query planned from scratch with RRT-Connect.
+///
+/// Paths planned from scratch and repaired paths are added to the library, so repetitive tasks
+/// (e.g., picking from the same bins) quickly stop planning from scratch.  Libraries are plain data,
+/// so they can be saved and reloaded between sessions.
+pub struct ExperiencePlanner<'a> {
+    collision_checker: &'a RobotSetSceneCollisionChecker<'a>,
+    step_size: f64,
+    region_radius: f64,
+    max_candidates: usize,
+    repair_max_iterations: usize,
+    scratch_max_iterations: usize,
+    store_new_paths: bool
+}
+impl <'a> ExperiencePlanner<'a> {
+    /// step_size is the RRT-Connect step size used for both repairs and planning from scratch.
+    pub fn new(collision_checker: &'a RobotSetSceneCollisionChecker<'a>, step_size: f64, region_radius: f64) -> Result<Self, OptimaError> {
+        if !(step_size > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Step size must be positive (given {}).", step_size), file!(), line!()));
+        }
+        if !(region_radius > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Region radius must be positive (given {}).", region_radius), file!(), line!()));
+        }
+
+        Ok(Self {
+            collision_checker,
+            step_size,
+            region_radius,
+            max_candidates: 3,
+            repair_max_iterations: 500,
+            scratch_max_iterations: 5000,
+            store_new_paths: true
+        })
+    }
+    /// The maximum number of retrieved paths that are tried before planning from scratch.
+    pub fn set_max_candidates(&mut self, max_candidates: usize) {
+        self.max_candidates = max_candidates;
+    }
+    pub fn set_repair_max_iterations(&mut self, repair_max_iterations: usize) {
+        self.repair_max_iterations = repair_max_iterations;
+    }
+    pub fn set_scratch_max_iterations(&mut self, scratch_max_iterations: usize) {
+        self.scratch_max_iterations = scratch_max_iterations;
+    }
+    /// If false, the library is only read from (use counts are still updated).
+    pub fn set_store_new_paths(&mut self, store_new_paths: bool) {
+        self.store_new_paths = store_new_paths;
+    }
+    /// Returns a collision free path of DOF states from start to goal (both included), along with
+    /// where it came from, or None if no path is found.  Returns an error if start or goal is in
+    /// collision.
+    pub fn plan(&self, path_library: &mut PathLibrary, start: &RobotSetJointState, goal: &RobotSetJointState) -> Result<Option<ExperiencePlannerOutput>, OptimaError> {
+        let robot_set_joint_state_module = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        if path_library.num_dofs != robot_set_joint_state_module.num_dofs() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("ExperiencePlanner::plan", path_library.num_dofs, robot_set_joint_state_module.num_dofs(), file!(), line!()));
+        }
+        let start = robot_set_joint_state_module.convert_state_to_dof_state(start)?;
+        let goal = robot_set_joint_state_module.convert_state_to_dof_state(goal)?;
+        if !self.collision_checker.state_is_valid(&start)? {
+            return Err(OptimaError::new_generic_error_str("Start state of experience planner is in collision.", file!(), line!()));
+        }
+        if !self.collision_checker.state_is_valid(&goal)? {
+            return Err(OptimaError::new_generic_error_str("Goal state of experience planner is in collision.", file!(), line!()));
+        }
+
+        return self.plan_to_any_goal(path_library, &start, &[(0, goal.concatenated_state().clone())], |rrt_connect_planner| {
+            Ok(rrt_connect_planner.plan(&start, &goal)?.map(|path| GoalSetPath::new(path, 0)))
+        });
+    }
+    /// Same as `plan`, but plans to any goal of the goal set (whose goals are concatenated DOF vectors
+    /// of the robot set).  Stored paths are retrieved for every goal and tried closest first, and if
+    /// none can be repaired, the query is planned from scratch with
+    /// `RRTConnectPlanner::plan_to_goal_set`.  The output reports the goal its path ends at.  Goals
+    /// in collision are skipped.  Returns an error if start is in collision or if no goal is
+    /// collision free.
+    pub fn plan_to_goal_set(&self, path_library: &mut PathLibrary, start: &RobotSetJointState, goal_set: &JointStateGoalSet) -> Result<Option<ExperiencePlannerOutput>, OptimaError> {
+        let robot_set_joint_state_module = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        if path_library.num_dofs != robot_set_joint_state_module.num_dofs() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("ExperiencePlanner::plan_to_goal_set", path_library.num_dofs, robot_set_joint_state_module.num_dofs(), file!(), line!()));
+        }
+        goal_set.check_dimension("ExperiencePlanner::plan_to_goal_set", robot_set_joint_state_module.num_dofs())?;
+        let start = robot_set_joint_state_module.convert_state_to_dof_state(start)?;
+        if !self.collision_checker.state_is_valid(&start)? {
+            return Err(OptimaError::new_generic_error_str("Start state of experience planner is in collision.", file!(), line!()));
+        }
+        let goals = goal_set.valid_goals(|goal| {
+            self.collision_checker.state_is_valid(&robot_set_joint_state_module.spawn_robot_set_joint_state(goal.clone(), RobotSetJointStateType::DOF)?)
+        })?;
+
+        return self.plan_to_any_goal(path_library, &start, &goals, |rrt_connect_planner| {
+            rrt_connect_planner.plan_to_goal_set(&start, goal_set)
+        });
+    }
+    pub fn step_size(&self) -> f64 {
+        self.step_size
+    }
+    pub fn region_radius(&self) -> f64 {
+        self.region_radius
+    }
+    pub fn max_candidates(&self) -> usize {
+        self.max_candidates
+    }
+    pub fn repair_max_iterations(&self) -> usize {
+        self.repair_max_iterations
+    }
+    pub fn scratch_max_iterations(&self) -> usize {
+        self.scratch_max_iterations
+    }
+    pub fn store_new_paths(&self) -> bool {
+        self.store_new_paths
+    }
+    /// The query shared by `plan` and `plan_to_goal_set`.  Start is a collision free DOF state, goals
+    /// are given as (goal index, goal) pairs of collision free DOF vectors, and plan_from_scratch
+    /// runs an RRT-Connect query with the given planner.
+    fn plan_to_any_goal<P: Fn(&RRTConnectPlanner) -> Result<Option<GoalSetPath<RobotSetJointState>>, OptimaError>>(&self, path_library: &mut PathLibrary, start: &RobotSetJointState, goals: &[(usize, DVector<f64>)], plan_from_scratch: P) -> Result<Option<ExperiencePlannerOutput>, OptimaError> {
+        let mut candidates = vec![];
+        for (goal_idx, goal) in goals {
+            for (distance, entry_idx) in path_library.retrieve_with_distances(start.concatenated_state(), goal, self.region_radius) {
+                candidates.push((distance, entry_idx, *goal_idx, goal));
+            }
+        }
+        candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        for (_, entry_idx, goal_idx, goal) in candidates.into_iter().take(self.max_candidates) {
+            let entry_path = &path_library.entries[entry_idx].path;
+            let mut waypoints = vec![start.concatenated_state().clone()];
+            waypoints.extend(entry_path.iter().cloned());
+            waypoints.push(goal.clone());
+
+            if let Some((path, num_repaired_segments)) = self.repair(&waypoints)? {
+                path_library.entries[entry_idx].num_uses += 1;
+                let source = if num_repaired_segments == 0 {
+                    ExperiencePathSource::Retrieved { entry_idx }
+                } else {
+                    if self.store_new_paths { path_library.add_path_vecs(path.iter().map(|s| s.concatenated_state().clone()).collect()); }
+                    ExperiencePathSource::Repaired { entry_idx, num_repaired_segments }
+                };
+                return Ok(Some(ExperiencePlannerOutput { path, goal_idx, source }));
+            }
+        }
+
+        let mut rrt_connect_planner = RRTConnectPlanner::new(self.collision_checker, self.step_size)?;
+        rrt_connect_planner.set_max_iterations(self.scratch_max_iterations);
+        return match plan_from_scratch(&rrt_connect_planner)? {
+            None => { Ok(None) }
+            Some(goal_set_path) => {
+                let goal_idx = goal_set_path.goal_idx();
+                let path = goal_set_path.into_path();
+                // Paths of a single state (a start already within tolerance of a goal) are not stored.
+                if self.store_new_paths && path.len() >= 2 { path_library.add_path_vecs(path.iter().map(|s| s.concatenated_state().clone()).collect()); }
+                Ok(Some(ExperiencePlannerOutput { path, goal_idx, source: ExperiencePathSource::Scratch }))
+            }
+        }
+    }
+    /// Repairs the given waypoints (whose first and last waypoints are known to be valid).  Returns
+    /// the repaired path and the number of segments that had to be replanned, or None if any
+    /// replanning query fails.
+    fn repair(&self, waypoints: &Vec<DVector<f64>>) -> Result<Option<(Vec<RobotSetJointState>, usize)>, OptimaError> {
+        let robot_set_joint_state_module = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        let mut valid_waypoints = vec![];
+        for (i, w) in waypoints.iter().enumerate() {
+            let state = robot_set_joint_state_module.spawn_robot_set_joint_state(w.clone(), RobotSetJointStateType::DOF)?;
+            if i == 0 || i == waypoints.len() - 1 || self.collision_checker.state_is_valid(&state)? { valid_waypoints.push(state); }
+        }
+
+        let mut rrt_connect_planner = RRTConnectPlanner::new(self.collision_checker, self.step_size)?;
+        rrt_connect_planner.set_max_iterations(self.repair_max_iterations);
+
+        let mut out_vec = vec![valid_waypoints[0].clone()];
+        let mut num_repaired_segments = 0;
+        for w in valid_waypoints.windows(2) {
+            if self.collision_checker.segment_is_valid(&w[0], &w[1])? {
+                out_vec.push(w[1].clone());
+                continue;
+            }
+            match rrt_connect_planner.plan(&w[0], &w[1])? {
+                None => { return Ok(None); }
+                Some(sub_path) => { out_vec.extend(sub_path.into_iter().skip(1)); }
+            }
+            num_repaired_segments += 1;
+        }
+
+        Ok(Some((out_vec, num_repaired_segments)))
+    }
+}
+
+/// The output of `ExperiencePlanner::plan`.
+#[derive(Clone, Debug)]
+pub struct ExperiencePlannerOutput {
+    path: Vec<RobotSetJointState>,
+    goal_idx: usize,
+    source: ExperiencePathSource
+}
+impl ExperiencePlannerOutput {
+    pub fn path(&self) -> &Vec<RobotSetJointState> {
+        &self.path
+    }
+    /// The index of the goal the path ends at (always 0 for `ExperiencePlanner::plan`).
+    pub fn goal_idx(&self) -> usize {
+        self.goal_idx
+    }
+    pub fn source(&self) -> &ExperiencePathSource {
+        &self.source
+    }
+}
+
+/// Where a path returned by an `ExperiencePlanner` came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExperiencePathSource {
+    /// The stored path (extended by the query's start and goal) was valid as is.
+    Retrieved { entry_idx: usize },
+    /// The stored path needed the given number of segments replanned.
+    Repaired { entry_idx: usize, num_repaired_segments: usize },
+    /// No stored path could be used.
+    Scratch
+}
+
+/// A library of joint space paths (as robot set DOF states) for an `ExperiencePlanner`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PathLibrary {
+    num_dofs: usize,
+    entries: Vec<PathLibraryEntry>
+}
+impl PathLibrary {
+    pub fn new_empty(num_dofs: usize) -> Self {
+        Self {
+            num_dofs,
+            entries: vec![]
+        }
+    }
+    /// Adds a path of DOF states (with at least two states).  Returns the index of the new entry.
+    pub fn add_path(&mut self, path: &Vec<RobotSetJointState>) -> Result<usize, OptimaError> {
+        let mut path_vecs = vec![];
+        for state in path {
+            if state.robot_set_joint_state_type() != &RobotSetJointStateType::DOF {
+                return Err(OptimaError::new_generic_error_str("Paths in a path library must consist of DOF states.", file!(), line!()));
+            }
+            if state.concatenated_state().len() != self.num_dofs {
+                return Err(OptimaError::new_robot_state_vec_wrong_size_error("PathLibrary::add_path", state.concatenated_state().len(), self.num_dofs, file!(), line!()));
+            }
+            path_vecs.push(state.concatenated_state().clone());
+        }
+        if path_vecs.len() < 2 {
+            return Err(OptimaError::new_generic_error_str("Paths in a path library must have at least two states.", file!(), line!()));
+        }
+        Ok(self.add_path_vecs(path_vecs))
+    }
+    pub fn remove_path(&mut self, entry_idx: usize) -> Result<(), OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(entry_idx, self.entries.len(), file!(), line!())?;
+        self.entries.remove(entry_idx);
+        Ok(())
+    }
+    /// Indices of the entries whose start is within region_radius of the given start and whose goal
+    /// is within region_radius of the given goal, sorted by the sum of the two distances.
+    pub fn retrieve(&self, start: &DVector<f64>, goal: &DVector<f64>, region_radius: f64) -> Vec<usize> {
+        return self.retrieve_with_distances(start, goal, region_radius).into_iter().map(|(_, i)| i).collect();
+    }
+    pub fn entries(&self) -> &Vec<PathLibraryEntry> {
+        &self.entries
+    }
+    pub fn num_entries(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn num_dofs(&self) -> usize {
+        self.num_dofs
+    }
+    /// Same as `retrieve`, but each index comes with the sum of the two distances.
+    fn retrieve_with_distances(&self, start: &DVector<f64>, goal: &DVector<f64>, region_radius: f64) -> Vec<(f64, usize)> {
+        let mut candidates = vec![];
+        for (i, entry) in self.entries.iter().enumerate() {
+            let start_distance = (entry.path.first().expect("error") - start).norm();
+            let goal_distance = (entry.path.last().expect("error") - goal).norm();
+            if start_distance <= region_radius && goal_distance <= region_radius { candidates.push((start_distance + goal_distance, i)); }
+        }
+        candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        return candidates;
+    }
+    fn add_path_vecs(&mut self, path: Vec<DVector<f64>>) -> usize {
+        self.entries.push(PathLibraryEntry { path, num_uses: 0 });
+        self.entries.len() - 1
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PathLibraryEntry {
+    path: Vec<DVector<f64>>,
+    num_uses: usize
+}
+impl PathLibraryEntry {
+    pub fn path(&self) -> &Vec<DVector<f64>> {
+        &self.path
+    }
+    /// The number of times this path was retrieved (and used, possibly after repair) by a planner.
+    pub fn num_uses(&self) -> usize {
+        self.num_uses
+    }
+}
@@ -0,0 +1,93 @@
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+use crate::motion_planning::collision_checking::RobotSetSceneCollisionChecker;
+use crate::robot_set_modules::robot_set_joint_state_module::RobotSetJointState;
+use crate::utils::utils_cancellation::{CancellableOutput, CancellationToken};
+use crate::utils::utils_errors::OptimaError;
+
+/// Removes redundant waypoints from a joint space path using the Douglas-Peucker algorithm.  A
+/// waypoint is removed when it lies within `tolerance` (Euclidean joint space distance) of the
+/// straight-line segment that would replace it, and only if that segment is re-validated as
+/// collision free.  The first and last waypoints are always kept.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JointSpacePathSimplifier {
+    tolerance: f64
+}
+impl JointSpacePathSimplifier {
+    pub fn new(tolerance: f64) -> Self {
+        Self {
+            tolerance
+        }
+    }
+    /// Simplifies a path of robot set joint states in the scene of the given collision checker.
+    pub fn simplify_robot_set_path(&self, path: &Vec<RobotSetJointState>, collision_checker: &RobotSetSceneCollisionChecker) -> Result<Vec<RobotSetJointState>, OptimaError> {
+        let vecs: Vec<DVector<f64>> = path.iter().map(|s| s.concatenated_state().clone()).collect();
+        let keep_idxs = self.simplify_idxs(&vecs, |i, j| collision_checker.segment_is_valid(&path[i], &path[j]))?;
+        return Ok(keep_idxs.iter().map(|i| path[*i].clone()).collect());
+    }
+    /// Simplifies a path given as raw joint space vectors.  segment_is_valid is called with two
+    /// waypoint indices and should return true if the straight-line segment between them is valid.
+    pub fn simplify<F>(&self, path: &Vec<DVector<f64>>, segment_is_valid: F) -> Result<Vec<DVector<f64>>, OptimaError> where F: Fn(usize, usize) -> Result<bool, OptimaError> {
+        let keep_idxs = self.simplify_idxs(path, segment_is_valid)?;
+        return Ok(keep_idxs.iter().map(|i| path[*i].clone()).collect());
+    }
+    /// Returns the indices of the waypoints that are kept, in increasing order.
+    pub fn simplify_idxs<F>(&self, path: &Vec<DVector<f64>>, segment_is_valid: F) -> Result<Vec<usize>, OptimaError> where F: Fn(usize, usize) -> Result<bool, OptimaError> {
+        let res = self.simplify_idxs_with_cancellation(path, segment_is_valid, &CancellationToken::new())?;
+        return Ok(res.into_output());
+    }
+    /// Same as `simplify_idxs`, but stops once the given token is cancelled.  The partial result is
+    /// still a valid path: every waypoint in a part of the path that was not yet processed is kept.
+    pub fn simplify_idxs_with_cancellation<F>(&self, path: &Vec<DVector<f64>>, segment_is_valid: F, cancellation_token: &CancellationToken) -> Result<CancellableOutput<Vec<usize>>, OptimaError> where F: Fn(usize, usize) -> Result<bool, OptimaError> {
+        if path.len() <= 2 { return Ok(CancellableOutput::Complete((0..path.len()).collect())); }
+
+        let mut keep = vec![false; path.len()];
+        keep[0] = true;
+        keep[path.len() - 1] = true;
+
+        let mut cancelled = false;
+        let mut stack = vec![(0, path.len() - 1)];
+        while let Some((start, end)) = stack.pop() {
+            if end <= start + 1 { continue; }
+
+            if cancellation_token.is_cancelled() {
+                cancelled = true;
+                for k in start..=end { keep[k] = true; }
+                for (s, e) in &stack {
+                    for k in *s..=*e { keep[k] = true; }
+                }
+                break;
+            }
+
+            let mut max_deviation = -1.0;
+            let mut max_idx = start + 1;
+            for k in (start + 1)..end {
+                let deviation = Self::distance_to_segment(&path[k], &path[start], &path[end]);
+                if deviation > max_deviation { max_deviation = deviation; max_idx = k; }
+            }
+
+            if max_deviation <= self.tolerance && segment_is_valid(start, end)? { continue; }
+
+            keep[max_idx] = true;
+            stack.push((start, max_idx));
+            stack.push((max_idx, end));
+        }
+
+        let mut out_vec = vec![];
+        for (i, k) in keep.iter().enumerate() {
+            if *k { out_vec.push(i); }
+        }
+        return if cancelled { Ok(CancellableOutput::Cancelled(out_vec)) } else { Ok(CancellableOutput::Complete(out_vec)) };
+    }
+    fn distance_to_segment(point: &DVector<f64>, a: &DVector<f64>, b: &DVector<f64>) -> f64 {
+        let ab = b - a;
+        let ab_norm_squared = ab.norm_squared();
+        if ab_norm_squared == 0.0 { return (point - a).norm(); }
+
+        let t = ((point - a).dot(&ab) / ab_norm_squared).max(0.0).min(1.0);
+        return (point - (a + t * ab)).norm();
+    }
+    pub fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+}
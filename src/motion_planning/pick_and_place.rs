@@ -0,0 +1,501 @@
+use nalgebra::{DVector, Vector3};
+use serde::{Serialize, Deserialize};
+use crate::motion_planning::collision_checking::RobotSetSceneCollisionChecker;
+use crate::motion_planning::grasp_set::{GraspReachabilityFilter, GraspSet, ReachableGrasp};
+use crate::motion_planning::rrt_connect::RRTConnectPlanner;
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateType};
+use crate::robot_set_modules::robot_set_joint_state_module::{RobotSetJointState, RobotSetJointStateType};
+use crate::scenes::robot_geometric_shape_scene::{EnvObjPoseConstraint, EnvObjPoseConstraintGroupInput, RobotGeometricShapeScene};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3Pose, OptimaSE3PoseType};
+use crate::utils::utils_se3::pose_error_metric::PoseErrorMetric;
+use crate::utils::utils_shape_geometry::geometric_shape::GeometricShapeSignature;
+
+/// Chains the steps of a pick (and optionally place) task for one robot of a scene into a single
+/// call:
+///
+/// 1. `ApproachPoseGeneration`: reachable grasps are found with `GraspReachabilityFilter`, and for
+/// each (best quality first) the pregrasp pose is found by backing off from the grasp pose by
+/// approach_distance against the approach direction.  The first grasp whose pregrasp pose has a
+/// collision free IK solution and whose Cartesian approach from there is feasible is selected.
+/// 2. `PlanToPregrasp`: RRT-Connect (see `RRTConnectPlanner`) from the start state to the pregrasp
+/// state.
+/// 3. `Approach`: the Cartesian approach from the pregrasp pose to the grasp pose.
+/// 4. `Attach`: the object is attached to the end effector link, so it follows the gripper in all
+/// later collision checks.
+/// 5. `Retreat`: a Cartesian move of retreat_distance along the retreat direction.
+///
+/// If a place pose is given, the object is then placed by the mirror image of the pick:
+/// `PlanToPreplace` (to the place pose moved by retreat_distance along the retreat direction),
+/// `PlaceApproach` (a Cartesian move down to the place pose), `Release`, and `PlaceRetreat` (a
+/// Cartesian move of approach_distance against the approach direction).
+///
+/// The approach direction is given in the end effector frame (e.g., +z for a gripper that closes
+/// along its z axis) and the retreat direction in the world frame (e.g., +z to lift the object).
+/// Cartesian moves are interpolated in steps of at most cartesian_step_size, with IK seeded from
+/// the previous step; a move fails if IK does not converge, any joint moves more than
+/// max_cartesian_joint_step between steps, or a segment is in collision.  Contacts between the
+/// object and the robot are ignored from the approach to the place retreat, since the gripper
+/// touches the object by design.  The other robots of the set stay at their start states.
+///
+/// The pipeline stops at the first phase that fails.  Errors are only returned for invalid inputs;
+/// planning failures are reported in the returned `PickAndPlaceResult`.
+pub struct PickAndPlacePipeline<'a> {
+    robot_geometric_shape_scene: &'a RobotGeometricShapeScene,
+    robot_idx_in_set: usize,
+    end_effector_frame_name: String,
+    object_env_obj_idx: usize,
+    approach_direction: Vector3<f64>,
+    approach_distance: f64,
+    retreat_direction: Vector3<f64>,
+    retreat_distance: f64,
+    cartesian_step_size: f64,
+    max_cartesian_joint_step: f64,
+    collision_checking_resolution: f64,
+    planner_step_size: f64,
+    planner_max_iterations: usize,
+    pose_error_metric: PoseErrorMetric,
+    num_ik_restarts: usize,
+    ik_max_iterations: usize,
+    ik_position_tolerance: f64,
+    ik_orientation_tolerance: f64,
+    ik_damping: f64
+}
+impl <'a> PickAndPlacePipeline<'a> {
+    pub fn new(robot_geometric_shape_scene: &'a RobotGeometricShapeScene, robot_idx_in_set: usize, end_effector_frame_name: &str, object_env_obj_idx: usize) -> Result<Self, OptimaError> {
+        let robot_kinematics_modules = robot_geometric_shape_scene.robot_set().robot_set_kinematics_module().robot_kinematics_modules();
+        OptimaError::new_check_for_idx_out_of_bound_error(robot_idx_in_set, robot_kinematics_modules.len(), file!(), line!())?;
+        robot_kinematics_modules[robot_idx_in_set].resolve_frame(end_effector_frame_name)?;
+        OptimaError::new_check_for_idx_out_of_bound_error(object_env_obj_idx, robot_geometric_shape_scene.num_env_objs(), file!(), line!())?;
+
+        Ok(Self {
+            robot_geometric_shape_scene,
+            robot_idx_in_set,
+            end_effector_frame_name: end_effector_frame_name.to_string(),
+            object_env_obj_idx,
+            approach_direction: Vector3::new(0.0, 0.0, 1.0),
+            approach_distance: 0.1,
+            retreat_direction: Vector3::new(0.0, 0.0, 1.0),
+            retreat_distance: 0.1,
+            cartesian_step_size: 0.01,
+            max_cartesian_joint_step: 0.2,
+            collision_checking_resolution: 0.02,
+            planner_step_size: 0.2,
+            planner_max_iterations: 5000,
+            pose_error_metric: PoseErrorMetric::default(),
+            num_ik_restarts: 10,
+            ik_max_iterations: 100,
+            ik_position_tolerance: 0.001,
+            ik_orientation_tolerance: 0.001,
+            ik_damping: 0.05
+        })
+    }
+    pub fn set_approach(&mut self, approach_direction: Vector3<f64>, approach_distance: f64) -> Result<(), OptimaError> {
+        self.approach_direction = Self::normalize_direction(&approach_direction)?;
+        self.approach_distance = approach_distance;
+        Ok(())
+    }
+    pub fn set_retreat(&mut self, retreat_direction: Vector3<f64>, retreat_distance: f64) -> Result<(), OptimaError> {
+        self.retreat_direction = Self::normalize_direction(&retreat_direction)?;
+        self.retreat_distance = retreat_distance;
+        Ok(())
+    }
+    pub fn set_cartesian_step_size(&mut self, cartesian_step_size: f64, max_cartesian_joint_step: f64) -> Result<(), OptimaError> {
+        if !(cartesian_step_size > 0.0) || !(max_cartesian_joint_step > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Cartesian step size and max joint step must be positive (given {} and {}).", cartesian_step_size, max_cartesian_joint_step), file!(), line!()));
+        }
+        self.cartesian_step_size = cartesian_step_size;
+        self.max_cartesian_joint_step = max_cartesian_joint_step;
+        Ok(())
+    }
+    pub fn set_collision_checking_resolution(&mut self, collision_checking_resolution: f64) {
+        self.collision_checking_resolution = collision_checking_resolution;
+    }
+    pub fn set_planner_settings(&mut self, planner_step_size: f64, planner_max_iterations: usize) {
+        self.planner_step_size = planner_step_size;
+        self.planner_max_iterations = planner_max_iterations;
+    }
+    pub fn set_pose_error_metric(&mut self, pose_error_metric: PoseErrorMetric) {
+        self.pose_error_metric = pose_error_metric;
+    }
+    pub fn set_num_ik_restarts(&mut self, num_ik_restarts: usize) {
+        self.num_ik_restarts = num_ik_restarts;
+    }
+    pub fn set_ik_settings(&mut self, max_iterations: usize, position_tolerance: f64, orientation_tolerance: f64, damping: f64) {
+        self.ik_max_iterations = max_iterations;
+        self.ik_position_tolerance = position_tolerance;
+        self.ik_orientation_tolerance = orientation_tolerance;
+        self.ik_damping = damping;
+    }
+    /// Picks the object from object_pose with one of the grasps of the grasp set, starting at the
+    /// given robot set state, and places it at place_object_pose if given.
+    pub fn run(&self, start: &RobotSetJointState, grasp_set: &GraspSet, object_pose: &OptimaSE3Pose, place_object_pose: Option<&OptimaSE3Pose>) -> Result<PickAndPlaceResult, OptimaError> {
+        let start = self.robot_geometric_shape_scene.robot_set().robot_set_joint_state_module().convert_state_to_dof_state(start)?;
+        let mut out = PickAndPlaceResult {
+            phase_results: vec![],
+            grasp_idx: None,
+            success: false
+        };
+
+        let free_checker = self.collision_checker(EnvObjPoseConstraint::Absolute(object_pose.clone()), false)?;
+        let grasp_contact_checker = self.collision_checker(EnvObjPoseConstraint::Absolute(object_pose.clone()), true)?;
+
+        // Approach pose generation //////////////////////////////////////////////////////////////////
+        let mut grasp_reachability_filter = GraspReachabilityFilter::new(self.robot_geometric_shape_scene, self.robot_idx_in_set, &self.end_effector_frame_name, &start)?;
+        grasp_reachability_filter.set_object_env_obj_idx(Some(self.object_env_obj_idx))?;
+        grasp_reachability_filter.set_pose_error_metric(self.pose_error_metric.clone());
+        grasp_reachability_filter.set_num_ik_restarts(self.num_ik_restarts);
+        grasp_reachability_filter.set_max_iterations(self.ik_max_iterations);
+        grasp_reachability_filter.set_tolerances(self.ik_position_tolerance, self.ik_orientation_tolerance);
+        grasp_reachability_filter.set_damping(self.ik_damping);
+        let reachable_grasps = grasp_reachability_filter.filter(grasp_set, object_pose)?;
+
+        let mut selected: Option<(ReachableGrasp, Vec<RobotSetJointState>)> = None;
+        for reachable_grasp in &reachable_grasps {
+            let grasp_pose = reachable_grasp.end_effector_pose();
+            let pregrasp_pose = self.local_offset_pose(grasp_pose, &(-self.approach_direction * self.approach_distance))?;
+            let pregrasp_state = match self.solve_ik(reachable_grasp.robot_set_joint_state(), &pregrasp_pose, &free_checker)? {
+                None => { continue; }
+                Some(pregrasp_state) => { pregrasp_state }
+            };
+            if let Some(approach_path) = self.cartesian_path(&pregrasp_state, &pregrasp_pose, grasp_pose, &grasp_contact_checker)? {
+                selected = Some((reachable_grasp.clone(), approach_path));
+                break;
+            }
+        }
+        let (reachable_grasp, approach_path) = match selected {
+            None => {
+                let message = format!("None of the {} reachable grasps (of {}) has a feasible pregrasp pose and approach.", reachable_grasps.len(), grasp_set.len());
+                out.push_phase_result(PickAndPlacePhase::ApproachPoseGeneration, false, vec![], Some(message));
+                return Ok(out);
+            }
+            Some(selected) => { selected }
+        };
+        out.grasp_idx = Some(reachable_grasp.grasp_idx());
+        out.push_phase_result(PickAndPlacePhase::ApproachPoseGeneration, true, vec![approach_path[0].clone()], None);
+
+        // Plan to pregrasp //////////////////////////////////////////////////////////////////////////
+        let mut curr_state = match self.plan(&free_checker, &start, &approach_path[0], PickAndPlacePhase::PlanToPregrasp, &mut out)? {
+            None => { return Ok(out); }
+            Some(curr_state) => { curr_state }
+        };
+
+        // Approach //////////////////////////////////////////////////////////////////////////////////
+        out.push_phase_result(PickAndPlacePhase::Approach, true, approach_path.clone(), None);
+        curr_state = approach_path.last().unwrap().clone();
+        let mut curr_pose = reachable_grasp.end_effector_pose().clone();
+
+        // Attach ////////////////////////////////////////////////////////////////////////////////////
+        let attachment = self.attachment_constraint(&curr_state, object_pose)?;
+        let attached_checker = self.collision_checker(attachment, true)?;
+        out.push_phase_result(PickAndPlacePhase::Attach, true, vec![curr_state.clone()], None);
+
+        // Retreat ///////////////////////////////////////////////////////////////////////////////////
+        let retreat_pose = self.world_offset_pose(&curr_pose, &(self.retreat_direction * self.retreat_distance))?;
+        match self.cartesian_path(&curr_state, &curr_pose, &retreat_pose, &attached_checker)? {
+            None => {
+                out.push_phase_result(PickAndPlacePhase::Retreat, false, vec![], Some("Cartesian retreat is infeasible.".to_string()));
+                return Ok(out);
+            }
+            Some(path) => {
+                curr_state = path.last().unwrap().clone();
+                out.push_phase_result(PickAndPlacePhase::Retreat, true, path, None);
+            }
+        }
+
+        let place_object_pose = match place_object_pose {
+            None => {
+                out.success = true;
+                return Ok(out);
+            }
+            Some(place_object_pose) => { place_object_pose }
+        };
+
+        // Plan to preplace //////////////////////////////////////////////////////////////////////////
+        let place_pose = place_object_pose.multiply(grasp_set.grasp(reachable_grasp.grasp_idx())?.object_to_end_effector_pose(), true)?;
+        let preplace_pose = self.world_offset_pose(&place_pose, &(self.retreat_direction * self.retreat_distance))?;
+        let preplace_state = match self.solve_ik(&curr_state, &preplace_pose, &attached_checker)? {
+            None => {
+                out.push_phase_result(PickAndPlacePhase::PlanToPreplace, false, vec![], Some("No collision free IK solution for the preplace pose.".to_string()));
+                return Ok(out);
+            }
+            Some(preplace_state) => { preplace_state }
+        };
+        curr_state = match self.plan(&attached_checker, &curr_state, &preplace_state, PickAndPlacePhase::PlanToPreplace, &mut out)? {
+            None => { return Ok(out); }
+            Some(curr_state) => { curr_state }
+        };
+        curr_pose = preplace_pose;
+
+        // Place approach ////////////////////////////////////////////////////////////////////////////
+        match self.cartesian_path(&curr_state, &curr_pose, &place_pose, &attached_checker)? {
+            None => {
+                out.push_phase_result(PickAndPlacePhase::PlaceApproach, false, vec![], Some("Cartesian place approach is infeasible.".to_string()));
+                return Ok(out);
+            }
+            Some(path) => {
+                curr_state = path.last().unwrap().clone();
+                out.push_phase_result(PickAndPlacePhase::PlaceApproach, true, path, None);
+            }
+        }
+        curr_pose = place_pose;
+
+        // Release ///////////////////////////////////////////////////////////////////////////////////
+        let released_checker = self.collision_checker(EnvObjPoseConstraint::Absolute(place_object_pose.clone()), true)?;
+        out.push_phase_result(PickAndPlacePhase::Release, true, vec![curr_state.clone()], None);
+
+        // Place retreat /////////////////////////////////////////////////////////////////////////////
+        let place_retreat_pose = self.local_offset_pose(&curr_pose, &(-self.approach_direction * self.approach_distance))?;
+        match self.cartesian_path(&curr_state, &curr_pose, &place_retreat_pose, &released_checker)? {
+            None => {
+                out.push_phase_result(PickAndPlacePhase::PlaceRetreat, false, vec![], Some("Cartesian place retreat is infeasible.".to_string()));
+                return Ok(out);
+            }
+            Some(path) => {
+                out.push_phase_result(PickAndPlacePhase::PlaceRetreat, true, path, None);
+            }
+        }
+
+        out.success = true;
+        Ok(out)
+    }
+    pub fn robot_idx_in_set(&self) -> usize {
+        self.robot_idx_in_set
+    }
+    pub fn end_effector_frame_name(&self) -> &str {
+        &self.end_effector_frame_name
+    }
+    pub fn object_env_obj_idx(&self) -> usize {
+        self.object_env_obj_idx
+    }
+    pub fn approach_direction(&self) -> &Vector3<f64> {
+        &self.approach_direction
+    }
+    pub fn approach_distance(&self) -> f64 {
+        self.approach_distance
+    }
+    pub fn retreat_direction(&self) -> &Vector3<f64> {
+        &self.retreat_direction
+    }
+    pub fn retreat_distance(&self) -> f64 {
+        self.retreat_distance
+    }
+    /// A checker with the object's pose overridden by the given constraint, optionally ignoring
+    /// contacts between the object and the robot.
+    fn collision_checker(&self, object_pose_constraint: EnvObjPoseConstraint, ignore_object_robot_contacts: bool) -> Result<RobotSetSceneCollisionChecker<'a>, OptimaError> {
+        let mut input = EnvObjPoseConstraintGroupInput::new_empty_some(self.robot_geometric_shape_scene);
+        input.inject_new_pose_constraint(self.object_env_obj_idx, object_pose_constraint)?;
+
+        let mut out = RobotSetSceneCollisionChecker::new(self.robot_geometric_shape_scene, self.collision_checking_resolution)?;
+        out.set_env_obj_pose_constraint_group_input(Some(input));
+        if ignore_object_robot_contacts { out.add_ignored_env_obj_robot_contact(self.object_env_obj_idx, self.robot_idx_in_set)?; }
+        Ok(out)
+    }
+    /// The constraint that keeps the object, currently at object_pose, at its current offset from the
+    /// first shape of the end effector link.
+    fn attachment_constraint(&self, robot_set_joint_state: &RobotSetJointState, object_pose: &OptimaSE3Pose) -> Result<EnvObjPoseConstraint, OptimaError> {
+        let robot_kinematics_module = &self.robot_geometric_shape_scene.robot_set().robot_set_kinematics_module().robot_kinematics_modules()[self.robot_idx_in_set];
+        let (link_id, _) = robot_kinematics_module.resolve_frame(&self.end_effector_frame_name)?;
+        let parent_signature = GeometricShapeSignature::RobotSetLink {
+            robot_idx_in_set: self.robot_idx_in_set,
+            link_idx_in_robot: link_id.idx(),
+            shape_idx_in_link: 0
+        };
+        let parent_shape_idx = self.robot_geometric_shape_scene.get_shape_idx_from_signature(&parent_signature)?;
+
+        let poses = self.robot_geometric_shape_scene.recover_poses(robot_set_joint_state, None)?;
+        let link_pose = match &poses.poses()[parent_shape_idx] {
+            None => { return Err(OptimaError::new_generic_error_str(&format!("End effector link {} has no pose, so the object cannot be attached to it.", link_id.idx()), file!(), line!())); }
+            Some(link_pose) => { link_pose }
+        };
+        let offset = link_pose.displacement(object_pose, true)?;
+
+        Ok(EnvObjPoseConstraint::RelativeOffset { parent_signature, offset })
+    }
+    /// Plans between two states with RRT-Connect and records the phase.  Returns the end state, or
+    /// None if planning failed.
+    fn plan(&self, collision_checker: &RobotSetSceneCollisionChecker, start: &RobotSetJointState, goal: &RobotSetJointState, phase: PickAndPlacePhase, out: &mut PickAndPlaceResult) -> Result<Option<RobotSetJointState>, OptimaError> {
+        for (state, name) in [(start, "start"), (goal, "goal")] {
+            if !collision_checker.state_is_valid(state)? {
+                out.push_phase_result(phase, false, vec![], Some(format!("The {} state is in collision.", name)));
+                return Ok(None);
+            }
+        }
+
+        let mut planner = RRTConnectPlanner::new(collision_checker, self.planner_step_size)?;
+        planner.set_max_iterations(self.planner_max_iterations);
+        planner.set_moving_robot_idxs(Some(vec![self.robot_idx_in_set]))?;
+        return match planner.plan(start, goal)? {
+            None => {
+                out.push_phase_result(phase, false, vec![], Some(format!("No path found in {} iterations.", self.planner_max_iterations)));
+                Ok(None)
+            }
+            Some(path) => {
+                let end = path.last().unwrap().clone();
+                out.push_phase_result(phase, true, path, None);
+                Ok(Some(end))
+            }
+        };
+    }
+    /// IK for the end effector at the target pose, seeded from the robot's state in the given robot
+    /// set state and then from random restarts.  Returns a collision free robot set state, or None.
+    fn solve_ik(&self, seed: &RobotSetJointState, target_pose: &OptimaSE3Pose, collision_checker: &RobotSetSceneCollisionChecker) -> Result<Option<RobotSetJointState>, OptimaError> {
+        let robot_kinematics_module = &self.robot_geometric_shape_scene.robot_set().robot_set_kinematics_module().robot_kinematics_modules()[self.robot_idx_in_set];
+        let seed_joint_state = self.robot_joint_state(seed)?;
+
+        for attempt in 0..self.num_ik_restarts + 1 {
+            let initial_joint_state = if attempt == 0 {
+                seed_joint_state.clone()
+            } else {
+                robot_kinematics_module.robot_joint_state_module().sample_joint_state(&RobotJointStateType::DOF)
+            };
+            let ik_res = robot_kinematics_module.compute_ik_damped_least_squares_on_frame(&initial_joint_state, &self.end_effector_frame_name, target_pose, &self.pose_error_metric, self.ik_max_iterations, self.ik_position_tolerance, self.ik_orientation_tolerance, self.ik_damping)?;
+            if !ik_res.converged() { continue; }
+
+            let robot_set_joint_state = self.replace_robot_joint_state(seed, ik_res.joint_state())?;
+            if collision_checker.state_is_valid(&robot_set_joint_state)? { return Ok(Some(robot_set_joint_state)); }
+        }
+
+        Ok(None)
+    }
+    /// A straight line end effector path from start_pose (reached at start) to end_pose.  The output
+    /// includes start.  Returns None if the path is infeasible.
+    fn cartesian_path(&self, start: &RobotSetJointState, start_pose: &OptimaSE3Pose, end_pose: &OptimaSE3Pose, collision_checker: &RobotSetSceneCollisionChecker) -> Result<Option<Vec<RobotSetJointState>>, OptimaError> {
+        let robot_kinematics_module = &self.robot_geometric_shape_scene.robot_set().robot_set_kinematics_module().robot_kinematics_modules()[self.robot_idx_in_set];
+        let distance = (end_pose.translation() - start_pose.translation()).norm();
+        let num_steps = ((distance / self.cartesian_step_size).ceil() as usize).max(1);
+
+        let mut out_vec = vec![start.clone()];
+        let mut prev_joint_state = self.robot_joint_state(start)?;
+        for i in 1..=num_steps {
+            let target_pose = start_pose.slerp(end_pose, i as f64 / num_steps as f64, true)?;
+            let ik_res = robot_kinematics_module.compute_ik_damped_least_squares_on_frame(&prev_joint_state, &self.end_effector_frame_name, &target_pose, &self.pose_error_metric, self.ik_max_iterations, self.ik_position_tolerance, self.ik_orientation_tolerance, self.ik_damping)?;
+            if !ik_res.converged() { return Ok(None); }
+
+            let joint_step = (ik_res.joint_state().joint_state() - prev_joint_state.joint_state()).amax();
+            if joint_step > self.max_cartesian_joint_step { return Ok(None); }
+
+            let robot_set_joint_state = self.replace_robot_joint_state(start, ik_res.joint_state())?;
+            if !collision_checker.state_is_valid(&robot_set_joint_state)? { return Ok(None); }
+            if !collision_checker.segment_is_valid(out_vec.last().unwrap(), &robot_set_joint_state)? { return Ok(None); }
+
+            out_vec.push(robot_set_joint_state);
+            prev_joint_state = ik_res.joint_state().clone();
+        }
+
+        Ok(Some(out_vec))
+    }
+    fn robot_joint_state(&self, robot_set_joint_state: &RobotSetJointState) -> Result<RobotJointState, OptimaError> {
+        let robot_joint_states = self.robot_geometric_shape_scene.robot_set().robot_set_joint_state_module().split_robot_set_joint_state_into_robot_joint_states(robot_set_joint_state)?;
+        return Ok(robot_joint_states[self.robot_idx_in_set].clone());
+    }
+    fn replace_robot_joint_state(&self, robot_set_joint_state: &RobotSetJointState, robot_joint_state: &RobotJointState) -> Result<RobotSetJointState, OptimaError> {
+        let robot_joint_states = self.robot_geometric_shape_scene.robot_set().robot_set_joint_state_module().split_robot_set_joint_state_into_robot_joint_states(robot_set_joint_state)?;
+        let mut out_vec = vec![];
+        for (i, s) in robot_joint_states.iter().enumerate() {
+            let s = if i == self.robot_idx_in_set { robot_joint_state } else { s };
+            out_vec.extend(s.joint_state().iter());
+        }
+        return self.robot_geometric_shape_scene.robot_set().robot_set_joint_state_module().spawn_robot_set_joint_state(DVector::from_vec(out_vec), RobotSetJointStateType::DOF);
+    }
+    /// The pose moved by the given translation expressed in its own frame.
+    fn local_offset_pose(&self, pose: &OptimaSE3Pose, translation: &Vector3<f64>) -> Result<OptimaSE3Pose, OptimaError> {
+        let offset = OptimaSE3Pose::new_from_euler_angles(0.0, 0.0, 0.0, translation[0], translation[1], translation[2], &OptimaSE3PoseType::ImplicitDualQuaternion);
+        return pose.multiply(&offset, true);
+    }
+    /// The pose moved by the given translation expressed in the world frame.
+    fn world_offset_pose(&self, pose: &OptimaSE3Pose, translation: &Vector3<f64>) -> Result<OptimaSE3Pose, OptimaError> {
+        let offset = OptimaSE3Pose::new_from_euler_angles(0.0, 0.0, 0.0, translation[0], translation[1], translation[2], &OptimaSE3PoseType::ImplicitDualQuaternion);
+        return offset.multiply(pose, true);
+    }
+    fn normalize_direction(direction: &Vector3<f64>) -> Result<Vector3<f64>, OptimaError> {
+        let norm = direction.norm();
+        if !(norm > 0.0) {
+            return Err(OptimaError::new_generic_error_str("Direction must be a nonzero vector.", file!(), line!()));
+        }
+        Ok(direction / norm)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PickAndPlacePhase {
+    ApproachPoseGeneration,
+    PlanToPregrasp,
+    Approach,
+    Attach,
+    Retreat,
+    PlanToPreplace,
+    PlaceApproach,
+    Release,
+    PlaceRetreat
+}
+
+/// The result of one phase of a `PickAndPlacePipeline` run.  The path is the robot set DOF states of
+/// the phase (a single state for `ApproachPoseGeneration`, `Attach`, and `Release`, which do not move
+/// the robot, and empty if the phase failed), and the message explains a failure.
+#[derive(Clone, Debug)]
+pub struct PickAndPlacePhaseResult {
+    phase: PickAndPlacePhase,
+    success: bool,
+    path: Vec<RobotSetJointState>,
+    message: Option<String>
+}
+impl PickAndPlacePhaseResult {
+    pub fn phase(&self) -> &PickAndPlacePhase {
+        &self.phase
+    }
+    pub fn success(&self) -> bool {
+        self.success
+    }
+    pub fn path(&self) -> &Vec<RobotSetJointState> {
+        &self.path
+    }
+    pub fn message(&self) -> &Option<String> {
+        &self.message
+    }
+}
+
+/// The output of `PickAndPlacePipeline::run`: the results of the phases that were run, in order (the
+/// last one is the failed phase if the run did not succeed), and the index of the selected grasp in
+/// the grasp set.
+#[derive(Clone, Debug)]
+pub struct PickAndPlaceResult {
+    phase_results: Vec<PickAndPlacePhaseResult>,
+    grasp_idx: Option<usize>,
+    success: bool
+}
+impl PickAndPlaceResult {
+    /// The concatenated paths of all phases, without repeated states at phase boundaries.
+    pub fn full_path(&self) -> Vec<RobotSetJointState> {
+        let mut out_vec: Vec<RobotSetJointState> = vec![];
+        for phase_result in &self.phase_results {
+            for state in &phase_result.path {
+                if let Some(last) = out_vec.last() {
+                    if last.concatenated_state() == state.concatenated_state() { continue; }
+                }
+                out_vec.push(state.clone());
+            }
+        }
+        out_vec
+    }
+    pub fn phase_result(&self, phase: &PickAndPlacePhase) -> Option<&PickAndPlacePhaseResult> {
+        return self.phase_results.iter().find(|r| &r.phase == phase);
+    }
+    pub fn phase_results(&self) -> &Vec<PickAndPlacePhaseResult> {
+        &self.phase_results
+    }
+    pub fn grasp_idx(&self) -> Option<usize> {
+        self.grasp_idx
+    }
+    pub fn success(&self) -> bool {
+        self.success
+    }
+    fn push_phase_result(&mut self, phase: PickAndPlacePhase, success: bool, path: Vec<RobotSetJointState>, message: Option<String>) {
+        self.phase_results.push(PickAndPlacePhaseResult {
+            phase,
+            success,
+            path,
+            message
+        });
+    }
+}
@@ -0,0 +1,388 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use nalgebra::DVector;
+use ordered_float::OrderedFloat;
+use rayon::prelude::*;
+use serde::{Serialize, Deserialize};
+use crate::motion_planning::collision_checking::RobotSetSceneCollisionChecker;
+use crate::motion_planning::goal_set::{GoalSetPath, JointStateGoalSet};
+use crate::robot_set_modules::robot_set_joint_state_module::{RobotSetJointState, RobotSetJointStateType};
+use crate::utils::utils_cancellation::{CancellableOutput, CancellationToken};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_nearest_neighbor::{JointStateDistanceMetric, NearestNeighborStructure, NearestNeighborStructureObject, NearestNeighborStructureType};
+
+/// Probabilistic roadmap (PRM; Kavraki et al., 1996) construction and querying in the scene of a
+/// `RobotSetSceneCollisionChecker`.  Roadmap nodes are collision free DOF states of the robot set,
+/// and each node is connected to its k nearest neighbors (in Euclidean joint space distance) by
+/// every straight segment that passes `RobotSetSceneCollisionChecker::segment_is_valid`.
+///
+/// Samples and candidate edges are validated in parallel, which is where nearly all of the time
+/// goes.  A roadmap can be densified incrementally (see `densify`): new samples are connected to
+/// their k nearest neighbors among all nodes, and existing nodes are connected to new samples that
+/// are among their own k nearest neighbors.  Roadmaps are plain data (`PRMRoadmap`), so they can be
+/// saved and reloaded for scenes whose static geometry has not changed.
+pub struct PRMPlanner<'a> {
+    collision_checker: &'a RobotSetSceneCollisionChecker<'a>,
+    k: usize,
+    max_edge_length: Option<f64>,
+    max_sample_attempts: usize,
+    nearest_neighbor_structure_type: NearestNeighborStructureType
+}
+impl <'a> PRMPlanner<'a> {
+    pub fn new(collision_checker: &'a RobotSetSceneCollisionChecker<'a>, k: usize) -> Result<Self, OptimaError> {
+        if k == 0 {
+            return Err(OptimaError::new_generic_error_str("PRM must connect each node to at least one neighbor (k must be positive).", file!(), line!()));
+        }
+
+        Ok(Self {
+            collision_checker,
+            k,
+            max_edge_length: None,
+            max_sample_attempts: 1000,
+            nearest_neighbor_structure_type: NearestNeighborStructureType::KDTree
+        })
+    }
+    /// Candidate edges longer than the given length are not validated (None means no limit).
+    pub fn set_max_edge_length(&mut self, max_edge_length: Option<f64>) -> Result<(), OptimaError> {
+        if let Some(max_edge_length) = max_edge_length {
+            if !(max_edge_length > 0.0) {
+                return Err(OptimaError::new_generic_error_str(&format!("Max edge length must be positive (given {}).", max_edge_length), file!(), line!()));
+            }
+        }
+        self.max_edge_length = max_edge_length;
+        Ok(())
+    }
+    /// The number of uniform samples drawn per roadmap node before giving up on finding a
+    /// collision free one.
+    pub fn set_max_sample_attempts(&mut self, max_sample_attempts: usize) -> Result<(), OptimaError> {
+        if max_sample_attempts == 0 {
+            return Err(OptimaError::new_generic_error_str("Max sample attempts must be positive.", file!(), line!()));
+        }
+        self.max_sample_attempts = max_sample_attempts;
+        Ok(())
+    }
+    /// The structure that indexes roadmap nodes for nearest neighbor queries (KD-tree by default;
+    /// GNAT scales better to robot sets with many DOFs).  A roadmap indexed by a different structure
+    /// is re-indexed on its next densification.
+    pub fn set_nearest_neighbor_structure_type(&mut self, nearest_neighbor_structure_type: NearestNeighborStructureType) {
+        self.nearest_neighbor_structure_type = nearest_neighbor_structure_type;
+    }
+    /// Builds a roadmap with num_samples collision free nodes.
+    pub fn build_roadmap(&self, num_samples: usize) -> Result<PRMRoadmap, OptimaError> {
+        let mut roadmap = PRMRoadmap::new_empty(self.num_dofs());
+        self.densify(&mut roadmap, num_samples)?;
+        Ok(roadmap)
+    }
+    /// Adds num_samples collision free nodes to the given roadmap and connects them.
+    pub fn densify(&self, roadmap: &mut PRMRoadmap, num_samples: usize) -> Result<(), OptimaError> {
+        self.densify_with_cancellation(roadmap, num_samples, &CancellationToken::new())?;
+        Ok(())
+    }
+    /// Same as `densify`, but stops once the given token is cancelled.  A densification step is all
+    /// or nothing: if it is cancelled, the roadmap is left unchanged.
+    pub fn densify_with_cancellation(&self, roadmap: &mut PRMRoadmap, num_samples: usize, cancellation_token: &CancellationToken) -> Result<CancellableOutput<()>, OptimaError> {
+        if roadmap.num_dofs != self.num_dofs() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("PRMPlanner::densify", roadmap.num_dofs, self.num_dofs(), file!(), line!()));
+        }
+
+        let samples: Result<Vec<Option<DVector<f64>>>, OptimaError> = (0..num_samples).into_par_iter().map(|_| self.sample(cancellation_token)).collect();
+        let samples = samples?;
+        if cancellation_token.is_cancelled() { return Ok(CancellableOutput::Cancelled(())); }
+
+        let mut new_roadmap = roadmap.clone();
+        let first_new_idx = new_roadmap.num_nodes();
+        for sample in samples { new_roadmap.add_node(sample.expect("error")); }
+        new_roadmap.update_nearest_neighbor_index(&self.nearest_neighbor_structure_type)?;
+
+        let mut candidate_edges = HashSet::new();
+        for node_idx in 0..new_roadmap.num_nodes() {
+            for neighbor_idx in new_roadmap.k_nearest(&new_roadmap.nodes[node_idx], self.k, Some(node_idx)) {
+                // Edges between two old nodes were already decided by an earlier densification step.
+                if node_idx < first_new_idx && neighbor_idx < first_new_idx { continue; }
+                candidate_edges.insert((node_idx.min(neighbor_idx), node_idx.max(neighbor_idx)));
+            }
+        }
+        let candidate_edges: Vec<(usize, usize)> = candidate_edges.into_iter().collect();
+
+        let valid_edges: Result<Vec<Option<(usize, usize, f64)>>, OptimaError> = candidate_edges.par_iter().map(|(a, b)| {
+            if cancellation_token.is_cancelled() { return Ok(None); }
+            let length = (&new_roadmap.nodes[*a] - &new_roadmap.nodes[*b]).norm();
+            if let Some(max_edge_length) = self.max_edge_length { if length > max_edge_length { return Ok(None); } }
+            let state_a = self.spawn_dof_state(&new_roadmap.nodes[*a])?;
+            let state_b = self.spawn_dof_state(&new_roadmap.nodes[*b])?;
+            return if self.collision_checker.segment_is_valid(&state_a, &state_b)? { Ok(Some((*a, *b, length))) } else { Ok(None) };
+        }).collect();
+        let valid_edges = valid_edges?;
+        if cancellation_token.is_cancelled() { return Ok(CancellableOutput::Cancelled(())); }
+
+        for (a, b, length) in valid_edges.into_iter().flatten() { new_roadmap.add_edge(a, b, length); }
+        *roadmap = new_roadmap;
+
+        Ok(CancellableOutput::Complete(()))
+    }
+    /// Returns a collision free path of DOF states from start to goal (both included) through the
+    /// given roadmap, or None if start and goal cannot both be connected to the same component of the
+    /// roadmap.  Start and goal are connected to their k nearest roadmap nodes for this query only;
+    /// the roadmap itself is neither changed nor copied.  Returns an error if start or goal is in collision.
+    /// Roadmaps that were loaded rather than built are searched linearly until they are indexed (see
+    /// `PRMRoadmap::update_nearest_neighbor_index`).
+    pub fn query(&self, roadmap: &PRMRoadmap, start: &RobotSetJointState, goal: &RobotSetJointState) -> Result<Option<Vec<RobotSetJointState>>, OptimaError> {
+        let robot_set_joint_state_module = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        let start = robot_set_joint_state_module.convert_state_to_dof_state(start)?;
+        let goal = robot_set_joint_state_module.convert_state_to_dof_state(goal)?;
+        if !self.collision_checker.state_is_valid(&start)? {
+            return Err(OptimaError::new_generic_error_str("Start state of PRM query is in collision.", file!(), line!()));
+        }
+        if !self.collision_checker.state_is_valid(&goal)? {
+            return Err(OptimaError::new_generic_error_str("Goal state of PRM query is in collision.", file!(), line!()));
+        }
+
+        let res = self.query_to_any_goal(roadmap, &start, &[(0, goal)])?;
+        return Ok(res.map(|goal_set_path| goal_set_path.into_path()));
+    }
+    /// Same as `query`, but returns a path to any goal of the goal set (whose goals are concatenated
+    /// DOF vectors of the robot set).  All goals are connected to the roadmap at once and the
+    /// shortest path search stops at the first goal it reaches, which the output reports (see
+    /// `GoalSetPath`).  Goals in collision are skipped.  Returns an error if start is in collision or
+    /// if no goal is collision free.
+    pub fn query_goal_set(&self, roadmap: &PRMRoadmap, start: &RobotSetJointState, goal_set: &JointStateGoalSet) -> Result<Option<GoalSetPath<RobotSetJointState>>, OptimaError> {
+        let robot_set_joint_state_module = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        goal_set.check_dimension("PRMPlanner::query_goal_set", robot_set_joint_state_module.num_dofs())?;
+        let start = robot_set_joint_state_module.convert_state_to_dof_state(start)?;
+        if !self.collision_checker.state_is_valid(&start)? {
+            return Err(OptimaError::new_generic_error_str("Start state of PRM query is in collision.", file!(), line!()));
+        }
+        if let Some(m) = goal_set.reached_goal(start.concatenated_state()) {
+            return Ok(Some(GoalSetPath::new(vec![start], m.goal_idx())));
+        }
+
+        let mut goals = vec![];
+        for (goal_idx, goal) in goal_set.valid_goals(|goal| self.collision_checker.state_is_valid(&self.spawn_dof_state(goal)?))? {
+            goals.push((goal_idx, self.spawn_dof_state(&goal)?));
+        }
+        return self.query_to_any_goal(roadmap, &start, &goals);
+    }
+    pub fn k(&self) -> usize {
+        self.k
+    }
+    pub fn max_edge_length(&self) -> Option<f64> {
+        self.max_edge_length
+    }
+    pub fn max_sample_attempts(&self) -> usize {
+        self.max_sample_attempts
+    }
+    pub fn nearest_neighbor_structure_type(&self) -> &NearestNeighborStructureType {
+        &self.nearest_neighbor_structure_type
+    }
+    /// The query shared by `query` and `query_goal_set`.  Start and goals are collision free DOF
+    /// states, and goals are given as (goal index, goal) pairs.
+    fn query_to_any_goal(&self, roadmap: &PRMRoadmap, start: &RobotSetJointState, goals: &[(usize, RobotSetJointState)]) -> Result<Option<GoalSetPath<RobotSetJointState>>, OptimaError> {
+        for (goal_idx, goal) in goals {
+            if self.collision_checker.segment_is_valid(start, goal)? {
+                return Ok(Some(GoalSetPath::new(vec![start.clone(), goal.clone()], *goal_idx)));
+            }
+        }
+
+        // Start and goals are connected to the roadmap through an overlay, so the roadmap is never
+        // copied.  Query node i has node index num_roadmap_nodes + i.
+        let num_roadmap_nodes = roadmap.num_nodes();
+        let mut query_states = vec![start];
+        query_states.extend(goals.iter().map(|(_, goal)| goal));
+        let start_idx = num_roadmap_nodes;
+        let goal_node_idxs: Vec<usize> = (0..goals.len()).map(|i| num_roadmap_nodes + 1 + i).collect();
+        let mut query_adjacency: Vec<Vec<(usize, f64)>> = vec![vec![]; query_states.len()];
+        let mut roadmap_overlay_adjacency: HashMap<usize, Vec<(usize, f64)>> = HashMap::new();
+        for (i, state) in query_states.iter().enumerate() {
+            let neighbor_idxs = roadmap.k_nearest(state.concatenated_state(), self.k, None);
+            let valid_edges: Result<Vec<Option<(usize, f64)>>, OptimaError> = neighbor_idxs.par_iter().map(|neighbor_idx| {
+                let length = (&roadmap.nodes[*neighbor_idx] - state.concatenated_state()).norm();
+                let neighbor_state = self.spawn_dof_state(&roadmap.nodes[*neighbor_idx])?;
+                return if self.collision_checker.segment_is_valid(state, &neighbor_state)? { Ok(Some((*neighbor_idx, length))) } else { Ok(None) };
+            }).collect();
+            for (neighbor_idx, length) in valid_edges?.into_iter().flatten() {
+                query_adjacency[i].push((neighbor_idx, length));
+                roadmap_overlay_adjacency.entry(neighbor_idx).or_insert_with(Vec::new).push((num_roadmap_nodes + i, length));
+            }
+        }
+
+        let path_idxs = shortest_path_to_any_on_graph(num_roadmap_nodes + query_states.len(), start_idx, &goal_node_idxs, |idx| {
+            if idx >= num_roadmap_nodes { return query_adjacency[idx - num_roadmap_nodes].clone(); }
+            let mut neighbors = roadmap.adjacency[idx].clone();
+            if let Some(overlay) = roadmap_overlay_adjacency.get(&idx) { neighbors.extend(overlay.iter().cloned()); }
+            neighbors
+        });
+        return match path_idxs {
+            None => { Ok(None) }
+            Some(path_idxs) => {
+                let goal_idx = goals[*path_idxs.last().expect("error") - num_roadmap_nodes - 1].0;
+                let mut out_vec = vec![];
+                for idx in path_idxs {
+                    if idx >= num_roadmap_nodes { out_vec.push(query_states[idx - num_roadmap_nodes].clone()); }
+                    else { out_vec.push(self.spawn_dof_state(&roadmap.nodes[idx])?); }
+                }
+                Ok(Some(GoalSetPath::new(out_vec, goal_idx)))
+            }
+        }
+    }
+    /// A uniform, collision free DOF sample, or None if the token was cancelled first.
+    fn sample(&self, cancellation_token: &CancellationToken) -> Result<Option<DVector<f64>>, OptimaError> {
+        let robot_set_joint_state_module = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        for _ in 0..self.max_sample_attempts {
+            if cancellation_token.is_cancelled() { return Ok(None); }
+            let sample = robot_set_joint_state_module.sample_set_joint_state(&RobotSetJointStateType::DOF);
+            if self.collision_checker.state_is_valid(&sample)? { return Ok(Some(sample.concatenated_state().clone())); }
+        }
+
+        Err(OptimaError::new_generic_error_str(&format!("Could not find a collision free PRM sample within {} attempts.", self.max_sample_attempts), file!(), line!()))
+    }
+    fn spawn_dof_state(&self, v: &DVector<f64>) -> Result<RobotSetJointState, OptimaError> {
+        let robot_set_joint_state_module = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        return robot_set_joint_state_module.spawn_robot_set_joint_state(v.clone(), RobotSetJointStateType::DOF);
+    }
+    fn num_dofs(&self) -> usize {
+        return self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module().num_dofs();
+    }
+}
+
+/// A probabilistic roadmap over robot set DOF states, built by a `PRMPlanner`.  Edges are undirected
+/// and weighted by their Euclidean joint space length.  Nodes are indexed by a nearest neighbor
+/// structure (see `PRMPlanner::set_nearest_neighbor_structure_type`); the index is not saved with the
+/// roadmap.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PRMRoadmap {
+    num_dofs: usize,
+    nodes: Vec<DVector<f64>>,
+    adjacency: Vec<Vec<(usize, f64)>>,
+    num_edges: usize,
+    #[serde(skip)]
+    nearest_neighbor_index: Option<NearestNeighborStructureObject<()>>
+}
+impl PRMRoadmap {
+    pub fn new_empty(num_dofs: usize) -> Self {
+        Self {
+            num_dofs,
+            nodes: vec![],
+            adjacency: vec![],
+            num_edges: 0,
+            nearest_neighbor_index: None
+        }
+    }
+    /// Adds all nodes that are not indexed yet to the roadmap's nearest neighbor index (e.g., to index
+    /// a loaded roadmap before querying it).  If the index is not of the given type, all nodes are
+    /// re-indexed with a new structure of that type.
+    pub fn update_nearest_neighbor_index(&mut self, nearest_neighbor_structure_type: &NearestNeighborStructureType) -> Result<(), OptimaError> {
+        update_nearest_neighbor_index(&mut self.nearest_neighbor_index, &self.nodes, nearest_neighbor_structure_type)
+    }
+    /// Returns the node indices of the shortest path (by total edge length) from start to goal, both
+    /// included, or None if they are not connected.
+    pub fn shortest_path(&self, start_idx: usize, goal_idx: usize) -> Result<Option<Vec<usize>>, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(start_idx, self.nodes.len(), file!(), line!())?;
+        OptimaError::new_check_for_idx_out_of_bound_error(goal_idx, self.nodes.len(), file!(), line!())?;
+        return Ok(shortest_path_on_graph(self.nodes.len(), start_idx, goal_idx, |idx| self.adjacency[idx].clone()));
+    }
+    pub fn has_edge(&self, a: usize, b: usize) -> bool {
+        if a >= self.adjacency.len() { return false; }
+        return self.adjacency[a].iter().any(|(idx, _)| *idx == b);
+    }
+    /// The neighbors of the given node along with the lengths of the connecting edges.
+    pub fn neighbors(&self, node_idx: usize) -> Result<&Vec<(usize, f64)>, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(node_idx, self.adjacency.len(), file!(), line!())?;
+        Ok(&self.adjacency[node_idx])
+    }
+    pub fn nodes(&self) -> &Vec<DVector<f64>> {
+        &self.nodes
+    }
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+    pub fn num_edges(&self) -> usize {
+        self.num_edges
+    }
+    pub fn num_dofs(&self) -> usize {
+        self.num_dofs
+    }
+    fn add_node(&mut self, node: DVector<f64>) -> usize {
+        self.nodes.push(node);
+        self.adjacency.push(vec![]);
+        self.nodes.len() - 1
+    }
+    fn add_edge(&mut self, a: usize, b: usize, length: f64) {
+        if a == b || self.has_edge(a, b) { return; }
+        self.adjacency[a].push((b, length));
+        self.adjacency[b].push((a, length));
+        self.num_edges += 1;
+    }
+    fn k_nearest(&self, target: &DVector<f64>, k: usize, excluded_idx: Option<usize>) -> Vec<usize> {
+        return k_nearest_nodes(&self.nodes, self.nearest_neighbor_index.as_ref(), target, k, excluded_idx);
+    }
+}
+
+/// Inserts nodes\[index.len()..] into the given roadmap index, creating the index if needed or if it
+/// is not of the given type.
+pub(crate) fn update_nearest_neighbor_index(nearest_neighbor_index: &mut Option<NearestNeighborStructureObject<()>>, nodes: &[DVector<f64>], nearest_neighbor_structure_type: &NearestNeighborStructureType) -> Result<(), OptimaError> {
+    let new_index = NearestNeighborStructureObject::new(nearest_neighbor_structure_type, JointStateDistanceMetric::Euclidean);
+    if nearest_neighbor_index.as_ref().map_or(true, |index| index.structure_type() != new_index.structure_type()) { *nearest_neighbor_index = Some(new_index); }
+    let index = nearest_neighbor_index.as_mut().expect("error");
+    for node in &nodes[index.len()..] { index.insert(node.clone(), ())?; }
+    Ok(())
+}
+
+/// The (at most) k nodes nearest to the target (in Euclidean distance), nearest first, optionally
+/// excluding one node.  Item i of nearest_neighbor_index must be node i; the index answers the query
+/// if it covers all given nodes, and the nodes are scanned linearly otherwise.
+pub(crate) fn k_nearest_nodes(nodes: &[DVector<f64>], nearest_neighbor_index: Option<&NearestNeighborStructureObject<()>>, target: &DVector<f64>, k: usize, excluded_idx: Option<usize>) -> Vec<usize> {
+    if let Some(index) = nearest_neighbor_index {
+        if index.len() == nodes.len() {
+            let num_queried = if excluded_idx.is_some() { k + 1 } else { k };
+            return index.k_nearest(target, num_queried).iter().map(|res| res.item_idx()).filter(|i| Some(*i) != excluded_idx).take(k).collect();
+        }
+    }
+
+    let mut distances: Vec<(f64, usize)> = nodes.iter().enumerate()
+        .filter(|(i, _)| Some(*i) != excluded_idx)
+        .map(|(i, node)| ((node - target).norm_squared(), i))
+        .collect();
+    distances.sort_by(|a, b| a.0.total_cmp(&b.0));
+    return distances.into_iter().take(k).map(|(_, i)| i).collect();
+}
+
+/// Dijkstra's algorithm over a graph given by a function from a node index to its (neighbor index,
+/// edge length) pairs.  Returns the node indices from start to goal (both included), or None if
+/// they are not connected.
+pub(crate) fn shortest_path_on_graph<F: Fn(usize) -> Vec<(usize, f64)>>(num_nodes: usize, start_idx: usize, goal_idx: usize, neighbors: F) -> Option<Vec<usize>> {
+    return shortest_path_to_any_on_graph(num_nodes, start_idx, &[goal_idx], neighbors);
+}
+
+/// Same as `shortest_path_on_graph`, but returns the shortest path to the nearest of the given goal
+/// nodes (the path's last node).
+pub(crate) fn shortest_path_to_any_on_graph<F: Fn(usize) -> Vec<(usize, f64)>>(num_nodes: usize, start_idx: usize, goal_idxs: &[usize], neighbors: F) -> Option<Vec<usize>> {
+    let mut distances = vec![f64::INFINITY; num_nodes];
+    let mut parents: Vec<Option<usize>> = vec![None; num_nodes];
+    let mut heap = BinaryHeap::new();
+    distances[start_idx] = 0.0;
+    heap.push(Reverse((OrderedFloat(0.0), start_idx)));
+
+    let mut reached_goal_idx = None;
+    while let Some(Reverse((OrderedFloat(distance), idx))) = heap.pop() {
+        if distance > distances[idx] { continue; }
+        if goal_idxs.contains(&idx) { reached_goal_idx = Some(idx); break; }
+        for (neighbor_idx, length) in neighbors(idx) {
+            let new_distance = distance + length;
+            if new_distance < distances[neighbor_idx] {
+                distances[neighbor_idx] = new_distance;
+                parents[neighbor_idx] = Some(idx);
+                heap.push(Reverse((OrderedFloat(new_distance), neighbor_idx)));
+            }
+        }
+    }
+
+    let goal_idx = reached_goal_idx?;
+    let mut out_vec = vec![goal_idx];
+    let mut curr = goal_idx;
+    while let Some(parent_idx) = parents[curr] {
+        out_vec.push(parent_idx);
+        curr = parent_idx;
+    }
+    out_vec.reverse();
+    Some(out_vec)
+}
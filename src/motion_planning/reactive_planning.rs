@@ -0,0 +1,287 @@
+use nalgebra::DVector;
+use rayon::prelude::*;
+use crate::robot_set_modules::robot_set_joint_state_module::{RobotSetJointState, RobotSetJointStateModule, RobotSetJointStateType};
+use crate::scenes::robot_geometric_shape_scene::{EnvObjPoseConstraintGroupInput, RobotGeometricShapeScene, RobotGeometricShapeSceneQuery};
+use crate::utils::utils_cancellation::{CancellableOutput, CancellationToken};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_shape_geometry::geometric_shape::{LogCondition, StopCondition};
+
+/// Reactive local planning by descending a potential field in the DOF joint space of the robot set
+/// of a `RobotGeometricShapeScene`.  The cost of a state q is
+///
+/// attractive_weight * 0.5 * |q - goal|^2 + repulsive_weight * sum_pairs c(d),
+///
+/// where d ranges over the distances of all shape pairs of the scene (robot to environment, robot to
+/// robot and self pairs) and c is the CHOMP obstacle cost (Ratliff et al., 2009):
+/// c(d) = (d - clearance)^2 / (2 * clearance) below clearance and 0 beyond it.  The repulsive
+/// gradient is computed by finite differences (one distance query per DOF, run in parallel).
+///
+/// Each step moves at most max_step (in Euclidean joint space distance) along the negative gradient,
+/// halving the step until the cost decreases.  Since it only looks at the current state, the planner
+/// is fast enough to run every control cycle (e.g., as a fallback while a global planner replans),
+/// but it can get stuck in local minima, which `plan` reports as stalled.
+///
+/// Shape distances are unsigned (0 on contact), so steps that bring any pair into contact are never
+/// taken.
+pub struct ReactiveLocalPlanner<'a> {
+    robot_geometric_shape_scene: &'a RobotGeometricShapeScene,
+    clearance: f64,
+    attractive_weight: f64,
+    repulsive_weight: f64,
+    max_step: f64,
+    finite_difference_step: f64,
+    goal_tolerance: f64,
+    max_backtracking_steps: usize,
+    env_obj_pose_constraint_group_input: Option<EnvObjPoseConstraintGroupInput>
+}
+impl <'a> ReactiveLocalPlanner<'a> {
+    pub fn new(robot_geometric_shape_scene: &'a RobotGeometricShapeScene, clearance: f64, max_step: f64) -> Result<Self, OptimaError> {
+        if !(clearance > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Clearance must be positive (given {}).", clearance), file!(), line!()));
+        }
+        if !(max_step > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Max step must be positive (given {}).", max_step), file!(), line!()));
+        }
+
+        Ok(Self {
+            robot_geometric_shape_scene,
+            clearance,
+            attractive_weight: 1.0,
+            repulsive_weight: 10.0,
+            max_step,
+            finite_difference_step: 1e-4,
+            goal_tolerance: 1e-3,
+            max_backtracking_steps: 5,
+            env_obj_pose_constraint_group_input: None
+        })
+    }
+    pub fn set_weights(&mut self, attractive_weight: f64, repulsive_weight: f64) -> Result<(), OptimaError> {
+        if !(attractive_weight >= 0.0 && repulsive_weight >= 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Weights must be non-negative (given {} and {}).", attractive_weight, repulsive_weight), file!(), line!()));
+        }
+        self.attractive_weight = attractive_weight;
+        self.repulsive_weight = repulsive_weight;
+        Ok(())
+    }
+    pub fn set_finite_difference_step(&mut self, finite_difference_step: f64) {
+        self.finite_difference_step = finite_difference_step;
+    }
+    /// `plan` stops once the state is within goal_tolerance of the goal (in Euclidean joint space
+    /// distance).
+    pub fn set_goal_tolerance(&mut self, goal_tolerance: f64) {
+        self.goal_tolerance = goal_tolerance;
+    }
+    pub fn set_max_backtracking_steps(&mut self, max_backtracking_steps: usize) {
+        self.max_backtracking_steps = max_backtracking_steps;
+    }
+    /// Overrides the pose constraints of environment objects (None reverts to the constraints saved in
+    /// the scene), e.g., to follow tracked obstacles.
+    pub fn set_env_obj_pose_constraint_group_input(&mut self, env_obj_pose_constraint_group_input: Option<EnvObjPoseConstraintGroupInput>) {
+        self.env_obj_pose_constraint_group_input = env_obj_pose_constraint_group_input;
+    }
+    /// Takes at most num_steps steps from start toward goal.  The returned path starts at start and
+    /// ends where the planner stopped, which is the goal only if reached_goal is true.
+    pub fn plan(&self, start: &RobotSetJointState, goal: &RobotSetJointState, num_steps: usize) -> Result<ReactiveLocalPlanOutput, OptimaError> {
+        let res = self.plan_with_cancellation(start, goal, num_steps, &CancellationToken::new())?;
+        return Ok(res.into_output());
+    }
+    /// Same as `plan`, but stops once the given token is cancelled, in which case the output holds the
+    /// steps taken so far.
+    pub fn plan_with_cancellation(&self, start: &RobotSetJointState, goal: &RobotSetJointState, num_steps: usize, cancellation_token: &CancellationToken) -> Result<CancellableOutput<ReactiveLocalPlanOutput>, OptimaError> {
+        let robot_set_joint_state_module = self.robot_set_joint_state_module();
+        let start = robot_set_joint_state_module.convert_state_to_dof_state(start)?;
+        let goal = robot_set_joint_state_module.convert_state_to_dof_state(goal)?;
+
+        let mut path = vec![start.clone()];
+        let mut curr = start;
+        let mut reached_goal = false;
+        let mut stalled = false;
+        for _ in 0..num_steps {
+            if cancellation_token.is_cancelled() {
+                return Ok(CancellableOutput::Cancelled(ReactiveLocalPlanOutput { path, reached_goal, stalled }));
+            }
+            if (goal.concatenated_state() - curr.concatenated_state()).norm() <= self.goal_tolerance { reached_goal = true; break; }
+
+            let step = self.step(&curr, &goal)?;
+            match step.next_state {
+                None => { stalled = true; break; }
+                Some(next_state) => {
+                    path.push(next_state.clone());
+                    curr = next_state;
+                }
+            }
+        }
+        if !reached_goal && !stalled { reached_goal = (goal.concatenated_state() - curr.concatenated_state()).norm() <= self.goal_tolerance; }
+
+        Ok(CancellableOutput::Complete(ReactiveLocalPlanOutput { path, reached_goal, stalled }))
+    }
+    /// Takes a single descent step from the given state, e.g., once per control cycle.
+    pub fn step(&self, robot_set_joint_state: &RobotSetJointState, goal: &RobotSetJointState) -> Result<ReactiveLocalStepOutput, OptimaError> {
+        let robot_set_joint_state_module = self.robot_set_joint_state_module();
+        let q = robot_set_joint_state_module.convert_state_to_dof_state(robot_set_joint_state)?.concatenated_state().clone();
+        let goal = robot_set_joint_state_module.convert_state_to_dof_state(goal)?.concatenated_state().clone();
+
+        let curr_cost = self.cost_vec(&q, &goal)?;
+        let gradient = self.gradient(&q, &goal, &curr_cost)?;
+        let gradient_norm = gradient.norm();
+        if gradient_norm == 0.0 {
+            return Ok(ReactiveLocalStepOutput { next_state: None, cost: curr_cost });
+        }
+
+        // The attractive term alone would stop exactly at the goal with a step of |q - goal|.
+        let mut step_length = self.max_step.min(gradient_norm / self.attractive_weight.max(f64::MIN_POSITIVE));
+        let bounds = robot_set_joint_state_module.get_joint_state_bounds(&RobotSetJointStateType::DOF);
+        for _ in 0..=self.max_backtracking_steps {
+            let mut next = &q - &gradient * (step_length / gradient_norm);
+            for (i, (lower, upper)) in bounds.iter().enumerate() { next[i] = next[i].max(*lower).min(*upper); }
+
+            let next_cost = self.cost_vec(&next, &goal)?;
+            if next_cost.total() < curr_cost.total() && next_cost.minimum_distance > 0.0 {
+                let next_state = robot_set_joint_state_module.spawn_robot_set_joint_state(next, RobotSetJointStateType::DOF)?;
+                return Ok(ReactiveLocalStepOutput { next_state: Some(next_state), cost: next_cost });
+            }
+            step_length *= 0.5;
+        }
+
+        Ok(ReactiveLocalStepOutput { next_state: None, cost: curr_cost })
+    }
+    pub fn cost(&self, robot_set_joint_state: &RobotSetJointState, goal: &RobotSetJointState) -> Result<ReactiveLocalCost, OptimaError> {
+        let robot_set_joint_state_module = self.robot_set_joint_state_module();
+        let q = robot_set_joint_state_module.convert_state_to_dof_state(robot_set_joint_state)?;
+        let goal = robot_set_joint_state_module.convert_state_to_dof_state(goal)?;
+        return self.cost_vec(q.concatenated_state(), goal.concatenated_state());
+    }
+    pub fn clearance(&self) -> f64 {
+        self.clearance
+    }
+    pub fn attractive_weight(&self) -> f64 {
+        self.attractive_weight
+    }
+    pub fn repulsive_weight(&self) -> f64 {
+        self.repulsive_weight
+    }
+    pub fn max_step(&self) -> f64 {
+        self.max_step
+    }
+    pub fn finite_difference_step(&self) -> f64 {
+        self.finite_difference_step
+    }
+    pub fn goal_tolerance(&self) -> f64 {
+        self.goal_tolerance
+    }
+    pub fn max_backtracking_steps(&self) -> usize {
+        self.max_backtracking_steps
+    }
+    fn gradient(&self, q: &DVector<f64>, goal: &DVector<f64>, cost_at_q: &ReactiveLocalCost) -> Result<DVector<f64>, OptimaError> {
+        let h = self.finite_difference_step;
+        let repulsive_partials: Result<Vec<f64>, OptimaError> = (0..q.len()).into_par_iter().map(|i| {
+            let mut q_h = q.clone();
+            q_h[i] += h;
+            return Ok((self.repulsive_cost(&q_h)?.0 - cost_at_q.repulsive) / h);
+        }).collect();
+        let repulsive_gradient = DVector::from_vec(repulsive_partials?);
+
+        return Ok((q - goal) * self.attractive_weight + repulsive_gradient * self.repulsive_weight);
+    }
+    fn cost_vec(&self, q: &DVector<f64>, goal: &DVector<f64>) -> Result<ReactiveLocalCost, OptimaError> {
+        let (repulsive, minimum_distance) = self.repulsive_cost(q)?;
+        Ok(ReactiveLocalCost {
+            attractive: 0.5 * (q - goal).norm_squared(),
+            repulsive,
+            attractive_weight: self.attractive_weight,
+            repulsive_weight: self.repulsive_weight,
+            minimum_distance
+        })
+    }
+    /// The unweighted obstacle cost and the minimum pair distance below clearance (f64::INFINITY if
+    /// there is none).
+    fn repulsive_cost(&self, q: &DVector<f64>) -> Result<(f64, f64), OptimaError> {
+        let robot_set_joint_state = self.robot_set_joint_state_module().spawn_robot_set_joint_state(q.clone(), RobotSetJointStateType::DOF)?;
+        let input = RobotGeometricShapeSceneQuery::Distance {
+            robot_set_joint_state: &robot_set_joint_state,
+            env_obj_pose_constraint_group_input: self.env_obj_pose_constraint_group_input.as_ref(),
+            inclusion_list: &None
+        };
+        let res = self.robot_geometric_shape_scene.shape_collection_query(&input, StopCondition::None, LogCondition::BelowMinDistance(self.clearance), false)?;
+
+        let mut cost = 0.0;
+        let mut minimum_distance = f64::INFINITY;
+        for output in res.outputs() {
+            let distance = output.raw_output().unwrap_distance()?;
+            if distance >= self.clearance { continue; }
+            cost += (distance - self.clearance).powi(2) / (2.0 * self.clearance);
+            minimum_distance = minimum_distance.min(distance);
+        }
+
+        Ok((cost, minimum_distance))
+    }
+    fn robot_set_joint_state_module(&self) -> &RobotSetJointStateModule {
+        return self.robot_geometric_shape_scene.robot_set().robot_set_joint_state_module();
+    }
+}
+
+/// The cost of a state under a `ReactiveLocalPlanner`'s potential field.
+#[derive(Clone, Debug)]
+pub struct ReactiveLocalCost {
+    attractive: f64,
+    repulsive: f64,
+    attractive_weight: f64,
+    repulsive_weight: f64,
+    minimum_distance: f64
+}
+impl ReactiveLocalCost {
+    /// 0.5 * |q - goal|^2 (unweighted).
+    pub fn attractive(&self) -> f64 {
+        self.attractive
+    }
+    /// The sum of the CHOMP obstacle costs of all shape pairs (unweighted).
+    pub fn repulsive(&self) -> f64 {
+        self.repulsive
+    }
+    pub fn total(&self) -> f64 {
+        return self.attractive_weight * self.attractive + self.repulsive_weight * self.repulsive;
+    }
+    /// The minimum distance over all shape pairs closer than clearance (f64::INFINITY if there is
+    /// none).
+    pub fn minimum_distance(&self) -> f64 {
+        self.minimum_distance
+    }
+}
+
+/// The output of `ReactiveLocalPlanner::step`.
+#[derive(Clone, Debug)]
+pub struct ReactiveLocalStepOutput {
+    next_state: Option<RobotSetJointState>,
+    cost: ReactiveLocalCost
+}
+impl ReactiveLocalStepOutput {
+    /// The next state, or None if no step lowers the cost (a local minimum, or the goal itself).
+    pub fn next_state(&self) -> &Option<RobotSetJointState> {
+        &self.next_state
+    }
+    /// The cost at the next state (or at the current state if no step was taken).
+    pub fn cost(&self) -> &ReactiveLocalCost {
+        &self.cost
+    }
+}
+
+/// The output of `ReactiveLocalPlanner::plan`.
+#[derive(Clone, Debug)]
+pub struct ReactiveLocalPlanOutput {
+    path: Vec<RobotSetJointState>,
+    reached_goal: bool,
+    stalled: bool
+}
+impl ReactiveLocalPlanOutput {
+    pub fn path(&self) -> &Vec<RobotSetJointState> {
+        &self.path
+    }
+    pub fn reached_goal(&self) -> bool {
+        self.reached_goal
+    }
+    /// True if the planner stopped in a local minimum away from the goal, in which case a global
+    /// planner is needed.
+    pub fn stalled(&self) -> bool {
+        self.stalled
+    }
+}
@@ -0,0 +1,220 @@
+use nalgebra::{DMatrix, DVector, Isometry3, Vector6};
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_kinematics_module::{JacobianEndPoint, JacobianMode};
+use crate::robot_set_modules::robot_set::RobotSet;
+use crate::robot_set_modules::robot_set_joint_state_module::{RobotSetJointState, RobotSetJointStateType};
+use crate::robot_set_modules::robot_set_kinematics_module::RobotSetFKResult;
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_robot::robot_ids::LinkId;
+use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3Pose, OptimaSE3PoseType};
+use crate::utils::utils_se3::pose_error_metric::PoseErrorMetric;
+
+/// Keeps two links of a `RobotSet` (usually the end effectors of two arms) at a fixed relative
+/// transform, as needed for coordinated motions such as carrying an object with both hands.  The
+/// constraint holds when the pose of link b equals pose_a * relative_pose, where pose_a is the pose
+/// of link a.
+///
+/// The constraint manifold has measure zero, so states cannot be found by rejection.  Instead,
+/// states are projected onto it with damped least squares: the error between link b's pose and its
+/// target is driven to zero along the Jacobian J_b - J_a, where J_a is the Jacobian of link a
+/// evaluated at link b's target point (so moving either arm reduces the error).  `sample` projects
+/// uniform samples and `interpolate` projects the intermediate states of a segment, which gives
+/// sampling-based planners the two operations they need to plan on the manifold.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RelativePoseConstraint {
+    robot_idx_a: usize,
+    link_id_a: LinkId,
+    robot_idx_b: usize,
+    link_id_b: LinkId,
+    relative_pose: Isometry3<f64>,
+    max_projection_iterations: usize,
+    position_tolerance: f64,
+    orientation_tolerance: f64,
+    damping: f64
+}
+impl RelativePoseConstraint {
+    /// relative_pose is the pose of link b expressed in the frame of link a.
+    pub fn new(robot_set: &RobotSet, robot_idx_a: usize, link_id_a: LinkId, robot_idx_b: usize, link_id_b: LinkId, relative_pose: &OptimaSE3Pose) -> Result<Self, OptimaError> {
+        let robot_configuration_modules = robot_set.robot_set_configuration_module().robot_configuration_modules();
+        for (robot_idx, link_id) in [(robot_idx_a, link_id_a), (robot_idx_b, link_id_b)] {
+            OptimaError::new_check_for_idx_out_of_bound_error(robot_idx, robot_configuration_modules.len(), file!(), line!())?;
+            let links = robot_configuration_modules[robot_idx].robot_model_module().links();
+            let link_idx = link_id.idx();
+            OptimaError::new_check_for_idx_out_of_bound_error(link_idx, links.len(), file!(), line!())?;
+            if !links[link_idx].present() {
+                return Err(OptimaError::new_generic_error_str(&format!("Link {} of robot {} is not present in the robot configuration.", link_idx, robot_idx), file!(), line!()));
+            }
+        }
+        if robot_idx_a == robot_idx_b && link_id_a == link_id_b {
+            return Err(OptimaError::new_generic_error_str("A relative pose constraint must be between two different links.", file!(), line!()));
+        }
+
+        Ok(Self {
+            robot_idx_a,
+            link_id_a,
+            robot_idx_b,
+            link_id_b,
+            relative_pose: relative_pose.to_nalgebra_isometry(),
+            max_projection_iterations: 100,
+            position_tolerance: 0.001,
+            orientation_tolerance: 0.001,
+            damping: 0.05
+        })
+    }
+    /// Uses the relative transform between the two links at the given state (e.g., the state at
+    /// which both arms grasp the object).
+    pub fn new_from_robot_set_joint_state(robot_set: &RobotSet, robot_idx_a: usize, link_id_a: LinkId, robot_idx_b: usize, link_id_b: LinkId, robot_set_joint_state: &RobotSetJointState) -> Result<Self, OptimaError> {
+        let fk_res = robot_set.robot_set_kinematics_module().compute_fk(robot_set_joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+        let pose_a = Self::link_isometry(&fk_res, robot_idx_a, link_id_a)?;
+        let pose_b = Self::link_isometry(&fk_res, robot_idx_b, link_id_b)?;
+        let relative_pose = pose_a.inverse() * pose_b;
+        return Self::new(robot_set, robot_idx_a, link_id_a, robot_idx_b, link_id_b, &OptimaSE3Pose::new_unit_quaternion_and_translation(relative_pose.rotation, relative_pose.translation.vector));
+    }
+    pub fn set_max_projection_iterations(&mut self, max_projection_iterations: usize) {
+        self.max_projection_iterations = max_projection_iterations;
+    }
+    pub fn set_tolerances(&mut self, position_tolerance: f64, orientation_tolerance: f64) -> Result<(), OptimaError> {
+        if !(position_tolerance > 0.0 && orientation_tolerance > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Tolerances must be positive (given {}, {}).", position_tolerance, orientation_tolerance), file!(), line!()));
+        }
+        self.position_tolerance = position_tolerance;
+        self.orientation_tolerance = orientation_tolerance;
+        Ok(())
+    }
+    pub fn set_damping(&mut self, damping: f64) {
+        self.damping = damping;
+    }
+    /// Returns the position error (in meters) and orientation error (in radians) of link b relative to
+    /// its target.
+    pub fn error(&self, robot_set: &RobotSet, robot_set_joint_state: &RobotSetJointState) -> Result<(f64, f64), OptimaError> {
+        let fk_res = robot_set.robot_set_kinematics_module().compute_fk(robot_set_joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+        let error = self.error_vector(&fk_res)?;
+        return Ok((error.fixed_rows::<3>(0).norm(), error.fixed_rows::<3>(3).norm()));
+    }
+    pub fn is_satisfied(&self, robot_set: &RobotSet, robot_set_joint_state: &RobotSetJointState) -> Result<bool, OptimaError> {
+        let (position_error, orientation_error) = self.error(robot_set, robot_set_joint_state)?;
+        return Ok(position_error <= self.position_tolerance && orientation_error <= self.orientation_tolerance);
+    }
+    /// Projects the state onto the constraint manifold, clamping to the joint bounds after every
+    /// step.  Returns a DOF state, or None if the projection does not converge within
+    /// max_projection_iterations steps.
+    pub fn project(&self, robot_set: &RobotSet, robot_set_joint_state: &RobotSetJointState) -> Result<Option<RobotSetJointState>, OptimaError> {
+        let robot_set_joint_state_module = robot_set.robot_set_joint_state_module();
+        let robot_set_kinematics_module = robot_set.robot_set_kinematics_module();
+        let bounds = robot_set_joint_state_module.get_joint_state_bounds(&RobotSetJointStateType::DOF);
+
+        let mut joint_state = robot_set_joint_state_module.convert_state_to_dof_state(robot_set_joint_state)?;
+        let mut iterations = 0;
+        loop {
+            let fk_res = robot_set_kinematics_module.compute_fk(&joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+            let error = self.error_vector(&fk_res)?;
+            if error.fixed_rows::<3>(0).norm() <= self.position_tolerance && error.fixed_rows::<3>(3).norm() <= self.orientation_tolerance {
+                return Ok(Some(joint_state));
+            }
+            if iterations >= self.max_projection_iterations { return Ok(None); }
+
+            let target_point = (Self::link_isometry(&fk_res, self.robot_idx_a, self.link_id_a)? * self.relative_pose).translation.vector;
+            let jacobian_a = robot_set_kinematics_module.compute_jacobian(&joint_state, self.robot_idx_a, None, self.link_id_a, &JacobianEndPoint::Global(target_point), None, JacobianMode::Full)?;
+            let jacobian_b = robot_set_kinematics_module.compute_jacobian(&joint_state, self.robot_idx_b, None, self.link_id_b, &JacobianEndPoint::Link, None, JacobianMode::Full)?;
+            let jacobian = jacobian_b - jacobian_a;
+
+            let jacobian_t = jacobian.transpose();
+            let jjt = &jacobian * &jacobian_t + DMatrix::identity(6, 6) * (self.damping * self.damping);
+            let solved = match jjt.lu().solve(&DVector::from_column_slice(error.as_slice())) {
+                None => { return Err(OptimaError::new_generic_error_str("Could not solve damped least squares system (singular matrix).  Try increasing the damping.", file!(), line!())); }
+                Some(solved) => { solved }
+            };
+            let step = jacobian_t * solved;
+
+            let mut new_state = joint_state.concatenated_state() + step;
+            for (i, (lower, upper)) in bounds.iter().enumerate() {
+                new_state[i] = new_state[i].max(*lower).min(*upper);
+            }
+            joint_state = robot_set_joint_state_module.spawn_robot_set_joint_state(new_state, RobotSetJointStateType::DOF)?;
+            iterations += 1;
+        }
+    }
+    /// Returns a DOF state that satisfies the constraint by projecting uniform samples, trying up to
+    /// max_attempts samples.
+    pub fn sample(&self, robot_set: &RobotSet, max_attempts: usize) -> Result<RobotSetJointState, OptimaError> {
+        for _ in 0..max_attempts {
+            let sample = robot_set.robot_set_joint_state_module().sample_set_joint_state(&RobotSetJointStateType::DOF);
+            if let Some(projected) = self.project(robot_set, &sample)? { return Ok(projected); }
+        }
+
+        Err(OptimaError::new_generic_error_str(&format!("Could not find a state satisfying the relative pose constraint within {} attempts.", max_attempts), file!(), line!()))
+    }
+    /// Interpolates from a to b (both of which should satisfy the constraint) such that consecutive
+    /// states are at most resolution apart before projection, projecting every intermediate state
+    /// onto the constraint manifold.  Returns the states from a to b (inclusive), or None if a
+    /// projection fails or jumps more than twice the resolution from the previous state, which means
+    /// the segment cannot be followed on the manifold.  Planners should check the returned states
+    /// for collisions rather than the straight segment.
+    pub fn interpolate(&self, robot_set: &RobotSet, a: &RobotSetJointState, b: &RobotSetJointState, resolution: f64) -> Result<Option<Vec<RobotSetJointState>>, OptimaError> {
+        if resolution <= 0.0 {
+            return Err(OptimaError::new_generic_error_str(&format!("Interpolation resolution must be positive (given {}).", resolution), file!(), line!()));
+        }
+        let robot_set_joint_state_module = robot_set.robot_set_joint_state_module();
+        let a = robot_set_joint_state_module.convert_state_to_dof_state(a)?;
+        let b = robot_set_joint_state_module.convert_state_to_dof_state(b)?;
+
+        let num_steps = ((b.concatenated_state() - a.concatenated_state()).norm() / resolution).ceil().max(1.0) as usize;
+
+        let mut out_vec = vec![a.clone()];
+        for i in 1..num_steps {
+            let t = i as f64 / num_steps as f64;
+            let state = robot_set_joint_state_module.interpolate_robot_set_joint_states(&a, &b, t)?;
+            let projected = match self.project(robot_set, &state)? {
+                None => { return Ok(None); }
+                Some(projected) => { projected }
+            };
+            let prev = out_vec.last().unwrap();
+            if (projected.concatenated_state() - prev.concatenated_state()).norm() > 2.0 * resolution { return Ok(None); }
+            out_vec.push(projected);
+        }
+        out_vec.push(b);
+
+        Ok(Some(out_vec))
+    }
+    pub fn robot_idx_a(&self) -> usize {
+        self.robot_idx_a
+    }
+    pub fn link_id_a(&self) -> LinkId {
+        self.link_id_a
+    }
+    pub fn robot_idx_b(&self) -> usize {
+        self.robot_idx_b
+    }
+    pub fn link_id_b(&self) -> LinkId {
+        self.link_id_b
+    }
+    pub fn relative_pose(&self) -> OptimaSE3Pose {
+        return OptimaSE3Pose::new_unit_quaternion_and_translation(self.relative_pose.rotation, self.relative_pose.translation.vector);
+    }
+    pub fn max_projection_iterations(&self) -> usize {
+        self.max_projection_iterations
+    }
+    pub fn position_tolerance(&self) -> f64 {
+        self.position_tolerance
+    }
+    pub fn orientation_tolerance(&self) -> f64 {
+        self.orientation_tolerance
+    }
+    pub fn damping(&self) -> f64 {
+        self.damping
+    }
+    fn error_vector(&self, fk_res: &RobotSetFKResult) -> Result<Vector6<f64>, OptimaError> {
+        let pose_a = Self::link_isometry(fk_res, self.robot_idx_a, self.link_id_a)?;
+        let pose_b = Self::link_isometry(fk_res, self.robot_idx_b, self.link_id_b)?;
+        return Ok(PoseErrorMetric::default().error_vector_from_isometries(&pose_b, &(pose_a * self.relative_pose)));
+    }
+    fn link_isometry(fk_res: &RobotSetFKResult, robot_idx: usize, link_id: LinkId) -> Result<Isometry3<f64>, OptimaError> {
+        let robot_fk_result = fk_res.robot_fk_result(robot_idx)?;
+        let link_idx = link_id.idx();
+        OptimaError::new_check_for_idx_out_of_bound_error(link_idx, robot_fk_result.link_entries().len(), file!(), line!())?;
+        return match robot_fk_result.link_entries()[link_idx].pose() {
+            None => { Err(OptimaError::new_generic_error_str(&format!("Link {} of robot {} is not present in the robot configuration.", link_idx, robot_idx), file!(), line!())) }
+            Some(pose) => { Ok(pose.to_nalgebra_isometry()) }
+        };
+    }
+}
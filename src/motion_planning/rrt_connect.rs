@@ -0,0 +1,378 @@
+use crate::motion_planning::collision_checking::RobotSetSceneCollisionChecker;
+use crate::motion_planning::goal_set::{GoalSetPath, JointStateGoalSet};
+use crate::motion_planning::state_space::{JointSpace, StateSpace};
+use crate::robot_set_modules::robot_set_joint_state_module::{RobotSetJointState, RobotSetJointStateType};
+use crate::utils::utils_cancellation::{CancellableOutput, CancellationToken};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_nearest_neighbor::{NearestNeighborStructure, NearestNeighborStructureObject, NearestNeighborStructureType};
+
+/// Bidirectional RRT (RRT-Connect; Kuffner and LaValle, 2000) between two robot set DOF states in the
+/// scene of a `RobotSetSceneCollisionChecker`.  One tree grows from the start and one from the goal;
+/// each iteration extends one tree by at most step_size toward a uniform sample and then greedily
+/// connects the other tree to the new node, swapping the trees' roles every iteration.  The straight
+/// segment from start to goal is tried first.
+///
+/// By default all robots of the set move.  With `set_moving_robot_idxs`, only the given robots are
+/// sampled and the others stay at their start states (e.g., to plan for one arm of a set).
+/// Returned paths are not smoothed; see `JointSpacePathSimplifier`.  For other state spaces, see
+/// `StateSpaceRRTConnectPlanner`.
+pub struct RRTConnectPlanner<'a> {
+    collision_checker: &'a RobotSetSceneCollisionChecker<'a>,
+    step_size: f64,
+    max_iterations: usize,
+    moving_robot_idxs: Option<Vec<usize>>,
+    nearest_neighbor_structure_type: NearestNeighborStructureType
+}
+impl <'a> RRTConnectPlanner<'a> {
+    pub fn new(collision_checker: &'a RobotSetSceneCollisionChecker<'a>, step_size: f64) -> Result<Self, OptimaError> {
+        if !(step_size > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Step size must be positive (given {}).", step_size), file!(), line!()));
+        }
+
+        Ok(Self {
+            collision_checker,
+            step_size,
+            max_iterations: 5000,
+            moving_robot_idxs: None,
+            nearest_neighbor_structure_type: NearestNeighborStructureType::KDTree
+        })
+    }
+    pub fn set_max_iterations(&mut self, max_iterations: usize) {
+        self.max_iterations = max_iterations;
+    }
+    pub fn set_moving_robot_idxs(&mut self, moving_robot_idxs: Option<Vec<usize>>) -> Result<(), OptimaError> {
+        if let Some(moving_robot_idxs) = &moving_robot_idxs {
+            let num_robots = self.robot_set_joint_state_module_num_robots();
+            for robot_idx_in_set in moving_robot_idxs {
+                OptimaError::new_check_for_idx_out_of_bound_error(*robot_idx_in_set, num_robots, file!(), line!())?;
+            }
+        }
+        self.moving_robot_idxs = moving_robot_idxs;
+        Ok(())
+    }
+    /// The structure that indexes tree nodes for nearest neighbor queries (KD-tree by default; GNAT
+    /// scales better to robot sets with many DOFs).
+    pub fn set_nearest_neighbor_structure_type(&mut self, nearest_neighbor_structure_type: NearestNeighborStructureType) {
+        self.nearest_neighbor_structure_type = nearest_neighbor_structure_type;
+    }
+    /// Returns a collision free path of DOF states from start to goal (both included), or None if no
+    /// path is found within max_iterations iterations.  Returns an error if start or goal is in
+    /// collision.
+    pub fn plan(&self, start: &RobotSetJointState, goal: &RobotSetJointState) -> Result<Option<Vec<RobotSetJointState>>, OptimaError> {
+        let res = self.plan_with_cancellation(start, goal, &CancellationToken::new())?;
+        return Ok(res.into_output());
+    }
+    /// Same as `plan`, but stops once the given token is cancelled, in which case the output is None.
+    pub fn plan_with_cancellation(&self, start: &RobotSetJointState, goal: &RobotSetJointState, cancellation_token: &CancellationToken) -> Result<CancellableOutput<Option<Vec<RobotSetJointState>>>, OptimaError> {
+        let robot_set_joint_state_module = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        let start = robot_set_joint_state_module.convert_state_to_dof_state(start)?;
+        let goal = robot_set_joint_state_module.convert_state_to_dof_state(goal)?;
+
+        let mut joint_space = JointSpace::new(self.collision_checker);
+        let fixed_dofs = self.moving_dofs().iter().enumerate().filter(|(_, moving)| !**moving).map(|(i, _)| (i, start.concatenated_state()[i])).collect();
+        joint_space.set_fixed_dofs(fixed_dofs)?;
+        let mut planner = StateSpaceRRTConnectPlanner::new(&joint_space, self.step_size)?;
+        planner.set_max_iterations(self.max_iterations);
+        planner.set_nearest_neighbor_structure_type(self.nearest_neighbor_structure_type.clone());
+
+        let res = planner.plan_with_cancellation(start.concatenated_state(), goal.concatenated_state(), cancellation_token)?;
+        let was_cancelled = res.was_cancelled();
+        let out = match res.into_output() {
+            None => { None }
+            Some(path_vecs) => {
+                let mut out_vec = vec![];
+                for v in path_vecs { out_vec.push(robot_set_joint_state_module.spawn_robot_set_joint_state(v, RobotSetJointStateType::DOF)?); }
+                Some(out_vec)
+            }
+        };
+        return if was_cancelled { Ok(CancellableOutput::Cancelled(out)) } else { Ok(CancellableOutput::Complete(out)) };
+    }
+    /// Returns a collision free path of DOF states from start to any goal of the goal set (whose goals
+    /// are concatenated DOF vectors of the robot set), or None if no path is found within
+    /// max_iterations iterations.  Planning stops as soon as any goal is reached, and the output
+    /// reports which one (see `GoalSetPath`).  Goals in collision are skipped.  Returns an error if
+    /// start is in collision or if no goal is collision free.
+    pub fn plan_to_goal_set(&self, start: &RobotSetJointState, goal_set: &JointStateGoalSet) -> Result<Option<GoalSetPath<RobotSetJointState>>, OptimaError> {
+        let res = self.plan_to_goal_set_with_cancellation(start, goal_set, &CancellationToken::new())?;
+        return Ok(res.into_output());
+    }
+    /// Same as `plan_to_goal_set`, but stops once the given token is cancelled, in which case the
+    /// output is None.
+    pub fn plan_to_goal_set_with_cancellation(&self, start: &RobotSetJointState, goal_set: &JointStateGoalSet, cancellation_token: &CancellationToken) -> Result<CancellableOutput<Option<GoalSetPath<RobotSetJointState>>>, OptimaError> {
+        let robot_set_joint_state_module = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        goal_set.check_dimension("RRTConnectPlanner::plan_to_goal_set", robot_set_joint_state_module.num_dofs())?;
+        let start = robot_set_joint_state_module.convert_state_to_dof_state(start)?;
+        if !self.collision_checker.state_is_valid(&start)? {
+            return Err(OptimaError::new_generic_error_str("Start state of RRT-Connect is in collision.", file!(), line!()));
+        }
+        if let Some(m) = goal_set.reached_goal(start.concatenated_state()) {
+            return Ok(CancellableOutput::Complete(Some(GoalSetPath::new(vec![start], m.goal_idx()))));
+        }
+
+        let mut joint_space = JointSpace::new(self.collision_checker);
+        let fixed_dofs = self.moving_dofs().iter().enumerate().filter(|(_, moving)| !**moving).map(|(i, _)| (i, start.concatenated_state()[i])).collect();
+        joint_space.set_fixed_dofs(fixed_dofs)?;
+        let mut planner = StateSpaceRRTConnectPlanner::new(&joint_space, self.step_size)?;
+        planner.set_max_iterations(self.max_iterations);
+        planner.set_nearest_neighbor_structure_type(self.nearest_neighbor_structure_type.clone());
+
+        let res = planner.plan_to_any_goal_with_cancellation(start.concatenated_state(), goal_set.goals(), cancellation_token)?;
+        let was_cancelled = res.was_cancelled();
+        let out = match res.into_output() {
+            None => { None }
+            Some(goal_set_path) => {
+                let goal_idx = goal_set_path.goal_idx();
+                let mut out_vec = vec![];
+                for v in goal_set_path.into_path() { out_vec.push(robot_set_joint_state_module.spawn_robot_set_joint_state(v, RobotSetJointStateType::DOF)?); }
+                Some(GoalSetPath::new(out_vec, goal_idx))
+            }
+        };
+        return if was_cancelled { Ok(CancellableOutput::Cancelled(out)) } else { Ok(CancellableOutput::Complete(out)) };
+    }
+    pub fn step_size(&self) -> f64 {
+        self.step_size
+    }
+    pub fn max_iterations(&self) -> usize {
+        self.max_iterations
+    }
+    pub fn moving_robot_idxs(&self) -> &Option<Vec<usize>> {
+        &self.moving_robot_idxs
+    }
+    pub fn nearest_neighbor_structure_type(&self) -> &NearestNeighborStructureType {
+        &self.nearest_neighbor_structure_type
+    }
+    fn moving_dofs(&self) -> Vec<bool> {
+        let robot_joint_state_modules = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module().robot_joint_state_modules();
+        let mut out_vec = vec![];
+        for (robot_idx_in_set, robot_joint_state_module) in robot_joint_state_modules.iter().enumerate() {
+            let moving = match &self.moving_robot_idxs {
+                None => { true }
+                Some(moving_robot_idxs) => { moving_robot_idxs.contains(&robot_idx_in_set) }
+            };
+            for _ in 0..robot_joint_state_module.num_dofs() { out_vec.push(moving); }
+        }
+        out_vec
+    }
+    fn robot_set_joint_state_module_num_robots(&self) -> usize {
+        return self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module().robot_joint_state_modules().len();
+    }
+}
+
+/// RRT-Connect (see `RRTConnectPlanner`) in any `StateSpace`, e.g., to plan base motions in an
+/// `SE2Space` with the same planner used for joint space paths.  Trees are extended by interpolating
+/// from the nearest node toward the sample by at most step_size (in the state space's distance).
+pub struct StateSpaceRRTConnectPlanner<'a, S: StateSpace> {
+    state_space: &'a S,
+    step_size: f64,
+    max_iterations: usize,
+    nearest_neighbor_structure_type: NearestNeighborStructureType
+}
+impl <'a, S: StateSpace> StateSpaceRRTConnectPlanner<'a, S> {
+    pub fn new(state_space: &'a S, step_size: f64) -> Result<Self, OptimaError> {
+        if !(step_size > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Step size must be positive (given {}).", step_size), file!(), line!()));
+        }
+
+        Ok(Self {
+            state_space,
+            step_size,
+            max_iterations: 5000,
+            nearest_neighbor_structure_type: NearestNeighborStructureType::KDTree
+        })
+    }
+    pub fn set_max_iterations(&mut self, max_iterations: usize) {
+        self.max_iterations = max_iterations;
+    }
+    /// The structure that indexes tree nodes for nearest neighbor queries.  Only used if the state
+    /// space provides nearest neighbor coordinates (see `StateSpace::nearest_neighbor_coordinates`).
+    pub fn set_nearest_neighbor_structure_type(&mut self, nearest_neighbor_structure_type: NearestNeighborStructureType) {
+        self.nearest_neighbor_structure_type = nearest_neighbor_structure_type;
+    }
+    /// Returns a valid path from start to goal (both included), or None if no path is found within
+    /// max_iterations iterations.  Returns an error if start or goal is invalid.
+    pub fn plan(&self, start: &S::State, goal: &S::State) -> Result<Option<Vec<S::State>>, OptimaError> {
+        let res = self.plan_with_cancellation(start, goal, &CancellationToken::new())?;
+        return Ok(res.into_output());
+    }
+    /// Same as `plan`, but stops once the given token is cancelled, in which case the output is None.
+    pub fn plan_with_cancellation(&self, start: &S::State, goal: &S::State, cancellation_token: &CancellationToken) -> Result<CancellableOutput<Option<Vec<S::State>>>, OptimaError> {
+        if !self.state_space.state_is_valid(start)? {
+            return Err(OptimaError::new_generic_error_str("Start state of RRT-Connect is in collision.", file!(), line!()));
+        }
+        if !self.state_space.state_is_valid(goal)? {
+            return Err(OptimaError::new_generic_error_str("Goal state of RRT-Connect is in collision.", file!(), line!()));
+        }
+        let res = self.plan_to_any_goal_with_cancellation(start, &[goal.clone()], cancellation_token)?;
+        let was_cancelled = res.was_cancelled();
+        let out = res.into_output().map(|goal_set_path| goal_set_path.into_path());
+        return if was_cancelled { Ok(CancellableOutput::Cancelled(out)) } else { Ok(CancellableOutput::Complete(out)) };
+    }
+    /// Returns a valid path from start to any of the given goals, or None if no path is found within
+    /// max_iterations iterations.  The goal tree is rooted at all valid goals at once, so planning
+    /// stops as soon as the start tree connects to any of them, and the output reports which one (as
+    /// an index into goals; see `GoalSetPath`).  Invalid goals are skipped.  Returns an error if start
+    /// is invalid or if no goal is valid.
+    pub fn plan_to_any_goal(&self, start: &S::State, goals: &[S::State]) -> Result<Option<GoalSetPath<S::State>>, OptimaError> {
+        let res = self.plan_to_any_goal_with_cancellation(start, goals, &CancellationToken::new())?;
+        return Ok(res.into_output());
+    }
+    /// Same as `plan_to_any_goal`, but stops once the given token is cancelled, in which case the
+    /// output is None.
+    pub fn plan_to_any_goal_with_cancellation(&self, start: &S::State, goals: &[S::State], cancellation_token: &CancellationToken) -> Result<CancellableOutput<Option<GoalSetPath<S::State>>>, OptimaError> {
+        if !self.state_space.state_is_valid(start)? {
+            return Err(OptimaError::new_generic_error_str("Start state of RRT-Connect is in collision.", file!(), line!()));
+        }
+        let mut valid_goal_idxs = vec![];
+        for (goal_idx, goal) in goals.iter().enumerate() {
+            if self.state_space.state_is_valid(goal)? { valid_goal_idxs.push(goal_idx); }
+        }
+        if valid_goal_idxs.is_empty() {
+            return Err(OptimaError::new_generic_error_str(&format!("None of the {} goal states of RRT-Connect is valid.", goals.len()), file!(), line!()));
+        }
+        for goal_idx in &valid_goal_idxs {
+            if self.state_space.segment_is_valid(start, &goals[*goal_idx])? {
+                return Ok(CancellableOutput::Complete(Some(GoalSetPath::new(vec![start.clone(), goals[*goal_idx].clone()], *goal_idx))));
+            }
+        }
+
+        let mut start_tree = RRTConnectTree::new(start.clone(), self.state_space, &self.nearest_neighbor_structure_type)?;
+        // Root i of the goal tree is goal valid_goal_idxs[i].
+        let mut goal_tree = RRTConnectTree::new(goals[valid_goal_idxs[0]].clone(), self.state_space, &self.nearest_neighbor_structure_type)?;
+        for goal_idx in &valid_goal_idxs[1..] { goal_tree.add_root(goals[*goal_idx].clone(), self.state_space)?; }
+        let mut extending_start_tree = true;
+        for _ in 0..self.max_iterations {
+            if cancellation_token.is_cancelled() { return Ok(CancellableOutput::Cancelled(None)); }
+
+            let sample = self.state_space.sample();
+            let (tree_a, tree_b) = if extending_start_tree { (&mut start_tree, &mut goal_tree) } else { (&mut goal_tree, &mut start_tree) };
+            if let RRTConnectExtendResult::Advanced(new_idx) | RRTConnectExtendResult::Reached(new_idx) = self.extend(tree_a, &sample)? {
+                let new_node = tree_a.nodes[new_idx].clone();
+                if let RRTConnectExtendResult::Reached(connection_idx) = self.connect(tree_b, &new_node)? {
+                    let (start_idx, goal_tree_idx) = if extending_start_tree { (new_idx, connection_idx) } else { (connection_idx, new_idx) };
+                    let mut out_vec = start_tree.path_to_root(start_idx);
+                    out_vec.reverse();
+                    // The connection node is in both trees, so it is skipped in the goal tree's half.
+                    out_vec.extend(goal_tree.path_to_root(goal_tree_idx).into_iter().skip(1));
+                    let goal_idx = valid_goal_idxs[goal_tree.root_idx(goal_tree_idx)];
+                    return Ok(CancellableOutput::Complete(Some(GoalSetPath::new(out_vec, goal_idx))));
+                }
+            }
+            extending_start_tree = !extending_start_tree;
+        }
+
+        Ok(CancellableOutput::Complete(None))
+    }
+    pub fn state_space(&self) -> &'a S {
+        self.state_space
+    }
+    pub fn step_size(&self) -> f64 {
+        self.step_size
+    }
+    pub fn max_iterations(&self) -> usize {
+        self.max_iterations
+    }
+    pub fn nearest_neighbor_structure_type(&self) -> &NearestNeighborStructureType {
+        &self.nearest_neighbor_structure_type
+    }
+    /// Moves the nearest node of the tree at most step_size toward the target.
+    fn extend(&self, tree: &mut RRTConnectTree<S::State>, target: &S::State) -> Result<RRTConnectExtendResult, OptimaError> {
+        let nearest_idx = tree.nearest(target, self.state_space);
+        let nearest = &tree.nodes[nearest_idx];
+        let distance = self.state_space.distance(nearest, target);
+        let (new_node, reached) = if distance <= self.step_size { (target.clone(), true) } else { (self.state_space.interpolate(nearest, target, self.step_size / distance), false) };
+
+        if !self.state_space.state_is_valid(&new_node)? || !self.state_space.segment_is_valid(nearest, &new_node)? {
+            return Ok(RRTConnectExtendResult::Trapped);
+        }
+
+        let new_idx = tree.add(new_node, nearest_idx, self.state_space)?;
+        return if reached { Ok(RRTConnectExtendResult::Reached(new_idx)) } else { Ok(RRTConnectExtendResult::Advanced(new_idx)) };
+    }
+    /// Extends the tree toward the target until it reaches it or is trapped.
+    fn connect(&self, tree: &mut RRTConnectTree<S::State>, target: &S::State) -> Result<RRTConnectExtendResult, OptimaError> {
+        loop {
+            let res = self.extend(tree, target)?;
+            if let RRTConnectExtendResult::Advanced(_) = res { continue; }
+            return Ok(res);
+        }
+    }
+}
+
+enum RRTConnectExtendResult {
+    Reached(usize),
+    Advanced(usize),
+    Trapped
+}
+
+/// Item i of nearest_neighbor_index is node i.  The index is None if the state space does not
+/// provide nearest neighbor coordinates, in which case nearest nodes are found by a linear scan.  A
+/// tree can have several roots (see `add_root`), which are its first nodes.
+struct RRTConnectTree<T: Clone> {
+    nodes: Vec<T>,
+    parents: Vec<Option<usize>>,
+    nearest_neighbor_index: Option<NearestNeighborStructureObject<()>>
+}
+impl <T: Clone> RRTConnectTree<T> {
+    fn new<S: StateSpace<State = T>>(root: T, state_space: &S, nearest_neighbor_structure_type: &NearestNeighborStructureType) -> Result<Self, OptimaError> {
+        let nearest_neighbor_index = match state_space.nearest_neighbor_coordinates(&root) {
+            None => { None }
+            Some(coordinates) => {
+                let mut index = NearestNeighborStructureObject::new(nearest_neighbor_structure_type, state_space.nearest_neighbor_metric());
+                index.insert(coordinates, ())?;
+                Some(index)
+            }
+        };
+
+        Ok(Self {
+            nodes: vec![root],
+            parents: vec![None],
+            nearest_neighbor_index
+        })
+    }
+    fn add<S: StateSpace<State = T>>(&mut self, node: T, parent_idx: usize, state_space: &S) -> Result<usize, OptimaError> {
+        self.add_with_parent(node, Some(parent_idx), state_space)
+    }
+    /// Adds another root.  Roots must be added before any other node.
+    fn add_root<S: StateSpace<State = T>>(&mut self, root: T, state_space: &S) -> Result<usize, OptimaError> {
+        self.add_with_parent(root, None, state_space)
+    }
+    fn add_with_parent<S: StateSpace<State = T>>(&mut self, node: T, parent_idx: Option<usize>, state_space: &S) -> Result<usize, OptimaError> {
+        if let Some(index) = &mut self.nearest_neighbor_index {
+            let coordinates = state_space.nearest_neighbor_coordinates(&node).expect("error");
+            index.insert(coordinates, ())?;
+        }
+        self.nodes.push(node);
+        self.parents.push(parent_idx);
+        Ok(self.nodes.len() - 1)
+    }
+    fn nearest<S: StateSpace<State = T>>(&self, target: &T, state_space: &S) -> usize {
+        if let Some(index) = &self.nearest_neighbor_index {
+            let coordinates = state_space.nearest_neighbor_coordinates(target).expect("error");
+            return index.nearest(&coordinates).map_or(0, |res| res.item_idx());
+        }
+
+        let mut out_idx = 0;
+        let mut min_distance = f64::INFINITY;
+        for (i, node) in self.nodes.iter().enumerate() {
+            let distance = state_space.distance(node, target);
+            if distance < min_distance { min_distance = distance; out_idx = i; }
+        }
+        out_idx
+    }
+    /// The index of the root the given node descends from.
+    fn root_idx(&self, idx: usize) -> usize {
+        let mut curr = idx;
+        while let Some(parent_idx) = self.parents[curr] { curr = parent_idx; }
+        curr
+    }
+    /// Nodes from the given node to its root (both included).
+    fn path_to_root(&self, idx: usize) -> Vec<T> {
+        let mut out_vec = vec![self.nodes[idx].clone()];
+        let mut curr = idx;
+        while let Some(parent_idx) = self.parents[curr] {
+            out_vec.push(self.nodes[parent_idx].clone());
+            curr = parent_idx;
+        }
+        out_vec
+    }
+}
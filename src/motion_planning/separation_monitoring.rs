@@ -0,0 +1,144 @@
+use nalgebra::DVector;
+use crate::robot_set_modules::robot_set_joint_state_module::RobotSetJointState;
+use crate::scenes::robot_geometric_shape_scene::{EnvObjPoseConstraintGroupInput, RobotGeometricShapeScene, RobotGeometricShapeSceneQuery};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_shape_geometry::geometric_shape::{GeometricShapeSignature, LogCondition, StopCondition};
+
+/// Runtime safety filter in the style of speed and separation monitoring: commanded joint velocities
+/// of each robot in a `RobotGeometricShapeScene` are scaled by that robot's current minimum distance
+/// to the scene's environment objects.  At or beyond slowdown_distance, velocities pass through
+/// unchanged; between slowdown_distance and stop_distance, they are scaled down linearly; at or below
+/// stop_distance, the robot is stopped.  Each robot is scaled uniformly, so the direction of its
+/// motion is kept.
+///
+/// Distances are computed with a single distance query in the scene's link shape representation,
+/// so the scene should use a fast representation (e.g., `RobotLinkShapeRepresentation::Cubes` or
+/// `ConvexShapes`) when this runs inside a control loop.  Only robot to environment object distances
+/// are considered; robot to robot and self distances are not.
+pub struct SeparationVelocityFilter<'a> {
+    robot_geometric_shape_scene: &'a RobotGeometricShapeScene,
+    slowdown_distance: f64,
+    stop_distance: f64,
+    env_obj_pose_constraint_group_input: Option<EnvObjPoseConstraintGroupInput>,
+    ignored_env_obj_idxs: Vec<usize>
+}
+impl <'a> SeparationVelocityFilter<'a> {
+    pub fn new(robot_geometric_shape_scene: &'a RobotGeometricShapeScene, slowdown_distance: f64, stop_distance: f64) -> Result<Self, OptimaError> {
+        if !(stop_distance >= 0.0 && slowdown_distance > stop_distance) {
+            return Err(OptimaError::new_generic_error_str(&format!("Separation distances must satisfy 0 <= stop_distance < slowdown_distance (given {} and {}).", stop_distance, slowdown_distance), file!(), line!()));
+        }
+
+        Ok(Self {
+            robot_geometric_shape_scene,
+            slowdown_distance,
+            stop_distance,
+            env_obj_pose_constraint_group_input: None,
+            ignored_env_obj_idxs: vec![]
+        })
+    }
+    /// Overrides the pose constraints of environment objects (None reverts to the constraints saved in
+    /// the scene), e.g., to follow tracked obstacles.
+    pub fn set_env_obj_pose_constraint_group_input(&mut self, env_obj_pose_constraint_group_input: Option<EnvObjPoseConstraintGroupInput>) {
+        self.env_obj_pose_constraint_group_input = env_obj_pose_constraint_group_input;
+    }
+    /// Ignores the given environment object, e.g., a grasped object or a table the robot is mounted on.
+    pub fn add_ignored_env_obj(&mut self, env_obj_idx: usize) -> Result<(), OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(env_obj_idx, self.robot_geometric_shape_scene.num_env_objs(), file!(), line!())?;
+        if !self.ignored_env_obj_idxs.contains(&env_obj_idx) { self.ignored_env_obj_idxs.push(env_obj_idx); }
+        Ok(())
+    }
+    pub fn clear_ignored_env_objs(&mut self) {
+        self.ignored_env_obj_idxs.clear();
+    }
+    /// Scales the given DOF velocities (concatenated over the robot set) according to each robot's
+    /// minimum distance to the environment at the given state.
+    pub fn filter(&self, robot_set_joint_state: &RobotSetJointState, commanded_velocities: &DVector<f64>) -> Result<SeparationVelocityFilterOutput, OptimaError> {
+        let robot_set_joint_state_module = self.robot_geometric_shape_scene.robot_set().robot_set_joint_state_module();
+        if commanded_velocities.len() != robot_set_joint_state_module.num_dofs() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("SeparationVelocityFilter::filter", commanded_velocities.len(), robot_set_joint_state_module.num_dofs(), file!(), line!()));
+        }
+
+        let minimum_distances = self.minimum_distances(robot_set_joint_state)?;
+        let mut scale_factors = vec![];
+        let mut filtered_velocities = commanded_velocities.clone();
+        let mut curr_idx = 0;
+        for (robot_idx_in_set, robot_joint_state_module) in robot_set_joint_state_module.robot_joint_state_modules().iter().enumerate() {
+            let scale_factor = self.scale_factor(minimum_distances[robot_idx_in_set]);
+            for _ in 0..robot_joint_state_module.num_dofs() {
+                filtered_velocities[curr_idx] *= scale_factor;
+                curr_idx += 1;
+            }
+            scale_factors.push(scale_factor);
+        }
+
+        Ok(SeparationVelocityFilterOutput {
+            filtered_velocities,
+            scale_factors,
+            minimum_distances
+        })
+    }
+    /// Returns, for each robot in the set, its minimum distance to any (non ignored) environment
+    /// object at the given state.  Robots farther than slowdown_distance from every object get
+    /// f64::INFINITY.
+    pub fn minimum_distances(&self, robot_set_joint_state: &RobotSetJointState) -> Result<Vec<f64>, OptimaError> {
+        let num_robots = self.robot_geometric_shape_scene.robot_set().robot_set_joint_state_module().robot_joint_state_modules().len();
+        let robot_set_joint_state = self.robot_geometric_shape_scene.robot_set().robot_set_joint_state_module().convert_state_to_dof_state(robot_set_joint_state)?;
+        let input = RobotGeometricShapeSceneQuery::Distance {
+            robot_set_joint_state: &robot_set_joint_state,
+            env_obj_pose_constraint_group_input: self.env_obj_pose_constraint_group_input.as_ref(),
+            inclusion_list: &None
+        };
+        let res = self.robot_geometric_shape_scene.shape_collection_query(&input, StopCondition::None, LogCondition::BelowMinDistance(self.slowdown_distance), false)?;
+
+        let mut out_vec = vec![f64::INFINITY; num_robots];
+        for output in res.outputs() {
+            let signatures = output.signatures();
+            let robot_idx_in_set = match (&signatures[0], &signatures[1]) {
+                (GeometricShapeSignature::RobotSetLink { robot_idx_in_set, .. }, GeometricShapeSignature::EnvironmentObject { environment_object_idx, .. }) |
+                (GeometricShapeSignature::EnvironmentObject { environment_object_idx, .. }, GeometricShapeSignature::RobotSetLink { robot_idx_in_set, .. }) => {
+                    if self.ignored_env_obj_idxs.contains(environment_object_idx) { continue; }
+                    *robot_idx_in_set
+                }
+                _ => { continue; }
+            };
+            let distance = output.raw_output().unwrap_distance()?;
+            if distance < out_vec[robot_idx_in_set] { out_vec[robot_idx_in_set] = distance; }
+        }
+
+        Ok(out_vec)
+    }
+    /// The velocity scale factor (in \[0, 1\]) for the given minimum distance.
+    pub fn scale_factor(&self, minimum_distance: f64) -> f64 {
+        return ((minimum_distance - self.stop_distance) / (self.slowdown_distance - self.stop_distance)).max(0.0).min(1.0);
+    }
+    pub fn slowdown_distance(&self) -> f64 {
+        self.slowdown_distance
+    }
+    pub fn stop_distance(&self) -> f64 {
+        self.stop_distance
+    }
+}
+
+/// The output of `SeparationVelocityFilter::filter`.  scale_factors and minimum_distances have one
+/// entry per robot in the set.
+#[derive(Clone, Debug)]
+pub struct SeparationVelocityFilterOutput {
+    filtered_velocities: DVector<f64>,
+    scale_factors: Vec<f64>,
+    minimum_distances: Vec<f64>
+}
+impl SeparationVelocityFilterOutput {
+    pub fn filtered_velocities(&self) -> &DVector<f64> {
+        &self.filtered_velocities
+    }
+    pub fn scale_factors(&self) -> &Vec<f64> {
+        &self.scale_factors
+    }
+    pub fn minimum_distances(&self) -> &Vec<f64> {
+        &self.minimum_distances
+    }
+    /// True if any robot was stopped.
+    pub fn stopped(&self) -> bool {
+        return self.scale_factors.iter().any(|s| *s == 0.0);
+    }
+}
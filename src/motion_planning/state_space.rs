@@ -0,0 +1,239 @@
+use std::f64::consts::PI;
+use nalgebra::{DVector, Isometry2, Isometry3, Quaternion, Translation2, Translation3, UnitComplex, UnitQuaternion, Vector3};
+use crate::motion_planning::collision_checking::RobotSetSceneCollisionChecker;
+use crate::robot_set_modules::robot_set_joint_state_module::RobotSetJointStateType;
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_nearest_neighbor::JointStateDistanceMetric;
+use crate::utils::utils_sampling::SimpleSamplers;
+
+/// The space a sampling-based planner searches, e.g., the joint space of a robot set
+/// (`JointSpace`) or the pose space of a mobile base or end effector (`SE2Space`, `SE3Space`).
+/// Planners written against this trait only need to sample, interpolate, measure and validity check
+/// states.  Currently, RRT-Connect is the only such planner (see `StateSpaceRRTConnectPlanner`); the
+/// PRM, Lazy PRM, kinodynamic RRT, anytime and experience planners work directly on the DOF states
+/// of a `RobotSetSceneCollisionChecker`'s robot set.
+pub trait StateSpace {
+    type State: Clone;
+    /// A uniform random state.
+    fn sample(&self) -> Self::State;
+    /// The state at fraction t (between 0.0 and 1.0) along the shortest segment from a to b.
+    fn interpolate(&self, a: &Self::State, b: &Self::State, t: f64) -> Self::State;
+    fn distance(&self, a: &Self::State, b: &Self::State) -> f64;
+    fn state_is_valid(&self, state: &Self::State) -> Result<bool, OptimaError>;
+    /// The maximum distance between consecutive states checked along a segment.
+    fn resolution(&self) -> f64;
+    /// Vector coordinates of the given state under which `distance` equals
+    /// `nearest_neighbor_metric`.  Planners index the states of spaces that return Some with a
+    /// nearest neighbor structure (see `utils_nearest_neighbor`), and fall back to a linear scan
+    /// over `distance` otherwise.
+    fn nearest_neighbor_coordinates(&self, _state: &Self::State) -> Option<DVector<f64>> {
+        None
+    }
+    fn nearest_neighbor_metric(&self) -> JointStateDistanceMetric {
+        JointStateDistanceMetric::Euclidean
+    }
+    /// Returns true if all interpolated states strictly between a and b are valid.  The end points
+    /// themselves are not checked.
+    fn segment_is_valid(&self, a: &Self::State, b: &Self::State) -> Result<bool, OptimaError> {
+        let num_steps = (self.distance(a, b) / self.resolution()).ceil() as usize;
+        for i in 1..num_steps {
+            let t = i as f64 / num_steps as f64;
+            if !self.state_is_valid(&self.interpolate(a, b, t))? { return Ok(false); }
+        }
+        Ok(true)
+    }
+}
+
+/// The DOF joint space of the robot set of a `RobotSetSceneCollisionChecker`, with states as
+/// concatenated DOF vectors and Euclidean distances.  DOFs can be fixed to given values (see
+/// `set_fixed_dofs`), in which case samples keep them at those values.
+pub struct JointSpace<'a> {
+    collision_checker: &'a RobotSetSceneCollisionChecker<'a>,
+    fixed_dofs: Vec<(usize, f64)>
+}
+impl <'a> JointSpace<'a> {
+    pub fn new(collision_checker: &'a RobotSetSceneCollisionChecker<'a>) -> Self {
+        Self {
+            collision_checker,
+            fixed_dofs: vec![]
+        }
+    }
+    /// Fixes the given (DOF index, value) pairs in all samples.
+    pub fn set_fixed_dofs(&mut self, fixed_dofs: Vec<(usize, f64)>) -> Result<(), OptimaError> {
+        let num_dofs = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module().num_dofs();
+        for (dof_idx, _) in &fixed_dofs {
+            OptimaError::new_check_for_idx_out_of_bound_error(*dof_idx, num_dofs, file!(), line!())?;
+        }
+        self.fixed_dofs = fixed_dofs;
+        Ok(())
+    }
+    pub fn collision_checker(&self) -> &'a RobotSetSceneCollisionChecker<'a> {
+        self.collision_checker
+    }
+    pub fn fixed_dofs(&self) -> &Vec<(usize, f64)> {
+        &self.fixed_dofs
+    }
+}
+impl <'a> StateSpace for JointSpace<'a> {
+    type State = DVector<f64>;
+    fn sample(&self) -> DVector<f64> {
+        let robot_set_joint_state_module = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        let mut out_vec = robot_set_joint_state_module.sample_set_joint_state(&RobotSetJointStateType::DOF).concatenated_state().clone();
+        for (dof_idx, value) in &self.fixed_dofs { out_vec[*dof_idx] = *value; }
+        out_vec
+    }
+    fn interpolate(&self, a: &DVector<f64>, b: &DVector<f64>, t: f64) -> DVector<f64> {
+        return a + (b - a) * t;
+    }
+    fn distance(&self, a: &DVector<f64>, b: &DVector<f64>) -> f64 {
+        return (b - a).norm();
+    }
+    fn state_is_valid(&self, state: &DVector<f64>) -> Result<bool, OptimaError> {
+        let robot_set_joint_state_module = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        let state = robot_set_joint_state_module.spawn_robot_set_joint_state(state.clone(), RobotSetJointStateType::DOF)?;
+        return self.collision_checker.state_is_valid(&state);
+    }
+    fn resolution(&self) -> f64 {
+        self.collision_checker.resolution()
+    }
+    fn nearest_neighbor_coordinates(&self, state: &DVector<f64>) -> Option<DVector<f64>> {
+        Some(state.clone())
+    }
+    fn segment_is_valid(&self, a: &DVector<f64>, b: &DVector<f64>) -> Result<bool, OptimaError> {
+        let robot_set_joint_state_module = self.collision_checker.robot_geometric_shape_scene().robot_set().robot_set_joint_state_module();
+        let a = robot_set_joint_state_module.spawn_robot_set_joint_state(a.clone(), RobotSetJointStateType::DOF)?;
+        let b = robot_set_joint_state_module.spawn_robot_set_joint_state(b.clone(), RobotSetJointStateType::DOF)?;
+        return self.collision_checker.segment_is_valid(&a, &b);
+    }
+}
+
+/// Planar poses (x, y, heading), e.g., of a mobile base, within rectangular translation bounds.  The
+/// distance between two poses is sqrt(d_translation^2 + (rotation_weight * d_heading)^2), where
+/// d_heading is the absolute heading difference in [0, pi].  Validity is decided by the given
+/// function, e.g., one that places the base of a robot set and checks it with a
+/// `RobotSetSceneCollisionChecker`.
+pub struct SE2Space<F: Fn(&Isometry2<f64>) -> Result<bool, OptimaError>> {
+    translation_bounds: [(f64, f64); 2],
+    rotation_weight: f64,
+    resolution: f64,
+    validity_function: F
+}
+impl <F: Fn(&Isometry2<f64>) -> Result<bool, OptimaError>> SE2Space<F> {
+    pub fn new(translation_bounds: [(f64, f64); 2], rotation_weight: f64, resolution: f64, validity_function: F) -> Result<Self, OptimaError> {
+        check_translation_bounds(&translation_bounds)?;
+        check_weight_and_resolution(rotation_weight, resolution)?;
+
+        Ok(Self {
+            translation_bounds,
+            rotation_weight,
+            resolution,
+            validity_function
+        })
+    }
+    pub fn translation_bounds(&self) -> &[(f64, f64); 2] {
+        &self.translation_bounds
+    }
+    pub fn rotation_weight(&self) -> f64 {
+        self.rotation_weight
+    }
+}
+impl <F: Fn(&Isometry2<f64>) -> Result<bool, OptimaError>> StateSpace for SE2Space<F> {
+    type State = Isometry2<f64>;
+    fn sample(&self) -> Isometry2<f64> {
+        let x = SimpleSamplers::uniform_samples(&self.translation_bounds.to_vec());
+        let heading = SimpleSamplers::uniform_sample((-PI, PI));
+        return Isometry2::from_parts(Translation2::new(x[0], x[1]), UnitComplex::new(heading));
+    }
+    fn interpolate(&self, a: &Isometry2<f64>, b: &Isometry2<f64>, t: f64) -> Isometry2<f64> {
+        let translation = a.translation.vector + (b.translation.vector - a.translation.vector) * t;
+        let heading_difference = (a.rotation.inverse() * b.rotation).angle();
+        return Isometry2::from_parts(Translation2::from(translation), a.rotation * UnitComplex::new(heading_difference * t));
+    }
+    fn distance(&self, a: &Isometry2<f64>, b: &Isometry2<f64>) -> f64 {
+        let translation_distance = (b.translation.vector - a.translation.vector).norm();
+        let heading_distance = (a.rotation.inverse() * b.rotation).angle().abs();
+        return (translation_distance.powi(2) + (self.rotation_weight * heading_distance).powi(2)).sqrt();
+    }
+    fn state_is_valid(&self, state: &Isometry2<f64>) -> Result<bool, OptimaError> {
+        return (self.validity_function)(state);
+    }
+    fn resolution(&self) -> f64 {
+        self.resolution
+    }
+}
+
+/// Spatial poses, e.g., of an end effector or a free-flying base, within box-shaped translation
+/// bounds and with uniformly sampled orientations.  The distance between two poses is
+/// sqrt(d_translation^2 + (rotation_weight * d_angle)^2), where d_angle is the angle of the relative
+/// rotation, and orientations are interpolated along the shortest arc.  Validity is decided by the
+/// given function.
+pub struct SE3Space<F: Fn(&Isometry3<f64>) -> Result<bool, OptimaError>> {
+    translation_bounds: [(f64, f64); 3],
+    rotation_weight: f64,
+    resolution: f64,
+    validity_function: F
+}
+impl <F: Fn(&Isometry3<f64>) -> Result<bool, OptimaError>> SE3Space<F> {
+    pub fn new(translation_bounds: [(f64, f64); 3], rotation_weight: f64, resolution: f64, validity_function: F) -> Result<Self, OptimaError> {
+        check_translation_bounds(&translation_bounds)?;
+        check_weight_and_resolution(rotation_weight, resolution)?;
+
+        Ok(Self {
+            translation_bounds,
+            rotation_weight,
+            resolution,
+            validity_function
+        })
+    }
+    pub fn translation_bounds(&self) -> &[(f64, f64); 3] {
+        &self.translation_bounds
+    }
+    pub fn rotation_weight(&self) -> f64 {
+        self.rotation_weight
+    }
+}
+impl <F: Fn(&Isometry3<f64>) -> Result<bool, OptimaError>> StateSpace for SE3Space<F> {
+    type State = Isometry3<f64>;
+    fn sample(&self) -> Isometry3<f64> {
+        let x = SimpleSamplers::uniform_samples(&self.translation_bounds.to_vec());
+        // Uniform random rotation (Shoemake, 1992).
+        let u = SimpleSamplers::uniform_samples(&vec![(0.0, 1.0), (0.0, 2.0 * PI), (0.0, 2.0 * PI)]);
+        let q = Quaternion::new(u[0].sqrt() * u[2].cos(), (1.0 - u[0]).sqrt() * u[1].sin(), (1.0 - u[0]).sqrt() * u[1].cos(), u[0].sqrt() * u[2].sin());
+        return Isometry3::from_parts(Translation3::new(x[0], x[1], x[2]), UnitQuaternion::from_quaternion(q));
+    }
+    fn interpolate(&self, a: &Isometry3<f64>, b: &Isometry3<f64>, t: f64) -> Isometry3<f64> {
+        let translation: Vector3<f64> = a.translation.vector + (b.translation.vector - a.translation.vector) * t;
+        let relative_rotation = a.rotation.inverse() * b.rotation;
+        return Isometry3::from_parts(Translation3::from(translation), a.rotation * UnitQuaternion::from_scaled_axis(relative_rotation.scaled_axis() * t));
+    }
+    fn distance(&self, a: &Isometry3<f64>, b: &Isometry3<f64>) -> f64 {
+        let translation_distance = (b.translation.vector - a.translation.vector).norm();
+        let angle = a.rotation.angle_to(&b.rotation);
+        return (translation_distance.powi(2) + (self.rotation_weight * angle).powi(2)).sqrt();
+    }
+    fn state_is_valid(&self, state: &Isometry3<f64>) -> Result<bool, OptimaError> {
+        return (self.validity_function)(state);
+    }
+    fn resolution(&self) -> f64 {
+        self.resolution
+    }
+}
+
+fn check_translation_bounds(translation_bounds: &[(f64, f64)]) -> Result<(), OptimaError> {
+    for (lower, upper) in translation_bounds {
+        if !(lower < upper) {
+            return Err(OptimaError::new_generic_error_str(&format!("Translation bounds must have lower < upper (given ({}, {})).", lower, upper), file!(), line!()));
+        }
+    }
+    Ok(())
+}
+
+fn check_weight_and_resolution(rotation_weight: f64, resolution: f64) -> Result<(), OptimaError> {
+    if !(rotation_weight >= 0.0) {
+        return Err(OptimaError::new_generic_error_str(&format!("Rotation weight must be non-negative (given {}).", rotation_weight), file!(), line!()));
+    }
+    if !(resolution > 0.0) {
+        return Err(OptimaError::new_generic_error_str(&format!("Resolution must be positive (given {}).", resolution), file!(), line!()));
+    }
+    Ok(())
+}
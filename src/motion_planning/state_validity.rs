@@ -0,0 +1,111 @@
+use nalgebra::DVector;
+use crate::motion_planning::collision_free_sampling::SelfCollisionFreeSampler;
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateModule};
+use crate::utils::utils_errors::OptimaError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils::utils_robot::robot_dataset_generator::RobotDatasetSample;
+use crate::utils::utils_robot::robot_joint_state_normalizer::RobotJointStateNormalizer;
+
+/// Decides whether robot joint states are valid, so planners can be written against any validity
+/// test: exact collision checks (e.g., `SelfCollisionFreeSampler`), learned approximations (see
+/// `LearnedSelfCollisionChecker`), or user defined constraints.
+pub trait StateValidityChecker {
+    fn state_is_valid(&self, robot_joint_state: &RobotJointState) -> Result<bool, OptimaError>;
+    /// Returns the index of the first invalid state, or None if all states are valid.  Planners that
+    /// search with an approximate checker use this with an exact checker to verify the final path.
+    fn first_invalid_state(&self, robot_joint_states: &Vec<RobotJointState>) -> Result<Option<usize>, OptimaError> {
+        for (i, robot_joint_state) in robot_joint_states.iter().enumerate() {
+            if !self.state_is_valid(robot_joint_state)? { return Ok(Some(i)); }
+        }
+        Ok(None)
+    }
+}
+impl <'a> StateValidityChecker for SelfCollisionFreeSampler<'a> {
+    fn state_is_valid(&self, robot_joint_state: &RobotJointState) -> Result<bool, OptimaError> {
+        return SelfCollisionFreeSampler::state_is_valid(self, robot_joint_state);
+    }
+}
+
+/// A learned approximation of the minimum self-distance of a robot (e.g., a small neural network
+/// trained on samples from `RobotDatasetGenerator`).  The input is a DOF joint state, normalized if
+/// the `LearnedSelfCollisionChecker` using the proxy has a normalizer.  Any
+/// `Fn(&DVector<f64>) -> f64` closure or function pointer is a proxy.
+pub trait SelfCollisionProxy: Send + Sync {
+    fn predict_min_self_distance(&self, input: &DVector<f64>) -> f64;
+}
+impl <F> SelfCollisionProxy for F where F: Fn(&DVector<f64>) -> f64 + Send + Sync {
+    fn predict_min_self_distance(&self, input: &DVector<f64>) -> f64 {
+        return self(input);
+    }
+}
+
+/// A `StateValidityChecker` that uses a learned self-collision proxy in place of geometric queries.
+/// A state is valid if the predicted minimum self-distance is greater than the safety margin.
+///
+/// Proxies are fast but approximate, so a positive safety margin should account for the proxy's
+/// error, and paths found with this checker should be verified with an exact checker before they
+/// are executed (see `StateValidityChecker::first_invalid_state`).  If the proxy was trained on
+/// normalized states, give the same normalizer here (`training_pairs` produces training data with
+/// it), so the proxy sees inputs in the form it was trained on.
+pub struct LearnedSelfCollisionChecker {
+    robot_joint_state_module: RobotJointStateModule,
+    proxy: Box<dyn SelfCollisionProxy>,
+    normalizer: Option<RobotJointStateNormalizer>,
+    safety_margin: f64
+}
+impl LearnedSelfCollisionChecker {
+    pub fn new(robot_joint_state_module: &RobotJointStateModule, proxy: Box<dyn SelfCollisionProxy>) -> Self {
+        Self {
+            robot_joint_state_module: robot_joint_state_module.clone(),
+            proxy,
+            normalizer: None,
+            safety_margin: 0.0
+        }
+    }
+    pub fn set_normalizer(&mut self, normalizer: Option<RobotJointStateNormalizer>) {
+        self.normalizer = normalizer;
+    }
+    pub fn set_safety_margin(&mut self, safety_margin: f64) {
+        self.safety_margin = safety_margin;
+    }
+    /// The proxy's prediction of the minimum self-distance at the given state.
+    pub fn predicted_min_self_distance(&self, robot_joint_state: &RobotJointState) -> Result<f64, OptimaError> {
+        let input = self.proxy_input(robot_joint_state)?;
+        return Ok(self.proxy.predict_min_self_distance(&input));
+    }
+    /// Converts dataset samples to (proxy input, minimum self-distance target) pairs, with inputs in
+    /// the same form this checker gives them to the proxy.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn training_pairs(&self, samples: &Vec<RobotDatasetSample>) -> Result<Vec<(DVector<f64>, f64)>, OptimaError> {
+        let mut out_vec = vec![];
+        for sample in samples {
+            let dof_state = DVector::from_vec(sample.joint_state().clone());
+            let input = match &self.normalizer {
+                None => { dof_state }
+                Some(normalizer) => { normalizer.normalize(&dof_state)? }
+            };
+            out_vec.push((input, sample.min_self_distance()));
+        }
+        Ok(out_vec)
+    }
+    pub fn normalizer(&self) -> &Option<RobotJointStateNormalizer> {
+        &self.normalizer
+    }
+    pub fn safety_margin(&self) -> f64 {
+        self.safety_margin
+    }
+    fn proxy_input(&self, robot_joint_state: &RobotJointState) -> Result<DVector<f64>, OptimaError> {
+        return match &self.normalizer {
+            None => {
+                let dof_state = self.robot_joint_state_module.convert_joint_state_to_dof_state(robot_joint_state)?;
+                Ok(dof_state.joint_state().clone())
+            }
+            Some(normalizer) => { normalizer.normalize_robot_joint_state(&self.robot_joint_state_module, robot_joint_state) }
+        };
+    }
+}
+impl StateValidityChecker for LearnedSelfCollisionChecker {
+    fn state_is_valid(&self, robot_joint_state: &RobotJointState) -> Result<bool, OptimaError> {
+        return Ok(self.predicted_min_self_distance(robot_joint_state)? > self.safety_margin);
+    }
+}
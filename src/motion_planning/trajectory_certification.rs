@@ -0,0 +1,261 @@
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_geometric_shape_module::{RobotGeometricShapeModule, RobotLinkShapeRepresentation, RobotShapeCollectionQuery};
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateType};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_robot::joint::JointAxisPrimitiveType;
+use crate::utils::utils_se3::optima_se3_pose::OptimaSE3PoseType;
+use crate::utils::utils_shape_geometry::geometric_shape::{GeometricShapeSignature, LogCondition, StopCondition};
+
+/// Proves that robot motions are free of self collision without densely sampling them.
+///
+/// For every link and DOF, a configuration independent bound on how fast any point of the link can
+/// move per unit of DOF motion (its "reach") is precomputed from the kinematic chain: for a revolute
+/// axis, this is the sum of the joint offsets between the axis and the link plus the radius of the
+/// link's shapes; for a prismatic axis, it is 1.  Consequently, for every configuration within a box
+/// of half widths d around a center configuration c, each point of link L lies within
+/// sum_i d_i * reach_i,L of where it is at c, i.e., the swept volume of the link is contained in
+/// its shapes at c inflated by that amount.  A box is certified if, at c, every checked pair of links
+/// is farther apart than the sum of their two inflation radii.
+///
+/// Segments are certified by bisecting them into boxes (a hierarchy over time) until every piece is
+/// certified or max_depth is reached.  Failing to certify does not mean that a collision exists,
+/// only that the bounds were too loose to prove its absence; in that case, the uncertified interval
+/// can be checked with a CCD query or dense sampling.
+pub struct RobotTrajectoryCertifier<'a> {
+    robot_geometric_shape_module: &'a RobotGeometricShapeModule,
+    robot_link_shape_representation: RobotLinkShapeRepresentation,
+    link_reaches: Vec<Vec<f64>>,
+    max_depth: usize
+}
+impl <'a> RobotTrajectoryCertifier<'a> {
+    pub fn new(robot_geometric_shape_module: &'a RobotGeometricShapeModule, robot_link_shape_representation: RobotLinkShapeRepresentation, max_depth: usize) -> Result<Self, OptimaError> {
+        let link_reaches = Self::compute_link_reaches(robot_geometric_shape_module, &robot_link_shape_representation)?;
+
+        Ok(Self {
+            robot_geometric_shape_module,
+            robot_link_shape_representation,
+            link_reaches,
+            max_depth
+        })
+    }
+    /// Returns true if every DOF joint state q with |q_i - center_i| <= max_deviation_i for all i is
+    /// proven to be free of self collision.
+    pub fn certify_region(&self, center: &RobotJointState, max_deviation: &DVector<f64>) -> Result<bool, OptimaError> {
+        let center = self.robot_geometric_shape_module.robot_joint_state_module().convert_joint_state_to_dof_state(center)?;
+        if max_deviation.len() != center.len() {
+            return Err(OptimaError::new_generic_error_str(&format!("Max deviation has length {}, but the robot has {} DOFs.", max_deviation.len(), center.len()), file!(), line!()));
+        }
+
+        let input = RobotShapeCollectionQuery::Distance {
+            robot_joint_state: &center,
+            inclusion_list: &None
+        };
+        let res = self.robot_geometric_shape_module.shape_collection_query(&input, self.robot_link_shape_representation.clone(), StopCondition::None, LogCondition::LogAll, false)?;
+
+        for output in res.outputs() {
+            let signatures = output.signatures();
+            let (link_idx1, link_idx2) = match (&signatures[0], &signatures[1]) {
+                (GeometricShapeSignature::RobotLink { link_idx: l1, .. }, GeometricShapeSignature::RobotLink { link_idx: l2, .. }) => { (*l1, *l2) }
+                _ => { continue; }
+            };
+            let dis = output.raw_output().unwrap_distance()?;
+            let bound = self.link_motion_bound(link_idx1, max_deviation)? + self.link_motion_bound(link_idx2, max_deviation)?;
+            if !(dis > bound) { return Ok(false); }
+        }
+
+        Ok(true)
+    }
+    /// Certifies the straight joint space segment from a to b (end points included).
+    pub fn certify_segment(&self, a: &RobotJointState, b: &RobotJointState) -> Result<TrajectoryCertificationOutput, OptimaError> {
+        return self.certify_trajectory(&vec![a.clone(), b.clone()]);
+    }
+    /// Certifies the piecewise linear joint space trajectory through the given waypoints.  Stops at
+    /// the first interval that cannot be certified.
+    pub fn certify_trajectory(&self, waypoints: &Vec<RobotJointState>) -> Result<TrajectoryCertificationOutput, OptimaError> {
+        let robot_joint_state_module = self.robot_geometric_shape_module.robot_joint_state_module();
+        let mut num_regions_checked = 0;
+
+        for segment_idx in 0..waypoints.len().max(1) - 1 {
+            let a = robot_joint_state_module.convert_joint_state_to_dof_state(&waypoints[segment_idx])?;
+            let b = robot_joint_state_module.convert_joint_state_to_dof_state(&waypoints[segment_idx + 1])?;
+            let uncertified = self.certify_subsegment(a.joint_state(), b.joint_state(), 0.0, 1.0, 0, &mut num_regions_checked)?;
+            if let Some((t_start, t_end)) = uncertified {
+                return Ok(TrajectoryCertificationOutput {
+                    certified: false,
+                    uncertified_interval: Some(UncertifiedInterval { segment_idx, t_start, t_end }),
+                    num_regions_checked
+                });
+            }
+        }
+
+        Ok(TrajectoryCertificationOutput {
+            certified: true,
+            uncertified_interval: None,
+            num_regions_checked
+        })
+    }
+    /// Certifies any motion (not necessarily a straight line) that starts at start and lasts at most
+    /// duration seconds while each DOF moves no faster than its velocity bound.  If
+    /// velocity_bounds is None, the robot's joint velocity limits are used.
+    pub fn certify_with_velocity_bounds(&self, start: &RobotJointState, velocity_bounds: Option<&Vec<f64>>, duration: f64) -> Result<bool, OptimaError> {
+        let robot_joint_state_module = self.robot_geometric_shape_module.robot_joint_state_module();
+        let velocity_bounds = match velocity_bounds {
+            None => { robot_joint_state_module.get_joint_state_velocity_limits(&RobotJointStateType::DOF) }
+            Some(velocity_bounds) => { velocity_bounds.clone() }
+        };
+        if !(duration >= 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Duration must be non-negative (given {}).", duration), file!(), line!()));
+        }
+
+        let max_deviation = DVector::from_iterator(velocity_bounds.len(), velocity_bounds.iter().map(|v| v.abs() * duration));
+        return self.certify_region(start, &max_deviation);
+    }
+    /// The distance that any point of the given link can move away from its position at the center
+    /// of a box of DOF joint states with the given half widths.
+    pub fn link_motion_bound(&self, link_idx: usize, max_deviation: &DVector<f64>) -> Result<f64, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(link_idx, self.link_reaches.len(), file!(), line!())?;
+        let reaches = &self.link_reaches[link_idx];
+        let mut out = 0.0;
+        for (i, reach) in reaches.iter().enumerate() {
+            if *reach > 0.0 { out += max_deviation[i].abs() * reach; }
+        }
+        Ok(out)
+    }
+    /// The reach of every DOF with respect to the given link (zero for DOFs that do not move it).
+    pub fn link_reaches(&self, link_idx: usize) -> Result<&Vec<f64>, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(link_idx, self.link_reaches.len(), file!(), line!())?;
+        Ok(&self.link_reaches[link_idx])
+    }
+    pub fn robot_link_shape_representation(&self) -> &RobotLinkShapeRepresentation {
+        &self.robot_link_shape_representation
+    }
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+    /// Returns the first interval of \[t_start, t_end\] (as fractions of the segment from a to b) that
+    /// could not be certified, if any.
+    fn certify_subsegment(&self, a: &DVector<f64>, b: &DVector<f64>, t_start: f64, t_end: f64, depth: usize, num_regions_checked: &mut usize) -> Result<Option<(f64, f64)>, OptimaError> {
+        let start = a + (b - a) * t_start;
+        let end = a + (b - a) * t_end;
+        let center = (&start + &end) * 0.5;
+        let max_deviation = (&end - &start).abs() * 0.5;
+
+        let center = self.robot_geometric_shape_module.robot_joint_state_module().spawn_robot_joint_state(center, RobotJointStateType::DOF)?;
+        *num_regions_checked += 1;
+        if self.certify_region(&center, &max_deviation)? { return Ok(None); }
+        if depth >= self.max_depth { return Ok(Some((t_start, t_end))); }
+
+        let t_mid = 0.5 * (t_start + t_end);
+        if let Some(uncertified) = self.certify_subsegment(a, b, t_start, t_mid, depth + 1, num_regions_checked)? { return Ok(Some(uncertified)); }
+        return self.certify_subsegment(a, b, t_mid, t_end, depth + 1, num_regions_checked);
+    }
+    fn compute_link_reaches(robot_geometric_shape_module: &RobotGeometricShapeModule, robot_link_shape_representation: &RobotLinkShapeRepresentation) -> Result<Vec<Vec<f64>>, OptimaError> {
+        let robot_model_module = robot_geometric_shape_module.robot_kinematics_module().robot_configuration_module().robot_model_module();
+        let dof_axes = robot_geometric_shape_module.robot_joint_state_module().ordered_dof_joint_axes();
+        let robot_shape_collection = robot_geometric_shape_module.robot_shape_collection(robot_link_shape_representation)?;
+        let links = robot_model_module.links();
+        let joints = robot_model_module.joints();
+        let world_link_id = robot_model_module.world_link_id();
+
+        // The largest distance that a joint can translate its child link, on top of its origin offset.
+        let max_translation = |joint_idx: usize| -> f64 {
+            let mut out = 0.0;
+            for axis in joints[joint_idx].joint_axes() {
+                if let JointAxisPrimitiveType::Translation = axis.axis_primitive_type() {
+                    out += match axis.fixed_value() {
+                        None => { axis.bounds().0.abs().max(axis.bounds().1.abs()) }
+                        Some(v) => { v.abs() }
+                    };
+                }
+            }
+            out
+        };
+
+        let mut out_vec = vec![];
+        for link_idx in 0..links.len() {
+            let mut reaches = vec![0.0; dof_axes.len()];
+
+            let shape_idxs = robot_shape_collection.get_shape_idxs_from_link_idx(link_idx)?;
+            let chain = if links[link_idx].present() && !shape_idxs.is_empty() { robot_model_module.get_link_chain(world_link_id, links[link_idx].link_id())? } else { None };
+            if let Some(chain) = chain {
+                let mut link_radius = 0.0;
+                for shape_idx in shape_idxs {
+                    link_radius = f64::max(link_radius, robot_shape_collection.shape_collection().shapes()[*shape_idx].f());
+                }
+
+                // downstream_lengths[i] bounds the distance from the origin of chain link i to any
+                // point on the link.
+                let mut downstream_lengths = vec![0.0; chain.len()];
+                let mut acc = link_radius;
+                for i in (0..chain.len()).rev() {
+                    downstream_lengths[i] = acc;
+                    if let Some(joint_idx) = links[chain[i].idx()].preceding_joint_idx() {
+                        let offset = joints[joint_idx].get_origin_offset(&OptimaSE3PoseType::ImplicitDualQuaternion).to_nalgebra_isometry().translation.vector.norm();
+                        acc += offset + max_translation(joint_idx);
+                    }
+                }
+
+                for (dof_idx, axis) in dof_axes.iter().enumerate() {
+                    let chain_position = chain.iter().position(|l| links[l.idx()].preceding_joint_idx() == Some(axis.joint_idx()));
+                    if let Some(chain_position) = chain_position {
+                        reaches[dof_idx] = match axis.axis_primitive_type() {
+                            JointAxisPrimitiveType::Translation => { 1.0 }
+                            // The joint's own translations (e.g., for a floating joint) may also move
+                            // the link away from the rotation axis.
+                            JointAxisPrimitiveType::Rotation => { downstream_lengths[chain_position] + max_translation(axis.joint_idx()) }
+                            // A unit change in a quaternion component does not bound the change in
+                            // rotation (it depends on the quaternion's norm).
+                            JointAxisPrimitiveType::QuaternionComponent => {
+                                return Err(OptimaError::new_unsupported_operation_error("compute_link_reaches", "Trajectory certification does not support ball joints.", file!(), line!()));
+                            }
+                        };
+                    }
+                }
+            }
+
+            out_vec.push(reaches);
+        }
+
+        Ok(out_vec)
+    }
+}
+
+/// The output of `RobotTrajectoryCertifier::certify_trajectory`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrajectoryCertificationOutput {
+    certified: bool,
+    uncertified_interval: Option<UncertifiedInterval>,
+    num_regions_checked: usize
+}
+impl TrajectoryCertificationOutput {
+    pub fn certified(&self) -> bool {
+        self.certified
+    }
+    pub fn uncertified_interval(&self) -> &Option<UncertifiedInterval> {
+        &self.uncertified_interval
+    }
+    pub fn num_regions_checked(&self) -> usize {
+        self.num_regions_checked
+    }
+}
+
+/// The portion \[t_start, t_end\] (as fractions from 0 to 1) of segment segment_idx that could not be
+/// certified.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UncertifiedInterval {
+    segment_idx: usize,
+    t_start: f64,
+    t_end: f64
+}
+impl UncertifiedInterval {
+    pub fn segment_idx(&self) -> usize {
+        self.segment_idx
+    }
+    pub fn t_start(&self) -> f64 {
+        self.t_start
+    }
+    pub fn t_end(&self) -> f64 {
+        self.t_end
+    }
+}
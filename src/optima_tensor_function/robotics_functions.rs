@@ -1,7 +1,9 @@
 use nalgebra::{DVector, Vector6};
 use crate::optima_tensor_function::{OptimaTensor, OptimaTensorFunction, OptimaTensorFunctionClone, OTFImmutVars, OTFImmutVarsObject, OTFImmutVarsObjectType, OTFMutVars, OTFMutVarsObjectType, OTFMutVarsSessionKey, OTFResult, RecomputeVarIf};
+use crate::robot_modules::robot_joint_state_module::{RobotJointStateModule, RobotJointStateType};
 use crate::robot_modules::robot_kinematics_module::{JacobianEndPoint, JacobianMode};
 use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_robot::robot_ids::LinkId;
 use crate::utils::utils_robot::robot_set_link_specification::RobotSetLinkSpecification;
 use crate::utils::utils_se3::optima_se3_pose::OptimaSE3PoseType;
 
@@ -24,37 +26,33 @@ impl OptimaTensorFunction for OTFRobotSetLinkSpecification {
 
         let mut out_error = 0.0;
         for s in specs {
-            match s {
-                RobotSetLinkSpecification::LinkSE3PoseGoal { robot_idx_in_set, link_idx_in_robot, goal, weight } => unsafe {
+            let delta = match s {
+                RobotSetLinkSpecification::LinkSE3PoseGoal { robot_idx_in_set, link_idx_in_robot, goal, .. } => {
                     let pose = robot_set_fk_result.get_pose_from_idxs(*robot_idx_in_set, *link_idx_in_robot);
-                    let se3_delta = pose.distance_function(&goal, true).expect("error");
-                    let weight = match weight {
-                        None => { 1.0 }
-                        Some(weight) => { *weight }
-                    };
-                    out_error += weight * se3_delta;
+                    pose.distance_function(&goal, true).expect("error")
                 }
-                RobotSetLinkSpecification::LinkPositionGoal { robot_idx_in_set, link_idx_in_robot, goal, weight } => {
+                RobotSetLinkSpecification::LinkPositionGoal { robot_idx_in_set, link_idx_in_robot, goal, .. } => {
                     let pose = robot_set_fk_result.get_pose_from_idxs(*robot_idx_in_set, *link_idx_in_robot);
-                    let position = pose.translation();
-                    let r3_delta = (goal - &position).norm();
-                    let weight = match weight {
-                        None => { 1.0 }
-                        Some(weight) => { *weight }
-                    };
-                    out_error += weight * r3_delta;
+                    (goal - &pose.translation()).norm()
                 }
-                RobotSetLinkSpecification::LinkRotationGoal { robot_idx_in_set, link_idx_in_robot, goal, weight } => {
+                RobotSetLinkSpecification::LinkRotationGoal { robot_idx_in_set, link_idx_in_robot, goal, .. } => {
                     let pose = robot_set_fk_result.get_pose_from_idxs(*robot_idx_in_set, *link_idx_in_robot);
-                    let rotation = pose.rotation();
-                    let so3_delta = rotation.angle_between(goal, true).expect("error");
-                    let weight = match weight {
-                        None => { 1.0 }
-                        Some(weight) => { *weight }
-                    };
-                    out_error += weight * so3_delta;
+                    pose.rotation().angle_between(goal, true).expect("error")
                 }
-            }
+                RobotSetLinkSpecification::TCPFrameSE3PoseGoal { robot_idx_in_set, tcp_frame_idx_in_robot, goal, .. } => {
+                    let pose = robot_set_fk_result.get_tcp_frame_pose_from_idxs(*robot_idx_in_set, *tcp_frame_idx_in_robot);
+                    pose.distance_function(&goal, true).expect("error")
+                }
+                RobotSetLinkSpecification::TCPFramePositionGoal { robot_idx_in_set, tcp_frame_idx_in_robot, goal, .. } => {
+                    let pose = robot_set_fk_result.get_tcp_frame_pose_from_idxs(*robot_idx_in_set, *tcp_frame_idx_in_robot);
+                    (goal - &pose.translation()).norm()
+                }
+                RobotSetLinkSpecification::TCPFrameRotationGoal { robot_idx_in_set, tcp_frame_idx_in_robot, goal, .. } => {
+                    let pose = robot_set_fk_result.get_tcp_frame_pose_from_idxs(*robot_idx_in_set, *tcp_frame_idx_in_robot);
+                    pose.rotation().angle_between(goal, true).expect("error")
+                }
+            };
+            out_error += s.weight() * delta;
         }
 
         return Ok(OTFResult::Complete(OptimaTensor::new_from_scalar(out_error)));
@@ -85,7 +83,7 @@ impl OptimaTensorFunction for OTFRobotSetLinkSpecification {
                     let disp_translation = (&pose_translation - &goal_translation);
                     let disp_rotation = pose_rotation.displacement(&goal_rotation, true).expect("error").ln();
                     let disp_vector = Vector6::new(disp_translation[0], disp_translation[1], disp_translation[2], disp_rotation[0], disp_rotation[1], disp_rotation[2]);
-                    let jacobian = robot_set.robot_set_kinematics_module().compute_jacobian(&robot_set_joint_state, *robot_idx_in_set, None, *link_idx_in_robot, &JacobianEndPoint::Link, None, JacobianMode::Full).expect("error");
+                    let jacobian = robot_set.robot_set_kinematics_module().compute_jacobian(&robot_set_joint_state, *robot_idx_in_set, None, LinkId::new(*link_idx_in_robot), &JacobianEndPoint::Link, None, JacobianMode::Full).expect("error");
                     let ps = jacobian.pseudo_inverse(0.0001).expect("error");
                     let delta_angle = ps * disp_vector;
                     let weight = match weight {
@@ -98,7 +96,7 @@ impl OptimaTensorFunction for OTFRobotSetLinkSpecification {
                     let pose = robot_set_fk_result.get_pose_from_idxs(*robot_idx_in_set, *link_idx_in_robot);
                     let translation = pose.translation();
                     let disp_translation = (&translation - goal);
-                    let jacobian = robot_set.robot_set_kinematics_module().compute_jacobian(&robot_set_joint_state, *robot_idx_in_set, None, *link_idx_in_robot, &JacobianEndPoint::Link, None, JacobianMode::Translational).expect("error");
+                    let jacobian = robot_set.robot_set_kinematics_module().compute_jacobian(&robot_set_joint_state, *robot_idx_in_set, None, LinkId::new(*link_idx_in_robot), &JacobianEndPoint::Link, None, JacobianMode::Translational).expect("error");
                     let ps = jacobian.pseudo_inverse(0.0001).expect("error");
                     let delta_angle = ps * disp_translation;
                     let weight = match weight {
@@ -111,7 +109,7 @@ impl OptimaTensorFunction for OTFRobotSetLinkSpecification {
                     let pose = robot_set_fk_result.get_pose_from_idxs(*robot_idx_in_set, *link_idx_in_robot);
                     let rotation = pose.rotation();
                     let disp_rotation = goal.displacement(&rotation, true).expect("error").ln();
-                    let jacobian = robot_set.robot_set_kinematics_module().compute_jacobian(&robot_set_joint_state, *robot_idx_in_set, None, *link_idx_in_robot, &JacobianEndPoint::Link, None, JacobianMode::Rotational).expect("error");
+                    let jacobian = robot_set.robot_set_kinematics_module().compute_jacobian(&robot_set_joint_state, *robot_idx_in_set, None, LinkId::new(*link_idx_in_robot), &JacobianEndPoint::Link, None, JacobianMode::Rotational).expect("error");
                     let ps = jacobian.pseudo_inverse(0.0001).expect("error");
                     let delta_angle = ps * disp_rotation;
                     let weight = match weight {
@@ -126,4 +124,82 @@ impl OptimaTensorFunction for OTFRobotSetLinkSpecification {
         Ok(OTFResult::Complete(OptimaTensor::new_from_vector(out_vec)))
     }
     */
-}
\ No newline at end of file
+}
+/// Soft joint limit cost of a robot joint state (see `RobotJointStateModule::soft_joint_limit_cost`).
+/// The input is a joint state vector of the given type.
+#[derive(Clone)]
+pub struct OTFRobotSoftJointLimitCost {
+    robot_joint_state_module: RobotJointStateModule,
+    robot_joint_state_type: RobotJointStateType
+}
+impl OTFRobotSoftJointLimitCost {
+    pub fn new(robot_joint_state_module: RobotJointStateModule, robot_joint_state_type: RobotJointStateType) -> Self {
+        Self {
+            robot_joint_state_module,
+            robot_joint_state_type
+        }
+    }
+}
+impl OptimaTensorFunction for OTFRobotSoftJointLimitCost {
+    fn output_dimensions(&self) -> Vec<usize> {
+        vec![]
+    }
+
+    fn call_raw(&self, input: &OptimaTensor, _immut_vars: &OTFImmutVars, _mut_vars: &mut OTFMutVars, _session_key: &OTFMutVarsSessionKey) -> Result<OTFResult, OptimaError> {
+        let joint_state = self.robot_joint_state_module.spawn_robot_joint_state(input.unwrap_vector().clone(), self.robot_joint_state_type.clone())?;
+        let val = self.robot_joint_state_module.soft_joint_limit_cost(&joint_state);
+        return Ok(OTFResult::Complete(OptimaTensor::new_from_scalar(val)));
+    }
+
+    fn derivative_analytical_raw(&self, input: &OptimaTensor, _immut_vars: &OTFImmutVars, _mut_vars: &mut OTFMutVars, _session_key: &OTFMutVarsSessionKey) -> Result<OTFResult, OptimaError> {
+        let joint_state = self.robot_joint_state_module.spawn_robot_joint_state(input.unwrap_vector().clone(), self.robot_joint_state_type.clone())?;
+        let grad = self.robot_joint_state_module.soft_joint_limit_cost_gradient(&joint_state);
+        return Ok(OTFResult::Complete(OptimaTensor::new_from_vector(grad)));
+    }
+}
+
+/// Posture preference cost of a robot joint state with respect to a named posture of the robot's
+/// configuration (see `RobotJointStateModule::posture_deviation_cost`), e.g., to keep the arm shapes
+/// along an optimized trajectory close to "home".  The input is a joint state vector of the given
+/// type.
+#[derive(Clone)]
+pub struct OTFRobotPosturePreferenceCost {
+    robot_joint_state_module: RobotJointStateModule,
+    robot_joint_state_type: RobotJointStateType,
+    posture_name: String,
+    joint_weighting_profile_name: Option<String>
+}
+impl OTFRobotPosturePreferenceCost {
+    /// Returns an error if the configuration has no posture (or joint weighting profile) with the
+    /// given name.
+    pub fn new(robot_joint_state_module: RobotJointStateModule, robot_joint_state_type: RobotJointStateType, posture_name: &str, joint_weighting_profile_name: Option<&str>) -> Result<Self, OptimaError> {
+        robot_joint_state_module.get_named_posture_values(posture_name, &robot_joint_state_type)?;
+        if let Some(profile_name) = joint_weighting_profile_name {
+            robot_joint_state_module.get_joint_state_weights(profile_name, &robot_joint_state_type)?;
+        }
+
+        Ok(Self {
+            robot_joint_state_module,
+            robot_joint_state_type,
+            posture_name: posture_name.to_string(),
+            joint_weighting_profile_name: joint_weighting_profile_name.map(|s| s.to_string())
+        })
+    }
+}
+impl OptimaTensorFunction for OTFRobotPosturePreferenceCost {
+    fn output_dimensions(&self) -> Vec<usize> {
+        vec![]
+    }
+
+    fn call_raw(&self, input: &OptimaTensor, _immut_vars: &OTFImmutVars, _mut_vars: &mut OTFMutVars, _session_key: &OTFMutVarsSessionKey) -> Result<OTFResult, OptimaError> {
+        let joint_state = self.robot_joint_state_module.spawn_robot_joint_state(input.unwrap_vector().clone(), self.robot_joint_state_type.clone())?;
+        let val = self.robot_joint_state_module.posture_deviation_cost(&joint_state, &self.posture_name, self.joint_weighting_profile_name.as_deref())?;
+        return Ok(OTFResult::Complete(OptimaTensor::new_from_scalar(val)));
+    }
+
+    fn derivative_analytical_raw(&self, input: &OptimaTensor, _immut_vars: &OTFImmutVars, _mut_vars: &mut OTFMutVars, _session_key: &OTFMutVarsSessionKey) -> Result<OTFResult, OptimaError> {
+        let joint_state = self.robot_joint_state_module.spawn_robot_joint_state(input.unwrap_vector().clone(), self.robot_joint_state_type.clone())?;
+        let grad = self.robot_joint_state_module.posture_deviation_cost_gradient(&joint_state, &self.posture_name, self.joint_weighting_profile_name.as_deref())?;
+        return Ok(OTFResult::Complete(OptimaTensor::new_from_vector(grad)));
+    }
+}
@@ -4,6 +4,21 @@ pub mod robot_configuration_module;
 pub mod robot_kinematics_module;
 pub mod robot_joint_state_module;
 pub mod robot_geometric_shape_module;
+pub mod robot_shape_distance_lookup;
+pub mod robot_shape_preprocessing_report;
 pub mod robot_mesh_file_manager_module;
+pub mod robot_module_cache;
+pub mod robot_ik_solution_memory;
+pub mod robot_ik_regularization;
+pub mod robot_workspace_bound;
+pub mod robot_poe_model;
+pub mod robot_session;
+pub mod robot_sim_state;
+pub mod robot_cartesian_jogger;
+pub mod robot_self_motion;
+#[cfg(target_arch = "wasm32")]
+pub mod robot_module_browser_cache;
 #[cfg(not(target_arch = "wasm32"))]
-pub mod robot_preprocessing_module;
\ No newline at end of file
+pub mod robot_preprocessing_module;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod robot_asset_watcher;
\ No newline at end of file
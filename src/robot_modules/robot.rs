@@ -9,7 +9,6 @@ use crate::robot_modules::robot_kinematics_module::RobotKinematicsModule;
 use crate::robot_modules::robot_geometric_shape_module::RobotGeometricShapeModule;
 use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateModule};
 use crate::utils::utils_errors::OptimaError;
-use crate::utils::utils_files::optima_path::load_object_from_json_string;
 use crate::utils::utils_robot::robot_module_utils::RobotNames;
 use crate::utils::utils_traits::SaveAndLoadable;
 
@@ -26,8 +25,8 @@ impl Robot {
     pub fn new_from_names(robot_name: RobotNames) -> Self {
         let robot_configuration_module = RobotConfigurationModule::new_from_names(robot_name.clone()).expect("error");
         let robot_mesh_file_manager_module = RobotMeshFileManagerModule::new(robot_configuration_module.robot_model_module()).expect("error");
-        let robot_joint_state_module = RobotJointStateModule::new(robot_configuration_module.clone());
         let robot_fk_module = RobotKinematicsModule::new(robot_configuration_module.clone());
+        let robot_joint_state_module = robot_fk_module.robot_joint_state_module().clone();
 
         Self {
             robot_configuration_module,
@@ -38,8 +37,8 @@ impl Robot {
     }
     pub fn new_from_robot_configuration_module(robot_configuration_module: RobotConfigurationModule) -> Self {
         let robot_mesh_file_manager_module = RobotMeshFileManagerModule::new(robot_configuration_module.robot_model_module()).expect("error");
-        let robot_joint_state_module = RobotJointStateModule::new(robot_configuration_module.clone());
         let robot_fk_module = RobotKinematicsModule::new(robot_configuration_module.clone());
+        let robot_joint_state_module = robot_fk_module.robot_joint_state_module().clone();
 
         Self {
             robot_configuration_module,
@@ -78,7 +77,7 @@ impl SaveAndLoadable for Robot {
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let load: Self::SaveType = load_object_from_json_string(json_str)?;
+        let load: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
         let robot_configuration_module = RobotConfigurationModule::load_from_json_string(&load.0)?;
         let robot_mesh_file_manager_module = RobotMeshFileManagerModule::load_from_json_string(&load.1)?;
         let robot_joint_state_module = RobotJointStateModule::load_from_json_string(&load.2)?;
@@ -113,7 +112,37 @@ impl RobotPy {
     pub fn new_from_names(robot_name: &str, configuration_name: Option<&str>, py: Python) -> Self {
         let robot_names = RobotNames::new(robot_name, configuration_name);
         let r = Robot::new_from_names(robot_names);
-        
+        Self::new_from_robot(r, py)
+    }
+    #[staticmethod]
+    pub fn new_from_robot_modules(robot_configuration_module: RobotConfigurationModule,
+                                  robot_mesh_file_manager_module: RobotMeshFileManagerModule,
+                                  robot_joint_state_module: RobotJointStateModule,
+                                  robot_kinematics_module: RobotKinematicsModule,
+                                  py: Python) -> Self {
+        let r = Robot {
+            robot_configuration_module,
+            robot_mesh_file_manager_module,
+            robot_joint_state_module,
+            robot_kinematics_module
+        };
+        Self::new_from_robot(r, py)
+    }
+    pub fn __reduce__(&self, py: Python) -> PyResult<(PyObject, (RobotConfigurationModule, RobotMeshFileManagerModule, RobotJointStateModule, RobotKinematicsModule))> {
+        let constructor = py.get_type::<Self>().getattr("new_from_robot_modules")?;
+        let args = (self.robot_configuration_module.borrow(py).clone(),
+                    self.robot_mesh_file_manager_module.borrow(py).clone(),
+                    self.robot_joint_state_module.borrow(py).clone(),
+                    self.robot_kinematics_module.borrow(py).clone());
+        return Ok((PyObject::from(constructor), args));
+    }
+    pub fn generate_robot_geometric_shape_module_py(&self) -> RobotGeometricShapeModule {
+        self.phantom_robot.generate_robot_geometric_shape_module().expect("error")
+    }
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl RobotPy {
+    pub fn new_from_robot(r: Robot, py: Python) -> Self {
         Self {
             robot_configuration_module: Py::new(py, r.robot_configuration_module.clone()).expect("error"),
             robot_mesh_file_manager_module: Py::new(py, r.robot_mesh_file_manager_module.clone()).expect("error"),
@@ -122,7 +151,4 @@ impl RobotPy {
             phantom_robot: r,
         }
     }
-    pub fn generate_robot_geometric_shape_module_py(&self) -> RobotGeometricShapeModule {
-        self.phantom_robot.generate_robot_geometric_shape_module().expect("error")
-    }
 }
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
+use crate::robot_modules::robot_configuration_module::RobotConfigurationModule;
+use crate::robot_modules::robot_geometric_shape_module::PreprocessingConfig;
+use crate::robot_modules::robot_preprocessing_module::RobotPreprocessingModule;
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_files::optima_path::{OptimaAssetLocation, OptimaPath, OptimaStemCellPath};
+use crate::utils::utils_robot::robot_module_utils::RobotNames;
+
+/// Subdirectories of a robot's asset directory that are written by preprocessing and are thus not
+/// watched (otherwise each rebuild would trigger another rebuild).
+const GENERATED_SUBDIRECTORIES: [&str; 3] = ["meshes", "glb_meshes", "preprocessed_data"];
+
+/// Watches a robot's asset directory (URDF, input meshes, and configuration files) for changes and,
+/// when something changes, re-runs the relevant preprocessing and rebuilds the robot's
+/// `RobotConfigurationModule`, passing the result to a callback.  This lets long-running
+/// visualization or planning servers pick up edits to a robot without restarting.
+///
+/// Changes are detected by polling file modification times on a background thread at the given
+/// interval, so no platform-specific file notification APIs are needed.  Only physical (non-embedded)
+/// asset directories can be watched.  Meshes referenced by the URDF from outside the robot's asset
+/// directory are not watched.  The watcher stops when `stop` is called or when it is dropped.
+pub struct RobotAssetWatcher {
+    robot_name: String,
+    stop_flag: Arc<AtomicBool>,
+    join_handle: Option<JoinHandle<()>>
+}
+impl RobotAssetWatcher {
+    pub fn new<F>(robot_name: &str, configuration_name: Option<&str>, poll_interval: Duration, callback: F) -> Result<Self, OptimaError>
+        where F: FnMut(RobotAssetChangeEvent) + Send + 'static {
+        let robot_directory = Self::physical_robot_directory(robot_name)?;
+        let stop_flag = Arc::new(AtomicBool::new(false));
+
+        let thread_robot_name = robot_name.to_string();
+        let thread_configuration_name = configuration_name.map(|s| s.to_string());
+        let thread_stop_flag = stop_flag.clone();
+        let mut callback = callback;
+
+        let join_handle = std::thread::spawn(move || {
+            let mut snapshot = Self::take_snapshot(&robot_directory);
+            while !thread_stop_flag.load(Ordering::Relaxed) {
+                std::thread::sleep(poll_interval);
+                if thread_stop_flag.load(Ordering::Relaxed) { break; }
+
+                let new_snapshot = Self::take_snapshot(&robot_directory);
+                let changed_paths = Self::changed_paths(&snapshot, &new_snapshot);
+                if changed_paths.is_empty() { continue; }
+
+                let rebuilt = Self::rebuild(&thread_robot_name, thread_configuration_name.as_ref().map(|s| s.as_str()), &changed_paths);
+                callback(RobotAssetChangeEvent {
+                    robot_name: thread_robot_name.clone(),
+                    changed_paths,
+                    rebuilt
+                });
+
+                // Re-snapshot so that any files touched during the rebuild do not trigger another one.
+                snapshot = Self::take_snapshot(&robot_directory);
+            }
+        });
+
+        Ok(Self {
+            robot_name: robot_name.to_string(),
+            stop_flag,
+            join_handle: Some(join_handle)
+        })
+    }
+    /// Stops the watcher and waits for its background thread to finish.
+    pub fn stop(&mut self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+        if let Some(join_handle) = self.join_handle.take() {
+            let _ = join_handle.join();
+        }
+    }
+    pub fn is_running(&self) -> bool {
+        self.join_handle.is_some()
+    }
+    pub fn robot_name(&self) -> &str {
+        &self.robot_name
+    }
+    fn rebuild(robot_name: &str, configuration_name: Option<&str>, changed_paths: &Vec<PathBuf>) -> Result<RobotConfigurationModule, OptimaError> {
+        let meshes_changed = changed_paths.iter().any(|p| {
+            let extension = p.extension().map(|e| e.to_string_lossy().to_lowercase());
+            match extension {
+                None => { false }
+                Some(extension) => { extension == "stl" || extension == "dae" || extension == "obj" || extension == "glb" || extension == "gltf" }
+            }
+        });
+
+        RobotPreprocessingModule {
+            replace_robot_model_module_json: true,
+            replace_robot_link_convex_shapes: meshes_changed,
+            replace_robot_link_convex_shape_subcomponents: meshes_changed,
+            preprocessing_config: PreprocessingConfig::default()
+        }.preprocess_robot(robot_name)?;
+
+        return RobotConfigurationModule::new_from_names(RobotNames::new(robot_name, configuration_name));
+    }
+    fn physical_robot_directory(robot_name: &str) -> Result<PathBuf, OptimaError> {
+        let mut path = OptimaStemCellPath::new_asset_path()?;
+        path.append_file_location(&OptimaAssetLocation::Robot { robot_name: robot_name.to_string() });
+        for p in path.optima_file_paths() {
+            if let OptimaPath::Path(p) = p {
+                if p.exists() { return Ok(p.clone()); }
+            }
+        }
+        return Err(OptimaError::new_unsupported_operation_error("RobotAssetWatcher::new", &format!("Robot {} does not have a physical asset directory to watch.", robot_name), file!(), line!()));
+    }
+    fn take_snapshot(robot_directory: &PathBuf) -> HashMap<PathBuf, SystemTime> {
+        let mut out_map = HashMap::new();
+
+        let walker = WalkDir::new(robot_directory).into_iter().filter_entry(|e| {
+            if e.depth() != 1 || !e.file_type().is_dir() { return true; }
+            let name = e.file_name().to_string_lossy();
+            return !GENERATED_SUBDIRECTORIES.contains(&&*name);
+        });
+        for entry in walker {
+            if let Ok(entry) = entry {
+                if !entry.file_type().is_file() { continue; }
+                if let Ok(metadata) = entry.metadata() {
+                    if let Ok(modified) = metadata.modified() {
+                        out_map.insert(entry.path().to_path_buf(), modified);
+                    }
+                }
+            }
+        }
+
+        out_map
+    }
+    fn changed_paths(old: &HashMap<PathBuf, SystemTime>, new: &HashMap<PathBuf, SystemTime>) -> Vec<PathBuf> {
+        let mut out_vec = vec![];
+        for (path, modified) in new {
+            if old.get(path) != Some(modified) { out_vec.push(path.clone()); }
+        }
+        for path in old.keys() {
+            if !new.contains_key(path) { out_vec.push(path.clone()); }
+        }
+        out_vec.sort();
+        out_vec
+    }
+}
+impl Drop for RobotAssetWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Passed to the `RobotAssetWatcher` callback after a change is detected.  rebuilt holds the newly
+/// built configuration module, or the error encountered while preprocessing or rebuilding.
+#[derive(Debug)]
+pub struct RobotAssetChangeEvent {
+    robot_name: String,
+    changed_paths: Vec<PathBuf>,
+    rebuilt: Result<RobotConfigurationModule, OptimaError>
+}
+impl RobotAssetChangeEvent {
+    pub fn robot_name(&self) -> &str {
+        &self.robot_name
+    }
+    /// Files that were added, modified, or removed.
+    pub fn changed_paths(&self) -> &Vec<PathBuf> {
+        &self.changed_paths
+    }
+    pub fn rebuilt(&self) -> &Result<RobotConfigurationModule, OptimaError> {
+        &self.rebuilt
+    }
+    pub fn take_rebuilt(self) -> Result<RobotConfigurationModule, OptimaError> {
+        self.rebuilt
+    }
+}
@@ -0,0 +1,322 @@
+#[cfg(not(target_arch = "wasm32"))]
+use pyo3::*;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_configuration_module::RobotConfigurationModule;
+use crate::robot_modules::robot_geometric_shape_module::{RobotGeometricShapeModule, RobotLinkShapeRepresentation, RobotShapeCollectionQuery};
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateModule, RobotJointStateType};
+use crate::robot_modules::robot_kinematics_module::{JacobianEndPoint, JacobianMode, RobotKinematicsModule};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_nalgebra::conversions::NalgebraConversions;
+use crate::utils::utils_robot::robot_module_utils::RobotNames;
+use crate::utils::utils_se3::optima_se3_pose::OptimaSE3PoseType;
+use crate::utils::utils_shape_geometry::geometric_shape::{LogCondition, StopCondition};
+use crate::utils::utils_traits::ToAndFromRonString;
+
+/// Converts incremental Cartesian twist commands for a named frame (see
+/// `RobotKinematicsModule::resolve_frame`) into joint state deltas that are safe to apply, e.g., for
+/// jogging a robot from a UI.
+///
+/// Each call to `jog` computes resolved-rate joint velocities for the twist (see
+/// `RobotKinematicsModule::compute_resolved_rate_joint_velocities`), multiplies them by the time
+/// step, and then scales the delta down uniformly (so the direction of the frame's motion is kept)
+/// such that:
+/// - no joint overshoots its bounds, and joints within joint_limit_margin of a bound slow down in
+/// proportion to their remaining distance to it;
+/// - if collision clearance is enabled (see `enable_collision_clearance`), motions that reduce the
+/// robot's minimum self-collision distance slow down linearly from slowdown_distance and stop at
+/// stop_distance.  Motions that increase the distance are never slowed, so the robot can always be
+/// jogged out of a tight spot.
+#[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen, derive(Clone, Debug, Serialize, Deserialize))]
+pub struct CartesianJogger {
+    robot_kinematics_module: RobotKinematicsModule,
+    robot_joint_state_module: RobotJointStateModule,
+    frame_name: String,
+    damping: f64,
+    joint_limit_margin: f64,
+    joint_velocity_limits: Option<Vec<f64>>,
+    collision_clearance: Option<CartesianJogCollisionClearance>
+}
+impl CartesianJogger {
+    pub fn new_from_names(robot_names: RobotNames, frame_name: &str) -> Result<Self, OptimaError> {
+        let robot_configuration_module = RobotConfigurationModule::new_from_names(robot_names)?;
+        return Self::new(robot_configuration_module, frame_name);
+    }
+    pub fn new(robot_configuration_module: RobotConfigurationModule, frame_name: &str) -> Result<Self, OptimaError> {
+        let robot_kinematics_module = RobotKinematicsModule::new(robot_configuration_module.clone());
+        robot_kinematics_module.resolve_frame(frame_name)?;
+        let robot_joint_state_module = RobotJointStateModule::new(robot_configuration_module);
+
+        Ok(Self {
+            robot_kinematics_module,
+            robot_joint_state_module,
+            frame_name: frame_name.to_string(),
+            damping: 0.01,
+            joint_limit_margin: 0.1,
+            joint_velocity_limits: None,
+            collision_clearance: None
+        })
+    }
+    /// Sets the damping of the resolved-rate solve (default 0.01).
+    pub fn set_damping(&mut self, damping: f64) -> Result<(), OptimaError> {
+        if !(damping >= 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Damping must be nonnegative (given {}).", damping), file!(), line!()));
+        }
+        self.damping = damping;
+        Ok(())
+    }
+    /// Sets the distance from a joint bound within which joints slow down (default 0.1).  A margin
+    /// of 0.0 only prevents overshooting the bounds.
+    pub fn set_joint_limit_margin(&mut self, joint_limit_margin: f64) -> Result<(), OptimaError> {
+        if !(joint_limit_margin >= 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Joint limit margin must be nonnegative (given {}).", joint_limit_margin), file!(), line!()));
+        }
+        self.joint_limit_margin = joint_limit_margin;
+        Ok(())
+    }
+    /// Overrides the DOF velocity limits from the robot's URDF (None restores them).
+    pub fn set_joint_velocity_limits(&mut self, joint_velocity_limits: Option<Vec<f64>>) -> Result<(), OptimaError> {
+        if let Some(joint_velocity_limits) = &joint_velocity_limits {
+            if joint_velocity_limits.len() != self.robot_joint_state_module.num_dofs() {
+                return Err(OptimaError::new_robot_state_vec_wrong_size_error("set_joint_velocity_limits", joint_velocity_limits.len(), self.robot_joint_state_module.num_dofs(), file!(), line!()));
+            }
+        }
+        self.joint_velocity_limits = joint_velocity_limits;
+        Ok(())
+    }
+    /// Enables scaling by self-collision clearance, computed with the given link shape
+    /// representation.  Requires 0 <= stop_distance < slowdown_distance.
+    pub fn enable_collision_clearance(&mut self, robot_link_shape_representation: RobotLinkShapeRepresentation, slowdown_distance: f64, stop_distance: f64) -> Result<(), OptimaError> {
+        if !(stop_distance >= 0.0 && slowdown_distance > stop_distance) {
+            return Err(OptimaError::new_generic_error_str(&format!("Collision clearance distances must satisfy 0 <= stop_distance < slowdown_distance (given {} and {}).", stop_distance, slowdown_distance), file!(), line!()));
+        }
+        let robot_geometric_shape_module = RobotGeometricShapeModule::new(self.robot_kinematics_module.robot_configuration_module().clone(), false)?;
+        self.collision_clearance = Some(CartesianJogCollisionClearance {
+            robot_geometric_shape_module,
+            robot_link_shape_representation,
+            slowdown_distance,
+            stop_distance
+        });
+        Ok(())
+    }
+    pub fn disable_collision_clearance(&mut self) {
+        self.collision_clearance = None;
+    }
+    /// Returns the joint state delta for jogging the frame by the given twist (\[linear; angular\],
+    /// expressed in twist_frame) over a time step of dt.  The returned delta is a DOF delta; add it to
+    /// the DOF joint state to apply it.
+    pub fn jog(&self, joint_state: &RobotJointState, twist: &DVector<f64>, twist_frame: &CartesianJogFrame, dt: f64) -> Result<CartesianJogOutput, OptimaError> {
+        if twist.len() != 6 {
+            return Err(OptimaError::new_generic_error_str(&format!("Twist has length {}, but should have length 6.", twist.len()), file!(), line!()));
+        }
+        if !(dt > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Time step must be positive (given {}).", dt), file!(), line!()));
+        }
+        let joint_state = self.robot_joint_state_module.spawn_robot_joint_state(joint_state.joint_state().clone(), joint_state.robot_joint_state_type().clone())?;
+        let joint_state = self.robot_joint_state_module.convert_joint_state_to_dof_state(&joint_state)?;
+
+        let world_twist = match twist_frame {
+            CartesianJogFrame::World => { twist.clone() }
+            CartesianJogFrame::Tool => {
+                let pose = self.robot_kinematics_module.compute_frame_pose(&joint_state, &self.frame_name, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+                let rotation = pose.to_nalgebra_isometry().rotation;
+                let linear = rotation * twist.fixed_rows::<3>(0).into_owned();
+                let angular = rotation * twist.fixed_rows::<3>(3).into_owned();
+                DVector::from_column_slice(&[linear[0], linear[1], linear[2], angular[0], angular[1], angular[2]])
+            }
+        };
+
+        let (link_id, offset) = self.robot_kinematics_module.resolve_frame(&self.frame_name)?;
+        let end_point = match offset {
+            None => { JacobianEndPoint::Link }
+            Some(offset) => { JacobianEndPoint::Local(offset.to_nalgebra_isometry().translation.vector) }
+        };
+        let res = self.robot_kinematics_module.compute_resolved_rate_joint_velocities(&joint_state, link_id, &end_point, JacobianMode::Full, &world_twist, self.damping, self.joint_velocity_limits.as_ref())?;
+        let mut limiting_factor = if res.scale_factor() < 1.0 { CartesianJogLimit::VelocityLimit } else { CartesianJogLimit::None };
+        let delta = res.joint_velocities() * dt;
+
+        let mut scale_factor = 1.0;
+        let bounds = self.robot_joint_state_module.get_joint_state_bounds(&RobotJointStateType::DOF);
+        for (i, (lower, upper)) in bounds.iter().enumerate() {
+            let d = delta[i];
+            if d == 0.0 { continue; }
+            let room = if d > 0.0 { (upper - joint_state[i]).max(0.0) } else { (joint_state[i] - lower).max(0.0) };
+            let allowed = if self.joint_limit_margin > 0.0 { (d.abs() * (room / self.joint_limit_margin).min(1.0)).min(room) } else { d.abs().min(room) };
+            if allowed / d.abs() < scale_factor {
+                scale_factor = allowed / d.abs();
+                limiting_factor = CartesianJogLimit::JointLimit;
+            }
+        }
+
+        if let Some(collision_clearance) = &self.collision_clearance {
+            if scale_factor > 0.0 {
+                let current_distance = collision_clearance.minimum_distance(&joint_state)?;
+                let candidate = self.robot_joint_state_module.spawn_robot_joint_state(joint_state.joint_state() + &delta * scale_factor, RobotJointStateType::DOF)?;
+                let candidate_distance = collision_clearance.minimum_distance(&candidate)?;
+                if candidate_distance < current_distance {
+                    let s = ((candidate_distance - collision_clearance.stop_distance) / (collision_clearance.slowdown_distance - collision_clearance.stop_distance)).max(0.0).min(1.0);
+                    if s < 1.0 {
+                        scale_factor *= s;
+                        limiting_factor = CartesianJogLimit::CollisionClearance;
+                    }
+                }
+            }
+        }
+
+        Ok(CartesianJogOutput {
+            joint_state_delta: delta * scale_factor,
+            scale_factor: scale_factor * res.scale_factor(),
+            limiting_factor
+        })
+    }
+    /// Same as `jog`, but returns the resulting DOF joint state.
+    pub fn jog_and_apply(&self, joint_state: &RobotJointState, twist: &DVector<f64>, twist_frame: &CartesianJogFrame, dt: f64) -> Result<RobotJointState, OptimaError> {
+        let joint_state = self.robot_joint_state_module.spawn_robot_joint_state(joint_state.joint_state().clone(), joint_state.robot_joint_state_type().clone())?;
+        let joint_state = self.robot_joint_state_module.convert_joint_state_to_dof_state(&joint_state)?;
+        let res = self.jog(&joint_state, twist, twist_frame, dt)?;
+        let new_state = self.robot_joint_state_module.spawn_robot_joint_state(joint_state.joint_state() + res.joint_state_delta(), RobotJointStateType::DOF)?;
+        return Ok(self.robot_joint_state_module.clamp_joint_state_to_bounds(&new_state));
+    }
+    pub fn frame_name(&self) -> &str {
+        &self.frame_name
+    }
+    pub fn damping(&self) -> f64 {
+        self.damping
+    }
+    pub fn joint_limit_margin(&self) -> f64 {
+        self.joint_limit_margin
+    }
+    pub fn collision_clearance_enabled(&self) -> bool {
+        self.collision_clearance.is_some()
+    }
+    pub fn robot_kinematics_module(&self) -> &RobotKinematicsModule {
+        &self.robot_kinematics_module
+    }
+}
+
+/// Python implementations.
+#[cfg(not(target_arch = "wasm32"))]
+#[pymethods]
+impl CartesianJogger {
+    #[new]
+    pub fn new_py(robot_name: &str, frame_name: &str, configuration_name: Option<&str>) -> Self {
+        return Self::new_from_names(RobotNames::new(robot_name, configuration_name), frame_name).expect("error");
+    }
+    pub fn set_damping_py(&mut self, damping: f64) {
+        self.set_damping(damping).expect("error");
+    }
+    pub fn set_joint_limit_margin_py(&mut self, joint_limit_margin: f64) {
+        self.set_joint_limit_margin(joint_limit_margin).expect("error");
+    }
+    pub fn set_joint_velocity_limits_py(&mut self, joint_velocity_limits: Option<Vec<f64>>) {
+        self.set_joint_velocity_limits(joint_velocity_limits).expect("error");
+    }
+    #[args(robot_link_shape_representation = "\"Cubes\"")]
+    pub fn enable_collision_clearance_py(&mut self, slowdown_distance: f64, stop_distance: f64, robot_link_shape_representation: &str) {
+        self.enable_collision_clearance(RobotLinkShapeRepresentation::from_ron_string(robot_link_shape_representation).expect("error"), slowdown_distance, stop_distance).expect("error");
+    }
+    pub fn disable_collision_clearance_py(&mut self) {
+        self.disable_collision_clearance();
+    }
+    /// Returns the DOF joint state delta and the overall scale factor that was applied to it.
+    #[args(twist_frame = "\"World\"")]
+    pub fn jog_py(&self, joint_state: Vec<f64>, twist: Vec<f64>, dt: f64, twist_frame: &str) -> (Vec<f64>, f64) {
+        let joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
+        let res = self.jog(&joint_state, &NalgebraConversions::vec_to_dvector(&twist), &CartesianJogFrame::from_ron_string(twist_frame).expect("error"), dt).expect("error");
+        return (NalgebraConversions::dvector_to_vec(res.joint_state_delta()), res.scale_factor());
+    }
+    #[args(twist_frame = "\"World\"")]
+    pub fn jog_and_apply_py(&self, joint_state: Vec<f64>, twist: Vec<f64>, dt: f64, twist_frame: &str) -> Vec<f64> {
+        let joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
+        let res = self.jog_and_apply(&joint_state, &NalgebraConversions::vec_to_dvector(&twist), &CartesianJogFrame::from_ron_string(twist_frame).expect("error"), dt).expect("error");
+        return NalgebraConversions::dvector_to_vec(res.joint_state());
+    }
+}
+
+/// WASM implementations.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl CartesianJogger {
+    #[wasm_bindgen(constructor)]
+    pub fn new_wasm(robot_name: String, frame_name: String, configuration_name: Option<String>) -> CartesianJogger {
+        let robot_names = match &configuration_name {
+            None => { RobotNames::new_base(&robot_name) }
+            Some(configuration_name) => { RobotNames::new(&robot_name, Some(configuration_name)) }
+        };
+        return Self::new_from_names(robot_names, &frame_name).expect("error");
+    }
+    pub fn set_joint_limit_margin_wasm(&mut self, joint_limit_margin: f64) {
+        self.set_joint_limit_margin(joint_limit_margin).expect("error");
+    }
+    pub fn enable_collision_clearance_wasm(&mut self, slowdown_distance: f64, stop_distance: f64, robot_link_shape_representation: &str) {
+        self.enable_collision_clearance(RobotLinkShapeRepresentation::from_ron_string(robot_link_shape_representation).expect("error"), slowdown_distance, stop_distance).expect("error");
+    }
+    /// Returns the DOF joint state delta.
+    pub fn jog_wasm(&self, joint_state: Vec<f64>, twist: Vec<f64>, dt: f64, twist_frame: &str) -> Vec<f64> {
+        let joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
+        let res = self.jog(&joint_state, &NalgebraConversions::vec_to_dvector(&twist), &CartesianJogFrame::from_ron_string(twist_frame).expect("error"), dt).expect("error");
+        return NalgebraConversions::dvector_to_vec(res.joint_state_delta());
+    }
+    pub fn jog_and_apply_wasm(&self, joint_state: Vec<f64>, twist: Vec<f64>, dt: f64, twist_frame: &str) -> Vec<f64> {
+        let joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
+        let res = self.jog_and_apply(&joint_state, &NalgebraConversions::vec_to_dvector(&twist), &CartesianJogFrame::from_ron_string(twist_frame).expect("error"), dt).expect("error");
+        return NalgebraConversions::dvector_to_vec(res.joint_state());
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CartesianJogCollisionClearance {
+    robot_geometric_shape_module: RobotGeometricShapeModule,
+    robot_link_shape_representation: RobotLinkShapeRepresentation,
+    slowdown_distance: f64,
+    stop_distance: f64
+}
+impl CartesianJogCollisionClearance {
+    fn minimum_distance(&self, joint_state: &RobotJointState) -> Result<f64, OptimaError> {
+        let input = RobotShapeCollectionQuery::Distance { robot_joint_state: joint_state, inclusion_list: &None };
+        let res = self.robot_geometric_shape_module.shape_collection_query(&input, self.robot_link_shape_representation.clone(), StopCondition::None, LogCondition::Intersection, false)?;
+        return Ok(res.minimum_distance());
+    }
+}
+
+/// The frame that a jogging twist is expressed in.  Tool twists are expressed in the orientation of
+/// the jogged frame (but still about the frame's origin).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CartesianJogFrame {
+    World,
+    Tool
+}
+
+/// What limited a jog step the most.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CartesianJogLimit {
+    None,
+    VelocityLimit,
+    JointLimit,
+    CollisionClearance
+}
+
+/// Output of `CartesianJogger::jog`.  scale_factor is the overall uniform factor (in \[0, 1\]) that
+/// was applied to the unconstrained resolved-rate delta.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CartesianJogOutput {
+    joint_state_delta: DVector<f64>,
+    scale_factor: f64,
+    limiting_factor: CartesianJogLimit
+}
+impl CartesianJogOutput {
+    pub fn joint_state_delta(&self) -> &DVector<f64> {
+        &self.joint_state_delta
+    }
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+    pub fn limiting_factor(&self) -> &CartesianJogLimit {
+        &self.limiting_factor
+    }
+}
+
+crate::impl_py_pickle!(CartesianJogger);
@@ -4,19 +4,23 @@ use pyo3::*;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+use nalgebra::Vector3;
 use serde::{Serialize, Deserialize};
 use crate::robot_modules::robot_model_module::RobotModelModule;
+use crate::robot_modules::robot_module_cache::RobotModuleCache;
 use crate::utils::utils_console::{ConsoleInputUtils, optima_print, PrintColor, PrintMode};
-use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3Pose, OptimaSE3PoseAll, OptimaSE3PosePy};
+use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3Pose, OptimaSE3PoseAll, OptimaSE3PosePy, OptimaSE3PoseType};
 use crate::utils::utils_errors::OptimaError;
-use crate::utils::utils_files::optima_path::{load_object_from_json_string, OptimaAssetLocation, OptimaStemCellPath};
+use crate::utils::utils_files::optima_path::{OptimaAssetLocation, OptimaStemCellPath};
+use crate::utils::utils_generic_data_structures::SharedHandle;
+use crate::utils::utils_robot::robot_ids::{JointId, LinkId};
 use crate::utils::utils_robot::robot_module_utils::RobotNames;
-use crate::utils::utils_traits::SaveAndLoadable;
+use crate::utils::utils_traits::{SaveAndLoadable, ToAndFromJsonString, ToAndFromTomlString, ToAndFromYamlString};
 
 /// A `RobotConfigurationModule` is a description of a robot model one abstraction layer above the
 /// `RobotModelModule`.  A robot configuration affords extra specificity and functionality over a robot
 /// model.  For example, a robot configuration can include a mobile base, a base offset, removed links,
-/// as well as fixed joint values that will not be considered degrees of freedom.
+/// as well as fixed joint values and joint coupling constraints that will not be considered degrees of freedom.
 ///
 /// A robot configuration consists of two components:
 /// - A `RobotConfigurationInfo` object that describes key features of the particular configuration.
@@ -24,35 +28,55 @@ use crate::utils::utils_traits::SaveAndLoadable;
 ///
 /// In many cases, the `RobotConfigurationInfo` will reflect a default base model configuration, meaning
 /// its respective configuration will be the base robot model given directly by the robot's URDF.
+///
+/// A `RobotConfigurationInfo` can also name a parent configuration that it extends.  In this case,
+/// the info only needs to hold its own additional overrides, and the full configuration is resolved
+/// by layering the info on top of its (recursively resolved) parent when the model is generated.
+/// The resolved parent is read from disk once and cached, so setters do not re-read the parent chain
+/// (see `reload_parent_configuration`).
+///
+/// Both the base model and the configured model are held in `SharedHandle`s, so clones of a
+/// configuration module share one copy of each model.
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen, derive(Clone, Debug, Serialize, Deserialize))]
 #[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
 pub struct RobotConfigurationModule {
     robot_configuration_info: RobotConfigurationInfo,
-    robot_model_module: RobotModelModule,
-    base_robot_model_module: RobotModelModule
+    resolved_robot_configuration_info: RobotConfigurationInfo,
+    #[serde(skip)]
+    resolved_parent_robot_configuration_info: Option<(String, RobotConfigurationInfo)>,
+    robot_model_module: SharedHandle<RobotModelModule>,
+    base_robot_model_module: SharedHandle<RobotModelModule>
 }
 impl RobotConfigurationModule {
     pub fn new_from_names(robot_names: RobotNames) -> Result<Self, OptimaError> {
         return match robot_names.configuration_name() {
             None => { Self::new_base_model(robot_names.robot_name()) }
             Some(configuration_name) => {
-                let mut path = OptimaStemCellPath::new_asset_path()?;
-                path.append_file_location(&OptimaAssetLocation::RobotConfigurations { robot_name: robot_names.robot_name().to_string() });
-                path.append(&(configuration_name.to_string() + ".JSON"));
-
-                if !path.exists() {
-                    return Err(OptimaError::new_generic_error_str(&format!("Robot {} does not have configuration {} at path {:?}.", robot_names.robot_name(), configuration_name, path), file!(), line!()))
-                }
-
-                return Self::load_from_path(&path);
+                let robot_configuration_info = RobotConfigurationInfo::load_unresolved(robot_names.robot_name(), configuration_name)?;
+                return Self::new_from_robot_name_and_info(robot_names.robot_name(), robot_configuration_info);
             }
         }
     }
+    /// The module is loaded through the process-wide `RobotModuleCache`, and the returned module is
+    /// an owned copy of the cached module.  Use `new_shared_from_robot_name_and_info` to avoid the
+    /// copy when read-only access is enough.
     pub fn new_from_robot_name_and_info(robot_name: &str, robot_configuration_info: RobotConfigurationInfo) -> Result<Self, OptimaError> {
-        let base_model_module = RobotModelModule::new(robot_name)?;
+        let handle = RobotModuleCache::get_or_load_robot_configuration_module(robot_name, &robot_configuration_info)?;
+        return Ok(handle.to_owned_inner());
+    }
+    /// Same as `new_from_robot_name_and_info`, but returns the handle held by the `RobotModuleCache`
+    /// rather than a copy.
+    pub fn new_shared_from_robot_name_and_info(robot_name: &str, robot_configuration_info: RobotConfigurationInfo) -> Result<SharedHandle<Self>, OptimaError> {
+        return RobotModuleCache::get_or_load_robot_configuration_module(robot_name, &robot_configuration_info);
+    }
+    /// Same as `new_from_robot_name_and_info`, but bypasses the process-wide `RobotModuleCache`.
+    pub fn new_from_robot_name_and_info_uncached(robot_name: &str, robot_configuration_info: RobotConfigurationInfo) -> Result<Self, OptimaError> {
+        let base_model_module = RobotModelModule::new_shared(robot_name)?;
 
         let mut out_self = Self {
-            robot_configuration_info,
+            robot_configuration_info: robot_configuration_info.clone(),
+            resolved_robot_configuration_info: robot_configuration_info,
+            resolved_parent_robot_configuration_info: None,
             robot_model_module: base_model_module.clone(),
             base_robot_model_module: base_model_module
         };
@@ -61,37 +85,77 @@ impl RobotConfigurationModule {
 
         return Ok(out_self);
     }
+    /// Returns the base configuration of the given model, e.g., a model that was built with
+    /// `RobotModelBuilder` rather than loaded from the optima_assets directory.
+    pub fn new_from_robot_model_module(robot_model_module: RobotModelModule) -> Self {
+        let robot_model_module = SharedHandle::new(robot_model_module);
+        Self {
+            robot_configuration_info: Default::default(),
+            resolved_robot_configuration_info: Default::default(),
+            resolved_parent_robot_configuration_info: None,
+            robot_model_module: robot_model_module.clone(),
+            base_robot_model_module: robot_model_module
+        }
+    }
     fn new_base_model(robot_name: &str) -> Result<Self, OptimaError> {
-        let robot_model_module = RobotModelModule::new(robot_name)?;
+        let robot_model_module = RobotModelModule::new_shared(robot_name)?;
         Ok(Self {
             robot_configuration_info: Default::default(),
+            resolved_robot_configuration_info: Default::default(),
+            resolved_parent_robot_configuration_info: None,
             robot_model_module: robot_model_module.clone(),
             base_robot_model_module: robot_model_module
         })
     }
+    /// Re-reads the parent configuration chain from disk (e.g., after a parent configuration file was
+    /// edited) and re-derives the configuration's model.
+    pub fn reload_parent_configuration(&mut self) -> Result<(), OptimaError> {
+        self.resolved_parent_robot_configuration_info = None;
+        return self.update();
+    }
     fn update(&mut self) -> Result<(), OptimaError> {
-        let mut robot_model_module = self.base_robot_model_module.clone();
-        let robot_configuration_info = &self.robot_configuration_info;
+        let mut robot_model_module = self.base_robot_model_module.to_owned_inner();
+        let mut robot_configuration_info = match self.robot_configuration_info.parent_configuration_name.clone() {
+            None => {
+                self.resolved_parent_robot_configuration_info = None;
+                self.robot_configuration_info.clone()
+            }
+            Some(parent_configuration_name) => {
+                let cached = match &self.resolved_parent_robot_configuration_info {
+                    Some((name, resolved_parent)) if name == &parent_configuration_name => { Some(resolved_parent.clone()) }
+                    _ => { None }
+                };
+                let resolved_parent = match cached {
+                    Some(resolved_parent) => { resolved_parent }
+                    None => {
+                        let resolved_parent = RobotConfigurationInfo::load_resolved(self.base_robot_model_module.robot_name(), &parent_configuration_name)?;
+                        self.resolved_parent_robot_configuration_info = Some((parent_configuration_name, resolved_parent.clone()));
+                        resolved_parent
+                    }
+                };
+                self.robot_configuration_info.layer_on_top_of(&resolved_parent)
+            }
+        };
 
-        let contiguous_chain_infos = &robot_configuration_info.contiguous_chain_infos;
+        let contiguous_chain_infos = robot_configuration_info.contiguous_chain_infos.clone();
         if contiguous_chain_infos.len() > 0 {
             // Set all links as not present.
-            let num_links = robot_model_module.links().len();
-            for link_idx in 0..num_links {
-                robot_model_module.set_link_as_not_present(link_idx)?;
+            let link_ids: Vec<LinkId> = robot_model_module.links().iter().map(|link| link.link_id()).collect();
+            for link_id in link_ids {
+                robot_model_module.set_link_as_not_present(link_id)?;
             }
         }
 
         let mut names_to_remove = vec![];
 
-        let mut link_idxs_that_are_already_a_part_of_chains = vec![];
-        for contiguous_chain_info in contiguous_chain_infos {
-            let link_idxs_to_possibly_add = match contiguous_chain_info.end_link_idx {
+        let mut link_ids_that_are_already_a_part_of_chains = vec![];
+        for contiguous_chain_info in &contiguous_chain_infos {
+            let link_ids_to_possibly_add = match contiguous_chain_info.end_link_idx {
                 None => {
-                    self.robot_model_module.get_all_downstream_links(contiguous_chain_info.start_link_idx)?
+                    self.robot_model_module.get_all_downstream_links(LinkId::new(contiguous_chain_info.start_link_idx))?
                 }
                 Some(end_link_idx) => {
-                    let chain = self.robot_model_module.get_link_chain(contiguous_chain_info.start_link_idx, end_link_idx)?;
+                    let chain = self.robot_model_module.get_link_chain(LinkId::new(contiguous_chain_info.start_link_idx), LinkId::new(end_link_idx))?;
                     if chain.is_none() { return Err(OptimaError::new_generic_error_str(&format!("Link chain does not exist between link {} and {}.", contiguous_chain_info.start_link_idx, end_link_idx), file!(), line!())) }
                     let chain = chain.unwrap().clone();
                     chain
@@ -99,9 +163,9 @@ impl RobotConfigurationModule {
             };
 
             let mut clash = false;
-            for link_idx_to_possibly_add in &link_idxs_to_possibly_add {
-                for link_idx_that_is_already_a_part_of_chains in &link_idxs_that_are_already_a_part_of_chains {
-                    if link_idx_to_possibly_add == link_idx_that_is_already_a_part_of_chains {
+            for link_id_to_possibly_add in &link_ids_to_possibly_add {
+                for link_id_that_is_already_a_part_of_chains in &link_ids_that_are_already_a_part_of_chains {
+                    if link_id_to_possibly_add == link_id_that_is_already_a_part_of_chains {
                         clash = true;
                         break;
                     }
@@ -109,9 +173,9 @@ impl RobotConfigurationModule {
             }
 
             if !clash {
-                for link_idx in &link_idxs_to_possibly_add {
-                    link_idxs_that_are_already_a_part_of_chains.push(*link_idx);
-                    robot_model_module.set_link_as_present(*link_idx)?;
+                for link_id in &link_ids_to_possibly_add {
+                    link_ids_that_are_already_a_part_of_chains.push(*link_id);
+                    robot_model_module.set_link_as_present(*link_id)?;
                 }
                 robot_model_module.add_contiguous_chain_link_and_joint(&contiguous_chain_info.mobility_mode, contiguous_chain_info.start_link_idx);
             } else {
@@ -123,7 +187,7 @@ impl RobotConfigurationModule {
 
         let dead_end_link_idxs = &robot_configuration_info.dead_end_link_idxs;
         for d in dead_end_link_idxs {
-            let all_downstream_links = robot_model_module.get_all_downstream_links(*d)?;
+            let all_downstream_links = robot_model_module.get_all_downstream_links(LinkId::new(*d))?;
             for dl in &all_downstream_links {
                 robot_model_module.set_link_as_not_present(*dl)?;
             }
@@ -131,24 +195,75 @@ impl RobotConfigurationModule {
 
         let fixed = &robot_configuration_info.fixed_joint_infos;
         for f in fixed {
-            robot_model_module.set_fixed_joint_sub_dof(f.joint_idx, f.joint_sub_idx, Some(f.fixed_joint_value))?;
+            robot_model_module.set_fixed_joint_sub_dof(JointId::new(f.joint_idx), f.joint_sub_idx, Some(f.fixed_joint_value))?;
+        }
+
+        let joint_coupling_infos = &robot_configuration_info.joint_coupling_infos;
+        for c in joint_coupling_infos {
+            Self::check_joint_coupling(&robot_model_module, c, joint_coupling_infos)?;
+            robot_model_module.set_coupled_joint_sub_dof(JointId::new(c.joint_idx), c.joint_sub_idx, Some(c.clone()))?;
+        }
+
+        for name_to_remove in &names_to_remove {
+            self.remove_contiguous_chain(name_to_remove);
+            robot_configuration_info.contiguous_chain_infos.retain(|c| &c.chain_name != name_to_remove);
         }
+        self.resolved_robot_configuration_info = robot_configuration_info;
+        self.robot_model_module = SharedHandle::new(robot_model_module);
 
-        for name_to_remove in &names_to_remove { self.remove_contiguous_chain(name_to_remove); }
-        self.robot_model_module = robot_model_module;
+        Ok(())
+    }
+    fn check_joint_coupling(robot_model_module: &RobotModelModule, joint_coupling_info: &JointCouplingInfo, all_joint_coupling_infos: &Vec<JointCouplingInfo>) -> Result<(), OptimaError> {
+        let joints = robot_model_module.joints();
+        let check_joint_axis = |joint_idx: usize, joint_sub_idx: usize| -> Result<(), OptimaError> {
+            OptimaError::new_check_for_idx_out_of_bound_error(joint_idx, joints.len(), file!(), line!())?;
+            OptimaError::new_check_for_idx_out_of_bound_error(joint_sub_idx, joints[joint_idx].joint_axes().len(), file!(), line!())?;
+            if !joints[joint_idx].active() {
+                return Err(OptimaError::new_generic_error_str(&format!("Joint coupling refers to joint {} ({}), which is not present in the configuration.", joint_idx, joints[joint_idx].name()), file!(), line!()));
+            }
+            Ok(())
+        };
+
+        check_joint_axis(joint_coupling_info.joint_idx, joint_coupling_info.joint_sub_idx)?;
+        if joints[joint_coupling_info.joint_idx].joint_axes()[joint_coupling_info.joint_sub_idx].is_fixed() {
+            return Err(OptimaError::new_generic_error_str(&format!("Joint {} sub idx {} cannot be both fixed and coupled.", joint_coupling_info.joint_idx, joint_coupling_info.joint_sub_idx), file!(), line!()));
+        }
+
+        for term in &joint_coupling_info.terms {
+            check_joint_axis(term.joint_idx, term.joint_sub_idx)?;
+            if all_joint_coupling_infos.iter().any(|c| c.refers_to_joint_axis(term.joint_idx, term.joint_sub_idx)) {
+                return Err(OptimaError::new_generic_error_str(&format!("Coupling of joint {} sub idx {} depends on joint {} sub idx {}, which is itself coupled.  Coupling constraints cannot be chained.", joint_coupling_info.joint_idx, joint_coupling_info.joint_sub_idx, term.joint_idx, term.joint_sub_idx), file!(), line!()));
+            }
+        }
 
         Ok(())
     }
     /// Returns a reference to the `RobotConfigurationInfo` that was used to change the configuration's
-    /// underlying model module.
+    /// underlying model module.  If the configuration extends a parent configuration, this is the
+    /// fully resolved info (i.e., the parent's info with this configuration's overrides layered on top).
     pub fn robot_configuration_info(&self) -> &RobotConfigurationInfo {
+        &self.resolved_robot_configuration_info
+    }
+    /// Returns a reference to this configuration's own `RobotConfigurationInfo` layer, prior to being
+    /// resolved against any parent configuration.  This is the info that will be saved.
+    pub fn robot_configuration_info_layer(&self) -> &RobotConfigurationInfo {
         &self.robot_configuration_info
     }
+    /// Sets the named configuration that this configuration extends.  This configuration's info will be
+    /// layered on top of the parent's info when the model is generated.  If None, the configuration
+    /// will extend the robot's base model.
+    pub fn set_parent_configuration(&mut self, parent_configuration_name: Option<&str>) -> Result<(), OptimaError> {
+        self.robot_configuration_info.parent_configuration_name = match parent_configuration_name {
+            None => { None }
+            Some(parent_configuration_name) => { Some(parent_configuration_name.to_string()) }
+        };
+        return self.update();
+    }
     /// Returns a reference to the robot model module that reflects the configuration's `RobotConfigurationInfo`.
     pub fn robot_model_module(&self) -> &RobotModelModule {
         &self.robot_model_module
     }
-    pub fn set_contiguous_chain(&mut self, chain_name: &str, start_link_idx: usize, end_link_idx: Option<usize>, mobility_mode: ContiguousChainMobilityMode) -> Result<(), OptimaError> {
+    pub fn set_contiguous_chain(&mut self, chain_name: &str, start_link_id: LinkId, end_link_id: Option<LinkId>, mobility_mode: ContiguousChainMobilityMode) -> Result<(), OptimaError> {
         for c in &self.robot_configuration_info.contiguous_chain_infos {
             if &c.chain_name == chain_name {
                 let print_string = format!("WARNING: Could not add contiguous chain {:?} because its name conflicts with an already added chain.", chain_name);
@@ -159,8 +274,8 @@ impl RobotConfigurationModule {
 
         self.robot_configuration_info.contiguous_chain_infos.push(ContiguousChainInfo {
             chain_name: chain_name.to_string(),
-            start_link_idx,
-            end_link_idx,
+            start_link_idx: start_link_id.idx(),
+            end_link_idx: end_link_id.map(|id| id.idx()),
             mobility_mode
         });
 
@@ -178,12 +293,18 @@ impl RobotConfigurationModule {
     /// Sets the given link as a "dead end" link.  A dead end link is a link such that it and all
     /// links that occur as successors in the kinematic chain will be inactive (essentially, removed)
     /// from the robot model.
-    pub fn set_dead_end_link(&mut self, link_idx: usize) -> Result<(), OptimaError> {
-        self.robot_configuration_info.dead_end_link_idxs.push(link_idx);
+    pub fn set_dead_end_link(&mut self, link_id: LinkId) -> Result<(), OptimaError> {
+        self.robot_configuration_info.dead_end_link_idxs.push(link_id.idx());
         return self.update();
     }
-    /// Removes the given link as a dead end link.
-    pub fn remove_dead_end_link(&mut self, link_idx: usize) -> Result<(), OptimaError> {
+    /// Removes the given link as a dead end link.  If the dead end link is inherited from the parent
+    /// configuration, this configuration will override the parent's dead end links from now on (see
+    /// `set_field_overridden`).
+    pub fn remove_dead_end_link(&mut self, link_id: LinkId) -> Result<(), OptimaError> {
+        let link_idx = link_id.idx();
+        if self.is_inherited(&RobotConfigurationInfoField::DeadEndLinks, |i| i.dead_end_link_idxs.contains(&link_idx)) {
+            self.set_field_overridden(&RobotConfigurationInfoField::DeadEndLinks, true)?;
+        }
         self.robot_configuration_info.dead_end_link_idxs =
             self.robot_configuration_info.dead_end_link_idxs
             .iter().filter_map(|s| if *s == link_idx { None } else { Some(*s) } ).collect();
@@ -191,27 +312,208 @@ impl RobotConfigurationModule {
     }
     /// Fixes the given joint to the given value.  Thus, this joint will not be a degree of freedom
     /// in the current configuration.
-    pub fn set_fixed_joint(&mut self, joint_idx: usize, joint_sub_idx: usize, fixed_joint_value: f64) -> Result<(), OptimaError> {
+    pub fn set_fixed_joint(&mut self, joint_id: JointId, joint_sub_idx: usize, fixed_joint_value: f64) -> Result<(), OptimaError> {
         self.robot_configuration_info.fixed_joint_infos.push(FixedJointInfo {
-            joint_idx,
+            joint_idx: joint_id.idx(),
             joint_sub_idx,
             fixed_joint_value
         });
         return self.update();
     }
     /// Removes the given joint as a fixed joint.  Thus, this joint will become a degree of freedom.
-    pub fn remove_fixed_joint(&mut self, joint_idx: usize, joint_sub_idx: usize) -> Result<(), OptimaError> {
+    /// If the fixed joint is inherited from the parent configuration, this configuration will
+    /// override the parent's fixed joints from now on (see `set_field_overridden`).
+    pub fn remove_fixed_joint(&mut self, joint_id: JointId, joint_sub_idx: usize) -> Result<(), OptimaError> {
+        let joint_idx = joint_id.idx();
+        if self.is_inherited(&RobotConfigurationInfoField::FixedJoints, |i| i.fixed_joint_infos.iter().any(|f| f.joint_idx == joint_idx && f.joint_sub_idx == joint_sub_idx)) {
+            self.set_field_overridden(&RobotConfigurationInfoField::FixedJoints, true)?;
+        }
         self.robot_configuration_info.fixed_joint_infos =
             self.robot_configuration_info.fixed_joint_infos
                 .iter().filter_map(|s| if s.joint_idx == joint_idx && s.joint_sub_idx == joint_sub_idx { None } else { Some(s.clone()) } ).collect();
 
         return self.update();
     }
-    /// sets the base offset of the robot configuration.
+    /// Couples the given joint axis to other joint axes (see `JointCouplingInfo`).  Thus, this joint
+    /// axis will not be a degree of freedom in the current configuration, and its value will be
+    /// offset + sum(coefficient * value) over the given terms.  If the joint axis is already coupled,
+    /// its coupling will be replaced.
+    pub fn set_joint_coupling(&mut self, joint_id: JointId, joint_sub_idx: usize, terms: Vec<JointCouplingTerm>, offset: f64) -> Result<(), OptimaError> {
+        let joint_idx = joint_id.idx();
+        self.robot_configuration_info.joint_coupling_infos.retain(|c| !c.refers_to_joint_axis(joint_idx, joint_sub_idx));
+        self.robot_configuration_info.joint_coupling_infos.push(JointCouplingInfo {
+            joint_idx,
+            joint_sub_idx,
+            terms,
+            offset
+        });
+        return self.update();
+    }
+    /// Removes the coupling of the given joint axis.  Thus, this joint axis will become a degree of freedom.
+    /// If the coupling is inherited from the parent configuration, this configuration will override
+    /// the parent's joint couplings from now on (see `set_field_overridden`).
+    pub fn remove_joint_coupling(&mut self, joint_id: JointId, joint_sub_idx: usize) -> Result<(), OptimaError> {
+        let joint_idx = joint_id.idx();
+        if self.is_inherited(&RobotConfigurationInfoField::JointCouplings, |i| i.joint_coupling_infos.iter().any(|c| c.refers_to_joint_axis(joint_idx, joint_sub_idx))) {
+            self.set_field_overridden(&RobotConfigurationInfoField::JointCouplings, true)?;
+        }
+        self.robot_configuration_info.joint_coupling_infos.retain(|c| !c.refers_to_joint_axis(joint_idx, joint_sub_idx));
+        return self.update();
+    }
+    /// Sets the base offset of the robot configuration, i.e., the fixed mounting transform of the
+    /// robot's base in the world (table height, wall or ceiling mounts, etc.).  It is the pose given
+    /// to the root link in forward kinematics, so it carries through to everything built on FK
+    /// (Jacobians, IK targets, shape and scene collision queries) without changing the URDF.  With a
+    /// mobile base, the base's motion is relative to this transform.  If the configuration extends a
+    /// parent configuration, the given base offset replaces the parent's (even if it is the identity).
     pub fn set_base_offset(&mut self, p: &OptimaSE3Pose) -> Result<(), OptimaError> {
         self.robot_configuration_info.base_offset = OptimaSE3PoseAll::new(p);
+        if self.robot_configuration_info.parent_configuration_name.is_some() {
+            self.robot_configuration_info.set_field_overridden(&RobotConfigurationInfoField::BaseOffset, true);
+        }
+        return self.update();
+    }
+    /// Adds a named tool center point (TCP) frame to the configuration.  A TCP frame is a fixed
+    /// SE(3) offset from the given link (e.g., the tip of a gripper or tool mounted on the robot's
+    /// flange).  TCP frames will be returned as additional entries in forward kinematics results.
+    /// TCP frames also serve as general named auxiliary frames (e.g., a camera optical frame) that
+    /// can be targeted by name in kinematics queries (see `RobotKinematicsModule::resolve_frame`).
+    /// If a TCP frame with the given name already exists, it will be replaced.
+    pub fn set_tcp_frame(&mut self, tcp_frame_name: &str, link_id: LinkId, offset: &OptimaSE3Pose) -> Result<(), OptimaError> {
+        let link_idx = link_id.idx();
+        let num_links = self.base_robot_model_module.links().len();
+        OptimaError::new_check_for_idx_out_of_bound_error(link_idx, num_links, file!(), line!())?;
+
+        let tcp_frame_info = TCPFrameInfo {
+            tcp_frame_name: tcp_frame_name.to_string(),
+            link_idx,
+            offset: OptimaSE3PoseAll::new(offset)
+        };
+
+        for t in &mut self.robot_configuration_info.tcp_frame_infos {
+            if &t.tcp_frame_name == tcp_frame_name {
+                *t = tcp_frame_info;
+                return self.update();
+            }
+        }
+
+        self.robot_configuration_info.tcp_frame_infos.push(tcp_frame_info);
+        return self.update();
+    }
+    /// Removes the TCP frame with the given name, if it exists.  If the TCP frame is inherited from the
+    /// parent configuration, this configuration will override the parent's TCP frames from now on
+    /// (see `set_field_overridden`).
+    pub fn remove_tcp_frame(&mut self, tcp_frame_name: &str) -> Result<(), OptimaError> {
+        if self.is_inherited(&RobotConfigurationInfoField::TCPFrames, |i| i.tcp_frame_infos.iter().any(|t| t.tcp_frame_name == tcp_frame_name)) {
+            self.set_field_overridden(&RobotConfigurationInfoField::TCPFrames, true)?;
+        }
+        self.robot_configuration_info.tcp_frame_infos =
+            self.robot_configuration_info.tcp_frame_infos
+                .iter().filter_map(|s| if &s.tcp_frame_name == tcp_frame_name { None } else { Some(s.clone()) } ).collect();
+        return self.update();
+    }
+    /// Sets the lower and upper bounds of all axes of the given joint in the configuration's base
+    /// model (e.g., to apply calibration results without re-authoring the URDF), and re-derives the
+    /// configuration's model.  Joints added by the configuration itself (i.e., mobile base connector
+    /// joints) get their bounds from their `ContiguousChainMobilityMode` instead.
+    pub fn set_joint_limits(&mut self, joint_id: JointId, lower: f64, upper: f64) -> Result<(), OptimaError> {
+        self.base_robot_model_module.make_mut().set_joint_limits(joint_id, lower, upper)?;
+        return self.update();
+    }
+    /// Sets the lower and upper bounds of a single axis of the given joint in the configuration's base
+    /// model, and re-derives the configuration's model.
+    pub fn set_joint_sub_dof_limits(&mut self, joint_id: JointId, joint_sub_idx: usize, lower: f64, upper: f64) -> Result<(), OptimaError> {
+        self.base_robot_model_module.make_mut().set_joint_sub_dof_limits(joint_id, joint_sub_idx, lower, upper)?;
+        return self.update();
+    }
+    /// Sets the axis of the given joint in the configuration's base model, and re-derives the
+    /// configuration's model.
+    pub fn set_joint_axis(&mut self, joint_id: JointId, axis: Vector3<f64>) -> Result<(), OptimaError> {
+        self.base_robot_model_module.make_mut().set_joint_axis(joint_id, axis)?;
+        return self.update();
+    }
+    /// Sets the origin offset of the given joint in the configuration's base model, and re-derives the
+    /// configuration's model.
+    pub fn set_joint_origin(&mut self, joint_id: JointId, origin_xyz: Vector3<f64>, origin_rpy: Vector3<f64>) -> Result<(), OptimaError> {
+        self.base_robot_model_module.make_mut().set_joint_origin(joint_id, origin_xyz, origin_rpy)?;
         return self.update();
     }
+    /// Sets the soft joint limit margin of the configuration (see `SoftJointLimitMargin`).  If None,
+    /// the soft limits are the same as the hard limits.  If the configuration extends a parent
+    /// configuration, the given margin replaces the parent's (even if it is None).
+    pub fn set_soft_joint_limit_margin(&mut self, soft_joint_limit_margin: Option<SoftJointLimitMargin>) -> Result<(), OptimaError> {
+        self.robot_configuration_info.soft_joint_limit_margin = soft_joint_limit_margin;
+        if self.robot_configuration_info.parent_configuration_name.is_some() {
+            self.robot_configuration_info.set_field_overridden(&RobotConfigurationInfoField::SoftJointLimitMargin, true);
+        }
+        return self.update();
+    }
+    /// Adds a named joint weighting profile to the configuration (see `JointWeightingProfile`).  If a
+    /// profile with the same name already exists, it will be replaced.
+    pub fn set_joint_weighting_profile(&mut self, joint_weighting_profile: JointWeightingProfile) -> Result<(), OptimaError> {
+        let joints = self.base_robot_model_module.joints();
+        for w in &joint_weighting_profile.joint_axis_weights {
+            OptimaError::new_check_for_idx_out_of_bound_error(w.joint_idx, joints.len(), file!(), line!())?;
+            OptimaError::new_check_for_idx_out_of_bound_error(w.joint_sub_idx, joints[w.joint_idx].joint_axes().len(), file!(), line!())?;
+        }
+
+        self.robot_configuration_info.joint_weighting_profiles.retain(|p| p.name != joint_weighting_profile.name);
+        self.robot_configuration_info.joint_weighting_profiles.push(joint_weighting_profile);
+        return self.update();
+    }
+    /// Removes the joint weighting profile with the given name, if it exists.  If the profile is
+    /// inherited from the parent configuration, this configuration will override the parent's
+    /// profiles from now on (see `set_field_overridden`).
+    pub fn remove_joint_weighting_profile(&mut self, profile_name: &str) -> Result<(), OptimaError> {
+        if self.is_inherited(&RobotConfigurationInfoField::JointWeightingProfiles, |i| i.joint_weighting_profile(profile_name).is_some()) {
+            self.set_field_overridden(&RobotConfigurationInfoField::JointWeightingProfiles, true)?;
+        }
+        self.robot_configuration_info.joint_weighting_profiles.retain(|p| p.name != profile_name);
+        return self.update();
+    }
+    /// Adds a named reference posture to the configuration (see `NamedPosture`).  If a posture with
+    /// the same name already exists, it will be replaced.
+    pub fn set_named_posture(&mut self, named_posture: NamedPosture) -> Result<(), OptimaError> {
+        let joints = self.base_robot_model_module.joints();
+        for v in &named_posture.joint_axis_values {
+            OptimaError::new_check_for_idx_out_of_bound_error(v.joint_idx, joints.len(), file!(), line!())?;
+            OptimaError::new_check_for_idx_out_of_bound_error(v.joint_sub_idx, joints[v.joint_idx].joint_axes().len(), file!(), line!())?;
+        }
+
+        self.robot_configuration_info.named_postures.retain(|p| p.name != named_posture.name);
+        self.robot_configuration_info.named_postures.push(named_posture);
+        return self.update();
+    }
+    /// Removes the named posture with the given name, if it exists.  If the posture is inherited from
+    /// the parent configuration, this configuration will override the parent's postures from now on
+    /// (see `set_field_overridden`).
+    pub fn remove_named_posture(&mut self, posture_name: &str) -> Result<(), OptimaError> {
+        if self.is_inherited(&RobotConfigurationInfoField::NamedPostures, |i| i.named_posture(posture_name).is_some()) {
+            self.set_field_overridden(&RobotConfigurationInfoField::NamedPostures, true)?;
+        }
+        self.robot_configuration_info.named_postures.retain(|p| p.name != posture_name);
+        return self.update();
+    }
+    /// Sets whether this configuration replaces the given field of its parent configuration's info
+    /// wholesale, rather than layering its entries on top of the parent's (see
+    /// `RobotConfigurationInfo::layer_on_top_of`).  When a field starts being overridden, it is seeded
+    /// with the field's current resolved value, so the configuration's model does not change until
+    /// entries are removed or replaced.
+    pub fn set_field_overridden(&mut self, field: &RobotConfigurationInfoField, overridden: bool) -> Result<(), OptimaError> {
+        if overridden && !self.robot_configuration_info.is_field_overridden(field) {
+            self.robot_configuration_info.copy_field_from(&self.resolved_robot_configuration_info, field);
+        }
+        self.robot_configuration_info.set_field_overridden(field, overridden);
+        return self.update();
+    }
+    /// Returns the index of the TCP frame with the given name in the configuration's list of
+    /// TCP frames.  This index is the one used in forward kinematics results.
+    pub fn get_tcp_frame_idx_from_name(&self, tcp_frame_name: &str) -> Option<usize> {
+        for (i, t) in self.resolved_robot_configuration_info.tcp_frame_infos.iter().enumerate() {
+            if &t.tcp_frame_name == tcp_frame_name { return Some(i); }
+        }
+        return None;
+    }
     pub fn print_contiguous_chains(&self) {
         for c in &self.robot_configuration_info.contiguous_chain_infos {
             println!("{:?}", c);
@@ -229,22 +531,35 @@ impl RobotConfigurationModule {
     }
     */
     pub fn save(&self, configuration_name: &str) -> Result<(), OptimaError> {
-        let mut path = OptimaStemCellPath::new_asset_path()?;
-        path.append_file_location(&OptimaAssetLocation::RobotConfigurations { robot_name: self.robot_model_module.robot_name().to_string() });
-        path.append(&(configuration_name.to_string() + ".JSON"));
-
-        if path.exists() {
-            let response = ConsoleInputUtils::get_console_input_string(&format!("Configuration with name {} already exists.  Overwrite?  (y or n)", configuration_name), PrintColor::Cyan)?;
+        return self.save_with_file_format(configuration_name, &RobotConfigurationFileFormat::JSON);
+    }
+    /// Saves the configuration in the given file format.  YAML and TOML files are easier to read
+    /// and edit by hand than JSON files, and will be picked up by `new_from_names` just like JSON files.
+    pub fn save_with_file_format(&self, configuration_name: &str, file_format: &RobotConfigurationFileFormat) -> Result<(), OptimaError> {
+        let robot_name = self.robot_model_module.robot_name();
+        if let Some((existing_path, _)) = RobotConfigurationFileFormat::find_configuration_file(robot_name, configuration_name)? {
+            let response = ConsoleInputUtils::get_console_input_string(&format!("Configuration with name {} already exists at {:?}.  Overwrite?  (y or n)", configuration_name, existing_path), PrintColor::Cyan)?;
             if response == "y" {
-                self.save_to_path(&path)?;
+                existing_path.delete_file()?;
             } else {
                 return Ok(());
             }
-        } else {
-            self.save_to_path(&path)?;
         }
 
-        Ok(())
+        let mut path = OptimaStemCellPath::new_asset_path()?;
+        path.append_file_location(&OptimaAssetLocation::RobotConfigurations { robot_name: robot_name.to_string() });
+        path.append(&(configuration_name.to_string() + "." + file_format.extension()));
+
+        return match file_format {
+            RobotConfigurationFileFormat::JSON => { self.save_to_path(&path) }
+            _ => {
+                let file = RobotConfigurationFile {
+                    robot_name: robot_name.to_string(),
+                    robot_configuration_info: self.robot_configuration_info.clone()
+                };
+                path.write_string_to_file(&file_format.serialize_with_round_trip_check(&file)?)
+            }
+        }
     }
     /// Prints summary of underlying robot model module.
     pub fn print_summary(&self) {
@@ -253,6 +568,15 @@ impl RobotConfigurationModule {
     pub fn robot_name(&self) -> &str {
         return self.robot_model_module.robot_name()
     }
+    /// Returns true if the resolved parent configuration satisfies f (i.e., has the entry) and the
+    /// given field is not already overridden.
+    fn is_inherited<F: Fn(&RobotConfigurationInfo) -> bool>(&self, field: &RobotConfigurationInfoField, f: F) -> bool {
+        if self.robot_configuration_info.is_field_overridden(field) { return false; }
+        return match &self.resolved_parent_robot_configuration_info {
+            None => { false }
+            Some((_, resolved_parent)) => { f(resolved_parent) }
+        }
+    }
 }
 impl SaveAndLoadable for RobotConfigurationModule {
     type SaveType = (String, RobotConfigurationInfo);
@@ -262,7 +586,7 @@ impl SaveAndLoadable for RobotConfigurationModule {
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let load: Self::SaveType = load_object_from_json_string(json_str)?;
+        let load: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
         return RobotConfigurationModule::new_from_robot_name_and_info(&load.0, load.1);
     }
 }
@@ -281,7 +605,7 @@ impl RobotConfigurationModulePy {
     #[new]
     pub fn new(robot_name: &str, py: Python) -> Self {
         let robot_configuration_module = RobotConfigurationModule::new_base_model(robot_name).expect("error");
-        let robot_model_module_py = Py::new(py, robot_configuration_module.robot_model_module.clone()).expect("error");
+        let robot_model_module_py = Py::new(py, robot_configuration_module.robot_model_module.to_owned_inner()).expect("error");
         Self {
             robot_configuration_module,
             robot_model_module_py
@@ -290,16 +614,22 @@ impl RobotConfigurationModulePy {
 
     #[staticmethod]
     pub fn new_from_configuration_module(robot_configuration_module: RobotConfigurationModule, py: Python) -> Self {
-        let robot_model_module_py = Py::new(py, robot_configuration_module.robot_model_module.clone()).expect("error");
+        let robot_model_module_py = Py::new(py, robot_configuration_module.robot_model_module.to_owned_inner()).expect("error");
         Self {
             robot_configuration_module,
             robot_model_module_py
         }
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn __reduce__(&self, py: Python) -> PyResult<(PyObject, (RobotConfigurationModule,))> {
+        let constructor = py.get_type::<Self>().getattr("new_from_configuration_module")?;
+        return Ok((PyObject::from(constructor), (self.robot_configuration_module.clone(),)));
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     fn copy_robot_model_module_to_py(&mut self, py: Python) {
-        self.robot_model_module_py = Py::new(py, self.robot_configuration_module.robot_model_module.clone()).expect("error");
+        self.robot_model_module_py = Py::new(py, self.robot_configuration_module.robot_model_module.to_owned_inner()).expect("error");
     }
 
     #[cfg(not(target_arch = "wasm32"))]
@@ -311,26 +641,40 @@ impl RobotConfigurationModulePy {
     /// links that occur as successors in the kinematic chain will be inactive (essentially, removed)
     /// from the robot model.
     pub fn set_dead_end_link(&mut self, link_idx: usize, py: Python) {
-        self.robot_configuration_module.set_dead_end_link(link_idx).expect("error");
+        self.robot_configuration_module.set_dead_end_link(LinkId::new(link_idx)).expect("error");
         self.copy_robot_model_module_to_py(py);
     }
 
     /// Removes the given link as a dead end link.
     pub fn remove_dead_end_link(&mut self, link_idx: usize, py: Python) {
-        self.robot_configuration_module.remove_dead_end_link(link_idx).expect("error");
+        self.robot_configuration_module.remove_dead_end_link(LinkId::new(link_idx)).expect("error");
         self.copy_robot_model_module_to_py(py);
     }
 
     /// Fixes the given joint to the given value.  Thus, this joint will not be a degree of freedom
     /// in the current configuration.
     pub fn set_fixed_joint(&mut self, joint_idx: usize, joint_sub_idx: usize, fixed_joint_value: f64, py: Python) {
-        self.robot_configuration_module.set_fixed_joint(joint_idx, joint_sub_idx, fixed_joint_value).expect("error");
+        self.robot_configuration_module.set_fixed_joint(JointId::new(joint_idx), joint_sub_idx, fixed_joint_value).expect("error");
         self.copy_robot_model_module_to_py(py);
     }
 
     /// Removes the given joint as a fixed joint.  Thus, this joint will become a degree of freedom.
     pub fn remove_fixed_joint(&mut self, joint_idx: usize, joint_sub_idx: usize, py: Python) {
-        self.robot_configuration_module.remove_fixed_joint(joint_idx, joint_sub_idx).expect("error");
+        self.robot_configuration_module.remove_fixed_joint(JointId::new(joint_idx), joint_sub_idx).expect("error");
+        self.copy_robot_model_module_to_py(py);
+    }
+
+    /// Couples the given joint axis to other joint axes.  Each term is given as
+    /// (joint_idx, joint_sub_idx, coefficient).
+    pub fn set_joint_coupling(&mut self, joint_idx: usize, joint_sub_idx: usize, terms: Vec<(usize, usize, f64)>, offset: f64, py: Python) {
+        let terms = terms.iter().map(|(joint_idx, joint_sub_idx, coefficient)| JointCouplingTerm { joint_idx: *joint_idx, joint_sub_idx: *joint_sub_idx, coefficient: *coefficient }).collect();
+        self.robot_configuration_module.set_joint_coupling(JointId::new(joint_idx), joint_sub_idx, terms, offset).expect("error");
+        self.copy_robot_model_module_to_py(py);
+    }
+
+    /// Removes the coupling of the given joint axis.  Thus, this joint axis will become a degree of freedom.
+    pub fn remove_joint_coupling(&mut self, joint_idx: usize, joint_sub_idx: usize, py: Python) {
+        self.robot_configuration_module.remove_joint_coupling(JointId::new(joint_idx), joint_sub_idx).expect("error");
         self.copy_robot_model_module_to_py(py);
     }
 
@@ -385,6 +729,67 @@ impl RobotConfigurationModulePy {
     }
     */
 
+    /// Adds a named tool center point (TCP) frame at the given offset from the given link.
+    pub fn set_tcp_frame(&mut self, tcp_frame_name: &str, link_idx: usize, offset: &OptimaSE3PosePy, py: Python) {
+        self.robot_configuration_module.set_tcp_frame(tcp_frame_name, LinkId::new(link_idx), offset.pose()).expect("error");
+        self.copy_robot_model_module_to_py(py);
+    }
+
+    /// Adds a named tool center point (TCP) frame at the given euler angle and translation offset from the given link.
+    pub fn set_tcp_frame_euler_angles(&mut self, tcp_frame_name: &str, link_idx: usize, rx: f64, ry: f64, rz: f64, x: f64, y: f64, z: f64, py: Python) {
+        self.robot_configuration_module.set_tcp_frame(tcp_frame_name, LinkId::new(link_idx), &OptimaSE3Pose::new_unit_quaternion_and_translation_from_euler_angles(rx, ry, rz, x, y, z)).expect("error");
+        self.copy_robot_model_module_to_py(py);
+    }
+
+    /// Removes the TCP frame with the given name.
+    pub fn remove_tcp_frame(&mut self, tcp_frame_name: &str, py: Python) {
+        self.robot_configuration_module.remove_tcp_frame(tcp_frame_name).expect("error");
+        self.copy_robot_model_module_to_py(py);
+    }
+
+    /// Adds a named joint weighting profile.  joint_axis_weights is a list of
+    /// (joint idx, joint sub idx, weight) tuples; all other joint axes get default_weight.
+    #[args(default_weight="1.0")]
+    pub fn set_joint_weighting_profile(&mut self, profile_name: &str, joint_axis_weights: Vec<(usize, usize, f64)>, default_weight: f64) {
+        let mut joint_weighting_profile = JointWeightingProfile::new(profile_name, default_weight).expect("error");
+        for (joint_idx, joint_sub_idx, weight) in joint_axis_weights {
+            joint_weighting_profile.set_joint_axis_weight(joint_idx, joint_sub_idx, weight).expect("error");
+        }
+        self.robot_configuration_module.set_joint_weighting_profile(joint_weighting_profile).expect("error");
+    }
+
+    /// Removes the joint weighting profile with the given name.
+    pub fn remove_joint_weighting_profile(&mut self, profile_name: &str) {
+        self.robot_configuration_module.remove_joint_weighting_profile(profile_name).expect("error");
+    }
+
+    /// Adds a named reference posture.  joint_axis_values is a list of
+    /// (joint idx, joint sub idx, value) tuples; all other joint axes are left unspecified.
+    pub fn set_named_posture(&mut self, posture_name: &str, joint_axis_values: Vec<(usize, usize, f64)>) {
+        let mut named_posture = NamedPosture::new(posture_name);
+        for (joint_idx, joint_sub_idx, value) in joint_axis_values {
+            named_posture.set_joint_axis_value(joint_idx, joint_sub_idx, value).expect("error");
+        }
+        self.robot_configuration_module.set_named_posture(named_posture).expect("error");
+    }
+
+    /// Removes the named posture with the given name.
+    pub fn remove_named_posture(&mut self, posture_name: &str) {
+        self.robot_configuration_module.remove_named_posture(posture_name).expect("error");
+    }
+
+    /// Sets the named configuration that this configuration extends.
+    pub fn set_parent_configuration(&mut self, parent_configuration_name: Option<&str>, py: Python) {
+        self.robot_configuration_module.set_parent_configuration(parent_configuration_name).expect("error");
+        self.copy_robot_model_module_to_py(py);
+    }
+
+    /// Re-reads the parent configuration chain from disk.
+    pub fn reload_parent_configuration(&mut self, py: Python) {
+        self.robot_configuration_module.reload_parent_configuration().expect("error");
+        self.copy_robot_model_module_to_py(py);
+    }
+
     pub fn set_base_offset_py(&mut self, pose: &OptimaSE3PosePy, py: Python) {
         self.robot_configuration_module.set_base_offset(pose.pose()).expect("error");
         self.copy_robot_model_module_to_py(py);
@@ -402,6 +807,245 @@ impl RobotConfigurationModulePy {
     pub fn save(&mut self, configuration_name: &str) {
         self.robot_configuration_module.save(configuration_name).expect("error");
     }
+
+    /// Saves the RobotConfigurationModule in the given file format ("JSON", "YAML", or "TOML").
+    pub fn save_with_file_format(&mut self, configuration_name: &str, file_format: &str) {
+        let file_format = RobotConfigurationFileFormat::new_from_extension(file_format).expect(&format!("Unsupported configuration file format {}.", file_format));
+        self.robot_configuration_module.save_with_file_format(configuration_name, &file_format).expect("error");
+    }
+}
+
+/// A fluent builder for a `RobotConfigurationModule`.  Joints and links are referred to by name, and
+/// the names are resolved against the robot's base model when `build` is called.  Building a
+/// configuration does not read or write any configuration asset files (unless the configuration
+/// extends a saved parent configuration).
+///
+/// # Example
+/// ```
+/// use optima::robot_modules::robot_configuration_module::{ContiguousChainMobilityModeType, RobotConfigurationBuilder};
+/// use optima::utils::utils_se3::optima_se3_pose::OptimaSE3Pose;
+///
+/// let robot_configuration_module = RobotConfigurationBuilder::new("ur5")
+///     .fix_joint("elbow_joint", 0.5)
+///     .remove_link_subtree("wrist_3_link")
+///     .mobile_base(ContiguousChainMobilityModeType::PlanarTranslation)
+///     .end_effector("gripper_tip", "wrist_2_link", OptimaSE3Pose::new_unit_quaternion_and_translation_from_euler_angles(0.,0.,0.,0.,0.,0.1))
+///     .build()
+///     .expect("error");
+/// ```
+#[derive(Clone, Debug)]
+pub struct RobotConfigurationBuilder {
+    robot_name: String,
+    parent_configuration_name: Option<String>,
+    fixed_joints: Vec<(String, Option<usize>, f64)>,
+    coupled_joints: Vec<(String, usize, Vec<(String, usize, f64)>, f64)>,
+    removed_link_subtrees: Vec<String>,
+    mobile_base: Option<ContiguousChainMobilityMode>,
+    base_offset: Option<OptimaSE3Pose>,
+    end_effectors: Vec<(String, String, OptimaSE3Pose)>,
+    soft_joint_limit_margin: Option<SoftJointLimitMargin>,
+    joint_weighting_profiles: Vec<(String, f64, Vec<(String, f64)>)>,
+    named_postures: Vec<(String, Vec<(String, f64)>)>
+}
+impl RobotConfigurationBuilder {
+    pub fn new(robot_name: &str) -> Self {
+        Self {
+            robot_name: robot_name.to_string(),
+            parent_configuration_name: None,
+            fixed_joints: vec![],
+            coupled_joints: vec![],
+            removed_link_subtrees: vec![],
+            mobile_base: None,
+            base_offset: None,
+            end_effectors: vec![],
+            soft_joint_limit_margin: None,
+            joint_weighting_profiles: vec![],
+            named_postures: vec![]
+        }
+    }
+    /// The built configuration will extend the saved configuration with the given name.
+    pub fn extends(mut self, parent_configuration_name: &str) -> Self {
+        self.parent_configuration_name = Some(parent_configuration_name.to_string());
+        self
+    }
+    /// Fixes all axes of the given joint to the given value.
+    pub fn fix_joint(mut self, joint_name: &str, fixed_joint_value: f64) -> Self {
+        self.fixed_joints.push((joint_name.to_string(), None, fixed_joint_value));
+        self
+    }
+    /// Fixes a single axis of the given joint to the given value.
+    pub fn fix_joint_sub_dof(mut self, joint_name: &str, joint_sub_idx: usize, fixed_joint_value: f64) -> Self {
+        self.fixed_joints.push((joint_name.to_string(), Some(joint_sub_idx), fixed_joint_value));
+        self
+    }
+    /// Couples the (single axis) given joint to other single axis joints, such that its value is
+    /// offset + sum(coefficient * value) over the given (joint name, coefficient) terms.
+    pub fn couple_joint(mut self, joint_name: &str, terms: Vec<(&str, f64)>, offset: f64) -> Self {
+        let terms = terms.iter().map(|(n, coefficient)| (n.to_string(), 0, *coefficient)).collect();
+        self.coupled_joints.push((joint_name.to_string(), 0, terms, offset));
+        self
+    }
+    /// Couples a single axis of the given joint to other joint axes, given as
+    /// (joint name, joint sub idx, coefficient) terms.
+    pub fn couple_joint_sub_dof(mut self, joint_name: &str, joint_sub_idx: usize, terms: Vec<(&str, usize, f64)>, offset: f64) -> Self {
+        let terms = terms.iter().map(|(n, s, coefficient)| (n.to_string(), *s, *coefficient)).collect();
+        self.coupled_joints.push((joint_name.to_string(), joint_sub_idx, terms, offset));
+        self
+    }
+    /// Removes the given link and all of its downstream links (i.e., sets it as a dead end link).
+    pub fn remove_link_subtree(mut self, link_name: &str) -> Self {
+        self.removed_link_subtrees.push(link_name.to_string());
+        self
+    }
+    /// Gives the robot a mobile base with default bounds for the given mobility mode type.
+    pub fn mobile_base(mut self, mobility_mode_type: ContiguousChainMobilityModeType) -> Self {
+        self.mobile_base = Some(ContiguousChainMobilityMode::new_default(&mobility_mode_type));
+        self
+    }
+    /// Gives the robot a mobile base with the given mobility mode (including bounds).
+    pub fn mobile_base_with_bounds(mut self, mobility_mode: ContiguousChainMobilityMode) -> Self {
+        self.mobile_base = Some(mobility_mode);
+        self
+    }
+    /// Mounts the robot's base at the given pose in the world (see
+    /// `RobotConfigurationModule::set_base_offset`).
+    pub fn base_offset(mut self, base_offset: OptimaSE3Pose) -> Self {
+        self.base_offset = Some(base_offset);
+        self
+    }
+    /// Adds a named end effector (TCP) frame at the given offset from the given link.  Auxiliary
+    /// frames (e.g., a camera optical frame or tool flange) are added the same way.
+    pub fn end_effector(mut self, tcp_frame_name: &str, link_name: &str, offset: OptimaSE3Pose) -> Self {
+        self.end_effectors.push((tcp_frame_name.to_string(), link_name.to_string(), offset));
+        self
+    }
+    /// Sets a soft joint limit band inside the hard limits of all joint axes.
+    pub fn soft_joint_limit_margin(mut self, soft_joint_limit_margin: SoftJointLimitMargin) -> Self {
+        self.soft_joint_limit_margin = Some(soft_joint_limit_margin);
+        self
+    }
+    /// Adds a named joint weighting profile in which all axes of each given joint get the given weight
+    /// and all other joint axes get default_weight (see `JointWeightingProfile`).
+    pub fn joint_weighting_profile(mut self, profile_name: &str, default_weight: f64, joint_weights: Vec<(&str, f64)>) -> Self {
+        let joint_weights = joint_weights.iter().map(|(n, weight)| (n.to_string(), *weight)).collect();
+        self.joint_weighting_profiles.push((profile_name.to_string(), default_weight, joint_weights));
+        self
+    }
+    /// Adds a named reference posture (e.g., "home") in which all axes of each given joint have the
+    /// given value and all other joint axes are unspecified (see `NamedPosture`).
+    pub fn named_posture(mut self, posture_name: &str, joint_values: Vec<(&str, f64)>) -> Self {
+        let joint_values = joint_values.iter().map(|(n, value)| (n.to_string(), *value)).collect();
+        self.named_postures.push((posture_name.to_string(), joint_values));
+        self
+    }
+    /// Resolves all joint and link names and outputs the resulting `RobotConfigurationInfo`.
+    pub fn build_robot_configuration_info(&self) -> Result<RobotConfigurationInfo, OptimaError> {
+        let robot_model_module = RobotModelModule::new(&self.robot_name)?;
+        let mut out = RobotConfigurationInfo::default();
+        out.parent_configuration_name = self.parent_configuration_name.clone();
+
+        for (joint_name, joint_sub_idx, fixed_joint_value) in &self.fixed_joints {
+            let joint_idx = Self::get_joint_idx(&robot_model_module, joint_name)?;
+            let num_axes = robot_model_module.joints()[joint_idx].joint_axes().len();
+            match joint_sub_idx {
+                None => {
+                    for joint_sub_idx in 0..num_axes {
+                        out.fixed_joint_infos.push(FixedJointInfo { joint_idx, joint_sub_idx, fixed_joint_value: *fixed_joint_value });
+                    }
+                }
+                Some(joint_sub_idx) => {
+                    OptimaError::new_check_for_idx_out_of_bound_error(*joint_sub_idx, num_axes, file!(), line!())?;
+                    out.fixed_joint_infos.push(FixedJointInfo { joint_idx, joint_sub_idx: *joint_sub_idx, fixed_joint_value: *fixed_joint_value });
+                }
+            }
+        }
+
+        for (joint_name, joint_sub_idx, terms, offset) in &self.coupled_joints {
+            let joint_idx = Self::get_joint_idx(&robot_model_module, joint_name)?;
+            let mut coupling_terms = vec![];
+            for (term_joint_name, term_joint_sub_idx, coefficient) in terms {
+                let term_joint_idx = Self::get_joint_idx(&robot_model_module, term_joint_name)?;
+                coupling_terms.push(JointCouplingTerm { joint_idx: term_joint_idx, joint_sub_idx: *term_joint_sub_idx, coefficient: *coefficient });
+            }
+            out.joint_coupling_infos.retain(|c| !c.refers_to_joint_axis(joint_idx, *joint_sub_idx));
+            out.joint_coupling_infos.push(JointCouplingInfo { joint_idx, joint_sub_idx: *joint_sub_idx, terms: coupling_terms, offset: *offset });
+        }
+
+        for link_name in &self.removed_link_subtrees {
+            let link_idx = Self::get_link_idx(&robot_model_module, link_name)?;
+            out.dead_end_link_idxs.push(link_idx);
+        }
+
+        if let Some(mobility_mode) = &self.mobile_base {
+            out.contiguous_chain_infos.push(ContiguousChainInfo {
+                chain_name: "mobile_base".to_string(),
+                start_link_idx: robot_model_module.robot_base_link_idx(),
+                end_link_idx: None,
+                mobility_mode: mobility_mode.clone()
+            });
+        }
+
+        if let Some(base_offset) = &self.base_offset {
+            out.base_offset = OptimaSE3PoseAll::new(base_offset);
+            if out.parent_configuration_name.is_some() { out.set_field_overridden(&RobotConfigurationInfoField::BaseOffset, true); }
+        }
+
+        out.soft_joint_limit_margin = self.soft_joint_limit_margin.clone();
+
+        for (profile_name, default_weight, joint_weights) in &self.joint_weighting_profiles {
+            let mut joint_weighting_profile = JointWeightingProfile::new(profile_name, *default_weight)?;
+            for (joint_name, weight) in joint_weights {
+                let joint_idx = Self::get_joint_idx(&robot_model_module, joint_name)?;
+                let num_axes = robot_model_module.joints()[joint_idx].joint_axes().len();
+                for joint_sub_idx in 0..num_axes {
+                    joint_weighting_profile.set_joint_axis_weight(joint_idx, joint_sub_idx, *weight)?;
+                }
+            }
+            out.joint_weighting_profiles.retain(|p| &p.name != profile_name);
+            out.joint_weighting_profiles.push(joint_weighting_profile);
+        }
+
+        for (posture_name, joint_values) in &self.named_postures {
+            let mut named_posture = NamedPosture::new(posture_name);
+            for (joint_name, value) in joint_values {
+                let joint_idx = Self::get_joint_idx(&robot_model_module, joint_name)?;
+                let num_axes = robot_model_module.joints()[joint_idx].joint_axes().len();
+                for joint_sub_idx in 0..num_axes {
+                    named_posture.set_joint_axis_value(joint_idx, joint_sub_idx, *value)?;
+                }
+            }
+            out.named_postures.retain(|p| &p.name != posture_name);
+            out.named_postures.push(named_posture);
+        }
+
+        for (tcp_frame_name, link_name, offset) in &self.end_effectors {
+            let link_idx = Self::get_link_idx(&robot_model_module, link_name)?;
+            out.tcp_frame_infos.retain(|t| &t.tcp_frame_name != tcp_frame_name);
+            out.tcp_frame_infos.push(TCPFrameInfo {
+                tcp_frame_name: tcp_frame_name.clone(),
+                link_idx,
+                offset: OptimaSE3PoseAll::new(offset)
+            });
+        }
+
+        Ok(out)
+    }
+    pub fn build(&self) -> Result<RobotConfigurationModule, OptimaError> {
+        let robot_configuration_info = self.build_robot_configuration_info()?;
+        return RobotConfigurationModule::new_from_robot_name_and_info(&self.robot_name, robot_configuration_info);
+    }
+    fn get_joint_idx(robot_model_module: &RobotModelModule, joint_name: &str) -> Result<usize, OptimaError> {
+        return match robot_model_module.get_joint_id_from_name(joint_name) {
+            None => { Err(OptimaError::new_generic_error_str(&format!("Robot {} does not have a joint named {}.", robot_model_module.robot_name(), joint_name), file!(), line!())) }
+            Some(joint_id) => { Ok(joint_id.idx()) }
+        }
+    }
+    fn get_link_idx(robot_model_module: &RobotModelModule, link_name: &str) -> Result<usize, OptimaError> {
+        return match robot_model_module.get_link_id_from_name(link_name) {
+            None => { Err(OptimaError::new_generic_error_str(&format!("Robot {} does not have a link named {}.", robot_model_module.robot_name(), link_name), file!(), line!())) }
+            Some(link_id) => { Ok(link_id.idx()) }
+        }
+    }
 }
 
 /// Methods supported by WASM.
@@ -420,7 +1064,23 @@ pub struct RobotConfigurationInfo {
     contiguous_chain_infos: Vec<ContiguousChainInfo>,
     dead_end_link_idxs: Vec<usize>,
     fixed_joint_infos: Vec<FixedJointInfo>,
-    base_offset: OptimaSE3PoseAll
+    /// World to base mounting transform, applied to the root link in forward kinematics.
+    #[serde(with = "pose_all_as_quaternion_and_translation")]
+    base_offset: OptimaSE3PoseAll,
+    #[serde(default)]
+    tcp_frame_infos: Vec<TCPFrameInfo>,
+    #[serde(default)]
+    parent_configuration_name: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_tagged_or_legacy_enum")]
+    soft_joint_limit_margin: Option<SoftJointLimitMargin>,
+    #[serde(default)]
+    joint_coupling_infos: Vec<JointCouplingInfo>,
+    #[serde(default)]
+    joint_weighting_profiles: Vec<JointWeightingProfile>,
+    #[serde(default)]
+    named_postures: Vec<NamedPosture>,
+    #[serde(default)]
+    overridden_fields: Vec<RobotConfigurationInfoField>
 }
 impl Default for RobotConfigurationInfo {
     /// By default, we will just have the robot's given base model directly from the robot's URDF.
@@ -429,7 +1089,14 @@ impl Default for RobotConfigurationInfo {
             contiguous_chain_infos: vec![],
             dead_end_link_idxs: vec![],
             fixed_joint_infos: vec![],
-            base_offset: OptimaSE3PoseAll::new_identity()
+            base_offset: OptimaSE3PoseAll::new_identity(),
+            tcp_frame_infos: vec![],
+            parent_configuration_name: None,
+            soft_joint_limit_margin: None,
+            joint_coupling_infos: vec![],
+            joint_weighting_profiles: vec![],
+            named_postures: vec![],
+            overridden_fields: vec![]
         }
     }
 }
@@ -446,6 +1113,393 @@ impl RobotConfigurationInfo {
     pub fn contiguous_chain_infos(&self) -> &Vec<ContiguousChainInfo> {
         &self.contiguous_chain_infos
     }
+    pub fn tcp_frame_infos(&self) -> &Vec<TCPFrameInfo> {
+        &self.tcp_frame_infos
+    }
+    pub fn parent_configuration_name(&self) -> &Option<String> {
+        &self.parent_configuration_name
+    }
+    pub fn soft_joint_limit_margin(&self) -> &Option<SoftJointLimitMargin> {
+        &self.soft_joint_limit_margin
+    }
+    pub fn joint_coupling_infos(&self) -> &Vec<JointCouplingInfo> {
+        &self.joint_coupling_infos
+    }
+    pub fn joint_weighting_profiles(&self) -> &Vec<JointWeightingProfile> {
+        &self.joint_weighting_profiles
+    }
+    /// Returns the joint weighting profile with the given name, if it exists.
+    pub fn joint_weighting_profile(&self, profile_name: &str) -> Option<&JointWeightingProfile> {
+        return self.joint_weighting_profiles.iter().find(|p| p.name == profile_name);
+    }
+    pub fn named_postures(&self) -> &Vec<NamedPosture> {
+        &self.named_postures
+    }
+    /// Returns the named posture with the given name, if it exists.
+    pub fn named_posture(&self, posture_name: &str) -> Option<&NamedPosture> {
+        return self.named_postures.iter().find(|p| p.name == posture_name);
+    }
+    /// The fields that this info replaces wholesale when layered on top of a parent configuration
+    /// (see `layer_on_top_of`).
+    pub fn overridden_fields(&self) -> &Vec<RobotConfigurationInfoField> {
+        &self.overridden_fields
+    }
+    pub fn is_field_overridden(&self, field: &RobotConfigurationInfoField) -> bool {
+        self.overridden_fields.contains(field)
+    }
+    pub fn set_field_overridden(&mut self, field: &RobotConfigurationInfoField, overridden: bool) {
+        self.overridden_fields.retain(|f| f != field);
+        if overridden { self.overridden_fields.push(field.clone()); }
+    }
+    /// Sets the given field of this info to the given info's value.
+    pub fn copy_field_from(&mut self, other: &RobotConfigurationInfo, field: &RobotConfigurationInfoField) {
+        match field {
+            RobotConfigurationInfoField::ContiguousChains => { self.contiguous_chain_infos = other.contiguous_chain_infos.clone(); }
+            RobotConfigurationInfoField::DeadEndLinks => { self.dead_end_link_idxs = other.dead_end_link_idxs.clone(); }
+            RobotConfigurationInfoField::FixedJoints => { self.fixed_joint_infos = other.fixed_joint_infos.clone(); }
+            RobotConfigurationInfoField::JointCouplings => { self.joint_coupling_infos = other.joint_coupling_infos.clone(); }
+            RobotConfigurationInfoField::BaseOffset => { self.base_offset = other.base_offset.clone(); }
+            RobotConfigurationInfoField::TCPFrames => { self.tcp_frame_infos = other.tcp_frame_infos.clone(); }
+            RobotConfigurationInfoField::SoftJointLimitMargin => { self.soft_joint_limit_margin = other.soft_joint_limit_margin.clone(); }
+            RobotConfigurationInfoField::JointWeightingProfiles => { self.joint_weighting_profiles = other.joint_weighting_profiles.clone(); }
+            RobotConfigurationInfoField::NamedPostures => { self.named_postures = other.named_postures.clone(); }
+        }
+    }
+    /// Loads the `RobotConfigurationInfo` saved under the given configuration name and resolves it
+    /// against its chain of parent configurations.
+    pub fn load_resolved(robot_name: &str, configuration_name: &str) -> Result<Self, OptimaError> {
+        let mut visited_configuration_names = vec![configuration_name.to_string()];
+        return Self::load_unresolved(robot_name, configuration_name)?.resolve_recursive(robot_name, &mut visited_configuration_names);
+    }
+    /// Loads the `RobotConfigurationInfo` saved under the given configuration name, without resolving
+    /// it against any parent configuration.
+    /// The configuration may be saved as a JSON, YAML, or TOML file.
+    pub fn load_unresolved(robot_name: &str, configuration_name: &str) -> Result<Self, OptimaError> {
+        let found = RobotConfigurationFileFormat::find_configuration_file(robot_name, configuration_name)?;
+        return match found {
+            None => {
+                Err(OptimaError::new_generic_error_str(&format!("Robot {} does not have configuration {} (looked for JSON, YAML, and TOML files).", robot_name, configuration_name), file!(), line!()))
+            }
+            Some((path, file_format)) => {
+                match file_format {
+                    RobotConfigurationFileFormat::JSON => {
+                        let load = RobotConfigurationModule::load_save_object_from_json_string(&path.read_file_contents_to_string()?)?;
+                        Ok(load.1)
+                    }
+                    _ => {
+                        let contents = path.read_file_contents_to_string()?;
+                        let load = file_format.deserialize(&contents)?;
+                        Ok(load.robot_configuration_info)
+                    }
+                }
+            }
+        }
+    }
+    /// Resolves this info against its chain of parent configurations.  The output info will not have
+    /// a parent configuration, and will reflect all of its ancestors' infos with each child layered
+    /// on top of its parent (see `layer_on_top_of`).
+    pub fn resolve(&self, robot_name: &str) -> Result<Self, OptimaError> {
+        let mut visited_configuration_names = vec![];
+        return self.resolve_recursive(robot_name, &mut visited_configuration_names);
+    }
+    fn resolve_recursive(&self, robot_name: &str, visited_configuration_names: &mut Vec<String>) -> Result<Self, OptimaError> {
+        return match &self.parent_configuration_name {
+            None => { Ok(self.clone()) }
+            Some(parent_configuration_name) => {
+                if visited_configuration_names.contains(parent_configuration_name) {
+                    return Err(OptimaError::new_generic_error_str(&format!("Configuration inheritance cycle detected on robot {} through configuration {}.", robot_name, parent_configuration_name), file!(), line!()));
+                }
+                visited_configuration_names.push(parent_configuration_name.clone());
+
+                let parent = Self::load_unresolved(robot_name, parent_configuration_name)?;
+                let resolved_parent = parent.resolve_recursive(robot_name, visited_configuration_names)?;
+
+                Ok(self.layer_on_top_of(&resolved_parent))
+            }
+        }
+    }
+    /// Layers this info on top of the given parent info.  Fields listed in this info's overridden
+    /// fields replace the parent's value wholesale (so, e.g., a child can reset the base offset to the
+    /// identity, or un-fix a parent's fixed joints).  All other fields are layered as follows:
+    /// - Contiguous chains and TCP frames are combined, with entries in this info replacing
+    /// entries in the parent with the same name.
+    /// - Dead end links are combined.
+    /// - Fixed joints and joint couplings are combined, with entries in this info replacing entries
+    /// in the parent that refer to the same joint and joint sub-index.
+    /// - The base offset of this info is used unless it is the identity, in which case the parent's
+    /// base offset is inherited.
+    /// - The soft joint limit margin of this info is used if it is set, otherwise the parent's margin
+    /// is inherited.
+    /// - Joint weighting profiles and named postures are combined, with entries in this info replacing
+    /// entries in the parent with the same name.
+    pub fn layer_on_top_of(&self, parent: &RobotConfigurationInfo) -> RobotConfigurationInfo {
+        let mut out = parent.clone();
+        let layered = |field: RobotConfigurationInfoField| -> bool { !self.is_field_overridden(&field) };
+
+        if layered(RobotConfigurationInfoField::ContiguousChains) {
+            for c in &self.contiguous_chain_infos {
+                out.contiguous_chain_infos.retain(|s| s.chain_name != c.chain_name);
+                out.contiguous_chain_infos.push(c.clone());
+            }
+        }
+
+        if layered(RobotConfigurationInfoField::DeadEndLinks) {
+            for d in &self.dead_end_link_idxs {
+                if !out.dead_end_link_idxs.contains(d) { out.dead_end_link_idxs.push(*d); }
+            }
+        }
+
+        if layered(RobotConfigurationInfoField::FixedJoints) {
+            for f in &self.fixed_joint_infos {
+                out.fixed_joint_infos.retain(|s| !(s.joint_idx == f.joint_idx && s.joint_sub_idx == f.joint_sub_idx));
+                out.fixed_joint_infos.push(f.clone());
+            }
+        }
+
+        if layered(RobotConfigurationInfoField::JointCouplings) {
+            for c in &self.joint_coupling_infos {
+                out.joint_coupling_infos.retain(|s| !s.refers_to_joint_axis(c.joint_idx, c.joint_sub_idx));
+                out.joint_coupling_infos.push(c.clone());
+            }
+        }
+
+        if layered(RobotConfigurationInfoField::BaseOffset) {
+            let (_, angle, translation) = self.base_offset.get_pose_by_type(&OptimaSE3PoseType::ImplicitDualQuaternion).to_axis_angle_and_translation();
+            if angle != 0.0 || translation.norm() != 0.0 {
+                out.base_offset = self.base_offset.clone();
+            }
+        }
+
+        if layered(RobotConfigurationInfoField::TCPFrames) {
+            for t in &self.tcp_frame_infos {
+                out.tcp_frame_infos.retain(|s| s.tcp_frame_name != t.tcp_frame_name);
+                out.tcp_frame_infos.push(t.clone());
+            }
+        }
+
+        if layered(RobotConfigurationInfoField::SoftJointLimitMargin) {
+            if self.soft_joint_limit_margin.is_some() {
+                out.soft_joint_limit_margin = self.soft_joint_limit_margin.clone();
+            }
+        }
+
+        if layered(RobotConfigurationInfoField::JointWeightingProfiles) {
+            for p in &self.joint_weighting_profiles {
+                out.joint_weighting_profiles.retain(|s| s.name != p.name);
+                out.joint_weighting_profiles.push(p.clone());
+            }
+        }
+
+        if layered(RobotConfigurationInfoField::NamedPostures) {
+            for p in &self.named_postures {
+                out.named_postures.retain(|s| s.name != p.name);
+                out.named_postures.push(p.clone());
+            }
+        }
+
+        for field in &self.overridden_fields {
+            out.copy_field_from(self, field);
+        }
+        out.overridden_fields = vec![];
+        out.parent_configuration_name = None;
+
+        out
+    }
+}
+
+/// The fields of a `RobotConfigurationInfo` that a child configuration can override, i.e., replace
+/// wholesale instead of layering on top of its parent configuration's value (see
+/// `RobotConfigurationInfo::layer_on_top_of`).
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RobotConfigurationInfoField {
+    ContiguousChains,
+    DeadEndLinks,
+    FixedJoints,
+    JointCouplings,
+    BaseOffset,
+    TCPFrames,
+    SoftJointLimitMargin,
+    JointWeightingProfiles,
+    NamedPostures
+}
+
+/// Specifies a soft joint limit band inside each joint axis' hard limits.  Joint state samplers avoid
+/// the band (see `RobotJointStateModule::sample_joint_state`) and the soft joint limit cost penalizes
+/// joint values inside of it, while the hard limits are still enforced by clamping.
+/// - `Absolute`: the band extends margin (in radians or meters) inward from each hard limit.
+/// - `Fractional`: the band extends fraction * (upper - lower) inward from each hard limit.
+///
+/// If the margins would cross, the soft bounds collapse to the middle of the hard bounds.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum SoftJointLimitMargin {
+    Absolute { margin: f64 },
+    Fractional { fraction: f64 }
+}
+impl SoftJointLimitMargin {
+    /// Returns the soft bounds for the given hard bounds.  Unbounded (infinite) sides are unchanged.
+    pub fn soft_bounds(&self, hard_bounds: (f64, f64)) -> (f64, f64) {
+        let (lower, upper) = hard_bounds;
+        let margin = match self {
+            SoftJointLimitMargin::Absolute { margin } => { margin.max(0.0) }
+            SoftJointLimitMargin::Fractional { fraction } => {
+                if lower.is_finite() && upper.is_finite() { fraction.max(0.0) * (upper - lower) } else { 0.0 }
+            }
+        };
+
+        let soft_lower = if lower.is_finite() { lower + margin } else { lower };
+        let soft_upper = if upper.is_finite() { upper - margin } else { upper };
+        if soft_lower > soft_upper {
+            let mid = (lower + upper) / 2.0;
+            return (mid, mid);
+        }
+
+        return (soft_lower, soft_upper);
+    }
+}
+
+/// The file formats that a robot configuration can be saved to and loaded from.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum RobotConfigurationFileFormat {
+    JSON,
+    YAML,
+    TOML
+}
+impl RobotConfigurationFileFormat {
+    pub fn extension(&self) -> &str {
+        match self {
+            RobotConfigurationFileFormat::JSON => { "JSON" }
+            RobotConfigurationFileFormat::YAML => { "yaml" }
+            RobotConfigurationFileFormat::TOML => { "toml" }
+        }
+    }
+    pub fn new_from_extension(extension: &str) -> Option<Self> {
+        return match extension.to_lowercase().as_str() {
+            "json" => { Some(Self::JSON) }
+            "yaml" | "yml" => { Some(Self::YAML) }
+            "toml" => { Some(Self::TOML) }
+            _ => { None }
+        }
+    }
+    /// Searches the robot's configurations directory for a file with the given configuration name
+    /// in any of the supported formats.  JSON files are preferred, then YAML, then TOML.
+    pub fn find_configuration_file(robot_name: &str, configuration_name: &str) -> Result<Option<(OptimaStemCellPath, RobotConfigurationFileFormat)>, OptimaError> {
+        let extensions = vec!["JSON", "json", "YAML", "yaml", "yml", "TOML", "toml"];
+        for extension in extensions {
+            let mut path = OptimaStemCellPath::new_asset_path()?;
+            path.append_file_location(&OptimaAssetLocation::RobotConfigurations { robot_name: robot_name.to_string() });
+            path.append(&(configuration_name.to_string() + "." + extension));
+            if path.exists() {
+                return Ok(Some((path, Self::new_from_extension(extension).unwrap())));
+            }
+        }
+        return Ok(None);
+    }
+    fn serialize(&self, file: &RobotConfigurationFile) -> Result<String, OptimaError> {
+        match self {
+            RobotConfigurationFileFormat::JSON => { Ok(file.to_json_string()) }
+            RobotConfigurationFileFormat::YAML => { Ok(file.to_yaml_string()) }
+            RobotConfigurationFileFormat::TOML => { file.to_toml_string() }
+        }
+    }
+    /// Serializes the file and checks that the output deserializes back to a file that serializes
+    /// to the same string, so that a file that could not be loaded again is never written.
+    fn serialize_with_round_trip_check(&self, file: &RobotConfigurationFile) -> Result<String, OptimaError> {
+        let serialized = self.serialize(file)?;
+        let reserialized = self.serialize(&self.deserialize(&serialized)?)?;
+        if reserialized != serialized {
+            return Err(OptimaError::new_generic_error_str(&format!("Configuration of robot {} does not round trip through {:?}.", file.robot_name, self), file!(), line!()));
+        }
+        return Ok(serialized);
+    }
+    fn deserialize(&self, s: &str) -> Result<RobotConfigurationFile, OptimaError> {
+        match self {
+            RobotConfigurationFileFormat::JSON => { RobotConfigurationFile::from_json_string(s) }
+            RobotConfigurationFileFormat::YAML => { RobotConfigurationFile::from_yaml_string(s) }
+            RobotConfigurationFileFormat::TOML => { RobotConfigurationFile::from_toml_string(s) }
+        }
+    }
+}
+
+/// The layout of a configuration saved as a YAML or TOML file.  Unlike the JSON layout (a tuple), this
+/// uses named fields so that the file is easy to edit by hand.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct RobotConfigurationFile {
+    robot_name: String,
+    robot_configuration_info: RobotConfigurationInfo
+}
+
+/// Serializes an `OptimaSE3PoseAll` in configuration files as a translation and a unit quaternion
+/// (\[w, i, j, k\]) rather than as all of its redundant pose types, so that it can be written to
+/// TOML (which has no representation for the enums inside `OptimaSE3Pose`) and edited by hand.
+/// Files that store the full `OptimaSE3PoseAll` can still be loaded.
+mod pose_all_as_quaternion_and_translation {
+    use nalgebra::{Quaternion, UnitQuaternion, Vector3};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3Pose, OptimaSE3PoseAll, OptimaSE3PoseType};
+
+    #[derive(Serialize, Deserialize)]
+    struct QuaternionAndTranslation {
+        translation: [f64; 3],
+        quaternion: [f64; 4]
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum QuaternionAndTranslationOrLegacy {
+        QuaternionAndTranslation(QuaternionAndTranslation),
+        Legacy(OptimaSE3PoseAll)
+    }
+
+    pub fn serialize<S: Serializer>(pose: &OptimaSE3PoseAll, serializer: S) -> Result<S::Ok, S::Error> {
+        let pose = pose.get_pose_by_type(&OptimaSE3PoseType::UnitQuaternionAndTranslation);
+        let q = pose.rotation().unwrap_unit_quaternion().expect("error").clone();
+        let t = pose.translation();
+        QuaternionAndTranslation {
+            translation: [t[0], t[1], t[2]],
+            quaternion: [q.w, q.i, q.j, q.k]
+        }.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<OptimaSE3PoseAll, D::Error> {
+        return match QuaternionAndTranslationOrLegacy::deserialize(deserializer)? {
+            QuaternionAndTranslationOrLegacy::QuaternionAndTranslation(p) => {
+                let [w, i, j, k] = p.quaternion;
+                let q = UnitQuaternion::from_quaternion(Quaternion::new(w, i, j, k));
+                let t = Vector3::new(p.translation[0], p.translation[1], p.translation[2]);
+                Ok(OptimaSE3PoseAll::new(&OptimaSE3Pose::new_unit_quaternion_and_translation(q, t)))
+            }
+            QuaternionAndTranslationOrLegacy::Legacy(p) => { Ok(p) }
+        }
+    }
+}
+
+/// Deserializes an internally tagged enum (e.g., `ContiguousChainMobilityMode`), also accepting the
+/// externally tagged layout that configurations were saved with before the enums were tagged (e.g.,
+/// `"Static"` or `{"PlanarRotation": {"zr_bounds": [..]}}`).
+fn deserialize_tagged_or_legacy_enum<'de, D: serde::Deserializer<'de>, T: serde::de::DeserializeOwned>(deserializer: D) -> Result<T, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+    let value = match value {
+        serde_json::Value::String(variant) => { serde_json::json!({ "type": variant }) }
+        serde_json::Value::Object(map) if map.len() == 1 && !map.contains_key("type") => {
+            let (variant, fields) = map.into_iter().next().expect("error");
+            let mut fields = match fields {
+                serde_json::Value::Object(fields) => { fields }
+                _ => { return Err(serde::de::Error::custom(format!("Expected the fields of enum variant {} to be a map.", variant))); }
+            };
+            fields.insert("type".to_string(), serde_json::Value::String(variant));
+            serde_json::Value::Object(fields)
+        }
+        value => { value }
+    };
+    return serde_json::from_value(value).map_err(serde::de::Error::custom);
+}
+
+fn deserialize_optional_tagged_or_legacy_enum<'de, D: serde::Deserializer<'de>, T: serde::de::DeserializeOwned>(deserializer: D) -> Result<Option<T>, D::Error> {
+    return match Option::<serde_json::Value>::deserialize(deserializer)? {
+        None | Some(serde_json::Value::Null) => { Ok(None) }
+        Some(value) => { deserialize_tagged_or_legacy_enum(value).map(Some).map_err(serde::de::Error::custom) }
+    }
 }
 
 /// An object that describes a fixed joint.  The joint_sub_idx refers to the index of a joint's
@@ -458,11 +1512,170 @@ pub struct FixedJointInfo {
     pub fixed_joint_value: f64
 }
 
+/// An object that describes a linear coupling constraint between joint axes (e.g., the two motors of
+/// a differential wrist mechanism).  The value of the dependent joint axis (given by joint_idx and
+/// joint_sub_idx) is not a degree of freedom, and is instead computed from the other joint axes as
+/// offset + sum(coefficient * value) over all terms.
+///
+/// The axes referred to by the terms must be present in the configuration and must not themselves be
+/// coupled, i.e., coupling constraints cannot be chained.  They may be fixed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JointCouplingInfo {
+    pub joint_idx: usize,
+    pub joint_sub_idx: usize,
+    pub terms: Vec<JointCouplingTerm>,
+    pub offset: f64
+}
+impl JointCouplingInfo {
+    pub fn refers_to_joint_axis(&self, joint_idx: usize, joint_sub_idx: usize) -> bool {
+        self.joint_idx == joint_idx && self.joint_sub_idx == joint_sub_idx
+    }
+}
+
+/// A single term of a `JointCouplingInfo`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JointCouplingTerm {
+    pub joint_idx: usize,
+    pub joint_sub_idx: usize,
+    pub coefficient: f64
+}
+
+/// A named set of positive per joint axis weights, e.g., "precise_wrist" (wrist axes weighted low so
+/// that they do most of the fine motion) or "prefer_base_still" (mobile base axes weighted high).
+/// Higher weights make motion of an axis more costly: consumers scale joint space distances by the
+/// weights (see `RobotJointStateModule::weighted_joint_state_distance`) and penalize joint steps in
+/// inverse kinematics by them (see
+/// `RobotKinematicsModule::compute_ik_damped_least_squares_on_frame_with_joint_weighting_profile`).
+/// `JointLimitAvoidance` instead uses them as per axis strengths of its pull toward the middle of
+/// the joint bounds.  Axes without an explicit weight get default_weight.  Weights refer to joint axes rather than DOF
+/// indices, so a profile stays valid when axes are fixed or coupled.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JointWeightingProfile {
+    name: String,
+    default_weight: f64,
+    joint_axis_weights: Vec<JointAxisWeight>
+}
+impl JointWeightingProfile {
+    pub fn new(name: &str, default_weight: f64) -> Result<Self, OptimaError> {
+        Self::check_weight(default_weight)?;
+        Ok(Self {
+            name: name.to_string(),
+            default_weight,
+            joint_axis_weights: vec![]
+        })
+    }
+    /// Sets the weight of the given joint axis, replacing any weight it already has.
+    pub fn set_joint_axis_weight(&mut self, joint_idx: usize, joint_sub_idx: usize, weight: f64) -> Result<(), OptimaError> {
+        Self::check_weight(weight)?;
+        self.joint_axis_weights.retain(|w| !(w.joint_idx == joint_idx && w.joint_sub_idx == joint_sub_idx));
+        self.joint_axis_weights.push(JointAxisWeight { joint_idx, joint_sub_idx, weight });
+        Ok(())
+    }
+    /// The weight of the given joint axis.
+    pub fn weight(&self, joint_idx: usize, joint_sub_idx: usize) -> f64 {
+        return match self.joint_axis_weights.iter().find(|w| w.joint_idx == joint_idx && w.joint_sub_idx == joint_sub_idx) {
+            None => { self.default_weight }
+            Some(w) => { w.weight }
+        };
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn default_weight(&self) -> f64 {
+        self.default_weight
+    }
+    pub fn joint_axis_weights(&self) -> &Vec<JointAxisWeight> {
+        &self.joint_axis_weights
+    }
+    fn check_weight(weight: f64) -> Result<(), OptimaError> {
+        if !(weight > 0.0) || !weight.is_finite() {
+            return Err(OptimaError::new_generic_error_str(&format!("Joint weights must be positive and finite (given {}).", weight), file!(), line!()));
+        }
+        Ok(())
+    }
+}
+
+/// The weight of a single joint axis in a `JointWeightingProfile`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JointAxisWeight {
+    pub joint_idx: usize,
+    pub joint_sub_idx: usize,
+    pub weight: f64
+}
+
+/// A named reference posture, e.g., "home" or "elbow_up", given as values for some or all joint
+/// axes.  Consumers penalize deviation from the posture on the specified axes only (see
+/// `RobotJointStateModule::posture_deviation_cost` and `PosturePreference`), so repeated solves for
+/// nearby targets settle on consistent arm shapes.  Like `JointWeightingProfile`, values refer to
+/// joint axes rather than DOF indices.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NamedPosture {
+    name: String,
+    joint_axis_values: Vec<JointAxisValue>
+}
+impl NamedPosture {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            joint_axis_values: vec![]
+        }
+    }
+    /// Sets the value of the given joint axis, replacing any value it already has.
+    pub fn set_joint_axis_value(&mut self, joint_idx: usize, joint_sub_idx: usize, value: f64) -> Result<(), OptimaError> {
+        if !value.is_finite() {
+            return Err(OptimaError::new_generic_error_str(&format!("Posture values must be finite (given {}).", value), file!(), line!()));
+        }
+        self.joint_axis_values.retain(|v| !(v.joint_idx == joint_idx && v.joint_sub_idx == joint_sub_idx));
+        self.joint_axis_values.push(JointAxisValue { joint_idx, joint_sub_idx, value });
+        Ok(())
+    }
+    /// The value of the given joint axis, or None if the posture does not specify it.
+    pub fn value(&self, joint_idx: usize, joint_sub_idx: usize) -> Option<f64> {
+        return self.joint_axis_values.iter().find(|v| v.joint_idx == joint_idx && v.joint_sub_idx == joint_sub_idx).map(|v| v.value);
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn joint_axis_values(&self) -> &Vec<JointAxisValue> {
+        &self.joint_axis_values
+    }
+}
+
+/// The value of a single joint axis in a `NamedPosture`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JointAxisValue {
+    pub joint_idx: usize,
+    pub joint_sub_idx: usize,
+    pub value: f64
+}
+
+/// An object that describes a tool center point (TCP) frame.  The TCP frame is rigidly attached to
+/// the link at link_idx, and its pose is the link's pose multiplied by the given offset.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TCPFrameInfo {
+    tcp_frame_name: String,
+    link_idx: usize,
+    #[serde(with = "pose_all_as_quaternion_and_translation")]
+    offset: OptimaSE3PoseAll
+}
+impl TCPFrameInfo {
+    pub fn tcp_frame_name(&self) -> &str {
+        &self.tcp_frame_name
+    }
+    pub fn link_idx(&self) -> usize {
+        self.link_idx
+    }
+    pub fn offset(&self) -> &OptimaSE3PoseAll {
+        &self.offset
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct ContiguousChainInfo {
     chain_name: String,
     start_link_idx: usize,
     end_link_idx: Option<usize>,
+    #[serde(deserialize_with = "deserialize_tagged_or_legacy_enum")]
     mobility_mode: ContiguousChainMobilityMode
 }
 impl ContiguousChainInfo {
@@ -485,6 +1698,7 @@ impl ContiguousChainInfo {
 /// Note that this enum does not implicitly handle something like differential drive constraints,
 /// this would have to be handled separately.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type")]
 pub enum ContiguousChainMobilityMode {
     /// The robot's base is immobile.
     Static,
@@ -568,3 +1782,5 @@ pub enum ContiguousChainMobilityModeType {
     PlanarTranslationAndRotation
 }
 
+
+crate::impl_py_pickle!(RobotConfigurationModule);
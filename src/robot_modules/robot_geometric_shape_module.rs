@@ -1,31 +1,42 @@
 #[cfg(not(target_arch = "wasm32"))]
 use pyo3::*;
+#[cfg(not(target_arch = "wasm32"))]
+use numpy::{IntoPyArray, PyArray2};
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
 use std::time::{Duration, Instant};
-use nalgebra::{DVector, Vector3};
+use nalgebra::{DMatrix, DVector, Vector3};
 use parry3d_f64::query::Ray;
+use rand_chacha::ChaCha8Rng;
+use rand::SeedableRng;
 use serde::{Deserialize, Serialize};
 use crate::robot_modules::robot_configuration_module::RobotConfigurationModule;
 use crate::robot_modules::robot_mesh_file_manager_module::RobotMeshFileManagerModule;
 use crate::robot_modules::robot_kinematics_module::{RobotFKResult, RobotKinematicsModule};
 use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateModule, RobotJointStateType};
 use crate::robot_modules::robot_model_module::RobotModelModule;
+use crate::robot_modules::robot_shape_distance_lookup::{DistanceLookupTableConfig, RobotShapeDistanceLookupTables};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::robot_modules::robot_shape_preprocessing_report::{PairSkipReason, RepresentationPreprocessingReport, RobotShapePreprocessingReport, ShapePairPreprocessingRecord};
+use crate::utils::utils_cancellation::{CancellableOutput, CancellationToken};
 use crate::utils::utils_console::{optima_print, PrintColor, PrintMode};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::utils::utils_console::{get_default_progress_bar, ConsoleInputUtils};
 use crate::utils::utils_errors::OptimaError;
-use crate::utils::utils_files::optima_path::{load_object_from_json_string, OptimaAssetLocation, RobotModuleJsonType};
-use crate::utils::utils_generic_data_structures::{AveragingFloat, SquareArray2D};
+use crate::utils::utils_files::optima_path::{load_object_from_json_string, OptimaAssetLocation, OptimaStemCellPath, RobotModuleJsonType};
+use crate::utils::utils_generic_data_structures::{AveragingFloat, SquareArray2D, SquareArray2DStorageType};
 use crate::utils::utils_robot::robot_module_utils::RobotNames;
 use crate::utils::utils_se3::optima_se3_pose::OptimaSE3PoseType;
 use crate::utils::utils_shape_geometry::geometric_shape::{BVHCombinableShape, GeometricShapeQueryGroupOutput, GeometricShapeSignature, LogCondition, StopCondition};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::utils::utils_shape_geometry::geometric_shape::GeometricShapeQueryGroupOutputPy;
-use crate::utils::utils_shape_geometry::shape_collection::{BVHSceneFilterOutput, BVHVisit, ProximaBudget, ProximaEngine, ProximaProximityOutput, ProximaSceneFilterOutput, ShapeCollection, ShapeCollectionBVH, ShapeCollectionInputPoses, ShapeCollectionQuery, ShapeCollectionQueryList, ShapeCollectionQueryPairsList, SignedDistanceLossFunction};
-use crate::utils::utils_traits::{AssetSaveAndLoadable, SaveAndLoadable, ToAndFromRonString};
+#[cfg(target_arch = "wasm32")]
+use crate::utils::utils_shape_geometry::geometric_shape::GeometricShapeQueryGroupOutputJsObject;
+use crate::utils::utils_shape_geometry::query_output_summary::QueryOutputSummary;
+use crate::utils::utils_shape_geometry::shape_collection::{BVHSceneFilterOutput, BVHVisit, ProximaBudget, ProximaEngine, ProximaProximityOutput, ProximaSceneFilterOutput, QueryScratch, ShapeCollection, ShapeCollectionBVH, ShapeCollectionInputPoses, ShapeCollectionQuery, ShapeCollectionQueryList, ShapeCollectionQueryPairsList, ShapeCollectionWarmStartCache, SignedDistanceLossFunction};
+use crate::utils::utils_traits::{AssetSaveAndLoadable, quarantine_corrupted_file, SaveAndLoadable, ToAndFromJsonString, ToAndFromRonString};
 
 /// Robot module that provides useful functions over geometric shapes.  For example, the module is
 /// able to compute if a robot is in collision given a particular robot joint state.  For all geometry
@@ -40,43 +51,69 @@ pub struct RobotGeometricShapeModule {
     robot_joint_state_module: RobotJointStateModule,
     robot_kinematics_module: RobotKinematicsModule,
     robot_mesh_file_manager_module: RobotMeshFileManagerModule,
-    robot_shape_collections: Vec<RobotShapeCollection>
+    robot_shape_collections: Vec<RobotShapeCollection>,
+    /// Serialization strings of shape representations that were not loaded (see
+    /// `new_with_robot_link_shape_representations`), kept so that they can be loaded on demand and
+    /// are not lost when the module is saved.
+    #[serde(default)]
+    unloaded_robot_shape_collections: Vec<(RobotLinkShapeRepresentation, String)>,
+    /// If true, changes to the skip matrices are only kept in memory until `commit` is called (see
+    /// `set_defer_commits`).
+    #[serde(skip)]
+    defer_commits: bool,
+    #[serde(skip)]
+    has_uncommitted_changes: bool,
+    #[serde(default)]
+    auto_representation_policy: AutoRepresentationPolicy
 }
 impl RobotGeometricShapeModule {
     #[cfg(not(target_arch = "wasm32"))]
     pub fn new(robot_configuration_module: RobotConfigurationModule, force_preprocessing: bool) -> Result<Self, OptimaError> {
-        let robot_joint_state_module = RobotJointStateModule::new(robot_configuration_module.clone());
-        let robot_kinematics_module = RobotKinematicsModule::new(robot_configuration_module.clone());
-        let robot_mesh_file_manager_module = RobotMeshFileManagerModule::new_from_name(robot_configuration_module.robot_name())?;
         return if force_preprocessing {
-            let mut out_self = Self {
-                robot_joint_state_module,
-                robot_kinematics_module,
-                robot_mesh_file_manager_module,
-                robot_shape_collections: vec![]
-            };
-            out_self.preprocessing()?;
-            Ok(out_self)
+            Self::new_with_preprocessing_config(robot_configuration_module, &PreprocessingConfig::default())
         } else {
-            let robot_name = robot_kinematics_module.robot_name().to_string();
-            let res = Self::load_as_asset(OptimaAssetLocation::RobotModuleJson { robot_name, t: RobotModuleJsonType::ShapeGeometryModule });
+            let robot_name = robot_configuration_module.robot_name().to_string();
+            let res = Self::load_as_asset_or_quarantine(OptimaAssetLocation::RobotModuleJson { robot_name, t: RobotModuleJsonType::ShapeGeometryModule });
             match res {
-                Ok(res) => { Ok(res) }
-                Err(_) => { Self::new(robot_configuration_module, true) }
+                Ok(Some(res)) => { Ok(res) }
+                _ => { Self::new(robot_configuration_module, true) }
             }
         }
     }
+    /// Always preprocesses the module, using the given settings (see `PreprocessingConfig`).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new_with_preprocessing_config(robot_configuration_module: RobotConfigurationModule, preprocessing_config: &PreprocessingConfig) -> Result<Self, OptimaError> {
+        let robot_kinematics_module = RobotKinematicsModule::new(robot_configuration_module.clone());
+        let robot_joint_state_module = robot_kinematics_module.robot_joint_state_module().clone();
+        let robot_mesh_file_manager_module = RobotMeshFileManagerModule::new_from_name(robot_configuration_module.robot_name())?;
+        let mut out_self = Self {
+            robot_joint_state_module,
+            robot_kinematics_module,
+            robot_mesh_file_manager_module,
+            robot_shape_collections: vec![],
+            unloaded_robot_shape_collections: vec![],
+            defer_commits: false,
+            has_uncommitted_changes: false,
+            auto_representation_policy: AutoRepresentationPolicy::default()
+        };
+        out_self.preprocessing(preprocessing_config)?;
+        Ok(out_self)
+    }
     #[cfg(target_arch = "wasm32")]
     pub fn new(robot_configuration_module: RobotConfigurationModule, force_preprocessing: bool) -> Result<Self, OptimaError> {
-        let robot_joint_state_module = RobotJointStateModule::new(robot_configuration_module.clone());
         let robot_kinematics_module = RobotKinematicsModule::new(robot_configuration_module.clone());
+        let robot_joint_state_module = robot_kinematics_module.robot_joint_state_module().clone();
         let robot_mesh_file_manager_module = RobotMeshFileManagerModule::new_from_name(robot_configuration_module.robot_name())?;
         return if force_preprocessing {
             let mut out_self = Self {
                 robot_joint_state_module,
                 robot_kinematics_module,
                 robot_mesh_file_manager_module,
-                robot_shape_collections: vec![]
+                robot_shape_collections: vec![],
+                unloaded_robot_shape_collections: vec![],
+                defer_commits: false,
+                has_uncommitted_changes: false,
+                auto_representation_policy: AutoRepresentationPolicy::default()
             };
             Err(OptimaError::new_generic_error_str("Cannot preprocess geometric shape module from WASM.", file!(), line!()))
         } else {
@@ -92,8 +129,77 @@ impl RobotGeometricShapeModule {
         let robot_configuration_module = RobotConfigurationModule::new_from_names(robot_names)?;
         Self::new(robot_configuration_module, force_preprocessing)
     }
+    /// Same as `new`, but only the given shape representations are loaded.  The others are kept as
+    /// unparsed serialization strings, which cuts startup time and memory substantially when only
+    /// one or two representations are ever queried (the subcomponent and triangle mesh
+    /// representations are by far the most expensive to load).  An unloaded representation can be
+    /// loaded later with `load_robot_link_shape_representation`.
+    ///
+    /// NOTE: settings such as collision margins only apply to representations that are loaded when
+    /// they are set.
+    pub fn new_with_robot_link_shape_representations(robot_configuration_module: RobotConfigurationModule, force_preprocessing: bool, robot_link_shape_representations: &Vec<RobotLinkShapeRepresentation>) -> Result<Self, OptimaError> {
+        if !force_preprocessing {
+            let mut path = OptimaStemCellPath::new_asset_path()?;
+            path.append_file_location(&OptimaAssetLocation::RobotModuleJson { robot_name: robot_configuration_module.robot_name().to_string(), t: RobotModuleJsonType::ShapeGeometryModule });
+            if let Ok(json_str) = path.read_file_contents_to_string() {
+                match Self::load_from_json_string_with_robot_link_shape_representations(&json_str, Some(robot_link_shape_representations)) {
+                    Ok(out_self) => { return Ok(out_self); }
+                    Err(e) => { quarantine_corrupted_file(&path, &e); }
+                }
+            }
+        }
+
+        let mut out_self = Self::new(robot_configuration_module, force_preprocessing)?;
+        for robot_link_shape_representation in Self::get_all_robot_link_shape_representations() {
+            if !robot_link_shape_representations.contains(&robot_link_shape_representation) {
+                out_self.unload_robot_link_shape_representation(&robot_link_shape_representation)?;
+            }
+        }
+        Ok(out_self)
+    }
+    pub fn new_from_names_with_robot_link_shape_representations(robot_names: RobotNames, force_preprocessing: bool, robot_link_shape_representations: &Vec<RobotLinkShapeRepresentation>) -> Result<Self, OptimaError> {
+        let robot_configuration_module = RobotConfigurationModule::new_from_names(robot_names)?;
+        Self::new_with_robot_link_shape_representations(robot_configuration_module, force_preprocessing, robot_link_shape_representations)
+    }
+    /// Loads a shape representation that was left unloaded.  Does nothing if it is already loaded.
+    pub fn load_robot_link_shape_representation(&mut self, robot_link_shape_representation: &RobotLinkShapeRepresentation) -> Result<(), OptimaError> {
+        if self.robot_link_shape_representation_is_loaded(robot_link_shape_representation) { return Ok(()); }
+
+        let idx = self.unloaded_robot_shape_collections.iter().position(|(r, _)| r == robot_link_shape_representation);
+        return match idx {
+            None => {
+                Err(OptimaError::new_generic_error_str(&format!("Robot link shape representation {:?} is not available in this module.", robot_link_shape_representation), file!(), line!()))
+            }
+            Some(idx) => {
+                let robot_shape_collection = Self::load_robot_shape_collection_from_json_string(&self.unloaded_robot_shape_collections[idx].1)?;
+                self.unloaded_robot_shape_collections.remove(idx);
+                self.robot_shape_collections.push(robot_shape_collection);
+                Ok(())
+            }
+        }
+    }
+    /// Frees a loaded shape representation, keeping only its serialization string so that it can be
+    /// loaded again with `load_robot_link_shape_representation`.
+    pub fn unload_robot_link_shape_representation(&mut self, robot_link_shape_representation: &RobotLinkShapeRepresentation) -> Result<(), OptimaError> {
+        let idx = self.robot_shape_collections.iter().position(|s| &s.robot_link_shape_representation == robot_link_shape_representation);
+        if let Some(idx) = idx {
+            let robot_shape_collection = self.robot_shape_collections.remove(idx);
+            self.unloaded_robot_shape_collections.push((robot_link_shape_representation.clone(), robot_shape_collection.get_serialization_string()));
+        }
+        Ok(())
+    }
+    pub fn robot_link_shape_representation_is_loaded(&self, robot_link_shape_representation: &RobotLinkShapeRepresentation) -> bool {
+        return self.robot_shape_collections.iter().any(|s| &s.robot_link_shape_representation == robot_link_shape_representation);
+    }
+    pub fn loaded_robot_link_shape_representations(&self) -> Vec<RobotLinkShapeRepresentation> {
+        let mut out_vec = vec![];
+        for s in &self.robot_shape_collections {
+            out_vec.push(s.robot_link_shape_representation.clone());
+        }
+        out_vec
+    }
     #[cfg(not(target_arch = "wasm32"))]
-    fn preprocessing(&mut self) -> Result<(), OptimaError> {
+    fn preprocessing(&mut self, preprocessing_config: &PreprocessingConfig) -> Result<(), OptimaError> {
         let robot_link_shape_representations = vec![
             RobotLinkShapeRepresentation::Cubes,
             RobotLinkShapeRepresentation::ConvexShapes,
@@ -103,20 +209,24 @@ impl RobotGeometricShapeModule {
             RobotLinkShapeRepresentation::TriangleMeshes
         ];
 
+        let mut report = RobotShapePreprocessingReport::new(self.robot_kinematics_module.robot_name(), preprocessing_config.deterministic().clone());
         for robot_link_shape_representation in &robot_link_shape_representations {
-            self.preprocessing_robot_geometric_shape_collection(robot_link_shape_representation)?;
+            let representation_report = self.preprocessing_robot_geometric_shape_collection(robot_link_shape_representation, preprocessing_config)?;
+            report.add_representation_report(representation_report);
         }
+        report.save_as_asset()?;
 
         Ok(())
     }
     #[cfg(not(target_arch = "wasm32"))]
     fn preprocessing_robot_geometric_shape_collection(&mut self,
-                                                      robot_link_shape_representation: &RobotLinkShapeRepresentation) -> Result<(), OptimaError> {
+                                                      robot_link_shape_representation: &RobotLinkShapeRepresentation,
+                                                      preprocessing_config: &PreprocessingConfig) -> Result<RepresentationPreprocessingReport, OptimaError> {
         optima_print(&format!("Setup on {:?}...", robot_link_shape_representation), PrintMode::Println, PrintColor::Blue, true);
         // Base model modules must be used as these computations apply to all derived configuration
         // variations of this model, not just particular configurations.
         let robot_name = self.robot_kinematics_module.robot_name();
-        let base_robot_model_module = RobotModelModule::new(robot_name)?;
+        let base_robot_model_module = RobotModelModule::new_shared(robot_name)?;
         let base_robot_kinematics_module = RobotKinematicsModule::new_from_names(RobotNames::new_base(robot_name))?;
         let base_robot_joint_state_module = RobotJointStateModule::new_from_names(RobotNames::new_base(robot_name))?;
         let num_links = base_robot_model_module.links().len();
@@ -140,18 +250,26 @@ impl RobotGeometricShapeModule {
         let mut collision_counter_array = SquareArray2D::<f64>::new(num_shapes, true, None);
 
         // This loop takes random robot joint state samples and determines intersection and average
-        // distance information between links.
+        // distance information between links.  In deterministic mode, exactly the given number of
+        // samples is drawn from a seeded random number generator, regardless of how long it takes.
         let start = Instant::now();
         let mut count = 0.0;
-        let max_samples = 100_000;
-        let min_samples = 70;
+        let max_samples = match preprocessing_config.deterministic() {
+            None => { 100_000 }
+            Some(deterministic) => { deterministic.num_samples() }
+        };
+        // Time-based stopping waits until the always colliding rule can apply.
+        let skip_decision_thresholds = preprocessing_config.skip_decision_thresholds(robot_link_shape_representation).clone();
+        let min_samples = skip_decision_thresholds.always_colliding_min_samples().max(70);
+        let representation_idx = Self::get_all_robot_link_shape_representations().iter().position(|r| r == robot_link_shape_representation).unwrap_or(0);
+        let mut rng = preprocessing_config.spawn_rng(representation_idx as u64);
 
         let mut pb = get_default_progress_bar(1000);
 
         // Where distances and intersections are actually checked at each joint state sample.
         for i in 0..max_samples {
             count += 1.0;
-            let sample = base_robot_joint_state_module.sample_joint_state(&RobotJointStateType::Full);
+            let sample = base_robot_joint_state_module.sample_joint_state_with_rng(&RobotJointStateType::Full, &mut rng);
             let fk_res = base_robot_kinematics_module.compute_fk(&sample, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
             let poses = robot_shape_collection.recover_poses(&fk_res)?;
             let input = ShapeCollectionQuery::Distance { poses: &poses, inclusion_list: &None };
@@ -176,64 +294,92 @@ impl RobotGeometricShapeModule {
             let duration_ratio = duration.as_secs_f64() / self.stop_at_min_sample_duration(robot_link_shape_representation).as_secs_f64();
             let max_sample_ratio = i as f64 / max_samples as f64;
             let min_sample_ratio = i as f64 / min_samples as f64;
-            let ratio = duration_ratio.max(max_sample_ratio).min(min_sample_ratio);
+            let ratio = if preprocessing_config.deterministic().is_some() { max_sample_ratio } else { duration_ratio.max(max_sample_ratio).min(min_sample_ratio) };
             pb.set((ratio * 1000.0) as u64);
             pb.message(&format!("sample {} ", i));
 
-            if duration > self.stop_at_min_sample_duration(robot_link_shape_representation) && i >= min_samples { break; }
+            if preprocessing_config.deterministic().is_none() && duration > self.stop_at_min_sample_duration(robot_link_shape_representation) && i >= min_samples { break; }
         }
 
         // Determines average distances and decides if links should be skipped based on previous
-        // computations.  These reesults are saved in the RobotGeometricShapeCollection.
+        // computations.  These reesults are saved in the RobotGeometricShapeCollection, and the
+        // reason for every decision is recorded in the report.
+        let mut pair_records = vec![];
         for i in 0..num_shapes {
             for j in 0..num_shapes {
                 // Retrieves and saves the average distance between the given pair of links.
-                let averaging_float = distance_average_array.data_cell(i, j)?;
+                let averaging_float = distance_average_array.data_cell(i, j)?.clone();
                 robot_shape_collection.shape_collection.replace_average_distance_from_idxs(averaging_float.value(), i, j)?;
 
                 // Pairwise checks should never happen between the same shape.
-                if i == j { robot_shape_collection.shape_collection.replace_skip_from_idxs(true, i, j)?; }
+                if i == j {
+                    robot_shape_collection.shape_collection.replace_skip_from_idxs(true, i, j)?;
+                    continue;
+                }
 
                 let shapes = robot_shape_collection.shape_collection.shapes();
-                let signature1 = shapes[i].signature();
-                let signature2 = shapes[j].signature();
-                match signature1 {
-                    GeometricShapeSignature::RobotLink { link_idx, shape_idx_in_link: _ } => {
-                        let link_idx1 = link_idx.clone();
-                        match signature2 {
-                            GeometricShapeSignature::RobotLink { link_idx, shape_idx_in_link: _ } => {
-                                let link_idx2 = link_idx.clone();
-                                if link_idx1 == link_idx2 {
-                                    robot_shape_collection.shape_collection.replace_skip_from_idxs(true, i, j)?;
-                                }
-                            }
-                            _ => { }
-                        }
-                    }
-                    _ => { }
-                }
+                let link_idx1 = match shapes[i].signature() { GeometricShapeSignature::RobotLink { link_idx, .. } => { Some(*link_idx) } _ => { None } };
+                let link_idx2 = match shapes[j].signature() { GeometricShapeSignature::RobotLink { link_idx, .. } => { Some(*link_idx) } _ => { None } };
+                let num_collisions = *collision_counter_array.data_cell(i, j)?;
+                let ratio_of_checks_in_collision = num_collisions / count;
 
-                // Checks if links are always in intersecting.
-                let ratio_of_checks_in_collision = collision_counter_array.data_cell(i, j)? / count;
-                if count >= min_samples as f64 && ratio_of_checks_in_collision > 0.99 {
+                let skip_reason = if link_idx1.is_some() && link_idx1 == link_idx2 {
+                    Some(PairSkipReason::SameLink)
+                } else if skip_decision_thresholds.is_always_colliding(count as usize, ratio_of_checks_in_collision) {
+                    // Links are always intersecting.
+                    Some(PairSkipReason::AlwaysColliding)
+                } else if skip_decision_thresholds.is_never_colliding(count as usize, ratio_of_checks_in_collision) {
+                    // Links are never in collision.
+                    Some(PairSkipReason::NeverColliding)
+                } else {
+                    None
+                };
+                if skip_reason.is_some() {
                     robot_shape_collection.shape_collection.replace_skip_from_idxs(true, i, j)?;
                 }
 
-                // Checks if links are never in collision
-                if count >= 1000.0 && ratio_of_checks_in_collision == 0.0 {
-                    robot_shape_collection.shape_collection.replace_skip_from_idxs(true, i, j)?;
+                if i < j {
+                    let link_name = |link_idx: Option<usize>| { link_idx.map(|l| base_robot_model_module.links()[l].name().to_string()).unwrap_or_default() };
+                    pair_records.push(ShapePairPreprocessingRecord {
+                        shape_idx1: i,
+                        shape_idx2: j,
+                        link_name1: link_name(link_idx1),
+                        link_name2: link_name(link_idx2),
+                        num_samples: averaging_float.count() as usize,
+                        num_collisions: num_collisions as usize,
+                        collision_ratio: ratio_of_checks_in_collision,
+                        average_distance: averaging_float.value(),
+                        skip_reason
+                    });
                 }
             }
         }
+        let representation_report = RepresentationPreprocessingReport {
+            robot_link_shape_representation: robot_link_shape_representation.clone(),
+            num_samples: count as usize,
+            skip_decision_thresholds,
+            pairs: pair_records
+        };
 
         pb.finish();
         println!();
 
+        if let Some(distance_lookup_table_config) = preprocessing_config.distance_lookup_table_config() {
+            if distance_lookup_table_config.robot_link_shape_representations().contains(robot_link_shape_representation) {
+                optima_print("Building distance lookup tables...", PrintMode::Println, PrintColor::Blue, true);
+                let distance_lookup_tables = RobotShapeDistanceLookupTables::build(&robot_shape_collection, &base_robot_model_module, &base_robot_kinematics_module, &base_robot_joint_state_module, distance_lookup_table_config, &mut rng)?;
+                robot_shape_collection.set_distance_lookup_tables(Some(distance_lookup_tables));
+            }
+        }
+
+        let (skips_storage_type, average_distances_storage_type) = Self::default_pairwise_storage_types(robot_link_shape_representation);
+        robot_shape_collection.shape_collection.set_pairwise_storage_types(skips_storage_type, average_distances_storage_type)?;
+
         self.robot_shape_collections.push(robot_shape_collection);
         self.save_as_asset(OptimaAssetLocation::RobotModuleJson { robot_name: robot_name.to_string(), t: RobotModuleJsonType::ShapeGeometryModule })?;
         self.save_as_asset(OptimaAssetLocation::RobotModuleJson { robot_name: robot_name.to_string(), t: RobotModuleJsonType::ShapeGeometryModulePermanent })?;
 
-        Ok(())
+        Ok(representation_report)
     }
     fn get_all_robot_link_shape_representations() -> Vec<RobotLinkShapeRepresentation> {
         let robot_link_shape_representations = vec![
@@ -250,13 +396,38 @@ impl RobotGeometricShapeModule {
         for s in &self.robot_shape_collections {
             if &s.robot_link_shape_representation == shape_representation { return Ok(s) }
         }
-        unreachable!()
+        Err(Self::representation_not_loaded_error(shape_representation))
     }
     fn robot_geometric_shape_collection_mut(&mut self, shape_representation: &RobotLinkShapeRepresentation) -> Result<&mut RobotShapeCollection, OptimaError> {
         for s in &mut self.robot_shape_collections {
             if &s.robot_link_shape_representation == shape_representation { return Ok(s) }
         }
-        unreachable!()
+        Err(Self::representation_not_loaded_error(shape_representation))
+    }
+    fn representation_not_loaded_error(shape_representation: &RobotLinkShapeRepresentation) -> OptimaError {
+        if shape_representation == &RobotLinkShapeRepresentation::Auto {
+            return OptimaError::new_generic_error_str("The Auto robot link shape representation can only be used with shape_collection_query and shape_collection_query_batch.", file!(), line!());
+        }
+        OptimaError::new_generic_error_str(&format!("Robot link shape representation {:?} is not loaded.  Use load_robot_link_shape_representation to load it.", shape_representation), file!(), line!())
+    }
+    /// Sets the collision margin applied to every link in all shape representations (see
+    /// `CollisionMargins`).  Links with a margin set via `set_link_collision_margin` keep their own.
+    pub fn set_global_collision_margin(&mut self, margin: f64) -> Result<(), OptimaError> {
+        for s in &mut self.robot_shape_collections {
+            s.shape_collection.set_global_collision_margin(margin)?;
+        }
+        Ok(())
+    }
+    /// Overrides the global collision margin on all shapes of the given link.  Passing None reverts
+    /// the link to the global margin.
+    pub fn set_link_collision_margin(&mut self, link_idx: usize, margin: Option<f64>) -> Result<(), OptimaError> {
+        for s in &mut self.robot_shape_collections {
+            let shape_idxs = s.get_shape_idxs_from_link_idx(link_idx)?.clone();
+            for shape_idx in shape_idxs {
+                s.shape_collection.set_shape_collision_margin(shape_idx, margin)?;
+            }
+        }
+        Ok(())
     }
     pub fn shape_collection_query<'a>(&'a self,
                                       input: &'a RobotShapeCollectionQuery,
@@ -264,6 +435,11 @@ impl RobotGeometricShapeModule {
                                       stop_condition: StopCondition,
                                       log_condition: LogCondition,
                                       sort_outputs: bool) -> Result<GeometricShapeQueryGroupOutput, OptimaError> {
+        if robot_link_shape_representation == RobotLinkShapeRepresentation::Auto {
+            let (output, _) = self.shape_collection_query_auto(input, log_condition, sort_outputs)?;
+            return Ok(output);
+        }
+
         return match input {
             RobotShapeCollectionQuery::ProjectPoint { robot_joint_state, point, solid, inclusion_list } => {
                 let res = self.robot_kinematics_module.compute_fk(robot_joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
@@ -386,6 +562,289 @@ impl RobotGeometricShapeModule {
         }
     }
 
+    /// Answers the query as described in `AutoRepresentationPolicy`, and also returns the
+    /// representation that answered it.  Representations that do not answer the query are run
+    /// until their minimum distance drops to the escalation distance, and the answering
+    /// representation is run without a stop condition so that its minimum distance is exact.
+    pub fn shape_collection_query_auto(&self,
+                                       input: &RobotShapeCollectionQuery,
+                                       log_condition: LogCondition,
+                                       sort_outputs: bool) -> Result<(GeometricShapeQueryGroupOutput, RobotLinkShapeRepresentation), OptimaError> {
+        let mut candidates = vec![];
+        for r in self.auto_representation_policy.escalation_order() {
+            if self.robot_link_shape_representation_is_loaded(r) { candidates.push(r.clone()); }
+        }
+        let last = match candidates.pop() {
+            None => { return Err(OptimaError::new_generic_error_str(&format!("None of the Auto representation escalation order {:?} is loaded.", self.auto_representation_policy.escalation_order()), file!(), line!())); }
+            Some(last) => { last }
+        };
+
+        let certifiable = match input {
+            RobotShapeCollectionQuery::ProjectPoint { .. } => { false }
+            RobotShapeCollectionQuery::CCD { .. } => { false }
+            _ => { true }
+        };
+        if certifiable {
+            let escalation_distance = self.auto_representation_policy.escalation_distance();
+            for r in candidates {
+                let output = self.shape_collection_query(input, r.clone(), StopCondition::BelowMinDistance(escalation_distance), log_condition.clone(), sort_outputs)?;
+                if output.minimum_distance() > escalation_distance { return Ok((output, r)); }
+            }
+        }
+
+        let output = self.shape_collection_query(input, last.clone(), StopCondition::None, log_condition, sort_outputs)?;
+        return Ok((output, last));
+    }
+    /// Returns the link by link matrix of minimum distances at the given state: entry (i, j) is the
+    /// minimum distance between any shape of link i and any shape of link j in the given
+    /// representation.  Intersecting links have a distance of 0.  The matrix is symmetric, and
+    /// entries for pairs that were not computed (the diagonal, skipped pairs, and links without
+    /// geometry) are NaN.  The matrix is indexed by link index, so it has the same size for every
+    /// representation.
+    pub fn link_distance_matrix(&self, robot_joint_state: &RobotJointState, robot_link_shape_representation: RobotLinkShapeRepresentation) -> Result<DMatrix<f64>, OptimaError> {
+        let num_links = self.robot_kinematics_module.robot_configuration_module().robot_model_module().links().len();
+        let mut out_matrix = DMatrix::from_element(num_links, num_links, f64::NAN);
+
+        let input = RobotShapeCollectionQuery::Distance { robot_joint_state, inclusion_list: &None };
+        let res = self.shape_collection_query(&input, robot_link_shape_representation, StopCondition::None, LogCondition::LogAll, false)?;
+        for output in res.outputs() {
+            let signatures = output.signatures();
+            let (link_idx1, link_idx2) = match (&signatures[0], &signatures[1]) {
+                (GeometricShapeSignature::RobotLink { link_idx: link_idx1, .. }, GeometricShapeSignature::RobotLink { link_idx: link_idx2, .. }) => { (*link_idx1, *link_idx2) }
+                _ => { continue; }
+            };
+            let distance = output.raw_output().unwrap_distance()?;
+            let current = out_matrix[(link_idx1, link_idx2)];
+            if current.is_nan() || distance < current {
+                out_matrix[(link_idx1, link_idx2)] = distance;
+                out_matrix[(link_idx2, link_idx1)] = distance;
+            }
+        }
+
+        Ok(out_matrix)
+    }
+    /// Returns the k smallest entries of `link_distance_matrix` as (link_idx1, link_idx2, distance)
+    /// tuples with link_idx1 < link_idx2, sorted from smallest to largest distance.  Fewer than k
+    /// entries are returned if fewer link pairs were computed.
+    pub fn k_smallest_link_distances(&self, robot_joint_state: &RobotJointState, robot_link_shape_representation: RobotLinkShapeRepresentation, k: usize) -> Result<Vec<(usize, usize, f64)>, OptimaError> {
+        let matrix = self.link_distance_matrix(robot_joint_state, robot_link_shape_representation)?;
+        let mut out_vec = vec![];
+        for i in 0..matrix.nrows() {
+            for j in (i + 1)..matrix.ncols() {
+                let distance = matrix[(i, j)];
+                if !distance.is_nan() { out_vec.push((i, j, distance)); }
+            }
+        }
+        out_vec.sort_by(|a, b| a.2.partial_cmp(&b.2).unwrap());
+        out_vec.truncate(k);
+        Ok(out_vec)
+    }
+    /// Returns the distances of all non-skipped shape pairs at the given state, using the
+    /// representation's distance lookup tables where they are trustworthy and exact distance queries
+    /// everywhere else.  A pair is answered by its table if the predicted distance minus the table's
+    /// max_error is greater than margin, i.e., if the pair is certainly (up to the validation error of
+    /// the table) farther apart than margin; pairs near or below the margin, and pairs without a
+    /// table, fall back to an exact query.  Distances include collision margins, as in
+    /// `shape_collection_query`.  Without lookup tables (see `PreprocessingConfig`), every pair is
+    /// answered exactly.
+    pub fn approximate_distance_query(&self, robot_joint_state: &RobotJointState, robot_link_shape_representation: RobotLinkShapeRepresentation, margin: f64) -> Result<ApproximateDistanceQueryOutput, OptimaError> {
+        let start = Instant::now();
+        let collection = self.robot_shape_collection(&robot_link_shape_representation)?;
+        let shape_collection = collection.shape_collection();
+        let num_shapes = shape_collection.shapes().len();
+
+        let mut pair_distances = vec![];
+        let mut approximated = vec![vec![false; num_shapes]; num_shapes];
+        if let Some(distance_lookup_tables) = collection.distance_lookup_tables() {
+            let full_joint_state = self.robot_joint_state_module.convert_joint_state_to_full_state(robot_joint_state)?;
+            let grid_points = distance_lookup_tables.grid_points(&self.robot_joint_state_module, &full_joint_state);
+            for (table_idx, table) in distance_lookup_tables.tables().iter().enumerate() {
+                let (i, j) = (table.shape_idx1(), table.shape_idx2());
+                if *shape_collection.skips().data_cell(i, j)?.curr_value() { continue; }
+                if let Some(prediction) = distance_lookup_tables.predict(table_idx, &grid_points)? {
+                    let distance = prediction - shape_collection.collision_margins().pair_margin(i, j);
+                    if distance - table.max_error() > margin {
+                        pair_distances.push(ApproximatePairDistance { shape_idx1: i, shape_idx2: j, distance, exact: false });
+                        approximated[i][j] = true;
+                    }
+                }
+            }
+        }
+        let num_approximate = pair_distances.len();
+
+        let mut pairs_list = shape_collection.spawn_query_pairs_list(false);
+        for i in 0..num_shapes {
+            for j in (i + 1)..num_shapes {
+                if !approximated[i][j] && !*shape_collection.skips().data_cell(i, j)?.curr_value() { pairs_list.add_pair((i, j)); }
+            }
+        }
+        if !pairs_list.pairs().is_empty() {
+            let fk_res = self.robot_kinematics_module.compute_fk(robot_joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+            let poses = collection.recover_poses(&fk_res)?;
+            let input = ShapeCollectionQuery::Distance { poses: &poses, inclusion_list: &Some(&pairs_list) };
+            let res = shape_collection.shape_collection_query(&input, StopCondition::None, LogCondition::LogAll, false)?;
+            for output in res.outputs() {
+                let signatures = output.signatures();
+                let shape_idx1 = shape_collection.get_shape_idx_from_signature(&signatures[0])?;
+                let shape_idx2 = shape_collection.get_shape_idx_from_signature(&signatures[1])?;
+                pair_distances.push(ApproximatePairDistance { shape_idx1, shape_idx2, distance: output.raw_output().unwrap_distance()?, exact: true });
+            }
+        }
+
+        let num_exact = pair_distances.len() - num_approximate;
+        Ok(ApproximateDistanceQueryOutput {
+            pair_distances,
+            num_approximate,
+            num_exact,
+            duration: start.elapsed()
+        })
+    }
+    pub fn set_auto_representation_policy(&mut self, auto_representation_policy: AutoRepresentationPolicy) {
+        self.auto_representation_policy = auto_representation_policy;
+    }
+    pub fn auto_representation_policy(&self) -> &AutoRepresentationPolicy {
+        &self.auto_representation_policy
+    }
+    /// Runs `shape_collection_query` on each input in order, stopping early once the given token is
+    /// cancelled.  A cancelled output holds the results of the queries that finished, so its i-th
+    /// entry still corresponds to the i-th input.
+    pub fn shape_collection_query_batch(&self,
+                                        inputs: &Vec<RobotShapeCollectionQuery>,
+                                        robot_link_shape_representation: RobotLinkShapeRepresentation,
+                                        stop_condition: StopCondition,
+                                        log_condition: LogCondition,
+                                        sort_outputs: bool,
+                                        cancellation_token: &CancellationToken) -> Result<CancellableOutput<Vec<GeometricShapeQueryGroupOutput>>, OptimaError> {
+        let mut out_vec = vec![];
+        for input in inputs {
+            if cancellation_token.is_cancelled() { return Ok(CancellableOutput::Cancelled(out_vec)); }
+            out_vec.push(self.shape_collection_query(input, robot_link_shape_representation.clone(), stop_condition.clone(), log_condition.clone(), sort_outputs)?);
+        }
+        return Ok(CancellableOutput::Complete(out_vec));
+    }
+
+    /// Same as `shape_collection_query`, but intersection tests, distance queries, and contact
+    /// queries are warm started from the given cache (see
+    /// `ShapeCollection::shape_collection_query_warm_started`).  Use one cache per representation and
+    /// stream of consecutive states, e.g., the states along a trajectory.  Other query types are
+    /// passed through to `shape_collection_query`.
+    pub fn shape_collection_query_warm_started(&self,
+                                               input: &RobotShapeCollectionQuery,
+                                               robot_link_shape_representation: RobotLinkShapeRepresentation,
+                                               stop_condition: StopCondition,
+                                               log_condition: LogCondition,
+                                               sort_outputs: bool,
+                                               cache: &mut ShapeCollectionWarmStartCache) -> Result<GeometricShapeQueryGroupOutput, OptimaError> {
+        return match input {
+            RobotShapeCollectionQuery::IntersectionTest { robot_joint_state, inclusion_list } => {
+                let res = self.robot_kinematics_module.compute_fk(robot_joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+                let collection = self.robot_shape_collection(&robot_link_shape_representation)?;
+                let poses = collection.recover_poses(&res)?;
+                collection.shape_collection.shape_collection_query_warm_started(&ShapeCollectionQuery::IntersectionTest {
+                    poses: &poses,
+                    inclusion_list
+                }, stop_condition, log_condition, sort_outputs, cache)
+            }
+            RobotShapeCollectionQuery::Distance { robot_joint_state, inclusion_list } => {
+                let res = self.robot_kinematics_module.compute_fk(robot_joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+                let collection = self.robot_shape_collection(&robot_link_shape_representation)?;
+                let poses = collection.recover_poses(&res)?;
+                collection.shape_collection.shape_collection_query_warm_started(&ShapeCollectionQuery::Distance {
+                    poses: &poses,
+                    inclusion_list
+                }, stop_condition, log_condition, sort_outputs, cache)
+            }
+            RobotShapeCollectionQuery::Contact { robot_joint_state, prediction, inclusion_list } => {
+                let res = self.robot_kinematics_module.compute_fk(robot_joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+                let collection = self.robot_shape_collection(&robot_link_shape_representation)?;
+                let poses = collection.recover_poses(&res)?;
+                collection.shape_collection.shape_collection_query_warm_started(&ShapeCollectionQuery::Contact {
+                    poses: &poses,
+                    prediction: *prediction,
+                    inclusion_list
+                }, stop_condition, log_condition, sort_outputs, cache)
+            }
+            _ => { self.shape_collection_query(input, robot_link_shape_representation, stop_condition, log_condition, sort_outputs) }
+        }
+    }
+    /// Same as `shape_collection_query`, but reuses the buffers in the given `QueryScratch` (see
+    /// `ShapeCollection::shape_collection_query_with_scratch`), including the buffer for the shape
+    /// poses.  Forward kinematics still allocates its result on every call, so unlike the shape
+    /// collection query itself, this is not allocation free.  CCD queries are not supported here
+    /// since they need two sets of poses.
+    pub fn shape_collection_query_with_scratch<'s>(&self,
+                                                   input: &RobotShapeCollectionQuery,
+                                                   robot_link_shape_representation: RobotLinkShapeRepresentation,
+                                                   stop_condition: StopCondition,
+                                                   log_condition: LogCondition,
+                                                   sort_outputs: bool,
+                                                   scratch: &'s mut QueryScratch) -> Result<&'s GeometricShapeQueryGroupOutput, OptimaError> {
+        let robot_joint_state = match input {
+            RobotShapeCollectionQuery::ProjectPoint { robot_joint_state, .. } => { robot_joint_state }
+            RobotShapeCollectionQuery::ContainsPoint { robot_joint_state, .. } => { robot_joint_state }
+            RobotShapeCollectionQuery::DistanceToPoint { robot_joint_state, .. } => { robot_joint_state }
+            RobotShapeCollectionQuery::IntersectsRay { robot_joint_state, .. } => { robot_joint_state }
+            RobotShapeCollectionQuery::CastRay { robot_joint_state, .. } => { robot_joint_state }
+            RobotShapeCollectionQuery::CastRayAndGetNormal { robot_joint_state, .. } => { robot_joint_state }
+            RobotShapeCollectionQuery::IntersectionTest { robot_joint_state, .. } => { robot_joint_state }
+            RobotShapeCollectionQuery::Distance { robot_joint_state, .. } => { robot_joint_state }
+            RobotShapeCollectionQuery::ClosestPoints { robot_joint_state, .. } => { robot_joint_state }
+            RobotShapeCollectionQuery::Contact { robot_joint_state, .. } => { robot_joint_state }
+            RobotShapeCollectionQuery::CCD { .. } => {
+                return Err(OptimaError::new_unsupported_operation_error("shape_collection_query_with_scratch", "CCD queries are not supported with a query scratch.", file!(), line!()));
+            }
+        };
+
+        let res = self.robot_kinematics_module.compute_fk(robot_joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+        let collection = self.robot_shape_collection(&robot_link_shape_representation)?;
+        let mut poses = match scratch.take_poses() {
+            None => { ShapeCollectionInputPoses::new(&collection.shape_collection) }
+            Some(poses) => { poses }
+        };
+        if let Err(e) = collection.recover_poses_into(&res, &mut poses) {
+            scratch.return_poses(poses);
+            return Err(e);
+        }
+
+        let query_res = match input {
+            RobotShapeCollectionQuery::ProjectPoint { point, solid, inclusion_list, .. } => {
+                collection.shape_collection.shape_collection_query_with_scratch(&ShapeCollectionQuery::ProjectPoint { poses: &poses, point, solid: *solid, inclusion_list }, stop_condition, log_condition, sort_outputs, scratch).map(|_| ())
+            }
+            RobotShapeCollectionQuery::ContainsPoint { point, inclusion_list, .. } => {
+                collection.shape_collection.shape_collection_query_with_scratch(&ShapeCollectionQuery::ContainsPoint { poses: &poses, point, inclusion_list }, stop_condition, log_condition, sort_outputs, scratch).map(|_| ())
+            }
+            RobotShapeCollectionQuery::DistanceToPoint { point, solid, inclusion_list, .. } => {
+                collection.shape_collection.shape_collection_query_with_scratch(&ShapeCollectionQuery::DistanceToPoint { poses: &poses, point, solid: *solid, inclusion_list }, stop_condition, log_condition, sort_outputs, scratch).map(|_| ())
+            }
+            RobotShapeCollectionQuery::IntersectsRay { ray, max_toi, inclusion_list, .. } => {
+                collection.shape_collection.shape_collection_query_with_scratch(&ShapeCollectionQuery::IntersectsRay { poses: &poses, ray, max_toi: *max_toi, inclusion_list }, stop_condition, log_condition, sort_outputs, scratch).map(|_| ())
+            }
+            RobotShapeCollectionQuery::CastRay { ray, max_toi, solid, inclusion_list, .. } => {
+                collection.shape_collection.shape_collection_query_with_scratch(&ShapeCollectionQuery::CastRay { poses: &poses, ray, max_toi: *max_toi, solid: *solid, inclusion_list }, stop_condition, log_condition, sort_outputs, scratch).map(|_| ())
+            }
+            RobotShapeCollectionQuery::CastRayAndGetNormal { ray, max_toi, solid, inclusion_list, .. } => {
+                collection.shape_collection.shape_collection_query_with_scratch(&ShapeCollectionQuery::CastRayAndGetNormal { poses: &poses, ray, max_toi: *max_toi, solid: *solid, inclusion_list }, stop_condition, log_condition, sort_outputs, scratch).map(|_| ())
+            }
+            RobotShapeCollectionQuery::IntersectionTest { inclusion_list, .. } => {
+                collection.shape_collection.shape_collection_query_with_scratch(&ShapeCollectionQuery::IntersectionTest { poses: &poses, inclusion_list }, stop_condition, log_condition, sort_outputs, scratch).map(|_| ())
+            }
+            RobotShapeCollectionQuery::Distance { inclusion_list, .. } => {
+                collection.shape_collection.shape_collection_query_with_scratch(&ShapeCollectionQuery::Distance { poses: &poses, inclusion_list }, stop_condition, log_condition, sort_outputs, scratch).map(|_| ())
+            }
+            RobotShapeCollectionQuery::ClosestPoints { max_dis, inclusion_list, .. } => {
+                collection.shape_collection.shape_collection_query_with_scratch(&ShapeCollectionQuery::ClosestPoints { poses: &poses, max_dis: *max_dis, inclusion_list }, stop_condition, log_condition, sort_outputs, scratch).map(|_| ())
+            }
+            RobotShapeCollectionQuery::Contact { prediction, inclusion_list, .. } => {
+                collection.shape_collection.shape_collection_query_with_scratch(&ShapeCollectionQuery::Contact { poses: &poses, prediction: *prediction, inclusion_list }, stop_condition, log_condition, sort_outputs, scratch).map(|_| ())
+            }
+            RobotShapeCollectionQuery::CCD { .. } => { unreachable!() }
+        };
+        scratch.return_poses(poses);
+        query_res?;
+
+        Ok(scratch.output())
+    }
+
     pub fn spawn_query_list(&self, robot_link_shape_representation: &RobotLinkShapeRepresentation) -> ShapeCollectionQueryList {
         let robot_shape_collection = self.robot_shape_collection(robot_link_shape_representation).expect("error");
         robot_shape_collection.shape_collection.spawn_query_list()
@@ -394,6 +853,10 @@ impl RobotGeometricShapeModule {
         let robot_shape_collection = self.robot_shape_collection(robot_link_shape_representation).expect("error");
         robot_shape_collection.shape_collection.spawn_query_pairs_list(override_all_skips)
     }
+    pub fn spawn_warm_start_cache(&self, robot_link_shape_representation: &RobotLinkShapeRepresentation) -> ShapeCollectionWarmStartCache {
+        let robot_shape_collection = self.robot_shape_collection(robot_link_shape_representation).expect("error");
+        robot_shape_collection.shape_collection.spawn_warm_start_cache()
+    }
     pub fn spawn_proxima_engine(&self, robot_link_shape_representation: &RobotLinkShapeRepresentation) -> ProximaEngine {
         let robot_shape_collection = self.robot_shape_collection(robot_link_shape_representation).expect("error");
         robot_shape_collection.shape_collection.spawn_proxima_engine()
@@ -488,23 +951,19 @@ impl RobotGeometricShapeModule {
             }
         }
 
-        self.save_as_asset(OptimaAssetLocation::RobotModuleJson { robot_name: self.robot_kinematics_module.robot_configuration_module().robot_name().to_string(), t: RobotModuleJsonType::ShapeGeometryModule })?;
-
-        Ok(())
+        return self.record_change();
     }
     #[cfg(not(target_arch = "wasm32"))]
     pub fn reset_robot_geometric_shape_collection(&mut self, robot_link_shape_representation: RobotLinkShapeRepresentation) -> Result<(), OptimaError> {
         let response = ConsoleInputUtils::get_console_input_string("About to reset robot geometric shape collections.  Confirm? (y or n).", PrintColor::Blue)?;
         if response == "y" {
             let permanent = Self::load_as_asset(OptimaAssetLocation::RobotModuleJson { robot_name: self.robot_kinematics_module.robot_configuration_module().robot_name().to_string(), t: RobotModuleJsonType::ShapeGeometryModulePermanent })?;
-            for (i, r) in self.robot_shape_collections.iter_mut().enumerate() {
-            if &r.robot_link_shape_representation == &robot_link_shape_representation {
-                *r = permanent.robot_shape_collections[i].clone();
-                self.save_as_asset(OptimaAssetLocation::RobotModuleJson { robot_name: self.robot_kinematics_module.robot_configuration_module().robot_name().to_string(), t: RobotModuleJsonType::ShapeGeometryModule })?;
-                return Ok(());
+            let idx = self.robot_shape_collections.iter().position(|r| &r.robot_link_shape_representation == &robot_link_shape_representation);
+            if let Some(idx) = idx {
+                self.robot_shape_collections[idx] = permanent.robot_shape_collection(&robot_link_shape_representation)?.clone();
+                return self.record_change();
             }
         }
-        }
         Ok(())
     }
     #[cfg(not(target_arch = "wasm32"))]
@@ -518,6 +977,87 @@ impl RobotGeometricShapeModule {
         }
         Ok(())
     }
+    /// Sets how the skip and average distance matrices of the given shape representation are stored
+    /// (see `SquareArray2DStorageType`).  By default, `TriangleMeshes` uses sparse skips and
+    /// symmetric packed average distances, and all other representations use dense storage.
+    pub fn set_pairwise_storage_types(&mut self, robot_link_shape_representation: &RobotLinkShapeRepresentation, skips_storage_type: SquareArray2DStorageType, average_distances_storage_type: SquareArray2DStorageType) -> Result<(), OptimaError> {
+        let collection = self.robot_geometric_shape_collection_mut(robot_link_shape_representation)?;
+        return collection.shape_collection.set_pairwise_storage_types(skips_storage_type, average_distances_storage_type);
+    }
+    /// By default, functions that change the skip matrices (e.g., `set_robot_joint_state_as_non_collision`)
+    /// save the module to disk right away.  With deferred commits, these changes are only kept in
+    /// memory until `commit` is called, so that an application can try out filter changes and undo
+    /// them with `snapshot` and `rollback` without touching any files.
+    pub fn set_defer_commits(&mut self, defer_commits: bool) {
+        self.defer_commits = defer_commits;
+    }
+    pub fn defer_commits(&self) -> bool {
+        self.defer_commits
+    }
+    /// Returns true if there are changes that have not been saved to disk by `commit`.
+    pub fn has_uncommitted_changes(&self) -> bool {
+        self.has_uncommitted_changes
+    }
+    /// Saves the module to disk.
+    pub fn commit(&mut self) -> Result<(), OptimaError> {
+        self.save_as_asset(OptimaAssetLocation::RobotModuleJson { robot_name: self.robot_kinematics_module.robot_configuration_module().robot_name().to_string(), t: RobotModuleJsonType::ShapeGeometryModule })?;
+        self.has_uncommitted_changes = false;
+        Ok(())
+    }
+    /// Takes an in memory copy of all shape collections (including their skip matrices and
+    /// collision margins) that can later be restored with `rollback`.
+    pub fn snapshot(&self) -> RobotGeometricShapeModuleSnapshot {
+        RobotGeometricShapeModuleSnapshot {
+            robot_name: self.robot_kinematics_module.robot_configuration_module().robot_name().to_string(),
+            robot_shape_collections: self.robot_shape_collections.clone(),
+            unloaded_robot_shape_collections: self.unloaded_robot_shape_collections.clone()
+        }
+    }
+    /// Restores the shape collections from a snapshot taken from a module of the same robot.  The
+    /// rollback is committed right away unless commits are deferred.
+    pub fn rollback(&mut self, snapshot: &RobotGeometricShapeModuleSnapshot) -> Result<(), OptimaError> {
+        let robot_name = self.robot_kinematics_module.robot_configuration_module().robot_name();
+        if snapshot.robot_name != robot_name {
+            return Err(OptimaError::new_generic_error_str(&format!("Cannot roll back geometric shape module of robot {} to a snapshot of robot {}.", robot_name, snapshot.robot_name), file!(), line!()));
+        }
+        self.robot_shape_collections = snapshot.robot_shape_collections.clone();
+        self.unloaded_robot_shape_collections = snapshot.unloaded_robot_shape_collections.clone();
+        return self.record_change();
+    }
+    pub fn robot_joint_state_module(&self) -> &RobotJointStateModule {
+        &self.robot_joint_state_module
+    }
+    pub fn robot_kinematics_module(&self) -> &RobotKinematicsModule {
+        &self.robot_kinematics_module
+    }
+    /// Summarizes the outputs of a query on this module (see `QueryOutputSummary`) with robot links
+    /// labeled by name.
+    pub fn summarize_query_output(&self, output: &GeometricShapeQueryGroupOutput, max_pairs: usize) -> QueryOutputSummary {
+        let links = self.robot_kinematics_module.robot_configuration_module().robot_model_module().links();
+        let shape_label = |signature: &GeometricShapeSignature| {
+            if let GeometricShapeSignature::RobotLink { link_idx, shape_idx_in_link } = signature {
+                if let Some(link) = links.get(*link_idx) { return format!("{} (shape {})", link.name(), shape_idx_in_link); }
+            }
+            QueryOutputSummary::default_shape_label(signature)
+        };
+        return output.summary_with_shape_labels(max_pairs, &shape_label);
+    }
+    fn record_change(&mut self) -> Result<(), OptimaError> {
+        return if self.defer_commits {
+            self.has_uncommitted_changes = true;
+            Ok(())
+        } else {
+            self.commit()
+        }
+    }
+    /// The triangle mesh collections have the most shapes, and most of their skip entries are the
+    /// same, so their pairwise matrices are stored compactly.
+    fn default_pairwise_storage_types(robot_link_shape_representation: &RobotLinkShapeRepresentation) -> (SquareArray2DStorageType, SquareArray2DStorageType) {
+        return match robot_link_shape_representation {
+            RobotLinkShapeRepresentation::TriangleMeshes => { (SquareArray2DStorageType::Sparse, SquareArray2DStorageType::SymmetricPacked) }
+            _ => { (SquareArray2DStorageType::Dense, SquareArray2DStorageType::Dense) }
+        }
+    }
     fn stop_at_min_sample_duration(&self, robot_link_shape_representation: &RobotLinkShapeRepresentation) -> Duration {
         match robot_link_shape_representation {
             RobotLinkShapeRepresentation::Cubes => { Duration::from_secs(20) }
@@ -526,32 +1066,82 @@ impl RobotGeometricShapeModule {
             RobotLinkShapeRepresentation::CubeSubcomponents => { Duration::from_secs(30) }
             RobotLinkShapeRepresentation::ConvexShapeSubcomponents => { Duration::from_secs(60) }
             RobotLinkShapeRepresentation::TriangleMeshes => { Duration::from_secs(120) }
+            // Auto is never preprocessed.
+            RobotLinkShapeRepresentation::Auto => { Duration::from_secs(0) }
         }
     }
 }
-impl SaveAndLoadable for RobotGeometricShapeModule {
-    type SaveType = (String, String, String);
-
-    fn get_save_serialization_object(&self) -> Self::SaveType {
-        (self.robot_kinematics_module.robot_configuration_module().get_serialization_string(), self.robot_mesh_file_manager_module.get_serialization_string(), self.robot_shape_collections.get_serialization_string())
-    }
-
-    fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let load: Self::SaveType = load_object_from_json_string(json_str)?;
+impl RobotGeometricShapeModule {
+    /// Loads the module, but only the shape representations in robot_link_shape_representations (or
+    /// all of them if None).
+    fn load_from_json_string_with_robot_link_shape_representations(json_str: &str, robot_link_shape_representations: Option<&Vec<RobotLinkShapeRepresentation>>) -> Result<Self, OptimaError> {
+        let load: <Self as SaveAndLoadable>::SaveType = Self::load_save_object_from_json_string(json_str)?;
         let robot_configuration_module = RobotConfigurationModule::load_from_json_string(&load.0)?;
-        let robot_joint_state_module = RobotJointStateModule::new(robot_configuration_module.clone());
         let robot_kinematics_module = RobotKinematicsModule::new(robot_configuration_module);
+        let robot_joint_state_module = robot_kinematics_module.robot_joint_state_module().clone();
         let robot_mesh_file_manager_module = RobotMeshFileManagerModule::load_from_json_string(&load.1)?;
-        // let robot_shape_collections: Vec<RobotShapeCollection> = SaveAndLoadableVec::load_from_json_string(&load.2)?;
-        let robot_shape_collections: Vec<RobotShapeCollection> = Vec::load_from_json_string(&load.2)?;
+
+        let robot_shape_collection_strings: Vec<String> = load_object_from_json_string(&load.2)?;
+        let mut robot_shape_collections = vec![];
+        let mut unloaded_robot_shape_collections = vec![];
+        for robot_shape_collection_string in robot_shape_collection_strings {
+            // Only the small header of the collection is parsed here; the shapes themselves stay an
+            // unparsed string.
+            let header = RobotShapeCollection::load_save_object_from_json_string(&robot_shape_collection_string)?;
+            let robot_link_shape_representation = header.0;
+            let load_now = match robot_link_shape_representations {
+                None => { true }
+                Some(robot_link_shape_representations) => { robot_link_shape_representations.contains(&robot_link_shape_representation) }
+            };
+            if load_now {
+                robot_shape_collections.push(Self::load_robot_shape_collection_from_json_string(&robot_shape_collection_string)?);
+            } else {
+                unloaded_robot_shape_collections.push((robot_link_shape_representation, robot_shape_collection_string));
+            }
+        }
 
         Ok(Self {
             robot_joint_state_module,
             robot_kinematics_module,
             robot_mesh_file_manager_module,
-            robot_shape_collections
+            robot_shape_collections,
+            unloaded_robot_shape_collections,
+            defer_commits: false,
+            has_uncommitted_changes: false,
+            auto_representation_policy: AutoRepresentationPolicy::default()
         })
     }
+    fn load_robot_shape_collection_from_json_string(json_str: &str) -> Result<RobotShapeCollection, OptimaError> {
+        let mut robot_shape_collection = RobotShapeCollection::load_from_json_string(json_str)?;
+        // Collections saved before storage types were selectable are dense.
+        let (skips_storage_type, average_distances_storage_type) = Self::default_pairwise_storage_types(&robot_shape_collection.robot_link_shape_representation);
+        if robot_shape_collection.shape_collection.skips().storage_type() != &skips_storage_type || robot_shape_collection.shape_collection.average_distances().storage_type() != &average_distances_storage_type {
+            robot_shape_collection.shape_collection.set_pairwise_storage_types(skips_storage_type, average_distances_storage_type)?;
+        }
+        Ok(robot_shape_collection)
+    }
+}
+impl SaveAndLoadable for RobotGeometricShapeModule {
+    type SaveType = (String, String, String);
+
+    fn get_save_serialization_object(&self) -> Self::SaveType {
+        // Unloaded representations are saved as they were loaded, and everything is saved in the
+        // canonical representation order.
+        let mut robot_shape_collection_strings = vec![];
+        for robot_link_shape_representation in Self::get_all_robot_link_shape_representations() {
+            if let Ok(robot_shape_collection) = self.robot_shape_collection(&robot_link_shape_representation) {
+                robot_shape_collection_strings.push(robot_shape_collection.get_serialization_string());
+            } else if let Some((_, s)) = self.unloaded_robot_shape_collections.iter().find(|(r, _)| r == &robot_link_shape_representation) {
+                robot_shape_collection_strings.push(s.clone());
+            }
+        }
+
+        (self.robot_kinematics_module.robot_configuration_module().get_serialization_string(), self.robot_mesh_file_manager_module.get_serialization_string(), robot_shape_collection_strings.to_json_string())
+    }
+
+    fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
+        return Self::load_from_json_string_with_robot_link_shape_representations(json_str, None);
+    }
 }
 
 /// Python implementations.
@@ -580,7 +1170,8 @@ impl RobotGeometricShapeModule {
                                               StopCondition::from_ron_string(stop_condition).expect("error"),
                                               LogCondition::from_ron_string(log_condition).expect("error"),
                                               sort_outputs).expect("error");
-        let py_output = res.convert_to_py_output(include_full_output_json_string);
+        let mut py_output = res.convert_to_py_output(include_full_output_json_string);
+        py_output.set_summary(self.summarize_query_output(&res, 10));
         py_output
     }
     #[args(robot_link_shape_representation = "\"Cubes\"", stop_condition = "\"Intersection\"", log_condition = "\"BelowMinDistance(0.5)\"", sort_outputs = "true", include_full_output_json_string = "true")]
@@ -601,7 +1192,8 @@ impl RobotGeometricShapeModule {
                                               StopCondition::from_ron_string(stop_condition).expect("error"),
                                               LogCondition::from_ron_string(log_condition).expect("error"),
                                               sort_outputs).expect("error");
-        let py_output = res.convert_to_py_output(include_full_output_json_string);
+        let mut py_output = res.convert_to_py_output(include_full_output_json_string);
+        py_output.set_summary(self.summarize_query_output(&res, 10));
         py_output
     }
     #[args(robot_link_shape_representation = "\"Cubes\"", stop_condition = "\"Intersection\"", log_condition = "\"BelowMinDistance(0.5)\"", sort_outputs = "true", include_full_output_json_string = "true")]
@@ -624,7 +1216,8 @@ impl RobotGeometricShapeModule {
                                               StopCondition::from_ron_string(stop_condition).expect("error"),
                                               LogCondition::from_ron_string(log_condition).expect("error"),
                                               sort_outputs).expect("error");
-        let py_output = res.convert_to_py_output(include_full_output_json_string);
+        let mut py_output = res.convert_to_py_output(include_full_output_json_string);
+        py_output.set_summary(self.summarize_query_output(&res, 10));
         py_output
     }
     #[args(robot_link_shape_representation = "\"Cubes\"", stop_condition = "\"Intersection\"", log_condition = "\"BelowMinDistance(0.5)\"", sort_outputs = "true", include_full_output_json_string = "true")]
@@ -649,7 +1242,8 @@ impl RobotGeometricShapeModule {
                                               StopCondition::from_ron_string(stop_condition).expect("error"),
                                               LogCondition::from_ron_string(log_condition).expect("error"),
                                               sort_outputs).expect("error");
-        let py_output = res.convert_to_py_output(include_full_output_json_string);
+        let mut py_output = res.convert_to_py_output(include_full_output_json_string);
+        py_output.set_summary(self.summarize_query_output(&res, 10));
         py_output
     }
     pub fn set_robot_joint_state_as_non_collision_py(&mut self, robot_joint_state: Vec<f64>) {
@@ -662,6 +1256,40 @@ impl RobotGeometricShapeModule {
     pub fn reset_all_robot_geometric_shape_collections_py(&mut self) {
         self.reset_all_robot_geometric_shape_collections().expect("error");
     }
+    #[args(robot_link_shape_representation = "\"Cubes\"")]
+    pub fn link_distance_matrix_py<'py>(&self, py: Python<'py>, joint_state: Vec<f64>, robot_link_shape_representation: &str) -> &'py PyArray2<f64> {
+        let robot_joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(DVector::from_vec(joint_state)).expect("error");
+        let matrix = self.link_distance_matrix(&robot_joint_state, RobotLinkShapeRepresentation::from_ron_string(robot_link_shape_representation).expect("error")).expect("error");
+        let array = ndarray::Array2::from_shape_fn((matrix.nrows(), matrix.ncols()), |(i, j)| matrix[(i, j)]);
+        array.into_pyarray(py)
+    }
+    #[args(robot_link_shape_representation = "\"Cubes\"")]
+    pub fn k_smallest_link_distances_py(&self, joint_state: Vec<f64>, k: usize, robot_link_shape_representation: &str) -> Vec<(usize, usize, f64)> {
+        let robot_joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(DVector::from_vec(joint_state)).expect("error");
+        self.k_smallest_link_distances(&robot_joint_state, RobotLinkShapeRepresentation::from_ron_string(robot_link_shape_representation).expect("error"), k).expect("error")
+    }
+    /// Returns (shape_idx1, shape_idx2, distance, exact) tuples (see `approximate_distance_query`).
+    #[args(robot_link_shape_representation = "\"Cubes\"")]
+    pub fn approximate_distance_query_py(&self, joint_state: Vec<f64>, margin: f64, robot_link_shape_representation: &str) -> Vec<(usize, usize, f64, bool)> {
+        let robot_joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(DVector::from_vec(joint_state)).expect("error");
+        let res = self.approximate_distance_query(&robot_joint_state, RobotLinkShapeRepresentation::from_ron_string(robot_link_shape_representation).expect("error"), margin).expect("error");
+        res.pair_distances().iter().map(|p| (p.shape_idx1, p.shape_idx2, p.distance, p.exact)).collect()
+    }
+    pub fn set_defer_commits_py(&mut self, defer_commits: bool) {
+        self.set_defer_commits(defer_commits);
+    }
+    pub fn has_uncommitted_changes_py(&self) -> bool {
+        self.has_uncommitted_changes()
+    }
+    pub fn commit_py(&mut self) {
+        self.commit().expect("error");
+    }
+    pub fn snapshot_py(&self) -> RobotGeometricShapeModuleSnapshot {
+        self.snapshot()
+    }
+    pub fn rollback_py(&mut self, snapshot: &RobotGeometricShapeModuleSnapshot) {
+        self.rollback(snapshot).expect("error");
+    }
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -674,7 +1302,7 @@ impl RobotGeometricShapeModule {
             Some(c) => { Self::new_from_names(RobotNames::new(&robot_name, Some(&c)), false).expect("error") }
         }
     }
-    pub fn intersection_test_query_wasm(&self, joint_state: Vec<f64>, robot_link_shape_representation: &str, stop_condition: &str, log_condition: &str, sort_outputs: bool) -> JsValue {
+    pub fn intersection_test_query_wasm(&self, joint_state: Vec<f64>, robot_link_shape_representation: &str, stop_condition: &str, log_condition: &str, sort_outputs: bool) -> GeometricShapeQueryGroupOutputJsObject {
         let joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(DVector::from_vec(joint_state)).expect("error");
         let input = RobotShapeCollectionQuery::IntersectionTest {
             robot_joint_state: &joint_state
@@ -685,9 +1313,9 @@ impl RobotGeometricShapeModule {
                                               StopCondition::from_ron_string(stop_condition).expect("error"),
                                               LogCondition::from_ron_string(log_condition).expect("error"),
                                               sort_outputs).expect("error");
-        JsValue::from_serde(&res).unwrap()
+        res.to_js_object()
     }
-    pub fn distance_query_wasm(&self, joint_state: Vec<f64>, robot_link_shape_representation: &str, stop_condition: &str, log_condition: &str, sort_outputs: bool) -> JsValue {
+    pub fn distance_query_wasm(&self, joint_state: Vec<f64>, robot_link_shape_representation: &str, stop_condition: &str, log_condition: &str, sort_outputs: bool) -> GeometricShapeQueryGroupOutputJsObject {
         let joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(DVector::from_vec(joint_state)).expect("error");
         let input = RobotShapeCollectionQuery::Distance {
             robot_joint_state: &joint_state
@@ -698,9 +1326,9 @@ impl RobotGeometricShapeModule {
                                               StopCondition::from_ron_string(stop_condition).expect("error"),
                                               LogCondition::from_ron_string(log_condition).expect("error"),
                                               sort_outputs).expect("error");
-        JsValue::from_serde(&res).unwrap()
+        res.to_js_object()
     }
-    pub fn contact_query_wasm(&self, joint_state: Vec<f64>, prediction: f64, robot_link_shape_representation: &str, stop_condition: &str, log_condition: &str, sort_outputs: bool) -> JsValue {
+    pub fn contact_query_wasm(&self, joint_state: Vec<f64>, prediction: f64, robot_link_shape_representation: &str, stop_condition: &str, log_condition: &str, sort_outputs: bool) -> GeometricShapeQueryGroupOutputJsObject {
         let joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(DVector::from_vec(joint_state)).expect("error");
         let input = RobotShapeCollectionQuery::Contact {
             robot_joint_state: &joint_state,
@@ -712,9 +1340,9 @@ impl RobotGeometricShapeModule {
                                               StopCondition::from_ron_string(stop_condition).expect("error"),
                                               LogCondition::from_ron_string(log_condition).expect("error"),
                                               sort_outputs).expect("error");
-        JsValue::from_serde(&res).unwrap()
+        res.to_js_object()
     }
-    pub fn ccd_query_wasm(&self, joint_state_t1: Vec<f64>, joint_state_t2: Vec<f64>, robot_link_shape_representation: &str, stop_condition: &str, log_condition: &str, sort_outputs: bool) -> JsValue {
+    pub fn ccd_query_wasm(&self, joint_state_t1: Vec<f64>, joint_state_t2: Vec<f64>, robot_link_shape_representation: &str, stop_condition: &str, log_condition: &str, sort_outputs: bool) -> GeometricShapeQueryGroupOutputJsObject {
         let joint_state_t1 = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(DVector::from_vec(joint_state_t1)).expect("error");
         let joint_state_t2 = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(DVector::from_vec(joint_state_t2)).expect("error");
 
@@ -728,10 +1356,64 @@ impl RobotGeometricShapeModule {
                                               StopCondition::from_ron_string(stop_condition).expect("error"),
                                               LogCondition::from_ron_string(log_condition).expect("error"),
                                               sort_outputs).expect("error");
-        JsValue::from_serde(&res).unwrap()
+        res.to_js_object()
     }
 }
 
+/// An in memory copy of the shape collections of a `RobotGeometricShapeModule`, taken with
+/// `RobotGeometricShapeModule::snapshot` and restored with `RobotGeometricShapeModule::rollback`.
+#[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
+#[cfg_attr(target_arch = "wasm32", derive(Clone, Debug, Serialize, Deserialize))]
+pub struct RobotGeometricShapeModuleSnapshot {
+    robot_name: String,
+    robot_shape_collections: Vec<RobotShapeCollection>,
+    unloaded_robot_shape_collections: Vec<(RobotLinkShapeRepresentation, String)>
+}
+impl RobotGeometricShapeModuleSnapshot {
+    pub fn robot_name(&self) -> &str {
+        &self.robot_name
+    }
+}
+
+/// The output of `RobotGeometricShapeModule::approximate_distance_query`.
+#[derive(Clone, Debug)]
+pub struct ApproximateDistanceQueryOutput {
+    pair_distances: Vec<ApproximatePairDistance>,
+    num_approximate: usize,
+    num_exact: usize,
+    duration: Duration
+}
+impl ApproximateDistanceQueryOutput {
+    pub fn pair_distances(&self) -> &Vec<ApproximatePairDistance> {
+        &self.pair_distances
+    }
+    /// The smallest distance over all pairs (f64::INFINITY if there are none).
+    pub fn minimum_distance(&self) -> f64 {
+        self.pair_distances.iter().fold(f64::INFINITY, |acc, p| acc.min(p.distance))
+    }
+    /// The number of pairs answered by a lookup table.
+    pub fn num_approximate(&self) -> usize {
+        self.num_approximate
+    }
+    /// The number of pairs answered by an exact distance query.
+    pub fn num_exact(&self) -> usize {
+        self.num_exact
+    }
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+/// The distance of one shape pair in an `ApproximateDistanceQueryOutput`.  exact is false if the
+/// distance was predicted by a lookup table.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApproximatePairDistance {
+    pub shape_idx1: usize,
+    pub shape_idx2: usize,
+    pub distance: f64,
+    pub exact: bool
+}
+
 /// A robot specific version of a `ShapeCollection`.  All shapes in the underlying `ShapeCollection`
 /// refers to geometry representing some part of a robot link.  This also includes information on
 /// the shape representation of the links as well as a nice way to map from a robot link index to
@@ -740,7 +1422,8 @@ impl RobotGeometricShapeModule {
 pub struct RobotShapeCollection {
     robot_link_shape_representation: RobotLinkShapeRepresentation,
     shape_collection: ShapeCollection,
-    link_idx_to_shape_idxs_mapping: Vec<Vec<usize>>
+    link_idx_to_shape_idxs_mapping: Vec<Vec<usize>>,
+    distance_lookup_tables: Option<RobotShapeDistanceLookupTables>
 }
 impl RobotShapeCollection {
     pub fn new(num_robot_links: usize, robot_link_shape_representation: RobotLinkShapeRepresentation, shape_collection: ShapeCollection) -> Result<Self, OptimaError> {
@@ -761,7 +1444,8 @@ impl RobotShapeCollection {
         Ok(Self {
             robot_link_shape_representation,
             shape_collection: shape_collection,
-            link_idx_to_shape_idxs_mapping: robot_link_idx_to_shape_idxs_mapping
+            link_idx_to_shape_idxs_mapping: robot_link_idx_to_shape_idxs_mapping,
+            distance_lookup_tables: None
         })
     }
     pub fn robot_link_shape_representation(&self) -> &RobotLinkShapeRepresentation {
@@ -773,6 +1457,14 @@ impl RobotShapeCollection {
     pub fn link_idx_to_shape_idxs_mapping(&self) -> &Vec<Vec<usize>> {
         &self.link_idx_to_shape_idxs_mapping
     }
+    /// The distance lookup tables built for this collection during preprocessing, if any (see
+    /// `RobotShapeDistanceLookupTables`).
+    pub fn distance_lookup_tables(&self) -> &Option<RobotShapeDistanceLookupTables> {
+        &self.distance_lookup_tables
+    }
+    pub fn set_distance_lookup_tables(&mut self, distance_lookup_tables: Option<RobotShapeDistanceLookupTables>) {
+        self.distance_lookup_tables = distance_lookup_tables;
+    }
     pub fn get_shape_idxs_from_link_idx(&self, link_idx: usize) -> Result<&Vec<usize>, OptimaError> {
         OptimaError::new_check_for_idx_out_of_bound_error(link_idx, self.link_idx_to_shape_idxs_mapping.len(), file!(), line!())?;
         return Ok(&self.link_idx_to_shape_idxs_mapping[link_idx]);
@@ -792,23 +1484,67 @@ impl RobotShapeCollection {
 
         Ok(geometric_shape_collection_input_poses)
     }
+    /// Same as `recover_poses`, but writes into the given poses, reusing its storage when it already
+    /// has the right length.
+    pub fn recover_poses_into(&self, robot_fk_result: &RobotFKResult, poses: &mut ShapeCollectionInputPoses) -> Result<(), OptimaError> {
+        if poses.len() != self.shape_collection.shapes().len() {
+            *poses = ShapeCollectionInputPoses::new(&self.shape_collection);
+        } else {
+            poses.clear();
+        }
+
+        let link_entries = robot_fk_result.link_entries();
+        for (link_idx, link_entry) in link_entries.iter().enumerate() {
+            let pose = link_entry.pose();
+            if let Some(pose) = pose {
+                let shape_idxs = self.get_shape_idxs_from_link_idx(link_idx)?;
+                for shape_idx in shape_idxs {
+                    poses.insert_or_replace_pose_by_idx(*shape_idx, pose.clone())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 impl SaveAndLoadable for RobotShapeCollection {
-    type SaveType = (RobotLinkShapeRepresentation, String, Vec<Vec<usize>>);
+    /// The distance lookup tables are kept as a string, like the shape collection, so that reading
+    /// the header of a collection stays cheap.
+    type SaveType = (RobotLinkShapeRepresentation, String, Vec<Vec<usize>>, Option<String>);
+    /// Version 2 added the distance lookup tables.
+    const SAVE_FORMAT_VERSION: u32 = 2;
 
     fn get_save_serialization_object(&self) -> Self::SaveType {
-        (self.robot_link_shape_representation.clone(), self.shape_collection.get_serialization_string(), self.link_idx_to_shape_idxs_mapping.clone())
+        (self.robot_link_shape_representation.clone(), self.shape_collection.get_serialization_string(), self.link_idx_to_shape_idxs_mapping.clone(), self.distance_lookup_tables.as_ref().map(|t| t.to_json_string()))
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let load: Self::SaveType = load_object_from_json_string(json_str)?;
+        let load: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
         let shape_collection = ShapeCollection::load_from_json_string(&load.1)?;
+        let distance_lookup_tables = match &load.3 {
+            None => { None }
+            Some(s) => { Some(RobotShapeDistanceLookupTables::from_json_string(s)?) }
+        };
         Ok(Self {
             robot_link_shape_representation: load.0.clone(),
             shape_collection,
-            link_idx_to_shape_idxs_mapping: load.2.clone()
+            link_idx_to_shape_idxs_mapping: load.2.clone(),
+            distance_lookup_tables
         })
     }
+
+    fn migrate_save_object(from_version: u32, value: serde_json::Value) -> Result<serde_json::Value, OptimaError> {
+        // Versions 0 and 1 are the same layout without lookup tables.
+        return match (from_version, value) {
+            (0, serde_json::Value::Array(mut a)) | (1, serde_json::Value::Array(mut a)) if a.len() == 3 => {
+                a.push(serde_json::Value::Null);
+                Ok(serde_json::Value::Array(a))
+            }
+            _ => {
+                Err(OptimaError::new_incompatible_save_format_error(std::any::type_name::<Self>(), from_version, Self::SAVE_FORMAT_VERSION, "No migration exists for this version.  Re-run preprocessing for the robot", file!(), line!()))
+            }
+        }
+    }
 }
 
 /// A robot specific version of a `ShapeCollectionQuery`.  Is basically the same but trades out
@@ -852,6 +1588,9 @@ impl <'a> RobotShapeCollectionQuery<'a> {
 /// - `CubeSubcomponents`: decomposes each link into convex subcomponents and wraps each in a best fitting cube.
 /// - `ConvexShapeSubcomponents`: decomposes each link into convex subcomponents.
 /// - `TriangleMeshes`: directly uses the given meshes as geometry.
+/// - `Auto`: not a stored representation, but a query policy that answers each query with the
+/// cheapest representation that can certify the result, escalating to finer representations near
+/// contact (see `AutoRepresentationPolicy`).  Only accepted by `RobotGeometricShapeModule` queries.
 #[derive(Clone, Debug, PartialOrd, PartialEq, Ord, Eq, Serialize, Deserialize)]
 pub enum RobotLinkShapeRepresentation {
     Cubes,
@@ -859,5 +1598,242 @@ pub enum RobotLinkShapeRepresentation {
     SphereSubcomponents,
     CubeSubcomponents,
     ConvexShapeSubcomponents,
-    TriangleMeshes
+    TriangleMeshes,
+    Auto
+}
+
+/// Decides how `RobotLinkShapeRepresentation::Auto` queries are answered.  The representations in
+/// `escalation_order` that are loaded are tried from first to last.  A query is answered by a
+/// representation if the minimum distance it finds is greater than `escalation_distance` (e.g., no
+/// intersection, no ray hit, or every pair farther apart than the escalation distance); otherwise,
+/// the query escalates to the next representation.  The last loaded representation always answers.
+///
+/// This is only sound if every representation encloses the ones after it, so that a negative result
+/// from a coarse representation carries over to the finer ones.  `Cubes` (bounding boxes) and
+/// `ConvexShapes` (convex hulls) enclose the link meshes, while the sphere and cube subcomponent
+/// representations do not, so the default order is `Cubes`, `ConvexShapes`, then `TriangleMeshes`.
+/// Note that each representation applies its own skip matrix.  Distances reported by a coarse
+/// representation are lower bounds on the distances between the finer shapes.  `ProjectPoint` and
+/// `CCD` queries are always answered by the last loaded representation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AutoRepresentationPolicy {
+    escalation_order: Vec<RobotLinkShapeRepresentation>,
+    escalation_distance: f64
+}
+impl AutoRepresentationPolicy {
+    pub fn new(escalation_order: Vec<RobotLinkShapeRepresentation>, escalation_distance: f64) -> Result<Self, OptimaError> {
+        if escalation_order.is_empty() || escalation_order.contains(&RobotLinkShapeRepresentation::Auto) {
+            return Err(OptimaError::new_generic_error_str(&format!("Auto representation escalation order must be a non-empty list of stored representations (given {:?}).", escalation_order), file!(), line!()));
+        }
+        if !(escalation_distance >= 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Auto representation escalation distance must be non-negative (given {}).", escalation_distance), file!(), line!()));
+        }
+        Ok(Self {
+            escalation_order,
+            escalation_distance
+        })
+    }
+    pub fn escalation_order(&self) -> &Vec<RobotLinkShapeRepresentation> {
+        &self.escalation_order
+    }
+    pub fn escalation_distance(&self) -> f64 {
+        self.escalation_distance
+    }
 }
+impl Default for AutoRepresentationPolicy {
+    fn default() -> Self {
+        Self {
+            escalation_order: vec![RobotLinkShapeRepresentation::Cubes, RobotLinkShapeRepresentation::ConvexShapes, RobotLinkShapeRepresentation::TriangleMeshes],
+            escalation_distance: 0.02
+        }
+    }
+}
+
+/// Settings for preprocessing a `RobotGeometricShapeModule` (see
+/// `RobotGeometricShapeModule::new_with_preprocessing_config`).
+/// - `distance_lookup_table_config`: if given, per-pair distance lookup tables are built for the
+/// selected representations (see `RobotShapeDistanceLookupTables`).  Off by default.
+/// - `deterministic`: if given, preprocessing is reproducible (see `DeterministicPreprocessing`).
+/// Off by default, in which case sampling stops after a per-representation time budget.
+/// - `skip_decision_thresholds`: when pairs are skipped as always or never colliding (see
+/// `SkipDecisionThresholds`), with optional overrides for particular representations, e.g., to keep
+/// intentionally touching links checked in the finer representations only.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PreprocessingConfig {
+    distance_lookup_table_config: Option<DistanceLookupTableConfig>,
+    deterministic: Option<DeterministicPreprocessing>,
+    skip_decision_thresholds: SkipDecisionThresholds,
+    skip_decision_threshold_overrides: Vec<(RobotLinkShapeRepresentation, SkipDecisionThresholds)>
+}
+impl PreprocessingConfig {
+    pub fn new() -> Self {
+        Self {
+            distance_lookup_table_config: None,
+            deterministic: None,
+            skip_decision_thresholds: SkipDecisionThresholds::default(),
+            skip_decision_threshold_overrides: vec![]
+        }
+    }
+    /// Default settings in deterministic mode with the given seed and number of samples.
+    pub fn new_deterministic(seed: u64, num_samples: usize) -> Result<Self, OptimaError> {
+        let mut out_self = Self::new();
+        out_self.set_deterministic(Some(DeterministicPreprocessing::new(seed, num_samples)?));
+        Ok(out_self)
+    }
+    pub fn set_distance_lookup_table_config(&mut self, distance_lookup_table_config: Option<DistanceLookupTableConfig>) {
+        self.distance_lookup_table_config = distance_lookup_table_config;
+    }
+    pub fn set_deterministic(&mut self, deterministic: Option<DeterministicPreprocessing>) {
+        self.deterministic = deterministic;
+    }
+    /// Sets the thresholds used for all representations without an override.
+    pub fn set_skip_decision_thresholds(&mut self, skip_decision_thresholds: SkipDecisionThresholds) {
+        self.skip_decision_thresholds = skip_decision_thresholds;
+    }
+    /// Overrides the thresholds for the given representation.  Passing None reverts the
+    /// representation to the thresholds set with `set_skip_decision_thresholds`.
+    pub fn set_skip_decision_threshold_override(&mut self, robot_link_shape_representation: RobotLinkShapeRepresentation, skip_decision_thresholds: Option<SkipDecisionThresholds>) -> Result<(), OptimaError> {
+        if robot_link_shape_representation == RobotLinkShapeRepresentation::Auto {
+            return Err(OptimaError::new_generic_error_str("The Auto representation is never preprocessed, so it cannot have skip decision thresholds.", file!(), line!()));
+        }
+        self.skip_decision_threshold_overrides.retain(|(r, _)| r != &robot_link_shape_representation);
+        if let Some(skip_decision_thresholds) = skip_decision_thresholds {
+            self.skip_decision_threshold_overrides.push((robot_link_shape_representation, skip_decision_thresholds));
+        }
+        Ok(())
+    }
+    /// The random number generator for the given stream (e.g., the index of a shape
+    /// representation): seeded from the deterministic seed and the stream index in deterministic
+    /// mode, and from entropy otherwise.
+    pub fn spawn_rng(&self, stream_idx: u64) -> ChaCha8Rng {
+        return match &self.deterministic {
+            None => { ChaCha8Rng::from_entropy() }
+            Some(deterministic) => {
+                let mut seed_bytes = [0u8; 32];
+                seed_bytes[..8].copy_from_slice(&deterministic.seed.to_le_bytes());
+                seed_bytes[8..16].copy_from_slice(&stream_idx.to_le_bytes());
+                ChaCha8Rng::from_seed(seed_bytes)
+            }
+        }
+    }
+    pub fn distance_lookup_table_config(&self) -> &Option<DistanceLookupTableConfig> {
+        &self.distance_lookup_table_config
+    }
+    pub fn deterministic(&self) -> &Option<DeterministicPreprocessing> {
+        &self.deterministic
+    }
+    /// The thresholds that apply to the given representation (its override, if it has one).
+    pub fn skip_decision_thresholds(&self, robot_link_shape_representation: &RobotLinkShapeRepresentation) -> &SkipDecisionThresholds {
+        return match self.skip_decision_threshold_overrides.iter().find(|(r, _)| r == robot_link_shape_representation) {
+            Some((_, skip_decision_thresholds)) => { skip_decision_thresholds }
+            None => { &self.skip_decision_thresholds }
+        }
+    }
+    pub fn skip_decision_threshold_overrides(&self) -> &Vec<(RobotLinkShapeRepresentation, SkipDecisionThresholds)> {
+        &self.skip_decision_threshold_overrides
+    }
+}
+impl Default for PreprocessingConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Deterministic preprocessing: every representation draws exactly num_samples joint states from a
+/// `ChaCha8Rng` seeded with seed (and the index of the representation), and there is no time-based
+/// stopping.  With the same meshes, seed, and number of samples, preprocessing produces identical
+/// skip matrices, average distances, and lookup tables on every machine, so preprocessed assets can
+/// be regenerated and compared in CI or shared between machines.
+///
+/// NOTE: by default, pairs are only skipped as never colliding after 1000 samples, so num_samples
+/// should be at least that (see `SkipDecisionThresholds`).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeterministicPreprocessing {
+    seed: u64,
+    num_samples: usize
+}
+impl DeterministicPreprocessing {
+    pub fn new(seed: u64, num_samples: usize) -> Result<Self, OptimaError> {
+        if num_samples == 0 {
+            return Err(OptimaError::new_generic_error_str("Deterministic preprocessing needs at least one sample.", file!(), line!()));
+        }
+        Ok(Self {
+            seed,
+            num_samples
+        })
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn num_samples(&self) -> usize {
+        self.num_samples
+    }
+}
+
+/// When preprocessing skips a pair of shapes based on how often it collided in the samples of a
+/// representation:
+/// - always colliding: at least always_colliding_min_samples samples were taken and the pair collided
+/// in more than always_colliding_ratio of them (by default, 0.99 after 70 samples).  Such pairs are
+/// usually adjacent links whose meshes overlap at the joint, but robots with intentionally touching
+/// links (e.g., gripper fingers resting against each other) need this raised or disabled.
+/// - never colliding: at least never_colliding_min_samples samples were taken and the pair never
+/// collided (by default, after 1000 samples).
+///
+/// A rule with a threshold of None is disabled, in which case such pairs are always checked.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SkipDecisionThresholds {
+    always_colliding_ratio: Option<f64>,
+    always_colliding_min_samples: usize,
+    never_colliding_min_samples: Option<usize>
+}
+impl SkipDecisionThresholds {
+    pub fn new(always_colliding_ratio: Option<f64>, always_colliding_min_samples: usize, never_colliding_min_samples: Option<usize>) -> Result<Self, OptimaError> {
+        if let Some(always_colliding_ratio) = always_colliding_ratio {
+            if !(always_colliding_ratio >= 0.0 && always_colliding_ratio < 1.0) {
+                return Err(OptimaError::new_generic_error_str(&format!("Always colliding ratio must be in [0, 1) (given {}).", always_colliding_ratio), file!(), line!()));
+            }
+        }
+
+        Ok(Self {
+            always_colliding_ratio,
+            always_colliding_min_samples,
+            never_colliding_min_samples
+        })
+    }
+    /// Returns true if a pair that collided in the given ratio of num_samples samples is always
+    /// colliding.
+    pub fn is_always_colliding(&self, num_samples: usize, collision_ratio: f64) -> bool {
+        return match self.always_colliding_ratio {
+            None => { false }
+            Some(always_colliding_ratio) => { num_samples >= self.always_colliding_min_samples && collision_ratio > always_colliding_ratio }
+        }
+    }
+    /// Returns true if a pair that collided in the given ratio of num_samples samples is never
+    /// colliding.
+    pub fn is_never_colliding(&self, num_samples: usize, collision_ratio: f64) -> bool {
+        return match self.never_colliding_min_samples {
+            None => { false }
+            Some(never_colliding_min_samples) => { num_samples >= never_colliding_min_samples && collision_ratio == 0.0 }
+        }
+    }
+    pub fn always_colliding_ratio(&self) -> Option<f64> {
+        self.always_colliding_ratio
+    }
+    pub fn always_colliding_min_samples(&self) -> usize {
+        self.always_colliding_min_samples
+    }
+    pub fn never_colliding_min_samples(&self) -> Option<usize> {
+        self.never_colliding_min_samples
+    }
+}
+impl Default for SkipDecisionThresholds {
+    fn default() -> Self {
+        Self {
+            always_colliding_ratio: Some(0.99),
+            always_colliding_min_samples: 70,
+            never_colliding_min_samples: Some(1000)
+        }
+    }
+}
+
+crate::impl_py_pickle!(RobotGeometricShapeModule);
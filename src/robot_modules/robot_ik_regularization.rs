@@ -0,0 +1,199 @@
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateModule, RobotJointStateType};
+use crate::utils::utils_errors::OptimaError;
+
+/// Secondary objectives for damped least squares inverse kinematics (see
+/// `RobotKinematicsModule::compute_ik_damped_least_squares_on_frame_with_regularization`).  Every
+/// iteration, the joint space steps of the enabled terms are summed and projected onto the null space
+/// of the (weighted) Jacobian, so they only use the redundancy left over by the pose error metric
+/// and do not keep the solver from reaching the target.
+///
+/// Without regularization, the solver stops as soon as the pose error is within tolerance, which
+/// leaves the solution wherever the first converging step happened to land.  With regularization, it
+/// keeps iterating after the pose has converged until the projected regularization step is shorter
+/// than settle_tolerance (or max_iterations is reached), so solutions actually settle where the terms
+/// want them.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IKRegularization {
+    joint_limit_avoidance: Option<JointLimitAvoidance>,
+    posture_preference: Option<PosturePreference>,
+    settle_tolerance: f64
+}
+impl IKRegularization {
+    /// No terms enabled and a settle tolerance of 1e-4.
+    pub fn new() -> Self {
+        Self {
+            joint_limit_avoidance: None,
+            posture_preference: None,
+            settle_tolerance: 1e-4
+        }
+    }
+    /// Joint limit avoidance with the given gain and, if given, per-DOF weights from the named joint
+    /// weighting profile of the configuration (see `JointLimitAvoidance`).
+    pub fn new_joint_limit_avoidance(gain: f64, joint_weighting_profile_name: Option<&str>) -> Result<Self, OptimaError> {
+        let mut out = Self::new();
+        out.set_joint_limit_avoidance(Some(JointLimitAvoidance::new(gain, joint_weighting_profile_name)?));
+        Ok(out)
+    }
+    /// Posture preference toward the named posture of the configuration with the given gain and, if
+    /// given, per-DOF weights from the named joint weighting profile (see `PosturePreference`).
+    pub fn new_posture_preference(posture_name: &str, gain: f64, joint_weighting_profile_name: Option<&str>) -> Result<Self, OptimaError> {
+        let mut out = Self::new();
+        out.set_posture_preference(Some(PosturePreference::new(posture_name, gain, joint_weighting_profile_name)?));
+        Ok(out)
+    }
+    pub fn set_joint_limit_avoidance(&mut self, joint_limit_avoidance: Option<JointLimitAvoidance>) {
+        self.joint_limit_avoidance = joint_limit_avoidance;
+    }
+    pub fn set_posture_preference(&mut self, posture_preference: Option<PosturePreference>) {
+        self.posture_preference = posture_preference;
+    }
+    pub fn set_settle_tolerance(&mut self, settle_tolerance: f64) -> Result<(), OptimaError> {
+        if !(settle_tolerance > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Settle tolerance must be positive (given {}).", settle_tolerance), file!(), line!()));
+        }
+        self.settle_tolerance = settle_tolerance;
+        Ok(())
+    }
+    /// The sum of the joint space steps of all enabled terms at the given DOF joint state (before the
+    /// null space projection).
+    pub fn joint_space_step(&self, robot_joint_state_module: &RobotJointStateModule, joint_state: &RobotJointState) -> Result<DVector<f64>, OptimaError> {
+        if joint_state.robot_joint_state_type() != &RobotJointStateType::DOF {
+            return Err(OptimaError::new_generic_error_str("IK regularization is only defined on DOF joint states.", file!(), line!()));
+        }
+
+        let mut out_step = DVector::zeros(joint_state.len());
+        if let Some(joint_limit_avoidance) = &self.joint_limit_avoidance {
+            out_step += joint_limit_avoidance.joint_space_step(robot_joint_state_module, joint_state)?;
+        }
+        if let Some(posture_preference) = &self.posture_preference {
+            out_step += posture_preference.joint_space_step(robot_joint_state_module, joint_state)?;
+        }
+        Ok(out_step)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.joint_limit_avoidance.is_none() && self.posture_preference.is_none()
+    }
+    pub fn joint_limit_avoidance(&self) -> &Option<JointLimitAvoidance> {
+        &self.joint_limit_avoidance
+    }
+    pub fn posture_preference(&self) -> &Option<PosturePreference> {
+        &self.posture_preference
+    }
+    pub fn settle_tolerance(&self) -> f64 {
+        self.settle_tolerance
+    }
+}
+impl Default for IKRegularization {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An `IKRegularization` term that pulls each DOF toward the middle of its bounds, so that solutions
+/// stay away from joint limits (where they would leave downstream planners and controllers no room
+/// to move).  The step for DOF i is -min(gain * w_i, 1) * (q_i - mid_i), where mid_i is the middle of
+/// the DOF's bounds and w_i is its weight in the named joint weighting profile of the robot's
+/// configuration (see `JointWeightingProfile`), or 1.0 if no profile is given.  Higher weights pull
+/// harder, so, e.g., a profile can favor keeping the wrist centered over the shoulder.  DOFs with
+/// infinite or empty bounds (continuous or fixed axes) are left alone.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JointLimitAvoidance {
+    gain: f64,
+    joint_weighting_profile_name: Option<String>
+}
+impl JointLimitAvoidance {
+    /// gain is the fraction of the distance to the middle of the bounds covered per iteration (before
+    /// weighting and null space projection); values around 0.1 work well.
+    pub fn new(gain: f64, joint_weighting_profile_name: Option<&str>) -> Result<Self, OptimaError> {
+        if !(gain > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Joint limit avoidance gain must be positive (given {}).", gain), file!(), line!()));
+        }
+
+        Ok(Self {
+            gain,
+            joint_weighting_profile_name: joint_weighting_profile_name.map(|s| s.to_string())
+        })
+    }
+    pub fn joint_space_step(&self, robot_joint_state_module: &RobotJointStateModule, joint_state: &RobotJointState) -> Result<DVector<f64>, OptimaError> {
+        let bounds = robot_joint_state_module.get_joint_state_bounds(&RobotJointStateType::DOF);
+        if bounds.len() != joint_state.len() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("JointLimitAvoidance::joint_space_step", joint_state.len(), bounds.len(), file!(), line!()));
+        }
+        let weights = match &self.joint_weighting_profile_name {
+            None => { vec![1.0; bounds.len()] }
+            Some(profile_name) => { robot_joint_state_module.get_joint_state_weights(profile_name, &RobotJointStateType::DOF)? }
+        };
+
+        let mut out_step = DVector::zeros(bounds.len());
+        for (i, (lower, upper)) in bounds.iter().enumerate() {
+            if !(lower.is_finite() && upper.is_finite() && upper > lower) { continue; }
+            let mid = (lower + upper) / 2.0;
+            out_step[i] = -(self.gain * weights[i]).min(1.0) * (joint_state[i] - mid);
+        }
+        Ok(out_step)
+    }
+    pub fn gain(&self) -> f64 {
+        self.gain
+    }
+    pub fn joint_weighting_profile_name(&self) -> &Option<String> {
+        &self.joint_weighting_profile_name
+    }
+}
+
+/// An `IKRegularization` term that pulls the joint axes specified by a named posture of the robot's
+/// configuration (see `NamedPosture`), e.g., "home", toward their posture values, so that repeated
+/// solves produce consistent, predictable arm shapes (e.g., always elbow up).  The step for DOF i is
+/// -min(gain * w_i, 1) * (q_i - r_i), where r_i is the posture value and w_i the DOF's weight in the
+/// named joint weighting profile (1.0 if no profile is given).  DOFs the posture does not specify are
+/// left alone.  The same preference is available as a cost for optimization-based solves (see
+/// `RobotJointStateModule::posture_deviation_cost`).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PosturePreference {
+    posture_name: String,
+    gain: f64,
+    joint_weighting_profile_name: Option<String>
+}
+impl PosturePreference {
+    /// gain is the fraction of the distance to the posture covered per iteration (before weighting
+    /// and null space projection).
+    pub fn new(posture_name: &str, gain: f64, joint_weighting_profile_name: Option<&str>) -> Result<Self, OptimaError> {
+        if !(gain > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Posture preference gain must be positive (given {}).", gain), file!(), line!()));
+        }
+
+        Ok(Self {
+            posture_name: posture_name.to_string(),
+            gain,
+            joint_weighting_profile_name: joint_weighting_profile_name.map(|s| s.to_string())
+        })
+    }
+    pub fn joint_space_step(&self, robot_joint_state_module: &RobotJointStateModule, joint_state: &RobotJointState) -> Result<DVector<f64>, OptimaError> {
+        let values = robot_joint_state_module.get_named_posture_values(&self.posture_name, &RobotJointStateType::DOF)?;
+        if values.len() != joint_state.len() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("PosturePreference::joint_space_step", joint_state.len(), values.len(), file!(), line!()));
+        }
+        let weights = match &self.joint_weighting_profile_name {
+            None => { vec![1.0; values.len()] }
+            Some(profile_name) => { robot_joint_state_module.get_joint_state_weights(profile_name, &RobotJointStateType::DOF)? }
+        };
+
+        let mut out_step = DVector::zeros(values.len());
+        for (i, value) in values.iter().enumerate() {
+            if let Some(value) = value {
+                out_step[i] = -(self.gain * weights[i]).min(1.0) * (joint_state[i] - value);
+            }
+        }
+        Ok(out_step)
+    }
+    pub fn posture_name(&self) -> &str {
+        &self.posture_name
+    }
+    pub fn gain(&self) -> f64 {
+        self.gain
+    }
+    pub fn joint_weighting_profile_name(&self) -> &Option<String> {
+        &self.joint_weighting_profile_name
+    }
+}
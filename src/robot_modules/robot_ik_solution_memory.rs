@@ -0,0 +1,103 @@
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateType};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_nearest_neighbor::{JointStateDistanceMetric, KDTreeNearestNeighbor, NearestNeighborStructure};
+use crate::utils::utils_robot::robot_ids::LinkId;
+use crate::utils::utils_se3::optima_se3_pose::OptimaSE3Pose;
+
+/// Stores (pose, solution) pairs from previous inverse kinematics solves for a single end link so
+/// that new solves can be seeded from the solution of the nearest stored pose (see
+/// `RobotKinematicsModule::compute_ik_damped_least_squares_with_memory`).  For repetitive tasks,
+/// where targets recur or vary smoothly, this usually brings the initial guess close enough that the
+/// solver converges in a handful of iterations and stays in a consistent solution branch.
+///
+/// Poses are embedded in a 9-dimensional feature space, \[position; first two columns of the rotation
+/// matrix\], which is continuous (unlike quaternions, which double cover rotations) and indexed by a
+/// KD-tree.  orientation_weight scales the rotation features relative to meters.  A pose is only
+/// inserted if no stored pose is within min_insertion_distance of it, which keeps the memory from
+/// filling up with duplicates when the same targets are solved repeatedly.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IKSolutionMemory {
+    end_link_id: LinkId,
+    orientation_weight: f64,
+    min_insertion_distance: f64,
+    tree: KDTreeNearestNeighbor<DVector<f64>>
+}
+impl IKSolutionMemory {
+    pub fn new(end_link_id: LinkId, orientation_weight: f64, min_insertion_distance: f64) -> Result<Self, OptimaError> {
+        if !(orientation_weight >= 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Orientation weight must be non-negative (given {}).", orientation_weight), file!(), line!()));
+        }
+        if !(min_insertion_distance >= 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Minimum insertion distance must be non-negative (given {}).", min_insertion_distance), file!(), line!()));
+        }
+
+        let w = orientation_weight * orientation_weight;
+        let metric = JointStateDistanceMetric::WeightedEuclidean { weights: vec![1.0, 1.0, 1.0, w, w, w, w, w, w] };
+
+        Ok(Self {
+            end_link_id,
+            orientation_weight,
+            min_insertion_distance,
+            tree: KDTreeNearestNeighbor::new(metric)
+        })
+    }
+    /// An orientation weight of 0.1 (so that a rotation of about 1 radian counts as much as 10 cm)
+    /// and a minimum insertion distance of 1 mm.
+    pub fn new_default(end_link_id: LinkId) -> Self {
+        return Self::new(end_link_id, 0.1, 0.001).expect("error");
+    }
+    /// Stores the given solution for the given pose.  Returns false if the pose was not inserted
+    /// because a stored pose is within min_insertion_distance of it.  The solution must be a DOF
+    /// joint state.
+    pub fn insert(&mut self, pose: &OptimaSE3Pose, solution: &RobotJointState) -> Result<bool, OptimaError> {
+        if solution.robot_joint_state_type() != &RobotJointStateType::DOF {
+            return Err(OptimaError::new_generic_error_str("IK solution memory only stores DOF joint states.", file!(), line!()));
+        }
+        if let Some(len) = self.solution_dimension() {
+            if solution.len() != len {
+                return Err(OptimaError::new_generic_error_str(&format!("Solution has dimension {}, but stored solutions have dimension {}.", solution.len(), len), file!(), line!()));
+            }
+        }
+
+        let features = Self::pose_features(pose);
+        if let Some(nearest) = self.tree.nearest(&features) {
+            if nearest.distance() <= self.min_insertion_distance { return Ok(false); }
+        }
+        self.tree.insert(features, solution.joint_state().clone())?;
+        Ok(true)
+    }
+    /// Returns the solution stored for the pose nearest to the given pose, along with the distance
+    /// between the two poses in feature space.
+    pub fn nearest_solution(&self, pose: &OptimaSE3Pose) -> Option<(&DVector<f64>, f64)> {
+        let nearest = self.tree.nearest(&Self::pose_features(pose))?;
+        let solution = self.tree.data(nearest.item_idx()).expect("error");
+        return Some((solution, nearest.distance()));
+    }
+    pub fn end_link_id(&self) -> LinkId {
+        self.end_link_id
+    }
+    pub fn orientation_weight(&self) -> f64 {
+        self.orientation_weight
+    }
+    pub fn min_insertion_distance(&self) -> f64 {
+        self.min_insertion_distance
+    }
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.tree.len() == 0
+    }
+    fn solution_dimension(&self) -> Option<usize> {
+        if self.is_empty() { return None; }
+        return Some(self.tree.data(0).expect("error").len());
+    }
+    fn pose_features(pose: &OptimaSE3Pose) -> DVector<f64> {
+        let isometry = pose.to_nalgebra_isometry();
+        let t = &isometry.translation.vector;
+        let r = isometry.rotation.to_rotation_matrix().into_inner();
+        return DVector::from_vec(vec![t[0], t[1], t[2], r[(0, 0)], r[(1, 0)], r[(2, 0)], r[(0, 1)], r[(1, 1)], r[(2, 1)]]);
+    }
+}
@@ -4,15 +4,18 @@ use pyo3::*;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-use nalgebra::DVector;
+use nalgebra::{DVector, Quaternion, SVector, UnitQuaternion, Vector3};
+use optima_core_kinematics::CoreJointComposition;
 use serde::{Serialize, Deserialize};
+use rand::Rng;
 use std::ops::{Add, Index, IndexMut, Mul};
 use crate::robot_modules::robot_configuration_module::{RobotConfigurationModule};
 use crate::utils::utils_console::{optima_print, PrintColor, PrintMode};
 use crate::utils::utils_errors::OptimaError;
-use crate::utils::utils_files::optima_path::{load_object_from_json_string};
+use crate::utils::utils_generic_data_structures::SharedHandle;
 use crate::utils::utils_nalgebra::conversions::NalgebraConversions;
 use crate::utils::utils_robot::joint::{JointAxis, JointAxisPrimitiveType};
+use crate::utils::utils_robot::robot_ids::{DofIdx, JointId};
 use crate::utils::utils_robot::robot_module_utils::RobotNames;
 use crate::utils::utils_sampling::SimpleSamplers;
 use crate::utils::utils_se3::optima_se3_pose::OptimaSE3Pose;
@@ -51,6 +54,10 @@ use crate::utils::utils_traits::{SaveAndLoadable, ToAndFromRonString};
 /// while a full joint state is important for operations such as forward kinematics where all present joint
 /// axes need to somehow contribute to the model.
 ///
+/// Joint axes that are coupled to other joint axes (see `JointCouplingInfo`) are treated like fixed
+/// axes in that they are not degrees of freedom, but their values in a full joint state are computed
+/// from the other axes' values.
+///
 /// A dof joint state can be converted to a full joint state via the function `convert_dof_state_to_full_state`.
 /// A full joint state can be converted to a dof joint state via the function `convert_full_state_to_dof_state`.
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen, derive(Clone, Debug, Serialize, Deserialize))]
@@ -60,12 +67,19 @@ pub struct RobotJointStateModule {
     num_axes: usize,
     ordered_dof_joint_axes: Vec<JointAxis>,
     ordered_joint_axes: Vec<JointAxis>,
-    robot_configuration_module: RobotConfigurationModule,
+    robot_configuration_module: SharedHandle<RobotConfigurationModule>,
     joint_idx_to_dof_state_idxs_mapping: Vec<Vec<usize>>,
     joint_idx_to_full_state_idxs_mapping: Vec<Vec<usize>>,
+    full_state_idx_to_dof_state_idx_mapping: Vec<Option<usize>>,
+    coupled_joint_axis_terms: Vec<Vec<(usize, f64)>>
 }
 impl RobotJointStateModule {
     pub fn new(robot_configuration_module: RobotConfigurationModule) -> Self {
+        return Self::new_from_shared(SharedHandle::new(robot_configuration_module));
+    }
+    /// Same as `new`, but holds on to the given configuration handle rather than taking ownership
+    /// of a copy, so that several modules can share one configuration.
+    pub fn new_from_shared(robot_configuration_module: SharedHandle<RobotConfigurationModule>) -> Self {
         let mut out_self = Self {
             num_dofs: 0,
             num_axes: 0,
@@ -73,12 +87,15 @@ impl RobotJointStateModule {
             ordered_joint_axes: vec![],
             robot_configuration_module,
             joint_idx_to_dof_state_idxs_mapping: vec![],
-            joint_idx_to_full_state_idxs_mapping: vec![]
+            joint_idx_to_full_state_idxs_mapping: vec![],
+            full_state_idx_to_dof_state_idx_mapping: vec![],
+            coupled_joint_axis_terms: vec![]
         };
 
         out_self.set_ordered_joint_axes();
         out_self.initialize_joint_idx_to_full_state_idxs();
         out_self.initialize_joint_idx_to_dof_state_idxs();
+        out_self.initialize_coupled_joint_axis_terms();
         out_self.num_dofs = out_self.ordered_dof_joint_axes.len();
         out_self.num_axes = out_self.ordered_joint_axes.len();
 
@@ -94,7 +111,7 @@ impl RobotJointStateModule {
                 let joint_axes = j.joint_axes();
                 for ja in joint_axes {
                     self.ordered_joint_axes.push(ja.clone());
-                    if !ja.is_fixed() {
+                    if ja.is_dof() {
                         self.ordered_dof_joint_axes.push(ja.clone());
                     }
                 }
@@ -123,23 +140,56 @@ impl RobotJointStateModule {
 
         self.joint_idx_to_full_state_idxs_mapping = out_vec;
     }
+    /// Resolves the terms of each coupled joint axis to (full state idx, coefficient) pairs.  The
+    /// configuration ensures that all terms refer to present joint axes.
+    fn initialize_coupled_joint_axis_terms(&mut self) {
+        let mut full_state_idx_to_dof_state_idx_mapping = vec![];
+        let mut dof_idx = 0;
+        for ja in &self.ordered_joint_axes {
+            if ja.is_dof() {
+                full_state_idx_to_dof_state_idx_mapping.push(Some(dof_idx));
+                dof_idx += 1;
+            } else {
+                full_state_idx_to_dof_state_idx_mapping.push(None);
+            }
+        }
+        self.full_state_idx_to_dof_state_idx_mapping = full_state_idx_to_dof_state_idx_mapping;
+
+        let mut out_vec = vec![];
+        for ja in &self.ordered_joint_axes {
+            let mut terms = vec![];
+            if let Some(coupling) = ja.coupling() {
+                for term in &coupling.terms {
+                    let full_state_idx = self.map_joint_idx_and_sub_dof_idx_to_joint_state_idx(term.joint_idx, term.joint_sub_idx, &RobotJointStateType::Full).expect("error");
+                    terms.push((full_state_idx, term.coefficient));
+                }
+            }
+            out_vec.push(terms);
+        }
+        self.coupled_joint_axis_terms = out_vec;
+    }
     pub fn num_dofs(&self) -> usize {
         self.num_dofs
     }
     pub fn num_axes(&self) -> usize {
         self.num_axes
     }
-    /// Returns joint axes in order (excluding fixed axes, thus only corresponding to degrees of freedom).
+    /// Returns joint axes in order (excluding fixed and coupled axes, thus only corresponding to degrees of freedom).
     pub fn ordered_dof_joint_axes(&self) -> &Vec<JointAxis> {
         &self.ordered_dof_joint_axes
     }
-    /// Returns all joint axes in order (included fixed axes).
+    /// Returns all joint axes in order (included fixed and coupled axes).
     pub fn ordered_joint_axes(&self) -> &Vec<JointAxis> {
         &self.ordered_joint_axes
     }
     /// Converts a joint state to a full state.
     pub fn convert_joint_state_to_full_state(&self, joint_state: &RobotJointState) -> Result<RobotJointState, OptimaError> {
-        if joint_state.robot_joint_state_type() == &RobotJointStateType::Full { return Ok(joint_state.clone()); }
+        if joint_state.robot_joint_state_type() == &RobotJointStateType::Full {
+            if joint_state.len() != self.num_axes {
+                return Err(OptimaError::new_robot_state_vec_wrong_size_error("convert_joint_state_to_full_state", joint_state.len(), self.num_axes, file!(), line!()))
+            }
+            return Ok(joint_state.clone());
+        }
 
         if joint_state.len() != self.num_dofs {
             return Err(OptimaError::new_robot_state_vec_wrong_size_error("convert_dof_state_to_full_state", joint_state.len(), self.num_dofs, file!(), line!()))
@@ -152,6 +202,8 @@ impl RobotJointStateModule {
         for (i, a) in self.ordered_joint_axes.iter().enumerate() {
             if a.is_fixed() {
                 out_robot_state_vector[i] = a.fixed_value().unwrap();
+            } else if a.is_coupled() {
+                out_robot_state_vector[i] = self.coupled_joint_value(joint_state, i);
             } else {
                 out_robot_state_vector[i] = joint_state[bookmark];
                 bookmark += 1;
@@ -173,7 +225,7 @@ impl RobotJointStateModule {
         let mut bookmark = 0 as usize;
 
         for (i, a) in self.ordered_joint_axes.iter().enumerate() {
-            if !a.is_fixed() {
+            if a.is_dof() {
                 out_robot_state_vector[bookmark] = joint_state[i];
                 bookmark += 1;
             }
@@ -181,6 +233,53 @@ impl RobotJointStateModule {
 
         return Ok(RobotJointState::new(out_robot_state_vector, RobotJointStateType::DOF, self)?);
     }
+    /// Returns the value of the coupled joint axis at the given full state index, computed from the
+    /// given DOF or Full joint state as offset + sum(coefficient * value) (see `JointCouplingInfo`).
+    /// Returns 0.0 if the axis is not coupled.
+    pub fn coupled_joint_value<S: RobotJointStateStorage>(&self, joint_state: &S, full_state_idx: usize) -> f64 {
+        let mut out = match self.ordered_joint_axes[full_state_idx].coupling() {
+            None => { return 0.0; }
+            Some(coupling) => { coupling.offset }
+        };
+
+        for (term_full_state_idx, coefficient) in &self.coupled_joint_axis_terms[full_state_idx] {
+            let value = match joint_state.robot_joint_state_type() {
+                RobotJointStateType::Full => { joint_state[*term_full_state_idx] }
+                RobotJointStateType::DOF => {
+                    match self.full_state_idx_to_dof_state_idx_mapping[*term_full_state_idx] {
+                        Some(dof_state_idx) => { joint_state[dof_state_idx] }
+                        None => { self.ordered_joint_axes[*term_full_state_idx].fixed_value().unwrap_or(0.0) }
+                    }
+                }
+            };
+            out += coefficient * value;
+        }
+
+        out
+    }
+    /// Returns true if the values of all coupled joint axes, as computed from the given joint state,
+    /// are within their bounds.  Coupled axes are not degrees of freedom, so their limits cannot be
+    /// enforced by clamping DOF values and have to be checked separately.
+    pub fn coupled_joint_values_within_bounds<S: RobotJointStateStorage>(&self, joint_state: &S) -> bool {
+        for (i, axis) in self.ordered_joint_axes.iter().enumerate() {
+            if axis.is_coupled() {
+                let value = self.coupled_joint_value(joint_state, i);
+                let (lower, upper) = axis.bounds();
+                if value < lower || value > upper { return false; }
+            }
+        }
+        true
+    }
+    /// Returns the indices of the given joint's degrees of freedom in a DOF joint state.
+    pub fn map_joint_id_to_dof_idxs(&self, joint_id: JointId) -> Result<Vec<DofIdx>, OptimaError> {
+        let idxs = self.map_joint_idx_to_joint_state_idxs(joint_id.idx(), &RobotJointStateType::DOF)?;
+        return Ok(idxs.iter().map(|idx| DofIdx::new(*idx)).collect());
+    }
+    /// Returns the joint axis that corresponds to the given degree of freedom.
+    pub fn dof_joint_axis(&self, dof_idx: DofIdx) -> Result<&JointAxis, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(dof_idx.idx(), self.ordered_dof_joint_axes.len(), file!(), line!())?;
+        return Ok(&self.ordered_dof_joint_axes[dof_idx.idx()]);
+    }
     pub fn map_joint_idx_to_joint_state_idxs(&self, joint_idx: usize, joint_state_type: &RobotJointStateType) -> Result<&Vec<usize>, OptimaError> {
         match joint_state_type {
             RobotJointStateType::DOF => {
@@ -206,18 +305,82 @@ impl RobotJointStateModule {
         }
         return Ok(idxs[joint_sub_dof_idx]);
     }
+    /// Returns the external name of the joint axis at the given full state index.  Axes of joints with a
+    /// single joint axis are named after the joint itself, while axes of joints with multiple joint axes
+    /// are named `<joint name>_<joint sub dof idx>`.  This is the naming scheme used by
+    /// `RobotJointStateRemapper` and by exported trajectories.
+    pub fn joint_axis_name(&self, full_state_idx: usize) -> Result<String, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(full_state_idx, self.ordered_joint_axes.len(), file!(), line!())?;
+        let joint_axis = &self.ordered_joint_axes[full_state_idx];
+        let joint = &self.robot_configuration_module.robot_model_module().joints()[joint_axis.joint_idx()];
+        return if joint.joint_axes().len() > 1 {
+            Ok(format!("{}_{}", joint.name(), joint_axis.joint_sub_dof_idx()))
+        } else {
+            Ok(joint.name().to_string())
+        }
+    }
+    /// Inverse of `joint_axis_name`.  Returns None if the given name does not refer to a present joint
+    /// axis.  Returns an error if the name refers to a joint with multiple joint axes without specifying
+    /// the joint sub dof idx.
+    pub fn full_state_idx_from_joint_axis_name(&self, joint_axis_name: &str) -> Result<Option<usize>, OptimaError> {
+        let robot_model_module = self.robot_configuration_module.robot_model_module();
+        if let Some(joint_id) = robot_model_module.get_joint_id_from_name(joint_axis_name) {
+            let num_joint_axes = robot_model_module.joint(joint_id)?.joint_axes().len();
+            if num_joint_axes > 1 {
+                return Err(OptimaError::new_generic_error_str(&format!("Joint {} has {} joint axes.  Its axes must be named individually as {}_<joint sub dof idx>.", joint_axis_name, num_joint_axes, joint_axis_name), file!(), line!()));
+            }
+            return Ok(self.map_joint_idx_to_joint_state_idxs(joint_id.idx(), &RobotJointStateType::Full)?.get(0).cloned());
+        }
+
+        let (joint_name, joint_sub_dof_idx) = match joint_axis_name.rsplit_once('_') {
+            None => { return Ok(None); }
+            Some((joint_name, suffix)) => {
+                match suffix.parse::<usize>() {
+                    Ok(joint_sub_dof_idx) => { (joint_name, joint_sub_dof_idx) }
+                    Err(_) => { return Ok(None); }
+                }
+            }
+        };
+        let joint_idx = match robot_model_module.get_joint_id_from_name(joint_name) {
+            None => { return Ok(None); }
+            Some(joint_id) => { joint_id.idx() }
+        };
+        if robot_model_module.joints()[joint_idx].joint_axes().len() <= 1 { return Ok(None); }
+
+        let full_state_idxs = self.map_joint_idx_to_joint_state_idxs(joint_idx, &RobotJointStateType::Full)?;
+        return Ok(full_state_idxs.iter().find(|i| self.ordered_joint_axes[**i].joint_sub_dof_idx() == joint_sub_dof_idx).cloned());
+    }
     pub fn spawn_robot_joint_state(&self, joint_state: DVector<f64>, robot_joint_state_type: RobotJointStateType) -> Result<RobotJointState, OptimaError> {
         return RobotJointState::new(joint_state, robot_joint_state_type, self);
     }
+    /// Spawns a fixed-size joint state.  N must equal the number of DOFs (for a DOF state) or the
+    /// number of axes (for a Full state).
+    pub fn spawn_robot_joint_state_n<const N: usize>(&self, joint_state: SVector<f64, N>, robot_joint_state_type: RobotJointStateType) -> Result<RobotJointStateN<N>, OptimaError> {
+        return RobotJointStateN::new(joint_state, robot_joint_state_type, self);
+    }
+    /// Converts a `RobotJointState` to a fixed-size joint state of the same type.
+    pub fn convert_robot_joint_state_to_robot_joint_state_n<const N: usize>(&self, joint_state: &RobotJointState) -> Result<RobotJointStateN<N>, OptimaError> {
+        if joint_state.len() != N {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("convert_robot_joint_state_to_robot_joint_state_n", joint_state.len(), N, file!(), line!()));
+        }
+        return RobotJointStateN::new(SVector::from_column_slice(joint_state.joint_state().as_slice()), joint_state.robot_joint_state_type().clone(), self);
+    }
     pub fn spawn_robot_joint_state_try_auto_type(&self, joint_state: DVector<f64>) -> Result<RobotJointState, OptimaError> {
         return RobotJointState::new_try_auto_type(joint_state, self);
     }
+    /// Spawns a joint state with all values set to zero, except for fixed axes (which take their
+    /// fixed value), coupled axes (which take their coupled value), and the w components of ball
+    /// joint quaternions (which are set to 1.0, such that all ball joints are at the identity rotation).
     pub fn spawn_zeros_robot_joint_state(&self, robot_state_type: RobotJointStateType) -> RobotJointState {
         let mut out_joint_state = match robot_state_type {
             RobotJointStateType::DOF => { DVector::zeros(self.num_dofs) }
             RobotJointStateType::Full => { DVector::zeros(self.num_axes) }
         };
 
+        for idxs in self.joint_quaternion_state_idxs(&robot_state_type) {
+            out_joint_state[idxs[0]] = 1.0;
+        }
+
         return match robot_state_type {
             RobotJointStateType::DOF => {
                 RobotJointState::new_unchecked(out_joint_state, robot_state_type.clone())
@@ -229,7 +392,13 @@ impl RobotJointStateModule {
                         out_joint_state[i] = fixed_value;
                     }
                 }
-                RobotJointState::new_unchecked(out_joint_state, robot_state_type.clone())
+                let mut out = RobotJointState::new_unchecked(out_joint_state, robot_state_type.clone());
+                for (i, axis) in self.ordered_joint_axes.iter().enumerate() {
+                    if axis.is_coupled() {
+                        out.joint_state[i] = self.coupled_joint_value(&out, i);
+                    }
+                }
+                out
             }
         }
     }
@@ -270,6 +439,9 @@ impl RobotJointStateModule {
                             else if axis[1] == 1.0 { robot_joint_state[*joint_state_idx] = t[1]; }
                             else if axis[2] == 1.0 { robot_joint_state[*joint_state_idx] = t[2]; }
                         }
+                        JointAxisPrimitiveType::QuaternionComponent => {
+                            /* Contiguous chain connector joints do not have quaternion components. */
+                        }
                     }
                 }
 
@@ -296,28 +468,333 @@ impl RobotJointStateModule {
 
         out_vec
     }
-    pub fn sample_joint_state(&self, t: &RobotJointStateType) -> RobotJointState {
+    /// Returns the velocity limit of each joint axis as specified in the robot's URDF.  Axes whose
+    /// joints do not specify a (positive) velocity limit are given f64::INFINITY, and fixed axes are
+    /// given 0.0.
+    pub fn get_joint_state_velocity_limits(&self, t: &RobotJointStateType) -> Vec<f64> {
+        let axes = match t {
+            RobotJointStateType::DOF => { &self.ordered_dof_joint_axes }
+            RobotJointStateType::Full => { &self.ordered_joint_axes }
+        };
+
+        let joints = self.robot_configuration_module.robot_model_module().joints();
+        let mut out_vec = vec![];
+
+        for axis in axes {
+            if axis.is_fixed() { out_vec.push(0.0); continue; }
+            let urdf_joint = joints[axis.joint_idx()].urdf_joint();
+            if urdf_joint.includes_limits() && urdf_joint.limits_velocity() > 0.0 {
+                out_vec.push(urdf_joint.limits_velocity());
+            } else {
+                out_vec.push(f64::INFINITY);
+            }
+        }
+
+        out_vec
+    }
+    /// Same as `get_joint_state_bounds`, but with the configuration's soft joint limit margin applied
+    /// (see `SoftJointLimitMargin`).  If the configuration has no soft joint limit margin, these are
+    /// the same as the hard bounds.
+    pub fn get_joint_state_soft_bounds(&self, t: &RobotJointStateType) -> Vec<(f64, f64)> {
         let axes = match t {
             RobotJointStateType::DOF => { &self.ordered_dof_joint_axes }
             RobotJointStateType::Full => { &self.ordered_joint_axes }
         };
 
-        let mut out_dvec = DVector::zeros(axes.len());
+        let mut out_vec = vec![];
 
-        for (i, axis) in axes.iter().enumerate() {
+        for axis in axes {
             let fixed_value = axis.fixed_value();
             match fixed_value {
-                None => {
-                    let sample = SimpleSamplers::uniform_samples(&vec![axis.bounds()]);
-                    out_dvec[i] = sample[0];
-                }
-                Some(fixed_value) => {
-                    out_dvec[i] = fixed_value
+                None => { out_vec.push( self.soft_bounds(axis.bounds()) ) }
+                Some(fixed_value) => { out_vec.push( (fixed_value, fixed_value) ); }
+            }
+        }
+
+        out_vec
+    }
+    /// Returns the weight of each joint axis under the configuration's joint weighting profile with the
+    /// given name (see `JointWeightingProfile`).  Returns an error if the configuration has no such
+    /// profile.
+    pub fn get_joint_state_weights(&self, profile_name: &str, t: &RobotJointStateType) -> Result<Vec<f64>, OptimaError> {
+        let axes = match t {
+            RobotJointStateType::DOF => { &self.ordered_dof_joint_axes }
+            RobotJointStateType::Full => { &self.ordered_joint_axes }
+        };
+
+        let joint_weighting_profile = match self.robot_configuration_module.robot_configuration_info().joint_weighting_profile(profile_name) {
+            None => { return Err(OptimaError::new_generic_error_str(&format!("Robot configuration has no joint weighting profile named {}.", profile_name), file!(), line!())); }
+            Some(joint_weighting_profile) => { joint_weighting_profile }
+        };
+
+        let mut out_vec = vec![];
+
+        for axis in axes {
+            out_vec.push(joint_weighting_profile.weight(axis.joint_idx(), axis.joint_sub_dof_idx()));
+        }
+
+        Ok(out_vec)
+    }
+    /// Returns the value of each joint axis under the configuration's named posture with the given
+    /// name (see `NamedPosture`), or None for axes the posture does not specify.  Returns an error if
+    /// the configuration has no such posture.
+    pub fn get_named_posture_values(&self, posture_name: &str, t: &RobotJointStateType) -> Result<Vec<Option<f64>>, OptimaError> {
+        let axes = match t {
+            RobotJointStateType::DOF => { &self.ordered_dof_joint_axes }
+            RobotJointStateType::Full => { &self.ordered_joint_axes }
+        };
+
+        let named_posture = match self.robot_configuration_module.robot_configuration_info().named_posture(posture_name) {
+            None => { return Err(OptimaError::new_generic_error_str(&format!("Robot configuration has no named posture {}.", posture_name), file!(), line!())); }
+            Some(named_posture) => { named_posture }
+        };
+
+        let mut out_vec = vec![];
+
+        for axis in axes {
+            out_vec.push(named_posture.value(axis.joint_idx(), axis.joint_sub_dof_idx()));
+        }
+
+        Ok(out_vec)
+    }
+    /// Returns sum_i w_i * (q_i - r_i)^2 over the axes specified by the named posture with the given
+    /// name, where r_i are the posture's values and w_i the weights of the given joint weighting
+    /// profile (all 1.0 if None).  This is the posture preference cost used for consistent arm shapes
+    /// across solves (see `NamedPosture`).
+    pub fn posture_deviation_cost(&self, joint_state: &RobotJointState, posture_name: &str, joint_weighting_profile_name: Option<&str>) -> Result<f64, OptimaError> {
+        let mut out = 0.0;
+        for (d, w) in self.posture_deviations(joint_state, posture_name, joint_weighting_profile_name)? {
+            out += w * d * d;
+        }
+        Ok(out)
+    }
+    /// Returns the gradient of `posture_deviation_cost` with respect to the joint state.
+    pub fn posture_deviation_cost_gradient(&self, joint_state: &RobotJointState, posture_name: &str, joint_weighting_profile_name: Option<&str>) -> Result<DVector<f64>, OptimaError> {
+        let mut out = DVector::zeros(joint_state.len());
+        for (i, (d, w)) in self.posture_deviations(joint_state, posture_name, joint_weighting_profile_name)?.iter().enumerate() {
+            out[i] = 2.0 * w * d;
+        }
+        Ok(out)
+    }
+    /// Returns sqrt(sum_i w_i * (a_i - b_i)^2), where the w_i are the weights of the joint weighting
+    /// profile with the given name (see `get_joint_state_weights`).
+    pub fn weighted_joint_state_distance(&self, a: &RobotJointState, b: &RobotJointState, profile_name: &str) -> Result<f64, OptimaError> {
+        if a.robot_joint_state_type() != b.robot_joint_state_type() {
+            return Err(OptimaError::new_generic_error_str(&format!("Cannot compute distance between joint states of type {:?} and {:?}.", a.robot_joint_state_type(), b.robot_joint_state_type()), file!(), line!()));
+        }
+        let weights = self.get_joint_state_weights(profile_name, a.robot_joint_state_type())?;
+
+        let mut out = 0.0;
+        for (i, w) in weights.iter().enumerate() {
+            out += w * (a[i] - b[i]).powi(2);
+        }
+        Ok(out.sqrt())
+    }
+    /// Returns a copy of the given joint state with every value clamped to its axis' hard bounds.
+    /// Ball joint quaternions are also normalized (see `normalize_joint_quaternions`).
+    pub fn clamp_joint_state_to_bounds(&self, joint_state: &RobotJointState) -> RobotJointState {
+        let bounds = self.get_joint_state_bounds(&joint_state.robot_joint_state_type);
+        let mut out = joint_state.clone();
+        for (i, (lower, upper)) in bounds.iter().enumerate() {
+            out[i] = out[i].max(*lower).min(*upper);
+        }
+        self.normalize_joint_quaternions(&mut out);
+        out
+    }
+    /// Returns, for each ball joint (see `JointAxisPrimitiveType::QuaternionComponent`), the indices of
+    /// its w, x, y, and z components in a joint state of the given type.  Ball joints with any fixed or
+    /// coupled components are skipped in DOF joint states.
+    pub fn joint_quaternion_state_idxs(&self, t: &RobotJointStateType) -> Vec<[usize; 4]> {
+        let mut out_vec = vec![];
+        let axes = match t {
+            RobotJointStateType::DOF => { &self.ordered_dof_joint_axes }
+            RobotJointStateType::Full => { &self.ordered_joint_axes }
+        };
+
+        for joint in self.robot_configuration_module.robot_model_module().joints() {
+            if !joint.active() { continue; }
+            if let Some(quaternion_sub_dof_idx) = joint.quaternion_sub_dof_idx() {
+                let joint_state_idxs = self.map_joint_idx_to_joint_state_idxs(joint.joint_idx(), t).expect("error");
+                let mut idxs = [0; 4];
+                let mut num_found = 0;
+                for joint_state_idx in joint_state_idxs.iter() {
+                    let sub_dof_idx = axes[*joint_state_idx].joint_sub_dof_idx();
+                    if sub_dof_idx >= quaternion_sub_dof_idx && sub_dof_idx < quaternion_sub_dof_idx + 4 {
+                        idxs[sub_dof_idx - quaternion_sub_dof_idx] = *joint_state_idx;
+                        num_found += 1;
+                    }
                 }
+                if num_found == 4 { out_vec.push(idxs); }
             }
         }
 
-        return RobotJointState::new(out_dvec, t.clone(), self).expect("error");
+        out_vec
+    }
+    /// Returns the (possibly non-unit) quaternion of the given ball joint from a DOF or Full joint
+    /// state, or None if the joint is not a ball joint.
+    pub fn joint_quaternion(&self, joint_state: &RobotJointState, joint_idx: usize) -> Result<Option<Quaternion<f64>>, OptimaError> {
+        let joints = self.robot_configuration_module.robot_model_module().joints();
+        OptimaError::new_check_for_idx_out_of_bound_error(joint_idx, joints.len(), file!(), line!())?;
+        let quaternion_sub_dof_idx = match joints[joint_idx].quaternion_sub_dof_idx() {
+            None => { return Ok(None); }
+            Some(quaternion_sub_dof_idx) => { quaternion_sub_dof_idx }
+        };
+
+        let full_state = self.convert_joint_state_to_full_state(joint_state)?;
+        let full_state_idxs = self.map_joint_idx_to_joint_state_idxs(joint_idx, &RobotJointStateType::Full)?;
+        let mut c = [0.0; 4];
+        for i in 0..4 {
+            c[i] = full_state[full_state_idxs[quaternion_sub_dof_idx + i]];
+        }
+
+        return Ok(Some(Quaternion::new(c[0], c[1], c[2], c[3])));
+    }
+    /// Converts ball joint values (w, x, y, z) to a unit quaternion.  A zero quaternion is treated as
+    /// the identity.
+    pub fn joint_values_to_unit_quaternion(w: f64, x: f64, y: f64, z: f64) -> UnitQuaternion<f64> {
+        return CoreJointComposition::unit_quaternion_from_joint_values(w, x, y, z);
+    }
+    /// Normalizes the quaternion of each ball joint in the given joint state to unit length.
+    pub fn normalize_joint_quaternions(&self, joint_state: &mut RobotJointState) {
+        for idxs in self.joint_quaternion_state_idxs(&joint_state.robot_joint_state_type) {
+            let q = Self::joint_values_to_unit_quaternion(joint_state[idxs[0]], joint_state[idxs[1]], joint_state[idxs[2]], joint_state[idxs[3]]);
+            joint_state[idxs[0]] = q.w;
+            joint_state[idxs[1]] = q.i;
+            joint_state[idxs[2]] = q.j;
+            joint_state[idxs[3]] = q.k;
+        }
+    }
+    /// Interpolates between two joint states of the same type, where u = 0 gives a and u = 1 gives b.
+    /// Scalar joint axes are interpolated linearly, and ball joint quaternions are interpolated along
+    /// the shortest great arc (slerp).
+    pub fn interpolate_joint_states(&self, a: &RobotJointState, b: &RobotJointState, u: f64) -> Result<RobotJointState, OptimaError> {
+        if a.robot_joint_state_type() != b.robot_joint_state_type() {
+            return Err(OptimaError::new_generic_error_str(&format!("Cannot interpolate between joint states of type {:?} and {:?}.", a.robot_joint_state_type(), b.robot_joint_state_type()), file!(), line!()));
+        }
+        if a.len() != b.len() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("interpolate_joint_states", b.len(), a.len(), file!(), line!()));
+        }
+
+        let mut out = RobotJointState::new(a.joint_state() + u * (b.joint_state() - a.joint_state()), a.robot_joint_state_type().clone(), self)?;
+        for idxs in self.joint_quaternion_state_idxs(a.robot_joint_state_type()) {
+            let qa = Self::joint_values_to_unit_quaternion(a[idxs[0]], a[idxs[1]], a[idxs[2]], a[idxs[3]]);
+            let mut qb = Self::joint_values_to_unit_quaternion(b[idxs[0]], b[idxs[1]], b[idxs[2]], b[idxs[3]]);
+            if qa.coords.dot(&qb.coords) < 0.0 { qb = UnitQuaternion::new_unchecked(-qb.into_inner()); }
+            let q = qa.slerp(&qb, u);
+            out[idxs[0]] = q.w;
+            out[idxs[1]] = q.i;
+            out[idxs[2]] = q.j;
+            out[idxs[3]] = q.k;
+        }
+
+        return Ok(out);
+    }
+    /// Returns the soft joint limit cost of the given joint state.  Each axis contributes
+    /// (d / w)^2, where d is how far the value is inside the soft limit band (or beyond it) and w
+    /// is the width of the band (1.0 if the band has zero width), so an axis at a hard limit contributes
+    /// 1.0 and an axis within its soft bounds contributes 0.0.
+    pub fn soft_joint_limit_cost(&self, joint_state: &RobotJointState) -> f64 {
+        let mut out = 0.0;
+        for (d, w) in self.soft_joint_limit_penetrations(joint_state) {
+            out += (d / w).powi(2);
+        }
+        out
+    }
+    /// Returns the gradient of `soft_joint_limit_cost` with respect to the joint state.
+    pub fn soft_joint_limit_cost_gradient(&self, joint_state: &RobotJointState) -> DVector<f64> {
+        let mut out = DVector::zeros(joint_state.len());
+        for (i, (d, w)) in self.soft_joint_limit_penetrations(joint_state).iter().enumerate() {
+            out[i] = 2.0 * d / (w * w);
+        }
+        out
+    }
+    /// Returns, for each axis, the signed distance of the value past its soft bounds (negative below
+    /// the soft lower bound, positive above the soft upper bound, and zero in between) along with the
+    /// width of the corresponding soft limit band.
+    fn soft_joint_limit_penetrations(&self, joint_state: &RobotJointState) -> Vec<(f64, f64)> {
+        let bounds = self.get_joint_state_bounds(&joint_state.robot_joint_state_type);
+        let soft_bounds = self.get_joint_state_soft_bounds(&joint_state.robot_joint_state_type);
+
+        let mut out_vec = vec![];
+        for i in 0..bounds.len() {
+            let value = joint_state[i];
+            let (lower, upper) = bounds[i];
+            let (soft_lower, soft_upper) = soft_bounds[i];
+            if value < soft_lower {
+                let w = soft_lower - lower;
+                out_vec.push((value - soft_lower, if w > 0.0 && w.is_finite() { w } else { 1.0 }));
+            } else if value > soft_upper {
+                let w = upper - soft_upper;
+                out_vec.push((value - soft_upper, if w > 0.0 && w.is_finite() { w } else { 1.0 }));
+            } else {
+                out_vec.push((0.0, 1.0));
+            }
+        }
+        out_vec
+    }
+    /// Returns, for each axis, the deviation of the value from the named posture along with the
+    /// axis' weight.  Axes the posture does not specify get a deviation of zero.
+    fn posture_deviations(&self, joint_state: &RobotJointState, posture_name: &str, joint_weighting_profile_name: Option<&str>) -> Result<Vec<(f64, f64)>, OptimaError> {
+        let values = self.get_named_posture_values(posture_name, &joint_state.robot_joint_state_type)?;
+        if values.len() != joint_state.len() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("posture_deviations", joint_state.len(), values.len(), file!(), line!()));
+        }
+        let weights = match joint_weighting_profile_name {
+            None => { vec![1.0; values.len()] }
+            Some(profile_name) => { self.get_joint_state_weights(profile_name, &joint_state.robot_joint_state_type)? }
+        };
+
+        let mut out_vec = vec![];
+        for (i, value) in values.iter().enumerate() {
+            match value {
+                None => { out_vec.push((0.0, weights[i])); }
+                Some(value) => { out_vec.push((joint_state[i] - value, weights[i])); }
+            }
+        }
+        Ok(out_vec)
+    }
+    fn soft_bounds(&self, hard_bounds: (f64, f64)) -> (f64, f64) {
+        return match self.robot_configuration_module.robot_configuration_info().soft_joint_limit_margin() {
+            None => { hard_bounds }
+            Some(soft_joint_limit_margin) => { soft_joint_limit_margin.soft_bounds(hard_bounds) }
+        }
+    }
+    /// Samples a joint state uniformly within the soft bounds of each axis (see
+    /// `get_joint_state_soft_bounds`).  Fixed axes take their fixed value, and coupled axes take the
+    /// value computed from the sampled axes.  Ball joint quaternions are sampled uniformly over all
+    /// rotations.  If the configuration has coupled axes, DOF values are resampled (up to
+    /// `MAX_COUPLED_SAMPLE_ATTEMPTS` times) until all coupled values are within their bounds; if no
+    /// such sample is found, the last sample is returned.
+    pub fn sample_joint_state(&self, t: &RobotJointStateType) -> RobotJointState {
+        return self.sample_joint_state_with_rng(t, &mut rand::thread_rng());
+    }
+    /// Same as `sample_joint_state`, drawing from the given random number generator (e.g., a seeded
+    /// `rand_chacha::ChaCha8Rng` for reproducible samples).
+    pub fn sample_joint_state_with_rng<R: Rng + ?Sized>(&self, t: &RobotJointStateType, rng: &mut R) -> RobotJointState {
+        let joint_quaternion_dof_idxs = self.joint_quaternion_state_idxs(&RobotJointStateType::DOF);
+        let mut out_dvec = DVector::zeros(self.num_dofs);
+        for _ in 0..MAX_COUPLED_SAMPLE_ATTEMPTS {
+            for (i, axis) in self.ordered_dof_joint_axes.iter().enumerate() {
+                let sample = SimpleSamplers::uniform_samples_with_rng(&vec![self.soft_bounds(axis.bounds())], rng);
+                out_dvec[i] = sample[0];
+            }
+            for idxs in &joint_quaternion_dof_idxs {
+                // Uniformly distributed unit quaternion (Shoemake, 1992).
+                let sample = SimpleSamplers::uniform_samples_with_rng(&vec![(0.0, 1.0), (0.0, 2.0 * std::f64::consts::PI), (0.0, 2.0 * std::f64::consts::PI)], rng);
+                out_dvec[idxs[0]] = sample[0].sqrt() * sample[2].cos();
+                out_dvec[idxs[1]] = (1.0 - sample[0]).sqrt() * sample[1].sin();
+                out_dvec[idxs[2]] = (1.0 - sample[0]).sqrt() * sample[1].cos();
+                out_dvec[idxs[3]] = sample[0].sqrt() * sample[2].sin();
+            }
+            let dof_state = RobotJointState::new_unchecked(out_dvec.clone(), RobotJointStateType::DOF);
+            if self.coupled_joint_values_within_bounds(&dof_state) { break; }
+        }
+
+        let dof_state = RobotJointState::new(out_dvec, RobotJointStateType::DOF, self).expect("error");
+        return match t {
+            RobotJointStateType::DOF => { dof_state }
+            RobotJointStateType::Full => { self.convert_joint_state_to_full_state(&dof_state).expect("error") }
+        }
     }
     pub fn print_robot_joint_state_summary(&self, robot_joint_state: &RobotJointState)  {
         let joint_axes = match robot_joint_state.robot_joint_state_type {
@@ -338,16 +815,82 @@ impl RobotJointStateModule {
     pub fn robot_name(&self) -> &str {
         return self.robot_configuration_module.robot_name()
     }
+    pub fn robot_configuration_module(&self) -> &RobotConfigurationModule {
+        &self.robot_configuration_module
+    }
+    /// Sets the lower and upper bounds of all axes of the given joint (see
+    /// `RobotConfigurationModule::set_joint_limits`) and re-derives the module's joint axes.
+    pub fn set_joint_limits(&mut self, joint_id: JointId, lower: f64, upper: f64) -> Result<(), OptimaError> {
+        let mut robot_configuration_module = self.robot_configuration_module.to_owned_inner();
+        robot_configuration_module.set_joint_limits(joint_id, lower, upper)?;
+        *self = Self::new(robot_configuration_module);
+        Ok(())
+    }
+    /// Sets the lower and upper bounds of a single axis of the given joint and re-derives the
+    /// module's joint axes.
+    pub fn set_joint_sub_dof_limits(&mut self, joint_id: JointId, joint_sub_idx: usize, lower: f64, upper: f64) -> Result<(), OptimaError> {
+        let mut robot_configuration_module = self.robot_configuration_module.to_owned_inner();
+        robot_configuration_module.set_joint_sub_dof_limits(joint_id, joint_sub_idx, lower, upper)?;
+        *self = Self::new(robot_configuration_module);
+        Ok(())
+    }
+    /// Sets the axis of the given joint and re-derives the module's joint axes.
+    pub fn set_joint_axis(&mut self, joint_id: JointId, axis: Vector3<f64>) -> Result<(), OptimaError> {
+        let mut robot_configuration_module = self.robot_configuration_module.to_owned_inner();
+        robot_configuration_module.set_joint_axis(joint_id, axis)?;
+        *self = Self::new(robot_configuration_module);
+        Ok(())
+    }
+    /// Maps a joint state from this module's configuration to the joint state space of another
+    /// configuration of the same robot (e.g., when one configuration has joints fixed that are free in
+    /// the other).  Joint axes are matched by joint name and joint sub dof index.  Axes that are fixed in
+    /// the other configuration always take their fixed value.  Axes that are free in the other
+    /// configuration but do not exist in this configuration (e.g., a mobile base chain) take the
+    /// corresponding value from other_base_joint_state if given, and are otherwise set to 0.0.
+    pub fn map_joint_state_to_other_configuration(&self, joint_state: &RobotJointState, other: &RobotJointStateModule, other_base_joint_state: Option<&RobotJointState>, t: &RobotJointStateType) -> Result<RobotJointState, OptimaError> {
+        if self.robot_name() != other.robot_name() {
+            return Err(OptimaError::new_generic_error_str(&format!("Cannot map joint state from robot {} to robot {}.", self.robot_name(), other.robot_name()), file!(), line!()));
+        }
+
+        let full_state = self.convert_joint_state_to_full_state(joint_state)?;
+        let mut out_full_state = match other_base_joint_state {
+            None => { other.spawn_zeros_robot_joint_state(RobotJointStateType::Full) }
+            Some(other_base_joint_state) => { other.convert_joint_state_to_full_state(other_base_joint_state)? }
+        };
+
+        let self_robot_model_module = self.robot_configuration_module.robot_model_module();
+        let other_robot_model_module = other.robot_configuration_module.robot_model_module();
+        for (i, axis) in other.ordered_joint_axes.iter().enumerate() {
+            if !axis.is_dof() { continue; }
+
+            let joint_name = other_robot_model_module.joints()[axis.joint_idx()].name();
+            let self_joint_id = self_robot_model_module.get_joint_id_from_name(joint_name);
+            if let Some(self_joint_id) = self_joint_id {
+                let self_full_state_idxs = self.map_joint_idx_to_joint_state_idxs(self_joint_id.idx(), &RobotJointStateType::Full)?;
+                if let Some(self_full_state_idx) = self_full_state_idxs.get(axis.joint_sub_dof_idx()) {
+                    out_full_state[i] = full_state[*self_full_state_idx];
+                }
+            }
+        }
+
+        // Round trip through a DOF state so that fixed and coupled axes (including those taken from a Full
+        // other_base_joint_state) are projected onto the other configuration.
+        let out_dof_state = other.convert_joint_state_to_dof_state(&out_full_state)?;
+        return match t {
+            RobotJointStateType::DOF => { Ok(out_dof_state) }
+            RobotJointStateType::Full => { other.convert_joint_state_to_full_state(&out_dof_state) }
+        }
+    }
 }
 impl SaveAndLoadable for RobotJointStateModule {
     type SaveType = String;
 
     fn get_save_serialization_object(&self) -> Self::SaveType {
-        self.robot_configuration_module.get_serialization_string()
+        (*self.robot_configuration_module).get_serialization_string()
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let load: Self::SaveType = load_object_from_json_string(json_str)?;
+        let load: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
         let robot_configuration_module = RobotConfigurationModule::load_from_json_string(&load)?;
         return Ok(RobotJointStateModule::new(robot_configuration_module));
     }
@@ -380,6 +923,19 @@ impl RobotJointStateModule {
         self.get_joint_state_bounds(&RobotJointStateType::from_ron_string(robot_joint_state_type).expect("error"))
     }
     #[args(robot_joint_state_type = "\"DOF\"")]
+    pub fn get_joint_state_velocity_limits_py(&self, robot_joint_state_type: &str) -> Vec<f64> {
+        self.get_joint_state_velocity_limits(&RobotJointStateType::from_ron_string(robot_joint_state_type).expect("error"))
+    }
+    #[args(robot_joint_state_type = "\"DOF\"")]
+    pub fn get_joint_state_weights_py(&self, profile_name: &str, robot_joint_state_type: &str) -> Vec<f64> {
+        self.get_joint_state_weights(profile_name, &RobotJointStateType::from_ron_string(robot_joint_state_type).expect("error")).expect("error")
+    }
+    /// Returns the named posture's value for each axis (None for unspecified axes).
+    #[args(robot_joint_state_type = "\"DOF\"")]
+    pub fn get_named_posture_values_py(&self, posture_name: &str, robot_joint_state_type: &str) -> Vec<Option<f64>> {
+        self.get_named_posture_values(posture_name, &RobotJointStateType::from_ron_string(robot_joint_state_type).expect("error")).expect("error")
+    }
+    #[args(robot_joint_state_type = "\"DOF\"")]
     pub fn sample_joint_state_py(&self, robot_joint_state_type: &str) -> Vec<f64> {
         let s = self.sample_joint_state(&RobotJointStateType::from_ron_string(robot_joint_state_type).expect("error"));
         let vec: &Vec<f64> = s.joint_state.data.as_vec();
@@ -397,6 +953,22 @@ impl RobotJointStateModule {
     pub fn ordered_joint_axes_py(&self) -> Vec<JointAxis> {
         self.ordered_joint_axes.clone()
     }
+    pub fn interpolate_joint_states_py(&self, a: Vec<f64>, b: Vec<f64>, u: f64) -> Vec<f64> {
+        let a = self.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&a)).expect("error");
+        let b = self.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&b)).expect("error");
+        let res = self.interpolate_joint_states(&a, &b, u).expect("error");
+        return NalgebraConversions::dvector_to_vec(&res.joint_state);
+    }
+    pub fn coupled_joint_values_within_bounds_py(&self, joint_state: Vec<f64>) -> bool {
+        let robot_state = self.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
+        self.coupled_joint_values_within_bounds(&robot_state)
+    }
+    #[args(robot_joint_state_type = "\"DOF\"")]
+    pub fn map_joint_state_to_other_configuration_py(&self, joint_state: Vec<f64>, other: &RobotJointStateModule, robot_joint_state_type: &str) -> Vec<f64> {
+        let robot_state = self.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
+        let res = self.map_joint_state_to_other_configuration(&robot_state, other, None, &RobotJointStateType::from_ron_string(robot_joint_state_type).expect("error")).expect("error");
+        return NalgebraConversions::dvector_to_vec(&res.joint_state);
+    }
 
 }
 
@@ -427,6 +999,142 @@ impl RobotJointStateModule {
     }
 }
 
+/// The maximum number of times `RobotJointStateModule::sample_joint_state` resamples the DOF values
+/// of a configuration with coupled joint axes.
+pub const MAX_COUPLED_SAMPLE_ATTEMPTS: usize = 1000;
+
+/// Converts joint value vectors given in an external joint ordering (e.g., the `name` list of a ROS
+/// JointState message or a vendor controller's joint list) to and from this library's canonical
+/// `RobotJointState` ordering.  The permutation between the two orderings is computed once on
+/// construction and cached.
+///
+/// External joint names that do not correspond to a present joint in the robot configuration
+/// (e.g., gripper finger joints that are not in the model) are ignored when converting to a
+/// `RobotJointState`, and are given a value of 0.0 when converting to an external vector.
+/// External names follow `RobotJointStateModule::joint_axis_name`: a joint with a single joint axis
+/// is referred to by its joint name, and each axis of a joint with multiple joint axes is referred to
+/// as `<joint name>_<joint sub dof idx>`.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen, derive(Clone, Debug, Serialize, Deserialize))]
+#[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
+pub struct RobotJointStateRemapper {
+    robot_joint_state_module: RobotJointStateModule,
+    external_joint_names: Vec<String>,
+    external_idx_to_full_state_idx: Vec<Option<usize>>
+}
+impl RobotJointStateRemapper {
+    pub fn new(robot_joint_state_module: &RobotJointStateModule, external_joint_names: &Vec<String>) -> Result<Self, OptimaError> {
+        let mut external_idx_to_full_state_idx = vec![];
+        for external_joint_name in external_joint_names {
+            external_idx_to_full_state_idx.push(robot_joint_state_module.full_state_idx_from_joint_axis_name(external_joint_name)?);
+        }
+
+        Ok(Self {
+            robot_joint_state_module: robot_joint_state_module.clone(),
+            external_joint_names: external_joint_names.clone(),
+            external_idx_to_full_state_idx
+        })
+    }
+    /// Converts a vector in the external ordering to a `RobotJointState` of the given type.
+    /// Degrees of freedom that are not covered by the external joint names are taken from
+    /// base_joint_state if given, and are otherwise set to 0.0.  The result is projected onto the robot
+    /// configuration: fixed axes always take their fixed value and coupled axes are recomputed from
+    /// the degrees of freedom, so values given for fixed or coupled axes are ignored.
+    pub fn external_vector_to_robot_joint_state(&self, external_vector: &DVector<f64>, base_joint_state: Option<&RobotJointState>, t: &RobotJointStateType) -> Result<RobotJointState, OptimaError> {
+        if external_vector.len() != self.external_joint_names.len() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("external_vector_to_robot_joint_state", external_vector.len(), self.external_joint_names.len(), file!(), line!()));
+        }
+
+        let mut full_state = match base_joint_state {
+            None => { self.robot_joint_state_module.spawn_zeros_robot_joint_state(RobotJointStateType::Full) }
+            Some(base_joint_state) => { self.robot_joint_state_module.convert_joint_state_to_full_state(base_joint_state)? }
+        };
+
+        for (external_idx, full_state_idx) in self.external_idx_to_full_state_idx.iter().enumerate() {
+            if let Some(full_state_idx) = full_state_idx {
+                full_state[*full_state_idx] = external_vector[external_idx];
+            }
+        }
+
+        // Round trip through a DOF state so that fixed and coupled axes are projected onto the configuration.
+        let dof_state = self.robot_joint_state_module.convert_joint_state_to_dof_state(&full_state)?;
+        return match t {
+            RobotJointStateType::DOF => { Ok(dof_state) }
+            RobotJointStateType::Full => { self.robot_joint_state_module.convert_joint_state_to_full_state(&dof_state) }
+        }
+    }
+    /// Converts a `RobotJointState` to a vector in the external ordering.
+    pub fn robot_joint_state_to_external_vector(&self, robot_joint_state: &RobotJointState) -> Result<DVector<f64>, OptimaError> {
+        let full_state = self.robot_joint_state_module.convert_joint_state_to_full_state(robot_joint_state)?;
+
+        let mut out_vec = DVector::zeros(self.external_joint_names.len());
+        for (external_idx, full_state_idx) in self.external_idx_to_full_state_idx.iter().enumerate() {
+            if let Some(full_state_idx) = full_state_idx {
+                out_vec[external_idx] = full_state[*full_state_idx];
+            }
+        }
+
+        Ok(out_vec)
+    }
+    pub fn external_joint_names(&self) -> &Vec<String> {
+        &self.external_joint_names
+    }
+    /// Returns the full joint state index that each external joint name maps to (None if the external
+    /// name does not refer to a present joint axis in the robot configuration).
+    pub fn external_idx_to_full_state_idx(&self) -> &Vec<Option<usize>> {
+        &self.external_idx_to_full_state_idx
+    }
+    /// Returns the external joint names that do not correspond to any joint in the robot configuration.
+    pub fn unmatched_external_joint_names(&self) -> Vec<String> {
+        let mut out_vec = vec![];
+        for (i, full_state_idx) in self.external_idx_to_full_state_idx.iter().enumerate() {
+            if full_state_idx.is_none() { out_vec.push(self.external_joint_names[i].clone()); }
+        }
+        out_vec
+    }
+}
+
+/// Python implementations.
+#[cfg(not(target_arch = "wasm32"))]
+#[pymethods]
+impl RobotJointStateRemapper {
+    #[new]
+    pub fn new_py(robot_joint_state_module: &RobotJointStateModule, external_joint_names: Vec<String>) -> Self {
+        Self::new(robot_joint_state_module, &external_joint_names).expect("error")
+    }
+    #[args(robot_joint_state_type = "\"DOF\"")]
+    pub fn external_vector_to_robot_joint_state_py(&self, external_vector: Vec<f64>, base_joint_state: Option<Vec<f64>>, robot_joint_state_type: &str) -> Vec<f64> {
+        let base_joint_state = match base_joint_state {
+            None => { None }
+            Some(base_joint_state) => { Some(self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&base_joint_state)).expect("error")) }
+        };
+        let res = self.external_vector_to_robot_joint_state(&NalgebraConversions::vec_to_dvector(&external_vector), base_joint_state.as_ref(), &RobotJointStateType::from_ron_string(robot_joint_state_type).expect("error")).expect("error");
+        return NalgebraConversions::dvector_to_vec(&res.joint_state);
+    }
+    pub fn robot_joint_state_to_external_vector_py(&self, joint_state: Vec<f64>) -> Vec<f64> {
+        let robot_joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
+        let res = self.robot_joint_state_to_external_vector(&robot_joint_state).expect("error");
+        return NalgebraConversions::dvector_to_vec(&res);
+    }
+    pub fn unmatched_external_joint_names_py(&self) -> Vec<String> {
+        self.unmatched_external_joint_names()
+    }
+}
+
+/// WASM implementations.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl RobotJointStateRemapper {
+    pub fn external_vector_to_robot_joint_state_wasm(&self, external_vector: Vec<f64>, robot_joint_state_type: &str) -> Vec<f64> {
+        let res = self.external_vector_to_robot_joint_state(&NalgebraConversions::vec_to_dvector(&external_vector), None, &RobotJointStateType::from_ron_string(robot_joint_state_type).expect("error")).expect("error");
+        return NalgebraConversions::dvector_to_vec(&res.joint_state);
+    }
+    pub fn robot_joint_state_to_external_vector_wasm(&self, joint_state: Vec<f64>) -> Vec<f64> {
+        let robot_joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
+        let res = self.robot_joint_state_to_external_vector(&robot_joint_state).expect("error");
+        return NalgebraConversions::dvector_to_vec(&res);
+    }
+}
+
 /// "Robot states" are vectors that contain scalar joint values for each joint axis in the robot model.
 /// These objects are sometimes referred to as robot configurations or robot poses in the robotics literature,
 /// but in this library, we will stick to the convention of referring to them as robot states.
@@ -526,6 +1234,84 @@ impl IndexMut<usize> for RobotJointState {
         &mut self.joint_state[index]
     }
 }
+impl RobotJointStateStorage for RobotJointState {
+    fn robot_joint_state_type(&self) -> &RobotJointStateType {
+        &self.robot_joint_state_type
+    }
+    fn len(&self) -> usize {
+        self.joint_state.len()
+    }
+}
+
+/// A robot joint state with a length fixed at compile time, backed by a stack-allocated `SVector`.
+/// For robots with a known number of DOFs (or axes), this can be used in place of a `RobotJointState`
+/// in tight control loops to avoid heap allocation (e.g., with `RobotKinematicsModule::compute_fk_in_place`).
+/// As with `RobotJointState`, a `RobotJointStateN` can only be spawned from a `RobotJointStateModule`,
+/// which checks that N matches the robot's number of DOFs or axes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobotJointStateN<const N: usize> {
+    joint_state: SVector<f64, N>,
+    robot_joint_state_type: RobotJointStateType
+}
+impl<const N: usize> RobotJointStateN<N> {
+    fn new(joint_state: SVector<f64, N>, robot_state_type: RobotJointStateType, robot_state_module: &RobotJointStateModule) -> Result<Self, OptimaError> {
+        let correct_len = match robot_state_type {
+            RobotJointStateType::DOF => { robot_state_module.num_dofs() }
+            RobotJointStateType::Full => { robot_state_module.num_axes() }
+        };
+        if N != correct_len {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("RobotJointStateN::new", N, correct_len, file!(), line!()));
+        }
+
+        Ok(Self {
+            joint_state,
+            robot_joint_state_type: robot_state_type
+        })
+    }
+    pub fn joint_state(&self) -> &SVector<f64, N> {
+        &self.joint_state
+    }
+    pub fn joint_state_mut(&mut self) -> &mut SVector<f64, N> {
+        &mut self.joint_state
+    }
+    pub fn robot_joint_state_type(&self) -> &RobotJointStateType {
+        &self.robot_joint_state_type
+    }
+    pub fn len(&self) -> usize {
+        N
+    }
+    /// Converts to a (heap allocated) `RobotJointState` of the same type.
+    pub fn to_robot_joint_state(&self) -> RobotJointState {
+        return RobotJointState::new_unchecked(DVector::from_column_slice(self.joint_state.as_slice()), self.robot_joint_state_type.clone());
+    }
+}
+impl<const N: usize> Index<usize> for RobotJointStateN<N> {
+    type Output = f64;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        return &self.joint_state[index];
+    }
+}
+impl<const N: usize> IndexMut<usize> for RobotJointStateN<N> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.joint_state[index]
+    }
+}
+impl<const N: usize> RobotJointStateStorage for RobotJointStateN<N> {
+    fn robot_joint_state_type(&self) -> &RobotJointStateType {
+        &self.robot_joint_state_type
+    }
+    fn len(&self) -> usize {
+        N
+    }
+}
+
+/// Common interface over joint state storage types (`RobotJointState` and `RobotJointStateN`) so
+/// that kinematics routines can be generic over how the state is stored.
+pub trait RobotJointStateStorage: Index<usize, Output = f64> {
+    fn robot_joint_state_type(&self) -> &RobotJointStateType;
+    fn len(&self) -> usize;
+}
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RobotJointStateType {
@@ -533,3 +1319,5 @@ pub enum RobotJointStateType {
     Full
 }
 
+
+crate::impl_py_pickle!(RobotJointStateModule, RobotJointStateRemapper);
@@ -5,23 +5,29 @@ use pyo3::*;
 use wasm_bindgen::prelude::*;
 
 use serde::{Serialize, Deserialize};
-use nalgebra::{DMatrix, Vector3};
+use nalgebra::{DMatrix, DVector, Isometry3, Matrix4, Matrix6, Quaternion, Unit, UnitQuaternion, Vector3};
+use optima_core_kinematics::{CoreAxisValue, CoreJointAxis, CoreJointComposition, CoreKinematicsModel, CoreLink};
 use crate::robot_modules::robot_configuration_module::RobotConfigurationModule;
-use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateModule, RobotJointStateType};
+use crate::robot_modules::robot_ik_regularization::IKRegularization;
+use crate::robot_modules::robot_ik_solution_memory::IKSolutionMemory;
+use crate::robot_modules::robot_workspace_bound::RobotWorkspaceBound;
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateModule, RobotJointStateStorage, RobotJointStateType};
 use crate::utils::utils_console::{optima_print, PrintColor, PrintMode};
 use crate::utils::utils_errors::OptimaError;
-use crate::utils::utils_files::optima_path::{load_object_from_json_string, OptimaStemCellPath};
+use crate::utils::utils_generic_data_structures::SharedHandle;
 use crate::utils::utils_nalgebra::conversions::NalgebraConversions;
 use crate::utils::utils_robot::joint::{JointAxisPrimitiveType};
+use crate::utils::utils_robot::robot_ids::{JointId, LinkId};
 use crate::utils::utils_robot::robot_module_utils::RobotNames;
 use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3Pose, OptimaSE3PoseType};
+use crate::utils::utils_se3::pose_error_metric::PoseErrorMetric;
 #[cfg(not(target_arch = "wasm32"))]
 use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3PosePy};
 #[cfg(target_arch = "wasm32")]
 use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3PoseWASM};
 use crate::utils::utils_traits::{SaveAndLoadable, ToAndFromRonString};
 #[cfg(target_arch = "wasm32")]
-use crate::utils::utils_wasm::JsMatrix;
+use crate::utils::utils_wasm::{JsMatrix, JsMatrixObject};
 
 /// The `RobotKinematicsModule` performs operations related to a robot's kinematics.
 /// For instance, one of the main subroutines afforded by this module is forward kinematics which
@@ -92,15 +98,21 @@ use crate::utils::utils_wasm::JsMatrix;
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen, derive(Clone, Debug, Serialize, Deserialize))]
 #[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
 pub struct RobotKinematicsModule {
-    robot_configuration_module: RobotConfigurationModule,
+    robot_configuration_module: SharedHandle<RobotConfigurationModule>,
     robot_joint_state_module: RobotJointStateModule,
     starter_result: RobotFKResult
 }
+
 impl RobotKinematicsModule {
     pub fn new(robot_configuration_module: RobotConfigurationModule) -> Self {
-        let robot_joint_state_module = RobotJointStateModule::new(robot_configuration_module.clone());
+        return Self::new_from_shared(SharedHandle::new(robot_configuration_module));
+    }
+    /// Same as `new`, but holds on to the given configuration handle rather than taking ownership
+    /// of a copy.  The module's `RobotJointStateModule` shares the same handle.
+    pub fn new_from_shared(robot_configuration_module: SharedHandle<RobotConfigurationModule>) -> Self {
+        let robot_joint_state_module = RobotJointStateModule::new_from_shared(robot_configuration_module.clone());
 
-        let mut starter_result = RobotFKResult { link_entries: vec![] };
+        let mut starter_result = RobotFKResult { link_entries: vec![], tcp_frame_entries: vec![] };
         let links = robot_configuration_module.robot_model_module().links();
         for (i, link) in links.iter().enumerate() {
             starter_result.link_entries.push( RobotFKResultLinkEntry {
@@ -109,6 +121,15 @@ impl RobotKinematicsModule {
                 pose: None
             } )
         }
+        let tcp_frame_infos = robot_configuration_module.robot_configuration_info().tcp_frame_infos();
+        for (i, tcp_frame_info) in tcp_frame_infos.iter().enumerate() {
+            starter_result.tcp_frame_entries.push( RobotFKResultTCPFrameEntry {
+                tcp_frame_idx: i,
+                tcp_frame_name: tcp_frame_info.tcp_frame_name().to_string(),
+                link_idx: tcp_frame_info.link_idx(),
+                pose: None
+            } )
+        }
 
         Self {
             robot_configuration_module,
@@ -121,8 +142,39 @@ impl RobotKinematicsModule {
         return Ok(Self::new(robot_configuration_module));
     }
     pub fn compute_fk(&self, joint_state: &RobotJointState, t: &OptimaSE3PoseType) -> Result<RobotFKResult, OptimaError> {
-        let joint_state = self.robot_joint_state_module.convert_joint_state_to_full_state(joint_state)?;
         let mut output = self.starter_result.clone();
+        self.compute_fk_in_place(joint_state, t, &mut output)?;
+        return Ok(output);
+    }
+    /// Same as `compute_fk`, but all poses are expressed in the given frame (see
+    /// `RobotFKResultFrame`).  For example, with `RobotFKResultFrame::Link` on a camera link, every
+    /// pose in the result is the pose of a link or TCP frame as seen from the camera.
+    pub fn compute_fk_in_frame(&self, joint_state: &RobotJointState, t: &OptimaSE3PoseType, frame: &RobotFKResultFrame) -> Result<RobotFKResult, OptimaError> {
+        let output = self.compute_fk(joint_state, t)?;
+        return match frame {
+            RobotFKResultFrame::World => { Ok(output) }
+            RobotFKResultFrame::RobotBase => { output.expressed_relative_to_link(self.robot_configuration_module.robot_model_module().robot_base_link_id()) }
+            RobotFKResultFrame::Link { link_id } => { output.expressed_relative_to_link(*link_id) }
+        }
+    }
+    /// Same as `compute_fk`, but writes into an existing output (e.g., one made with
+    /// `RobotFKResult::new_empty`) and is generic over the joint state storage, so it accepts either a
+    /// `RobotJointState` or a fixed-size `RobotJointStateN`.  DOF states are read directly rather than
+    /// being converted to full states, so when output is reused across calls no heap allocation occurs.
+    pub fn compute_fk_in_place<S: RobotJointStateStorage>(&self, joint_state: &S, t: &OptimaSE3PoseType, output: &mut RobotFKResult) -> Result<(), OptimaError> {
+        let correct_len = match joint_state.robot_joint_state_type() {
+            RobotJointStateType::DOF => { self.robot_joint_state_module.num_dofs() }
+            RobotJointStateType::Full => { self.robot_joint_state_module.num_axes() }
+        };
+        if joint_state.len() != correct_len {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("compute_fk_in_place", joint_state.len(), correct_len, file!(), line!()));
+        }
+        if output.link_entries.len() != self.starter_result.link_entries.len() || output.tcp_frame_entries.len() != self.starter_result.tcp_frame_entries.len() {
+            return Err(OptimaError::new_generic_error_str("The given RobotFKResult was not made for this robot.", file!(), line!()));
+        }
+
+        for e in output.link_entries.iter_mut() { e.pose = None; }
+        for e in output.tcp_frame_entries.iter_mut() { e.pose = None; }
 
         let link_tree_traversal_layers = self.robot_configuration_module.robot_model_module().link_tree_traversal_layers();
 
@@ -131,12 +183,14 @@ impl RobotKinematicsModule {
         for link_tree_traversal_layer in link_tree_traversal_layers {
             for link_idx in link_tree_traversal_layer {
                 if links[*link_idx].present() {
-                    self.compute_fk_on_single_link(&joint_state, *link_idx, t, &mut output)?;
+                    self.compute_fk_on_single_link(joint_state, *link_idx, t, output)?;
                 }
             }
         }
 
-        return Ok(output);
+        self.compute_fk_on_tcp_frames(t, output)?;
+
+        Ok(())
     }
     /// This function computes the forward kinematics for some part of the whole robot configuration.
     /// It provides three primary arguments over the standard `compute_fk` function:
@@ -205,25 +259,28 @@ impl RobotKinematicsModule {
                 }
             }
             Some(end_link_idx) => {
-                let chain = self.robot_configuration_module.robot_model_module().get_link_chain(start_link_idx, end_link_idx)?;
+                let chain = self.robot_configuration_module.robot_model_module().get_link_chain(LinkId::new(start_link_idx), LinkId::new(end_link_idx))?;
                 if chain.is_none() {
                     return Err(OptimaError::new_generic_error_str(&format!("No link chain exists between link {} and link {}", start_link_idx, end_link_idx), file!(), line!()));
                 }
                 let chain = chain.unwrap();
-                for link_idx in chain {
-                    if links[*link_idx].present() {
-                        if output.link_entries[*link_idx].pose.is_some() { continue; }
-                        let predecessor_link_idx_option = links[*link_idx].preceding_link_idx();
+                for link_id in chain {
+                    let link_idx = link_id.idx();
+                    if links[link_idx].present() {
+                        if output.link_entries[link_idx].pose.is_some() { continue; }
+                        let predecessor_link_idx_option = links[link_idx].preceding_link_idx();
                         if predecessor_link_idx_option.is_none() { continue; }
                         let predecessor_link_idx = predecessor_link_idx_option.unwrap();
                         if output.link_entries[predecessor_link_idx].pose.is_some() {
-                            self.compute_fk_on_single_link(&joint_state, *link_idx, t, &mut output)?;
+                            self.compute_fk_on_single_link(&joint_state, link_idx, t, &mut output)?;
                         }
                     }
                 }
             }
         }
 
+        self.compute_fk_on_tcp_frames(t, &mut output)?;
+
         return Ok(output);
     }
     pub fn compute_fk_dof_perturbations(&self, joint_state: &RobotJointState, t: &OptimaSE3PoseType, perturbation: Option<f64>) -> Result<RobotFKDOFPerturbationsResult, OptimaError> {
@@ -254,24 +311,25 @@ impl RobotKinematicsModule {
     }
     pub fn compute_jacobian(&self,
                             joint_state: &RobotJointState,
-                            start_link_idx: Option<usize>,
-                            end_link_idx: usize,
+                            start_link_id: Option<LinkId>,
+                            end_link_id: LinkId,
                             robot_jacobian_end_point: &JacobianEndPoint,
                             start_link_pose: Option<OptimaSE3Pose>,
                             jacobian_mode: JacobianMode) -> Result<DMatrix<f64>, OptimaError> {
-        let start_idx = match start_link_idx {
-            None => { self.robot_configuration_module.robot_model_module().world_link_idx() }
+        let end_link_idx = end_link_id.idx();
+        let start_id = match start_link_id {
+            None => { self.robot_configuration_module.robot_model_module().world_link_id() }
             Some(s) => {s}
         };
-        let chain = self.robot_configuration_module.robot_model_module().get_link_chain(start_idx, end_link_idx)?;
+        let chain = self.robot_configuration_module.robot_model_module().get_link_chain(start_id, end_link_id)?;
         if chain.is_none() {
-            let s = format!("Link chain does not exist between link {} and {}.  Cannot perform jacobian calculation.", start_idx, end_link_idx);
+            let s = format!("Link chain does not exist between link {} and {}.  Cannot perform jacobian calculation.", start_id.idx(), end_link_idx);
             return Err(OptimaError::new_generic_error_str(&s, file!(), line!()));
         }
         let chain = chain.unwrap();
         for c in chain {
-            if !self.robot_configuration_module.robot_model_module().links()[*c].present() {
-                let s = format!("Valid link chain does not exist between link {} and {} because link {} is not present.  Cannot perform jacobian calculation.", start_idx, end_link_idx, *c);
+            if !self.robot_configuration_module.robot_model_module().links()[c.idx()].present() {
+                let s = format!("Valid link chain does not exist between link {} and {} because link {} is not present.  Cannot perform jacobian calculation.", start_id.idx(), end_link_idx, c.idx());
                 return Err(OptimaError::new_generic_error_str(&s, file!(), line!()));
             }
         }
@@ -285,7 +343,7 @@ impl RobotKinematicsModule {
         let mut jacobian = DMatrix::zeros(num_rows, num_dofs);
 
         let floating_link_input = FloatingLinkInput {
-            start_link_idx,
+            start_link_idx: start_link_id.map(|start_link_id| start_link_id.idx()),
             end_link_idx: Some(end_link_idx),
             start_link_pose
         };
@@ -315,10 +373,13 @@ impl RobotKinematicsModule {
                     for joint_state_idx in joint_state_idxs {
                         let pose_as_idq = pose.unwrap_implicit_dual_quaternion()?;
                         let joint_axis = self.robot_joint_state_module.ordered_dof_joint_axes().get(*joint_state_idx).unwrap();
-                        let axis = joint_axis.axis();
+                        let axis = match joint_axis.axis_primitive_type() {
+                            JointAxisPrimitiveType::QuaternionComponent => { self.joint_quaternion_component_angular_velocity(joint_state, joint_idx, joint_axis.joint_sub_dof_idx())? }
+                            _ => { joint_axis.axis() }
+                        };
 
                         match joint_axis.axis_primitive_type() {
-                            JointAxisPrimitiveType::Rotation => {
+                            JointAxisPrimitiveType::Rotation | JointAxisPrimitiveType::QuaternionComponent => {
                                 match jacobian_mode {
                                     JacobianMode::Full => {
                                         let rotated_axis = pose_as_idq.rotation() * &axis;
@@ -368,6 +429,452 @@ impl RobotKinematicsModule {
 
         return Ok(jacobian)
     }
+    /// Propagates a covariance over the DOF joint values (e.g., from encoder noise or calibration
+    /// error) to a 6x6 covariance of the pose of the given link (or point on it), to first order:
+    /// Sigma_pose = J Sigma_q J^T, where J is the full Jacobian.  As with the Jacobian, the first
+    /// three rows and columns are the world frame translation (in m^2) and the last three are the
+    /// world frame small angle rotation vector (in rad^2).
+    pub fn compute_pose_covariance(&self,
+                                   joint_state: &RobotJointState,
+                                   link_id: LinkId,
+                                   robot_jacobian_end_point: &JacobianEndPoint,
+                                   joint_covariance: &DMatrix<f64>) -> Result<Matrix6<f64>, OptimaError> {
+        let num_dofs = self.robot_joint_state_module.num_dofs();
+        if joint_covariance.nrows() != num_dofs || joint_covariance.ncols() != num_dofs {
+            return Err(OptimaError::new_generic_error_str(&format!("Joint covariance must be {} x {} (given {} x {}).", num_dofs, num_dofs, joint_covariance.nrows(), joint_covariance.ncols()), file!(), line!()));
+        }
+
+        let jacobian = self.compute_jacobian(joint_state, None, link_id, robot_jacobian_end_point, None, JacobianMode::Full)?;
+        let covariance = &jacobian * joint_covariance * jacobian.transpose();
+
+        let mut out = Matrix6::zeros();
+        for row in 0..6 {
+            for col in 0..6 {
+                // Averaged with the transpose to remove floating point asymmetry.
+                out[(row, col)] = 0.5 * (covariance[(row, col)] + covariance[(col, row)]);
+            }
+        }
+        Ok(out)
+    }
+    /// Same as `compute_pose_covariance`, but for independent joint errors with the given standard
+    /// deviations (one per DOF).
+    pub fn compute_pose_covariance_from_joint_std_devs(&self,
+                                                       joint_state: &RobotJointState,
+                                                       link_id: LinkId,
+                                                       robot_jacobian_end_point: &JacobianEndPoint,
+                                                       joint_std_devs: &DVector<f64>) -> Result<Matrix6<f64>, OptimaError> {
+        let joint_covariance = DMatrix::from_diagonal(&joint_std_devs.map(|s| s * s));
+        return self.compute_pose_covariance(joint_state, link_id, robot_jacobian_end_point, &joint_covariance);
+    }
+    /// Estimates the reachable workspace of the given link by sampling forward kinematics over
+    /// num_samples random joint states.  See `RobotWorkspaceBound`.
+    pub fn estimate_workspace_bound(&self, link_id: LinkId, num_samples: usize, voxel_size: f64) -> Result<RobotWorkspaceBound, OptimaError> {
+        return RobotWorkspaceBound::new(self, link_id, num_samples, voxel_size);
+    }
+    /// Returns the second order kinematics of the link, i.e., the derivatives of the Jacobian
+    /// (`compute_jacobian` with the same arguments) with respect to the DOF joint variables.  The
+    /// j-th matrix of the output is the derivative of the Jacobian with respect to the j-th DOF, so
+    /// entry (r, k) of the j-th matrix is the derivative of Jacobian entry (r, k) with respect to
+    /// DOF j.
+    ///
+    /// Computed by central differences of the analytic Jacobian, which is accurate to
+    /// O(perturbation^2) (perturbation defaults to 0.00001).  Note that a
+    /// `JacobianEndPoint::Global` end point stays fixed in the world under the perturbations, so use
+    /// `JacobianEndPoint::Local` for a point that moves with the link.
+    pub fn compute_jacobian_hessian(&self,
+                                    joint_state: &RobotJointState,
+                                    start_link_id: Option<LinkId>,
+                                    end_link_id: LinkId,
+                                    robot_jacobian_end_point: &JacobianEndPoint,
+                                    start_link_pose: Option<OptimaSE3Pose>,
+                                    jacobian_mode: JacobianMode,
+                                    perturbation: Option<f64>) -> Result<Vec<DMatrix<f64>>, OptimaError> {
+        let perturbation = Self::jacobian_finite_difference_perturbation(perturbation)?;
+
+        let dof_joint_state = self.robot_joint_state_module.convert_joint_state_to_dof_state(joint_state)?;
+
+        let mut out_vec = vec![];
+        let len = dof_joint_state.joint_state().len();
+        for i in 0..len {
+            let mut plus = dof_joint_state.clone();
+            plus[i] += perturbation;
+            let mut minus = dof_joint_state.clone();
+            minus[i] -= perturbation;
+
+            let jacobian_plus = self.compute_jacobian(&plus, start_link_id, end_link_id, robot_jacobian_end_point, start_link_pose.clone(), jacobian_mode.clone())?;
+            let jacobian_minus = self.compute_jacobian(&minus, start_link_id, end_link_id, robot_jacobian_end_point, start_link_pose.clone(), jacobian_mode.clone())?;
+            out_vec.push((jacobian_plus - jacobian_minus) / (2.0 * perturbation));
+        }
+
+        Ok(out_vec)
+    }
+    /// Returns the time derivative of the Jacobian (see `compute_jacobian`) when the joints move
+    /// with the given DOF joint velocities, i.e., the sum over j of the j-th Hessian matrix times the
+    /// j-th joint velocity.  The task space acceleration is then J * qdd + Jdot * qd, which is what
+    /// acceleration-level controllers need.
+    ///
+    /// This only takes two Jacobian evaluations (a central difference along the joint velocity
+    /// direction), so it is much cheaper than forming the full Hessian.
+    pub fn compute_jacobian_time_derivative(&self,
+                                            joint_state: &RobotJointState,
+                                            joint_velocities: &DVector<f64>,
+                                            start_link_id: Option<LinkId>,
+                                            end_link_id: LinkId,
+                                            robot_jacobian_end_point: &JacobianEndPoint,
+                                            start_link_pose: Option<OptimaSE3Pose>,
+                                            jacobian_mode: JacobianMode,
+                                            perturbation: Option<f64>) -> Result<DMatrix<f64>, OptimaError> {
+        let perturbation = Self::jacobian_finite_difference_perturbation(perturbation)?;
+
+        let dof_joint_state = self.robot_joint_state_module.convert_joint_state_to_dof_state(joint_state)?;
+        if joint_velocities.len() != dof_joint_state.len() {
+            return Err(OptimaError::new_generic_error_str(&format!("Joint velocities have length {}, but the robot has {} DOFs.", joint_velocities.len(), dof_joint_state.len()), file!(), line!()));
+        }
+
+        let plus = self.robot_joint_state_module.spawn_robot_joint_state(dof_joint_state.joint_state() + joint_velocities * perturbation, RobotJointStateType::DOF)?;
+        let minus = self.robot_joint_state_module.spawn_robot_joint_state(dof_joint_state.joint_state() - joint_velocities * perturbation, RobotJointStateType::DOF)?;
+
+        let jacobian_plus = self.compute_jacobian(&plus, start_link_id, end_link_id, robot_jacobian_end_point, start_link_pose.clone(), jacobian_mode.clone())?;
+        let jacobian_minus = self.compute_jacobian(&minus, start_link_id, end_link_id, robot_jacobian_end_point, start_link_pose, jacobian_mode)?;
+
+        Ok((jacobian_plus - jacobian_minus) / (2.0 * perturbation))
+    }
+    fn jacobian_finite_difference_perturbation(perturbation: Option<f64>) -> Result<f64, OptimaError> {
+        return match perturbation {
+            None => { Ok(0.00001) }
+            Some(p) => {
+                if !(p > 0.0) || !p.is_finite() {
+                    return Err(OptimaError::new_generic_error_str(&format!("The perturbation must be positive and finite (given {}).", p), file!(), line!()));
+                }
+                Ok(p)
+            }
+        }
+    }
+    /// Resolved-rate inverse velocity kinematics.  Maps a desired end effector twist (expressed in the
+    /// world frame, ordered \[linear; angular\] for `JacobianMode::Full`, or just the linear or
+    /// angular part for the other modes) to DOF joint velocities using the damped least squares
+    /// solution J^T (J J^T + damping^2 I)^-1 twist.  The damping keeps joint velocities bounded near
+    /// singularities.
+    ///
+    /// If any joint velocity exceeds its limit, all joint velocities are scaled down uniformly so that
+    /// the direction of the resulting end effector motion is preserved.  If joint_velocity_limits is
+    /// None, the velocity limits from the robot's URDF are used.  This function only requires a single
+    /// Jacobian computation and a small linear solve, so it is suitable for teleoperation loops running
+    /// at hundreds of Hz.
+    pub fn compute_resolved_rate_joint_velocities(&self,
+                                                  joint_state: &RobotJointState,
+                                                  end_link_id: LinkId,
+                                                  robot_jacobian_end_point: &JacobianEndPoint,
+                                                  jacobian_mode: JacobianMode,
+                                                  desired_twist: &DVector<f64>,
+                                                  damping: f64,
+                                                  joint_velocity_limits: Option<&Vec<f64>>) -> Result<ResolvedRateOutput, OptimaError> {
+        let jacobian = self.compute_jacobian(joint_state, None, end_link_id, robot_jacobian_end_point, None, jacobian_mode)?;
+        if desired_twist.len() != jacobian.nrows() {
+            return Err(OptimaError::new_generic_error_str(&format!("Desired twist has length {}, but should have length {} for the given jacobian mode.", desired_twist.len(), jacobian.nrows()), file!(), line!()));
+        }
+
+        let jacobian_t = jacobian.transpose();
+        let jjt = &jacobian * &jacobian_t + DMatrix::identity(jacobian.nrows(), jacobian.nrows()) * (damping * damping);
+        let solved = jjt.lu().solve(desired_twist);
+        let solved = match solved {
+            None => { return Err(OptimaError::new_generic_error_str("Could not solve resolved-rate system (singular matrix).  Try increasing the damping.", file!(), line!())); }
+            Some(solved) => { solved }
+        };
+        let mut joint_velocities = jacobian_t * solved;
+
+        let urdf_velocity_limits;
+        let joint_velocity_limits = match joint_velocity_limits {
+            None => {
+                urdf_velocity_limits = self.robot_joint_state_module.get_joint_state_velocity_limits(&RobotJointStateType::DOF);
+                &urdf_velocity_limits
+            }
+            Some(joint_velocity_limits) => { joint_velocity_limits }
+        };
+        if joint_velocity_limits.len() != joint_velocities.len() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("compute_resolved_rate_joint_velocities", joint_velocity_limits.len(), joint_velocities.len(), file!(), line!()));
+        }
+
+        let mut scale_factor: f64 = 1.0;
+        for (i, v) in joint_velocities.iter().enumerate() {
+            if v.abs() > joint_velocity_limits[i] {
+                scale_factor = scale_factor.min(joint_velocity_limits[i] / v.abs());
+            }
+        }
+        joint_velocities *= scale_factor;
+
+        Ok(ResolvedRateOutput {
+            joint_velocities,
+            scale_factor
+        })
+    }
+    /// Iterative damped least squares inverse kinematics.  Starting from initial_joint_state, takes
+    /// resolved-rate steps (see `compute_resolved_rate_joint_velocities`) toward target_pose for the
+    /// given link, clamping to the joint bounds after each step, until the position error (and, for
+    /// `JacobianMode::Full`, the rotation error in radians) is below tolerance or max_iterations is
+    /// reached.  With `JacobianMode::Translational`, only the link position is matched.  This is a
+    /// local method: it returns the closest solution it finds (with converged set to false) rather than
+    /// an error when the target is not reached.
+    pub fn compute_ik_damped_least_squares(&self,
+                                           initial_joint_state: &RobotJointState,
+                                           end_link_id: LinkId,
+                                           target_pose: &OptimaSE3Pose,
+                                           jacobian_mode: JacobianMode,
+                                           max_iterations: usize,
+                                           tolerance: f64,
+                                           damping: f64) -> Result<InverseKinematicsOutput, OptimaError> {
+        let pose_error_metric = match jacobian_mode {
+            JacobianMode::Full => { PoseErrorMetric::default() }
+            JacobianMode::Translational => { PoseErrorMetric::new_position_only() }
+            JacobianMode::Rotational => {
+                return Err(OptimaError::new_unsupported_operation_error("compute_ik_damped_least_squares", "Rotational jacobian mode is not supported for inverse kinematics.  Use Full or Translational.", file!(), line!()));
+            }
+        };
+        return self.compute_ik_damped_least_squares_with_metric(initial_joint_state, end_link_id, target_pose, &pose_error_metric, max_iterations, tolerance, tolerance, damping);
+    }
+    /// Same as `compute_ik_damped_least_squares`, but the error being driven to zero is given by a
+    /// `PoseErrorMetric`.  Each step solves the damped least squares problem on the weighted error
+    /// W e with the weighted Jacobian W J (see `PoseErrorMetric::weight_matrix`), so axes masked out
+    /// by the metric are left free.  Converges once the metric's masked position and orientation
+    /// errors are within their tolerances.  The errors in the output are the metric's masked,
+    /// unweighted errors.
+    pub fn compute_ik_damped_least_squares_with_metric(&self,
+                                                       initial_joint_state: &RobotJointState,
+                                                       end_link_id: LinkId,
+                                                       target_pose: &OptimaSE3Pose,
+                                                       pose_error_metric: &PoseErrorMetric,
+                                                       max_iterations: usize,
+                                                       position_tolerance: f64,
+                                                       orientation_tolerance: f64,
+                                                       damping: f64) -> Result<InverseKinematicsOutput, OptimaError> {
+        return self.compute_ik_damped_least_squares_on_link_offset(initial_joint_state, end_link_id, None, target_pose, pose_error_metric, max_iterations, position_tolerance, orientation_tolerance, damping, None, None);
+    }
+    /// Same as `compute_ik_damped_least_squares_with_metric`, but the target is for a named frame (see
+    /// `resolve_frame`), e.g., a TCP frame such as a tool tip or camera frame, rather than a link.
+    pub fn compute_ik_damped_least_squares_on_frame(&self,
+                                                    initial_joint_state: &RobotJointState,
+                                                    frame_name: &str,
+                                                    target_pose: &OptimaSE3Pose,
+                                                    pose_error_metric: &PoseErrorMetric,
+                                                    max_iterations: usize,
+                                                    position_tolerance: f64,
+                                                    orientation_tolerance: f64,
+                                                    damping: f64) -> Result<InverseKinematicsOutput, OptimaError> {
+        let (link_id, offset) = self.resolve_frame(frame_name)?;
+        return self.compute_ik_damped_least_squares_on_link_offset(initial_joint_state, link_id, offset.as_ref(), target_pose, pose_error_metric, max_iterations, position_tolerance, orientation_tolerance, damping, None, None);
+    }
+    /// Same as `compute_ik_damped_least_squares_on_frame`, but joint steps are weighted by the
+    /// configuration's joint weighting profile with the given name (see `JointWeightingProfile`).
+    /// Each step is W^-1 J^T (J W^-1 J^T + damping^2 I)^-1 e, i.e., the damped least squares step
+    /// minimizing the W-weighted norm of the step, so axes with higher weights move less.
+    pub fn compute_ik_damped_least_squares_on_frame_with_joint_weighting_profile(&self,
+                                                                                 initial_joint_state: &RobotJointState,
+                                                                                 frame_name: &str,
+                                                                                 target_pose: &OptimaSE3Pose,
+                                                                                 pose_error_metric: &PoseErrorMetric,
+                                                                                 joint_weighting_profile_name: &str,
+                                                                                 max_iterations: usize,
+                                                                                 position_tolerance: f64,
+                                                                                 orientation_tolerance: f64,
+                                                                                 damping: f64) -> Result<InverseKinematicsOutput, OptimaError> {
+        let (link_id, offset) = self.resolve_frame(frame_name)?;
+        let joint_weights = self.robot_joint_state_module.get_joint_state_weights(joint_weighting_profile_name, &RobotJointStateType::DOF)?;
+        return self.compute_ik_damped_least_squares_on_link_offset(initial_joint_state, link_id, offset.as_ref(), target_pose, pose_error_metric, max_iterations, position_tolerance, orientation_tolerance, damping, Some(&joint_weights), None);
+    }
+    /// Same as `compute_ik_damped_least_squares_on_frame`, but with secondary objectives (see
+    /// `IKRegularization`), e.g., joint limit avoidance or a posture preference, acting in the null
+    /// space of the frame's Jacobian.  Once the pose has converged, iterations continue until the
+    /// regularization settles.
+    pub fn compute_ik_damped_least_squares_on_frame_with_regularization(&self,
+                                                                        initial_joint_state: &RobotJointState,
+                                                                        frame_name: &str,
+                                                                        target_pose: &OptimaSE3Pose,
+                                                                        pose_error_metric: &PoseErrorMetric,
+                                                                        regularization: &IKRegularization,
+                                                                        max_iterations: usize,
+                                                                        position_tolerance: f64,
+                                                                        orientation_tolerance: f64,
+                                                                        damping: f64) -> Result<InverseKinematicsOutput, OptimaError> {
+        let (link_id, offset) = self.resolve_frame(frame_name)?;
+        return self.compute_ik_damped_least_squares_on_link_offset(initial_joint_state, link_id, offset.as_ref(), target_pose, pose_error_metric, max_iterations, position_tolerance, orientation_tolerance, damping, None, Some(regularization));
+    }
+    /// Resolves a frame name to the link it is attached to and its fixed offset from that link (None
+    /// for the link's own frame).  TCP frames (see `RobotConfigurationModule::set_tcp_frame`) are
+    /// checked first, followed by link names, so auxiliary frames such as a camera optical frame or
+    /// tool flange can be used as query targets without adding fixed joints to the URDF.
+    pub fn resolve_frame(&self, frame_name: &str) -> Result<(LinkId, Option<OptimaSE3Pose>), OptimaError> {
+        for tcp_frame_info in self.robot_configuration_module.robot_configuration_info().tcp_frame_infos() {
+            if tcp_frame_info.tcp_frame_name() == frame_name {
+                return Ok((LinkId::new(tcp_frame_info.link_idx()), Some(tcp_frame_info.offset().get_pose_by_type(&OptimaSE3PoseType::ImplicitDualQuaternion).clone())));
+            }
+        }
+        return match self.robot_configuration_module.robot_model_module().get_link_id_from_name(frame_name) {
+            None => { Err(OptimaError::new_generic_error_str(&format!("Robot {} has no TCP frame or link named {}.", self.robot_name(), frame_name), file!(), line!())) }
+            Some(link_id) => { Ok((link_id, None)) }
+        };
+    }
+    /// Returns the pose of the named frame (see `resolve_frame`) in the world frame.
+    pub fn compute_frame_pose(&self, joint_state: &RobotJointState, frame_name: &str, t: &OptimaSE3PoseType) -> Result<OptimaSE3Pose, OptimaError> {
+        let (link_id, offset) = self.resolve_frame(frame_name)?;
+        let fk_res = self.compute_fk(joint_state, t)?;
+        let link_pose = match fk_res.link_pose(link_id) {
+            None => { return Err(OptimaError::new_generic_error_str(&format!("Link {} (of frame {}) is not present in the robot configuration.", link_id.idx(), frame_name), file!(), line!())); }
+            Some(link_pose) => { link_pose }
+        };
+        return match offset {
+            None => { Ok(link_pose.clone()) }
+            Some(offset) => { link_pose.multiply(&offset, true) }
+        };
+    }
+    /// Returns the Jacobian of the named frame (see `resolve_frame`), i.e., the link Jacobian with its
+    /// end point at the frame's origin.
+    pub fn compute_frame_jacobian(&self, joint_state: &RobotJointState, frame_name: &str, jacobian_mode: JacobianMode) -> Result<DMatrix<f64>, OptimaError> {
+        let (link_id, offset) = self.resolve_frame(frame_name)?;
+        let end_point = match offset {
+            None => { JacobianEndPoint::Link }
+            Some(offset) => { JacobianEndPoint::Local(offset.to_nalgebra_isometry().translation.vector) }
+        };
+        return self.compute_jacobian(joint_state, None, link_id, &end_point, None, jacobian_mode);
+    }
+    /// Damped least squares inverse kinematics for the frame at the given offset from end_link_id (or
+    /// the link itself if the offset is None).  If joint_weights is given, steps are weighted per DOF
+    /// (see `compute_ik_damped_least_squares_on_frame_with_joint_weighting_profile`).  If regularization
+    /// is given, its step is projected onto the null space of the (weighted) Jacobian and added to
+    /// every step (see `IKRegularization`).
+    fn compute_ik_damped_least_squares_on_link_offset(&self,
+                                                      initial_joint_state: &RobotJointState,
+                                                      end_link_id: LinkId,
+                                                      offset: Option<&OptimaSE3Pose>,
+                                                      target_pose: &OptimaSE3Pose,
+                                                      pose_error_metric: &PoseErrorMetric,
+                                                      max_iterations: usize,
+                                                      position_tolerance: f64,
+                                                      orientation_tolerance: f64,
+                                                      damping: f64,
+                                                      joint_weights: Option<&Vec<f64>>,
+                                                      regularization: Option<&IKRegularization>) -> Result<InverseKinematicsOutput, OptimaError> {
+        let end_link_idx = end_link_id.idx();
+        let num_links = self.robot_configuration_module.robot_model_module().links().len();
+        OptimaError::new_check_for_idx_out_of_bound_error(end_link_idx, num_links, file!(), line!())?;
+
+        let offset = offset.map(|o| o.to_nalgebra_isometry());
+        let jacobian_end_point = match &offset {
+            None => { JacobianEndPoint::Link }
+            Some(offset) => { JacobianEndPoint::Local(offset.translation.vector) }
+        };
+        let target = target_pose.to_nalgebra_isometry();
+        let weight_matrix = DMatrix::from_column_slice(6, 6, pose_error_metric.weight_matrix(target_pose).as_slice());
+        let bounds = self.robot_joint_state_module.get_joint_state_bounds(&RobotJointStateType::DOF);
+
+        let mut joint_state = self.robot_joint_state_module.convert_joint_state_to_dof_state(initial_joint_state)?;
+        let mut iterations = 0;
+        loop {
+            let fk_res = self.compute_fk(&joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+            let current = match &fk_res.link_entries[end_link_idx].pose {
+                None => { return Err(OptimaError::new_generic_error_str(&format!("Link {} is not present in the robot configuration.", end_link_idx), file!(), line!())); }
+                Some(pose) => {
+                    match &offset {
+                        None => { pose.to_nalgebra_isometry() }
+                        Some(offset) => { pose.to_nalgebra_isometry() * offset }
+                    }
+                }
+            };
+
+            let error = pose_error_metric.error_vector_from_isometries(&current, &target);
+            let position_error = error.fixed_rows::<3>(0).norm();
+            let rotation_error = error.fixed_rows::<3>(3).norm();
+            let converged = (!pose_error_metric.constrains_position() || position_error <= position_tolerance) &&
+                (!pose_error_metric.constrains_orientation() || rotation_error <= orientation_tolerance);
+
+            if (converged && regularization.is_none()) || iterations >= max_iterations {
+                return Ok(InverseKinematicsOutput {
+                    joint_state,
+                    converged,
+                    position_error,
+                    rotation_error,
+                    iterations
+                });
+            }
+
+            // The error is already masked, so only the weights remain to be applied.
+            let mut weighted_error = DVector::from_column_slice(error.as_slice());
+            for i in 0..3 { weighted_error[i] *= pose_error_metric.position_weight(); }
+            for i in 3..6 { weighted_error[i] *= pose_error_metric.orientation_weight(); }
+
+            let jacobian = &weight_matrix * self.compute_jacobian(&joint_state, None, end_link_id, &jacobian_end_point, None, JacobianMode::Full)?;
+            let mut jacobian_t = jacobian.transpose();
+            if let Some(joint_weights) = joint_weights {
+                // W^-1 J^T
+                for (i, w) in joint_weights.iter().enumerate() {
+                    let mut row = jacobian_t.row_mut(i);
+                    row /= *w;
+                }
+            }
+            let jjt = (&jacobian * &jacobian_t + DMatrix::identity(6, 6) * (damping * damping)).lu();
+            let solved = match jjt.solve(&weighted_error) {
+                None => { return Err(OptimaError::new_generic_error_str("Could not solve damped least squares system (singular matrix).  Try increasing the damping.", file!(), line!())); }
+                Some(solved) => { solved }
+            };
+            let mut step = &jacobian_t * solved;
+
+            if let Some(regularization) = regularization {
+                // N z = z - W^-1 J^T (J W^-1 J^T + damping^2 I)^-1 J z
+                let z = regularization.joint_space_step(&self.robot_joint_state_module, &joint_state)?;
+                let solved = match jjt.solve(&(&jacobian * &z)) {
+                    None => { return Err(OptimaError::new_generic_error_str("Could not solve null space projection system (singular matrix).  Try increasing the damping.", file!(), line!())); }
+                    Some(solved) => { solved }
+                };
+                let projected = z - &jacobian_t * solved;
+                if converged && projected.norm() <= regularization.settle_tolerance() {
+                    return Ok(InverseKinematicsOutput {
+                        joint_state,
+                        converged,
+                        position_error,
+                        rotation_error,
+                        iterations
+                    });
+                }
+                step += projected;
+            }
+
+            let mut new_state = joint_state.joint_state() + step;
+            for (i, (lower, upper)) in bounds.iter().enumerate() {
+                new_state[i] = new_state[i].max(*lower).min(*upper);
+            }
+            joint_state = self.robot_joint_state_module.spawn_robot_joint_state(new_state, RobotJointStateType::DOF)?;
+            iterations += 1;
+        }
+    }
+    /// Same as `compute_ik_damped_least_squares_with_metric`, but the solve is seeded from the
+    /// solution stored in memory for the nearest previously solved pose.  If memory is empty, or the
+    /// seeded solve does not converge, the solve is (re)run from fallback_initial_joint_state.
+    /// Converged solutions are added to memory.  The solve is for the memory's end link.
+    pub fn compute_ik_damped_least_squares_with_memory(&self,
+                                                       memory: &mut IKSolutionMemory,
+                                                       fallback_initial_joint_state: &RobotJointState,
+                                                       target_pose: &OptimaSE3Pose,
+                                                       pose_error_metric: &PoseErrorMetric,
+                                                       max_iterations: usize,
+                                                       position_tolerance: f64,
+                                                       orientation_tolerance: f64,
+                                                       damping: f64) -> Result<InverseKinematicsOutput, OptimaError> {
+        let end_link_id = memory.end_link_id();
+
+        let mut res = None;
+        if let Some((seed, _)) = memory.nearest_solution(target_pose) {
+            let seed = self.robot_joint_state_module.spawn_robot_joint_state(seed.clone(), RobotJointStateType::DOF)?;
+            let seeded_res = self.compute_ik_damped_least_squares_with_metric(&seed, end_link_id, target_pose, pose_error_metric, max_iterations, position_tolerance, orientation_tolerance, damping)?;
+            if seeded_res.converged { res = Some(seeded_res); }
+        }
+        let res = match res {
+            None => { self.compute_ik_damped_least_squares_with_metric(fallback_initial_joint_state, end_link_id, target_pose, pose_error_metric, max_iterations, position_tolerance, orientation_tolerance, damping)? }
+            Some(res) => { res }
+        };
+
+        if res.converged { memory.insert(target_pose, &res.joint_state)?; }
+
+        Ok(res)
+    }
     pub fn compute_reverse_fk(&self, input: &RobotFKResult) -> Result<RobotJointState, OptimaError> {
         let mut out_joint_state = self.robot_joint_state_module.spawn_zeros_robot_joint_state(RobotJointStateType::Full);
 
@@ -388,8 +895,75 @@ impl RobotKinematicsModule {
     pub fn robot_name(&self) -> &str {
         return self.robot_configuration_module.robot_model_module().robot_name()
     }
-    fn compute_fk_on_single_link(&self, joint_state: &RobotJointState, link_idx: usize, t: &OptimaSE3PoseType, output: &mut RobotFKResult) -> Result<(), OptimaError> {
-        let link = self.robot_configuration_module.robot_model_module().get_link_by_idx(link_idx)?;
+    /// Builds a `CoreKinematicsModel` that computes the same link poses as this module (except TCP
+    /// frames), to be serialized and evaluated on a `no_std` target (see the `optima_core_kinematics`
+    /// crate).
+    pub fn spawn_core_kinematics_model(&self) -> Result<CoreKinematicsModel, OptimaError> {
+        let robot_model_module = self.robot_configuration_module.robot_model_module();
+        let links = robot_model_module.links();
+        let joints = robot_model_module.joints();
+        let t = OptimaSE3PoseType::ImplicitDualQuaternion;
+
+        let mut core_links = vec![];
+        for link in links {
+            let core_link = match (link.preceding_link_idx(), link.preceding_joint_idx()) {
+                (None, _) => {
+                    let base_offset = self.robot_configuration_module.robot_configuration_info().base_offset().get_pose_by_type(&t).to_nalgebra_isometry();
+                    CoreLink::new(None, base_offset, vec![], CoreJointComposition::EulerAngles)
+                }
+                (Some(preceding_link_idx), None) => {
+                    CoreLink::new(Some(preceding_link_idx), Isometry3::identity(), vec![], CoreJointComposition::EulerAngles)
+                }
+                (Some(preceding_link_idx), Some(preceding_joint_idx)) => {
+                    let joint = &joints[preceding_joint_idx];
+                    let full_state_idxs = self.robot_joint_state_module.map_joint_idx_to_joint_state_idxs(preceding_joint_idx, &RobotJointStateType::Full)?;
+                    let mut joint_axes = vec![];
+                    for (i, full_state_idx) in full_state_idxs.iter().enumerate() {
+                        let joint_axis = &joint.joint_axes()[i];
+                        joint_axes.push(CoreJointAxis::new(*full_state_idx, joint_axis.axis_as_unit(), joint_axis.core_joint_axis_type()));
+                    }
+                    let origin_offset = joint.origin_offset_pose().get_pose_by_type(&t).to_nalgebra_isometry();
+                    CoreLink::new(Some(preceding_link_idx), origin_offset, joint_axes, joint.core_joint_composition())
+                }
+            };
+            core_links.push(core_link);
+        }
+
+        let mut traversal_order = vec![];
+        for layer in robot_model_module.link_tree_traversal_layers() {
+            for link_idx in layer {
+                if links[*link_idx].present() { traversal_order.push(*link_idx); }
+            }
+        }
+
+        let mut full_state_axis_values = vec![];
+        let mut dof_idx = 0;
+        for joint_axis in self.robot_joint_state_module.ordered_joint_axes() {
+            if let Some(coupling) = joint_axis.coupling() {
+                let mut terms = vec![];
+                for term in &coupling.terms {
+                    let term_full_state_idx = self.robot_joint_state_module.map_joint_idx_and_sub_dof_idx_to_joint_state_idx(term.joint_idx, term.joint_sub_idx, &RobotJointStateType::Full)?;
+                    terms.push((term_full_state_idx, term.coefficient));
+                }
+                full_state_axis_values.push(CoreAxisValue::Coupled { offset: coupling.offset, terms });
+                continue;
+            }
+            match joint_axis.fixed_value() {
+                None => {
+                    full_state_axis_values.push(CoreAxisValue::Dof(dof_idx));
+                    dof_idx += 1;
+                }
+                Some(fixed_value) => { full_state_axis_values.push(CoreAxisValue::Fixed(fixed_value)); }
+            }
+        }
+
+        return match CoreKinematicsModel::new(core_links, traversal_order, full_state_axis_values) {
+            Ok(model) => { Ok(model) }
+            Err(e) => { Err(OptimaError::new_generic_error_str(&format!("Could not build core kinematics model: {:?}", e), file!(), line!())) }
+        }
+    }
+    fn compute_fk_on_single_link<S: RobotJointStateStorage>(&self, joint_state: &S, link_idx: usize, t: &OptimaSE3PoseType, output: &mut RobotFKResult) -> Result<(), OptimaError> {
+        let link = self.robot_configuration_module.robot_model_module().link(LinkId::new(link_idx))?;
 
         let preceding_link_option = link.preceding_link_idx();
         if preceding_link_option.is_none() {
@@ -407,9 +981,10 @@ impl RobotKinematicsModule {
 
         let preceding_joint_idx = preceding_joint_option.unwrap();
         let preceding_joint = &self.robot_configuration_module.robot_model_module().joints()[preceding_joint_idx];
-        let is_joint_with_all_standard_axes = preceding_joint.is_joint_with_all_standard_axes();
 
         let full_state_idxs = self.robot_joint_state_module.map_joint_idx_to_joint_state_idxs(preceding_joint_idx, &RobotJointStateType::Full)?;
+        let dof_state_idxs = self.robot_joint_state_module.map_joint_idx_to_joint_state_idxs(preceding_joint_idx, &RobotJointStateType::DOF)?;
+        let mut dof_bookmark = 0;
 
         let out_pose = output.link_entries[preceding_link_idx].pose.clone();
         if out_pose.is_none() { return Ok(()); }
@@ -418,65 +993,73 @@ impl RobotKinematicsModule {
         let offset_pose_all = preceding_joint.origin_offset_pose();
         out_pose = out_pose.multiply(offset_pose_all.get_pose_by_type(t), false)?;
 
-        let joint_axes = preceding_joint.joint_axes();
-
-        // On a chain base link or a joint with all standard axes, we can just use Euler angles.
-        // On other links, some axes will not be [1,0,0], [0,1,0], or [0,0,1],
-        // so we must do a sequence of multiplications on axis angle representations.
-        if is_joint_with_all_standard_axes {
-            if full_state_idxs.len() > 0 {
-                let mut tt = vec![0., 0., 0.];
-                let mut rr = vec![0., 0., 0.];
-
-                for (i, full_state_idx) in full_state_idxs.iter().enumerate() {
-                    let joint_axis = &joint_axes[i];
-                    let joint_value = joint_state[*full_state_idx];
-
-                    let axis = joint_axis.axis();
-                    match joint_axis.axis_primitive_type() {
-                        JointAxisPrimitiveType::Rotation => {
-                            if axis[0] == 1.0 { rr[0] = joint_value }
-                            else if axis[1] == 1.0 { rr[1] = joint_value }
-                            else if axis[2] == 1.0 { rr[2] = joint_value }
-                            else if axis[0] == -1.0 { rr[0] = -joint_value }
-                            else if axis[1] == -1.0 { rr[1] = -joint_value }
-                            else if axis[2] == -1.0 { rr[2] = -joint_value }
-                        }
-                        JointAxisPrimitiveType::Translation => {
-                            if axis[0] == 1.0 { tt[0] = joint_value }
-                            else if axis[1] == 1.0 { tt[1] = joint_value }
-                            else if axis[2] == 1.0 { tt[2] = joint_value }
-                            else if axis[0] == -1.0 { tt[0] = -joint_value }
-                            else if axis[1] == -1.0 { tt[1] = -joint_value }
-                            else if axis[2] == -1.0 { tt[2] = -joint_value }
-                        }
-                    }
-                }
-
-                let euler_pose = OptimaSE3Pose::new_from_euler_angles(rr[0], rr[1], rr[2], tt[0], tt[1], tt[2], t);
-                out_pose = out_pose.multiply(&euler_pose, false)?;
-            }
-        } else {
-            for (i, full_state_idx) in full_state_idxs.iter().enumerate() {
+        // The joint transform is computed by the same kernel as `CoreKinematicsModel::compute_fk`.
+        if !full_state_idxs.is_empty() {
+            let joint_axes = preceding_joint.joint_axes();
+            let joint_axis_values = full_state_idxs.iter().enumerate().map(|(i, full_state_idx)| {
                 let joint_axis = &joint_axes[i];
-                let joint_value = joint_state[*full_state_idx];
+                let joint_value = self.get_joint_value_on_full_state_idx(joint_state, *full_state_idx, dof_state_idxs, &mut dof_bookmark);
+                (joint_axis.core_joint_axis_type(), joint_axis.axis_as_unit(), joint_value)
+            });
+            let joint_transform = match preceding_joint.core_joint_composition().joint_transform(joint_axis_values) {
+                Ok(joint_transform) => { joint_transform }
+                Err(e) => { return Err(OptimaError::new_generic_error_str(&format!("Could not compute the transform of joint {}: {:?}", preceding_joint_idx, e), file!(), line!())); }
+            };
+            out_pose = out_pose.multiply(&OptimaSE3Pose::new_from_nalgebra_isometry(&joint_transform, t), false)?;
+        }
 
-                let axis_pose = match joint_axis.axis_primitive_type() {
-                    JointAxisPrimitiveType::Rotation => {
-                        let axis = &joint_axis.axis_as_unit();
-                        OptimaSE3Pose::new_from_axis_angle(axis, joint_value, 0.,0.,0., t)
-                    }
-                    JointAxisPrimitiveType::Translation => {
-                        let axis = joint_value * &joint_axis.axis();
-                        OptimaSE3Pose::new_from_euler_angles(0.,0.,0., axis[0], axis[1], axis[2], t)
-                    }
-                };
+        output.link_entries[link_idx].pose = Some(out_pose);
 
-                out_pose = out_pose.multiply(&axis_pose, false)?;
+        Ok(())
+    }
+    /// Returns the value of the given full state axis from a DOF or Full joint state.  For DOF states,
+    /// dof_state_idxs are the DOF indices of the current joint and dof_bookmark tracks how many of them
+    /// have been consumed (axes must be visited in order).
+    fn get_joint_value_on_full_state_idx<S: RobotJointStateStorage>(&self, joint_state: &S, full_state_idx: usize, dof_state_idxs: &Vec<usize>, dof_bookmark: &mut usize) -> f64 {
+        return match joint_state.robot_joint_state_type() {
+            RobotJointStateType::Full => { joint_state[full_state_idx] }
+            RobotJointStateType::DOF => {
+                let joint_axis = &self.robot_joint_state_module.ordered_joint_axes()[full_state_idx];
+                if joint_axis.is_coupled() { return self.robot_joint_state_module.coupled_joint_value(joint_state, full_state_idx); }
+                match joint_axis.fixed_value() {
+                    Some(fixed_value) => { fixed_value }
+                    None => {
+                        let joint_value = joint_state[dof_state_idxs[*dof_bookmark]];
+                        *dof_bookmark += 1;
+                        joint_value
+                    }
+                }
             }
         }
-
-        output.link_entries[link_idx].pose = Some(out_pose);
+    }
+    /// Returns the angular velocity (in the ball joint's child link frame) that results from a unit
+    /// rate of change of the given quaternion component of the ball joint.  With q the joint's
+    /// (possibly non-unit) quaternion and e_k the unit rate of change of component k, this is
+    /// (2 / |q|) * vec(q_hat^* e_k), where q_hat is q normalized.
+    fn joint_quaternion_component_angular_velocity(&self, joint_state: &RobotJointState, joint_idx: usize, joint_sub_dof_idx: usize) -> Result<Vector3<f64>, OptimaError> {
+        let joint = &self.robot_configuration_module.robot_model_module().joints()[joint_idx];
+        let quaternion_sub_dof_idx = joint.quaternion_sub_dof_idx().expect("error");
+        let q = self.robot_joint_state_module.joint_quaternion(joint_state, joint_idx)?.expect("error");
+        let q_norm = q.norm();
+        if q_norm == 0.0 { return Ok(Vector3::zeros()); }
+
+        let mut e = [0.0; 4];
+        e[joint_sub_dof_idx - quaternion_sub_dof_idx] = 1.0;
+        let e_k = Quaternion::new(e[0], e[1], e[2], e[3]);
+        let q_hat = UnitQuaternion::from_quaternion(q);
+
+        return Ok((2.0 / q_norm) * (q_hat.conjugate().into_inner() * e_k).vector().into_owned());
+    }
+    fn compute_fk_on_tcp_frames(&self, t: &OptimaSE3PoseType, output: &mut RobotFKResult) -> Result<(), OptimaError> {
+        let tcp_frame_infos = self.robot_configuration_module.robot_configuration_info().tcp_frame_infos();
+        for (tcp_frame_idx, tcp_frame_info) in tcp_frame_infos.iter().enumerate() {
+            let link_pose = &output.link_entries[tcp_frame_info.link_idx()].pose;
+            let tcp_frame_pose = match link_pose {
+                None => { None }
+                Some(link_pose) => { Some(link_pose.multiply(tcp_frame_info.offset().get_pose_by_type(t), false)?) }
+            };
+            output.tcp_frame_entries[tcp_frame_idx].pose = tcp_frame_pose;
+        }
 
         Ok(())
     }
@@ -500,7 +1083,7 @@ impl RobotKinematicsModule {
         let joints = self.robot_configuration_module.robot_model_module().joints();
         let preceding_joint = &joints[preceding_joint_idx];
 
-        if !preceding_joint.is_joint_with_all_standard_axes() {
+        if !preceding_joint.is_joint_with_all_standard_axes() && preceding_joint.quaternion_sub_dof_idx().is_none() {
             return Err(OptimaError::new_generic_error_str(&format!("Cannot perform reverse fk on a joint with non-standard axes.  Axes ---> {:?}", preceding_joint.joint_axes()), file!(), line!()));
         }
 
@@ -512,7 +1095,20 @@ impl RobotKinematicsModule {
 
         let joint_state_idxs = self.robot_joint_state_module.map_joint_idx_to_joint_state_idxs(preceding_joint_idx, &RobotJointStateType::Full)?;
 
-        if joint_state_idxs.len() == 1 {
+        if let Some(quaternion_sub_dof_idx) = preceding_joint.quaternion_sub_dof_idx() {
+            let (axis, angle, translation) = displacement.to_axis_angle_and_translation();
+            let q = if angle == 0.0 || axis.norm() == 0.0 { UnitQuaternion::identity() } else { UnitQuaternion::from_axis_angle(&Unit::new_normalize(axis), angle) };
+            let q_values = [q.w, q.i, q.j, q.k];
+
+            for (i, joint_state_idx) in joint_state_idxs.iter().enumerate() {
+                let joint_axis = &self.robot_joint_state_module.ordered_joint_axes()[*joint_state_idx];
+                match joint_axis.axis_primitive_type() {
+                    JointAxisPrimitiveType::Translation => { joint_state[*joint_state_idx] = translation.dot(&joint_axis.axis()); }
+                    JointAxisPrimitiveType::QuaternionComponent => { joint_state[*joint_state_idx] = q_values[i - quaternion_sub_dof_idx]; }
+                    JointAxisPrimitiveType::Rotation => { /* Ball joints do not have rotation axes. */ }
+                }
+            }
+        } else if joint_state_idxs.len() == 1 {
             let axis_angle_and_translation = displacement.to_axis_angle_and_translation();
             let axis = axis_angle_and_translation.0;
             let angle= axis_angle_and_translation.1;
@@ -536,6 +1132,7 @@ impl RobotKinematicsModule {
                         else if *aa == -1.0 { joint_state[joint_state_idx] = -translation[i]; }
                     }
                 }
+                JointAxisPrimitiveType::QuaternionComponent => { /* Handled above. */ }
             }
         } else {
             let euler_angles_and_translation = displacement.to_euler_angles_and_translation();
@@ -560,6 +1157,7 @@ impl RobotKinematicsModule {
                             else if *aa == -1.0 { joint_state[*joint_state_idx] = -t[i] }
                         }
                     }
+                    JointAxisPrimitiveType::QuaternionComponent => { /* Handled above. */ }
                 }
             }
         }
@@ -572,21 +1170,50 @@ impl RobotKinematicsModule {
     pub fn robot_joint_state_module(&self) -> &RobotJointStateModule {
         &self.robot_joint_state_module
     }
+    /// Sets the lower and upper bounds of all axes of the given joint (see
+    /// `RobotConfigurationModule::set_joint_limits`), e.g., to apply calibration results.  The
+    /// module's joint state module is re-derived accordingly.
+    pub fn set_joint_limits(&mut self, joint_id: JointId, lower: f64, upper: f64) -> Result<(), OptimaError> {
+        let mut robot_configuration_module = self.robot_configuration_module.to_owned_inner();
+        robot_configuration_module.set_joint_limits(joint_id, lower, upper)?;
+        *self = Self::new(robot_configuration_module);
+        Ok(())
+    }
+    /// Sets the lower and upper bounds of a single axis of the given joint.  The module's joint state
+    /// module is re-derived accordingly.
+    pub fn set_joint_sub_dof_limits(&mut self, joint_id: JointId, joint_sub_idx: usize, lower: f64, upper: f64) -> Result<(), OptimaError> {
+        let mut robot_configuration_module = self.robot_configuration_module.to_owned_inner();
+        robot_configuration_module.set_joint_sub_dof_limits(joint_id, joint_sub_idx, lower, upper)?;
+        *self = Self::new(robot_configuration_module);
+        Ok(())
+    }
+    /// Sets the axis of the given joint.  Subsequent forward kinematics and Jacobian computations
+    /// use the new axis.
+    pub fn set_joint_axis(&mut self, joint_id: JointId, axis: Vector3<f64>) -> Result<(), OptimaError> {
+        let mut robot_configuration_module = self.robot_configuration_module.to_owned_inner();
+        robot_configuration_module.set_joint_axis(joint_id, axis)?;
+        *self = Self::new(robot_configuration_module);
+        Ok(())
+    }
+    /// Sets the origin offset of the given joint relative to its preceding link, given as in a URDF
+    /// (e.g., to apply kinematic calibration results).  Subsequent forward kinematics and Jacobian
+    /// computations use the new offset.
+    pub fn set_joint_origin(&mut self, joint_id: JointId, origin_xyz: Vector3<f64>, origin_rpy: Vector3<f64>) -> Result<(), OptimaError> {
+        let mut robot_configuration_module = self.robot_configuration_module.to_owned_inner();
+        robot_configuration_module.set_joint_origin(joint_id, origin_xyz, origin_rpy)?;
+        *self = Self::new(robot_configuration_module);
+        Ok(())
+    }
 }
 impl SaveAndLoadable for RobotKinematicsModule {
     type SaveType = RobotConfigurationModule;
 
     fn get_save_serialization_object(&self) -> Self::SaveType {
-        self.robot_configuration_module.clone()
-    }
-
-    fn load_from_path(path: &OptimaStemCellPath) -> Result<Self, OptimaError> where Self: Sized {
-        let r: Self::SaveType = path.load_object_from_json_file()?;
-        return Ok(RobotKinematicsModule::new(r));
+        self.robot_configuration_module.to_owned_inner()
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let r: Self::SaveType = load_object_from_json_string(json_str)?;
+        let r: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
         return Ok(RobotKinematicsModule::new(r));
     }
 }
@@ -633,8 +1260,8 @@ impl RobotKinematicsModule {
             Some(p) => { Some(p.pose().clone()) }
         };
         let jac = self.compute_jacobian(&robot_joint_state,
-                                        start_link_idx,
-                                        end_link_idx,
+                                        start_link_idx.map(LinkId::new),
+                                        LinkId::new(end_link_idx),
                                         &JacobianEndPoint::from_ron_string(robot_jacobian_end_point).expect("error"),
                                         start_link_pose,
                                         JacobianMode::from_ron_string(jacobian_mode).expect("error")).expect("error");
@@ -642,6 +1269,30 @@ impl RobotKinematicsModule {
         let jac_vecs = NalgebraConversions::dmatrix_to_vecs(&jac);
         return jac_vecs;
     }
+    pub fn compute_frame_pose_py(&self, joint_state: Vec<f64>, frame_name: &str) -> OptimaSE3PosePy {
+        let robot_joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
+        let pose = self.compute_frame_pose(&robot_joint_state, frame_name, &OptimaSE3PoseType::ImplicitDualQuaternion).expect("error");
+        let (e, t) = pose.to_euler_angles_and_translation();
+        return OptimaSE3PosePy::new_euler_angles_and_translation_py(e[0], e[1], e[2], t[0], t[1], t[2]);
+    }
+    #[args(jacobian_mode = "\"Full\"")]
+    pub fn compute_frame_jacobian_py(&self, joint_state: Vec<f64>, frame_name: &str, jacobian_mode: &str) -> Vec<Vec<f64>> {
+        let robot_joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
+        let jac = self.compute_frame_jacobian(&robot_joint_state, frame_name, JacobianMode::from_ron_string(jacobian_mode).expect("error")).expect("error");
+        return NalgebraConversions::dmatrix_to_vecs(&jac);
+    }
+    #[args(robot_jacobian_end_point = "\"Link\"", jacobian_mode = "\"Full\"", damping = "0.01")]
+    pub fn compute_resolved_rate_joint_velocities_py(&self, joint_state: Vec<f64>, end_link_idx: usize, desired_twist: Vec<f64>, robot_jacobian_end_point: &str, jacobian_mode: &str, damping: f64, joint_velocity_limits: Option<Vec<f64>>) -> Vec<f64> {
+        let robot_joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
+        let res = self.compute_resolved_rate_joint_velocities(&robot_joint_state,
+                                                              LinkId::new(end_link_idx),
+                                                              &JacobianEndPoint::from_ron_string(robot_jacobian_end_point).expect("error"),
+                                                              JacobianMode::from_ron_string(jacobian_mode).expect("error"),
+                                                              &NalgebraConversions::vec_to_dvector(&desired_twist),
+                                                              damping,
+                                                              joint_velocity_limits.as_ref()).expect("error");
+        return NalgebraConversions::dvector_to_vec(&res.joint_velocities);
+    }
     pub fn compute_reverse_fk_py(&self, v: Vec<Option<OptimaSE3PosePy>>) -> Vec<f64> {
         let mut input = RobotFKResult::new_empty(self);
         let num_link_entries = input.link_entries().len();
@@ -677,12 +1328,11 @@ impl RobotKinematicsModule {
         };
         return RobotKinematicsModule::new_from_names(robot_names).expect("error");
     }
-    pub fn compute_fk_wasm(&self, joint_state: Vec<f64>, pose_type: &str) -> JsValue {
+    pub fn compute_fk_wasm(&self, joint_state: Vec<f64>, pose_type: &str) -> RobotFKResult {
         let robot_joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
-        let res = self.compute_fk(&robot_joint_state, &OptimaSE3PoseType::from_ron_string(pose_type).expect("error")).expect("error");
-       return  JsValue::from_serde(&res).unwrap();
+        return self.compute_fk(&robot_joint_state, &OptimaSE3PoseType::from_ron_string(pose_type).expect("error")).expect("error");
     }
-    pub fn compute_fk_floating_chain_py(&self, joint_state: Vec<f64>, pose_type: &str, start_link_idx: Option<usize>, end_link_idx: Option<usize>, start_link_pose: Option<OptimaSE3PoseWASM>) -> JsValue {
+    pub fn compute_fk_floating_chain_py(&self, joint_state: Vec<f64>, pose_type: &str, start_link_idx: Option<usize>, end_link_idx: Option<usize>, start_link_pose: Option<OptimaSE3PoseWASM>) -> RobotFKResult {
         let robot_joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
         let floating_link_input = FloatingLinkInput {
             start_link_idx,
@@ -693,35 +1343,36 @@ impl RobotKinematicsModule {
             }
         };
 
-        let res = self.compute_fk_floating_chain(&robot_joint_state, &OptimaSE3PoseType::from_ron_string(pose_type).expect("error"), &floating_link_input).expect("error");
-        return JsValue::from_serde(&res).unwrap();
+        return self.compute_fk_floating_chain(&robot_joint_state, &OptimaSE3PoseType::from_ron_string(pose_type).expect("error"), &floating_link_input).expect("error");
     }
-    pub fn compute_jacobian_wasm(&self, joint_state: Vec<f64>, end_link_idx: usize, start_link_idx: Option<usize>, start_link_pose: Option<OptimaSE3PoseWASM>, robot_jacobian_end_point: &str, jacobian_mode: &str) -> JsValue {
+    pub fn compute_jacobian_wasm(&self, joint_state: Vec<f64>, end_link_idx: usize, start_link_idx: Option<usize>, start_link_pose: Option<OptimaSE3PoseWASM>, robot_jacobian_end_point: &str, jacobian_mode: &str) -> JsMatrixObject {
         let robot_joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
         let start_link_pose = match start_link_pose {
             None => { None }
             Some(p) => { Some(p.pose().clone()) }
         };
         let jac = self.compute_jacobian(&robot_joint_state,
-                                        start_link_idx,
-                                        end_link_idx,
+                                        start_link_idx.map(LinkId::new),
+                                        LinkId::new(end_link_idx),
                                         &JacobianEndPoint::from_ron_string(robot_jacobian_end_point).expect("error"),
                                         start_link_pose,
                                         JacobianMode::from_ron_string(jacobian_mode).expect("error")).expect("error");
 
         let jac_vecs = NalgebraConversions::dmatrix_to_vecs(&jac);
         let jac_vecs_js = JsMatrix::new(jac_vecs);
-        return JsValue::from_serde(&jac_vecs_js).unwrap();
+        return jac_vecs_js.to_js_object();
     }
 }
 
 /// The output of a forward kinematics computation.
 /// The primary field in this object is `link_entries`.  This is a list of `RobotFKResultLinkEntry`
-/// objects.
+/// objects.  If the robot configuration specifies any tool center point (TCP) frames, their poses
+/// will be included in `tcp_frame_entries`.
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen, derive(Clone, Debug, Serialize, Deserialize))]
 #[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
 pub struct RobotFKResult {
-    link_entries: Vec<RobotFKResultLinkEntry>
+    link_entries: Vec<RobotFKResultLinkEntry>,
+    tcp_frame_entries: Vec<RobotFKResultTCPFrameEntry>
 }
 impl RobotFKResult {
     pub fn new_empty(robot_kinematics_module: &RobotKinematicsModule) -> Self {
@@ -731,6 +1382,127 @@ impl RobotFKResult {
     pub fn link_entries(&self) -> &Vec<RobotFKResultLinkEntry> {
         &self.link_entries
     }
+    /// Returns the pose of the given link, or None if the link is not included in the result (or
+    /// does not exist).
+    pub fn link_pose(&self, link_id: LinkId) -> Option<&OptimaSE3Pose> {
+        return match self.link_entries.get(link_id.idx()) {
+            None => { None }
+            Some(e) => { e.pose.as_ref() }
+        }
+    }
+    /// Returns a reference to the results TCP frame entries.
+    pub fn tcp_frame_entries(&self) -> &Vec<RobotFKResultTCPFrameEntry> {
+        &self.tcp_frame_entries
+    }
+    /// Returns the TCP frame entry with the given name, if it exists.
+    pub fn get_tcp_frame_entry_by_name(&self, tcp_frame_name: &str) -> Option<&RobotFKResultTCPFrameEntry> {
+        for e in &self.tcp_frame_entries {
+            if e.tcp_frame_name == tcp_frame_name { return Some(e); }
+        }
+        return None;
+    }
+    /// Returns the pose of every link as a 4x4 homogeneous matrix, or None for links that are not
+    /// included in the result.
+    pub fn link_homogeneous_matrices(&self) -> Vec<Option<Matrix4<f64>>> {
+        let mut out_vec = vec![];
+        for e in &self.link_entries {
+            out_vec.push(e.pose.as_ref().map(|pose| pose.to_nalgebra_isometry().to_homogeneous()));
+        }
+        out_vec
+    }
+    /// Same as `link_homogeneous_matrices`, but for the TCP frames.
+    pub fn tcp_frame_homogeneous_matrices(&self) -> Vec<Option<Matrix4<f64>>> {
+        let mut out_vec = vec![];
+        for e in &self.tcp_frame_entries {
+            out_vec.push(e.pose.as_ref().map(|pose| pose.to_nalgebra_isometry().to_homogeneous()));
+        }
+        out_vec
+    }
+    /// Returns the poses of all links packed into one flat vector with 7 entries per link,
+    /// \[x, y, z, qx, qy, qz, qw\].  Links that are not included in the result are filled with NaN.
+    pub fn link_poses_packed(&self) -> Vec<f64> {
+        let mut out_vec = vec![];
+        for e in &self.link_entries {
+            out_vec.extend_from_slice(&Self::pose_to_packed(&e.pose));
+        }
+        out_vec
+    }
+    /// Same as `link_poses_packed`, but for the TCP frames.
+    pub fn tcp_frame_poses_packed(&self) -> Vec<f64> {
+        let mut out_vec = vec![];
+        for e in &self.tcp_frame_entries {
+            out_vec.extend_from_slice(&Self::pose_to_packed(&e.pose));
+        }
+        out_vec
+    }
+    /// Returns the poses of all links as row-major 4x4 homogeneous matrices packed into one flat
+    /// vector (16 entries per link).  Links that are not included in the result are filled with NaN.
+    pub fn link_homogeneous_matrices_packed(&self) -> Vec<f64> {
+        let mut out_vec = vec![];
+        for m in self.link_homogeneous_matrices() {
+            match m {
+                None => { out_vec.extend_from_slice(&[f64::NAN; 16]); }
+                Some(m) => {
+                    for row in 0..4 {
+                        for col in 0..4 { out_vec.push(m[(row, col)]); }
+                    }
+                }
+            }
+        }
+        out_vec
+    }
+    fn pose_to_packed(pose: &Option<OptimaSE3Pose>) -> [f64; 7] {
+        return match pose {
+            None => { [f64::NAN; 7] }
+            Some(pose) => {
+                let isometry = pose.to_nalgebra_isometry();
+                let t = &isometry.translation.vector;
+                let q = &isometry.rotation;
+                [t[0], t[1], t[2], q.i, q.j, q.k, q.w]
+            }
+        }
+    }
+    /// Returns the pose of link_id expressed in the frame of reference_link_id, i.e., the
+    /// displacement T such that T_reference * T = T_link.  Returns None if either link is not
+    /// included in the result.
+    pub fn link_pose_relative_to_link(&self, link_id: LinkId, reference_link_id: LinkId) -> Result<Option<OptimaSE3Pose>, OptimaError> {
+        let link_idx = link_id.idx();
+        let reference_link_idx = reference_link_id.idx();
+        OptimaError::new_check_for_idx_out_of_bound_error(link_idx, self.link_entries.len(), file!(), line!())?;
+        OptimaError::new_check_for_idx_out_of_bound_error(reference_link_idx, self.link_entries.len(), file!(), line!())?;
+
+        return match (&self.link_entries[reference_link_idx].pose, &self.link_entries[link_idx].pose) {
+            (Some(reference_pose), Some(pose)) => { Ok(Some(reference_pose.displacement(pose, true)?)) }
+            _ => { Ok(None) }
+        }
+    }
+    /// Returns a copy of this result with every link and TCP frame pose expressed in the frame of
+    /// reference_link_id instead of the world frame.  The reference link's own pose becomes the
+    /// identity.  Returns an error if the reference link is not included in the result.
+    pub fn expressed_relative_to_link(&self, reference_link_id: LinkId) -> Result<RobotFKResult, OptimaError> {
+        let reference_link_idx = reference_link_id.idx();
+        OptimaError::new_check_for_idx_out_of_bound_error(reference_link_idx, self.link_entries.len(), file!(), line!())?;
+        let reference_pose = match &self.link_entries[reference_link_idx].pose {
+            None => {
+                return Err(OptimaError::new_generic_error_str(&format!("Reference link {} is not included in the forward kinematics result.", reference_link_idx), file!(), line!()));
+            }
+            Some(reference_pose) => { reference_pose.clone() }
+        };
+
+        let mut out = self.clone();
+        for e in &mut out.link_entries {
+            if let Some(pose) = &e.pose {
+                e.pose = Some(reference_pose.displacement(pose, true)?);
+            }
+        }
+        for e in &mut out.tcp_frame_entries {
+            if let Some(pose) = &e.pose {
+                e.pose = Some(reference_pose.displacement(pose, true)?);
+            }
+        }
+
+        Ok(out)
+    }
     /// Prints a summary of the forward kinematics result.
     pub fn print_summary(&self) {
         for e in self.link_entries() {
@@ -742,6 +1514,15 @@ impl RobotFKResult {
                 optima_print(&format!("   > Pose Translation: {:?}", euler_angles.1), PrintMode::Println, PrintColor::None, false);
             }
         }
+        for e in self.tcp_frame_entries() {
+            optima_print(&format!("TCP Frame {} {} (on link {}) ---> ", e.tcp_frame_idx, e.tcp_frame_name, e.link_idx), PrintMode::Println, PrintColor::Magenta, true);
+            optima_print(&format!("   > Pose: {:?}", e.pose), PrintMode::Println, PrintColor::None, false);
+            if e.pose.is_some() {
+                let euler_angles = e.pose.as_ref().unwrap().to_euler_angles_and_translation();
+                optima_print(&format!("   > Pose Euler Angles: {:?}", euler_angles.0), PrintMode::Println, PrintColor::None, false);
+                optima_print(&format!("   > Pose Translation: {:?}", euler_angles.1), PrintMode::Println, PrintColor::None, false);
+            }
+        }
     }
 }
 
@@ -762,6 +1543,120 @@ impl RobotFKResult {
         let e = &self.link_entries[link_idx];
         return e.pose_py();
     }
+    pub fn tcp_frame_entries_py(&self) -> Vec<RobotFKResultTCPFrameEntry> {
+        self.tcp_frame_entries.clone()
+    }
+    pub fn get_link_pose_relative_to_link(&self, link_idx: usize, reference_link_idx: usize) -> Option<OptimaSE3PosePy> {
+        let pose = self.link_pose_relative_to_link(LinkId::new(link_idx), LinkId::new(reference_link_idx)).expect("error");
+        return match &pose {
+            None => { None }
+            Some(pose) => {
+                let euler_angles_and_translation = pose.to_euler_angles_and_translation();
+                let e = euler_angles_and_translation.0;
+                let t = euler_angles_and_translation.1;
+                Some(OptimaSE3PosePy::new_euler_angles_and_translation_py(e[0], e[1], e[2], t[0], t[1], t[2]))
+            }
+        }
+    }
+    pub fn expressed_relative_to_link_py(&self, reference_link_idx: usize) -> RobotFKResult {
+        return self.expressed_relative_to_link(LinkId::new(reference_link_idx)).expect("error");
+    }
+    /// Row-major 4x4 homogeneous matrices, one per link (None for links not in the result).
+    pub fn link_homogeneous_matrices_py(&self) -> Vec<Option<Vec<Vec<f64>>>> {
+        let mut out_vec = vec![];
+        for m in self.link_homogeneous_matrices() {
+            out_vec.push(m.map(|m| (0..4).map(|row| (0..4).map(|col| m[(row, col)]).collect()).collect()));
+        }
+        out_vec
+    }
+    pub fn link_poses_packed_py(&self) -> Vec<f64> {
+        self.link_poses_packed()
+    }
+    pub fn tcp_frame_poses_packed_py(&self) -> Vec<f64> {
+        self.tcp_frame_poses_packed()
+    }
+    /// Returns None if the TCP frame does not exist or has no pose.
+    pub fn get_tcp_frame_pose(&self, tcp_frame_name: &str) -> Option<OptimaSE3PosePy> {
+        return self.get_tcp_frame_entry_by_name(tcp_frame_name)?.pose_py();
+    }
+}
+
+/// WASM implementations.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl RobotFKResult {
+    #[wasm_bindgen(getter = num_links)]
+    pub fn num_links_wasm(&self) -> usize { self.link_entries.len() }
+    #[wasm_bindgen(getter = num_tcp_frames)]
+    pub fn num_tcp_frames_wasm(&self) -> usize { self.tcp_frame_entries.len() }
+    pub fn link_entry_wasm(&self, link_idx: usize) -> RobotFKResultLinkEntry {
+        return self.link_entries[link_idx].clone();
+    }
+    pub fn link_pose_wasm(&self, link_idx: usize) -> Option<OptimaSE3PoseWASM> {
+        return self.link_entries[link_idx].pose_wasm();
+    }
+    /// Translations of all links as a flat `Float64Array` (3 entries per link).  Links that are
+    /// not included in the FK computation are filled with NaN.
+    pub fn link_translations_wasm(&self) -> Vec<f64> {
+        let mut out_vec = vec![];
+        for e in &self.link_entries {
+            match &e.pose {
+                None => { out_vec.extend_from_slice(&[f64::NAN; 3]); }
+                Some(pose) => {
+                    let t = pose.translation();
+                    out_vec.extend_from_slice(&[t[0], t[1], t[2]]);
+                }
+            }
+        }
+        out_vec
+    }
+    /// Rotations of all links as a flat `Float64Array` of unit quaternions (4 entries per link,
+    /// ordered \[x, y, z, w\]).  Links that are not included in the FK computation are filled with NaN.
+    pub fn link_unit_quaternions_wasm(&self) -> Vec<f64> {
+        let mut out_vec = vec![];
+        for e in &self.link_entries {
+            match &e.pose {
+                None => { out_vec.extend_from_slice(&[f64::NAN; 4]); }
+                Some(pose) => {
+                    let q = pose.to_nalgebra_isometry().rotation;
+                    out_vec.extend_from_slice(&[q.i, q.j, q.k, q.w]);
+                }
+            }
+        }
+        out_vec
+    }
+    /// Poses of all links as a flat `Float64Array` (7 entries per link, ordered
+    /// \[x, y, z, qx, qy, qz, qw\]).  Links that are not included in the FK computation are filled with NaN.
+    pub fn link_poses_packed_wasm(&self) -> Vec<f64> {
+        self.link_poses_packed()
+    }
+    /// Poses of all links as a flat `Float64Array` of row-major 4x4 homogeneous matrices (16 entries
+    /// per link).  Links that are not included in the FK computation are filled with NaN.
+    pub fn link_homogeneous_matrices_wasm(&self) -> Vec<f64> {
+        self.link_homogeneous_matrices_packed()
+    }
+    pub fn tcp_frame_poses_packed_wasm(&self) -> Vec<f64> {
+        self.tcp_frame_poses_packed()
+    }
+    pub fn tcp_frame_entry_wasm(&self, tcp_frame_idx: usize) -> RobotFKResultTCPFrameEntry {
+        return self.tcp_frame_entries[tcp_frame_idx].clone();
+    }
+    pub fn tcp_frame_pose_wasm(&self, tcp_frame_name: &str) -> Option<OptimaSE3PoseWASM> {
+        let e = self.get_tcp_frame_entry_by_name(tcp_frame_name).expect(&format!("TCP frame {} does not exist.", tcp_frame_name));
+        return e.pose_wasm();
+    }
+}
+
+/// The frame that the poses of a `RobotFKResult` are expressed in (see
+/// `RobotKinematicsModule::compute_fk_in_frame`).
+/// - `World`: the default output of forward kinematics.
+/// - `RobotBase`: the frame of the robot's base link.
+/// - `Link`: the frame of an arbitrary reference link, e.g., a wrist camera.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RobotFKResultFrame {
+    World,
+    RobotBase,
+    Link { link_id: LinkId }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -813,11 +1708,132 @@ impl RobotFKResultLinkEntry {
     }
 }
 
+/// WASM implementations.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl RobotFKResultLinkEntry {
+    #[wasm_bindgen(getter = link_idx)]
+    pub fn link_idx_wasm(&self) -> usize { self.link_idx }
+    #[wasm_bindgen(getter = link_name)]
+    pub fn link_name_wasm(&self) -> String { self.link_name.clone() }
+    #[wasm_bindgen(getter = pose)]
+    pub fn pose_wasm(&self) -> Option<OptimaSE3PoseWASM> {
+        return self.pose.as_ref().map(|p| OptimaSE3PoseWASM::new(p.clone()));
+    }
+}
+
+/// A `RobotFKResultTCPFrameEntry` specifies information about one tool center point (TCP) frame
+/// in the forward kinematics process.  It provides the TCP frame's index and name, the index of the
+/// link it is attached to, and the pose of the TCP frame.  If the link the TCP frame is attached to
+/// is not included in the FK computation, the pose will be None.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen, derive(Clone, Debug, Serialize, Deserialize))]
+#[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
+pub struct RobotFKResultTCPFrameEntry {
+    tcp_frame_idx: usize,
+    tcp_frame_name: String,
+    link_idx: usize,
+    pose: Option<OptimaSE3Pose>
+}
+impl RobotFKResultTCPFrameEntry {
+    pub fn tcp_frame_idx(&self) -> usize {
+        self.tcp_frame_idx
+    }
+    pub fn tcp_frame_name(&self) -> &str {
+        &self.tcp_frame_name
+    }
+    pub fn link_idx(&self) -> usize {
+        self.link_idx
+    }
+    pub fn pose(&self) -> &Option<OptimaSE3Pose> {
+        &self.pose
+    }
+}
+
+/// Python implementations.
+#[cfg(not(target_arch = "wasm32"))]
+#[pymethods]
+impl RobotFKResultTCPFrameEntry {
+    pub fn tcp_frame_idx_py(&self) -> usize { self.tcp_frame_idx }
+    pub fn tcp_frame_name_py(&self) -> String { self.tcp_frame_name.clone() }
+    pub fn link_idx_py(&self) -> usize { self.link_idx }
+    pub fn pose_py(&self) -> Option<OptimaSE3PosePy> {
+        return match &self.pose {
+            None => { None }
+            Some(pose) => {
+                let euler_angles_and_translation = pose.to_euler_angles_and_translation();
+                let e = euler_angles_and_translation.0;
+                let t = euler_angles_and_translation.1;
+                Some(OptimaSE3PosePy::new_euler_angles_and_translation_py(e[0], e[1], e[2], t[0], t[1], t[2]))
+            }
+        }
+    }
+}
+
+/// WASM implementations.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl RobotFKResultTCPFrameEntry {
+    #[wasm_bindgen(getter = tcp_frame_idx)]
+    pub fn tcp_frame_idx_wasm(&self) -> usize { self.tcp_frame_idx }
+    #[wasm_bindgen(getter = tcp_frame_name)]
+    pub fn tcp_frame_name_wasm(&self) -> String { self.tcp_frame_name.clone() }
+    #[wasm_bindgen(getter = link_idx)]
+    pub fn link_idx_wasm(&self) -> usize { self.link_idx }
+    #[wasm_bindgen(getter = pose)]
+    pub fn pose_wasm(&self) -> Option<OptimaSE3PoseWASM> {
+        return self.pose.as_ref().map(|p| OptimaSE3PoseWASM::new(p.clone()));
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum JacobianMode {
     Full, Translational, Rotational
 }
 
+/// Output of `RobotKinematicsModule::compute_resolved_rate_joint_velocities`.  scale_factor is the
+/// uniform factor (in (0, 1\]) that was applied to the joint velocities to respect the joint velocity
+/// limits.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ResolvedRateOutput {
+    joint_velocities: DVector<f64>,
+    scale_factor: f64
+}
+impl ResolvedRateOutput {
+    pub fn joint_velocities(&self) -> &DVector<f64> {
+        &self.joint_velocities
+    }
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+}
+
+/// Output of `RobotKinematicsModule::compute_ik_damped_least_squares`.  joint_state is a DOF state.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InverseKinematicsOutput {
+    joint_state: RobotJointState,
+    converged: bool,
+    position_error: f64,
+    rotation_error: f64,
+    iterations: usize
+}
+impl InverseKinematicsOutput {
+    pub fn joint_state(&self) -> &RobotJointState {
+        &self.joint_state
+    }
+    pub fn converged(&self) -> bool {
+        self.converged
+    }
+    pub fn position_error(&self) -> f64 {
+        self.position_error
+    }
+    pub fn rotation_error(&self) -> f64 {
+        self.rotation_error
+    }
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum JacobianEndPoint {
     Link,
@@ -825,3 +1841,5 @@ pub enum JacobianEndPoint {
     Global(Vector3<f64>),
     InertialOrigin
 }
+
+crate::impl_py_pickle!(RobotKinematicsModule, RobotFKResult, RobotFKResultLinkEntry, RobotFKResultTCPFrameEntry);
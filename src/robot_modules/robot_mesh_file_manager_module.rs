@@ -4,11 +4,14 @@ use pyo3::*;
 use serde::{Serialize, Deserialize};
 use crate::robot_modules::robot_geometric_shape_module::RobotLinkShapeRepresentation;
 use crate::robot_modules::robot_model_module::RobotModelModule;
-use crate::utils::utils_console::{optima_print, PrintColor, PrintMode};
 #[cfg(not(target_arch = "wasm32"))]
-use crate::utils::utils_console::get_default_progress_bar;
+use crate::utils::utils_console::{get_default_progress_bar, optima_print, PrintColor, PrintMode};
 use crate::utils::utils_errors::OptimaError;
-use crate::utils::utils_files::optima_path::{load_object_from_json_string, OptimaAssetLocation, OptimaPath, OptimaPathMatchingPattern, OptimaPathMatchingStopCondition, OptimaStemCellPath};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils::utils_files::mesh_uri_resolver::MeshUriResolver;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils::utils_files::optima_path::OptimaPath;
+use crate::utils::utils_files::optima_path::{OptimaAssetLocation, OptimaStemCellPath};
 use crate::utils::utils_robot::link::Link;
 use crate::utils::utils_shape_geometry::geometric_shape::{GeometricShape, GeometricShapeSignature};
 use crate::utils::utils_traits::SaveAndLoadable;
@@ -22,7 +25,7 @@ pub struct RobotMeshFileManagerModule {
 }
 impl RobotMeshFileManagerModule {
     pub fn new_from_name(robot_name: &str) -> Result<Self, OptimaError> {
-        let robot_model_module = RobotModelModule::new(robot_name)?;
+        let robot_model_module = RobotModelModule::new_shared(robot_name)?;
         return Self::new(&robot_model_module);
     }
     pub fn new(robot_model_module: &RobotModelModule) -> Result<Self, OptimaError> {
@@ -31,111 +34,37 @@ impl RobotMeshFileManagerModule {
             links: robot_model_module.links().clone()
         })
     }
-    fn get_urdf_link_mesh_path_split_vecs(&self, link_mesh_type: &LinkMeshType) -> Vec<Option<Vec<String>>> {
+    /// The mesh filename (uri) of each link as given in the URDF, or None if the link has no mesh of
+    /// the given type.
+    pub fn get_urdf_link_mesh_uris(&self, link_mesh_type: &LinkMeshType) -> Vec<Option<String>> {
         let mut out_vec = vec![];
-
-        let links = &self.links;
-        for link in links {
-            let path_string_string = match link_mesh_type {
+        for link in &self.links {
+            let uri = match link_mesh_type {
                 LinkMeshType::Visual => { link.urdf_link().visual_mesh_filename().clone() }
                 LinkMeshType::Collision => { link.urdf_link().collision_mesh_filename().clone() }
             };
-
-            match path_string_string {
-                None => { out_vec.push(None); }
-                Some(path_string) => {
-                    let path_string_split: Vec<&str> = path_string.split("/").collect();
-                    let mut v = vec![];
-                    for path_string in path_string_split {
-                        v.push(path_string.to_string());
-                    }
-                    out_vec.push(Some(v));
-                }
-            }
-        }
-
-        out_vec
-    }
-    fn get_final_n_subcomponents_from_urdf_link_mesh_path_split_vecs(&self, link_mesh_type: &LinkMeshType, n: usize) -> Vec<Option<Vec<String>>> {
-        let mut out_vec = vec![];
-
-        let split = self.get_urdf_link_mesh_path_split_vecs(link_mesh_type);
-        for s in &split {
-            match s {
-                None => { out_vec.push(None); }
-                Some(ss) => {
-                    let mut v = vec![];
-                    let n_local = n.min(ss.len());
-                    for i in 0..n_local {
-                        v.push(ss[ss.len() - n_local + i].clone());
-                    }
-                    out_vec.push(Some(v));
-                }
-            }
+            out_vec.push(uri);
         }
-
         out_vec
     }
-    fn find_optima_paths_to_urdf_link_meshes(&self, link_mesh_type: &LinkMeshType) -> Result<Vec<Option<OptimaPath>>, OptimaError> {
-        let mut out_vec = vec![];
-
-        let mut directory_string_vecs = vec![];
-        let mut directory_idxs = vec![];
-        let subcomponents = self.get_final_n_subcomponents_from_urdf_link_mesh_path_split_vecs(link_mesh_type, 3);
-        for s in &subcomponents {
-            if let Some(ss) = s {
-                let check_vec = vec![ ss[0].clone(), ss[1].clone() ];
-                if !directory_string_vecs.contains(&check_vec) {
-                    directory_idxs.push(Some(directory_string_vecs.len()));
-                    directory_string_vecs.push(check_vec.clone());
-                } else {
-                    for (i, d) in directory_string_vecs.iter().enumerate() {
-                        if d == &check_vec {
-                            directory_idxs.push(Some(i));
-                        }
-                    }
-                }
-            } else {
-                directory_idxs.push(None);
-            }
-        }
-
-        let mut directory_optima_paths = vec![];
-        optima_print("Finding mesh file directories.  This may take a while...", PrintMode::Println, PrintColor::Cyan, true);
-        for d in &directory_string_vecs {
-            let p = OptimaPath::new_home_path()?;
-            let res = p.walk_directory_and_match(OptimaPathMatchingPattern::PathComponents(d.clone()), OptimaPathMatchingStopCondition::First);
-            if res.len() > 0 { directory_optima_paths.push(res[0].clone()); }
-            else {
-                return Err(OptimaError::new_generic_error_str(&format!("Could not find directory corresponding to path components {:?}.", d), file!(), line!()));
-            }
-        }
-
-        let subcomponents = self.get_final_n_subcomponents_from_urdf_link_mesh_path_split_vecs(link_mesh_type, 1);
-        for (i, directory_optima_path_idx_option) in directory_idxs.iter().enumerate() {
-            match directory_optima_path_idx_option {
-                None => { out_vec.push(None); }
-                Some(idx) => {
-                    let ss = subcomponents[i].as_ref().unwrap();
-                    let mut out_path_clone = directory_optima_paths[*idx].clone();
-                    out_path_clone.append(&ss[0]);
-                    out_vec.push(Some(out_path_clone));
-                }
-            }
-        }
-
-        Ok(out_vec)
-    }
     /// Attempts to find mesh files on the user's computer based on the file paths specified in the robot URDF.
     /// The mesh files can be anywhere on the computer, but these files will be easiest to find in
     /// a major directory like the desktop.  If the files are found, they are copied to the local
     /// optima_assets directory.  If the files cannot be found, this function will return an error.
-    #[allow(unused_must_use)]
+    /// Uses the default `MeshUriResolver` (see `find_and_copy_visual_meshes_to_assets_with_resolver`).
     #[cfg(not(target_arch = "wasm32"))]
     pub fn find_and_copy_visual_meshes_to_assets(&self) -> Result<(), OptimaError> {
+        return self.find_and_copy_visual_meshes_to_assets_with_resolver(&MeshUriResolver::new());
+    }
+    /// Same as `find_and_copy_visual_meshes_to_assets`, with mesh uris (including `package://` and
+    /// `file://` uris) resolved by the given resolver, so meshes can be found under configured mesh
+    /// roots and verified against checksums.
+    #[allow(unused_must_use)]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn find_and_copy_visual_meshes_to_assets_with_resolver(&self, mesh_uri_resolver: &MeshUriResolver) -> Result<(), OptimaError> {
         optima_print(&format!("Finding and copying visual meshes to assets folder..."), PrintMode::Println, PrintColor::Blue, true);
         let destination = OptimaPath::new_asset_physical_path_from_json_file()?;
-        let paths = self.find_optima_paths_to_urdf_link_meshes(&LinkMeshType::Visual)?;
+        let paths = mesh_uri_resolver.resolve_all(&self.get_urdf_link_mesh_uris(&LinkMeshType::Visual))?;
         let num_paths = paths.len();
         let mut pb = get_default_progress_bar(num_paths);
 
@@ -333,6 +262,9 @@ impl RobotMeshFileManagerModule {
                     }
                 }
             }
+            RobotLinkShapeRepresentation::Auto => {
+                return Err(OptimaError::new_generic_error_str("The Auto robot link shape representation does not have geometric shapes of its own.", file!(), line!()));
+            }
         }
 
         Ok(out_vec)
@@ -340,6 +272,9 @@ impl RobotMeshFileManagerModule {
     pub fn robot_name(&self) -> &str {
         &self.robot_name
     }
+    pub fn links(&self) -> &Vec<Link> {
+        &self.links
+    }
 }
 impl SaveAndLoadable for RobotMeshFileManagerModule {
     type SaveType = String;
@@ -349,7 +284,7 @@ impl SaveAndLoadable for RobotMeshFileManagerModule {
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let robot_name: Self::SaveType = load_object_from_json_string(json_str)?;
+        let robot_name: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
         return RobotMeshFileManagerModule::new_from_name(&robot_name);
     }
 }
@@ -362,6 +297,10 @@ impl RobotMeshFileManagerModule {
         return Self::new_from_name(robot_name).expect("error");
     }
 
+    pub fn find_and_copy_visual_meshes_to_assets_with_resolver_py(&self, mesh_uri_resolver: &MeshUriResolver) {
+        self.find_and_copy_visual_meshes_to_assets_with_resolver(mesh_uri_resolver).expect("error");
+    }
+
     pub fn get_paths_to_meshes_as_strings(&self) -> Vec<Option<String>> {
         let mut out_vec = vec![];
 
@@ -426,3 +365,5 @@ pub enum LinkMeshType {
     Collision
 }
 
+
+crate::impl_py_pickle!(RobotMeshFileManagerModule);
@@ -5,17 +5,22 @@ use pyo3::*;
 use wasm_bindgen::prelude::*;
 
 use std::collections::HashMap;
+use nalgebra::{Isometry3, Vector3};
 use serde::{Serialize, Deserialize};
-use crate::robot_modules::robot_configuration_module::ContiguousChainMobilityMode;
+use crate::robot_modules::robot_configuration_module::{ContiguousChainMobilityMode, JointCouplingInfo};
+use crate::robot_modules::robot_module_cache::RobotModuleCache;
 use crate::utils::utils_errors::OptimaError;
-use crate::utils::utils_robot::joint::{Joint};
+use crate::utils::utils_robot::joint::{Joint, JointAxis};
 use crate::utils::utils_robot::link::Link;
-use crate::utils::utils_robot::urdf_joint::URDFJoint;
+use crate::utils::utils_robot::robot_ids::{JointId, LinkId};
+use crate::utils::utils_robot::robot_import_options::RobotImportOptions;
+use crate::utils::utils_robot::urdf_joint::{JointTypeWrapper, URDFJoint};
 use crate::utils::utils_robot::urdf_link::URDFLink;
+use crate::utils::utils_se3::optima_se3_pose::OptimaSE3Pose;
 use crate::utils::utils_console::{optima_print, PrintColor, PrintMode};
-use crate::utils::utils_files::optima_path::{load_object_from_json_string, OptimaAssetLocation, OptimaPathMatchingPattern, OptimaPathMatchingStopCondition, OptimaStemCellPath, RobotModuleJsonType};
-use crate::utils::utils_generic_data_structures::SquareArray2D;
-use crate::utils::utils_traits::{AssetSaveAndLoadable, SaveAndLoadable};
+use crate::utils::utils_files::optima_path::{OptimaAssetLocation, OptimaPathMatchingPattern, OptimaPathMatchingStopCondition, OptimaStemCellPath, RobotModuleJsonType};
+use crate::utils::utils_generic_data_structures::{SharedHandle, SquareArray2D};
+use crate::utils::utils_traits::{AssetSaveAndLoadable, SaveAndLoadable, ToAndFromJsonString};
 
 /// The `RobotModelModule` is the base description level for a robot.  It reflects component and
 /// connectivity information about the robot as specified directly by the URDF.
@@ -42,10 +47,11 @@ pub struct RobotModelModule {
     link_tree_traversal_layers: Vec<Vec<usize>>,
     link_tree_max_depth: usize,
     preceding_actuated_joint_idxs: Vec<Option<usize>>,
-    link_chains: SquareArray2D<Vec<usize>>,
+    link_chains: SquareArray2D<Vec<LinkId>>,
     link_name_to_idx_hashmap: HashMap<String, usize>,
     joint_name_to_idx_hashmap: HashMap<String, usize>
 }
+
 impl RobotModelModule {
     /// Creates a new `RobotModelModule`.  The robot_name string is the name of the folder in the
     /// optima_assets/optima_robots directory.
@@ -55,18 +61,25 @@ impl RobotModelModule {
     /// use optima::robot_modules::robot_model_module::RobotModelModule;
     /// let mut r = RobotModelModule::new_from_absolute_paths("ur5");
     /// ```
+    ///
+    /// The model is loaded through the process-wide `RobotModuleCache`, and the returned module is
+    /// an owned copy of the cached module.  Use `new_shared` to avoid the copy when read-only
+    /// access is enough.
     pub fn new(robot_name: &str) -> Result<Self, OptimaError> {
-        let load_result = Self::load_as_asset(OptimaAssetLocation::RobotModuleJson { robot_name: robot_name.to_string(), t: RobotModuleJsonType::ModelModule });
-        if let Ok(load_result) = load_result { return Ok(load_result); }
-
-        let mut joints = vec![];
-        let mut links = vec![];
-
-        let mut urdf_robot_joints = vec![];
-        let mut urdf_robot_links = vec![];
-
-        let mut link_name_to_idx_hashmap = HashMap::new();
-        let mut joint_name_to_idx_hashmap = HashMap::new();
+        let handle = RobotModuleCache::get_or_load_robot_model_module(robot_name)?;
+        return Ok(handle.to_owned_inner());
+    }
+    /// Same as `new`, but returns the handle held by the `RobotModuleCache` rather than a copy.
+    pub fn new_shared(robot_name: &str) -> Result<SharedHandle<Self>, OptimaError> {
+        return RobotModuleCache::get_or_load_robot_model_module(robot_name);
+    }
+    /// Same as `new`, but bypasses the process-wide `RobotModuleCache`.
+    pub fn new_uncached(robot_name: &str) -> Result<Self, OptimaError> {
+        let location = OptimaAssetLocation::RobotModuleJson { robot_name: robot_name.to_string(), t: RobotModuleJsonType::ModelModule };
+        let mut asset_path = OptimaStemCellPath::new_asset_path()?;
+        asset_path.append_file_location(&location);
+        let asset_existed = asset_path.exists();
+        if let Ok(Some(load_result)) = Self::load_as_asset_or_quarantine(location.clone()) { return Ok(load_result); }
 
         let mut path_to_robot = OptimaStemCellPath::new_asset_path()?;
         path_to_robot.append_file_location(&OptimaAssetLocation::Robot {robot_name: robot_name.to_string()});
@@ -79,15 +92,46 @@ impl RobotModelModule {
         }
         let path_to_urdf = path_to_urdf_vec[0].clone();
         let urdf_robot = path_to_urdf.load_urdf()?;
-        for (i, j) in urdf_robot.joints.iter().enumerate() {
-            joint_name_to_idx_hashmap.insert(j.name.clone(), i);
-            joints.push(Joint::new(URDFJoint::new_from_urdf_joint(j), i));
-            urdf_robot_joints.push(j);
+
+        let mut urdf_links: Vec<URDFLink> = urdf_robot.links.iter().map(|l| URDFLink::new_from_urdf_link(l)).collect();
+        let mut urdf_joints: Vec<URDFJoint> = urdf_robot.joints.iter().map(|j| URDFJoint::new_from_urdf_joint(j)).collect();
+        if let Some(robot_import_options) = Self::load_robot_import_options(robot_name)? {
+            robot_import_options.apply(&mut urdf_links, &mut urdf_joints);
         }
-        for (i, l) in urdf_robot.links.iter().enumerate() {
-            link_name_to_idx_hashmap.insert(l.name.clone(), i);
-            links.push(Link::new(URDFLink::new_from_urdf_link(l), i));
-            urdf_robot_links.push(l);
+
+        let out_self = Self::new_from_urdf_links_and_joints(robot_name, urdf_links, urdf_joints);
+        // The preprocessed file could not be loaded and was quarantined, so it is regenerated here.
+        if asset_existed && !asset_path.exists() { out_self.save_as_asset(location)?; }
+
+        return Ok(out_self);
+    }
+    /// Loads the robot's `RobotImportOptions` from its import_options.json file, if it has one.
+    pub fn load_robot_import_options(robot_name: &str) -> Result<Option<RobotImportOptions>, OptimaError> {
+        let mut path = OptimaStemCellPath::new_asset_path()?;
+        path.append_file_location(&OptimaAssetLocation::RobotImportOptions { robot_name: robot_name.to_string() });
+        if !path.exists() { return Ok(None); }
+
+        let robot_import_options = RobotImportOptions::from_json_string(&path.read_file_contents_to_string()?)?;
+        return Ok(Some(robot_import_options));
+    }
+    /// Creates a new `RobotModelModule` directly from link and joint descriptions, rather than from
+    /// a URDF file in the optima_assets directory.  Links and joints keep the order that they are
+    /// given in.  This does not check that the links and joints form a valid tree; see
+    /// `RobotModelBuilder` for a checked way to build a model programmatically.
+    pub fn new_from_urdf_links_and_joints(robot_name: &str, urdf_links: Vec<URDFLink>, urdf_joints: Vec<URDFJoint>) -> Self {
+        let mut joints = vec![];
+        let mut links = vec![];
+
+        let mut link_name_to_idx_hashmap = HashMap::new();
+        let mut joint_name_to_idx_hashmap = HashMap::new();
+
+        for (i, j) in urdf_joints.into_iter().enumerate() {
+            joint_name_to_idx_hashmap.insert(j.name().to_string(), i);
+            joints.push(Joint::new(j, i));
+        }
+        for (i, l) in urdf_links.into_iter().enumerate() {
+            link_name_to_idx_hashmap.insert(l.name().to_string(), i);
+            links.push(Link::new(l, i));
         }
 
         let num_links = links.len();
@@ -112,7 +156,7 @@ impl RobotModelModule {
         out_self.set_link_tree_traversal_info();
         out_self.assign_all_link_chains();
 
-        Ok(out_self)
+        out_self
     }
     fn assign_all_link_connections_manual(&mut self) {
         let l1 = self.links.len();
@@ -121,14 +165,14 @@ impl RobotModelModule {
         for i in 0..l1 {
             for j in 0..l2 {
                 if self.links[i].name() == self.joints[j].urdf_joint().child_link() {
-                    let link_idx = self.get_link_idx_from_name( &self.joints[j].urdf_joint().parent_link().to_string() );
-                    let joint_idx = self.get_joint_idx_from_name( &self.joints[j].name().to_string() );
+                    let link_idx = self.link_name_to_idx_hashmap.get( self.joints[j].urdf_joint().parent_link() ).copied();
+                    let joint_idx = self.joint_name_to_idx_hashmap.get( self.joints[j].name() ).copied();
                     self.links[i].set_preceding_link_idx( link_idx );
                     self.links[i].set_preceding_joint_idx( joint_idx );
                 }
 
                 if self.links[i].name() == self.joints[j].urdf_joint().parent_link() {
-                    let link_idx = self.get_link_idx_from_name( &self.joints[j].urdf_joint().child_link().to_string() );
+                    let link_idx = self.link_name_to_idx_hashmap.get( self.joints[j].urdf_joint().child_link() ).copied();
                     if link_idx.is_some() { self.links[i].add_child_link_idx(link_idx.unwrap()); }
                 }
             }
@@ -138,9 +182,9 @@ impl RobotModelModule {
         let l = self.joints.len();
 
         for i in 0..l {
-            let link_idx = self.get_link_idx_from_name(  &self.joints[i].urdf_joint().parent_link().to_string()  );
+            let link_idx = self.link_name_to_idx_hashmap.get(  self.joints[i].urdf_joint().parent_link()  ).copied();
             self.joints[i].set_preceding_link_idx(link_idx);
-            let link_idx = self.get_link_idx_from_name(  &self.joints[i].urdf_joint().child_link().to_string()  );
+            let link_idx = self.link_name_to_idx_hashmap.get(  self.joints[i].urdf_joint().child_link()  ).copied();
             self.joints[i].set_child_link_idx(link_idx);
         }
     }
@@ -164,7 +208,8 @@ impl RobotModelModule {
             let preceding_link_idx = preceding_link_idx_option.unwrap();
             out_vec.insert(0, preceding_link_idx);
             if preceding_link_idx == from_idx {
-                self.link_chains.adjust_data(|x| *x = out_vec.clone(), from_idx, to_idx).expect("error");
+                let link_chain: Vec<LinkId> = out_vec.iter().map(|idx| LinkId::new(*idx)).collect();
+                self.link_chains.adjust_data(|x| *x = link_chain.clone(), from_idx, to_idx).expect("error");
                 return;
             }
         }
@@ -179,34 +224,52 @@ impl RobotModelModule {
     }
     /// Returns the link by link idx.  If the index is too high for the given link, the
     /// function will return an error.
-    pub fn get_link_by_idx(&self, idx: usize) -> Result<&Link, OptimaError> {
+    pub fn link(&self, link_id: LinkId) -> Result<&Link, OptimaError> {
+        let idx = link_id.idx();
         if idx >= self.links().len() {
             return Err(OptimaError::new_idx_out_of_bound_error(idx, self.links().len(), file!(), line!()));
         }
 
         return Ok(&self.links[idx]);
     }
+    #[deprecated(note = "use `link`, which takes a typed `LinkId`")]
+    pub fn get_link_by_idx(&self, idx: usize) -> Result<&Link, OptimaError> {
+        return self.link(LinkId::new(idx));
+    }
     /// Returns the list of robot joints.  Joints are stored in top-down order from the URDF.
     pub fn joints(&self) -> &Vec<Joint> {
         &self.joints
     }
     /// Returns the joint by link idx.  If the index is too high for the given joint, the
     /// function will return an error.
-    pub fn get_joint_by_idx(&self, idx: usize) -> Result<&Joint, OptimaError> {
+    pub fn joint(&self, joint_id: JointId) -> Result<&Joint, OptimaError> {
+        let idx = joint_id.idx();
         if idx >= self.joints().len() {
             return Err(OptimaError::new_idx_out_of_bound_error(idx, self.joints().len(), file!(), line!()));
         }
 
         return Ok(&self.joints[idx]);
     }
+    #[deprecated(note = "use `joint`, which takes a typed `JointId`")]
+    pub fn get_joint_by_idx(&self, idx: usize) -> Result<&Joint, OptimaError> {
+        return self.joint(JointId::new(idx));
+    }
     /// Returns the link index that represents the root global world as specified by the URDF.
     pub fn world_link_idx(&self) -> usize {
         self.world_link_idx
     }
+    /// Same as `world_link_idx`, but returns a typed `LinkId`.
+    pub fn world_link_id(&self) -> LinkId {
+        LinkId::new(self.world_link_idx)
+    }
     /// Returns the link index that represents the base of the robot.
     pub fn robot_base_link_idx(&self) -> usize {
         self.robot_base_link_idx
     }
+    /// Same as `robot_base_link_idx`, but returns a typed `LinkId`.
+    pub fn robot_base_link_id(&self) -> LinkId {
+        LinkId::new(self.robot_base_link_idx)
+    }
     /// Returns the link tree traversal layers.  Each list in this ordered list specifies the links
     /// that are at a given layer in the robot's hierarchy.  For instance, suppose this diagram specifies
     /// a robot's link hierarchy:
@@ -255,42 +318,43 @@ impl RobotModelModule {
     /// ```
     /// In this case, link 2 is in the "second" layer, which would return 1 from this function
     /// because it is zero-indexed.
-    pub fn get_link_tree_traversal_layer(&self, link_idx: usize) -> Result<usize, OptimaError> {
+    pub fn get_link_tree_traversal_layer(&self, link_id: LinkId) -> Result<usize, OptimaError> {
+        let link_idx = link_id.idx();
         for (i, l) in self.link_tree_traversal_layers.iter().enumerate() {
             if l.contains(&link_idx) { return Ok(i); }
         }
         return Err(OptimaError::new_generic_error_str("link_idx not found in get_link_tree_traversal_layer()", file!(), line!()));
     }
-    /// Returns the link index of the given link indices that is in the highest tree traveral layer.
-    pub fn get_link_with_highest_tree_traversal_layer(&self, link_idxs: &Vec<usize>) -> Result<usize, OptimaError> {
-        if link_idxs.len() == 1 { return Ok(link_idxs[0]); }
-        if link_idxs.len() == 0 { return Err(OptimaError::new_generic_error_str(&format!("cannot have link_idxs with length 0 in get_link_with_highest_tree_traversal_layer()"), file!(), line!())); }
+    /// Returns the link of the given links that is in the highest tree traveral layer.
+    pub fn get_link_with_highest_tree_traversal_layer(&self, link_ids: &Vec<LinkId>) -> Result<LinkId, OptimaError> {
+        if link_ids.len() == 1 { return Ok(link_ids[0]); }
+        if link_ids.len() == 0 { return Err(OptimaError::new_generic_error_str(&format!("cannot have link_ids with length 0 in get_link_with_highest_tree_traversal_layer()"), file!(), line!())); }
 
         let mut highest_layer = 0;
-        let mut highest_layer_link_idx = 0;
-        for l in link_idxs {
+        let mut highest_layer_link_id = link_ids[0];
+        for l in link_ids {
             let layer = self.get_link_tree_traversal_layer(*l)?;
             if layer >= highest_layer {
                 highest_layer = layer;
-                highest_layer_link_idx = *l;
+                highest_layer_link_id = *l;
             }
         }
-        return Ok(highest_layer_link_idx);
+        return Ok(highest_layer_link_id);
     }
-    /// Returns all links that are successors of link_idx in the kinematic chain (including link_idx itself).
-    pub fn get_all_downstream_links(&self, link_idx: usize) -> Result<Vec<usize>, OptimaError> {
-        let mut out_vec = vec![link_idx];
+    /// Returns all links that are successors of link_id in the kinematic chain (including link_id itself).
+    pub fn get_all_downstream_links(&self, link_id: LinkId) -> Result<Vec<LinkId>, OptimaError> {
+        let mut out_vec = vec![link_id];
 
-        let curr_link = self.get_link_by_idx(link_idx)?;
-        let mut stack = curr_link.children_link_idxs().clone();
+        let curr_link = self.link(link_id)?;
+        let mut stack = curr_link.children_link_ids();
 
         loop {
             if stack.is_empty() { return Ok(out_vec) }
 
             let p = stack.remove(0);
             out_vec.push(p);
-            let link = self.get_link_by_idx(p)?;
-            for c in link.children_link_idxs() { stack.push(*c); }
+            let link = self.link(p)?;
+            stack.extend(link.children_link_ids());
         }
     }
     /// Function used during setup.  It is public since other modules may need to access it,
@@ -340,17 +404,17 @@ impl RobotModelModule {
         self.preceding_actuated_joint_idxs = vec![];
         let num_links = self.links.len();
         for i in 0..num_links {
-            let res = self.get_preceding_actuated_joint_idx(i);
+            let res = self.get_preceding_actuated_joint_idx(self.links[i].link_id());
             self.preceding_actuated_joint_idxs.push(res);
         }
     }
     /// Returns the closest preceding actuated joint index (i.e., a joint that has >0 DOFs) behind the
     /// given link.
-    pub fn get_preceding_actuated_joint_idx(&self, link_idx: usize) -> Option<usize> {
+    pub fn get_preceding_actuated_joint_idx(&self, link_id: LinkId) -> Option<usize> {
         let links = &self.links;
         let joints = &self.joints;
 
-        let mut curr_link_idx = link_idx;
+        let mut curr_link_idx = link_id.idx();
 
         loop {
             let joint_idx = links[curr_link_idx].preceding_joint_idx();
@@ -396,7 +460,8 @@ impl RobotModelModule {
     }
     /// Returns all links (by index) that have the given joint index as their closest preceding
     /// actuated joint index.
-    pub fn get_all_link_idxs_with_given_preceding_actuated_joint_idx(&self, joint_idx: usize) -> Vec<usize> {
+    pub fn get_all_link_idxs_with_given_preceding_actuated_joint_idx(&self, joint_id: JointId) -> Vec<usize> {
+        let joint_idx = joint_id.idx();
         let mut out_vec = vec![];
         for (i, a) in self.preceding_actuated_joint_idxs.iter().enumerate() {
             if a.is_some() && a.unwrap() == joint_idx {
@@ -405,22 +470,30 @@ impl RobotModelModule {
         }
         out_vec
     }
-    /// Returns link index by name.  If link with given name doesn't exist, this will return an error.
-    pub fn get_link_idx_from_name(&self, link_name: &str) -> Option<usize> {
+    /// Returns link id by name.  If link with given name doesn't exist, this will return None.
+    pub fn get_link_id_from_name(&self, link_name: &str) -> Option<LinkId> {
         let res = self.link_name_to_idx_hashmap.get(link_name);
         match res {
             None => { return None }
-            Some(u) => { return Some(*u) }
+            Some(u) => { return Some(LinkId::new(*u)) }
         }
     }
-    /// Returns joint index by name.  If joint with given name doesn't exist, this will return an error.
-    pub fn get_joint_idx_from_name(&self, joint_name: &str) -> Option<usize> {
+    /// Returns joint id by name.  If joint with given name doesn't exist, this will return None.
+    pub fn get_joint_id_from_name(&self, joint_name: &str) -> Option<JointId> {
         let res = self.joint_name_to_idx_hashmap.get(joint_name);
         match res {
             None => { return None }
-            Some(u) => { return Some(*u) }
+            Some(u) => { return Some(JointId::new(*u)) }
         }
     }
+    #[deprecated(note = "use `get_link_id_from_name`, which returns a typed `LinkId`")]
+    pub fn get_link_idx_from_name(&self, link_name: &str) -> Option<usize> {
+        return self.get_link_id_from_name(link_name).map(|link_id| link_id.idx());
+    }
+    #[deprecated(note = "use `get_joint_id_from_name`, which returns a typed `JointId`")]
+    pub fn get_joint_idx_from_name(&self, joint_name: &str) -> Option<usize> {
+        return self.get_joint_id_from_name(joint_name).map(|joint_id| joint_id.idx());
+    }
     /// Prints the link tree traversal layers with link name descriptions.
     pub fn print_link_tree_traversal_layers_with_link_names(&self) {
         for i in 0..self.link_tree_max_depth {
@@ -435,7 +508,8 @@ impl RobotModelModule {
         }
     }
     /// Sets given link as not present in the model.
-    pub fn set_link_as_not_present(&mut self, link_idx: usize) -> Result<(), OptimaError> {
+    pub fn set_link_as_not_present(&mut self, link_id: LinkId) -> Result<(), OptimaError> {
+        let link_idx = link_id.idx();
         if link_idx >= self.links().len() {
             return Err(OptimaError::new_idx_out_of_bound_error(link_idx, self.links().len(), file!(), line!()));
         }
@@ -451,14 +525,15 @@ impl RobotModelModule {
             let prec_option = self.joints[i].preceding_link_idx();
             if let Some(prec) = prec_option {
                 if prec == link_idx {
-                    self.set_joint_as_not_present(i)?;
+                    self.set_joint_as_not_present(self.joints[i].joint_id())?;
                 }
             }
         }
 
         Ok(())
     }
-    pub fn set_joint_as_not_present(&mut self, joint_idx: usize) -> Result<(), OptimaError> {
+    pub fn set_joint_as_not_present(&mut self, joint_id: JointId) -> Result<(), OptimaError> {
+        let joint_idx = joint_id.idx();
         if joint_idx >= self.joints().len() {
             return Err(OptimaError::new_idx_out_of_bound_error(joint_idx, self.joints().len(), file!(), line!()));
         }
@@ -467,7 +542,8 @@ impl RobotModelModule {
 
         Ok(())
     }
-    pub fn set_link_as_present(&mut self, link_idx: usize) -> Result<(), OptimaError> {
+    pub fn set_link_as_present(&mut self, link_id: LinkId) -> Result<(), OptimaError> {
+        let link_idx = link_id.idx();
         if link_idx >= self.links().len() {
             return Err(OptimaError::new_idx_out_of_bound_error(link_idx, self.links().len(), file!(), line!()));
         }
@@ -479,14 +555,15 @@ impl RobotModelModule {
             let prec_option = self.joints[i].preceding_link_idx();
             if let Some(prec) = prec_option {
                 if prec == link_idx {
-                    self.set_joint_as_present(i)?;
+                    self.set_joint_as_present(self.joints[i].joint_id())?;
                 }
             }
         }
 
         Ok(())
     }
-    pub fn set_joint_as_present(&mut self, joint_idx: usize) -> Result<(), OptimaError> {
+    pub fn set_joint_as_present(&mut self, joint_id: JointId) -> Result<(), OptimaError> {
+        let joint_idx = joint_id.idx();
         if joint_idx >= self.joints().len() {
             return Err(OptimaError::new_idx_out_of_bound_error(joint_idx, self.joints().len(), file!(), line!()));
         }
@@ -495,14 +572,22 @@ impl RobotModelModule {
 
         Ok(())
     }
-    pub fn set_fixed_joint_sub_dof(&mut self, joint_idx: usize, joint_sub_idx: usize, fixed_value: Option<f64>) -> Result<(), OptimaError> {
+    pub fn set_fixed_joint_sub_dof(&mut self, joint_id: JointId, joint_sub_idx: usize, fixed_value: Option<f64>) -> Result<(), OptimaError> {
+        let joint_idx = joint_id.idx();
         if joint_idx >= self.joints.len() {
             return Err(OptimaError::new_idx_out_of_bound_error(joint_idx, self.joints.len(), file!(), line!()));
         }
 
         return self.joints[joint_idx].set_fixed_joint_sub_dof(joint_sub_idx, fixed_value);
     }
-    pub fn set_fixed_joint(&mut self, joint_idx: usize, fixed_value: Option<f64>) -> Result<(), OptimaError> {
+    /// Sets the linear coupling constraint of the given joint axis (see `JointCouplingInfo`).
+    pub fn set_coupled_joint_sub_dof(&mut self, joint_id: JointId, joint_sub_idx: usize, coupling: Option<JointCouplingInfo>) -> Result<(), OptimaError> {
+        let joint_idx = joint_id.idx();
+        OptimaError::new_check_for_idx_out_of_bound_error(joint_idx, self.joints.len(), file!(), line!())?;
+        return self.joints[joint_idx].set_coupled_joint_sub_dof(joint_sub_idx, coupling);
+    }
+    pub fn set_fixed_joint(&mut self, joint_id: JointId, fixed_value: Option<f64>) -> Result<(), OptimaError> {
+        let joint_idx = joint_id.idx();
         if joint_idx >= self.joints.len() {
             return Err(OptimaError::new_idx_out_of_bound_error(joint_idx, self.joints.len(), file!(), line!()));
         }
@@ -513,7 +598,36 @@ impl RobotModelModule {
         }
         Ok(())
     }
-    pub fn get_link_chain(&self, from_link_idx: usize, to_link_idx: usize) -> Result<Option<&Vec<usize>>, OptimaError> {
+    /// Sets the lower and upper bounds of all axes of the given joint.
+    pub fn set_joint_limits(&mut self, joint_id: JointId, lower: f64, upper: f64) -> Result<(), OptimaError> {
+        let joint_idx = joint_id.idx();
+        OptimaError::new_check_for_idx_out_of_bound_error(joint_idx, self.joints.len(), file!(), line!())?;
+        return self.joints[joint_idx].set_limits(lower, upper);
+    }
+    /// Sets the lower and upper bounds of a single axis of the given joint.
+    pub fn set_joint_sub_dof_limits(&mut self, joint_id: JointId, joint_sub_idx: usize, lower: f64, upper: f64) -> Result<(), OptimaError> {
+        let joint_idx = joint_id.idx();
+        OptimaError::new_check_for_idx_out_of_bound_error(joint_idx, self.joints.len(), file!(), line!())?;
+        return self.joints[joint_idx].set_joint_sub_dof_limits(joint_sub_idx, lower, upper);
+    }
+    /// Sets the axis of the given (revolute, continuous, or prismatic) joint.
+    pub fn set_joint_axis(&mut self, joint_id: JointId, axis: Vector3<f64>) -> Result<(), OptimaError> {
+        let joint_idx = joint_id.idx();
+        OptimaError::new_check_for_idx_out_of_bound_error(joint_idx, self.joints.len(), file!(), line!())?;
+        return self.joints[joint_idx].set_axis(axis);
+    }
+    /// Sets the origin offset of the given joint relative to its preceding link, given as in a URDF.
+    pub fn set_joint_origin(&mut self, joint_id: JointId, origin_xyz: Vector3<f64>, origin_rpy: Vector3<f64>) -> Result<(), OptimaError> {
+        let joint_idx = joint_id.idx();
+        OptimaError::new_check_for_idx_out_of_bound_error(joint_idx, self.joints.len(), file!(), line!())?;
+        self.joints[joint_idx].set_origin(origin_xyz, origin_rpy);
+        Ok(())
+    }
+    /// Returns the chain of links from the first link to the second link (including both), or None
+    /// if the second link is not downstream of the first link.
+    pub fn get_link_chain(&self, from_link_id: LinkId, to_link_id: LinkId) -> Result<Option<&Vec<LinkId>>, OptimaError> {
+        let from_link_idx = from_link_id.idx();
+        let to_link_idx = to_link_id.idx();
         OptimaError::new_check_for_idx_out_of_bound_error(from_link_idx, self.links.len(), file!(), line!())?;
         OptimaError::new_check_for_idx_out_of_bound_error(to_link_idx, self.links.len(), file!(), line!())?;
 
@@ -524,6 +638,153 @@ impl RobotModelModule {
             Ok(Some(res))
         }
     }
+    /// Returns a copy of this model with the kinematic tree re-rooted at the given link, e.g., to
+    /// treat a tool as the base of an inverted chain (hand-eye calibration, inverted mounting).
+    /// Joints on the path from the current world link to the new root are reversed (parent and child
+    /// links are swapped and the axis is negated), so every joint value still produces the same
+    /// relative link poses as before.  Preceding and child links, traversal layers, and link chains are
+    /// regenerated; link and joint orders, names, and limits are unchanged.
+    ///
+    /// The frame of each link on the path (other than the new root) moves to the frame of the joint
+    /// that used to connect it to the next link on the path.  The returned `RobotModelRerootOutput`
+    /// annotates every link with its frame offset, so poses of the old frames can be recovered.
+    ///
+    /// Only the URDF description is re-rooted: models with mobile base links, or with floating,
+    /// planar, or spherical joints on the path, are not supported.
+    pub fn reroot(&self, new_root_link_id: LinkId) -> Result<RobotModelRerootOutput, OptimaError> {
+        let new_root_link_idx = new_root_link_id.idx();
+        OptimaError::new_check_for_idx_out_of_bound_error(new_root_link_idx, self.links.len(), file!(), line!())?;
+        if self.links.iter().any(|l| l.is_chain_base_link()) || self.joints.iter().any(|j| j.is_chain_base_connector_joint()) {
+            return Err(OptimaError::new_generic_error_str("Cannot re-root a robot model with mobile base links.", file!(), line!()));
+        }
+
+        let mut urdf_links: Vec<URDFLink> = self.links.iter().map(|l| l.urdf_link().clone()).collect();
+        let mut urdf_joints: Vec<URDFJoint> = self.joints.iter().map(|j| j.urdf_joint().clone()).collect();
+        let mut offsets = vec![Isometry3::identity(); self.links.len()];
+
+        if new_root_link_idx != self.world_link_idx {
+            let link_chain: Vec<usize> = match self.get_link_chain(self.world_link_id(), new_root_link_id)? {
+                None => { return Err(OptimaError::new_generic_error_str(&format!("Link {} is not connected to the world link.", self.links[new_root_link_idx].name()), file!(), line!())); }
+                Some(link_chain) => { link_chain.iter().map(|link_id| link_id.idx()).collect() }
+            };
+
+            // Walk from the new root back toward the old root, reversing each joint on the way.
+            let mut reversed_joint_idxs = vec![];
+            for child_link_idx in link_chain.iter().skip(1).rev() {
+                let joint_idx = self.links[*child_link_idx].preceding_joint_idx().expect("error");
+                let urdf_joint = self.joints[joint_idx].urdf_joint();
+                match urdf_joint.joint_type() {
+                    JointTypeWrapper::Floating | JointTypeWrapper::Planar | JointTypeWrapper::Spherical => {
+                        return Err(OptimaError::new_generic_error_str(&format!("Cannot reverse joint {} on the path to the new root link ({:?} joints are not supported).", urdf_joint.name(), urdf_joint.joint_type()), file!(), line!()));
+                    }
+                    _ => { }
+                }
+                let parent_link_idx = self.joints[joint_idx].preceding_link_idx().expect("error");
+
+                let new_joint = &mut urdf_joints[joint_idx];
+                new_joint.set_parent_and_child_links(self.links[*child_link_idx].name(), self.links[parent_link_idx].name());
+                new_joint.set_origin_isometry(&offsets[*child_link_idx]);
+                new_joint.set_axis(-urdf_joint.axis());
+                offsets[parent_link_idx] = urdf_joint.origin_isometry().inverse();
+                reversed_joint_idxs.push(joint_idx);
+            }
+
+            for (joint_idx, joint) in self.joints.iter().enumerate() {
+                if reversed_joint_idxs.contains(&joint_idx) { continue; }
+                if let Some(parent_link_idx) = joint.preceding_link_idx() {
+                    if link_chain.contains(&parent_link_idx) { urdf_joints[joint_idx].premultiply_origin(&offsets[parent_link_idx]); }
+                }
+            }
+            for link_idx in &link_chain {
+                urdf_links[*link_idx].premultiply_origins(&offsets[*link_idx]);
+            }
+        }
+
+        let robot_model_module = Self::new_from_urdf_links_and_joints(&self.robot_name, urdf_links, urdf_joints);
+        let link_frame_offsets = offsets.iter().map(|o| OptimaSE3Pose::new_unit_quaternion_and_translation(o.rotation, o.translation.vector)).collect();
+
+        Ok(RobotModelRerootOutput {
+            robot_model_module,
+            link_frame_offsets
+        })
+    }
+    /// Same as `reroot`, with the new root link given by name.
+    pub fn reroot_by_link_name(&self, new_root_link_name: &str) -> Result<RobotModelRerootOutput, OptimaError> {
+        return match self.get_link_id_from_name(new_root_link_name) {
+            None => { Err(OptimaError::new_generic_error_str(&format!("Link {} does not exist in robot {}.", new_root_link_name, self.robot_name), file!(), line!())) }
+            Some(new_root_link_id) => { self.reroot(new_root_link_id) }
+        }
+    }
+    /// Returns the model as a graph with one node per link and one edge per joint (from the joint's
+    /// parent link to its child link), carrying names, joint types, axes, limits, origins, and mesh
+    /// files.  The graph is plain serializable data, so external tools (e.g., GUIs or documentation
+    /// generators) can render the robot's structure without parsing the URDF.
+    pub fn model_graph(&self) -> RobotModelGraph {
+        let mut nodes = vec![];
+        for link in &self.links {
+            let urdf_link = link.urdf_link();
+            nodes.push(RobotModelGraphLinkNode {
+                link_idx: link.link_idx(),
+                name: link.name().to_string(),
+                present: link.present(),
+                is_chain_base_link: link.is_chain_base_link(),
+                tree_traversal_layer: self.get_link_tree_traversal_layer(link.link_id()).ok(),
+                mass: urdf_link.intertial_mass(),
+                visual_mesh_filename: urdf_link.visual_mesh_filename().clone(),
+                visual_mesh_scale: urdf_link.visual_mesh_scale(),
+                collision_mesh_filename: urdf_link.collision_mesh_filename().clone(),
+                collision_mesh_scale: urdf_link.collision_mesh_scale()
+            });
+        }
+
+        let mut edges = vec![];
+        for joint in &self.joints {
+            let (parent_link_idx, child_link_idx) = match (joint.preceding_link_idx(), joint.child_link_idx()) {
+                (Some(parent_link_idx), Some(child_link_idx)) => { (parent_link_idx, child_link_idx) }
+                _ => { continue; }
+            };
+            let urdf_joint = joint.urdf_joint();
+            edges.push(RobotModelGraphJointEdge {
+                joint_idx: joint.joint_idx(),
+                name: joint.name().to_string(),
+                joint_type: urdf_joint.joint_type().clone(),
+                present: joint.present(),
+                parent_link_idx,
+                child_link_idx,
+                origin_xyz: urdf_joint.origin_xyz().clone(),
+                origin_rpy: urdf_joint.origin_rpy().clone(),
+                axes: joint.joint_axes().clone(),
+                velocity_limit: if urdf_joint.includes_limits() { Some(urdf_joint.limits_velocity()) } else { None },
+                effort_limit: if urdf_joint.includes_limits() { Some(urdf_joint.limits_effort()) } else { None }
+            });
+        }
+
+        RobotModelGraph {
+            robot_name: self.robot_name.clone(),
+            world_link_idx: self.world_link_idx,
+            nodes,
+            edges
+        }
+    }
+    /// Prints the link tree starting from the world link, showing the joint (and joint type) that
+    /// precedes each link.  Links that are not present in the model are marked.
+    pub fn print_link_joint_tree(&self) {
+        self.print_link_joint_tree_recursive(self.world_link_idx, 0);
+    }
+    fn print_link_joint_tree_recursive(&self, link_idx: usize, depth: usize) {
+        let link = &self.links[link_idx];
+        let indent = "    ".repeat(depth);
+        let joint_string = match link.preceding_joint_idx() {
+            None => { "".to_string() }
+            Some(joint_idx) => { format!("--[{} ({:?})]--> ", self.joints[joint_idx].name(), self.joints[joint_idx].urdf_joint().joint_type()) }
+        };
+        let present_string = if link.present() { "" } else { " (not present)" };
+        optima_print(&format!("{}{}", indent, joint_string), PrintMode::Print, PrintColor::None, false);
+        optima_print(&format!("{} {}{}", link_idx, link.name(), present_string), PrintMode::Println, PrintColor::Blue, true);
+        for child_link_idx in link.children_link_idxs() {
+            self.print_link_joint_tree_recursive(*child_link_idx, depth + 1);
+        }
+    }
     pub fn print_links(&self) {
         for l in self.links.iter() {
             l.print_summary();
@@ -543,15 +804,261 @@ impl RobotModelModule {
         print!("\n");
     }
 }
+
+/// The output of `RobotModelModule::reroot`.  link_frame_offsets has one entry per link: the pose of
+/// the link's original frame relative to its frame in the re-rooted model (identity for links whose
+/// frame did not move).
+#[derive(Clone, Debug)]
+pub struct RobotModelRerootOutput {
+    robot_model_module: RobotModelModule,
+    link_frame_offsets: Vec<OptimaSE3Pose>
+}
+impl RobotModelRerootOutput {
+    pub fn robot_model_module(&self) -> &RobotModelModule {
+        &self.robot_model_module
+    }
+    pub fn link_frame_offsets(&self) -> &Vec<OptimaSE3Pose> {
+        &self.link_frame_offsets
+    }
+    pub fn unwrap_robot_model_module(self) -> RobotModelModule {
+        self.robot_model_module
+    }
+}
+
+/// The structure of a `RobotModelModule` as a graph (see `RobotModelModule::model_graph`).  Nodes
+/// are links and edges are joints; both are indexed as in the model.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobotModelGraph {
+    robot_name: String,
+    world_link_idx: usize,
+    nodes: Vec<RobotModelGraphLinkNode>,
+    edges: Vec<RobotModelGraphJointEdge>
+}
+impl RobotModelGraph {
+    pub fn robot_name(&self) -> &str {
+        &self.robot_name
+    }
+    pub fn world_link_idx(&self) -> usize {
+        self.world_link_idx
+    }
+    pub fn nodes(&self) -> &Vec<RobotModelGraphLinkNode> {
+        &self.nodes
+    }
+    pub fn edges(&self) -> &Vec<RobotModelGraphJointEdge> {
+        &self.edges
+    }
+}
+
+/// A link of a `RobotModelGraph`.  tree_traversal_layer is the link's depth in the link tree (None
+/// for links that are not part of the tree).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobotModelGraphLinkNode {
+    pub link_idx: usize,
+    pub name: String,
+    pub present: bool,
+    pub is_chain_base_link: bool,
+    pub tree_traversal_layer: Option<usize>,
+    pub mass: f64,
+    pub visual_mesh_filename: Option<String>,
+    pub visual_mesh_scale: Option<Vector3<f64>>,
+    pub collision_mesh_filename: Option<String>,
+    pub collision_mesh_scale: Option<Vector3<f64>>
+}
+
+/// A joint of a `RobotModelGraph`, from its parent link to its child link.  axes holds one entry per
+/// joint sub dof, with its axis, bounds, and whether it is fixed or coupled.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobotModelGraphJointEdge {
+    pub joint_idx: usize,
+    pub name: String,
+    pub joint_type: JointTypeWrapper,
+    pub present: bool,
+    pub parent_link_idx: usize,
+    pub child_link_idx: usize,
+    pub origin_xyz: Vector3<f64>,
+    pub origin_rpy: Vector3<f64>,
+    pub axes: Vec<JointAxis>,
+    pub velocity_limit: Option<f64>,
+    pub effort_limit: Option<f64>
+}
+/// A fluent builder for a `RobotModelModule` that is constructed programmatically rather than read
+/// from a URDF, e.g., for tests or for research on synthetic kinematic structures.  Links and joints
+/// are referred to by name and keep the order in which they are added, so link and joint indices
+/// follow the same convention as models read from a URDF.  The name maps, link chains, and tree
+/// traversal layers of the output model are generated exactly as they are for URDF models.
+///
+/// A built model can be given to `RobotConfigurationModule::new_from_robot_model_module` in order
+/// to use it with the rest of the robot modules.
+///
+/// # Example
+/// ```
+/// use nalgebra::Vector3;
+/// use optima::robot_modules::robot_model_module::RobotModelBuilder;
+/// use optima::utils::utils_robot::urdf_joint::JointTypeWrapper;
+///
+/// let robot_model_module = RobotModelBuilder::new("two_link_arm")
+///     .add_link("base")
+///     .add_link("upper_arm")
+///     .add_link("forearm")
+///     .add_joint("shoulder", JointTypeWrapper::Revolute, "base", "upper_arm", Vector3::new(0.,0.,1.), Vector3::new(0.,0.,0.1), Vector3::zeros(), Some((-3.14, 3.14)))
+///     .add_joint("elbow", JointTypeWrapper::Revolute, "upper_arm", "forearm", Vector3::new(0.,1.,0.), Vector3::new(0.,0.,0.5), Vector3::zeros(), Some((-2.0, 2.0)))
+///     .build()
+///     .expect("error");
+/// ```
+#[derive(Clone, Debug)]
+pub struct RobotModelBuilder {
+    robot_name: String,
+    urdf_links: Vec<URDFLink>,
+    urdf_joints: Vec<URDFJoint>,
+    robot_import_options: Option<RobotImportOptions>
+}
+impl RobotModelBuilder {
+    pub fn new(robot_name: &str) -> Self {
+        Self {
+            robot_name: robot_name.to_string(),
+            urdf_links: vec![],
+            urdf_joints: vec![],
+            robot_import_options: None
+        }
+    }
+    /// Returns a builder for a serial chain of num_revolute_joints revolute joints, with links named
+    /// link_0, ..., link_n and joints named joint_1, ..., joint_n.  Each joint is offset by
+    /// link_length along the z axis of the preceding link, and joint axes alternate between the z and
+    /// y axes.  The builder can be extended further before calling `build`.
+    pub fn new_serial_chain(robot_name: &str, num_revolute_joints: usize, link_length: f64, joint_limits: (f64, f64)) -> Self {
+        let mut out_self = Self::new(robot_name).add_link("link_0");
+        for i in 1..=num_revolute_joints {
+            let axis = if i % 2 == 1 { Vector3::new(0., 0., 1.) } else { Vector3::new(0., 1., 0.) };
+            let parent_link = format!("link_{}", i - 1);
+            let child_link = format!("link_{}", i);
+            out_self = out_self
+                .add_link(&child_link)
+                .add_joint(&format!("joint_{}", i), JointTypeWrapper::Revolute, &parent_link, &child_link, axis, Vector3::new(0., 0., link_length), Vector3::zeros(), Some(joint_limits));
+        }
+        out_self
+    }
+    /// Adds a link with no inertial, visual, or collision information.
+    pub fn add_link(mut self, link_name: &str) -> Self {
+        self.urdf_links.push(URDFLink::new_with_name(link_name));
+        self
+    }
+    /// Adds a link from a full `URDFLink` description.
+    pub fn add_urdf_link(mut self, urdf_link: URDFLink) -> Self {
+        self.urdf_links.push(urdf_link);
+        self
+    }
+    /// Adds a joint connecting parent_link to child_link.  As in a URDF, origin_xyz and origin_rpy
+    /// specify the pose of the joint frame relative to the parent link frame, and the axis is
+    /// expressed in the joint frame.  If limits is None, the joint is treated as not including
+    /// limits.
+    pub fn add_joint(mut self, joint_name: &str, joint_type: JointTypeWrapper, parent_link: &str, child_link: &str, axis: Vector3<f64>, origin_xyz: Vector3<f64>, origin_rpy: Vector3<f64>, limits: Option<(f64, f64)>) -> Self {
+        self.urdf_joints.push(URDFJoint::new(joint_name, joint_type, parent_link, child_link, axis, origin_xyz, origin_rpy, limits));
+        self
+    }
+    /// Adds a joint from a full `URDFJoint` description.
+    pub fn add_urdf_joint(mut self, urdf_joint: URDFJoint) -> Self {
+        self.urdf_joints.push(urdf_joint);
+        self
+    }
+    /// Sets unit and axis conventions that the added links and joints are given in (see
+    /// `RobotImportOptions`).  They are converted when the model is built.
+    pub fn set_import_options(mut self, robot_import_options: RobotImportOptions) -> Self {
+        self.robot_import_options = Some(robot_import_options);
+        self
+    }
+    /// Checks that the links and joints form a single tree and outputs the resulting
+    /// `RobotModelModule`.
+    pub fn build(&self) -> Result<RobotModelModule, OptimaError> {
+        self.validate()?;
+        let mut urdf_links = self.urdf_links.clone();
+        let mut urdf_joints = self.urdf_joints.clone();
+        if let Some(robot_import_options) = &self.robot_import_options {
+            robot_import_options.apply(&mut urdf_links, &mut urdf_joints);
+        }
+        return Ok(RobotModelModule::new_from_urdf_links_and_joints(&self.robot_name, urdf_links, urdf_joints));
+    }
+    fn validate(&self) -> Result<(), OptimaError> {
+        if self.urdf_links.is_empty() {
+            return Err(OptimaError::new_generic_error_str(&format!("Robot model {} must have at least one link.", self.robot_name), file!(), line!()));
+        }
+
+        let mut link_name_to_idx = HashMap::new();
+        for (i, l) in self.urdf_links.iter().enumerate() {
+            if link_name_to_idx.insert(l.name().to_string(), i).is_some() {
+                return Err(OptimaError::new_generic_error_str(&format!("Robot model {} has more than one link named {}.", self.robot_name, l.name()), file!(), line!()));
+            }
+        }
+
+        let mut joint_names = vec![];
+        let mut parent_link_idxs: Vec<Option<usize>> = vec![None; self.urdf_links.len()];
+        for j in &self.urdf_joints {
+            if joint_names.contains(&j.name()) {
+                return Err(OptimaError::new_generic_error_str(&format!("Robot model {} has more than one joint named {}.", self.robot_name, j.name()), file!(), line!()));
+            }
+            joint_names.push(j.name());
+
+            let parent_link_idx = match link_name_to_idx.get(j.parent_link()) {
+                None => { return Err(OptimaError::new_generic_error_str(&format!("Joint {} has parent link {}, which is not in robot model {}.", j.name(), j.parent_link(), self.robot_name), file!(), line!())); }
+                Some(idx) => { *idx }
+            };
+            let child_link_idx = match link_name_to_idx.get(j.child_link()) {
+                None => { return Err(OptimaError::new_generic_error_str(&format!("Joint {} has child link {}, which is not in robot model {}.", j.name(), j.child_link(), self.robot_name), file!(), line!())); }
+                Some(idx) => { *idx }
+            };
+            if parent_link_idx == child_link_idx {
+                return Err(OptimaError::new_generic_error_str(&format!("Joint {} connects link {} to itself.", j.name(), j.parent_link()), file!(), line!()));
+            }
+            if parent_link_idxs[child_link_idx].is_some() {
+                return Err(OptimaError::new_generic_error_str(&format!("Link {} is the child link of more than one joint.", j.child_link()), file!(), line!()));
+            }
+            parent_link_idxs[child_link_idx] = Some(parent_link_idx);
+
+            match j.joint_type() {
+                JointTypeWrapper::Revolute | JointTypeWrapper::Continuous | JointTypeWrapper::Prismatic => {
+                    if j.axis().norm() == 0.0 {
+                        return Err(OptimaError::new_generic_error_str(&format!("Joint {} must have a non-zero axis.", j.name()), file!(), line!()));
+                    }
+                }
+                JointTypeWrapper::Planar => {
+                    return Err(OptimaError::new_generic_error_str(&format!("Joint {} is a planar joint, which is not supported.", j.name()), file!(), line!()));
+                }
+                _ => { }
+            }
+            if j.includes_limits() && j.limits_lower() > j.limits_upper() {
+                return Err(OptimaError::new_generic_error_str(&format!("Joint {} has a lower limit ({}) that is greater than its upper limit ({}).", j.name(), j.limits_lower(), j.limits_upper()), file!(), line!()));
+            }
+        }
+
+        let num_roots = parent_link_idxs.iter().filter(|p| p.is_none()).count();
+        if num_roots != 1 {
+            return Err(OptimaError::new_generic_error_str(&format!("Robot model {} must have exactly one root link (i.e., a link that is not the child of any joint), but it has {}.", self.robot_name, num_roots), file!(), line!()));
+        }
+
+        // With a single root and at most one parent per link, every link reaches the root unless the
+        // joints form a cycle.
+        let num_links = self.urdf_links.len();
+        for link_idx in 0..num_links {
+            let mut curr_link_idx = link_idx;
+            let mut num_steps = 0;
+            while let Some(parent_link_idx) = parent_link_idxs[curr_link_idx] {
+                curr_link_idx = parent_link_idx;
+                num_steps += 1;
+                if num_steps > num_links {
+                    return Err(OptimaError::new_generic_error_str(&format!("The joints of robot model {} form a cycle that includes link {}.", self.robot_name, self.urdf_links[link_idx].name()), file!(), line!()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 impl SaveAndLoadable for RobotModelModule {
     type SaveType = Self;
 
     fn get_save_serialization_object(&self) -> Self::SaveType { self.clone() }
-    fn load_from_path(path: &OptimaStemCellPath) -> Result<Self, OptimaError> {
-        return path.load_object_from_json_file();
-    }
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let load: Self::SaveType = load_object_from_json_string(json_str)?;
+        let load: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
         return Ok(load);
     }
 }
@@ -581,6 +1088,12 @@ impl RobotModelModule {
     pub fn link_tree_traversal_layers_py(&self) -> Vec<Vec<usize>> {
         self.link_tree_traversal_layers.clone()
     }
+    pub fn reroot_py(&self, new_root_link_name: &str) -> RobotModelModule {
+        self.reroot_by_link_name(new_root_link_name).expect("error").unwrap_robot_model_module()
+    }
+    pub fn model_graph_json_string_py(&self) -> String {
+        self.model_graph().to_json_string()
+    }
 }
 
 /// Methods supported by WASM.
@@ -604,7 +1117,12 @@ impl RobotModelModule {
     pub fn print_link_tree_traversal_layers_with_link_names_wasm(&self) {
         self.print_link_tree_traversal_layers_with_link_names()
     }
+    pub fn model_graph_json_string_wasm(&self) -> String {
+        self.model_graph().to_json_string()
+    }
 }
 
 
 
+
+crate::impl_py_pickle!(RobotModelModule);
@@ -0,0 +1,220 @@
+use js_sys::{Function, Promise, Reflect};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use web_sys::{Event, IdbDatabase, IdbFactory, IdbKeyRange, IdbObjectStore, IdbOpenDbRequest, IdbRequest, IdbTransactionMode};
+use crate::robot_modules::robot_configuration_module::{RobotConfigurationInfo, RobotConfigurationModule};
+use crate::robot_modules::robot_geometric_shape_module::RobotGeometricShapeModule;
+use crate::robot_modules::robot_kinematics_module::RobotKinematicsModule;
+use crate::robot_modules::robot_module_cache::RobotModuleCache;
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_files::optima_path::{OptimaAssetLocation, OptimaStemCellPath, RobotModuleJsonType};
+use crate::utils::utils_robot::robot_module_utils::RobotNames;
+use crate::utils::utils_traits::{SaveAndLoadable, ToAndFromRonString};
+
+const ROBOT_MODULE_BROWSER_CACHE_DB_NAME: &'static str = "optima_robot_module_cache";
+const ROBOT_MODULE_BROWSER_CACHE_DB_VERSION: u32 = 1;
+const ROBOT_MODULE_BROWSER_CACHE_STORE_NAME: &'static str = "robot_modules";
+
+/// A browser-side cache of serialized robot modules, backed by IndexedDB.  On the web, building a
+/// `RobotGeometricShapeModule` (or even a `RobotConfigurationModule`) from assets on every page load
+/// is prohibitively slow, so this stores the serialized modules once and restores them on later
+/// loads.
+///
+/// Entries are keyed by module type, robot name, configuration name, and a content hash of everything
+/// the module is built from: the robot's URDF and preprocessed model (see
+/// `RobotModuleCache::robot_content_hash`), the configuration resolved against its parent chain, and,
+/// for geometric shape modules, the robot's preprocessed shape data.  A stale entry is therefore never
+/// restored after any of these change.  Because the hash depends on the build, upgrading Optima may
+/// also cause entries to be rebuilt once.  When an entry is rebuilt, the entries it supersedes (same
+/// module type, robot, and configuration, but a different hash) are deleted.
+///
+/// Works on the main thread as well as in web workers (anywhere `indexedDB` is in the global scope).
+#[wasm_bindgen]
+pub struct RobotModuleBrowserCache {
+    db: IdbDatabase
+}
+impl RobotModuleBrowserCache {
+    pub async fn open() -> Result<Self, OptimaError> {
+        let factory: IdbFactory = Reflect::get(&js_sys::global(), &JsValue::from_str("indexedDB"))
+            .ok()
+            .and_then(|f| f.dyn_into::<IdbFactory>().ok())
+            .ok_or_else(|| OptimaError::new_generic_error_str("IndexedDB is not available in this environment.", file!(), line!()))?;
+
+        let open_request: IdbOpenDbRequest = factory.open_with_u32(ROBOT_MODULE_BROWSER_CACHE_DB_NAME, ROBOT_MODULE_BROWSER_CACHE_DB_VERSION).map_err(|e| Self::idb_error(e, file!(), line!()))?;
+
+        let upgrade_request = open_request.clone();
+        let on_upgrade_needed = Closure::once_into_js(move |_e: Event| {
+            if let Ok(db) = upgrade_request.result() {
+                let db: IdbDatabase = db.unchecked_into();
+                if !db.object_store_names().contains(ROBOT_MODULE_BROWSER_CACHE_STORE_NAME) {
+                    let _ = db.create_object_store(ROBOT_MODULE_BROWSER_CACHE_STORE_NAME);
+                }
+            }
+        });
+        open_request.set_onupgradeneeded(Some(on_upgrade_needed.unchecked_ref()));
+
+        let db = Self::await_request(&open_request).await?;
+
+        Ok(Self {
+            db: db.unchecked_into()
+        })
+    }
+    /// Returns the module stored under the given key, or builds it with `f` and stores it.  If the
+    /// stored entry can no longer be deserialized, it is rebuilt and overwritten.
+    pub async fn get_or_insert_with<T: SaveAndLoadable, F: FnOnce() -> Result<T, OptimaError>>(&self, key: &str, f: F) -> Result<T, OptimaError> {
+        if let Some(s) = self.get(key).await? {
+            if let Ok(t) = T::load_from_json_string(&s) { return Ok(t); }
+        }
+
+        let t = f()?;
+        self.put(key, &t.get_serialization_string()).await?;
+        Ok(t)
+    }
+    pub async fn get_or_load_robot_configuration_module(&self, robot_names: RobotNames<'_>) -> Result<RobotConfigurationModule, OptimaError> {
+        let (key_prefix, key) = Self::key("RobotConfigurationModule", &robot_names, false)?;
+        return self.get_or_insert_with_superseding(&key_prefix, &key, move || RobotConfigurationModule::new_from_names(robot_names)).await;
+    }
+    pub async fn get_or_load_robot_kinematics_module(&self, robot_names: RobotNames<'_>) -> Result<RobotKinematicsModule, OptimaError> {
+        let (key_prefix, key) = Self::key("RobotKinematicsModule", &robot_names, false)?;
+        return self.get_or_insert_with_superseding(&key_prefix, &key, move || RobotKinematicsModule::new_from_names(robot_names)).await;
+    }
+    pub async fn get_or_load_robot_geometric_shape_module(&self, robot_names: RobotNames<'_>) -> Result<RobotGeometricShapeModule, OptimaError> {
+        let (key_prefix, key) = Self::key("RobotGeometricShapeModule", &robot_names, true)?;
+        return self.get_or_insert_with_superseding(&key_prefix, &key, move || RobotGeometricShapeModule::new_from_names(robot_names, false)).await;
+    }
+    /// Returns the serialized string stored under the given key, if any.
+    pub async fn get(&self, key: &str) -> Result<Option<String>, OptimaError> {
+        let store = self.object_store(IdbTransactionMode::Readonly)?;
+        let request = store.get(&JsValue::from_str(key)).map_err(|e| Self::idb_error(e, file!(), line!()))?;
+        let res = Self::await_request(&request).await?;
+        return Ok(res.as_string());
+    }
+    pub async fn put(&self, key: &str, value: &str) -> Result<(), OptimaError> {
+        let store = self.object_store(IdbTransactionMode::Readwrite)?;
+        let request = store.put_with_key(&JsValue::from_str(value), &JsValue::from_str(key)).map_err(|e| Self::idb_error(e, file!(), line!()))?;
+        Self::await_request(&request).await?;
+        Ok(())
+    }
+    pub async fn remove(&self, key: &str) -> Result<(), OptimaError> {
+        let store = self.object_store(IdbTransactionMode::Readwrite)?;
+        let request = store.delete(&JsValue::from_str(key)).map_err(|e| Self::idb_error(e, file!(), line!()))?;
+        Self::await_request(&request).await?;
+        Ok(())
+    }
+    /// Removes all entries whose key starts with the given prefix.
+    pub async fn remove_with_prefix(&self, key_prefix: &str) -> Result<(), OptimaError> {
+        let upper = format!("{}\u{ffff}", key_prefix);
+        let key_range = IdbKeyRange::bound(&JsValue::from_str(key_prefix), &JsValue::from_str(&upper)).map_err(|e| Self::idb_error(e, file!(), line!()))?;
+        let store = self.object_store(IdbTransactionMode::Readwrite)?;
+        let request = store.delete(&key_range).map_err(|e| Self::idb_error(e, file!(), line!()))?;
+        Self::await_request(&request).await?;
+        Ok(())
+    }
+    /// Removes all cached modules.
+    pub async fn clear(&self) -> Result<(), OptimaError> {
+        let store = self.object_store(IdbTransactionMode::Readwrite)?;
+        let request = store.clear().map_err(|e| Self::idb_error(e, file!(), line!()))?;
+        Self::await_request(&request).await?;
+        Ok(())
+    }
+    /// Same as `get_or_insert_with`, but when the module has to be built, first removes all entries
+    /// under the given key prefix (i.e., entries superseded by the new key).
+    async fn get_or_insert_with_superseding<T: SaveAndLoadable, F: FnOnce() -> Result<T, OptimaError>>(&self, key_prefix: &str, key: &str, f: F) -> Result<T, OptimaError> {
+        if let Some(s) = self.get(key).await? {
+            if let Ok(t) = T::load_from_json_string(&s) { return Ok(t); }
+        }
+
+        let t = f()?;
+        self.remove_with_prefix(key_prefix).await?;
+        self.put(key, &t.get_serialization_string()).await?;
+        Ok(t)
+    }
+    /// Returns the (key prefix, key) of the given module.  The key prefix identifies the module type,
+    /// robot, and configuration, and the key appends the content hash to it.
+    fn key(module_type: &str, robot_names: &RobotNames, include_shape_geometry: bool) -> Result<(String, String), OptimaError> {
+        let robot_name = robot_names.robot_name();
+        let mut hasher = DefaultHasher::new();
+        RobotModuleCache::robot_content_hash(robot_name)?.hash(&mut hasher);
+
+        let configuration_name = match robot_names.configuration_name() {
+            None => { "base" }
+            Some(c) => {
+                RobotConfigurationInfo::load_resolved(robot_name, c)?.to_ron_string().hash(&mut hasher);
+                c
+            }
+        };
+
+        if include_shape_geometry {
+            let mut path = OptimaStemCellPath::new_asset_path()?;
+            path.append_file_location(&OptimaAssetLocation::RobotModuleJson { robot_name: robot_name.to_string(), t: RobotModuleJsonType::ShapeGeometryModule });
+            if path.exists() {
+                path.read_file_contents_to_string()?.hash(&mut hasher);
+            }
+        }
+
+        let key_prefix = format!("{}::{}::{}::", module_type, robot_name, configuration_name);
+        let key = format!("{}{:016x}", key_prefix, hasher.finish());
+        return Ok((key_prefix, key));
+    }
+    fn object_store(&self, mode: IdbTransactionMode) -> Result<IdbObjectStore, OptimaError> {
+        let transaction = self.db.transaction_with_str_and_mode(ROBOT_MODULE_BROWSER_CACHE_STORE_NAME, mode).map_err(|e| Self::idb_error(e, file!(), line!()))?;
+        return transaction.object_store(ROBOT_MODULE_BROWSER_CACHE_STORE_NAME).map_err(|e| Self::idb_error(e, file!(), line!()));
+    }
+    /// Resolves once the request succeeds, returning its result.
+    async fn await_request(request: &IdbRequest) -> Result<JsValue, OptimaError> {
+        let promise = Promise::new(&mut |resolve: Function, reject: Function| {
+            let success_request = request.clone();
+            let on_success = Closure::once_into_js(move |_e: Event| {
+                let res = success_request.result().unwrap_or(JsValue::UNDEFINED);
+                let _ = resolve.call1(&JsValue::NULL, &res);
+            });
+            let error_request = request.clone();
+            let on_error = Closure::once_into_js(move |_e: Event| {
+                let err = match error_request.error() {
+                    Ok(Some(e)) => { JsValue::from(e) }
+                    _ => { JsValue::from_str("unknown IndexedDB error") }
+                };
+                let _ = reject.call1(&JsValue::NULL, &err);
+            });
+            request.set_onsuccess(Some(on_success.unchecked_ref()));
+            request.set_onerror(Some(on_error.unchecked_ref()));
+        });
+
+        return JsFuture::from(promise).await.map_err(|e| Self::idb_error(e, file!(), line!()));
+    }
+    fn idb_error(e: JsValue, file: &str, line: u32) -> OptimaError {
+        return OptimaError::new_generic_error_str(&format!("IndexedDB error: {:?}", e), file, line);
+    }
+}
+
+/// WASM implementations.  Each function opens the database, so no handle needs to be kept around on
+/// the JS side.
+#[wasm_bindgen]
+impl RobotModuleBrowserCache {
+    pub async fn load_robot_configuration_module_wasm(robot_name: String, configuration_name: Option<String>) -> Result<RobotConfigurationModule, JsValue> {
+        let cache = Self::open().await.map_err(Self::to_js_error)?;
+        let robot_names = RobotNames::new(&robot_name, configuration_name.as_deref());
+        return cache.get_or_load_robot_configuration_module(robot_names).await.map_err(Self::to_js_error);
+    }
+    pub async fn load_robot_kinematics_module_wasm(robot_name: String, configuration_name: Option<String>) -> Result<RobotKinematicsModule, JsValue> {
+        let cache = Self::open().await.map_err(Self::to_js_error)?;
+        let robot_names = RobotNames::new(&robot_name, configuration_name.as_deref());
+        return cache.get_or_load_robot_kinematics_module(robot_names).await.map_err(Self::to_js_error);
+    }
+    pub async fn load_robot_geometric_shape_module_wasm(robot_name: String, configuration_name: Option<String>) -> Result<RobotGeometricShapeModule, JsValue> {
+        let cache = Self::open().await.map_err(Self::to_js_error)?;
+        let robot_names = RobotNames::new(&robot_name, configuration_name.as_deref());
+        return cache.get_or_load_robot_geometric_shape_module(robot_names).await.map_err(Self::to_js_error);
+    }
+    pub async fn clear_wasm() -> Result<(), JsValue> {
+        let cache = Self::open().await.map_err(Self::to_js_error)?;
+        return cache.clear().await.map_err(Self::to_js_error);
+    }
+}
+impl RobotModuleBrowserCache {
+    fn to_js_error(e: OptimaError) -> JsValue {
+        return JsValue::from_str(&format!("{:?}", e));
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use crate::robot_modules::robot_configuration_module::{RobotConfigurationFileFormat, RobotConfigurationInfo, RobotConfigurationModule};
+use crate::robot_modules::robot_model_module::RobotModelModule;
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_files::optima_path::{OptimaAssetLocation, OptimaPathMatchingPattern, OptimaPathMatchingStopCondition, OptimaStemCellPath, RobotModuleJsonType};
+use crate::utils::utils_generic_data_structures::SharedHandle;
+use crate::utils::utils_traits::ToAndFromRonString;
+
+static ROBOT_MODULE_CACHE: Mutex<Option<RobotModuleCacheData>> = Mutex::new(None);
+
+/// The default maximum number of entries held in each of the cache's two maps.
+pub const ROBOT_MODULE_CACHE_DEFAULT_MAX_ENTRIES: usize = 16;
+
+/// A process-wide cache of constructed `RobotModelModule` and `RobotConfigurationModule` objects.
+/// Constructing these modules requires locating and parsing the robot's URDF (or loading its
+/// preprocessed JSON) and resolving the configuration, which adds up when modules are constructed
+/// many times in one process (e.g., each time a robot is spawned from Python).
+///
+/// Model modules are keyed by robot name, and configuration modules by robot name and configuration
+/// info.  Each entry also records the input files it was built from: the robot's URDF, import options,
+/// and preprocessed model module file, plus, for configuration modules, the file of every configuration
+/// in the info's parent chain.  The resolved configuration is a function of the info and these parent
+/// files, so an entry is never reused once any of them changes on disk.  Lookups only compare file
+/// fingerprints (see `OptimaPath::file_fingerprint`) and never re-read file contents.
+///
+/// Each map holds at most `max_entries` entries; the least recently used entry is evicted first.
+/// `RobotModelModule::new_shared` and `RobotConfigurationModule::new_shared_from_robot_name_and_info`
+/// return the cached handles directly, while `RobotModelModule::new` and
+/// `RobotConfigurationModule::new_from_robot_name_and_info` return an owned copy.  Use
+/// `set_enabled(false)` to opt out.
+pub struct RobotModuleCache;
+impl RobotModuleCache {
+    pub fn get_or_load_robot_model_module(robot_name: &str) -> Result<SharedHandle<RobotModelModule>, OptimaError> {
+        if !Self::enabled() { return Ok(SharedHandle::new(RobotModelModule::new_uncached(robot_name)?)); }
+
+        let key = robot_name.to_string();
+        if let Some(handle) = Self::with_data(|data| data.robot_model_modules.get_if_current(&key)) {
+            return Ok(handle);
+        }
+
+        let handle = SharedHandle::new(RobotModelModule::new_uncached(robot_name)?);
+        let input_files = Self::robot_model_input_files(robot_name)?;
+        Self::with_data(|data| {
+            let max_entries = data.max_entries;
+            data.robot_model_modules.insert(key, handle.clone(), input_files, max_entries);
+        });
+        Ok(handle)
+    }
+    pub fn get_or_load_robot_configuration_module(robot_name: &str, robot_configuration_info: &RobotConfigurationInfo) -> Result<SharedHandle<RobotConfigurationModule>, OptimaError> {
+        if !Self::enabled() { return Ok(SharedHandle::new(RobotConfigurationModule::new_from_robot_name_and_info_uncached(robot_name, robot_configuration_info.clone())?)); }
+
+        let key = (robot_name.to_string(), robot_configuration_info.to_ron_string());
+        if let Some(handle) = Self::with_data(|data| data.robot_configuration_modules.get_if_current(&key)) {
+            return Ok(handle);
+        }
+
+        let handle = SharedHandle::new(RobotConfigurationModule::new_from_robot_name_and_info_uncached(robot_name, robot_configuration_info.clone())?);
+        let mut input_files = Self::robot_model_input_files(robot_name)?;
+        input_files.extend(Self::parent_configuration_input_files(robot_name, robot_configuration_info)?);
+        Self::with_data(|data| {
+            let max_entries = data.max_entries;
+            data.robot_configuration_modules.insert(key, handle.clone(), input_files, max_entries);
+        });
+        Ok(handle)
+    }
+    /// Removes all cached modules.  Handles that were already given out remain valid.
+    pub fn clear() {
+        Self::with_data(|data| {
+            data.robot_model_modules.clear();
+            data.robot_configuration_modules.clear();
+        });
+    }
+    /// Returns the number of cached (model modules, configuration modules).
+    pub fn num_entries() -> (usize, usize) {
+        return Self::with_data(|data| (data.robot_model_modules.len(), data.robot_configuration_modules.len()));
+    }
+    pub fn set_enabled(enabled: bool) {
+        Self::with_data(|data| { data.enabled = enabled; });
+    }
+    pub fn enabled() -> bool {
+        return Self::with_data(|data| data.enabled);
+    }
+    /// Sets the maximum number of entries held in each of the cache's two maps, evicting the least
+    /// recently used entries if there are currently more.  A value of 0 is treated as 1.
+    pub fn set_max_entries(max_entries: usize) {
+        Self::with_data(|data| {
+            data.max_entries = max_entries.max(1);
+            let max_entries = data.max_entries;
+            data.robot_model_modules.evict_to(max_entries);
+            data.robot_configuration_modules.evict_to(max_entries);
+        });
+    }
+    pub fn max_entries() -> usize {
+        return Self::with_data(|data| data.max_entries);
+    }
+    /// Hash of the robot's URDF file contents, its import options (if it has any), and its preprocessed
+    /// model module JSON (if it exists).
+    pub fn robot_content_hash(robot_name: &str) -> Result<u64, OptimaError> {
+        let mut hasher = DefaultHasher::new();
+
+        for (path, _) in Self::robot_model_input_files(robot_name)? {
+            if let Some(path) = path {
+                if path.exists() { path.read_file_contents_to_string()?.hash(&mut hasher); }
+            }
+        }
+
+        Ok(hasher.finish())
+    }
+    /// The robot's URDF, import options, and preprocessed model module JSON, each with its current
+    /// fingerprint.  Files that do not exist are still listed (with a None fingerprint) so that an
+    /// entry is invalidated once they are created.  The URDF's path is None if the robot has no URDF.
+    fn robot_model_input_files(robot_name: &str) -> Result<Vec<(Option<OptimaStemCellPath>, Option<String>)>, OptimaError> {
+        let mut out_vec = vec![];
+
+        let mut path_to_robot = OptimaStemCellPath::new_asset_path()?;
+        path_to_robot.append_file_location(&OptimaAssetLocation::Robot { robot_name: robot_name.to_string() });
+        if !path_to_robot.exists() {
+            return Err(OptimaError::new_generic_error_str(&format!("Robot directory for robot {} does not exist.", robot_name), file!(), line!()));
+        }
+        let path_to_urdf_vec = path_to_robot.walk_directory_and_match(OptimaPathMatchingPattern::Extension("urdf".to_string()), OptimaPathMatchingStopCondition::First);
+        match path_to_urdf_vec.first() {
+            None => { out_vec.push((None, None)); }
+            Some(path_to_urdf) => {
+                let path_to_urdf = OptimaStemCellPath::new_from_optima_path(path_to_urdf.clone());
+                let fingerprint = path_to_urdf.file_fingerprint();
+                out_vec.push((Some(path_to_urdf), fingerprint));
+            }
+        }
+
+        let mut path_to_import_options = OptimaStemCellPath::new_asset_path()?;
+        path_to_import_options.append_file_location(&OptimaAssetLocation::RobotImportOptions { robot_name: robot_name.to_string() });
+        let fingerprint = path_to_import_options.file_fingerprint();
+        out_vec.push((Some(path_to_import_options), fingerprint));
+
+        let mut path_to_model_module_json = OptimaStemCellPath::new_asset_path()?;
+        path_to_model_module_json.append_file_location(&OptimaAssetLocation::RobotModuleJson { robot_name: robot_name.to_string(), t: RobotModuleJsonType::ModelModule });
+        let fingerprint = path_to_model_module_json.file_fingerprint();
+        out_vec.push((Some(path_to_model_module_json), fingerprint));
+
+        Ok(out_vec)
+    }
+    /// The file of every configuration in the given info's parent chain, each with its current
+    /// fingerprint.
+    fn parent_configuration_input_files(robot_name: &str, robot_configuration_info: &RobotConfigurationInfo) -> Result<Vec<(Option<OptimaStemCellPath>, Option<String>)>, OptimaError> {
+        let mut out_vec = vec![];
+
+        let mut visited_configuration_names: Vec<String> = vec![];
+        let mut parent_configuration_name = robot_configuration_info.parent_configuration_name().clone();
+        while let Some(configuration_name) = parent_configuration_name {
+            if visited_configuration_names.contains(&configuration_name) { break; }
+            let found = RobotConfigurationFileFormat::find_configuration_file(robot_name, &configuration_name)?;
+            match found {
+                None => { break; }
+                Some((path, _)) => {
+                    let fingerprint = path.file_fingerprint();
+                    out_vec.push((Some(path), fingerprint));
+                }
+            }
+            parent_configuration_name = RobotConfigurationInfo::load_unresolved(robot_name, &configuration_name)?.parent_configuration_name().clone();
+            visited_configuration_names.push(configuration_name);
+        }
+
+        Ok(out_vec)
+    }
+    fn with_data<R, F: FnOnce(&mut RobotModuleCacheData) -> R>(f: F) -> R {
+        let mut guard = match ROBOT_MODULE_CACHE.lock() {
+            Ok(guard) => { guard }
+            Err(poisoned) => { poisoned.into_inner() }
+        };
+        let data = guard.get_or_insert_with(RobotModuleCacheData::new);
+        return f(data);
+    }
+}
+
+struct RobotModuleCacheData {
+    enabled: bool,
+    max_entries: usize,
+    robot_model_modules: RobotModuleCacheMap<String, RobotModelModule>,
+    robot_configuration_modules: RobotModuleCacheMap<(String, String), RobotConfigurationModule>
+}
+impl RobotModuleCacheData {
+    fn new() -> Self {
+        Self {
+            enabled: true,
+            max_entries: ROBOT_MODULE_CACHE_DEFAULT_MAX_ENTRIES,
+            robot_model_modules: RobotModuleCacheMap::new(),
+            robot_configuration_modules: RobotModuleCacheMap::new()
+        }
+    }
+}
+
+struct RobotModuleCacheEntry<T> {
+    handle: SharedHandle<T>,
+    input_files: Vec<(Option<OptimaStemCellPath>, Option<String>)>,
+    last_used: u64
+}
+
+struct RobotModuleCacheMap<K, T> {
+    entries: HashMap<K, RobotModuleCacheEntry<T>>,
+    clock: u64
+}
+impl <K, T> RobotModuleCacheMap<K, T> where K: Hash + Eq + Clone {
+    fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            clock: 0
+        }
+    }
+    /// Returns the entry's handle if none of its input files changed since it was inserted.  Stale
+    /// entries are removed.
+    fn get_if_current(&mut self, key: &K) -> Option<SharedHandle<T>> {
+        let current = match self.entries.get(key) {
+            None => { return None; }
+            Some(entry) => {
+                entry.input_files.iter().all(|(path, fingerprint)| {
+                    let current_fingerprint = match path {
+                        None => { None }
+                        Some(path) => { path.file_fingerprint() }
+                    };
+                    &current_fingerprint == fingerprint
+                })
+            }
+        };
+        if !current {
+            self.entries.remove(key);
+            return None;
+        }
+
+        self.clock += 1;
+        let entry = self.entries.get_mut(key).unwrap();
+        entry.last_used = self.clock;
+        return Some(entry.handle.clone());
+    }
+    fn insert(&mut self, key: K, handle: SharedHandle<T>, input_files: Vec<(Option<OptimaStemCellPath>, Option<String>)>, max_entries: usize) {
+        self.clock += 1;
+        self.entries.insert(key, RobotModuleCacheEntry {
+            handle,
+            input_files,
+            last_used: self.clock
+        });
+        self.evict_to(max_entries);
+    }
+    fn evict_to(&mut self, max_entries: usize) {
+        while self.entries.len() > max_entries {
+            let least_recently_used = self.entries.iter().min_by_key(|(_, entry)| entry.last_used).map(|(key, _)| key.clone());
+            match least_recently_used {
+                None => { return; }
+                Some(key) => { self.entries.remove(&key); }
+            }
+        }
+    }
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
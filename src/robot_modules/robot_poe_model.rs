@@ -0,0 +1,233 @@
+#[cfg(not(target_arch = "wasm32"))]
+use pyo3::*;
+
+use nalgebra::{DVector, Isometry3, Matrix3, Translation3, Unit, UnitQuaternion, Vector3, Vector6};
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_joint_state_module::RobotJointStateType;
+use crate::robot_modules::robot_kinematics_module::RobotKinematicsModule;
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_robot::joint::JointAxisPrimitiveType;
+use crate::utils::utils_robot::robot_ids::{DofIdx, LinkId};
+use crate::utils::utils_se3::optima_se3_pose::OptimaSE3PoseType;
+#[cfg(not(target_arch = "wasm32"))]
+use nalgebra::DMatrix;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils::utils_nalgebra::conversions::NalgebraConversions;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils::utils_traits::ToAndFromJsonString;
+
+/// The product of exponentials (POE) model of a kinematic chain, from a start link to an end link
+/// that descends from it:
+///
+/// T(theta) = exp(\[S_1\] theta_1) * ... * exp(\[S_n\] theta_n) * M
+///
+/// where M is the pose of the end link in the start link's frame at the home configuration (all DOFs
+/// at zero, fixed axes at their fixed values) and S_i is the screw axis of the i-th DOF along the
+/// chain, expressed in the start link's frame at the home configuration.  Screw axes are 6-vectors
+/// \[omega; v\]: for a revolute axis, omega is the unit rotation axis and v = -omega x q for a point
+/// q on the axis; for a prismatic axis, omega is zero and v is the unit translation direction.  This
+/// is the convention of Lynch and Park's *Modern Robotics*, so the model can be used directly by
+/// custom controllers or Lie-theoretic tools.
+///
+/// `dof_idxs` gives the index in the robot's DOF joint state of each screw axis.  DOFs that are not
+/// on the chain do not appear.  Coupled joint axes and ball joints have no fixed screw axis, so
+/// chains through them are not supported.
+#[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
+#[cfg_attr(target_arch = "wasm32", derive(Clone, Debug, Serialize, Deserialize))]
+pub struct ProductOfExponentialsModel {
+    robot_name: String,
+    start_link_id: LinkId,
+    end_link_id: LinkId,
+    home_configuration: Isometry3<f64>,
+    space_screw_axes: Vec<Vector6<f64>>,
+    dof_idxs: Vec<DofIdx>
+}
+impl ProductOfExponentialsModel {
+    /// If start_link_id is None, the chain starts at the world link, so the screw axes and home
+    /// configuration are expressed in the world frame (including the robot's base offset).
+    pub fn new_from_robot_kinematics_module(robot_kinematics_module: &RobotKinematicsModule, start_link_id: Option<LinkId>, end_link_id: LinkId) -> Result<Self, OptimaError> {
+        let robot_model_module = robot_kinematics_module.robot_configuration_module().robot_model_module();
+        let robot_joint_state_module = robot_kinematics_module.robot_joint_state_module();
+        let links = robot_model_module.links();
+        let joints = robot_model_module.joints();
+
+        let start_link_id = match start_link_id {
+            None => { robot_model_module.world_link_id() }
+            Some(start_link_id) => { start_link_id }
+        };
+        OptimaError::new_check_for_idx_out_of_bound_error(start_link_id.idx(), links.len(), file!(), line!())?;
+        OptimaError::new_check_for_idx_out_of_bound_error(end_link_id.idx(), links.len(), file!(), line!())?;
+
+        let chain = if start_link_id == end_link_id { vec![start_link_id] } else {
+            match robot_model_module.get_link_chain(start_link_id, end_link_id)? {
+                None => { return Err(OptimaError::new_generic_error_str(&format!("Link {} is not a descendant of link {}, so there is no chain between them.", end_link_id.idx(), start_link_id.idx()), file!(), line!())); }
+                Some(chain) => { chain.clone() }
+            }
+        };
+        for link_id in &chain {
+            if !links[link_id.idx()].present() {
+                return Err(OptimaError::new_generic_error_str(&format!("Link {} on the chain is not present in the robot configuration.", link_id.idx()), file!(), line!()));
+            }
+        }
+
+        let home_state = robot_joint_state_module.spawn_zeros_robot_joint_state(RobotJointStateType::DOF);
+        let fk_res = robot_kinematics_module.compute_fk(&home_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+        let link_pose = |link_id: LinkId| -> Result<Isometry3<f64>, OptimaError> {
+            return match fk_res.link_pose(link_id) {
+                None => { Err(OptimaError::new_generic_error_str(&format!("Link {} has no pose at the home configuration.", link_id.idx()), file!(), line!())) }
+                Some(pose) => { Ok(pose.to_nalgebra_isometry()) }
+            };
+        };
+        let start_pose_inverse = link_pose(start_link_id)?.inverse();
+
+        let mut space_screw_axes = vec![];
+        let mut dof_idxs = vec![];
+        for i in 1..chain.len() {
+            let joint_idx = match links[chain[i].idx()].preceding_joint_idx() {
+                None => { continue; }
+                Some(joint_idx) => { joint_idx }
+            };
+            let joint = &joints[joint_idx];
+
+            if joint.is_joint_with_all_standard_axes() {
+                let num_nonzero = joint.joint_axes().iter().filter(|a| a.fixed_value().unwrap_or(0.0) != 0.0).count();
+                if num_nonzero > 1 {
+                    return Err(OptimaError::new_unsupported_operation_error("new_from_robot_kinematics_module", &format!("Joint {} combines several non-zero fixed axis values, so its axes have no fixed order.", joint.name()), file!(), line!()));
+                }
+            }
+
+            // Axes of a joint are applied one after the other, each at its home value.
+            let mut axis_frame = start_pose_inverse * link_pose(chain[i - 1])? * joint.origin_offset_pose().get_pose_by_type(&OptimaSE3PoseType::ImplicitDualQuaternion).to_nalgebra_isometry();
+            for joint_axis in joint.joint_axes() {
+                if joint_axis.is_coupled() {
+                    return Err(OptimaError::new_unsupported_operation_error("new_from_robot_kinematics_module", &format!("Joint {} has a coupled axis, which has no independent screw axis.", joint.name()), file!(), line!()));
+                }
+                let home_value = joint_axis.fixed_value().unwrap_or(0.0);
+                let axis = joint_axis.axis_as_unit();
+                let (screw_axis, axis_transform) = match joint_axis.axis_primitive_type() {
+                    JointAxisPrimitiveType::Rotation => {
+                        let omega = axis_frame.rotation * axis.into_inner();
+                        let q = axis_frame.translation.vector;
+                        let v = -omega.cross(&q);
+                        (Vector6::new(omega[0], omega[1], omega[2], v[0], v[1], v[2]), Isometry3::from_parts(Translation3::identity(), UnitQuaternion::from_axis_angle(&axis, home_value)))
+                    }
+                    JointAxisPrimitiveType::Translation => {
+                        let v = axis_frame.rotation * axis.into_inner();
+                        let t = axis.into_inner() * home_value;
+                        (Vector6::new(0.0, 0.0, 0.0, v[0], v[1], v[2]), Isometry3::from_parts(Translation3::new(t[0], t[1], t[2]), UnitQuaternion::identity()))
+                    }
+                    JointAxisPrimitiveType::QuaternionComponent => {
+                        return Err(OptimaError::new_unsupported_operation_error("new_from_robot_kinematics_module", &format!("Joint {} is a ball joint, which has no screw axes.", joint.name()), file!(), line!()));
+                    }
+                };
+
+                if joint_axis.fixed_value().is_none() {
+                    let dof_idx = robot_joint_state_module.ordered_dof_joint_axes().iter().position(|a| a.joint_idx() == joint_axis.joint_idx() && a.joint_sub_dof_idx() == joint_axis.joint_sub_dof_idx());
+                    match dof_idx {
+                        None => { return Err(OptimaError::new_generic_error_str(&format!("Could not find the DOF of axis {} of joint {}.", joint_axis.joint_sub_dof_idx(), joint.name()), file!(), line!())); }
+                        Some(dof_idx) => {
+                            space_screw_axes.push(screw_axis);
+                            dof_idxs.push(DofIdx::new(dof_idx));
+                        }
+                    }
+                }
+                axis_frame = axis_frame * axis_transform;
+            }
+        }
+
+        Ok(Self {
+            robot_name: robot_model_module.robot_name().to_string(),
+            start_link_id,
+            end_link_id,
+            home_configuration: start_pose_inverse * link_pose(end_link_id)?,
+            space_screw_axes,
+            dof_idxs
+        })
+    }
+    /// The screw axes expressed in the end link's frame at the home configuration, for the body form
+    /// T(theta) = M * exp(\[B_1\] theta_1) * ... * exp(\[B_n\] theta_n).
+    pub fn body_screw_axes(&self) -> Vec<Vector6<f64>> {
+        let m_inverse = self.home_configuration.inverse();
+        return self.space_screw_axes.iter().map(|s| Self::adjoint(&m_inverse, s)).collect();
+    }
+    /// Evaluates the space form of the model for the given robot DOF joint state, returning the pose
+    /// of the end link in the start link's frame.  This should match the robot's own forward
+    /// kinematics, which makes it a convenient check of an exported model.
+    pub fn compute_end_link_pose(&self, dof_state: &DVector<f64>) -> Result<Isometry3<f64>, OptimaError> {
+        let mut out_pose = Isometry3::identity();
+        for (screw_axis, dof_idx) in self.space_screw_axes.iter().zip(self.dof_idxs.iter()) {
+            OptimaError::new_check_for_idx_out_of_bound_error(dof_idx.idx(), dof_state.len(), file!(), line!())?;
+            out_pose = out_pose * Self::screw_exponential(screw_axis, dof_state[dof_idx.idx()]);
+        }
+        Ok(out_pose * self.home_configuration)
+    }
+    pub fn robot_name(&self) -> &str {
+        &self.robot_name
+    }
+    pub fn start_link_id(&self) -> LinkId {
+        self.start_link_id
+    }
+    pub fn end_link_id(&self) -> LinkId {
+        self.end_link_id
+    }
+    pub fn home_configuration(&self) -> &Isometry3<f64> {
+        &self.home_configuration
+    }
+    pub fn space_screw_axes(&self) -> &Vec<Vector6<f64>> {
+        &self.space_screw_axes
+    }
+    pub fn dof_idxs(&self) -> &Vec<DofIdx> {
+        &self.dof_idxs
+    }
+    /// Ad_T applied to a screw axis \[omega; v\]: \[R omega; p x (R omega) + R v\].
+    fn adjoint(t: &Isometry3<f64>, screw_axis: &Vector6<f64>) -> Vector6<f64> {
+        let omega = t.rotation * Vector3::new(screw_axis[0], screw_axis[1], screw_axis[2]);
+        let v = t.translation.vector.cross(&omega) + t.rotation * Vector3::new(screw_axis[3], screw_axis[4], screw_axis[5]);
+        return Vector6::new(omega[0], omega[1], omega[2], v[0], v[1], v[2]);
+    }
+    fn screw_exponential(screw_axis: &Vector6<f64>, theta: f64) -> Isometry3<f64> {
+        let omega = Vector3::new(screw_axis[0], screw_axis[1], screw_axis[2]);
+        let v = Vector3::new(screw_axis[3], screw_axis[4], screw_axis[5]);
+        if omega.norm() == 0.0 {
+            let t = v * theta;
+            return Isometry3::from_parts(Translation3::new(t[0], t[1], t[2]), UnitQuaternion::identity());
+        }
+
+        let omega_hat = omega.cross_matrix();
+        let g = Matrix3::identity() * theta + omega_hat * (1.0 - theta.cos()) + omega_hat * omega_hat * (theta - theta.sin());
+        let t = g * v;
+        return Isometry3::from_parts(Translation3::new(t[0], t[1], t[2]), UnitQuaternion::from_axis_angle(&Unit::new_unchecked(omega), theta));
+    }
+}
+
+/// Python implementations.
+#[cfg(not(target_arch = "wasm32"))]
+#[pymethods]
+impl ProductOfExponentialsModel {
+    #[staticmethod]
+    pub fn new_py(robot_kinematics_module: &RobotKinematicsModule, end_link_idx: usize, start_link_idx: Option<usize>) -> Self {
+        Self::new_from_robot_kinematics_module(robot_kinematics_module, start_link_idx.map(LinkId::new), LinkId::new(end_link_idx)).expect("error")
+    }
+    pub fn space_screw_axes_py(&self) -> Vec<Vec<f64>> {
+        self.space_screw_axes.iter().map(|s| s.as_slice().to_vec()).collect()
+    }
+    pub fn body_screw_axes_py(&self) -> Vec<Vec<f64>> {
+        self.body_screw_axes().iter().map(|s| s.as_slice().to_vec()).collect()
+    }
+    /// Returns M as a 4 x 4 homogeneous matrix (list of rows).
+    pub fn home_configuration_py(&self) -> Vec<Vec<f64>> {
+        NalgebraConversions::dmatrix_to_vecs(&DMatrix::from_column_slice(4, 4, self.home_configuration.to_homogeneous().as_slice()))
+    }
+    pub fn dof_idxs_py(&self) -> Vec<usize> {
+        self.dof_idxs.iter().map(|dof_idx| dof_idx.idx()).collect()
+    }
+    pub fn compute_end_link_pose_py(&self, dof_state: Vec<f64>) -> Vec<Vec<f64>> {
+        let pose = self.compute_end_link_pose(&NalgebraConversions::vec_to_dvector(&dof_state)).expect("error");
+        NalgebraConversions::dmatrix_to_vecs(&DMatrix::from_column_slice(4, 4, pose.to_homogeneous().as_slice()))
+    }
+    pub fn to_json_string_py(&self) -> String {
+        self.to_json_string()
+    }
+}
+
+crate::impl_py_pickle!(ProductOfExponentialsModel);
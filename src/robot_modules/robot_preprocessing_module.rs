@@ -5,14 +5,18 @@ use pyo3::*;
 use wasm_bindgen::prelude::*;
 
 use serde::{Serialize, Deserialize};
+use crate::utils::utils_cancellation::CancellationToken;
 use crate::utils::utils_console::{ConsoleInputUtils, get_default_progress_bar, optima_print, PrintColor, PrintMode};
 use crate::utils::utils_errors::OptimaError;
 use crate::robot_modules::robot_mesh_file_manager_module::RobotMeshFileManagerModule;
 use crate::robot_modules::robot_model_module::RobotModelModule;
-use crate::robot_modules::robot_geometric_shape_module::RobotGeometricShapeModule;
+use crate::robot_modules::robot_configuration_module::RobotConfigurationModule;
+use crate::robot_modules::robot_geometric_shape_module::{PreprocessingConfig, RobotGeometricShapeModule};
+use crate::robot_modules::robot_shape_distance_lookup::DistanceLookupTableConfig;
 use crate::utils::utils_files::optima_path::{OptimaAssetLocation, OptimaPathMatchingPattern, OptimaPathMatchingStopCondition, OptimaStemCellPath, RobotModuleJsonType};
 use crate::utils::utils_robot::robot_module_utils::{RobotNames};
 use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3Pose, OptimaSE3PoseType};
+use crate::utils::utils_shape_geometry::convex_shape_cache::ConvexShapeCache;
 use crate::utils::utils_shape_geometry::trimesh_engine::ConvexDecompositionResolution;
 use crate::utils::utils_traits::AssetSaveAndLoadable;
 
@@ -21,7 +25,8 @@ use crate::utils::utils_traits::AssetSaveAndLoadable;
 pub struct RobotPreprocessingModule {
     pub replace_robot_model_module_json: bool,
     pub replace_robot_link_convex_shapes: bool,
-    pub replace_robot_link_convex_shape_subcomponents: bool
+    pub replace_robot_link_convex_shape_subcomponents: bool,
+    pub preprocessing_config: PreprocessingConfig
 }
 impl RobotPreprocessingModule {
     pub fn preprocess_all_robots_from_console_input() -> Result<(), OptimaError> {
@@ -49,13 +54,17 @@ impl RobotPreprocessingModule {
         let replace_robot_link_convex_shapes = if &line == "y" { true } else { false };
         let line = ConsoleInputUtils::get_console_input_string("Replace robot link convex subcomponents?  (y or n)", PrintColor::Blue)?;
         let replace_robot_link_convex_shape_subcomponents = if &line == "y" { true } else { false };
+        let line = ConsoleInputUtils::get_console_input_string("Build distance lookup tables?  (y or n)", PrintColor::Blue)?;
+        let mut preprocessing_config = PreprocessingConfig::default();
+        if &line == "y" { preprocessing_config.set_distance_lookup_table_config(Some(DistanceLookupTableConfig::default())); }
 
         for robot_name in &all_robot_strings {
             optima_print(&format!("Preprocessing robot {:?}", robot_name), PrintMode::Println, PrintColor::Blue, true);
             let res = RobotPreprocessingModule {
                 replace_robot_model_module_json,
                 replace_robot_link_convex_shapes,
-                replace_robot_link_convex_shape_subcomponents
+                replace_robot_link_convex_shape_subcomponents,
+                preprocessing_config: preprocessing_config.clone()
             }.preprocess_robot(robot_name);
             if res.is_err() {
                 optima_print(&format!("Could not successfully preprocess robot {:?}.  Encountered error {:?}", robot_name, res), PrintMode::Println, PrintColor::Red, true);
@@ -76,23 +85,41 @@ impl RobotPreprocessingModule {
         let replace_robot_link_convex_shapes = if &line == "y" { true } else { false };
         let line = ConsoleInputUtils::get_console_input_string("Replace robot link convex subcomponents?  (y or n)", PrintColor::Blue)?;
         let replace_robot_link_convex_shape_subcomponents = if &line == "y" { true } else { false };
+        let line = ConsoleInputUtils::get_console_input_string("Build distance lookup tables?  (y or n)", PrintColor::Blue)?;
+        let mut preprocessing_config = PreprocessingConfig::default();
+        if &line == "y" { preprocessing_config.set_distance_lookup_table_config(Some(DistanceLookupTableConfig::default())); }
 
         return Self {
             replace_robot_model_module_json,
             replace_robot_link_convex_shapes,
-            replace_robot_link_convex_shape_subcomponents
+            replace_robot_link_convex_shape_subcomponents,
+            preprocessing_config
         }.preprocess_robot(robot_name);
     }
     pub fn preprocess_robot(&self, robot_name: &str) -> Result<(), OptimaError> {
+        return self.preprocess_robot_with_cancellation(robot_name, &CancellationToken::new());
+    }
+    /// Same as `preprocess_robot`, but returns a `Cancelled` error as soon as the given token is
+    /// cancelled (checked between stages and between links).  Stages that already finished keep
+    /// their outputs; the partial outputs of the interrupted stage are removed so that the next call
+    /// redoes that stage.
+    pub fn preprocess_robot_with_cancellation(&self, robot_name: &str, cancellation_token: &CancellationToken) -> Result<(), OptimaError> {
         if cfg!(feature = "only_use_embedded_assets") {
             return Err(OptimaError::new_unsupported_operation_error("preprocess_robot", "Cannot preprocess robot using only_use_embedded_assets feature.", file!(), line!()));
         }
 
+        let f = "preprocess_robot_with_cancellation";
+        cancellation_token.check(f, file!(), line!())?;
         self.preprocess_robot_model_module_json(robot_name)?;
+        cancellation_token.check(f, file!(), line!())?;
         self.copy_link_meshes_to_assets_folder(robot_name)?;
-        self.preprocess_robot_link_meshes(robot_name)?;
-        self.preprocess_robot_link_convex_shapes(robot_name)?;
-        self.preprocess_robot_link_convex_shape_subcomponents(robot_name)?;
+        cancellation_token.check(f, file!(), line!())?;
+        self.preprocess_robot_link_meshes(robot_name, cancellation_token)?;
+        cancellation_token.check(f, file!(), line!())?;
+        self.preprocess_robot_link_convex_shapes(robot_name, cancellation_token)?;
+        cancellation_token.check(f, file!(), line!())?;
+        self.preprocess_robot_link_convex_shape_subcomponents(robot_name, cancellation_token)?;
+        cancellation_token.check(f, file!(), line!())?;
         self.preprocess_robot_shape_geometry_module(robot_name)?;
 
         println!();
@@ -123,7 +150,7 @@ impl RobotPreprocessingModule {
         }
         Ok(())
     }
-    fn preprocess_robot_link_meshes(&self, robot_name: &str) -> Result<(), OptimaError> {
+    fn preprocess_robot_link_meshes(&self, robot_name: &str, cancellation_token: &CancellationToken) -> Result<(), OptimaError> {
         let mut directory_path = OptimaStemCellPath::new_asset_path()?;
         directory_path.append_file_location(&OptimaAssetLocation::RobotMeshes { robot_name: robot_name.to_string() });
         optima_print("Preprocessing robot link meshes...", PrintMode::Println, PrintColor::Blue, true);
@@ -139,6 +166,10 @@ impl RobotPreprocessingModule {
         pb.show_counter = true;
 
         for (i, link) in links.iter().enumerate() {
+            if cancellation_token.is_cancelled() {
+                directory_path.delete_all_items_in_directory()?;
+                return Err(OptimaError::new_cancelled_error("preprocess_robot_link_meshes", file!(), line!()));
+            }
             let has_visual_mesh = link.urdf_link().visual_mesh_filename().is_some();
             if has_visual_mesh {
                 let res = base_meshes_directory_path.walk_directory_and_match(OptimaPathMatchingPattern::PathComponentsWithoutExtension(vec![format!("{}", i)]), OptimaPathMatchingStopCondition::First);
@@ -168,7 +199,7 @@ impl RobotPreprocessingModule {
 
         Ok(())
     }
-    fn preprocess_robot_link_convex_shapes(&self, robot_name: &str) -> Result<(), OptimaError> {
+    fn preprocess_robot_link_convex_shapes(&self, robot_name: &str, cancellation_token: &CancellationToken) -> Result<(), OptimaError> {
         let mut directory_path = OptimaStemCellPath::new_asset_path()?;
         directory_path.append_file_location(&OptimaAssetLocation::RobotConvexShapes { robot_name: robot_name.to_string() });
         let files_in_directory = directory_path.get_all_items_in_directory(false, false);
@@ -186,12 +217,16 @@ impl RobotPreprocessingModule {
             pb.show_counter = true;
 
             for (i, link) in links.iter().enumerate() {
+                if cancellation_token.is_cancelled() {
+                    directory_path.delete_all_items_in_directory()?;
+                    return Err(OptimaError::new_cancelled_error("preprocess_robot_link_convex_shapes", file!(), line!()));
+                }
                 let has_visual_mesh = link.urdf_link().visual_mesh_filename().is_some();
                 if has_visual_mesh {
                     let res = base_meshes_directory_path.walk_directory_and_match(OptimaPathMatchingPattern::PathComponentsWithoutExtension(vec![format!("{}", i)]), OptimaPathMatchingStopCondition::First);
                     let optima_path = res[0].clone();
                     let trimesh = optima_path.load_file_to_trimesh_engine()?;
-                    let convex_hull = trimesh.compute_convex_hull();
+                    let convex_hull = ConvexShapeCache::get_or_compute_convex_hull(&trimesh)?;
 
                     let mut directory_path_copy = directory_path.clone();
                     directory_path_copy.append(&format!("{}.stl", i));
@@ -204,7 +239,7 @@ impl RobotPreprocessingModule {
         }
         Ok(())
     }
-    fn preprocess_robot_link_convex_shape_subcomponents(&self, robot_name: &str) -> Result<(), OptimaError> {
+    fn preprocess_robot_link_convex_shape_subcomponents(&self, robot_name: &str, cancellation_token: &CancellationToken) -> Result<(), OptimaError> {
         let mut directory_path = OptimaStemCellPath::new_asset_path()?;
         directory_path.append_file_location(&OptimaAssetLocation::RobotConvexSubcomponents { robot_name: robot_name.to_string() });
         let files_in_directory = directory_path.get_all_items_in_directory(false, false);
@@ -223,13 +258,17 @@ impl RobotPreprocessingModule {
 
             let mut messages = vec![];
             for (i, link) in links.iter().enumerate() {
+                if cancellation_token.is_cancelled() {
+                    directory_path.delete_all_items_in_directory()?;
+                    return Err(OptimaError::new_cancelled_error("preprocess_robot_link_convex_shape_subcomponents", file!(), line!()));
+                }
                 let has_visual_mesh = link.urdf_link().visual_mesh_filename().is_some();
                 if has_visual_mesh {
                     let res = base_meshes_directory_path.walk_directory_and_match(OptimaPathMatchingPattern::PathComponentsWithoutExtension(vec![format!("{}", i)]), OptimaPathMatchingStopCondition::First);
                     let optima_path = res[0].clone();
                     let trimesh = optima_path.load_file_to_trimesh_engine()?;
 
-                    let convex_components = trimesh.compute_convex_decomposition(ConvexDecompositionResolution::Low);
+                    let convex_components = ConvexShapeCache::get_or_compute_convex_decomposition(&trimesh, ConvexDecompositionResolution::Low)?;
                     messages.push(format!("{:?} convex subcomponents for link {:?}: {}. ", convex_components.len(), link.link_idx(), link.name()));
                     for (j, c) in convex_components.iter().enumerate() {
                         let mut directory_path_copy = directory_path.clone();
@@ -255,19 +294,55 @@ impl RobotPreprocessingModule {
 
         if !directory_path.exists() || !directory_path_permanent.exists() || self.replace_robot_link_convex_shapes || self.replace_robot_link_convex_shape_subcomponents {
             optima_print("Preprocessing robot shape geometry module...", PrintMode::Println, PrintColor::Blue, true);
-            let robot_shape_geometry_module = RobotGeometricShapeModule::new_from_names(RobotNames::new_base(robot_name), true)?;
+            let robot_configuration_module = RobotConfigurationModule::new_from_names(RobotNames::new_base(robot_name))?;
+            let robot_shape_geometry_module = RobotGeometricShapeModule::new_with_preprocessing_config(robot_configuration_module, &self.preprocessing_config)?;
             robot_shape_geometry_module.save_as_asset(OptimaAssetLocation::RobotModuleJson { robot_name: robot_name.to_string(), t: RobotModuleJsonType::ShapeGeometryModule })?;
             robot_shape_geometry_module.save_as_asset(OptimaAssetLocation::RobotModuleJson { robot_name: robot_name.to_string(), t: RobotModuleJsonType::ShapeGeometryModulePermanent })?;
         }
         Ok(())
     }
 }
+impl RobotPreprocessingModule {
+    /// Reports which preprocessed assets currently exist for the given robot, without creating any.
+    pub fn preprocessing_status(robot_name: &str) -> Result<RobotPreprocessingStatus, OptimaError> {
+        let mut robot_path = OptimaStemCellPath::new_asset_path()?;
+        robot_path.append_file_location(&OptimaAssetLocation::Robot { robot_name: robot_name.to_string() });
+        if !robot_path.exists() {
+            return Err(OptimaError::new_generic_error_str(&format!("Robot directory for robot {} does not exist.", robot_name), file!(), line!()));
+        }
+
+        let json_exists = |t: RobotModuleJsonType| -> Result<bool, OptimaError> {
+            let mut path = OptimaStemCellPath::new_asset_path()?;
+            path.append_file_location(&OptimaAssetLocation::RobotModuleJson { robot_name: robot_name.to_string(), t });
+            Ok(path.exists())
+        };
+        let num_items = |location: OptimaAssetLocation| -> Result<usize, OptimaError> {
+            let mut path = OptimaStemCellPath::new_asset_path()?;
+            path.append_file_location(&location);
+            if !path.exists() { return Ok(0); }
+            Ok(path.get_all_items_in_directory(false, false).len())
+        };
+
+        Ok(RobotPreprocessingStatus {
+            robot_name: robot_name.to_string(),
+            robot_model_module_json: json_exists(RobotModuleJsonType::ModelModule)?,
+            robot_shape_geometry_module_json: json_exists(RobotModuleJsonType::ShapeGeometryModule)?,
+            robot_shape_geometry_module_permanent_json: json_exists(RobotModuleJsonType::ShapeGeometryModulePermanent)?,
+            robot_shape_geometry_preprocessing_report_json: json_exists(RobotModuleJsonType::ShapeGeometryPreprocessingReport)?,
+            num_input_meshes: num_items(OptimaAssetLocation::RobotInputMeshes { robot_name: robot_name.to_string() })?,
+            num_meshes: num_items(OptimaAssetLocation::RobotMeshes { robot_name: robot_name.to_string() })?,
+            num_convex_shapes: num_items(OptimaAssetLocation::RobotConvexShapes { robot_name: robot_name.to_string() })?,
+            num_convex_shape_subcomponents: num_items(OptimaAssetLocation::RobotConvexSubcomponents { robot_name: robot_name.to_string() })?
+        })
+    }
+}
 impl Default for RobotPreprocessingModule {
     fn default() -> Self {
         Self {
             replace_robot_model_module_json: true,
             replace_robot_link_convex_shapes: false,
-            replace_robot_link_convex_shape_subcomponents: false
+            replace_robot_link_convex_shape_subcomponents: false,
+            preprocessing_config: PreprocessingConfig::default()
         }
     }
 }
@@ -285,3 +360,66 @@ impl RobotPreprocessingModule {
         self.preprocess_robot(robot_name).expect("error");
     }
 }
+
+/// Which preprocessed assets exist for a robot (see `RobotPreprocessingModule::preprocessing_status`).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobotPreprocessingStatus {
+    robot_name: String,
+    robot_model_module_json: bool,
+    robot_shape_geometry_module_json: bool,
+    robot_shape_geometry_module_permanent_json: bool,
+    robot_shape_geometry_preprocessing_report_json: bool,
+    num_input_meshes: usize,
+    num_meshes: usize,
+    num_convex_shapes: usize,
+    num_convex_shape_subcomponents: usize
+}
+impl RobotPreprocessingStatus {
+    /// Returns true if all of the assets that `RobotPreprocessingModule::preprocess_robot` produces exist.
+    pub fn is_fully_preprocessed(&self) -> bool {
+        return self.robot_model_module_json && self.robot_shape_geometry_module_json && self.robot_shape_geometry_module_permanent_json && self.num_meshes > 0 && self.num_convex_shapes > 0 && self.num_convex_shape_subcomponents > 0;
+    }
+    pub fn robot_name(&self) -> &str {
+        &self.robot_name
+    }
+    pub fn robot_model_module_json(&self) -> bool {
+        self.robot_model_module_json
+    }
+    pub fn robot_shape_geometry_module_json(&self) -> bool {
+        self.robot_shape_geometry_module_json
+    }
+    pub fn robot_shape_geometry_module_permanent_json(&self) -> bool {
+        self.robot_shape_geometry_module_permanent_json
+    }
+    /// The report is not required for a robot to count as fully preprocessed, since robots
+    /// preprocessed before reports were written do not have one.
+    pub fn robot_shape_geometry_preprocessing_report_json(&self) -> bool {
+        self.robot_shape_geometry_preprocessing_report_json
+    }
+    pub fn num_input_meshes(&self) -> usize {
+        self.num_input_meshes
+    }
+    pub fn num_meshes(&self) -> usize {
+        self.num_meshes
+    }
+    pub fn num_convex_shapes(&self) -> usize {
+        self.num_convex_shapes
+    }
+    pub fn num_convex_shape_subcomponents(&self) -> usize {
+        self.num_convex_shape_subcomponents
+    }
+    pub fn print_summary(&self) {
+        let color = if self.is_fully_preprocessed() { PrintColor::Green } else { PrintColor::Yellow };
+        optima_print(&format!("Robot {} ---> ", self.robot_name), PrintMode::Println, color, true);
+        optima_print(&format!("   > robot model module json: {}", self.robot_model_module_json), PrintMode::Println, PrintColor::None, false);
+        optima_print(&format!("   > robot shape geometry module json: {}", self.robot_shape_geometry_module_json), PrintMode::Println, PrintColor::None, false);
+        optima_print(&format!("   > robot shape geometry module permanent json: {}", self.robot_shape_geometry_module_permanent_json), PrintMode::Println, PrintColor::None, false);
+        optima_print(&format!("   > robot shape geometry preprocessing report json: {}", self.robot_shape_geometry_preprocessing_report_json), PrintMode::Println, PrintColor::None, false);
+        optima_print(&format!("   > input meshes: {}", self.num_input_meshes), PrintMode::Println, PrintColor::None, false);
+        optima_print(&format!("   > meshes: {}", self.num_meshes), PrintMode::Println, PrintColor::None, false);
+        optima_print(&format!("   > convex shapes: {}", self.num_convex_shapes), PrintMode::Println, PrintColor::None, false);
+        optima_print(&format!("   > convex shape subcomponents: {}", self.num_convex_shape_subcomponents), PrintMode::Println, PrintColor::None, false);
+    }
+}
+
+crate::impl_py_pickle!(RobotPreprocessingModule);
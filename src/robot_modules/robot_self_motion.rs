@@ -0,0 +1,364 @@
+#[cfg(not(target_arch = "wasm32"))]
+use pyo3::*;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+use nalgebra::{DMatrix, DVector};
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_configuration_module::RobotConfigurationModule;
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateModule, RobotJointStateType};
+use crate::robot_modules::robot_kinematics_module::{JacobianMode, RobotKinematicsModule};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_nalgebra::conversions::NalgebraConversions;
+use crate::utils::utils_robot::robot_module_utils::RobotNames;
+use crate::utils::utils_sampling::SimpleSamplers;
+use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3Pose, OptimaSE3PoseType};
+use crate::utils::utils_se3::pose_error_metric::PoseErrorMetric;
+use crate::utils::utils_traits::ToAndFromRonString;
+
+/// Explores the self-motion manifold of a redundant robot, i.e., the set of joint states that keep a
+/// named frame (see `RobotKinematicsModule::resolve_frame`) at a fixed pose, and returns a diverse
+/// set of postures on it, e.g., to pick the one that leaves the most room for subsequent motions.
+///
+/// Starting from a seed joint state, each walk picks a random joint space direction, projects it
+/// onto the null space of the frame's Jacobian, N = I - J^T (J J^T + damping^2 I)^-1 J, and then
+/// continues along the manifold: every step moves step_size along the current null space direction
+/// (the previous direction projected onto the new null space, so walks do not double back), and the
+/// result is pulled back onto the pose with damped least squares inverse kinematics.  A walk ends
+/// after max_steps_per_walk steps, when the null space direction vanishes (e.g., at a singularity),
+/// when a step would leave the joint bounds, or when the correction does not converge.
+///
+/// All postures visited are candidates.  The output postures are picked greedily by farthest point
+/// selection in joint space, starting with the seed, and no two are closer than min_posture_distance.
+/// With `JacobianMode::Translational`, only the frame's position is held fixed, so a 6-DOF arm is
+/// also redundant.
+#[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen, derive(Clone, Debug, Serialize, Deserialize))]
+pub struct SelfMotionExplorer {
+    robot_kinematics_module: RobotKinematicsModule,
+    robot_joint_state_module: RobotJointStateModule,
+    frame_name: String,
+    jacobian_mode: JacobianMode,
+    step_size: f64,
+    max_steps_per_walk: usize,
+    min_posture_distance: f64,
+    damping: f64,
+    max_correction_iterations: usize,
+    position_tolerance: f64,
+    orientation_tolerance: f64
+}
+impl SelfMotionExplorer {
+    pub fn new_from_names(robot_names: RobotNames, frame_name: &str, jacobian_mode: JacobianMode) -> Result<Self, OptimaError> {
+        let robot_configuration_module = RobotConfigurationModule::new_from_names(robot_names)?;
+        return Self::new(robot_configuration_module, frame_name, jacobian_mode);
+    }
+    pub fn new(robot_configuration_module: RobotConfigurationModule, frame_name: &str, jacobian_mode: JacobianMode) -> Result<Self, OptimaError> {
+        let task_dimension = match jacobian_mode {
+            JacobianMode::Full => { 6 }
+            JacobianMode::Translational => { 3 }
+            JacobianMode::Rotational => {
+                return Err(OptimaError::new_unsupported_operation_error("SelfMotionExplorer::new", "Rotational jacobian mode is not supported for self-motion exploration.  Use Full or Translational.", file!(), line!()));
+            }
+        };
+        let robot_kinematics_module = RobotKinematicsModule::new(robot_configuration_module.clone());
+        robot_kinematics_module.resolve_frame(frame_name)?;
+        let robot_joint_state_module = RobotJointStateModule::new(robot_configuration_module);
+        if robot_joint_state_module.num_dofs() <= task_dimension {
+            return Err(OptimaError::new_generic_error_str(&format!("Robot {} has {} DOFs, so it is not redundant for a {}-dimensional task.", robot_kinematics_module.robot_name(), robot_joint_state_module.num_dofs(), task_dimension), file!(), line!()));
+        }
+
+        Ok(Self {
+            robot_kinematics_module,
+            robot_joint_state_module,
+            frame_name: frame_name.to_string(),
+            jacobian_mode,
+            step_size: 0.05,
+            max_steps_per_walk: 40,
+            min_posture_distance: 0.2,
+            damping: 0.01,
+            max_correction_iterations: 50,
+            position_tolerance: 0.0005,
+            orientation_tolerance: 0.001
+        })
+    }
+    /// Sets the joint space length of each step along the manifold (default 0.05).
+    pub fn set_step_size(&mut self, step_size: f64) -> Result<(), OptimaError> {
+        if !(step_size > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Step size must be positive (given {}).", step_size), file!(), line!()));
+        }
+        self.step_size = step_size;
+        Ok(())
+    }
+    /// Sets the maximum number of steps of each walk (default 40).
+    pub fn set_max_steps_per_walk(&mut self, max_steps_per_walk: usize) {
+        self.max_steps_per_walk = max_steps_per_walk;
+    }
+    /// Sets the minimum joint space distance between any two returned postures (default 0.2).
+    pub fn set_min_posture_distance(&mut self, min_posture_distance: f64) -> Result<(), OptimaError> {
+        if !(min_posture_distance >= 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Minimum posture distance must be nonnegative (given {}).", min_posture_distance), file!(), line!()));
+        }
+        self.min_posture_distance = min_posture_distance;
+        Ok(())
+    }
+    /// Sets the damping of the null space projection and of the inverse kinematics correction
+    /// (default 0.01).
+    pub fn set_damping(&mut self, damping: f64) -> Result<(), OptimaError> {
+        if !(damping >= 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Damping must be nonnegative (given {}).", damping), file!(), line!()));
+        }
+        self.damping = damping;
+        Ok(())
+    }
+    /// Sets the iteration limit and tolerances of the inverse kinematics correction after each step
+    /// (defaults 50, 0.5 mm and 0.001 radians).
+    pub fn set_correction_parameters(&mut self, max_correction_iterations: usize, position_tolerance: f64, orientation_tolerance: f64) -> Result<(), OptimaError> {
+        if !(position_tolerance > 0.0 && orientation_tolerance > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Correction tolerances must be positive (given {} and {}).", position_tolerance, orientation_tolerance), file!(), line!()));
+        }
+        self.max_correction_iterations = max_correction_iterations;
+        self.position_tolerance = position_tolerance;
+        self.orientation_tolerance = orientation_tolerance;
+        Ok(())
+    }
+    /// Runs num_walks walks from the seed joint state and returns at most max_postures diverse
+    /// postures (the seed, corrected onto the manifold, is always the first).  The frame is held at
+    /// its pose in the seed joint state.
+    pub fn explore(&self, seed_joint_state: &RobotJointState, num_walks: usize, max_postures: usize) -> Result<SelfMotionExplorationOutput, OptimaError> {
+        let seed_joint_state = self.robot_joint_state_module.spawn_robot_joint_state(seed_joint_state.joint_state().clone(), seed_joint_state.robot_joint_state_type().clone())?;
+        let seed_joint_state = self.robot_joint_state_module.convert_joint_state_to_dof_state(&seed_joint_state)?;
+        let target_pose = self.robot_kinematics_module.compute_frame_pose(&seed_joint_state, &self.frame_name, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+        return self.explore_at_pose(&seed_joint_state, &target_pose, num_walks, max_postures);
+    }
+    /// Same as `explore`, but the frame is held at the given target pose.  The seed is first moved
+    /// onto the manifold with inverse kinematics; returns an error if that does not converge.
+    pub fn explore_at_pose(&self, seed_joint_state: &RobotJointState, target_pose: &OptimaSE3Pose, num_walks: usize, max_postures: usize) -> Result<SelfMotionExplorationOutput, OptimaError> {
+        let seed_joint_state = self.robot_joint_state_module.spawn_robot_joint_state(seed_joint_state.joint_state().clone(), seed_joint_state.robot_joint_state_type().clone())?;
+        let seed_joint_state = self.robot_joint_state_module.convert_joint_state_to_dof_state(&seed_joint_state)?;
+        let seed = match self.correct(&seed_joint_state, target_pose)? {
+            None => { return Err(OptimaError::new_generic_error_str(&format!("Could not move the seed joint state onto the self-motion manifold of frame {} at the target pose.", self.frame_name), file!(), line!())); }
+            Some(seed) => { seed }
+        };
+
+        let mut candidates = vec![seed.joint_state().clone()];
+        for _ in 0..num_walks {
+            self.walk(&seed, target_pose, &mut candidates)?;
+        }
+        let num_candidates = candidates.len();
+
+        let mut postures = vec![];
+        for idx in self.select_diverse(&candidates, max_postures) {
+            let joint_state = self.robot_joint_state_module.spawn_robot_joint_state(candidates[idx].clone(), RobotJointStateType::DOF)?;
+            postures.push(self.evaluate_posture(joint_state)?);
+        }
+
+        Ok(SelfMotionExplorationOutput {
+            target_pose: target_pose.clone(),
+            postures,
+            num_candidates
+        })
+    }
+    pub fn frame_name(&self) -> &str {
+        &self.frame_name
+    }
+    pub fn jacobian_mode(&self) -> &JacobianMode {
+        &self.jacobian_mode
+    }
+    pub fn step_size(&self) -> f64 {
+        self.step_size
+    }
+    pub fn max_steps_per_walk(&self) -> usize {
+        self.max_steps_per_walk
+    }
+    pub fn min_posture_distance(&self) -> f64 {
+        self.min_posture_distance
+    }
+    pub fn damping(&self) -> f64 {
+        self.damping
+    }
+    pub fn robot_kinematics_module(&self) -> &RobotKinematicsModule {
+        &self.robot_kinematics_module
+    }
+    /// Follows the manifold from the seed in a random direction, adding every posture visited to
+    /// candidates.
+    fn walk(&self, seed: &RobotJointState, target_pose: &OptimaSE3Pose, candidates: &mut Vec<DVector<f64>>) -> Result<(), OptimaError> {
+        let bounds = self.robot_joint_state_module.get_joint_state_bounds(&RobotJointStateType::DOF);
+        let mut direction = DVector::from_vec(SimpleSamplers::uniform_samples(&vec![(-1.0, 1.0); seed.len()]));
+        let mut joint_state = seed.clone();
+        for _ in 0..self.max_steps_per_walk {
+            let projected = self.null_space_projector(&joint_state)? * &direction;
+            let norm = projected.norm();
+            if norm < 1e-6 { return Ok(()); }
+            direction = projected / norm;
+
+            let stepped = joint_state.joint_state() + &direction * self.step_size;
+            for (i, (lower, upper)) in bounds.iter().enumerate() {
+                if stepped[i] < *lower || stepped[i] > *upper { return Ok(()); }
+            }
+            let stepped = self.robot_joint_state_module.spawn_robot_joint_state(stepped, RobotJointStateType::DOF)?;
+            joint_state = match self.correct(&stepped, target_pose)? {
+                None => { return Ok(()); }
+                Some(corrected) => { corrected }
+            };
+            candidates.push(joint_state.joint_state().clone());
+        }
+        Ok(())
+    }
+    fn null_space_projector(&self, joint_state: &RobotJointState) -> Result<DMatrix<f64>, OptimaError> {
+        let jacobian = self.robot_kinematics_module.compute_frame_jacobian(joint_state, &self.frame_name, self.jacobian_mode.clone())?;
+        let jacobian_t = jacobian.transpose();
+        let jjt = &jacobian * &jacobian_t + DMatrix::identity(jacobian.nrows(), jacobian.nrows()) * (self.damping * self.damping);
+        let solved = match jjt.lu().solve(&jacobian) {
+            None => { return Err(OptimaError::new_generic_error_str("Could not solve null space projection system (singular matrix).  Try increasing the damping.", file!(), line!())); }
+            Some(solved) => { solved }
+        };
+        return Ok(DMatrix::identity(jacobian.ncols(), jacobian.ncols()) - jacobian_t * solved);
+    }
+    /// Pulls the joint state back onto the manifold.  Returns None if the correction does not
+    /// converge.
+    fn correct(&self, joint_state: &RobotJointState, target_pose: &OptimaSE3Pose) -> Result<Option<RobotJointState>, OptimaError> {
+        let pose_error_metric = match self.jacobian_mode {
+            JacobianMode::Translational => { PoseErrorMetric::new_position_only() }
+            _ => { PoseErrorMetric::default() }
+        };
+        let res = self.robot_kinematics_module.compute_ik_damped_least_squares_on_frame(joint_state, &self.frame_name, target_pose, &pose_error_metric, self.max_correction_iterations, self.position_tolerance, self.orientation_tolerance, self.damping)?;
+        return if res.converged() { Ok(Some(res.joint_state().clone())) } else { Ok(None) };
+    }
+    /// Farthest point selection, starting with the first candidate.
+    fn select_diverse(&self, candidates: &Vec<DVector<f64>>, max_postures: usize) -> Vec<usize> {
+        let mut out_vec = vec![];
+        if candidates.is_empty() || max_postures == 0 { return out_vec; }
+        out_vec.push(0);
+        let mut min_distances: Vec<f64> = candidates.iter().map(|c| (c - &candidates[0]).norm()).collect();
+        while out_vec.len() < max_postures {
+            let mut best: Option<(usize, f64)> = None;
+            for (i, d) in min_distances.iter().enumerate() {
+                if best.is_none() || *d > best.unwrap().1 { best = Some((i, *d)); }
+            }
+            let (best_idx, best_distance) = best.expect("error");
+            if best_distance <= self.min_posture_distance || best_distance == 0.0 { break; }
+            out_vec.push(best_idx);
+            for (i, c) in candidates.iter().enumerate() {
+                min_distances[i] = min_distances[i].min((c - &candidates[best_idx]).norm());
+            }
+        }
+        out_vec
+    }
+    fn evaluate_posture(&self, joint_state: RobotJointState) -> Result<SelfMotionPosture, OptimaError> {
+        let jacobian = self.robot_kinematics_module.compute_frame_jacobian(&joint_state, &self.frame_name, self.jacobian_mode.clone())?;
+        let manipulability = (&jacobian * jacobian.transpose()).determinant().max(0.0).sqrt();
+
+        let bounds = self.robot_joint_state_module.get_joint_state_bounds(&RobotJointStateType::DOF);
+        let mut joint_limit_margin = f64::INFINITY;
+        for (i, (lower, upper)) in bounds.iter().enumerate() {
+            joint_limit_margin = joint_limit_margin.min(joint_state[i] - lower).min(upper - joint_state[i]);
+        }
+
+        Ok(SelfMotionPosture {
+            joint_state,
+            manipulability,
+            joint_limit_margin
+        })
+    }
+}
+
+/// Python implementations.
+#[cfg(not(target_arch = "wasm32"))]
+#[pymethods]
+impl SelfMotionExplorer {
+    #[new]
+    #[args(jacobian_mode = "\"Full\"")]
+    pub fn new_py(robot_name: &str, frame_name: &str, configuration_name: Option<&str>, jacobian_mode: &str) -> Self {
+        return Self::new_from_names(RobotNames::new(robot_name, configuration_name), frame_name, JacobianMode::from_ron_string(jacobian_mode).expect("error")).expect("error");
+    }
+    pub fn set_step_size_py(&mut self, step_size: f64) {
+        self.set_step_size(step_size).expect("error");
+    }
+    pub fn set_max_steps_per_walk_py(&mut self, max_steps_per_walk: usize) {
+        self.set_max_steps_per_walk(max_steps_per_walk);
+    }
+    pub fn set_min_posture_distance_py(&mut self, min_posture_distance: f64) {
+        self.set_min_posture_distance(min_posture_distance).expect("error");
+    }
+    pub fn set_damping_py(&mut self, damping: f64) {
+        self.set_damping(damping).expect("error");
+    }
+    /// Returns the DOF joint states of the selected postures.
+    pub fn explore_py(&self, seed_joint_state: Vec<f64>, num_walks: usize, max_postures: usize) -> Vec<Vec<f64>> {
+        let seed_joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&seed_joint_state)).expect("error");
+        let res = self.explore(&seed_joint_state, num_walks, max_postures).expect("error");
+        return res.postures().iter().map(|p| NalgebraConversions::dvector_to_vec(p.joint_state())).collect();
+    }
+}
+
+/// WASM implementations.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl SelfMotionExplorer {
+    #[wasm_bindgen(constructor)]
+    pub fn new_wasm(robot_name: String, frame_name: String, configuration_name: Option<String>, jacobian_mode: &str) -> SelfMotionExplorer {
+        let robot_names = match &configuration_name {
+            None => { RobotNames::new_base(&robot_name) }
+            Some(configuration_name) => { RobotNames::new(&robot_name, Some(configuration_name)) }
+        };
+        return Self::new_from_names(robot_names, &frame_name, JacobianMode::from_ron_string(jacobian_mode).expect("error")).expect("error");
+    }
+    /// Returns the DOF joint states of the selected postures, concatenated.
+    pub fn explore_wasm(&self, seed_joint_state: Vec<f64>, num_walks: usize, max_postures: usize) -> Vec<f64> {
+        let seed_joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&seed_joint_state)).expect("error");
+        let res = self.explore(&seed_joint_state, num_walks, max_postures).expect("error");
+        let mut out_vec = vec![];
+        for p in res.postures() { out_vec.extend(p.joint_state().joint_state().iter()); }
+        out_vec
+    }
+}
+
+/// A posture on the self-motion manifold, along with measures that help choose among postures:
+/// the frame's manipulability, sqrt(det(J J^T)), and the smallest distance of any DOF to its bounds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SelfMotionPosture {
+    joint_state: RobotJointState,
+    manipulability: f64,
+    joint_limit_margin: f64
+}
+impl SelfMotionPosture {
+    pub fn joint_state(&self) -> &RobotJointState {
+        &self.joint_state
+    }
+    pub fn manipulability(&self) -> f64 {
+        self.manipulability
+    }
+    pub fn joint_limit_margin(&self) -> f64 {
+        self.joint_limit_margin
+    }
+}
+
+/// Output of `SelfMotionExplorer::explore`.  Postures are in selection order, so any prefix is
+/// itself a diverse set.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SelfMotionExplorationOutput {
+    target_pose: OptimaSE3Pose,
+    postures: Vec<SelfMotionPosture>,
+    num_candidates: usize
+}
+impl SelfMotionExplorationOutput {
+    pub fn target_pose(&self) -> &OptimaSE3Pose {
+        &self.target_pose
+    }
+    pub fn postures(&self) -> &Vec<SelfMotionPosture> {
+        &self.postures
+    }
+    /// The number of postures visited during exploration (including the seed).
+    pub fn num_candidates(&self) -> usize {
+        self.num_candidates
+    }
+    /// The posture with the highest manipulability.
+    pub fn most_manipulable_posture(&self) -> Option<&SelfMotionPosture> {
+        return self.postures.iter().max_by(|a, b| a.manipulability.partial_cmp(&b.manipulability).unwrap_or(std::cmp::Ordering::Equal));
+    }
+    /// The posture that is farthest from its joint bounds.
+    pub fn most_centered_posture(&self) -> Option<&SelfMotionPosture> {
+        return self.postures.iter().max_by(|a, b| a.joint_limit_margin.partial_cmp(&b.joint_limit_margin).unwrap_or(std::cmp::Ordering::Equal));
+    }
+}
+
+crate::impl_py_pickle!(SelfMotionExplorer);
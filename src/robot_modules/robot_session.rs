@@ -0,0 +1,142 @@
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_configuration_module::RobotConfigurationModule;
+use crate::robot_modules::robot_geometric_shape_module::{RobotGeometricShapeModule, RobotLinkShapeRepresentation};
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateModule, RobotJointStateType};
+use crate::robot_modules::robot_kinematics_module::{RobotFKResult, RobotKinematicsModule};
+use crate::robot_modules::robot_model_module::RobotModelModule;
+use crate::robot_set_modules::robot_set::RobotSet;
+use crate::robot_set_modules::robot_set_configuration_module::RobotSetConfigurationModule;
+use crate::scenes::robot_geometric_shape_scene::{EnvObjSpawner, RobotGeometricShapeScene};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_robot::robot_module_utils::RobotNames;
+use crate::utils::utils_se3::optima_se3_pose::OptimaSE3PoseType;
+use crate::utils::utils_traits::SaveAndLoadable;
+
+/// A complete, self contained snapshot of a robot setup: the configuration (which includes the
+/// robot model), the joint state and kinematics modules, the geometric shape module, the current
+/// joint state, and, optionally, an environment scene around the robot.
+///
+/// Every module is serialized by value, so a saved session restores fully initialized without
+/// reading any robot assets from disk (e.g., robots built with `RobotModelBuilder` or edited at
+/// runtime come back exactly as they were saved).  Use `SaveAndLoadable` to save a session to a
+/// single json file, or `to_bytes` and `from_bytes` to get it as one blob.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobotSession {
+    robot_configuration_module: RobotConfigurationModule,
+    robot_joint_state_module: RobotJointStateModule,
+    robot_kinematics_module: RobotKinematicsModule,
+    robot_geometric_shape_module: RobotGeometricShapeModule,
+    robot_joint_state: RobotJointState,
+    environment: Option<RobotGeometricShapeScene>
+}
+impl RobotSession {
+    pub fn new_from_names(robot_names: RobotNames) -> Result<Self, OptimaError> {
+        let robot_configuration_module = RobotConfigurationModule::new_from_names(robot_names)?;
+        return Self::new(robot_configuration_module);
+    }
+    /// Starts a session at the all zeros DOF joint state, with no environment.
+    pub fn new(robot_configuration_module: RobotConfigurationModule) -> Result<Self, OptimaError> {
+        let robot_kinematics_module = RobotKinematicsModule::new(robot_configuration_module.clone());
+        let robot_joint_state_module = robot_kinematics_module.robot_joint_state_module().clone();
+        let robot_geometric_shape_module = RobotGeometricShapeModule::new(robot_configuration_module.clone(), false)?;
+        let robot_joint_state = robot_joint_state_module.spawn_zeros_robot_joint_state(RobotJointStateType::DOF);
+
+        Ok(Self {
+            robot_configuration_module,
+            robot_joint_state_module,
+            robot_kinematics_module,
+            robot_geometric_shape_module,
+            robot_joint_state,
+            environment: None
+        })
+    }
+    /// Replaces the environment with a scene around the session's robot, spawned from the given
+    /// environment objects.
+    pub fn set_environment(&mut self, robot_link_shape_representation: RobotLinkShapeRepresentation, env_obj_spawners: Vec<EnvObjSpawner>) -> Result<(), OptimaError> {
+        let mut robot_set_configuration_module = RobotSetConfigurationModule::new_empty();
+        robot_set_configuration_module.add_robot_configuration(self.robot_configuration_module.clone())?;
+        let robot_set = RobotSet::new_from_robot_set_configuration_module(robot_set_configuration_module);
+        self.environment = Some(RobotGeometricShapeScene::new(robot_set, robot_link_shape_representation, env_obj_spawners)?);
+        Ok(())
+    }
+    pub fn clear_environment(&mut self) {
+        self.environment = None;
+    }
+    /// Sets the current joint state.  The state is converted to a DOF state if it is given as a Full
+    /// state.
+    pub fn set_robot_joint_state(&mut self, robot_joint_state: &RobotJointState) -> Result<(), OptimaError> {
+        let robot_joint_state = self.robot_joint_state_module.spawn_robot_joint_state(robot_joint_state.joint_state().clone(), robot_joint_state.robot_joint_state_type().clone())?;
+        self.robot_joint_state = self.robot_joint_state_module.convert_joint_state_to_dof_state(&robot_joint_state)?;
+        Ok(())
+    }
+    /// Forward kinematics at the current joint state.
+    pub fn compute_fk(&self, t: &OptimaSE3PoseType) -> Result<RobotFKResult, OptimaError> {
+        return self.robot_kinematics_module.compute_fk(&self.robot_joint_state, t);
+    }
+    /// Serializes the whole session into one blob.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        return self.get_serialization_string().into_bytes();
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, OptimaError> {
+        let json_str = std::str::from_utf8(bytes).map_err(|e| OptimaError::new_generic_error_str(&format!("Robot session bytes are not valid utf-8: {}", e), file!(), line!()))?;
+        return Self::load_from_json_string(json_str);
+    }
+    pub fn robot_model_module(&self) -> &RobotModelModule {
+        self.robot_configuration_module.robot_model_module()
+    }
+    pub fn robot_configuration_module(&self) -> &RobotConfigurationModule {
+        &self.robot_configuration_module
+    }
+    pub fn robot_joint_state_module(&self) -> &RobotJointStateModule {
+        &self.robot_joint_state_module
+    }
+    pub fn robot_kinematics_module(&self) -> &RobotKinematicsModule {
+        &self.robot_kinematics_module
+    }
+    pub fn robot_geometric_shape_module(&self) -> &RobotGeometricShapeModule {
+        &self.robot_geometric_shape_module
+    }
+    pub fn robot_joint_state(&self) -> &RobotJointState {
+        &self.robot_joint_state
+    }
+    pub fn environment(&self) -> &Option<RobotGeometricShapeScene> {
+        &self.environment
+    }
+    pub fn environment_mut(&mut self) -> &mut Option<RobotGeometricShapeScene> {
+        &mut self.environment
+    }
+    /// Checks that the loaded modules all describe the same robot and that the saved joint state
+    /// fits it, so that a hand edited or mismatched file fails on load rather than on first use.
+    fn check_consistency(&self) -> Result<(), OptimaError> {
+        let robot_name = self.robot_configuration_module.robot_name();
+        let names = vec![
+            self.robot_joint_state_module.robot_configuration_module().robot_name(),
+            self.robot_kinematics_module.robot_configuration_module().robot_name(),
+            self.robot_geometric_shape_module.robot_kinematics_module().robot_configuration_module().robot_name()
+        ];
+        for name in names {
+            if name != robot_name {
+                return Err(OptimaError::new_generic_error_str(&format!("Robot session modules describe different robots ({} and {}).", robot_name, name), file!(), line!()));
+            }
+        }
+
+        if self.robot_joint_state.robot_joint_state_type() != &RobotJointStateType::DOF || self.robot_joint_state.len() != self.robot_joint_state_module.num_dofs() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("RobotSession::check_consistency", self.robot_joint_state.len(), self.robot_joint_state_module.num_dofs(), file!(), line!()));
+        }
+
+        Ok(())
+    }
+}
+impl SaveAndLoadable for RobotSession {
+    type SaveType = Self;
+
+    fn get_save_serialization_object(&self) -> Self::SaveType {
+        self.clone()
+    }
+
+    fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
+        let load: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
+        load.check_consistency()?;
+        return Ok(load);
+    }
+}
@@ -0,0 +1,374 @@
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_geometric_shape_module::{RobotLinkShapeRepresentation, RobotShapeCollection};
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateModule, RobotJointStateType};
+use crate::robot_modules::robot_kinematics_module::RobotKinematicsModule;
+use crate::robot_modules::robot_model_module::RobotModelModule;
+use crate::utils::utils_console::{optima_print, PrintColor, PrintMode};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_robot::joint::JointAxisPrimitiveType;
+use crate::utils::utils_robot::robot_ids::LinkId;
+use crate::utils::utils_se3::optima_se3_pose::OptimaSE3PoseType;
+use crate::utils::utils_shape_geometry::geometric_shape::{GeometricShapeSignature, LogCondition, StopCondition};
+use crate::utils::utils_shape_geometry::shape_collection::ShapeCollectionQuery;
+
+/// Settings for building `RobotShapeDistanceLookupTables` during preprocessing (see
+/// `PreprocessingConfig`).  A table is built for every non-skipped shape pair whose relative pose
+/// depends on at most max_joint_axes joint axes, sampling the exact distance on a regular grid with
+/// resolution points per axis.  num_validation_samples random joint states are then used to measure
+/// the worst error of each table, which queries use to decide when to trust a prediction.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DistanceLookupTableConfig {
+    max_joint_axes: usize,
+    resolution: usize,
+    num_validation_samples: usize,
+    robot_link_shape_representations: Vec<RobotLinkShapeRepresentation>
+}
+impl DistanceLookupTableConfig {
+    /// Tables are built for the `Cubes` and `ConvexShapes` representations by default (see
+    /// `set_robot_link_shape_representations`).
+    pub fn new(max_joint_axes: usize, resolution: usize, num_validation_samples: usize) -> Result<Self, OptimaError> {
+        if max_joint_axes > 4 {
+            return Err(OptimaError::new_generic_error_str(&format!("Distance lookup tables support at most 4 joint axes (given {}).", max_joint_axes), file!(), line!()));
+        }
+        if resolution < 2 {
+            return Err(OptimaError::new_generic_error_str(&format!("Distance lookup table resolution must be at least 2 (given {}).", resolution), file!(), line!()));
+        }
+        if num_validation_samples == 0 {
+            return Err(OptimaError::new_generic_error_str("Distance lookup tables need at least one validation sample.", file!(), line!()));
+        }
+
+        Ok(Self {
+            max_joint_axes,
+            resolution,
+            num_validation_samples,
+            robot_link_shape_representations: vec![RobotLinkShapeRepresentation::Cubes, RobotLinkShapeRepresentation::ConvexShapes]
+        })
+    }
+    pub fn set_robot_link_shape_representations(&mut self, robot_link_shape_representations: Vec<RobotLinkShapeRepresentation>) -> Result<(), OptimaError> {
+        if robot_link_shape_representations.contains(&RobotLinkShapeRepresentation::Auto) {
+            return Err(OptimaError::new_generic_error_str("Distance lookup tables cannot be built for the Auto representation.", file!(), line!()));
+        }
+        self.robot_link_shape_representations = robot_link_shape_representations;
+        Ok(())
+    }
+    pub fn max_joint_axes(&self) -> usize {
+        self.max_joint_axes
+    }
+    pub fn resolution(&self) -> usize {
+        self.resolution
+    }
+    pub fn num_validation_samples(&self) -> usize {
+        self.num_validation_samples
+    }
+    pub fn robot_link_shape_representations(&self) -> &Vec<RobotLinkShapeRepresentation> {
+        &self.robot_link_shape_representations
+    }
+}
+impl Default for DistanceLookupTableConfig {
+    fn default() -> Self {
+        Self::new(3, 12, 300).expect("error")
+    }
+}
+
+/// Per-pair regressions of shape distances over the few joint axes that move one shape relative to
+/// the other, e.g., the elbow and wrist joints between a forearm and a gripper finger.  Each table
+/// holds exact distances on a regular grid over the bounds of those axes and predicts the distance
+/// at any joint state by multilinear interpolation, with no forward kinematics or geometry at all.
+/// Every table also stores its worst observed prediction error (see `DistanceLookupTableConfig`).
+///
+/// Tables are built on the base model of the robot during preprocessing and saved with the shape
+/// collection they belong to.  Pairs whose relative pose depends on ball joints, coupled axes,
+/// unbounded axes, or too many axes get no table.  Tables are consumed by
+/// `RobotGeometricShapeModule::approximate_distance_query`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobotShapeDistanceLookupTables {
+    axis_groups: Vec<DistanceLookupAxisGroup>,
+    tables: Vec<ShapePairDistanceLookupTable>
+}
+impl RobotShapeDistanceLookupTables {
+    /// Builds tables for all non-skipped pairs of the given shape collection.  The modules must be
+    /// those of the base model of the robot.  Validation samples are drawn from the given random
+    /// number generator.
+    pub fn build<R: Rng + ?Sized>(robot_shape_collection: &RobotShapeCollection,
+                                  base_robot_model_module: &RobotModelModule,
+                                  base_robot_kinematics_module: &RobotKinematicsModule,
+                                  base_robot_joint_state_module: &RobotJointStateModule,
+                                  config: &DistanceLookupTableConfig,
+                                  rng: &mut R) -> Result<Self, OptimaError> {
+        let shape_collection = robot_shape_collection.shape_collection();
+        let shapes = shape_collection.shapes();
+
+        // Pairs that depend on the same joint axes share a grid, so every grid point costs a single
+        // forward kinematics call and one distance query over all pairs of the group.
+        let mut axis_groups: Vec<DistanceLookupAxisGroup> = vec![];
+        let mut group_pairs: Vec<Vec<(usize, usize)>> = vec![];
+        for i in 0..shapes.len() {
+            for j in (i + 1)..shapes.len() {
+                if *shape_collection.skips().data_cell(i, j)?.curr_value() { continue; }
+                let (link_idx1, link_idx2) = match (shapes[i].signature(), shapes[j].signature()) {
+                    (GeometricShapeSignature::RobotLink { link_idx: link_idx1, .. }, GeometricShapeSignature::RobotLink { link_idx: link_idx2, .. }) => { (*link_idx1, *link_idx2) }
+                    _ => { continue; }
+                };
+                let joint_axes = match Self::relative_pose_joint_axes(base_robot_model_module, link_idx1, link_idx2)? {
+                    Some(joint_axes) if joint_axes.len() <= config.max_joint_axes => { joint_axes }
+                    _ => { continue; }
+                };
+
+                match axis_groups.iter().position(|g| g.joint_axes == joint_axes) {
+                    Some(group_idx) => { group_pairs[group_idx].push((i, j)); }
+                    None => {
+                        let mut bounds = vec![];
+                        for (joint_idx, joint_sub_idx) in &joint_axes {
+                            bounds.push(base_robot_model_module.joints()[*joint_idx].joint_axes()[*joint_sub_idx].bounds());
+                        }
+                        axis_groups.push(DistanceLookupAxisGroup { joint_axes, bounds, resolution: config.resolution });
+                        group_pairs.push(vec![(i, j)]);
+                    }
+                }
+            }
+        }
+
+        let mut tables = vec![];
+        for (group_idx, axis_group) in axis_groups.iter().enumerate() {
+            let full_state_idxs = match axis_group.full_state_idxs(base_robot_joint_state_module) {
+                Some(full_state_idxs) => { full_state_idxs }
+                None => { return Err(OptimaError::new_generic_error_str(&format!("Joint axes {:?} are not part of the base full state.", axis_group.joint_axes), file!(), line!())); }
+            };
+            let pairs = &group_pairs[group_idx];
+            let mut values = vec![vec![f64::NAN; axis_group.num_grid_points()]; pairs.len()];
+
+            let mut joint_state = base_robot_joint_state_module.spawn_zeros_robot_joint_state(RobotJointStateType::Full);
+            for grid_idx in 0..axis_group.num_grid_points() {
+                let grid_point = axis_group.grid_point(grid_idx);
+                for (k, full_state_idx) in full_state_idxs.iter().enumerate() { joint_state[*full_state_idx] = grid_point[k]; }
+
+                let distances = Self::exact_distances(robot_shape_collection, base_robot_kinematics_module, &joint_state, pairs)?;
+                for (pair_idx, distance) in distances.iter().enumerate() { values[pair_idx][grid_idx] = *distance; }
+            }
+
+            for (pair_idx, (i, j)) in pairs.iter().enumerate() {
+                // Pairs that were not computed (e.g., links without poses) get no table.
+                if values[pair_idx].iter().any(|v| v.is_nan()) { continue; }
+                tables.push(ShapePairDistanceLookupTable {
+                    shape_idx1: *i,
+                    shape_idx2: *j,
+                    axis_group_idx: group_idx,
+                    values: values[pair_idx].clone(),
+                    max_error: 0.0
+                });
+            }
+        }
+
+        let mut out_self = Self { axis_groups, tables };
+
+        // Validation on random states, where all other axes also move.
+        let all_pairs: Vec<(usize, usize)> = out_self.tables.iter().map(|t| (t.shape_idx1, t.shape_idx2)).collect();
+        for _ in 0..config.num_validation_samples {
+            if all_pairs.is_empty() { break; }
+            let sample = base_robot_joint_state_module.sample_joint_state_with_rng(&RobotJointStateType::Full, rng);
+            let distances = Self::exact_distances(robot_shape_collection, base_robot_kinematics_module, &sample, &all_pairs)?;
+            let grid_points = out_self.grid_points(base_robot_joint_state_module, &sample);
+            for (table_idx, distance) in distances.iter().enumerate() {
+                let table = &out_self.tables[table_idx];
+                let prediction = match &grid_points[table.axis_group_idx] {
+                    Some(x) => { out_self.axis_groups[table.axis_group_idx].interpolate(&table.values, x) }
+                    None => { continue; }
+                };
+                let error = (prediction - distance).abs();
+                if error.is_finite() && error > out_self.tables[table_idx].max_error { out_self.tables[table_idx].max_error = error; }
+            }
+        }
+
+        optima_print(&format!("Built {} distance lookup tables over {} joint axis groups.", out_self.tables.len(), out_self.axis_groups.len()), PrintMode::Println, PrintColor::None, false);
+
+        Ok(out_self)
+    }
+    /// Returns the point of every axis group at the given full state of the given joint state module,
+    /// or None for groups whose axes are not part of that state (e.g., in configurations that remove
+    /// the corresponding joints).
+    pub fn grid_points(&self, robot_joint_state_module: &RobotJointStateModule, full_joint_state: &RobotJointState) -> Vec<Option<Vec<f64>>> {
+        let mut out_vec = vec![];
+        for axis_group in &self.axis_groups {
+            let point = match axis_group.full_state_idxs(robot_joint_state_module) {
+                None => { None }
+                Some(full_state_idxs) => {
+                    if full_state_idxs.iter().any(|idx| *idx >= full_joint_state.len()) { None } else { Some(full_state_idxs.iter().map(|idx| full_joint_state[*idx]).collect()) }
+                }
+            };
+            out_vec.push(point);
+        }
+        out_vec
+    }
+    /// Returns the predicted (margin free) distance of the given table at the given grid points (as
+    /// returned by `grid_points`), or None if the table's axis group has no point.
+    pub fn predict(&self, table_idx: usize, grid_points: &Vec<Option<Vec<f64>>>) -> Result<Option<f64>, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(table_idx, self.tables.len(), file!(), line!())?;
+        let table = &self.tables[table_idx];
+        return match &grid_points[table.axis_group_idx] {
+            None => { Ok(None) }
+            Some(x) => { Ok(Some(self.axis_groups[table.axis_group_idx].interpolate(&table.values, x))) }
+        }
+    }
+    pub fn axis_groups(&self) -> &Vec<DistanceLookupAxisGroup> {
+        &self.axis_groups
+    }
+    pub fn tables(&self) -> &Vec<ShapePairDistanceLookupTable> {
+        &self.tables
+    }
+    /// The (joint_idx, joint_sub_idx) joint axes that move link_idx2 relative to link_idx1, i.e., the
+    /// axes of the joints on the path between the two links in the kinematic tree.  Axes with fixed
+    /// values are left out.  Returns None if the relative pose depends on a ball joint, a coupled axis,
+    /// or an axis without finite bounds.
+    fn relative_pose_joint_axes(robot_model_module: &RobotModelModule, link_idx1: usize, link_idx2: usize) -> Result<Option<Vec<(usize, usize)>>, OptimaError> {
+        let chain1 = Self::preceding_joint_idxs(robot_model_module, link_idx1)?;
+        let chain2 = Self::preceding_joint_idxs(robot_model_module, link_idx2)?;
+
+        // Joints shared by both chains move both links together.
+        let mut joint_idxs: Vec<usize> = chain1.iter().filter(|j| !chain2.contains(j)).cloned().collect();
+        joint_idxs.extend(chain2.iter().filter(|j| !chain1.contains(j)).cloned());
+        joint_idxs.sort();
+
+        let mut out_vec = vec![];
+        for joint_idx in joint_idxs {
+            for joint_axis in robot_model_module.joints()[joint_idx].joint_axes() {
+                if joint_axis.is_fixed() { continue; }
+                if joint_axis.is_coupled() || joint_axis.axis_primitive_type() == &JointAxisPrimitiveType::QuaternionComponent { return Ok(None); }
+                let (lower, upper) = joint_axis.bounds();
+                if !(lower.is_finite() && upper.is_finite() && upper > lower) { return Ok(None); }
+                out_vec.push((joint_idx, joint_axis.joint_sub_dof_idx()));
+            }
+        }
+
+        Ok(Some(out_vec))
+    }
+    fn preceding_joint_idxs(robot_model_module: &RobotModelModule, link_idx: usize) -> Result<Vec<usize>, OptimaError> {
+        let mut out_vec = vec![];
+        let mut curr_link = robot_model_module.link(LinkId::new(link_idx))?;
+        while let (Some(preceding_link_id), Some(preceding_joint_idx)) = (curr_link.preceding_link_id(), curr_link.preceding_joint_idx()) {
+            out_vec.push(preceding_joint_idx);
+            curr_link = robot_model_module.link(preceding_link_id)?;
+        }
+        Ok(out_vec)
+    }
+    /// The margin free distances of the given pairs at the given full state, in the order of pairs
+    /// (NaN for pairs that were not computed).
+    fn exact_distances(robot_shape_collection: &RobotShapeCollection, robot_kinematics_module: &RobotKinematicsModule, joint_state: &RobotJointState, pairs: &Vec<(usize, usize)>) -> Result<Vec<f64>, OptimaError> {
+        let shape_collection = robot_shape_collection.shape_collection();
+        let fk_res = robot_kinematics_module.compute_fk(joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+        let poses = robot_shape_collection.recover_poses(&fk_res)?;
+        let mut pairs_list = shape_collection.spawn_query_pairs_list(true);
+        pairs_list.add_pairs(pairs.clone());
+        let input = ShapeCollectionQuery::Distance { poses: &poses, inclusion_list: &Some(&pairs_list) };
+        let res = shape_collection.shape_collection_query(&input, StopCondition::None, LogCondition::LogAll, false)?;
+
+        let mut out_vec = vec![f64::NAN; pairs.len()];
+        for output in res.outputs() {
+            let signatures = output.signatures();
+            let shape_idx1 = shape_collection.get_shape_idx_from_signature(&signatures[0])?;
+            let shape_idx2 = shape_collection.get_shape_idx_from_signature(&signatures[1])?;
+            let pair_idx = pairs.iter().position(|p| *p == (shape_idx1, shape_idx2) || *p == (shape_idx2, shape_idx1));
+            if let Some(pair_idx) = pair_idx {
+                out_vec[pair_idx] = output.raw_output().unwrap_distance()? + shape_collection.collision_margins().pair_margin(shape_idx1, shape_idx2);
+            }
+        }
+        Ok(out_vec)
+    }
+}
+
+/// The joint axes, bounds, and grid resolution shared by the tables of all shape pairs whose
+/// relative pose depends on the same joint axes.  Axes are given as (joint_idx, joint_sub_idx) in
+/// the robot model, so the grid applies to every configuration that keeps them.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DistanceLookupAxisGroup {
+    joint_axes: Vec<(usize, usize)>,
+    bounds: Vec<(f64, f64)>,
+    resolution: usize
+}
+impl DistanceLookupAxisGroup {
+    pub fn num_grid_points(&self) -> usize {
+        self.resolution.pow(self.joint_axes.len() as u32)
+    }
+    /// Grid points are ordered with the first axis varying fastest.
+    pub fn grid_point(&self, grid_idx: usize) -> Vec<f64> {
+        let mut out_vec = vec![];
+        let mut rest = grid_idx;
+        for (lower, upper) in &self.bounds {
+            let k = rest % self.resolution;
+            rest /= self.resolution;
+            out_vec.push(lower + (upper - lower) * k as f64 / (self.resolution - 1) as f64);
+        }
+        out_vec
+    }
+    /// Multilinear interpolation of the given grid values at x (clamped to the bounds).
+    pub fn interpolate(&self, values: &Vec<f64>, x: &Vec<f64>) -> f64 {
+        let num_axes = self.joint_axes.len();
+        let mut base_idxs = vec![0; num_axes];
+        let mut fractions = vec![0.0; num_axes];
+        for (k, (lower, upper)) in self.bounds.iter().enumerate() {
+            let t = ((x[k] - lower) / (upper - lower)).max(0.0).min(1.0) * (self.resolution - 1) as f64;
+            let base_idx = (t.floor() as usize).min(self.resolution - 2);
+            base_idxs[k] = base_idx;
+            fractions[k] = t - base_idx as f64;
+        }
+
+        let mut out = 0.0;
+        for corner in 0..(1usize << num_axes) {
+            let mut weight = 1.0;
+            let mut grid_idx = 0;
+            let mut stride = 1;
+            for k in 0..num_axes {
+                let upper_corner = (corner >> k) & 1 == 1;
+                weight *= if upper_corner { fractions[k] } else { 1.0 - fractions[k] };
+                grid_idx += (base_idxs[k] + if upper_corner { 1 } else { 0 }) * stride;
+                stride *= self.resolution;
+            }
+            if weight != 0.0 { out += weight * values[grid_idx]; }
+        }
+        out
+    }
+    pub fn joint_axes(&self) -> &Vec<(usize, usize)> {
+        &self.joint_axes
+    }
+    pub fn bounds(&self) -> &Vec<(f64, f64)> {
+        &self.bounds
+    }
+    pub fn resolution(&self) -> usize {
+        self.resolution
+    }
+    fn full_state_idxs(&self, robot_joint_state_module: &RobotJointStateModule) -> Option<Vec<usize>> {
+        let mut out_vec = vec![];
+        for (joint_idx, joint_sub_idx) in &self.joint_axes {
+            match robot_joint_state_module.map_joint_idx_and_sub_dof_idx_to_joint_state_idx(*joint_idx, *joint_sub_idx, &RobotJointStateType::Full) {
+                Ok(idx) => { out_vec.push(idx); }
+                Err(_) => { return None; }
+            }
+        }
+        Some(out_vec)
+    }
+}
+
+/// The distance table of one shape pair (by shape index in its shape collection).  max_error is the
+/// largest absolute prediction error seen during validation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ShapePairDistanceLookupTable {
+    shape_idx1: usize,
+    shape_idx2: usize,
+    axis_group_idx: usize,
+    values: Vec<f64>,
+    max_error: f64
+}
+impl ShapePairDistanceLookupTable {
+    pub fn shape_idx1(&self) -> usize {
+        self.shape_idx1
+    }
+    pub fn shape_idx2(&self) -> usize {
+        self.shape_idx2
+    }
+    pub fn axis_group_idx(&self) -> usize {
+        self.axis_group_idx
+    }
+    pub fn max_error(&self) -> f64 {
+        self.max_error
+    }
+}
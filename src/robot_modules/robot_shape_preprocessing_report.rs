@@ -0,0 +1,113 @@
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_geometric_shape_module::{DeterministicPreprocessing, RobotLinkShapeRepresentation, SkipDecisionThresholds};
+use crate::utils::utils_console::{optima_print, PrintColor, PrintMode};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_files::optima_path::{OptimaAssetLocation, OptimaStemCellPath, RobotModuleJsonType};
+
+/// A machine-readable record of the skip decisions made while preprocessing a
+/// `RobotGeometricShapeModule`: for every shape representation and every pair of shapes, how often
+/// the pair was sampled and found in collision, its average distance, and why it was skipped (if it
+/// was).  Preprocessing writes it next to the shape geometry module json (see `save_as_asset`), so
+/// that users can audit why a pair is never checked and tune the thresholds, rather than trusting the
+/// skip matrices blindly.
+///
+/// The report is plain json (without a save format version envelope) and only contains values that
+/// are reproducible in deterministic mode, so it can be diffed between machines.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobotShapePreprocessingReport {
+    robot_name: String,
+    deterministic: Option<DeterministicPreprocessing>,
+    representations: Vec<RepresentationPreprocessingReport>
+}
+impl RobotShapePreprocessingReport {
+    pub fn new(robot_name: &str, deterministic: Option<DeterministicPreprocessing>) -> Self {
+        Self {
+            robot_name: robot_name.to_string(),
+            deterministic,
+            representations: vec![]
+        }
+    }
+    /// Adds the report of a representation, replacing an earlier report of the same representation.
+    pub fn add_representation_report(&mut self, representation_report: RepresentationPreprocessingReport) {
+        self.representations.retain(|r| r.robot_link_shape_representation != representation_report.robot_link_shape_representation);
+        self.representations.push(representation_report);
+    }
+    pub fn save_as_asset(&self) -> Result<(), OptimaError> {
+        let mut path = OptimaStemCellPath::new_asset_path()?;
+        path.append_file_location(&OptimaAssetLocation::RobotModuleJson { robot_name: self.robot_name.clone(), t: RobotModuleJsonType::ShapeGeometryPreprocessingReport });
+        path.save_object_to_file_as_json(self)
+    }
+    /// Loads the report written by the last preprocessing of the given robot.  Returns None if the
+    /// robot was preprocessed before reports were written.
+    pub fn load_as_asset(robot_name: &str) -> Result<Option<Self>, OptimaError> {
+        let mut path = OptimaStemCellPath::new_asset_path()?;
+        path.append_file_location(&OptimaAssetLocation::RobotModuleJson { robot_name: robot_name.to_string(), t: RobotModuleJsonType::ShapeGeometryPreprocessingReport });
+        if !path.exists() { return Ok(None); }
+        return Ok(Some(path.load_object_from_json_file()?));
+    }
+    pub fn robot_name(&self) -> &str {
+        &self.robot_name
+    }
+    pub fn deterministic(&self) -> &Option<DeterministicPreprocessing> {
+        &self.deterministic
+    }
+    pub fn representations(&self) -> &Vec<RepresentationPreprocessingReport> {
+        &self.representations
+    }
+    pub fn representation(&self, robot_link_shape_representation: &RobotLinkShapeRepresentation) -> Option<&RepresentationPreprocessingReport> {
+        return self.representations.iter().find(|r| &r.robot_link_shape_representation == robot_link_shape_representation);
+    }
+    pub fn print_summary(&self) {
+        optima_print(&format!("Preprocessing report for robot {} ---> ", self.robot_name), PrintMode::Println, PrintColor::Blue, true);
+        for r in &self.representations {
+            let count = |reason: PairSkipReason| r.pairs.iter().filter(|p| p.skip_reason == Some(reason.clone())).count();
+            optima_print(&format!("   > {:?}: {} samples, {} of {} pairs skipped ({} same link, {} always colliding, {} never colliding)",
+                                  r.robot_link_shape_representation, r.num_samples, r.num_skipped_pairs(), r.pairs.len(),
+                                  count(PairSkipReason::SameLink), count(PairSkipReason::AlwaysColliding), count(PairSkipReason::NeverColliding)), PrintMode::Println, PrintColor::None, false);
+        }
+    }
+}
+
+/// The part of a `RobotShapePreprocessingReport` for one shape representation, including the
+/// thresholds the skip decisions were made with.  pairs holds one record per pair of distinct shapes
+/// (shape_idx1 < shape_idx2).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RepresentationPreprocessingReport {
+    pub robot_link_shape_representation: RobotLinkShapeRepresentation,
+    pub num_samples: usize,
+    pub skip_decision_thresholds: SkipDecisionThresholds,
+    pub pairs: Vec<ShapePairPreprocessingRecord>
+}
+impl RepresentationPreprocessingReport {
+    pub fn num_skipped_pairs(&self) -> usize {
+        return self.pairs.iter().filter(|p| p.skip_reason.is_some()).count();
+    }
+}
+
+/// The preprocessing record of one pair of shapes.  num_samples is the number of samples in which
+/// the pair's distance was computed, and collision_ratio is the fraction of all samples of the
+/// representation in which the pair was in collision.  skip_reason is None if the pair is checked.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ShapePairPreprocessingRecord {
+    pub shape_idx1: usize,
+    pub shape_idx2: usize,
+    pub link_name1: String,
+    pub link_name2: String,
+    pub num_samples: usize,
+    pub num_collisions: usize,
+    pub collision_ratio: f64,
+    pub average_distance: f64,
+    pub skip_reason: Option<PairSkipReason>
+}
+
+/// Why preprocessing disabled a pair of shapes.
+/// - `SameLink`: both shapes belong to the same link, so they never move relative to each other.
+/// - `AlwaysColliding`: the pair was in collision in more than the always colliding ratio of samples
+/// (e.g., adjacent links whose meshes overlap at the joint).
+/// - `NeverColliding`: the pair was never in collision in enough samples to be considered unreachable.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PairSkipReason {
+    SameLink,
+    AlwaysColliding,
+    NeverColliding
+}
@@ -0,0 +1,206 @@
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_configuration_module::RobotConfigurationModule;
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateModule, RobotJointStateType};
+use crate::robot_modules::robot_kinematics_module::{RobotFKResult, RobotKinematicsModule};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_robot::robot_module_utils::RobotNames;
+use crate::utils::utils_se3::optima_se3_pose::OptimaSE3PoseType;
+
+/// A lightweight, purely kinematic simulation of a robot that is advanced at a fixed time step, e.g.,
+/// as the "robot" in a teleoperation or visual servoing loop.  The current command (a DOF velocity or
+/// acceleration, see `RobotSimCommand`) is held until it is replaced and is integrated by `step`.
+///
+/// Each step, the velocity is clamped to the URDF velocity limits (see
+/// `RobotJointStateModule::get_joint_state_velocity_limits`) and, if set, changes in velocity are
+/// clamped to the acceleration limits.  Positions are clamped to the joint bounds, and the velocity of
+/// any DOF that hits a bound is set to zero.  There are no dynamics, contacts, or collision checks.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobotSimState {
+    robot_joint_state_module: RobotJointStateModule,
+    robot_kinematics_module: RobotKinematicsModule,
+    time_step: f64,
+    time: f64,
+    robot_joint_state: RobotJointState,
+    velocity: DVector<f64>,
+    velocity_limits: Vec<f64>,
+    acceleration_limits: Option<Vec<f64>>,
+    command: RobotSimCommand
+}
+impl RobotSimState {
+    pub fn new_from_names(robot_names: RobotNames, time_step: f64) -> Result<Self, OptimaError> {
+        let robot_configuration_module = RobotConfigurationModule::new_from_names(robot_names)?;
+        return Self::new(robot_configuration_module, time_step);
+    }
+    /// Starts at rest at the all zeros DOF joint state (clamped to the joint bounds), at time 0.0.
+    pub fn new(robot_configuration_module: RobotConfigurationModule, time_step: f64) -> Result<Self, OptimaError> {
+        if !(time_step > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Time step must be positive (given {}).", time_step), file!(), line!()));
+        }
+
+        let robot_joint_state_module = RobotJointStateModule::new(robot_configuration_module.clone());
+        let robot_kinematics_module = RobotKinematicsModule::new(robot_configuration_module);
+        let zeros = robot_joint_state_module.spawn_zeros_robot_joint_state(RobotJointStateType::DOF);
+        let robot_joint_state = robot_joint_state_module.clamp_joint_state_to_bounds(&zeros);
+        let num_dofs = robot_joint_state_module.num_dofs();
+        let velocity_limits = robot_joint_state_module.get_joint_state_velocity_limits(&RobotJointStateType::DOF);
+
+        Ok(Self {
+            robot_joint_state_module,
+            robot_kinematics_module,
+            time_step,
+            time: 0.0,
+            robot_joint_state,
+            velocity: DVector::zeros(num_dofs),
+            velocity_limits,
+            acceleration_limits: None,
+            command: RobotSimCommand::Hold
+        })
+    }
+    /// Teleports the robot to the given joint state (converted to a DOF state and clamped to the joint
+    /// bounds) and brings it to rest.  The current command is kept.
+    pub fn set_robot_joint_state(&mut self, robot_joint_state: &RobotJointState) -> Result<(), OptimaError> {
+        let robot_joint_state = self.robot_joint_state_module.spawn_robot_joint_state(robot_joint_state.joint_state().clone(), robot_joint_state.robot_joint_state_type().clone())?;
+        let robot_joint_state = self.robot_joint_state_module.convert_joint_state_to_dof_state(&robot_joint_state)?;
+        self.robot_joint_state = self.robot_joint_state_module.clamp_joint_state_to_bounds(&robot_joint_state);
+        self.velocity = DVector::zeros(self.num_dofs());
+        Ok(())
+    }
+    /// Sets per DOF acceleration limits (None removes them).  These bound how quickly the velocity
+    /// can change under both velocity and acceleration commands.
+    pub fn set_acceleration_limits(&mut self, acceleration_limits: Option<Vec<f64>>) -> Result<(), OptimaError> {
+        if let Some(acceleration_limits) = &acceleration_limits {
+            self.check_dimension(acceleration_limits.len())?;
+            for (i, a) in acceleration_limits.iter().enumerate() {
+                if !(*a > 0.0) {
+                    return Err(OptimaError::new_generic_error_str(&format!("Acceleration limit for DOF {} must be positive (given {}).", i, a), file!(), line!()));
+                }
+            }
+        }
+        self.acceleration_limits = acceleration_limits;
+        Ok(())
+    }
+    /// Overrides the velocity limits taken from the robot's URDF.
+    pub fn set_velocity_limits(&mut self, velocity_limits: Vec<f64>) -> Result<(), OptimaError> {
+        self.check_dimension(velocity_limits.len())?;
+        for (i, v) in velocity_limits.iter().enumerate() {
+            if !(*v >= 0.0) {
+                return Err(OptimaError::new_generic_error_str(&format!("Velocity limit for DOF {} must be nonnegative (given {}).", i, v), file!(), line!()));
+            }
+        }
+        self.velocity_limits = velocity_limits;
+        Ok(())
+    }
+    /// Sets the command that will be held from the next step on.
+    pub fn set_command(&mut self, command: RobotSimCommand) -> Result<(), OptimaError> {
+        match &command {
+            RobotSimCommand::Velocity(v) => { self.check_dimension(v.len())?; }
+            RobotSimCommand::Acceleration(a) => { self.check_dimension(a.len())?; }
+            RobotSimCommand::Hold => { }
+        }
+        self.command = command;
+        Ok(())
+    }
+    /// Same as `set_command` with `RobotSimCommand::Velocity`.
+    pub fn command_velocity(&mut self, velocity: DVector<f64>) -> Result<(), OptimaError> {
+        return self.set_command(RobotSimCommand::Velocity(velocity));
+    }
+    /// Same as `set_command` with `RobotSimCommand::Acceleration`.
+    pub fn command_acceleration(&mut self, acceleration: DVector<f64>) -> Result<(), OptimaError> {
+        return self.set_command(RobotSimCommand::Acceleration(acceleration));
+    }
+    /// Commands a velocity of zero on every DOF (the robot decelerates under the acceleration limits,
+    /// if set, and stops immediately otherwise).
+    pub fn stop(&mut self) {
+        self.command = RobotSimCommand::Velocity(DVector::zeros(self.num_dofs()));
+    }
+    /// Advances the simulation by one time step under the current command.
+    pub fn step(&mut self) -> Result<(), OptimaError> {
+        let dt = self.time_step;
+        let n = self.num_dofs();
+
+        let mut velocity = self.velocity.clone();
+        for i in 0..n {
+            let max_delta = match &self.acceleration_limits {
+                None => { f64::INFINITY }
+                Some(acceleration_limits) => { acceleration_limits[i] * dt }
+            };
+            let delta = match &self.command {
+                RobotSimCommand::Velocity(v) => { v[i] - velocity[i] }
+                RobotSimCommand::Acceleration(a) => { a[i] * dt }
+                RobotSimCommand::Hold => { 0.0 }
+            };
+            velocity[i] += delta.max(-max_delta).min(max_delta);
+            velocity[i] = velocity[i].max(-self.velocity_limits[i]).min(self.velocity_limits[i]);
+        }
+
+        let new_state = self.robot_joint_state.joint_state() + &velocity * dt;
+        let bounds = self.robot_joint_state_module.get_joint_state_bounds(&RobotJointStateType::DOF);
+        for (i, (lower, upper)) in bounds.iter().enumerate() {
+            if new_state[i] <= *lower || new_state[i] >= *upper { velocity[i] = 0.0; }
+        }
+        let new_state = self.robot_joint_state_module.spawn_robot_joint_state(new_state, RobotJointStateType::DOF)?;
+
+        self.robot_joint_state = self.robot_joint_state_module.clamp_joint_state_to_bounds(&new_state);
+        self.velocity = velocity;
+        self.time += dt;
+        Ok(())
+    }
+    /// Advances the simulation by the given number of time steps.
+    pub fn step_n(&mut self, num_steps: usize) -> Result<(), OptimaError> {
+        for _ in 0..num_steps { self.step()?; }
+        Ok(())
+    }
+    /// Forward kinematics at the current joint state.
+    pub fn compute_fk(&self, t: &OptimaSE3PoseType) -> Result<RobotFKResult, OptimaError> {
+        return self.robot_kinematics_module.compute_fk(&self.robot_joint_state, t);
+    }
+    /// The current DOF joint state.
+    pub fn robot_joint_state(&self) -> &RobotJointState {
+        &self.robot_joint_state
+    }
+    /// The current DOF velocity.
+    pub fn velocity(&self) -> &DVector<f64> {
+        &self.velocity
+    }
+    pub fn command(&self) -> &RobotSimCommand {
+        &self.command
+    }
+    /// Simulated time elapsed since the simulation was created.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+    pub fn time_step(&self) -> f64 {
+        self.time_step
+    }
+    pub fn velocity_limits(&self) -> &Vec<f64> {
+        &self.velocity_limits
+    }
+    pub fn acceleration_limits(&self) -> &Option<Vec<f64>> {
+        &self.acceleration_limits
+    }
+    pub fn num_dofs(&self) -> usize {
+        self.robot_joint_state_module.num_dofs()
+    }
+    pub fn robot_joint_state_module(&self) -> &RobotJointStateModule {
+        &self.robot_joint_state_module
+    }
+    pub fn robot_kinematics_module(&self) -> &RobotKinematicsModule {
+        &self.robot_kinematics_module
+    }
+    fn check_dimension(&self, len: usize) -> Result<(), OptimaError> {
+        if len != self.num_dofs() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("RobotSimState", len, self.num_dofs(), file!(), line!()));
+        }
+        Ok(())
+    }
+}
+
+/// The command held by a `RobotSimState`.  Velocity and acceleration commands are per DOF.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum RobotSimCommand {
+    Velocity(DVector<f64>),
+    Acceleration(DVector<f64>),
+    /// Keeps the current velocity.
+    Hold
+}
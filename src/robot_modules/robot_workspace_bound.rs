@@ -0,0 +1,156 @@
+use nalgebra::Vector3;
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_joint_state_module::RobotJointStateType;
+use crate::robot_modules::robot_kinematics_module::RobotKinematicsModule;
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_robot::robot_ids::LinkId;
+use crate::utils::utils_se3::optima_se3_pose::OptimaSE3PoseType;
+use crate::utils::utils_shape_geometry::trimesh_engine::TrimeshEngine;
+
+/// An estimate of the reachable workspace of a single link (e.g., an end effector), built by
+/// sampling forward kinematics over uniformly sampled joint states.  Three nested bounds are kept,
+/// from coarsest to finest: an axis aligned bounding box, the convex hull of the sampled positions,
+/// and the set of voxels that contain at least one sampled position.
+///
+/// Since the estimate is built from samples, it can slightly under-approximate the true workspace;
+/// the containment checks therefore accept a margin (in meters).  The object is serializable, so it
+/// can be computed once per robot and reloaded (e.g., via `ToAndFromJsonString`) for cell layout or
+/// quick reachability rejection before running inverse kinematics.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobotWorkspaceBound {
+    link_id: LinkId,
+    num_samples: usize,
+    aabb_mins: Vector3<f64>,
+    aabb_maxs: Vector3<f64>,
+    convex_hull: TrimeshEngine,
+    voxel_size: f64,
+    /// Sorted integer coordinates of the occupied voxels.
+    voxels: Vec<[i64; 3]>
+}
+impl RobotWorkspaceBound {
+    pub fn new(robot_kinematics_module: &RobotKinematicsModule, link_id: LinkId, num_samples: usize, voxel_size: f64) -> Result<Self, OptimaError> {
+        let link_idx = link_id.idx();
+        if num_samples < 4 {
+            return Err(OptimaError::new_generic_error_str(&format!("At least 4 samples are needed to estimate a workspace (given {}).", num_samples), file!(), line!()));
+        }
+        if !(voxel_size > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Voxel size must be positive (given {}).", voxel_size), file!(), line!()));
+        }
+
+        let num_links = robot_kinematics_module.robot_configuration_module().robot_model_module().links().len();
+        OptimaError::new_check_for_idx_out_of_bound_error(link_idx, num_links, file!(), line!())?;
+
+        let mut positions = vec![];
+        for _ in 0..num_samples {
+            let joint_state = robot_kinematics_module.robot_joint_state_module().sample_joint_state(&RobotJointStateType::DOF);
+            let res = robot_kinematics_module.compute_fk(&joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+            let pose = res.link_entries()[link_idx].pose().as_ref().ok_or(OptimaError::new_generic_error_str(&format!("Link {} is not included in forward kinematics.", link_idx), file!(), line!()))?;
+            positions.push(pose.translation());
+        }
+
+        return Ok(Self::new_from_positions(link_id, positions, voxel_size));
+    }
+    /// Builds the bound directly from sampled positions of the link (e.g., logged from a real
+    /// robot).
+    pub fn new_from_positions(link_id: LinkId, positions: Vec<Vector3<f64>>, voxel_size: f64) -> Self {
+        let mut aabb_mins = Vector3::repeat(f64::INFINITY);
+        let mut aabb_maxs = Vector3::repeat(-f64::INFINITY);
+        let mut voxels = vec![];
+        for p in &positions {
+            aabb_mins = aabb_mins.inf(p);
+            aabb_maxs = aabb_maxs.sup(p);
+            voxels.push(Self::voxel_coordinates(p, voxel_size));
+        }
+        voxels.sort();
+        voxels.dedup();
+
+        let num_samples = positions.len();
+        let convex_hull = TrimeshEngine::new_from_points(positions).compute_convex_hull();
+
+        Self {
+            link_id,
+            num_samples,
+            aabb_mins,
+            aabb_maxs,
+            convex_hull,
+            voxel_size,
+            voxels
+        }
+    }
+    /// Returns true if the point is within margin of the axis aligned bounding box.
+    pub fn aabb_contains_point(&self, point: &Vector3<f64>, margin: f64) -> bool {
+        for i in 0..3 {
+            if point[i] < self.aabb_mins[i] - margin || point[i] > self.aabb_maxs[i] + margin { return false; }
+        }
+        return true;
+    }
+    /// Returns true if the point is inside the convex hull, or outside of it by at most margin along
+    /// every face normal.
+    pub fn convex_hull_contains_point(&self, point: &Vector3<f64>, margin: f64) -> bool {
+        if !self.aabb_contains_point(point, margin) { return false; }
+
+        let vertices = self.convex_hull.vertices();
+        if vertices.is_empty() { return false; }
+        let mut centroid = Vector3::zeros();
+        for v in vertices { centroid += v; }
+        centroid /= vertices.len() as f64;
+
+        for face in self.convex_hull.indices() {
+            let a = &vertices[face[0]];
+            let normal = (&vertices[face[1]] - a).cross(&(&vertices[face[2]] - a));
+            let norm = normal.norm();
+            if norm < 1e-12 { continue; }
+            // Orient the normal away from the interior.
+            let mut normal = normal / norm;
+            if normal.dot(&(&centroid - a)) > 0.0 { normal = -normal; }
+            if normal.dot(&(point - a)) > margin { return false; }
+        }
+        return true;
+    }
+    /// Returns true if the voxel containing the point, or any voxel within margin of it, was reached
+    /// by a sample.  This is the tightest of the bounds.
+    pub fn voxels_contain_point(&self, point: &Vector3<f64>, margin: f64) -> bool {
+        if !self.aabb_contains_point(point, margin + self.voxel_size) { return false; }
+
+        let r = (margin.max(0.0) / self.voxel_size).ceil() as i64;
+        let center = Self::voxel_coordinates(point, self.voxel_size);
+        for dx in -r..=r {
+            for dy in -r..=r {
+                for dz in -r..=r {
+                    let voxel = [center[0] + dx, center[1] + dy, center[2] + dz];
+                    if self.voxels.binary_search(&voxel).is_ok() { return true; }
+                }
+            }
+        }
+        return false;
+    }
+    pub fn link_id(&self) -> LinkId {
+        self.link_id
+    }
+    pub fn num_samples(&self) -> usize {
+        self.num_samples
+    }
+    pub fn aabb_mins(&self) -> &Vector3<f64> {
+        &self.aabb_mins
+    }
+    pub fn aabb_maxs(&self) -> &Vector3<f64> {
+        &self.aabb_maxs
+    }
+    /// The convex hull of the sampled positions as a triangle mesh (e.g., to save as an stl file).
+    pub fn convex_hull(&self) -> &TrimeshEngine {
+        &self.convex_hull
+    }
+    pub fn voxel_size(&self) -> f64 {
+        self.voxel_size
+    }
+    pub fn voxels(&self) -> &Vec<[i64; 3]> {
+        &self.voxels
+    }
+    /// The center point of the voxel with the given integer coordinates.
+    pub fn voxel_center(&self, voxel: &[i64; 3]) -> Vector3<f64> {
+        return Vector3::new(voxel[0] as f64 + 0.5, voxel[1] as f64 + 0.5, voxel[2] as f64 + 0.5) * self.voxel_size;
+    }
+    fn voxel_coordinates(point: &Vector3<f64>, voxel_size: f64) -> [i64; 3] {
+        return [(point[0] / voxel_size).floor() as i64, (point[1] / voxel_size).floor() as i64, (point[2] / voxel_size).floor() as i64];
+    }
+}
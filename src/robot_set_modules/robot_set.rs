@@ -11,7 +11,6 @@ use crate::robot_set_modules::robot_set_joint_state_module::{RobotSetJointState,
 use crate::robot_set_modules::robot_set_mesh_file_manager_module::RobotSetMeshFileManagerModule;
 use crate::utils::utils_console::{optima_print, PrintColor, PrintMode};
 use crate::utils::utils_errors::OptimaError;
-use crate::utils::utils_files::optima_path::load_object_from_json_string;
 use crate::utils::utils_robot::robot_module_utils::RobotNames;
 use crate::utils::utils_se3::optima_se3_pose::OptimaSE3PoseType;
 use crate::utils::utils_traits::SaveAndLoadable;
@@ -104,7 +103,7 @@ impl SaveAndLoadable for RobotSet {
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let load: Self::SaveType = load_object_from_json_string(json_str)?;
+        let load: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
 
         let robot_set_configuration_module = RobotSetConfigurationModule::load_from_json_string(&load.0)?;
         let robot_set_joint_state_module = RobotSetJointStateModule::load_from_json_string(&load.1)?;
@@ -145,26 +144,12 @@ impl RobotSetPy {
     #[new]
     pub fn new_from_set_name(set_name: &str, py: Python) -> Self {
         let r = RobotSet::new_from_set_name(set_name);
-
-        Self {
-            robot_set_configuration_module: Py::new(py, r.robot_set_configuration_module.clone()).expect("error"),
-            robot_set_joint_state_module: Py::new(py, r.robot_set_joint_state_module.clone()).expect("error"),
-            robot_set_mesh_file_manager_module: Py::new(py, r.robot_set_mesh_file_manager_module.clone()).expect("error"),
-            robot_set_kinematics_module: Py::new(py, r.robot_set_kinematics_module.clone()).expect("error"),
-            phantom_robot_set: r
-        }
+        Self::new_from_robot_set(r, py)
     }
     #[staticmethod]
     pub fn new(robot_set_configuration_module: &RobotSetConfigurationModule, py: Python) -> Self {
         let r = RobotSet::new_from_robot_set_configuration_module(robot_set_configuration_module.clone());
-
-        Self {
-            robot_set_configuration_module: Py::new(py, r.robot_set_configuration_module.clone()).expect("error"),
-            robot_set_joint_state_module: Py::new(py, r.robot_set_joint_state_module.clone()).expect("error"),
-            robot_set_mesh_file_manager_module: Py::new(py, r.robot_set_mesh_file_manager_module.clone()).expect("error"),
-            robot_set_kinematics_module: Py::new(py, r.robot_set_kinematics_module.clone()).expect("error"),
-            phantom_robot_set: r
-        }
+        Self::new_from_robot_set(r, py)
     }
     #[staticmethod]
     pub fn new_single_robot(robot_name: &str, configuration_name: Option<&str>, py: Python) -> Self {
@@ -172,12 +157,43 @@ impl RobotSetPy {
         robot_set_configuration_module.add_robot_configuration_from_names(RobotNames::new(robot_name, configuration_name)).expect("error");
         Self::new(&robot_set_configuration_module, py)
     }
+    #[staticmethod]
+    pub fn new_from_robot_set_modules(robot_set_configuration_module: RobotSetConfigurationModule,
+                                      robot_set_joint_state_module: RobotSetJointStateModule,
+                                      robot_set_mesh_file_manager_module: RobotSetMeshFileManagerModule,
+                                      robot_set_kinematics_module: RobotSetKinematicsModule,
+                                      py: Python) -> Self {
+        let r = RobotSet {
+            robot_set_configuration_module,
+            robot_set_joint_state_module,
+            robot_set_mesh_file_manager_module,
+            robot_set_kinematics_module
+        };
+        Self::new_from_robot_set(r, py)
+    }
+    pub fn __reduce__(&self, py: Python) -> PyResult<(PyObject, (RobotSetConfigurationModule, RobotSetJointStateModule, RobotSetMeshFileManagerModule, RobotSetKinematicsModule))> {
+        let constructor = py.get_type::<Self>().getattr("new_from_robot_set_modules")?;
+        let args = (self.robot_set_configuration_module.borrow(py).clone(),
+                    self.robot_set_joint_state_module.borrow(py).clone(),
+                    self.robot_set_mesh_file_manager_module.borrow(py).clone(),
+                    self.robot_set_kinematics_module.borrow(py).clone());
+        return Ok((PyObject::from(constructor), args));
+    }
     pub fn generate_robot_set_geometric_shape_module(&self) -> RobotSetGeometricShapeModule {
         self.phantom_robot_set.generate_robot_set_geometric_shape_module().expect("error")
     }
 }
 #[cfg(not(target_arch = "wasm32"))]
 impl RobotSetPy {
+    pub fn new_from_robot_set(r: RobotSet, py: Python) -> Self {
+        Self {
+            robot_set_configuration_module: Py::new(py, r.robot_set_configuration_module.clone()).expect("error"),
+            robot_set_joint_state_module: Py::new(py, r.robot_set_joint_state_module.clone()).expect("error"),
+            robot_set_mesh_file_manager_module: Py::new(py, r.robot_set_mesh_file_manager_module.clone()).expect("error"),
+            robot_set_kinematics_module: Py::new(py, r.robot_set_kinematics_module.clone()).expect("error"),
+            phantom_robot_set: r
+        }
+    }
     pub fn get_robot_set(&self) -> &RobotSet {
         &self.phantom_robot_set
     }
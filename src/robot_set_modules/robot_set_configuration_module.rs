@@ -106,10 +106,14 @@ impl SaveAndLoadable for RobotSetConfigurationModule {
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let load = Vec::load_from_json_string(&json_str)?;
+        let load = Self::load_save_object_from_json_string(json_str)?;
+        let mut robot_configuration_modules = vec![];
+        for s in &load {
+            robot_configuration_modules.push(RobotConfigurationModule::load_from_json_string(s)?);
+        }
 
         Ok(Self {
-            robot_configuration_modules: load
+            robot_configuration_modules
         })
     }
 }
@@ -148,3 +152,5 @@ impl RobotSetConfigurationModule {
 }
 
 
+
+crate::impl_py_pickle!(RobotSetConfigurationModule);
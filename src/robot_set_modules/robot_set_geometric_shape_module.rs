@@ -12,13 +12,15 @@ use crate::robot_set_modules::robot_set_configuration_module::RobotSetConfigurat
 use crate::robot_set_modules::robot_set_kinematics_module::{RobotSetFKResult, RobotSetKinematicsModule};
 use crate::robot_set_modules::robot_set_joint_state_module::{RobotSetJointState, RobotSetJointStateModule};
 use crate::utils::utils_errors::OptimaError;
-use crate::utils::utils_files::optima_path::load_object_from_json_string;
 use crate::utils::utils_generic_data_structures::{MemoryCell, SquareArray2D};
 use crate::utils::utils_robot::robot_module_utils::RobotNames;
 use crate::utils::utils_se3::optima_se3_pose::OptimaSE3PoseType;
 use crate::utils::utils_shape_geometry::geometric_shape::{BVHCombinableShape, GeometricShapeQueryGroupOutput, GeometricShapeSignature, LogCondition, StopCondition};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::utils::utils_shape_geometry::geometric_shape::{GeometricShapeQueryGroupOutputPy};
+#[cfg(target_arch = "wasm32")]
+use crate::utils::utils_shape_geometry::geometric_shape::GeometricShapeQueryGroupOutputJsObject;
+use crate::utils::utils_shape_geometry::query_output_summary::QueryOutputSummary;
 use crate::utils::utils_shape_geometry::shape_collection::{BVHSceneFilterOutput, BVHVisit, ProximaBudget, ProximaEngine, ProximaProximityOutput, ProximaSceneFilterOutput, ShapeCollection, ShapeCollectionBVH, ShapeCollectionInputPoses, ShapeCollectionQuery, ShapeCollectionQueryList, ShapeCollectionQueryPairsList, SignedDistanceLossFunction};
 use crate::utils::utils_traits::{SaveAndLoadable, ToAndFromRonString};
 
@@ -181,6 +183,25 @@ impl RobotSetGeometricShapeModule {
         }
         unreachable!();
     }
+    /// Sets the collision margin applied to every link of every robot in all shape representations
+    /// (see `CollisionMargins`).
+    pub fn set_global_collision_margin(&mut self, margin: f64) -> Result<(), OptimaError> {
+        for s in &mut self.robot_set_shape_collections {
+            s.shape_collection.set_global_collision_margin(margin)?;
+        }
+        Ok(())
+    }
+    /// Overrides the global collision margin on all shapes of the given link.  Passing None reverts
+    /// the link to the global margin.
+    pub fn set_robot_link_collision_margin(&mut self, robot_idx_in_set: usize, link_idx_in_robot: usize, margin: Option<f64>) -> Result<(), OptimaError> {
+        for s in &mut self.robot_set_shape_collections {
+            let shape_idxs = s.get_shape_idxs_from_robot_idx_and_link_idx(robot_idx_in_set, link_idx_in_robot)?.clone();
+            for shape_idx in shape_idxs {
+                s.shape_collection.set_shape_collision_margin(shape_idx, margin)?;
+            }
+        }
+        Ok(())
+    }
 
     pub fn spawn_query_list(&self, robot_link_shape_representation: &RobotLinkShapeRepresentation) -> ShapeCollectionQueryList {
         let robot_set_shape_collection = self.robot_set_shape_collection(robot_link_shape_representation).expect("error");
@@ -248,6 +269,11 @@ impl RobotSetGeometricShapeModule {
         return collection.shape_collection.bvh_scene_filter(bvh, &poses, visit);
     }
 
+    /// Summarizes the outputs of a query on this module (see `QueryOutputSummary`) with robot links
+    /// labeled by robot and link name.
+    pub fn summarize_query_output(&self, output: &GeometricShapeQueryGroupOutput, max_pairs: usize) -> QueryOutputSummary {
+        return output.summary_with_shape_labels(max_pairs, &|signature| robot_set_shape_label(&self.robot_set_kinematics_module, signature));
+    }
     fn setup_robot_set_shape_collections(&mut self, robot_set_configuration_module: &RobotSetConfigurationModule) -> Result<(), OptimaError> {
         let mut robot_geometric_shape_modules = vec![];
         for r in robot_set_configuration_module.robot_configuration_modules() {
@@ -337,6 +363,21 @@ impl RobotSetGeometricShapeModule {
         robot_link_shape_representations
     }
 }
+
+/// Labels robot set links as "robot_name/link_name (shape i)", falling back to
+/// `QueryOutputSummary::default_shape_label` for other signatures.
+pub(crate) fn robot_set_shape_label(robot_set_kinematics_module: &RobotSetKinematicsModule, signature: &GeometricShapeSignature) -> String {
+    if let GeometricShapeSignature::RobotSetLink { robot_idx_in_set, link_idx_in_robot, shape_idx_in_link } = signature {
+        if let Some(robot_kinematics_module) = robot_set_kinematics_module.robot_kinematics_modules().get(*robot_idx_in_set) {
+            let robot_model_module = robot_kinematics_module.robot_configuration_module().robot_model_module();
+            if let Some(link) = robot_model_module.links().get(*link_idx_in_robot) {
+                return format!("{}/{} (shape {})", robot_model_module.robot_name(), link.name(), shape_idx_in_link);
+            }
+        }
+    }
+    return QueryOutputSummary::default_shape_label(signature);
+}
+
 impl SaveAndLoadable for RobotSetGeometricShapeModule {
     type SaveType = (String, String, String);
 
@@ -345,7 +386,7 @@ impl SaveAndLoadable for RobotSetGeometricShapeModule {
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let load: Self::SaveType = load_object_from_json_string(json_str)?;
+        let load: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
         let robot_set_joint_state_module = RobotSetJointStateModule::load_from_json_string(&load.0)?;
         let robot_set_kinematics_module = RobotSetKinematicsModule::load_from_json_string(&load.0)?;
         let robot_set_shape_collections = Vec::load_from_json_string(&load.1)?;
@@ -408,7 +449,7 @@ impl SaveAndLoadable for RobotSetShapeCollection {
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let load: Self::SaveType = load_object_from_json_string(json_str)?;
+        let load: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
         let shape_collection = ShapeCollection::load_from_json_string(&load.1)?;
 
         Ok(Self {
@@ -482,7 +523,8 @@ impl RobotSetGeometricShapeModule {
                                               StopCondition::from_ron_string(stop_condition).expect("error"),
                                               LogCondition::from_ron_string(log_condition).expect("error"),
                                               sort_outputs).expect("error");
-        let py_output = res.convert_to_py_output(include_full_output_json_string);
+        let mut py_output = res.convert_to_py_output(include_full_output_json_string);
+        py_output.set_summary(self.summarize_query_output(&res, 10));
         py_output
     }
     #[args(robot_link_shape_representation = "\"Cubes\"", stop_condition = "\"Intersection\"", log_condition = "\"BelowMinDistance(0.5)\"", sort_outputs = "true", include_full_output_json_string = "true")]
@@ -503,7 +545,8 @@ impl RobotSetGeometricShapeModule {
                                               StopCondition::from_ron_string(stop_condition).expect("error"),
                                               LogCondition::from_ron_string(log_condition).expect("error"),
                                               sort_outputs).expect("error");
-        let py_output = res.convert_to_py_output(include_full_output_json_string);
+        let mut py_output = res.convert_to_py_output(include_full_output_json_string);
+        py_output.set_summary(self.summarize_query_output(&res, 10));
         py_output
     }
     #[args(robot_link_shape_representation = "\"Cubes\"", stop_condition = "\"Intersection\"", log_condition = "\"BelowMinDistance(0.5)\"", sort_outputs = "true", include_full_output_json_string = "true")]
@@ -526,7 +569,8 @@ impl RobotSetGeometricShapeModule {
                                               StopCondition::from_ron_string(stop_condition).expect("error"),
                                               LogCondition::from_ron_string(log_condition).expect("error"),
                                               sort_outputs).expect("error");
-        let py_output = res.convert_to_py_output(include_full_output_json_string);
+        let mut py_output = res.convert_to_py_output(include_full_output_json_string);
+        py_output.set_summary(self.summarize_query_output(&res, 10));
         py_output
     }
     #[args(robot_link_shape_representation = "\"Cubes\"", stop_condition = "\"Intersection\"", log_condition = "\"BelowMinDistance(0.5)\"", sort_outputs = "true", include_full_output_json_string = "true")]
@@ -551,7 +595,8 @@ impl RobotSetGeometricShapeModule {
                                               StopCondition::from_ron_string(stop_condition).expect("error"),
                                               LogCondition::from_ron_string(log_condition).expect("error"),
                                               sort_outputs).expect("error");
-        let py_output = res.convert_to_py_output(include_full_output_json_string);
+        let mut py_output = res.convert_to_py_output(include_full_output_json_string);
+        py_output.set_summary(self.summarize_query_output(&res, 10));
         py_output
     }
 }
@@ -563,7 +608,7 @@ impl RobotSetGeometricShapeModule {
     pub fn new_from_set_name_wasm(set_name: &str) -> Self {
         Self::new_from_set_name(set_name).expect("error")
     }
-    pub fn intersection_test_query_wasm(&self, joint_state: Vec<f64>, robot_link_shape_representation: &str, stop_condition: &str, log_condition: &str, sort_outputs: bool) -> JsValue {
+    pub fn intersection_test_query_wasm(&self, joint_state: Vec<f64>, robot_link_shape_representation: &str, stop_condition: &str, log_condition: &str, sort_outputs: bool) -> GeometricShapeQueryGroupOutputJsObject {
         let joint_state = self.robot_set_joint_state_module.spawn_robot_set_joint_state_try_auto_type(DVector::from_vec(joint_state)).expect("error");
         let input = RobotSetShapeCollectionQuery::IntersectionTest {
             robot_joint_state: &joint_state
@@ -574,9 +619,9 @@ impl RobotSetGeometricShapeModule {
                                               StopCondition::from_ron_string(stop_condition).expect("error"),
                                               LogCondition::from_ron_string(log_condition).expect("error"),
                                               sort_outputs).expect("error");
-        JsValue::from_serde(&res).unwrap()
+        res.to_js_object()
     }
-    pub fn distance_query_wasm(&self, joint_state: Vec<f64>, robot_link_shape_representation: &str, stop_condition: &str, log_condition: &str, sort_outputs: bool) -> JsValue {
+    pub fn distance_query_wasm(&self, joint_state: Vec<f64>, robot_link_shape_representation: &str, stop_condition: &str, log_condition: &str, sort_outputs: bool) -> GeometricShapeQueryGroupOutputJsObject {
         let joint_state = self.robot_set_joint_state_module.spawn_robot_set_joint_state_try_auto_type(DVector::from_vec(joint_state)).expect("error");
         let input = RobotSetShapeCollectionQuery::Distance {
             robot_joint_state: &joint_state
@@ -587,9 +632,9 @@ impl RobotSetGeometricShapeModule {
                                               StopCondition::from_ron_string(stop_condition).expect("error"),
                                               LogCondition::from_ron_string(log_condition).expect("error"),
                                               sort_outputs).expect("error");
-        JsValue::from_serde(&res).unwrap()
+        res.to_js_object()
     }
-    pub fn contact_query_wasm(&self, joint_state: Vec<f64>, prediction: f64, robot_link_shape_representation: &str, stop_condition: &str, log_condition: &str, sort_outputs: bool) -> JsValue {
+    pub fn contact_query_wasm(&self, joint_state: Vec<f64>, prediction: f64, robot_link_shape_representation: &str, stop_condition: &str, log_condition: &str, sort_outputs: bool) -> GeometricShapeQueryGroupOutputJsObject {
         let joint_state = self.robot_set_joint_state_module.spawn_robot_set_joint_state_try_auto_type(DVector::from_vec(joint_state)).expect("error");
         let input = RobotSetShapeCollectionQuery::Contact {
             robot_joint_state: &joint_state,
@@ -601,9 +646,9 @@ impl RobotSetGeometricShapeModule {
                                               StopCondition::from_ron_string(stop_condition).expect("error"),
                                               LogCondition::from_ron_string(log_condition).expect("error"),
                                               sort_outputs).expect("error");
-        JsValue::from_serde(&res).unwrap()
+        res.to_js_object()
     }
-    pub fn ccd_query_wasm(&self, joint_state_t1: Vec<f64>, joint_state_t2: Vec<f64>, robot_link_shape_representation: &str, stop_condition: &str, log_condition: &str, sort_outputs: bool) -> JsValue {
+    pub fn ccd_query_wasm(&self, joint_state_t1: Vec<f64>, joint_state_t2: Vec<f64>, robot_link_shape_representation: &str, stop_condition: &str, log_condition: &str, sort_outputs: bool) -> GeometricShapeQueryGroupOutputJsObject {
         let joint_state_t1 = self.robot_set_joint_state_module.spawn_robot_set_joint_state_try_auto_type(DVector::from_vec(joint_state_t1)).expect("error");
         let joint_state_t2 = self.robot_set_joint_state_module.spawn_robot_set_joint_state_try_auto_type(DVector::from_vec(joint_state_t2)).expect("error");
 
@@ -617,6 +662,8 @@ impl RobotSetGeometricShapeModule {
                                               StopCondition::from_ron_string(stop_condition).expect("error"),
                                               LogCondition::from_ron_string(log_condition).expect("error"),
                                               sort_outputs).expect("error");
-        JsValue::from_serde(&res).unwrap()
+        res.to_js_object()
     }
-}
\ No newline at end of file
+}
+
+crate::impl_py_pickle!(RobotSetGeometricShapeModule);
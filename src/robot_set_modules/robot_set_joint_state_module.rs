@@ -10,7 +10,6 @@ use serde::{Serialize, Deserialize};
 use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateModule, RobotJointStateType};
 use crate::robot_set_modules::robot_set_configuration_module::RobotSetConfigurationModule;
 use crate::utils::utils_errors::OptimaError;
-use crate::utils::utils_files::optima_path::load_object_from_json_string;
 use crate::utils::utils_traits::{SaveAndLoadable, ToAndFromRonString};
 
 /// RobotSet analogue of the `RobotJointStateModule`.  The same concepts apply, just on a set of possibly
@@ -146,6 +145,34 @@ impl RobotSetJointStateModule {
         }
         out_vec
     }
+    /// Concatenates the weights of the joint weighting profile with the given name from each robot in
+    /// the set (see `RobotJointStateModule::get_joint_state_weights`).  Robots whose configuration does
+    /// not have the profile get a weight of 1.0 on every axis.  Returns an error if no robot in the set
+    /// has the profile.
+    pub fn get_joint_state_weights(&self, profile_name: &str, t: &RobotSetJointStateType) -> Result<Vec<f64>, OptimaError> {
+        let mut out_vec = vec![];
+        let mut found = false;
+        for r in &self.robot_joint_state_modules {
+            let robot_joint_state_type = t.map_to_robot_joint_state_type();
+            if r.robot_configuration_module().robot_configuration_info().joint_weighting_profile(profile_name).is_some() {
+                found = true;
+                let weights = r.get_joint_state_weights(profile_name, &robot_joint_state_type)?;
+                for w in weights { out_vec.push(w); }
+            } else {
+                let num = match robot_joint_state_type {
+                    RobotJointStateType::DOF => { r.num_dofs() }
+                    RobotJointStateType::Full => { r.num_axes() }
+                };
+                for _ in 0..num { out_vec.push(1.0); }
+            }
+        }
+
+        if !found {
+            return Err(OptimaError::new_generic_error_str(&format!("No robot in the set has a joint weighting profile named {}.", profile_name), file!(), line!()));
+        }
+
+        Ok(out_vec)
+    }
     pub fn sample_set_joint_state(&self, t: &RobotSetJointStateType) -> RobotSetJointState {
         let mut out_dvec = match t {
             RobotSetJointStateType::DOF => { DVector::zeros(self.num_dofs) }
@@ -169,6 +196,24 @@ impl RobotSetJointStateModule {
             concatenated_state: out_dvec
         }
     }
+    /// Interpolates between two robot set joint states of the same type (see
+    /// `RobotJointStateModule::interpolate_joint_states`).
+    pub fn interpolate_robot_set_joint_states(&self, a: &RobotSetJointState, b: &RobotSetJointState, u: f64) -> Result<RobotSetJointState, OptimaError> {
+        if a.robot_set_joint_state_type() != b.robot_set_joint_state_type() {
+            return Err(OptimaError::new_generic_error_str(&format!("Cannot interpolate between robot set joint states of type {:?} and {:?}.", a.robot_set_joint_state_type(), b.robot_set_joint_state_type()), file!(), line!()));
+        }
+
+        let a_joint_states = self.split_robot_set_joint_state_into_robot_joint_states(a)?;
+        let b_joint_states = self.split_robot_set_joint_state_into_robot_joint_states(b)?;
+
+        let mut out_vec = vec![];
+        for (i, r) in self.robot_joint_state_modules.iter().enumerate() {
+            let interpolated = r.interpolate_joint_states(&a_joint_states[i], &b_joint_states[i], u)?;
+            for v in interpolated.joint_state().iter() { out_vec.push(*v); }
+        }
+
+        return self.spawn_robot_set_joint_state(DVector::from_vec(out_vec), a.robot_set_joint_state_type().clone());
+    }
     pub fn split_robot_set_joint_state_into_robot_joint_states(&self, robot_set_joint_state: &RobotSetJointState) -> Result<Vec<RobotJointState>, OptimaError> {
         let split = self.split_concatenated_dvec_into_separate_robot_dvecs(&robot_set_joint_state.concatenated_state, &robot_set_joint_state.robot_set_joint_state_type)?;
 
@@ -229,7 +274,7 @@ impl SaveAndLoadable for RobotSetJointStateModule {
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let load: Self::SaveType = load_object_from_json_string(json_str)?;
+        let load: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
 
         let robot_joint_state_modules = Vec::load_from_json_string(&load.2)?;
 
@@ -384,4 +429,6 @@ impl RobotSetJointStateType {
             RobotSetJointStateType::Full => { RobotJointStateType::Full }
         }
     }
-}
\ No newline at end of file
+}
+
+crate::impl_py_pickle!(RobotSetJointStateModule);
@@ -11,8 +11,8 @@ use crate::robot_set_modules::robot_set_configuration_module::RobotSetConfigurat
 use crate::robot_set_modules::robot_set_joint_state_module::{RobotSetJointState, RobotSetJointStateModule, RobotSetJointStateType};
 use crate::utils::utils_console::{optima_print, PrintColor, PrintMode};
 use crate::utils::utils_errors::OptimaError;
-use crate::utils::utils_files::optima_path::load_object_from_json_string;
 use crate::utils::utils_nalgebra::conversions::NalgebraConversions;
+use crate::utils::utils_robot::robot_ids::LinkId;
 use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3Pose, OptimaSE3PoseType};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3PosePy};
@@ -20,7 +20,7 @@ use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3PosePy};
 use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3PoseWASM};
 use crate::utils::utils_traits::{SaveAndLoadable, ToAndFromRonString};
 #[cfg(target_arch = "wasm32")]
-use crate::utils::utils_wasm::JsMatrix;
+use crate::utils::utils_wasm::{JsMatrix, JsMatrixObject};
 
 /// RobotSet analogue of the `RobotKinematicsModule`.  The same concepts apply, just on a set of possibly
 /// multiple robots.
@@ -130,8 +130,8 @@ impl RobotSetKinematicsModule {
     pub fn compute_jacobian(&self,
                             joint_state: &RobotSetJointState,
                             robot_idx_in_set: usize,
-                            start_link_idx: Option<usize>,
-                            end_link_idx: usize,
+                            start_link_id: Option<LinkId>,
+                            end_link_id: LinkId,
                             robot_jacobian_end_point: &JacobianEndPoint,
                             start_link_pose: Option<OptimaSE3Pose>,
                             jacobian_mode: JacobianMode) -> Result<DMatrix<f64>, OptimaError> {
@@ -154,7 +154,7 @@ impl RobotSetKinematicsModule {
 
         let robot_states = self.robot_set_joint_state_module.split_robot_set_joint_state_into_robot_joint_states(joint_state)?;
         let robot_state = robot_states.get(robot_idx_in_set).unwrap();
-        let robot_jacobian = self.robot_kinematics_modules.get(robot_idx_in_set).unwrap().compute_jacobian(robot_state, start_link_idx, end_link_idx, robot_jacobian_end_point, start_link_pose, jacobian_mode)?;
+        let robot_jacobian = self.robot_kinematics_modules.get(robot_idx_in_set).unwrap().compute_jacobian(robot_state, start_link_id, end_link_id, robot_jacobian_end_point, start_link_pose, jacobian_mode)?;
 
         let num_columns = robot_state.len();
         for i in 0..num_columns {
@@ -179,6 +179,9 @@ impl RobotSetKinematicsModule {
         }
         Ok(out_state)
     }
+    pub fn robot_kinematics_modules(&self) -> &Vec<RobotKinematicsModule> {
+        &self.robot_kinematics_modules
+    }
 }
 impl SaveAndLoadable for RobotSetKinematicsModule {
     type SaveType = (String, String);
@@ -188,7 +191,7 @@ impl SaveAndLoadable for RobotSetKinematicsModule {
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let load: Self::SaveType = load_object_from_json_string(json_str)?;
+        let load: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
         let robot_set_joint_state_module = RobotSetJointStateModule::load_from_json_string(&load.0)?;
         let robot_kinematics_modules  = Vec::load_from_json_string(&load.1)?;
 
@@ -225,8 +228,8 @@ impl RobotSetKinematicsModule {
         };
         let jac = self.compute_jacobian(&robot_joint_state,
                                         robot_idx_in_set,
-                                        start_link_idx,
-                                        end_link_idx,
+                                        start_link_idx.map(LinkId::new),
+                                        LinkId::new(end_link_idx),
                                         &JacobianEndPoint::from_ron_string(robot_jacobian_end_point).expect("error"),
                                         start_link_pose,
                                         JacobianMode::from_ron_string(jacobian_mode).expect("error")).expect("error");
@@ -253,12 +256,11 @@ impl RobotSetKinematicsModule {
     pub fn new_from_set_name_wasm(set_name: &str) -> Self {
         Self::new_from_set_name(set_name).expect("error")
     }
-    pub fn compute_fk_wasm(&self, joint_state: Vec<f64>, pose_type: &str) -> JsValue {
+    pub fn compute_fk_wasm(&self, joint_state: Vec<f64>, pose_type: &str) -> RobotSetFKResult {
         let robot_joint_state = self.robot_set_joint_state_module.spawn_robot_set_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
-        let res = self.compute_fk(&robot_joint_state, &OptimaSE3PoseType::from_ron_string(pose_type).expect("error")).expect("error");
-        return JsValue::from_serde(&res).unwrap();
+        return self.compute_fk(&robot_joint_state, &OptimaSE3PoseType::from_ron_string(pose_type).expect("error")).expect("error");
     }
-    pub fn compute_jacobian_wasm(&self, joint_state: Vec<f64>, robot_idx_in_set: usize, end_link_idx: usize, start_link_idx: Option<usize>, start_link_pose: Option<OptimaSE3PoseWASM>, robot_jacobian_end_point: &str, jacobian_mode: &str) -> JsValue {
+    pub fn compute_jacobian_wasm(&self, joint_state: Vec<f64>, robot_idx_in_set: usize, end_link_idx: usize, start_link_idx: Option<usize>, start_link_pose: Option<OptimaSE3PoseWASM>, robot_jacobian_end_point: &str, jacobian_mode: &str) -> JsMatrixObject {
         let robot_joint_state = self.robot_set_joint_state_module.spawn_robot_set_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
         let start_link_pose = match start_link_pose {
             None => { None }
@@ -266,15 +268,15 @@ impl RobotSetKinematicsModule {
         };
         let jac = self.compute_jacobian(&robot_joint_state,
                                         robot_idx_in_set,
-                                        start_link_idx,
-                                        end_link_idx,
+                                        start_link_idx.map(LinkId::new),
+                                        LinkId::new(end_link_idx),
                                         &JacobianEndPoint::from_ron_string(robot_jacobian_end_point).expect("error"),
                                         start_link_pose,
                                         JacobianMode::from_ron_string(jacobian_mode).expect("error")).expect("error");
 
         let jac_vecs = NalgebraConversions::dmatrix_to_vecs(&jac);
         let jac_vecs_js = JsMatrix::new(jac_vecs);
-        return JsValue::from_serde(&jac_vecs_js).unwrap();
+        return jac_vecs_js.to_js_object();
     }
 }
 
@@ -282,7 +284,7 @@ impl RobotSetKinematicsModule {
 /// multiple robots.  Just contains a vector of individual `RobotFKResult` structs corresponding to
 /// the possibly multiple robots in the set.
 #[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
-#[cfg_attr(target_arch = "wasm32", derive(Clone, Debug, Serialize, Deserialize))]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen, derive(Clone, Debug, Serialize, Deserialize))]
 pub struct RobotSetFKResult {
     robot_fk_results: Vec<RobotFKResult>
 }
@@ -291,6 +293,10 @@ impl RobotSetFKResult {
         let res =  &self.robot_fk_results[robot_idx_in_set].link_entries()[link_idx_in_robot].pose().as_ref().unwrap();
         return res;
     }
+    pub fn get_tcp_frame_pose_from_idxs(&self, robot_idx_in_set: usize, tcp_frame_idx_in_robot: usize) ->&OptimaSE3Pose {
+        let res =  &self.robot_fk_results[robot_idx_in_set].tcp_frame_entries()[tcp_frame_idx_in_robot].pose().as_ref().unwrap();
+        return res;
+    }
     pub fn robot_fk_results(&self) -> &Vec<RobotFKResult> {
         &self.robot_fk_results
     }
@@ -330,9 +336,26 @@ impl RobotSetFKResult {
     }
 }
 
+/// WASM implementations.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl RobotSetFKResult {
+    #[wasm_bindgen(getter = num_robots)]
+    pub fn num_robots_wasm(&self) -> usize { self.robot_fk_results.len() }
+    pub fn robot_fk_result_wasm(&self, robot_idx_in_set: usize) -> RobotFKResult {
+        return self.robot_fk_result(robot_idx_in_set).expect("error").clone();
+    }
+    pub fn link_pose_wasm(&self, robot_idx_in_set: usize, link_idx_in_robot: usize) -> Option<OptimaSE3PoseWASM> {
+        let robot_fk_result = self.robot_fk_result(robot_idx_in_set).expect("error");
+        return robot_fk_result.link_entries()[link_idx_in_robot].pose().as_ref().map(|p| OptimaSE3PoseWASM::new(p.clone()));
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RobotSetFKDOFPerturbationsResult {
     perturbation: f64,
     central_fk_result: RobotSetFKResult,
     fk_dof_perturbation_results: Vec<RobotSetFKResult>
-}
\ No newline at end of file
+}
+
+crate::impl_py_pickle!(RobotSetKinematicsModule, RobotSetFKResult);
@@ -5,7 +5,6 @@ use serde::{Serialize, Deserialize};
 use crate::robot_modules::robot_mesh_file_manager_module::RobotMeshFileManagerModule;
 use crate::robot_set_modules::robot_set_configuration_module::RobotSetConfigurationModule;
 use crate::utils::utils_errors::OptimaError;
-use crate::utils::utils_files::optima_path::load_object_from_json_string;
 use crate::utils::utils_traits::SaveAndLoadable;
 
 /// RobotSet analogue of the `RobotSetMeshFileManagerModule`.  The same concepts apply, just on a set of possibly
@@ -44,7 +43,7 @@ impl SaveAndLoadable for RobotSetMeshFileManagerModule {
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let load: Self::SaveType = load_object_from_json_string(json_str)?;
+        let load: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
         let robot_mesh_file_manager_modules = Vec::load_from_json_string(&load)?;
         Ok(Self {
             robot_mesh_file_manager_modules
@@ -66,4 +65,6 @@ impl RobotSetMeshFileManagerModule {
     pub fn robot_mesh_file_manager_modules_py(&self) -> Vec<RobotMeshFileManagerModule> {
         self.robot_mesh_file_manager_modules.clone()
     }
-}
\ No newline at end of file
+}
+
+crate::impl_py_pickle!(RobotSetMeshFileManagerModule);
@@ -0,0 +1,466 @@
+#[cfg(not(target_arch = "wasm32"))]
+use pyo3::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+use std::f64::consts::PI;
+use nalgebra::{Isometry3, Vector3};
+use serde_json::{json, Map, Value};
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateType};
+use crate::robot_modules::robot_kinematics_module::{RobotFKResult, RobotKinematicsModule};
+use crate::robot_modules::robot_mesh_file_manager_module::RobotMeshFileManagerModule;
+use crate::robot_set_modules::robot_set_joint_state_module::RobotSetJointState;
+use crate::scenes::robot_geometric_shape_scene::RobotGeometricShapeScene;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::scenes::robot_geometric_shape_scene::RobotGeometricShapeScenePy;
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_files::optima_path::{OptimaPath, OptimaStemCellPath};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils::utils_nalgebra::conversions::NalgebraConversions;
+use crate::utils::utils_robot::robot_joint_trajectory::RobotJointTrajectory;
+use crate::utils::utils_se3::optima_se3_pose::OptimaSE3PoseType;
+use crate::utils::utils_shape_geometry::collision_visualization::CollisionVisualization;
+use crate::utils::utils_shape_geometry::geometric_shape::{GeometricShape, GeometricShapeSpawner};
+use crate::utils::utils_shape_geometry::shape_collection::{ShapeCollection, ShapeCollectionInputPoses};
+
+const GLB_MAGIC: u32 = 0x46546C67;
+const GLB_CHUNK_TYPE_JSON: u32 = 0x4E4F534A;
+const GLB_CHUNK_TYPE_BIN: u32 = 0x004E4942;
+const GLTF_COMPONENT_TYPE_FLOAT: u32 = 5126;
+const GLTF_COMPONENT_TYPE_UNSIGNED_INT: u32 = 5125;
+const GLTF_TARGET_ARRAY_BUFFER: u32 = 34962;
+const GLTF_TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const ROBOT_MATERIAL_IDX: usize = 0;
+const ENVIRONMENT_MATERIAL_IDX: usize = 1;
+const WITNESS_SEGMENT_MATERIAL_IDX: usize = 2;
+const CONTACT_NORMAL_MATERIAL_IDX: usize = 3;
+const PENETRATING_SHAPE_MATERIAL_IDX: usize = 4;
+const GLTF_MODE_LINES: u32 = 1;
+
+/// Builds a glTF 2.0 scene out of robots (their visual meshes, posed by forward kinematics) and
+/// environment objects, and writes it as a single binary `.glb` file that can be opened in standard
+/// viewers and renderers (e.g., Blender, three.js, or any online glTF viewer).  A robot can also be
+/// added with a trajectory, which is baked into the file as an animation of its link nodes.
+///
+/// Optima uses a z-up convention while glTF is y-up, so every object is placed under a single root
+/// node that rotates the scene into the glTF convention.  Each robot link becomes its own node
+/// named `<robot_name>/<link_name>`, and each environment object shape is named
+/// `environment/env_obj_<i>/shape_<j>`.
+#[cfg_attr(not(target_arch = "wasm32"), pyclass)]
+#[derive(Clone, Debug)]
+pub struct GltfSceneExporter {
+    binary: Vec<u8>,
+    nodes: Vec<Value>,
+    root_children: Vec<usize>,
+    meshes: Vec<Value>,
+    accessors: Vec<Value>,
+    buffer_views: Vec<Value>,
+    animations: Vec<Value>
+}
+impl GltfSceneExporter {
+    pub fn new() -> Self {
+        Self {
+            binary: vec![],
+            nodes: vec![],
+            root_children: vec![],
+            meshes: vec![],
+            accessors: vec![],
+            buffer_views: vec![],
+            animations: vec![]
+        }
+    }
+    /// Adds the robot's link meshes at the forward kinematics poses of the given joint state.
+    pub fn add_robot(&mut self, robot_kinematics_module: &RobotKinematicsModule, robot_joint_state: &RobotJointState) -> Result<(), OptimaError> {
+        let fk_res = robot_kinematics_module.compute_fk(robot_joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+        self.add_robot_link_nodes(robot_kinematics_module, &fk_res)?;
+        Ok(())
+    }
+    /// Adds the robot posed at the first waypoint of the trajectory, along with an animation (named
+    /// `<robot_name>_trajectory`) that moves its links through the trajectory using the waypoints'
+    /// times.  Waypoints that do not come strictly later than the previous one are skipped, as glTF
+    /// requires strictly increasing keyframe times.
+    pub fn add_robot_with_trajectory(&mut self, robot_kinematics_module: &RobotKinematicsModule, trajectory: &RobotJointTrajectory) -> Result<(), OptimaError> {
+        let robot_joint_states = trajectory.to_robot_joint_states(robot_kinematics_module.robot_joint_state_module(), &RobotJointStateType::DOF)?;
+        if robot_joint_states.is_empty() {
+            return Err(OptimaError::new_generic_error_str("Cannot export an empty trajectory.", file!(), line!()));
+        }
+
+        let first_fk_res = robot_kinematics_module.compute_fk(&robot_joint_states[0], &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+        let link_node_idxs = self.add_robot_link_nodes(robot_kinematics_module, &first_fk_res)?;
+
+        let mut times: Vec<f32> = vec![];
+        let mut link_isometries: Vec<Vec<Isometry3<f64>>> = vec![vec![]; link_node_idxs.len()];
+        for (waypoint, robot_joint_state) in trajectory.waypoints().iter().zip(robot_joint_states.iter()) {
+            let time = waypoint.time_from_start() as f32;
+            if let Some(last_time) = times.last() {
+                if time <= *last_time { continue; }
+            }
+            times.push(time);
+
+            let fk_res = robot_kinematics_module.compute_fk(robot_joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+            for (link_idx, link_entry) in fk_res.link_entries().iter().enumerate() {
+                if link_node_idxs[link_idx].is_none() { continue; }
+                let isometry = match link_entry.pose() {
+                    None => { link_isometries[link_idx].last().cloned().unwrap_or(Isometry3::identity()) }
+                    Some(pose) => { pose.to_nalgebra_isometry() }
+                };
+                link_isometries[link_idx].push(isometry);
+            }
+        }
+
+        let input_accessor_idx = self.add_float_accessor(&times, "SCALAR", 1, None, true);
+        let mut samplers = vec![];
+        let mut channels = vec![];
+        for (link_idx, node_idx) in link_node_idxs.iter().enumerate() {
+            let node_idx = match node_idx {
+                None => { continue; }
+                Some(node_idx) => { *node_idx }
+            };
+
+            let mut translations = vec![];
+            let mut rotations = vec![];
+            for isometry in &link_isometries[link_idx] {
+                let t = &isometry.translation.vector;
+                let q = &isometry.rotation;
+                translations.extend_from_slice(&[t[0] as f32, t[1] as f32, t[2] as f32]);
+                rotations.extend_from_slice(&[q.i as f32, q.j as f32, q.k as f32, q.w as f32]);
+            }
+
+            let translation_accessor_idx = self.add_float_accessor(&translations, "VEC3", 3, None, false);
+            let rotation_accessor_idx = self.add_float_accessor(&rotations, "VEC4", 4, None, false);
+            channels.push(json!({ "sampler": samplers.len(), "target": { "node": node_idx, "path": "translation" } }));
+            samplers.push(json!({ "input": input_accessor_idx, "output": translation_accessor_idx, "interpolation": "LINEAR" }));
+            channels.push(json!({ "sampler": samplers.len(), "target": { "node": node_idx, "path": "rotation" } }));
+            samplers.push(json!({ "input": input_accessor_idx, "output": rotation_accessor_idx, "interpolation": "LINEAR" }));
+        }
+
+        let robot_name = robot_kinematics_module.robot_configuration_module().robot_name().to_string();
+        self.animations.push(json!({ "name": format!("{}_trajectory", robot_name), "samplers": samplers, "channels": channels }));
+
+        Ok(())
+    }
+    /// Adds every environment object in the scene, posed at the given robot set state (which matters
+    /// for objects attached to robot links).  Robot link shapes in the scene are not added; use
+    /// `add_robot` for those.
+    pub fn add_environment(&mut self, scene: &RobotGeometricShapeScene, robot_set_joint_state: &RobotSetJointState) -> Result<(), OptimaError> {
+        let poses = scene.recover_poses(robot_set_joint_state, None)?;
+        let shapes = scene.shape_collection().shapes();
+        for env_obj_idx in 0..scene.num_env_objs() {
+            let shape_idxs = scene.get_shape_idxs_from_env_obj_idx(env_obj_idx)?;
+            for (i, shape_idx) in shape_idxs.iter().enumerate() {
+                let pose = match &poses.poses()[*shape_idx] {
+                    None => { continue; }
+                    Some(pose) => { pose }
+                };
+                let shape = &shapes[*shape_idx];
+                let name = format!("environment/env_obj_{}/shape_{}", env_obj_idx, i);
+                let (vertices, indices) = Self::geometric_shape_triangles(shape)?;
+                let mesh_idx = self.add_mesh(&name, &vertices, &indices, ENVIRONMENT_MATERIAL_IDX);
+                self.add_node(&name, Some(mesh_idx), &shape.isometry_at_pose(pose));
+            }
+        }
+        Ok(())
+    }
+    /// Adds the primitives of a collision visualization: witness segments (red lines), contact
+    /// normals (green lines of the given length), and, if the shapes and poses that were queried are
+    /// given, a highlighted copy of every shape in a penetrating pair.  Their node names all start
+    /// with `collisions/`.
+    pub fn add_collision_visualization(&mut self, collision_visualization: &CollisionVisualization, shapes_and_poses: Option<(&ShapeCollection, &ShapeCollectionInputPoses)>, normal_length: f64) -> Result<(), OptimaError> {
+        let mut segments = vec![];
+        for witness_segment in collision_visualization.witness_segments() {
+            segments.push(witness_segment.points);
+        }
+        if !segments.is_empty() {
+            let mesh_idx = self.add_line_mesh("collisions/witness_segments", &segments, WITNESS_SEGMENT_MATERIAL_IDX);
+            self.add_node("collisions/witness_segments", Some(mesh_idx), &Isometry3::identity());
+        }
+
+        let mut segments = vec![];
+        for contact_normal in collision_visualization.contact_normals() {
+            segments.push((contact_normal.point, contact_normal.point + contact_normal.normal * normal_length));
+        }
+        if !segments.is_empty() {
+            let mesh_idx = self.add_line_mesh("collisions/contact_normals", &segments, CONTACT_NORMAL_MATERIAL_IDX);
+            self.add_node("collisions/contact_normals", Some(mesh_idx), &Isometry3::identity());
+        }
+
+        if let Some((shape_collection, poses)) = shapes_and_poses {
+            let mut highlighted_shape_idxs = vec![];
+            for penetrating_pair in collision_visualization.penetrating_pairs() {
+                for signature in [&penetrating_pair.shape_signatures.0, &penetrating_pair.shape_signatures.1] {
+                    let shape_idx = shape_collection.get_shape_idx_from_signature(signature)?;
+                    if !highlighted_shape_idxs.contains(&shape_idx) { highlighted_shape_idxs.push(shape_idx); }
+                }
+            }
+            for shape_idx in highlighted_shape_idxs {
+                let pose = match &poses.poses()[shape_idx] {
+                    None => { continue; }
+                    Some(pose) => { pose }
+                };
+                let shape = &shape_collection.shapes()[shape_idx];
+                let name = format!("collisions/penetrating_shape_{}", shape_idx);
+                let (vertices, indices) = Self::geometric_shape_triangles(shape)?;
+                let mesh_idx = self.add_mesh(&name, &vertices, &indices, PENETRATING_SHAPE_MATERIAL_IDX);
+                self.add_node(&name, Some(mesh_idx), &shape.isometry_at_pose(pose));
+            }
+        }
+
+        Ok(())
+    }
+    pub fn to_glb_bytes(&self) -> Vec<u8> {
+        let mut json_bytes = self.to_gltf_json().to_string().into_bytes();
+        while json_bytes.len() % 4 != 0 { json_bytes.push(b' '); }
+
+        let mut total_length = 12 + 8 + json_bytes.len();
+        if !self.binary.is_empty() { total_length += 8 + self.binary.len(); }
+
+        let mut out_vec = Vec::with_capacity(total_length);
+        out_vec.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+        out_vec.extend_from_slice(&2u32.to_le_bytes());
+        out_vec.extend_from_slice(&(total_length as u32).to_le_bytes());
+        out_vec.extend_from_slice(&(json_bytes.len() as u32).to_le_bytes());
+        out_vec.extend_from_slice(&GLB_CHUNK_TYPE_JSON.to_le_bytes());
+        out_vec.extend_from_slice(&json_bytes);
+        if !self.binary.is_empty() {
+            out_vec.extend_from_slice(&(self.binary.len() as u32).to_le_bytes());
+            out_vec.extend_from_slice(&GLB_CHUNK_TYPE_BIN.to_le_bytes());
+            out_vec.extend_from_slice(&self.binary);
+        }
+
+        out_vec
+    }
+    pub fn save_glb(&self, path: &OptimaPath) -> Result<(), OptimaError> {
+        path.verify_extension(&vec!["glb"])?;
+        return path.write_bytes_to_file(&self.to_glb_bytes());
+    }
+    /// Returns the node index of each link that has a pose in the given forward kinematics result
+    /// (whether or not the link has a mesh), indexed by link index.
+    fn add_robot_link_nodes(&mut self, robot_kinematics_module: &RobotKinematicsModule, fk_res: &RobotFKResult) -> Result<Vec<Option<usize>>, OptimaError> {
+        let robot_name = robot_kinematics_module.robot_configuration_module().robot_name().to_string();
+        let robot_mesh_file_manager_module = RobotMeshFileManagerModule::new(robot_kinematics_module.robot_configuration_module().robot_model_module())?;
+        let paths = robot_mesh_file_manager_module.get_paths_to_meshes()?;
+
+        let mut out_vec = vec![];
+        for (link_idx, link_entry) in fk_res.link_entries().iter().enumerate() {
+            let pose = match link_entry.pose() {
+                None => { out_vec.push(None); continue; }
+                Some(pose) => { pose }
+            };
+            let name = format!("{}/{}", robot_name, link_entry.link_name());
+            let mesh_idx = match &paths[link_idx] {
+                None => { None }
+                Some(path) => {
+                    let trimesh_engine = path.load_file_to_trimesh_engine()?;
+                    Some(self.add_mesh(&name, trimesh_engine.vertices(), trimesh_engine.indices(), ROBOT_MATERIAL_IDX))
+                }
+            };
+            out_vec.push(Some(self.add_node(&name, mesh_idx, &pose.to_nalgebra_isometry())));
+        }
+
+        Ok(out_vec)
+    }
+    fn add_node(&mut self, name: &str, mesh_idx: Option<usize>, isometry: &Isometry3<f64>) -> usize {
+        let t = &isometry.translation.vector;
+        let q = &isometry.rotation;
+        let mut node = json!({ "name": name, "translation": [t[0], t[1], t[2]], "rotation": [q.i, q.j, q.k, q.w] });
+        if let Some(mesh_idx) = mesh_idx { node["mesh"] = json!(mesh_idx); }
+
+        // Node 0 is the root node added in to_gltf_json, so node indices are offset by one.
+        let node_idx = self.nodes.len() + 1;
+        self.nodes.push(node);
+        self.root_children.push(node_idx);
+        node_idx
+    }
+    fn add_mesh(&mut self, name: &str, vertices: &Vec<Vector3<f64>>, indices: &Vec<[usize; 3]>, material_idx: usize) -> usize {
+        let mut positions = vec![];
+        for v in vertices { positions.extend_from_slice(&[v[0] as f32, v[1] as f32, v[2] as f32]); }
+        let position_accessor_idx = self.add_float_accessor(&positions, "VEC3", 3, Some(GLTF_TARGET_ARRAY_BUFFER), true);
+
+        let mut bytes = vec![];
+        for face in indices {
+            for i in face { bytes.extend_from_slice(&(*i as u32).to_le_bytes()); }
+        }
+        let buffer_view_idx = self.add_buffer_view(bytes, Some(GLTF_TARGET_ELEMENT_ARRAY_BUFFER));
+        let index_accessor_idx = self.accessors.len();
+        self.accessors.push(json!({ "bufferView": buffer_view_idx, "componentType": GLTF_COMPONENT_TYPE_UNSIGNED_INT, "count": 3 * indices.len(), "type": "SCALAR" }));
+
+        let mesh_idx = self.meshes.len();
+        self.meshes.push(json!({
+            "name": name,
+            "primitives": [ { "attributes": { "POSITION": position_accessor_idx }, "indices": index_accessor_idx, "material": material_idx } ]
+        }));
+        mesh_idx
+    }
+    fn add_line_mesh(&mut self, name: &str, segments: &Vec<(Vector3<f64>, Vector3<f64>)>, material_idx: usize) -> usize {
+        let mut positions = vec![];
+        for (a, b) in segments {
+            positions.extend_from_slice(&[a[0] as f32, a[1] as f32, a[2] as f32, b[0] as f32, b[1] as f32, b[2] as f32]);
+        }
+        let position_accessor_idx = self.add_float_accessor(&positions, "VEC3", 3, Some(GLTF_TARGET_ARRAY_BUFFER), true);
+
+        let mesh_idx = self.meshes.len();
+        self.meshes.push(json!({
+            "name": name,
+            "primitives": [ { "attributes": { "POSITION": position_accessor_idx }, "mode": GLTF_MODE_LINES, "material": material_idx } ]
+        }));
+        mesh_idx
+    }
+    /// Adds an accessor over the given floats, grouped into elements of num_components values each.
+    /// Per component min and max values are included if include_bounds is true (glTF requires them
+    /// for positions and animation inputs).
+    fn add_float_accessor(&mut self, values: &Vec<f32>, accessor_type: &str, num_components: usize, target: Option<u32>, include_bounds: bool) -> usize {
+        let mut bytes = vec![];
+        for v in values { bytes.extend_from_slice(&v.to_le_bytes()); }
+        let buffer_view_idx = self.add_buffer_view(bytes, target);
+
+        let mut accessor = json!({ "bufferView": buffer_view_idx, "componentType": GLTF_COMPONENT_TYPE_FLOAT, "count": values.len() / num_components, "type": accessor_type });
+        if include_bounds && !values.is_empty() {
+            let mut mins = vec![f32::INFINITY; num_components];
+            let mut maxs = vec![f32::NEG_INFINITY; num_components];
+            for (i, v) in values.iter().enumerate() {
+                let c = i % num_components;
+                mins[c] = mins[c].min(*v);
+                maxs[c] = maxs[c].max(*v);
+            }
+            accessor["min"] = json!(mins);
+            accessor["max"] = json!(maxs);
+        }
+
+        let accessor_idx = self.accessors.len();
+        self.accessors.push(accessor);
+        accessor_idx
+    }
+    fn add_buffer_view(&mut self, mut bytes: Vec<u8>, target: Option<u32>) -> usize {
+        let byte_offset = self.binary.len();
+        let byte_length = bytes.len();
+        // Keep every buffer view 4 byte aligned, as all of our components are 4 bytes.
+        while bytes.len() % 4 != 0 { bytes.push(0); }
+        self.binary.extend(bytes);
+
+        let mut buffer_view = json!({ "buffer": 0, "byteOffset": byte_offset, "byteLength": byte_length });
+        if let Some(target) = target { buffer_view["target"] = json!(target); }
+
+        let buffer_view_idx = self.buffer_views.len();
+        self.buffer_views.push(buffer_view);
+        buffer_view_idx
+    }
+    fn to_gltf_json(&self) -> Value {
+        // Rotates optima's z-up frame into glTF's y-up frame (-90 degrees about the x axis).
+        let half_sqrt_2 = std::f64::consts::FRAC_1_SQRT_2;
+        let mut nodes = vec![json!({ "name": "optima_scene", "rotation": [-half_sqrt_2, 0.0, 0.0, half_sqrt_2], "children": self.root_children })];
+        nodes.extend(self.nodes.iter().cloned());
+
+        let mut out = Map::new();
+        out.insert("asset".to_string(), json!({ "version": "2.0", "generator": "optima" }));
+        out.insert("scene".to_string(), json!(0));
+        out.insert("scenes".to_string(), json!([ { "nodes": [0] } ]));
+        out.insert("nodes".to_string(), json!(nodes));
+        out.insert("materials".to_string(), json!([
+            { "name": "robot", "doubleSided": true, "pbrMetallicRoughness": { "baseColorFactor": [0.7, 0.7, 0.7, 1.0], "metallicFactor": 0.1, "roughnessFactor": 0.7 } },
+            { "name": "environment", "doubleSided": true, "alphaMode": "BLEND", "pbrMetallicRoughness": { "baseColorFactor": [0.27, 0.47, 0.8, 0.6], "metallicFactor": 0.0, "roughnessFactor": 0.9 } },
+            { "name": "witness_segment", "pbrMetallicRoughness": { "baseColorFactor": [1.0, 0.1, 0.1, 1.0], "metallicFactor": 0.0, "roughnessFactor": 1.0 }, "emissiveFactor": [1.0, 0.1, 0.1] },
+            { "name": "contact_normal", "pbrMetallicRoughness": { "baseColorFactor": [0.1, 0.9, 0.1, 1.0], "metallicFactor": 0.0, "roughnessFactor": 1.0 }, "emissiveFactor": [0.1, 0.9, 0.1] },
+            { "name": "penetrating_shape", "doubleSided": true, "alphaMode": "BLEND", "pbrMetallicRoughness": { "baseColorFactor": [1.0, 0.2, 0.2, 0.5], "metallicFactor": 0.0, "roughnessFactor": 0.9 } }
+        ]));
+        if !self.meshes.is_empty() { out.insert("meshes".to_string(), json!(self.meshes)); }
+        if !self.accessors.is_empty() { out.insert("accessors".to_string(), json!(self.accessors)); }
+        if !self.buffer_views.is_empty() { out.insert("bufferViews".to_string(), json!(self.buffer_views)); }
+        if !self.binary.is_empty() { out.insert("buffers".to_string(), json!([ { "byteLength": self.binary.len() } ])); }
+        if !self.animations.is_empty() { out.insert("animations".to_string(), json!(self.animations)); }
+
+        Value::Object(out)
+    }
+    /// Triangulates a shape in its own local frame.  Mesh shapes are loaded from their trimesh or
+    /// file; cubes and spheres are generated.
+    fn geometric_shape_triangles(shape: &GeometricShape) -> Result<(Vec<Vector3<f64>>, Vec<[usize; 3]>), OptimaError> {
+        return match shape.spawner() {
+            GeometricShapeSpawner::Cube { half_extent_x, half_extent_y, half_extent_z, .. } => {
+                let mut vertices = vec![];
+                // Vertex i is at the corner with signs given by the bits of i (x, then y, then z).
+                for i in 0..8 {
+                    let x = if i & 1 == 0 { -half_extent_x } else { *half_extent_x };
+                    let y = if i & 2 == 0 { -half_extent_y } else { *half_extent_y };
+                    let z = if i & 4 == 0 { -half_extent_z } else { *half_extent_z };
+                    vertices.push(Vector3::new(x, y, z));
+                }
+                let indices = vec![[4, 5, 7], [4, 7, 6], [0, 2, 3], [0, 3, 1], [1, 3, 7], [1, 7, 5], [0, 4, 6], [0, 6, 2], [2, 6, 7], [2, 7, 3], [0, 1, 5], [0, 5, 4]];
+                Ok((vertices, indices))
+            }
+            GeometricShapeSpawner::Sphere { radius, .. } => {
+                let num_latitude_segments = 12;
+                let num_longitude_segments = 24;
+                let mut vertices = vec![];
+                for i in 0..=num_latitude_segments {
+                    let theta = PI * i as f64 / num_latitude_segments as f64;
+                    for j in 0..num_longitude_segments {
+                        let phi = 2.0 * PI * j as f64 / num_longitude_segments as f64;
+                        vertices.push(Vector3::new(radius * theta.sin() * phi.cos(), radius * theta.sin() * phi.sin(), radius * theta.cos()));
+                    }
+                }
+                let mut indices = vec![];
+                for i in 0..num_latitude_segments {
+                    for j in 0..num_longitude_segments {
+                        let a = i * num_longitude_segments + j;
+                        let b = i * num_longitude_segments + (j + 1) % num_longitude_segments;
+                        let c = a + num_longitude_segments;
+                        let d = b + num_longitude_segments;
+                        indices.push([a, c, d]);
+                        indices.push([a, d, b]);
+                    }
+                }
+                Ok((vertices, indices))
+            }
+            GeometricShapeSpawner::ConvexShape { path_string_components, trimesh_engine, .. } |
+            GeometricShapeSpawner::TriangleMesh { path_string_components, trimesh_engine, .. } => {
+                let trimesh_engine = match trimesh_engine {
+                    Some(trimesh_engine) => { trimesh_engine.clone() }
+                    None => { OptimaStemCellPath::new_asset_path_from_string_components(path_string_components)?.load_file_to_trimesh_engine()? }
+                };
+                Ok((trimesh_engine.vertices().clone(), trimesh_engine.indices().clone()))
+            }
+        }
+    }
+}
+impl Default for GltfSceneExporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Python implementations.
+#[cfg(not(target_arch = "wasm32"))]
+#[pymethods]
+impl GltfSceneExporter {
+    #[new]
+    pub fn new_py() -> Self {
+        Self::new()
+    }
+    pub fn add_robot_py(&mut self, robot_kinematics_module: &RobotKinematicsModule, joint_state: Vec<f64>) {
+        let robot_joint_state = robot_kinematics_module.robot_joint_state_module().spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
+        self.add_robot(robot_kinematics_module, &robot_joint_state).expect("error");
+    }
+    pub fn add_robot_with_trajectory_py(&mut self, robot_kinematics_module: &RobotKinematicsModule, trajectory: &RobotJointTrajectory) {
+        self.add_robot_with_trajectory(robot_kinematics_module, trajectory).expect("error");
+    }
+    pub fn add_environment_py(&mut self, scene: &RobotGeometricShapeScenePy, robot_set_joint_state: Vec<f64>) {
+        let scene = scene.robot_geometric_shape_scene();
+        let robot_set_joint_state = scene.robot_set().robot_set_joint_state_module().spawn_robot_set_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&robot_set_joint_state)).expect("error");
+        self.add_environment(scene, &robot_set_joint_state).expect("error");
+    }
+    #[args(normal_length = "0.05")]
+    pub fn add_collision_visualization_py(&mut self, collision_visualization: &CollisionVisualization, scene: Option<PyRef<RobotGeometricShapeScenePy>>, robot_set_joint_state: Option<Vec<f64>>, normal_length: f64) {
+        match (scene, robot_set_joint_state) {
+            (Some(scene_py), Some(robot_set_joint_state)) => {
+                let scene = scene_py.robot_geometric_shape_scene();
+                let robot_set_joint_state = scene.robot_set().robot_set_joint_state_module().spawn_robot_set_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&robot_set_joint_state)).expect("error");
+                let poses = scene.recover_poses(&robot_set_joint_state, None).expect("error");
+                self.add_collision_visualization(collision_visualization, Some((scene.shape_collection(), &poses)), normal_length).expect("error");
+            }
+            _ => { self.add_collision_visualization(collision_visualization, None, normal_length).expect("error"); }
+        }
+    }
+    pub fn save_glb_py(&self, path: &str) {
+        let path = OptimaPath::Path(PathBuf::from(path));
+        self.save_glb(&path).expect("error");
+    }
+}
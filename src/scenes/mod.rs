@@ -5,3 +5,5 @@ pub trait GetRobotGeometricShapeScene {
 }
 
 pub mod robot_geometric_shape_scene;
+pub mod gltf_export;
+pub mod scene_description;
@@ -4,24 +4,30 @@ use pyo3::*;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-use nalgebra::{DVector, Vector3};
+use nalgebra::{DVector, Point3, Vector3};
 use parry3d_f64::query::Ray;
 use serde::{Serialize, Deserialize};
 use crate::robot_modules::robot_geometric_shape_module::RobotLinkShapeRepresentation;
 use crate::robot_set_modules::GetRobotSet;
 use crate::robot_set_modules::robot_set::{RobotSet};
+use crate::robot_set_modules::robot_set_geometric_shape_module::robot_set_shape_label;
 #[cfg(not(target_arch = "wasm32"))]
 use crate::robot_set_modules::robot_set::{RobotSetPy};
 use crate::robot_set_modules::robot_set_joint_state_module::RobotSetJointState;
 use crate::scenes::GetRobotGeometricShapeScene;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::scenes::scene_description::SceneDescription;
 use crate::utils::utils_console::{optima_print, optima_print_new_line, PrintColor, PrintMode};
 use crate::utils::utils_errors::OptimaError;
-use crate::utils::utils_files::optima_path::{load_object_from_json_string, OptimaAssetLocation, OptimaStemCellPath};
+use crate::utils::utils_files::optima_path::{OptimaAssetLocation, OptimaStemCellPath};
 use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3Pose, OptimaSE3PosePy, OptimaSE3PoseType};
 use crate::utils::utils_shape_geometry::geometric_shape::{BVHCombinableShape, BVHCombinableShapeAABB, GeometricShape, GeometricShapeQueryGroupOutput, GeometricShapeSignature, LogCondition, StopCondition};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::utils::utils_shape_geometry::geometric_shape::{GeometricShapeQueryGroupOutputPy};
-use crate::utils::utils_shape_geometry::shape_collection::{BVH, BVHSceneFilterOutput, BVHVisit, ProximaBudget, ProximaEngine, ProximaProximityOutput, ProximaSceneFilterOutput, ShapeCollection, ShapeCollectionBVH, ShapeCollectionBVHAABB, ShapeCollectionInputPoses, ShapeCollectionQuery, ShapeCollectionQueryList, ShapeCollectionQueryPairsList, SignedDistanceLossFunction};
+use crate::utils::utils_shape_geometry::query_output_summary::QueryOutputSummary;
+use crate::utils::utils_shape_geometry::shape_collection::{BatchRayCastOutput, BVH, BVHSceneFilterOutput, BVHVisit, ProximaBudget, ProximaEngine, ProximaProximityOutput, ProximaSceneFilterOutput, ShapeCollection, ShapeCollectionBVH, ShapeCollectionBVHAABB, ShapeCollectionInputPoses, ShapeCollectionQuery, ShapeCollectionQueryList, ShapeCollectionQueryPairsList, SignedDistanceLossFunction};
+use crate::utils::utils_shape_geometry::depth_rendering::{CameraIntrinsics, DepthImage, DepthRenderer};
+use crate::utils::utils_shape_geometry::convex_shape_cache::ConvexShapeCache;
 use crate::utils::utils_shape_geometry::trimesh_engine::ConvexDecompositionResolution;
 use crate::utils::utils_traits::{SaveAndLoadable, ToAndFromRonString};
 
@@ -80,6 +86,8 @@ pub struct RobotGeometricShapeScene {
     robot_and_link_idx_to_shape_idxs_mapping: Vec<Vec<Vec<usize>>>,
     env_obj_idx_to_shape_idxs_mapping: Vec<Vec<usize>>,
     env_obj_idx_to_pose_constraint: Vec<EnvObjPoseConstraint>,
+    #[serde(default)]
+    env_obj_idx_to_name: Vec<String>,
     last_robot_link_shape_idx: usize,
     env_obj_count: usize,
     env_obj_spawners: Vec<EnvObjSpawner>
@@ -101,6 +109,7 @@ impl RobotGeometricShapeScene {
             robot_and_link_idx_to_shape_idxs_mapping,
             env_obj_idx_to_shape_idxs_mapping: vec![],
             env_obj_idx_to_pose_constraint: vec![],
+            env_obj_idx_to_name: vec![],
             last_robot_link_shape_idx,
             env_obj_count: 0,
             env_obj_spawners: vec![]
@@ -111,13 +120,35 @@ impl RobotGeometricShapeScene {
         Ok(out_self)
     }
     /// Adds an environment object to the scene.  Returns environment object index.
+    ///
+    /// The object is named by the spawner's name if one is given (which must not already be used in
+    /// the scene), or otherwise by its asset name (with its environment object index appended if
+    /// that name is already taken).
     pub fn add_environment_object(&mut self,
                                   spawner: EnvObjSpawner,
                                   force_preprocessing: bool) -> Result<usize, OptimaError> {
+        let name = match &spawner.name {
+            Some(name) => {
+                if self.env_obj_idx_to_name.contains(name) {
+                    return Err(OptimaError::new_generic_error_str(&format!("Environment object with name {:?} is already in the scene.", name), file!(), line!()));
+                }
+                name.clone()
+            }
+            None => {
+                if self.env_obj_idx_to_name.contains(&spawner.asset_name) { format!("{}_{}", spawner.asset_name, self.env_obj_count) } else { spawner.asset_name.clone() }
+            }
+        };
+
         self.env_obj_spawners.push( spawner.clone());
 
-        self.preprocess_object_shape_if_necessary(&spawner.asset_name, spawner.decomposition_resolution, force_preprocessing)?;
-        let geometric_shapes = self.get_geometric_shapes_to_add_to_environment(&spawner.asset_name, spawner.scale, spawner.shape_representation)?;
+        let geometric_shapes = match &spawner.primitive {
+            None => {
+                self.preprocess_object_shape_if_necessary(&spawner.asset_name, spawner.decomposition_resolution, force_preprocessing)?;
+                self.get_geometric_shapes_to_add_to_environment(&spawner.asset_name, &name, spawner.scale, spawner.shape_representation)?
+            }
+            Some(primitive) => { vec![self.get_primitive_geometric_shape_to_add_to_environment(primitive, &name, spawner.scale)] }
+        };
+        self.env_obj_idx_to_name.push(name);
         return self.add_env_obj_geometric_shapes_to_scene(&geometric_shapes, spawner.pose_constraint);
     }
     fn get_path_to_mesh_file(&self, name: &str) -> Result<OptimaStemCellPath, OptimaError> {
@@ -154,8 +185,8 @@ impl RobotGeometricShapeScene {
             let path_to_mesh_file = self.get_path_to_mesh_file(name)?;
             let trimesh_engine = path_to_mesh_file.load_file_to_trimesh_engine()?;
 
-            let convex_shape = trimesh_engine.compute_convex_hull();
-            let convex_shape_subcomponents = trimesh_engine.compute_convex_decomposition(decomposition_resolution);
+            let convex_shape = ConvexShapeCache::get_or_compute_convex_hull(&trimesh_engine)?;
+            let convex_shape_subcomponents = ConvexShapeCache::get_or_compute_convex_decomposition(&trimesh_engine, decomposition_resolution)?;
 
             let mut output_path = OptimaStemCellPath::new_asset_path()?;
             output_path.append_file_location(&OptimaAssetLocation::SceneMeshFileConvexShape {name: name.to_string()});
@@ -174,7 +205,7 @@ impl RobotGeometricShapeScene {
 
         Ok(())
     }
-    fn get_geometric_shapes_to_add_to_environment(&self, name: &str, scale: Option<f64>, shape_representation: Option<EnvObjShapeRepresentation>) -> Result<Vec<GeometricShape>, OptimaError> {
+    fn get_geometric_shapes_to_add_to_environment(&self, name: &str, env_obj_name: &str, scale: Option<f64>, shape_representation: Option<EnvObjShapeRepresentation>) -> Result<Vec<GeometricShape>, OptimaError> {
         let mut out_vec = vec![];
 
         let shape_representation = match shape_representation {
@@ -212,7 +243,7 @@ impl RobotGeometricShapeScene {
 
         let mut base_shapes = vec![];
         for (i, t) in base_trimesh_engines.iter().enumerate() {
-            let signature = GeometricShapeSignature::EnvironmentObject { environment_object_idx: add_idx, shape_idx_in_object: i };
+            let signature = GeometricShapeSignature::EnvironmentObject { environment_object_idx: add_idx, shape_idx_in_object: i, name: env_obj_name.to_string() };
             base_shapes.push(GeometricShape::new_triangle_mesh_from_trimesh_engine(t, signature))
         }
 
@@ -244,6 +275,22 @@ impl RobotGeometricShapeScene {
 
         Ok(out_vec)
     }
+    fn get_primitive_geometric_shape_to_add_to_environment(&self, primitive: &EnvObjPrimitive, env_obj_name: &str, scale: Option<f64>) -> GeometricShape {
+        let scale = match scale {
+            None => { 1.0 }
+            Some(scale) => { scale }
+        };
+        let signature = GeometricShapeSignature::EnvironmentObject { environment_object_idx: self.env_obj_count, shape_idx_in_object: 0, name: env_obj_name.to_string() };
+
+        return match primitive {
+            EnvObjPrimitive::Cube { half_extent_x, half_extent_y, half_extent_z } => {
+                GeometricShape::new_cube(scale * *half_extent_x, scale * *half_extent_y, scale * *half_extent_z, signature, None)
+            }
+            EnvObjPrimitive::Sphere { radius } => {
+                GeometricShape::new_sphere(scale * *radius, signature, None)
+            }
+        };
+    }
     fn add_env_obj_geometric_shapes_to_scene(&mut self, shapes: &Vec<GeometricShape>, pose_constraint: Option<EnvObjPoseConstraint>) -> Result<usize, OptimaError> {
         let add_idx = self.env_obj_count.clone();
 
@@ -280,7 +327,7 @@ impl RobotGeometricShapeScene {
             match curr_pose_constraint {
                 EnvObjPoseConstraint::RelativeOffset { parent_signature, offset:_ } => {
                     match parent_signature {
-                        GeometricShapeSignature::EnvironmentObject { environment_object_idx, shape_idx_in_object: _ } => {
+                        GeometricShapeSignature::EnvironmentObject { environment_object_idx, .. } => {
                             if *environment_object_idx == env_obj_idx {
                                 return true;
                             } else {
@@ -298,9 +345,17 @@ impl RobotGeometricShapeScene {
     pub fn robot_set(&self) -> &RobotSet {
         &self.robot_set
     }
+    /// Summarizes the outputs of a query on this scene (see `QueryOutputSummary`) with robot links
+    /// labeled by robot and link name and environment objects by name.
+    pub fn summarize_query_output(&self, output: &GeometricShapeQueryGroupOutput, max_pairs: usize) -> QueryOutputSummary {
+        return output.summary_with_shape_labels(max_pairs, &|signature| robot_set_shape_label(self.robot_set.robot_set_kinematics_module(), signature));
+    }
     pub fn robot_link_shape_representation(&self) -> &RobotLinkShapeRepresentation {
         &self.robot_link_shape_representation
     }
+    pub fn num_env_objs(&self) -> usize {
+        self.env_obj_count
+    }
     pub fn shape_collection(&self) -> &ShapeCollection {
         &self.shape_collection
     }
@@ -315,6 +370,33 @@ impl RobotGeometricShapeScene {
 
         return Ok(&self.env_obj_idx_to_shape_idxs_mapping[env_obj_idx])
     }
+    pub fn get_shape_idxs_from_env_obj_name(&self, env_obj_name: &str) -> Result<&Vec<usize>, OptimaError> {
+        let env_obj_idx = self.get_env_obj_idx_from_name(env_obj_name)?;
+        return self.get_shape_idxs_from_env_obj_idx(env_obj_idx);
+    }
+    pub fn get_env_obj_idx_from_name(&self, env_obj_name: &str) -> Result<usize, OptimaError> {
+        return match self.env_obj_idx_to_name.iter().position(|n| n == env_obj_name) {
+            None => { Err(OptimaError::new_generic_error_str(&format!("Environment object with name {:?} not found in scene.", env_obj_name), file!(), line!())) }
+            Some(idx) => { Ok(idx) }
+        };
+    }
+    pub fn get_env_obj_name_from_idx(&self, env_obj_idx: usize) -> Result<&String, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(env_obj_idx, self.env_obj_idx_to_name.len(), file!(), line!())?;
+        return Ok(&self.env_obj_idx_to_name[env_obj_idx]);
+    }
+    /// Maps a signature to a shape index.  Environment object signatures are resolved by environment
+    /// object index and shape index in object alone, so a signature given without a name still
+    /// resolves (e.g., a parent signature in an `EnvObjPoseConstraint`).
+    pub fn get_shape_idx_from_signature(&self, signature: &GeometricShapeSignature) -> Result<usize, OptimaError> {
+        return match signature {
+            GeometricShapeSignature::EnvironmentObject { environment_object_idx, shape_idx_in_object, .. } => {
+                let shape_idxs = self.get_shape_idxs_from_env_obj_idx(*environment_object_idx)?;
+                OptimaError::new_check_for_idx_out_of_bound_error(*shape_idx_in_object, shape_idxs.len(), file!(), line!())?;
+                Ok(shape_idxs[*shape_idx_in_object])
+            }
+            _ => { self.shape_collection.get_shape_idx_from_signature(signature) }
+        };
+    }
     /// Updates the pose constraint on a given environment object in the scene.
     pub fn update_env_obj_pose_constraint(&mut self, env_obj_idx: usize, pose_constraint: EnvObjPoseConstraint) -> Result<(), OptimaError> {
         OptimaError::new_check_for_idx_out_of_bound_error(env_obj_idx, self.env_obj_idx_to_pose_constraint.len(), file!(), line!())?;
@@ -381,7 +463,7 @@ impl RobotGeometricShapeScene {
                         env_obj_is_done[env_obj_idx] = true;
                     }
                     EnvObjPoseConstraint::RelativeOffset { parent_signature, offset } => {
-                        let parent_shape_idx = self.shape_collection.get_shape_idx_from_signature(parent_signature)?;
+                        let parent_shape_idx = self.get_shape_idx_from_signature(parent_signature)?;
                         OptimaError::new_check_for_idx_out_of_bound_error(parent_shape_idx, out_poses.poses().len(), file!(), line!())?;
                         let parent_shape_pose_option = out_poses.poses().get(parent_shape_idx).unwrap();
                         match parent_shape_pose_option {
@@ -460,6 +542,29 @@ impl RobotGeometricShapeScene {
         Ok(())
     }
     */
+    /// Sets the collision margin applied to all shapes in the scene (see `CollisionMargins`).  With a
+    /// margin of 0.02, for example, robot links within 2 cm of an obstacle count as in collision.
+    pub fn set_global_collision_margin(&mut self, margin: f64) -> Result<(), OptimaError> {
+        return self.shape_collection.set_global_collision_margin(margin);
+    }
+    /// Overrides the global collision margin on all shapes of the given robot link.  Passing None
+    /// reverts the link to the global margin.
+    pub fn set_robot_link_collision_margin(&mut self, robot_idx_in_set: usize, link_idx_in_robot: usize, margin: Option<f64>) -> Result<(), OptimaError> {
+        let shape_idxs = self.get_shape_idxs_from_robot_idx_and_link_idx(robot_idx_in_set, link_idx_in_robot)?.clone();
+        for shape_idx in shape_idxs {
+            self.shape_collection.set_shape_collision_margin(shape_idx, margin)?;
+        }
+        Ok(())
+    }
+    /// Overrides the global collision margin on all shapes of the given environment object.  Passing
+    /// None reverts the object to the global margin.
+    pub fn set_env_obj_collision_margin(&mut self, env_obj_idx: usize, margin: Option<f64>) -> Result<(), OptimaError> {
+        let shape_idxs = self.get_shape_idxs_from_env_obj_idx(env_obj_idx)?.clone();
+        for shape_idx in shape_idxs {
+            self.shape_collection.set_shape_collision_margin(shape_idx, margin)?;
+        }
+        Ok(())
+    }
     pub fn shape_collection_query<'a>(&'a self,
                                       input: &'a RobotGeometricShapeSceneQuery,
                                       stop_condition: StopCondition,
@@ -613,6 +718,29 @@ impl RobotGeometricShapeScene {
 
         return self.shape_collection.bvh_scene_filter(bvh, &poses, visit);
     }
+    /// Casts all given rays against the robot set and environment objects at the given joint state
+    /// (see `ShapeCollection::batch_cast_rays`).
+    pub fn batch_cast_rays(&self,
+                           robot_set_joint_state: &RobotSetJointState,
+                           env_obj_pose_constraint_group_input: Option<&EnvObjPoseConstraintGroupInput>,
+                           rays: &Vec<Ray>,
+                           max_toi: f64,
+                           solid: bool,
+                           inclusion_list: &Option<&ShapeCollectionQueryList>) -> Result<BatchRayCastOutput, OptimaError> {
+        let poses = self.recover_poses(robot_set_joint_state, env_obj_pose_constraint_group_input)?;
+        return Ok(self.shape_collection.batch_cast_rays(&poses, rays, max_toi, solid, inclusion_list));
+    }
+    /// Renders a depth image of the robot set and environment objects at the given joint state from
+    /// a camera at camera_pose (see `DepthRenderer`).
+    pub fn render_depth_image(&self,
+                              robot_set_joint_state: &RobotSetJointState,
+                              env_obj_pose_constraint_group_input: Option<&EnvObjPoseConstraintGroupInput>,
+                              intrinsics: &CameraIntrinsics,
+                              camera_pose: &OptimaSE3Pose,
+                              max_depth: f64) -> Result<DepthImage, OptimaError> {
+        let poses = self.recover_poses(robot_set_joint_state, env_obj_pose_constraint_group_input)?;
+        return Ok(DepthRenderer::render_depth_image(&self.shape_collection, &poses, intrinsics, camera_pose, max_depth, &None));
+    }
 
     pub fn print_summary(&self) {
         self.robot_set.print_summary();
@@ -621,7 +749,7 @@ impl RobotGeometricShapeScene {
         let num_objects = self.env_obj_count;
         optima_print(&format!("{} objects.", num_objects), PrintMode::Println, PrintColor::Cyan, true);
         for i in 0..num_objects {
-            optima_print(&format!(" Object {} ---> ", i), PrintMode::Println, PrintColor::Cyan, false);
+            optima_print(&format!(" Object {} ({}) ---> ", i, self.env_obj_idx_to_name[i]), PrintMode::Println, PrintColor::Cyan, false);
             optima_print(&format!("    Object Info: {:?}", self.env_obj_spawners[i].to_self_no_nones()), PrintMode::Println, PrintColor::None, false);
             optima_print(&format!("    Object Pose: {:?}", self.env_obj_idx_to_pose_constraint[i]), PrintMode::Println, PrintColor::None, false);
         }
@@ -635,7 +763,7 @@ impl SaveAndLoadable for RobotGeometricShapeScene {
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let load: Self::SaveType = load_object_from_json_string(json_str)?;
+        let load: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
         return Ok(load);
     }
 }
@@ -670,6 +798,12 @@ pub struct RobotGeometricShapeScenePy {
     robot_geometric_shape_scene: RobotGeometricShapeScene
 }
 #[cfg(not(target_arch = "wasm32"))]
+impl RobotGeometricShapeScenePy {
+    pub fn robot_geometric_shape_scene(&self) -> &RobotGeometricShapeScene {
+        &self.robot_geometric_shape_scene
+    }
+}
+#[cfg(not(target_arch = "wasm32"))]
 #[pymethods]
 impl RobotGeometricShapeScenePy {
     #[new]
@@ -681,9 +815,31 @@ impl RobotGeometricShapeScenePy {
             robot_geometric_shape_scene
         }
     }
+    #[staticmethod]
+    pub fn new_from_robot_geometric_shape_scene(robot_geometric_shape_scene: RobotGeometricShapeScene, py: Python) -> Self {
+        let robot_set_py = RobotSetPy::new_from_robot_set(robot_geometric_shape_scene.robot_set.clone(), py);
+        Self {
+            robot_set_py: Py::new(py, robot_set_py).expect("error"),
+            robot_geometric_shape_scene
+        }
+    }
+    pub fn __reduce__(&self, py: Python) -> PyResult<(PyObject, (RobotGeometricShapeScene,))> {
+        let constructor = py.get_type::<Self>().getattr("new_from_robot_geometric_shape_scene")?;
+        return Ok((PyObject::from(constructor), (self.robot_geometric_shape_scene.clone(),)));
+    }
+    /// Spawns the scene of the scene description saved under the given name (see `SceneDescription`).
+    #[staticmethod]
+    pub fn new_from_scene_description(scene_description_name: &str, py: Python) -> Self {
+        let robot_geometric_shape_scene = SceneDescription::load_planning_scene(scene_description_name).expect("error").into_robot_geometric_shape_scene();
+        let robot_set_py = RobotSetPy::new_from_robot_set(robot_geometric_shape_scene.robot_set.clone(), py);
+        Self {
+            robot_set_py: Py::new(py, robot_set_py).expect("error"),
+            robot_geometric_shape_scene
+        }
+    }
     #[args(scale="1.0", shape_representation="\"CubeSubcomponents\"", decomposition_resolution="\"Medium\"", force_preprocessing="false")]
-    pub fn add_environment_object_py(&mut self, asset_name: &str, scale: f64, shape_representation: &str, decomposition_resolution: &str, force_preprocessing: bool, pose: Option<OptimaSE3PosePy>) -> usize {
-        let env_obj_spawner = EnvObjSpawner::new(
+    pub fn add_environment_object_py(&mut self, asset_name: &str, scale: f64, shape_representation: &str, decomposition_resolution: &str, force_preprocessing: bool, pose: Option<OptimaSE3PosePy>, name: Option<&str>) -> usize {
+        let mut env_obj_spawner = EnvObjSpawner::new(
             asset_name,
             Some(scale),
             Some(EnvObjShapeRepresentation::from_ron_string(shape_representation).unwrap()),
@@ -692,11 +848,44 @@ impl RobotGeometricShapeScenePy {
                 None => { OptimaSE3Pose::default() }
                 Some(p) => { p.pose().clone() }
             })));
+        if let Some(name) = name { env_obj_spawner.set_name(name); }
 
         let idx = self.robot_geometric_shape_scene.add_environment_object(env_obj_spawner, force_preprocessing).expect("error");
 
         return idx;
     }
+    /// ray_origins and ray_directions are lists of \[x, y, z\] vectors of the same length.
+    #[args(max_toi="100.0", solid="true")]
+    pub fn batch_cast_rays_py(&self, robot_set_joint_state: Vec<f64>, ray_origins: Vec<Vec<f64>>, ray_directions: Vec<Vec<f64>>, max_toi: f64, solid: bool) -> BatchRayCastOutput {
+        assert_eq!(ray_origins.len(), ray_directions.len(), "ray_origins and ray_directions must have the same length.");
+        let robot_set_joint_state = self.robot_geometric_shape_scene.robot_set.robot_set_joint_state_module().spawn_robot_set_joint_state_try_auto_type(DVector::from_vec(robot_set_joint_state)).expect("error");
+
+        let mut rays = vec![];
+        for (o, d) in ray_origins.iter().zip(ray_directions.iter()) {
+            rays.push(Ray::new(Point3::new(o[0], o[1], o[2]), Vector3::new(d[0], d[1], d[2])));
+        }
+
+        return self.robot_geometric_shape_scene.batch_cast_rays(&robot_set_joint_state, None, &rays, max_toi, solid, &None).expect("error");
+    }
+    /// Returns height rows of width depths, with inf where nothing is hit.
+    #[args(max_depth="10.0")]
+    pub fn render_depth_image_py(&self, robot_set_joint_state: Vec<f64>, camera_pose: OptimaSE3PosePy, width: usize, height: usize, fx: f64, fy: f64, cx: f64, cy: f64, max_depth: f64) -> Vec<Vec<f64>> {
+        let robot_set_joint_state = self.robot_geometric_shape_scene.robot_set.robot_set_joint_state_module().spawn_robot_set_joint_state_try_auto_type(DVector::from_vec(robot_set_joint_state)).expect("error");
+        let intrinsics = CameraIntrinsics::new(width, height, fx, fy, cx, cy).expect("error");
+        let res = self.robot_geometric_shape_scene.render_depth_image(&robot_set_joint_state, None, &intrinsics, camera_pose.pose(), max_depth).expect("error");
+        return res.to_rows();
+    }
+    /// Returns the world frame point cloud seen by the given camera as a list of \[x, y, z\] points.
+    #[args(max_depth="10.0")]
+    pub fn render_point_cloud_py(&self, robot_set_joint_state: Vec<f64>, camera_pose: OptimaSE3PosePy, width: usize, height: usize, fx: f64, fy: f64, cx: f64, cy: f64, max_depth: f64) -> Vec<Vec<f64>> {
+        let robot_set_joint_state = self.robot_geometric_shape_scene.robot_set.robot_set_joint_state_module().spawn_robot_set_joint_state_try_auto_type(DVector::from_vec(robot_set_joint_state)).expect("error");
+        let intrinsics = CameraIntrinsics::new(width, height, fx, fy, cx, cy).expect("error");
+        let res = self.robot_geometric_shape_scene.render_depth_image(&robot_set_joint_state, None, &intrinsics, camera_pose.pose(), max_depth).expect("error");
+        return res.point_cloud_in_world_frame().iter().map(|p| vec![p[0], p[1], p[2]]).collect();
+    }
+    pub fn get_env_obj_idx_from_name_py(&self, env_obj_name: &str) -> usize {
+        return self.robot_geometric_shape_scene.get_env_obj_idx_from_name(env_obj_name).expect("error");
+    }
     pub fn update_env_obj_pose_constraint_py(&mut self, env_obj_idx: usize, pose: OptimaSE3PosePy, parent_signature: Option<&str>) {
         match parent_signature {
             None => {
@@ -710,6 +899,15 @@ impl RobotGeometricShapeScenePy {
             }
         }
     }
+    pub fn set_global_collision_margin_py(&mut self, margin: f64) {
+        self.robot_geometric_shape_scene.set_global_collision_margin(margin).expect("error");
+    }
+    pub fn set_robot_link_collision_margin_py(&mut self, robot_idx_in_set: usize, link_idx_in_robot: usize, margin: Option<f64>) {
+        self.robot_geometric_shape_scene.set_robot_link_collision_margin(robot_idx_in_set, link_idx_in_robot, margin).expect("error");
+    }
+    pub fn set_env_obj_collision_margin_py(&mut self, env_obj_idx: usize, margin: Option<f64>) {
+        self.robot_geometric_shape_scene.set_env_obj_collision_margin(env_obj_idx, margin).expect("error");
+    }
     pub fn print_summary_py(&self) {
         self.robot_geometric_shape_scene.print_summary();
     }
@@ -726,7 +924,8 @@ impl RobotGeometricShapeScenePy {
             inclusion_list: &None
         };
         let res = self.robot_geometric_shape_scene.shape_collection_query(&input, stop_condition, log_condition, sort_outputs).expect("error");
-        let py_output = res.convert_to_py_output(include_full_output_json_string);
+        let mut py_output = res.convert_to_py_output(include_full_output_json_string);
+        py_output.set_summary(self.robot_geometric_shape_scene.summarize_query_output(&res, 10));
         return py_output;
     }
 
@@ -780,16 +979,25 @@ impl RobotGeometricShapeScenePy {
         let log_condition = LogCondition::from_ron_string(log_condition).expect("error");
 
         let res = self.robot_geometric_shape_scene.shape_collection_query(&input, stop_condition, log_condition, sort_outputs).expect("error");
-        let py_output = res.convert_to_py_output(include_full_output_json_string);
+        let mut py_output = res.convert_to_py_output(include_full_output_json_string);
+        py_output.set_summary(self.robot_geometric_shape_scene.summarize_query_output(&res, 10));
         return py_output;
     }
 }
 
 /// Used to spawn environment objects in the scene.  These spawners can also be saved to
 /// load the same environment at a later time.
+///
+/// A spawner either refers to a mesh asset in the optima_scenes/mesh_files/ directory or, if made with
+/// `new_primitive`, describes a primitive shape directly (in which case the shape and decomposition
+/// settings do not apply).
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct EnvObjSpawner {
     asset_name: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    primitive: Option<EnvObjPrimitive>,
     scale: Option<f64>,
     shape_representation: Option<EnvObjShapeRepresentation>,
     decomposition_resolution: Option<ConvexDecompositionResolution>,
@@ -803,15 +1011,44 @@ impl EnvObjSpawner {
                pose_constraint: Option<EnvObjPoseConstraint>) -> Self {
         Self {
             asset_name: asset_name.to_string(),
+            name: None,
+            primitive: None,
             scale,
             shape_representation,
             decomposition_resolution,
             pose_constraint
         }
     }
+    /// A spawner for a primitive shape.  If no name is set, the object is named "cube" or "sphere".
+    pub fn new_primitive(primitive: EnvObjPrimitive, pose_constraint: Option<EnvObjPoseConstraint>) -> Self {
+        let asset_name = match &primitive {
+            EnvObjPrimitive::Cube { .. } => { "cube" }
+            EnvObjPrimitive::Sphere { .. } => { "sphere" }
+        };
+
+        Self {
+            asset_name: asset_name.to_string(),
+            name: None,
+            primitive: Some(primitive),
+            scale: None,
+            shape_representation: None,
+            decomposition_resolution: None,
+            pose_constraint
+        }
+    }
+    /// Sets the name of the spawned environment object in its scene.  If no name is set, the asset
+    /// name is used.
+    pub fn set_name(&mut self, name: &str) {
+        self.name = Some(name.to_string());
+    }
     fn to_self_no_nones(&self) -> Self {
         Self {
             asset_name: self.asset_name.clone(),
+            name: match &self.name {
+                None => { Some(self.asset_name.clone()) }
+                Some(n) => { Some(n.clone()) }
+            },
+            primitive: self.primitive.clone(),
             scale: match self.scale {
                 None => { Some(1.0) }
                 Some(s) => {Some(s)}
@@ -835,6 +1072,8 @@ impl Default for EnvObjSpawner {
     fn default() -> Self {
         Self {
             asset_name: "sphere".to_string(),
+            name: None,
+            primitive: None,
             scale: None,
             shape_representation: None,
             decomposition_resolution: None,
@@ -843,6 +1082,13 @@ impl Default for EnvObjSpawner {
     }
 }
 
+/// A primitive environment object shape, centered at the object's frame.  Dimensions are in meters.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EnvObjPrimitive {
+    Cube { half_extent_x: f64, half_extent_y: f64, half_extent_z: f64 },
+    Sphere { radius: f64 }
+}
+
 /// Used to specify a pose for a given environment object in a scene.  For example, a pose constraint
 /// can be an absolue pose in the scene, or a pose that is parented to another shape with a local
 /// pose offset.
@@ -914,3 +1160,5 @@ pub enum RobotGeometricShapeSceneQuery<'a> {
     Contact { robot_set_joint_state: &'a RobotSetJointState, env_obj_pose_constraint_group_input: Option<&'a EnvObjPoseConstraintGroupInput>, prediction: f64, inclusion_list: &'a Option<&'a ShapeCollectionQueryPairsList> },
     CCD { robot_set_joint_state_t1: &'a RobotSetJointState, env_obj_pose_constraint_group_input_t1: Option<&'a EnvObjPoseConstraintGroupInput>, robot_set_joint_state_t2: &'a RobotSetJointState, env_obj_pose_constraint_group_input_t2: Option<&'a EnvObjPoseConstraintGroupInput>, inclusion_list: &'a Option<&'a ShapeCollectionQueryPairsList> }
 }
+
+crate::impl_py_pickle!(RobotGeometricShapeScene);
@@ -0,0 +1,387 @@
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_configuration_module::RobotConfigurationModule;
+use crate::robot_modules::robot_geometric_shape_module::RobotLinkShapeRepresentation;
+use crate::robot_set_modules::robot_set::RobotSet;
+use crate::robot_set_modules::robot_set_configuration_module::RobotSetConfigurationModule;
+use crate::scenes::robot_geometric_shape_scene::{EnvObjPoseConstraint, EnvObjPrimitive, EnvObjShapeRepresentation, EnvObjSpawner, RobotGeometricShapeScene};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_files::optima_path::{OptimaAssetLocation, OptimaStemCellPath};
+use crate::utils::utils_robot::robot_module_utils::RobotNames;
+use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3Pose, OptimaSE3PoseType};
+use crate::utils::utils_shape_geometry::geometric_shape::GeometricShapeSignature;
+use crate::utils::utils_shape_geometry::trimesh_engine::ConvexDecompositionResolution;
+use crate::utils::utils_traits::{ToAndFromJsonString, ToAndFromYamlString};
+
+/// A human editable description of a complete planning scene: the robots (either a saved robot set
+/// or a list of robot configurations with base poses), obstacles (mesh assets from the
+/// optima_scenes/mesh_files/ directory or primitive shapes) with poses, and named frames (e.g., a
+/// place location or a camera mount).  Descriptions are YAML or JSON files saved in the
+/// optima_scenes/scene_descriptions/ directory and are turned into a `PlanningScene` with
+/// `load_planning_scene`.
+///
+/// Poses are given as a translation and XYZ euler angles (see
+/// `OptimaSE3Pose::new_from_euler_angles`), relative to the world or to a parent, which is the name
+/// of an obstacle for obstacles, and the name of an obstacle or an earlier frame for frames.  An
+/// obstacle with a parent keeps its offset from the parent when the parent is moved in the scene.
+/// Parents must be listed before their children.
+///
+/// # Example
+/// ```yaml
+/// robots:
+///   - robot_name: ur5
+///     base_pose: { translation: [0.0, 0.0, 0.8] }
+/// robot_link_shape_representation: ConvexShapes
+/// obstacles:
+///   - name: table
+///     primitive: { Cube: { half_extent_x: 0.6, half_extent_y: 0.4, half_extent_z: 0.4 } }
+///     pose: { translation: [0.5, 0.0, 0.4] }
+///   - name: mug
+///     mesh: mug
+///     pose: { translation: [0.1, 0.1, 0.45], rotation: [0.0, 0.0, 1.57] }
+///     parent: table
+/// frames:
+///   - name: place_location
+///     pose: { translation: [-0.2, 0.1, 0.45] }
+///     parent: table
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneDescription {
+    #[serde(default)]
+    robot_set_name: Option<String>,
+    #[serde(default)]
+    robots: Vec<SceneDescriptionRobot>,
+    #[serde(default)]
+    robot_link_shape_representation: Option<RobotLinkShapeRepresentation>,
+    #[serde(default)]
+    obstacles: Vec<SceneDescriptionObstacle>,
+    #[serde(default)]
+    frames: Vec<SceneDescriptionFrame>
+}
+impl SceneDescription {
+    pub fn new_empty() -> Self {
+        Self {
+            robot_set_name: None,
+            robots: vec![],
+            robot_link_shape_representation: None,
+            obstacles: vec![],
+            frames: vec![]
+        }
+    }
+    /// Loads the description saved under the given name in the optima_scenes/scene_descriptions/
+    /// directory (as name.yaml, name.yml, or name.json).
+    pub fn new_from_name(name: &str) -> Result<Self, OptimaError> {
+        for extension in ["yaml", "yml", "json"] {
+            let mut path = OptimaStemCellPath::new_asset_path()?;
+            path.append_file_location(&OptimaAssetLocation::SceneDescriptions);
+            path.append(&format!("{}.{}", name, extension));
+            if path.exists() { return Self::new_from_path(&path); }
+        }
+
+        Err(OptimaError::new_generic_error_str(&format!("Scene description {:?} does not exist in the scene descriptions directory.", name), file!(), line!()))
+    }
+    /// Loads a description from a YAML (.yaml or .yml) or JSON (.json) file.
+    pub fn new_from_path(path: &OptimaStemCellPath) -> Result<Self, OptimaError> {
+        let contents = path.read_file_contents_to_string()?;
+        return match path.extension().unwrap_or_default().to_lowercase().as_str() {
+            "yaml" | "yml" => { Self::from_yaml_string(&contents) }
+            "json" => { Self::from_json_string(&contents) }
+            e => { Err(OptimaError::new_generic_error_str(&format!("Unsupported scene description extension {:?} (expected yaml, yml, or json).", e), file!(), line!())) }
+        };
+    }
+    /// Loads the description saved under the given name and spawns its planning scene.
+    pub fn load_planning_scene(name: &str) -> Result<PlanningScene, OptimaError> {
+        return Self::new_from_name(name)?.spawn_planning_scene();
+    }
+    /// Saves the description as name.yaml in the optima_scenes/scene_descriptions/ directory.
+    pub fn save(&self, name: &str) -> Result<(), OptimaError> {
+        let mut path = OptimaStemCellPath::new_asset_path()?;
+        path.append_file_location(&OptimaAssetLocation::SceneDescriptions);
+        path.append(&format!("{}.yaml", name));
+        return path.write_string_to_file(&self.to_yaml_string());
+    }
+    /// Uses a robot set saved with `RobotSetConfigurationModule` instead of the listed robots.
+    pub fn set_robot_set_name(&mut self, robot_set_name: Option<&str>) {
+        self.robot_set_name = robot_set_name.map(|s| s.to_string());
+    }
+    pub fn add_robot(&mut self, robot_name: &str, configuration_name: Option<&str>, base_pose: Option<&OptimaSE3Pose>) {
+        self.robots.push(SceneDescriptionRobot {
+            robot_name: robot_name.to_string(),
+            configuration_name: configuration_name.map(|s| s.to_string()),
+            base_pose: base_pose.map(|p| SceneDescriptionPose::from_optima_se3_pose(p))
+        });
+    }
+    pub fn set_robot_link_shape_representation(&mut self, robot_link_shape_representation: Option<RobotLinkShapeRepresentation>) {
+        self.robot_link_shape_representation = robot_link_shape_representation;
+    }
+    pub fn add_mesh_obstacle(&mut self, name: &str, asset_name: &str, pose: &OptimaSE3Pose, parent: Option<&str>) {
+        self.obstacles.push(SceneDescriptionObstacle::new(name, Some(asset_name.to_string()), None, pose, parent));
+    }
+    pub fn add_primitive_obstacle(&mut self, name: &str, primitive: EnvObjPrimitive, pose: &OptimaSE3Pose, parent: Option<&str>) {
+        self.obstacles.push(SceneDescriptionObstacle::new(name, None, Some(primitive), pose, parent));
+    }
+    pub fn add_frame(&mut self, name: &str, pose: &OptimaSE3Pose, parent: Option<&str>) {
+        self.frames.push(SceneDescriptionFrame {
+            name: name.to_string(),
+            pose: SceneDescriptionPose::from_optima_se3_pose(pose),
+            parent: parent.map(|s| s.to_string())
+        });
+    }
+    /// Builds the robot set, adds the obstacles to a new `RobotGeometricShapeScene`, and resolves the
+    /// world poses of the named frames.
+    pub fn spawn_planning_scene(&self) -> Result<PlanningScene, OptimaError> {
+        let robot_set = self.spawn_robot_set()?;
+        let robot_link_shape_representation = match &self.robot_link_shape_representation {
+            None => { RobotLinkShapeRepresentation::ConvexShapes }
+            Some(r) => { r.clone() }
+        };
+        let mut robot_geometric_shape_scene = RobotGeometricShapeScene::new(robot_set, robot_link_shape_representation, vec![])?;
+
+        // World poses of obstacles and frames by name, for resolving the frames' parents.
+        let mut world_poses: Vec<(String, OptimaSE3Pose)> = vec![];
+
+        for obstacle in &self.obstacles {
+            if world_poses.iter().any(|(n, _)| n == &obstacle.name) {
+                return Err(OptimaError::new_generic_error_str(&format!("Obstacle name {:?} is used more than once in the scene description.", obstacle.name), file!(), line!()));
+            }
+
+            let local_pose = obstacle.pose.to_optima_se3_pose();
+            let (pose_constraint, world_pose) = match &obstacle.parent {
+                None => { (EnvObjPoseConstraint::Absolute(local_pose.clone()), local_pose) }
+                Some(parent) => {
+                    let parent_env_obj_idx = match self.obstacles.iter().position(|o| &o.name == parent) {
+                        Some(idx) if world_poses.iter().any(|(n, _)| n == parent) => { idx }
+                        _ => { return Err(OptimaError::new_generic_error_str(&format!("Parent {:?} of obstacle {:?} must be an obstacle listed before it.", parent, obstacle.name), file!(), line!())); }
+                    };
+                    let parent_signature = GeometricShapeSignature::EnvironmentObject {
+                        environment_object_idx: parent_env_obj_idx,
+                        shape_idx_in_object: 0,
+                        name: parent.clone()
+                    };
+                    let world_pose = Self::resolve_world_pose(&world_poses, parent, &local_pose)?;
+                    (EnvObjPoseConstraint::RelativeOffset { parent_signature, offset: local_pose }, world_pose)
+                }
+            };
+
+            let mut spawner = match (&obstacle.mesh, &obstacle.primitive) {
+                (Some(asset_name), None) => { EnvObjSpawner::new(asset_name, obstacle.scale, obstacle.shape_representation.clone(), obstacle.decomposition_resolution.clone(), Some(pose_constraint)) }
+                (None, Some(primitive)) => {
+                    if obstacle.scale.is_some() {
+                        return Err(OptimaError::new_generic_error_str(&format!("Obstacle {:?} is a primitive, so its dimensions should be given directly rather than with a scale.", obstacle.name), file!(), line!()));
+                    }
+                    EnvObjSpawner::new_primitive(primitive.clone(), Some(pose_constraint))
+                }
+                _ => { return Err(OptimaError::new_generic_error_str(&format!("Obstacle {:?} must have exactly one of mesh or primitive.", obstacle.name), file!(), line!())); }
+            };
+            spawner.set_name(&obstacle.name);
+            robot_geometric_shape_scene.add_environment_object(spawner, false)?;
+
+            world_poses.push((obstacle.name.clone(), world_pose));
+        }
+
+        let mut frames = vec![];
+        for frame in &self.frames {
+            if world_poses.iter().any(|(n, _)| n == &frame.name) {
+                return Err(OptimaError::new_generic_error_str(&format!("Frame name {:?} is already used by an obstacle or frame in the scene description.", frame.name), file!(), line!()));
+            }
+
+            let local_pose = frame.pose.to_optima_se3_pose();
+            let world_pose = match &frame.parent {
+                None => { local_pose }
+                Some(parent) => { Self::resolve_world_pose(&world_poses, parent, &local_pose)? }
+            };
+            world_poses.push((frame.name.clone(), world_pose.clone()));
+            frames.push((frame.name.clone(), world_pose));
+        }
+
+        Ok(PlanningScene {
+            robot_geometric_shape_scene,
+            frames
+        })
+    }
+    pub fn robot_set_name(&self) -> &Option<String> {
+        &self.robot_set_name
+    }
+    pub fn robots(&self) -> &Vec<SceneDescriptionRobot> {
+        &self.robots
+    }
+    pub fn obstacles(&self) -> &Vec<SceneDescriptionObstacle> {
+        &self.obstacles
+    }
+    pub fn frames(&self) -> &Vec<SceneDescriptionFrame> {
+        &self.frames
+    }
+    fn spawn_robot_set(&self) -> Result<RobotSet, OptimaError> {
+        let robot_set_configuration_module = match (&self.robot_set_name, self.robots.is_empty()) {
+            (Some(robot_set_name), true) => { RobotSetConfigurationModule::new_from_set_name(robot_set_name)? }
+            (None, false) => {
+                let mut r = RobotSetConfigurationModule::new_empty();
+                for robot in &self.robots {
+                    let mut robot_configuration_module = RobotConfigurationModule::new_from_names(RobotNames::new(&robot.robot_name, robot.configuration_name.as_deref()))?;
+                    if let Some(base_pose) = &robot.base_pose {
+                        robot_configuration_module.set_base_offset(&base_pose.to_optima_se3_pose())?;
+                    }
+                    r.add_robot_configuration(robot_configuration_module)?;
+                }
+                r
+            }
+            _ => { return Err(OptimaError::new_generic_error_str("A scene description must have exactly one of robot_set_name or a nonempty list of robots.", file!(), line!())); }
+        };
+
+        Ok(RobotSet::new_from_robot_set_configuration_module(robot_set_configuration_module))
+    }
+    fn resolve_world_pose(world_poses: &Vec<(String, OptimaSE3Pose)>, parent: &str, local_pose: &OptimaSE3Pose) -> Result<OptimaSE3Pose, OptimaError> {
+        return match world_poses.iter().find(|(n, _)| n == parent) {
+            None => { Err(OptimaError::new_generic_error_str(&format!("Parent {:?} must be an obstacle or frame listed before its child.", parent), file!(), line!())) }
+            Some((_, parent_pose)) => { parent_pose.multiply(local_pose, true) }
+        };
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneDescriptionRobot {
+    robot_name: String,
+    #[serde(default)]
+    configuration_name: Option<String>,
+    #[serde(default)]
+    base_pose: Option<SceneDescriptionPose>
+}
+impl SceneDescriptionRobot {
+    pub fn robot_name(&self) -> &str {
+        &self.robot_name
+    }
+    pub fn configuration_name(&self) -> &Option<String> {
+        &self.configuration_name
+    }
+    pub fn base_pose(&self) -> &Option<SceneDescriptionPose> {
+        &self.base_pose
+    }
+}
+
+/// An obstacle of a `SceneDescription`.  Exactly one of mesh (a mesh asset name) or primitive is
+/// given; scale, shape_representation, and decomposition_resolution only apply to meshes (see
+/// `EnvObjSpawner`).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneDescriptionObstacle {
+    name: String,
+    #[serde(default)]
+    mesh: Option<String>,
+    #[serde(default)]
+    primitive: Option<EnvObjPrimitive>,
+    #[serde(default)]
+    pose: SceneDescriptionPose,
+    #[serde(default)]
+    parent: Option<String>,
+    #[serde(default)]
+    scale: Option<f64>,
+    #[serde(default)]
+    shape_representation: Option<EnvObjShapeRepresentation>,
+    #[serde(default)]
+    decomposition_resolution: Option<ConvexDecompositionResolution>
+}
+impl SceneDescriptionObstacle {
+    fn new(name: &str, mesh: Option<String>, primitive: Option<EnvObjPrimitive>, pose: &OptimaSE3Pose, parent: Option<&str>) -> Self {
+        Self {
+            name: name.to_string(),
+            mesh,
+            primitive,
+            pose: SceneDescriptionPose::from_optima_se3_pose(pose),
+            parent: parent.map(|s| s.to_string()),
+            scale: None,
+            shape_representation: None,
+            decomposition_resolution: None
+        }
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn mesh(&self) -> &Option<String> {
+        &self.mesh
+    }
+    pub fn primitive(&self) -> &Option<EnvObjPrimitive> {
+        &self.primitive
+    }
+    pub fn pose(&self) -> &SceneDescriptionPose {
+        &self.pose
+    }
+    pub fn parent(&self) -> &Option<String> {
+        &self.parent
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneDescriptionFrame {
+    name: String,
+    #[serde(default)]
+    pose: SceneDescriptionPose,
+    #[serde(default)]
+    parent: Option<String>
+}
+impl SceneDescriptionFrame {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn pose(&self) -> &SceneDescriptionPose {
+        &self.pose
+    }
+    pub fn parent(&self) -> &Option<String> {
+        &self.parent
+    }
+}
+
+/// A pose in a `SceneDescription`: a translation (x, y, z) and XYZ euler angles (rx, ry, rz), both
+/// zero if omitted.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SceneDescriptionPose {
+    #[serde(default)]
+    translation: [f64; 3],
+    #[serde(default)]
+    rotation: [f64; 3]
+}
+impl SceneDescriptionPose {
+    pub fn from_optima_se3_pose(pose: &OptimaSE3Pose) -> Self {
+        let (e, t) = pose.to_euler_angles_and_translation();
+        Self {
+            translation: [t[0], t[1], t[2]],
+            rotation: [e[0], e[1], e[2]]
+        }
+    }
+    pub fn to_optima_se3_pose(&self) -> OptimaSE3Pose {
+        let (e, t) = (&self.rotation, &self.translation);
+        return OptimaSE3Pose::new_from_euler_angles(e[0], e[1], e[2], t[0], t[1], t[2], &OptimaSE3PoseType::ImplicitDualQuaternion);
+    }
+    pub fn translation(&self) -> &[f64; 3] {
+        &self.translation
+    }
+    pub fn rotation(&self) -> &[f64; 3] {
+        &self.rotation
+    }
+}
+
+/// A planning-ready scene spawned from a `SceneDescription`: the `RobotGeometricShapeScene` with the
+/// description's robots and obstacles (obstacle names are environment object names, see
+/// `RobotGeometricShapeScene::get_env_obj_idx_from_name`), and the world poses of its named frames.
+#[derive(Clone, Debug)]
+pub struct PlanningScene {
+    robot_geometric_shape_scene: RobotGeometricShapeScene,
+    frames: Vec<(String, OptimaSE3Pose)>
+}
+impl PlanningScene {
+    pub fn frame_pose(&self, frame_name: &str) -> Result<&OptimaSE3Pose, OptimaError> {
+        return match self.frames.iter().find(|(n, _)| n == frame_name) {
+            None => { Err(OptimaError::new_generic_error_str(&format!("Frame {:?} is not in the planning scene.", frame_name), file!(), line!())) }
+            Some((_, pose)) => { Ok(pose) }
+        };
+    }
+    pub fn frame_names(&self) -> Vec<&str> {
+        return self.frames.iter().map(|(n, _)| n.as_str()).collect();
+    }
+    pub fn robot_geometric_shape_scene(&self) -> &RobotGeometricShapeScene {
+        &self.robot_geometric_shape_scene
+    }
+    pub fn robot_geometric_shape_scene_mut(&mut self) -> &mut RobotGeometricShapeScene {
+        &mut self.robot_geometric_shape_scene
+    }
+    pub fn into_robot_geometric_shape_scene(self) -> RobotGeometricShapeScene {
+        self.robot_geometric_shape_scene
+    }
+}
@@ -11,4 +11,7 @@ pub mod utils_generic_data_structures;
 pub mod utils_traits;
 pub mod utils_wasm;
 pub mod utils_algorithms;
-pub mod utils_combinations;
\ No newline at end of file
+pub mod utils_combinations;
+pub mod utils_nearest_neighbor;
+pub mod utils_trajectory;
+pub mod utils_cancellation;
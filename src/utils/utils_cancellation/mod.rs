@@ -0,0 +1,117 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use instant::{Duration, Instant};
+use crate::utils::utils_errors::OptimaError;
+
+/// A cheaply cloneable handle that lets long computations (preprocessing, sampling, path
+/// simplification, batch queries, etc.) be aborted from another thread, e.g., by a GUI or a service
+/// request handler.  All clones share the same cancellation flag, so calling `cancel` on any clone
+/// cancels them all.  A token can also carry a deadline, after which it reports itself as cancelled.
+///
+/// Computations check the token between units of work, so cancellation takes effect at the next
+/// check rather than immediately.
+#[derive(Clone, Debug)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>
+}
+impl CancellationToken {
+    /// A token that is only cancelled by calling `cancel`.
+    pub fn new() -> Self {
+        Self {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            deadline: None
+        }
+    }
+    /// A token that is cancelled once the given time budget (starting now) runs out, or when
+    /// `cancel` is called.
+    pub fn new_with_time_budget(time_budget: Duration) -> Self {
+        return Self::new_with_deadline(Instant::now() + time_budget);
+    }
+    pub fn new_with_deadline(deadline: Instant) -> Self {
+        Self {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            deadline: Some(deadline)
+        }
+    }
+    /// Returns a token that shares this token's cancellation flag, but with an additional time
+    /// budget (starting now).  The earlier of the two deadlines applies.  Useful for giving one stage
+    /// of a computation its own budget while still respecting the overall one.
+    pub fn child_with_time_budget(&self, time_budget: Duration) -> Self {
+        let deadline = Instant::now() + time_budget;
+        let deadline = match self.deadline {
+            None => { deadline }
+            Some(d) => { if d < deadline { d } else { deadline } }
+        };
+        Self {
+            cancelled: self.cancelled.clone(),
+            deadline: Some(deadline)
+        }
+    }
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+    /// Returns true if `cancel` was called on this token (or any of its clones) or its deadline has
+    /// passed.
+    pub fn is_cancelled(&self) -> bool {
+        if self.cancelled.load(Ordering::SeqCst) { return true; }
+        return match self.deadline {
+            None => { false }
+            Some(deadline) => { Instant::now() >= deadline }
+        }
+    }
+    /// Returns the time left before the deadline, if the token has one.
+    pub fn remaining_time(&self) -> Option<Duration> {
+        let now = Instant::now();
+        return self.deadline.map(|deadline| if deadline > now { deadline - now } else { Duration::from_secs(0) });
+    }
+    /// Returns a `Cancelled` error if the token is cancelled.  Intended to be used with `?` between
+    /// units of work.
+    pub fn check(&self, function_name: &str, file: &str, line: u32) -> Result<(), OptimaError> {
+        return if self.is_cancelled() {
+            Err(OptimaError::new_cancelled_error(function_name, file, line))
+        } else {
+            Ok(())
+        }
+    }
+}
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The output of a cancellable computation that can return partial results.  `Cancelled` holds
+/// whatever was completed before the computation noticed that its `CancellationToken` was cancelled.
+#[derive(Clone, Debug)]
+pub enum CancellableOutput<T> {
+    Complete(T),
+    Cancelled(T)
+}
+impl<T> CancellableOutput<T> {
+    pub fn output(&self) -> &T {
+        return match self {
+            CancellableOutput::Complete(output) => { output }
+            CancellableOutput::Cancelled(output) => { output }
+        }
+    }
+    pub fn into_output(self) -> T {
+        return match self {
+            CancellableOutput::Complete(output) => { output }
+            CancellableOutput::Cancelled(output) => { output }
+        }
+    }
+    pub fn was_cancelled(&self) -> bool {
+        return match self {
+            CancellableOutput::Complete(_) => { false }
+            CancellableOutput::Cancelled(_) => { true }
+        }
+    }
+    /// Returns the output if the computation completed, and a `Cancelled` error otherwise.
+    pub fn complete_or_error(self, function_name: &str, file: &str, line: u32) -> Result<T, OptimaError> {
+        return match self {
+            CancellableOutput::Complete(output) => { Ok(output) }
+            CancellableOutput::Cancelled(_) => { Err(OptimaError::new_cancelled_error(function_name, file, line)) }
+        }
+    }
+}
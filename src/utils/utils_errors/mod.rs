@@ -11,7 +11,9 @@ pub enum OptimaError {
     RobotStateVecWrongSizeError(String),
     CannotBeNoneError(String),
     PathDoesNotExist(String),
-    OptimaTensorFunctionInputError(String)
+    OptimaTensorFunctionInputError(String),
+    Cancelled(String),
+    IncompatibleSaveFormatError(String)
 }
 impl OptimaError {
     pub fn new_generic_error_str(s: &str, file: &str, line: u32) -> Self {
@@ -59,6 +61,23 @@ impl OptimaError {
         // optima_print(&s, PrintMode::Println, PrintColor::Red, true);
         return Self::UnsupportedOperationError(s);
     }
+    /// Returned by computations that were aborted through a `CancellationToken`.
+    pub fn new_cancelled_error(function_name: &str, file: &str, line: u32) -> Self {
+        let s = format!("ERROR: Computation in function {} was cancelled. -- File: {}, Line: {}", function_name, file, line);
+        return Self::Cancelled(s);
+    }
+    /// Returned when a saved payload cannot be loaded because of its save format version.  The
+    /// hint should tell the user how to regenerate the payload.
+    pub fn new_incompatible_save_format_error(type_name: &str, found_version: u32, supported_version: u32, hint: &str, file: &str, line: u32) -> Self {
+        let s = format!("ERROR: Could not load saved {} (save format version {}, this version of optima uses version {}).  {}. -- File: {}, Line: {}", type_name, found_version, supported_version, hint, file, line);
+        return Self::IncompatibleSaveFormatError(s);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        return match self {
+            OptimaError::Cancelled(_) => { true }
+            _ => { false }
+        }
+    }
     pub fn new_robot_state_vec_wrong_size_error(function_name: &str, given_robot_state_vec_len: usize, correct_robot_state_vec_len: usize, file: &str, line: u32) -> Self {
         let s = format!("Wrong size of robot state vector in function {}.  It should be length {}, but is currently length {}. -- {}, {}", function_name, correct_robot_state_vec_len, given_robot_state_vec_len, file, line);
         return Self::RobotStateVecWrongSizeError(s);
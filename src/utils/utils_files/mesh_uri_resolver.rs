@@ -0,0 +1,278 @@
+#[cfg(not(target_arch = "wasm32"))]
+use pyo3::*;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use serde::{Serialize, Deserialize};
+use crate::utils::utils_console::{optima_print, PrintColor, PrintMode};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_files::optima_path::{OptimaPath, OptimaPathMatchingPattern, OptimaPathMatchingStopCondition, sha256_hex};
+
+/// Environment variable holding additional mesh search roots (separated like `PATH`), read by
+/// `MeshUriResolver::new`.
+pub const OPTIMA_MESH_PATH_ENV_VAR: &str = "OPTIMA_MESH_PATH";
+/// Environment variable listing ROS package directories, read by `MeshUriResolver::new`.
+pub const ROS_PACKAGE_PATH_ENV_VAR: &str = "ROS_PACKAGE_PATH";
+
+/// A mesh filename as it appears in a URDF.
+/// - `Package`: `package://<package_name>/<relative path>`, relative to the root of a ROS package.
+/// - `File`: `file://<absolute path>`.
+/// - `Relative`: a plain path without a scheme, relative to a search root.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MeshUri {
+    Package { package_name: String, relative_components: Vec<String> },
+    File { path: PathBuf },
+    Relative { components: Vec<String> }
+}
+impl MeshUri {
+    pub fn parse(uri: &str) -> Result<Self, OptimaError> {
+        let uri = uri.trim();
+        if let Some(rest) = uri.strip_prefix("package://") {
+            let components = Self::split_components(rest);
+            if components.len() < 2 {
+                return Err(OptimaError::new_generic_error_str(&format!("Mesh uri {} must name a package and a file within it.", uri), file!(), line!()));
+            }
+            return Ok(Self::Package { package_name: components[0].clone(), relative_components: components[1..].to_vec() });
+        }
+        if let Some(rest) = uri.strip_prefix("file://") {
+            return Ok(Self::File { path: PathBuf::from(rest) });
+        }
+        if uri.contains("://") {
+            return Err(OptimaError::new_generic_error_str(&format!("Mesh uri {} has an unsupported scheme.  Supported schemes are package:// and file://.", uri), file!(), line!()));
+        }
+
+        let components = Self::split_components(uri);
+        if components.is_empty() {
+            return Err(OptimaError::new_generic_error_str("Mesh uri is empty.", file!(), line!()));
+        }
+        return Ok(Self::Relative { components });
+    }
+    /// The path components of the mesh below its package or search root (for `File`, all components
+    /// of the path).
+    pub fn components(&self) -> Vec<String> {
+        return match self {
+            MeshUri::Package { relative_components, .. } => { relative_components.clone() }
+            MeshUri::File { path } => { path.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect() }
+            MeshUri::Relative { components } => { components.clone() }
+        }
+    }
+    fn split_components(s: &str) -> Vec<String> {
+        return s.split('/').filter(|c| !c.is_empty() && *c != ".").map(|c| c.to_string()).collect();
+    }
+}
+
+/// Resolves the mesh filenames found in URDFs (see `MeshUri`) to files on disk, optionally verifying
+/// each file against an expected SHA-256 checksum.  Meshes are looked up, in order:
+/// 1. Under the package roots registered with `add_package_root` (for `package://` uris).
+/// 2. Under each search root, both as `<root>/<package_name>/<path>` and, if the root is itself
+/// the package directory, as `<root>/<path>`.  Relative uris are looked up as `<root>/<path>`.
+/// Search roots are those added with `add_search_root`, followed by the entries of
+/// `OPTIMA_MESH_PATH` and `ROS_PACKAGE_PATH`.
+/// 3. If enabled (the default), by walking the home directory for a directory that ends with the
+/// mesh's parent directories.  This is slow, but finds meshes of robots that were downloaded
+/// anywhere on the computer.
+///
+/// A resolved mesh whose contents do not match its checksum (see `set_checksum`) is an error rather
+/// than a fallback to the next candidate, so a stale or corrupted mesh is never used silently.
+#[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
+#[cfg_attr(target_arch = "wasm32", derive(Clone, Debug, Serialize, Deserialize))]
+pub struct MeshUriResolver {
+    package_roots: Vec<(String, PathBuf)>,
+    search_roots: Vec<PathBuf>,
+    environment_search_roots: Vec<PathBuf>,
+    checksums: HashMap<String, String>,
+    search_home_directory: bool
+}
+impl MeshUriResolver {
+    /// A resolver with the search roots listed in the `OPTIMA_MESH_PATH` and `ROS_PACKAGE_PATH`
+    /// environment variables.
+    pub fn new() -> Self {
+        let mut out_self = Self::new_empty();
+        for env_var in [OPTIMA_MESH_PATH_ENV_VAR, ROS_PACKAGE_PATH_ENV_VAR] {
+            if let Some(paths) = std::env::var_os(env_var) {
+                for path in std::env::split_paths(&paths) {
+                    if !path.as_os_str().is_empty() { out_self.environment_search_roots.push(path); }
+                }
+            }
+        }
+        out_self
+    }
+    /// A resolver without any search roots.
+    pub fn new_empty() -> Self {
+        Self {
+            package_roots: vec![],
+            search_roots: vec![],
+            environment_search_roots: vec![],
+            checksums: HashMap::new(),
+            search_home_directory: true
+        }
+    }
+    /// Resolves `package://<package_name>/...` uris under the given directory.
+    pub fn add_package_root(&mut self, package_name: &str, path: PathBuf) {
+        self.package_roots.push((package_name.to_string(), path));
+    }
+    /// Added search roots are searched before the ones taken from environment variables.
+    pub fn add_search_root(&mut self, path: PathBuf) {
+        self.search_roots.push(path);
+    }
+    /// Sets the expected SHA-256 checksum (hex) of the mesh with the given uri, exactly as the uri
+    /// appears in the URDF.
+    pub fn set_checksum(&mut self, uri: &str, sha256: &str) {
+        self.checksums.insert(uri.trim().to_string(), sha256.trim().to_lowercase());
+    }
+    /// Loads checksums from a JSON file that maps uris to SHA-256 checksums (hex).
+    pub fn load_checksums_from_json_file(&mut self, path: &OptimaPath) -> Result<(), OptimaError> {
+        let checksums: HashMap<String, String> = path.load_object_from_json_file()?;
+        for (uri, sha256) in &checksums { self.set_checksum(uri, sha256); }
+        Ok(())
+    }
+    pub fn set_search_home_directory(&mut self, search_home_directory: bool) {
+        self.search_home_directory = search_home_directory;
+    }
+    pub fn resolve(&self, uri: &str) -> Result<OptimaPath, OptimaError> {
+        let res = self.resolve_all(&vec![Some(uri.to_string())])?;
+        return Ok(res[0].clone().unwrap());
+    }
+    /// Resolves each uri (None entries stay None).  Directories found by walking the home directory
+    /// are reused across uris, so resolving all meshes of a robot at once only walks the home
+    /// directory once per mesh directory.
+    pub fn resolve_all(&self, uris: &Vec<Option<String>>) -> Result<Vec<Option<OptimaPath>>, OptimaError> {
+        let mut out_vec = vec![];
+        let mut home_directory_matches: HashMap<Vec<String>, OptimaPath> = HashMap::new();
+
+        for uri in uris {
+            let uri = match uri {
+                None => { out_vec.push(None); continue; }
+                Some(uri) => { uri }
+            };
+            let mesh_uri = MeshUri::parse(uri)?;
+
+            let mut resolved = self.candidate_paths(&mesh_uri).into_iter().find(|p| p.is_file()).map(|p| OptimaPath::Path(p));
+            if resolved.is_none() && self.search_home_directory {
+                resolved = Self::find_in_home_directory(&mesh_uri, &mut home_directory_matches)?;
+            }
+            let resolved = match resolved {
+                None => { return Err(OptimaError::new_generic_error_str(&format!("Could not resolve mesh uri {}.  Add its package or directory as a mesh root.", uri), file!(), line!())); }
+                Some(resolved) => { resolved }
+            };
+
+            self.verify_checksum(uri, &resolved)?;
+            out_vec.push(Some(resolved));
+        }
+
+        Ok(out_vec)
+    }
+    /// Checks the file against the checksum set for the uri.  Passes if no checksum is set.
+    pub fn verify_checksum(&self, uri: &str, path: &OptimaPath) -> Result<(), OptimaError> {
+        let expected = match self.checksums.get(uri.trim()) {
+            None => { return Ok(()); }
+            Some(expected) => { expected }
+        };
+        let actual = sha256_hex(&path.read_file_contents_to_bytes()?);
+        if &actual != expected {
+            return Err(OptimaError::new_generic_error_str(&format!("Mesh {} resolved from uri {} has checksum {}, but {} was expected.", path.to_string(), uri, actual, expected), file!(), line!()));
+        }
+        Ok(())
+    }
+    pub fn package_roots(&self) -> &Vec<(String, PathBuf)> {
+        &self.package_roots
+    }
+    pub fn search_roots(&self) -> &Vec<PathBuf> {
+        &self.search_roots
+    }
+    pub fn environment_search_roots(&self) -> &Vec<PathBuf> {
+        &self.environment_search_roots
+    }
+    fn candidate_paths(&self, mesh_uri: &MeshUri) -> Vec<PathBuf> {
+        let mut out_vec = vec![];
+        match mesh_uri {
+            MeshUri::Package { package_name, relative_components } => {
+                for (name, root) in &self.package_roots {
+                    if name == package_name { out_vec.push(Self::join(root, relative_components)); }
+                }
+                for root in self.search_roots.iter().chain(self.environment_search_roots.iter()) {
+                    out_vec.push(Self::join(&root.join(package_name), relative_components));
+                    if root.file_name().map(|n| n.to_string_lossy() == package_name.as_str()).unwrap_or(false) {
+                        out_vec.push(Self::join(root, relative_components));
+                    }
+                }
+            }
+            MeshUri::File { path } => {
+                out_vec.push(path.clone());
+            }
+            MeshUri::Relative { components } => {
+                for root in self.search_roots.iter().chain(self.environment_search_roots.iter()) { out_vec.push(Self::join(root, components)); }
+                for (_, root) in &self.package_roots { out_vec.push(Self::join(root, components)); }
+            }
+        }
+        out_vec
+    }
+    fn find_in_home_directory(mesh_uri: &MeshUri, home_directory_matches: &mut HashMap<Vec<String>, OptimaPath>) -> Result<Option<OptimaPath>, OptimaError> {
+        let components = mesh_uri.components();
+        let filename = match components.last() {
+            None => { return Ok(None); }
+            Some(filename) => { filename.clone() }
+        };
+        // The last (up to two) parent directories of the mesh identify its directory.
+        let num_directory_components = (components.len() - 1).min(2);
+        let directory_components = components[components.len() - 1 - num_directory_components..components.len() - 1].to_vec();
+
+        if directory_components.is_empty() {
+            let res = OptimaPath::new_home_path()?.walk_directory_and_match(OptimaPathMatchingPattern::FileOrDirName(filename), OptimaPathMatchingStopCondition::First);
+            return Ok(res.first().cloned());
+        }
+
+        if !home_directory_matches.contains_key(&directory_components) {
+            optima_print(&format!("Searching home directory for mesh directory {:?}.  This may take a while...", directory_components), PrintMode::Println, PrintColor::Cyan, true);
+            let res = OptimaPath::new_home_path()?.walk_directory_and_match(OptimaPathMatchingPattern::PathComponents(directory_components.clone()), OptimaPathMatchingStopCondition::First);
+            match res.first() {
+                None => { return Ok(None); }
+                Some(directory) => { home_directory_matches.insert(directory_components.clone(), directory.clone()); }
+            }
+        }
+
+        let mut out_path = home_directory_matches[&directory_components].clone();
+        out_path.append(&filename);
+        return if out_path.exists() { Ok(Some(out_path)) } else { Ok(None) };
+    }
+    fn join(root: &PathBuf, components: &Vec<String>) -> PathBuf {
+        let mut out_path = root.clone();
+        for c in components { out_path.push(c); }
+        out_path
+    }
+}
+impl Default for MeshUriResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Python implementations.
+#[cfg(not(target_arch = "wasm32"))]
+#[pymethods]
+impl MeshUriResolver {
+    #[new]
+    pub fn new_py() -> Self {
+        Self::new()
+    }
+    pub fn add_package_root_py(&mut self, package_name: &str, path: &str) {
+        self.add_package_root(package_name, PathBuf::from(path));
+    }
+    pub fn add_search_root_py(&mut self, path: &str) {
+        self.add_search_root(PathBuf::from(path));
+    }
+    pub fn set_checksum_py(&mut self, uri: &str, sha256: &str) {
+        self.set_checksum(uri, sha256);
+    }
+    pub fn load_checksums_from_json_file_py(&mut self, path: &str) {
+        self.load_checksums_from_json_file(&OptimaPath::Path(PathBuf::from(path))).expect("error");
+    }
+    pub fn set_search_home_directory_py(&mut self, search_home_directory: bool) {
+        self.set_search_home_directory(search_home_directory);
+    }
+    pub fn resolve_py(&self, uri: &str) -> String {
+        self.resolve(uri).expect("error").to_string()
+    }
+}
+
+crate::impl_py_pickle!(MeshUriResolver);
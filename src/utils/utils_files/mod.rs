@@ -1,2 +1,3 @@
 pub mod optima_path;
-
+pub mod optima_bundle;
+pub mod mesh_uri_resolver;
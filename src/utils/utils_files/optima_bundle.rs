@@ -0,0 +1,305 @@
+#[cfg(not(target_arch = "wasm32"))]
+use pyo3::*;
+
+use std::io::{Cursor, Read, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+use std::sync::Mutex;
+use serde::{Serialize, Deserialize};
+use vfs::{MemoryFS, VfsPath};
+use walkdir::WalkDir;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+use zip::write::FileOptions;
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_files::optima_path::{OptimaAssetLocation, OptimaPath, OptimaStemCellPath, sha256_hex};
+use crate::utils::utils_traits::ToAndFromJsonString;
+
+/// File extension of robot description bundles.
+pub const OPTIMA_BUNDLE_EXTENSION: &str = "optima";
+/// Name of the manifest file at the root of a bundle.
+pub const OPTIMA_BUNDLE_MANIFEST_FILENAME: &str = "manifest.JSON";
+/// Version of the bundle layout written by `OptimaBundle::create_from_robot`.  Bundles with a newer
+/// version are rejected when loaded.
+pub const OPTIMA_BUNDLE_FORMAT_VERSION: u32 = 1;
+
+static OPTIMA_BUNDLE_REGISTRY: Mutex<Option<Vec<LoadedOptimaBundle>>> = Mutex::new(None);
+
+/// A self-contained robot description bundle: a single `.optima` file that holds everything in a
+/// robot's asset directory (URDF, meshes, configurations, and preprocessed data), so a robot can be
+/// distributed as one artifact instead of a directory under `optima_assets/optima_robots`.
+///
+/// A bundle is a zip archive that mirrors the layout of the asset folder, i.e., the robot's files
+/// are stored under `optima_robots/<robot_name>/`, along with a manifest (`manifest.JSON`, see
+/// `OptimaBundleManifest`) at the root of the archive that lists every file and its SHA-256 hash.
+///
+/// `load` verifies a bundle against its manifest and mounts its contents in memory.  Every
+/// `OptimaStemCellPath` asset path searches the mounted bundles before the asset folder, so all
+/// modules (e.g., `RobotModelModule::new`, `RobotConfigurationModule`, mesh loading) load a bundled
+/// robot by name without any other changes.  Bundles are read-only: anything written for a bundled
+/// robot (e.g., newly preprocessed data) goes to the asset folder as usual.
+///
+/// # Example
+/// ```
+/// use optima::utils::utils_files::optima_bundle::OptimaBundle;
+/// use optima::utils::utils_files::optima_path::OptimaPath;
+///
+/// let path = OptimaPath::Path(std::env::temp_dir().join("ur5.optima"));
+/// OptimaBundle::create_from_robot("ur5", &path).expect("error");
+/// OptimaBundle::load(&path).expect("error");
+/// ```
+#[cfg_attr(not(target_arch = "wasm32"), pyclass)]
+pub struct OptimaBundle;
+impl OptimaBundle {
+    /// Packs the asset directory of the given robot into a bundle at destination.  Hidden files are
+    /// skipped.
+    pub fn create_from_robot(robot_name: &str, destination: &OptimaPath) -> Result<OptimaBundleManifest, OptimaError> {
+        let robot_location = OptimaAssetLocation::Robot { robot_name: robot_name.to_string() };
+        let mut path_to_robot = OptimaStemCellPath::new_asset_path()?;
+        path_to_robot.append_file_location(&robot_location);
+        let robot_dir = match path_to_robot.optima_file_paths().iter().find(|p| p.exists()) {
+            None => { return Err(OptimaError::new_generic_error_str(&format!("Robot directory for robot {} does not exist.", robot_name), file!(), line!())); }
+            Some(robot_dir) => { robot_dir.clone() }
+        };
+        let robot_prefix = robot_location.get_path_wrt_asset_folder().join("/");
+
+        let mut files = vec![];
+        let mut contents = vec![];
+        for (relative_components, path) in Self::collect_files(&robot_dir) {
+            let bytes = path.read_file_contents_to_bytes()?;
+            files.push(OptimaBundleFileEntry {
+                path: format!("{}/{}", robot_prefix, relative_components.join("/")),
+                sha256: sha256_hex(&bytes),
+                num_bytes: bytes.len() as u64
+            });
+            contents.push(bytes);
+        }
+
+        let manifest = OptimaBundleManifest {
+            format_version: OPTIMA_BUNDLE_FORMAT_VERSION,
+            robot_name: robot_name.to_string(),
+            files
+        };
+
+        let mut zip = ZipWriter::new(destination.get_file_for_writing()?);
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        Self::write_zip_entry(&mut zip, OPTIMA_BUNDLE_MANIFEST_FILENAME, manifest.to_json_string().as_bytes(), options)?;
+        for (entry, bytes) in manifest.files.iter().zip(contents.iter()) {
+            Self::write_zip_entry(&mut zip, &entry.path, bytes, options)?;
+        }
+        zip.finish().map_err(|e| OptimaError::new_generic_error_str(&format!("Could not finish writing bundle {}: {}", destination.to_string(), e), file!(), line!()))?;
+
+        Ok(manifest)
+    }
+    /// Verifies the bundle at the given path and mounts it, so its robot can be loaded by name.  A
+    /// previously loaded bundle of the same robot is replaced.
+    pub fn load(path: &OptimaPath) -> Result<OptimaBundleManifest, OptimaError> {
+        let bytes = path.read_file_contents_to_bytes()?;
+        return Self::load_from_bytes(&bytes);
+    }
+    /// Same as `load`, with the bundle given as bytes (e.g., fetched over the network in a browser).
+    pub fn load_from_bytes(bytes: &[u8]) -> Result<OptimaBundleManifest, OptimaError> {
+        let (manifest, root) = Self::read_and_verify(bytes)?;
+        Self::with_data(|data| {
+            data.retain(|b| b.manifest.robot_name != manifest.robot_name);
+            data.push(LoadedOptimaBundle { manifest: manifest.clone(), root });
+        });
+        Ok(manifest)
+    }
+    /// Checks that the bundle at the given path is complete and that every file matches the hash in
+    /// its manifest, without mounting it.
+    pub fn verify(path: &OptimaPath) -> Result<OptimaBundleManifest, OptimaError> {
+        let bytes = path.read_file_contents_to_bytes()?;
+        let (manifest, _) = Self::read_and_verify(&bytes)?;
+        Ok(manifest)
+    }
+    /// Unmounts the bundle of the given robot.  Returns false if no such bundle was loaded.
+    pub fn unload(robot_name: &str) -> bool {
+        return Self::with_data(|data| {
+            let num_bundles = data.len();
+            data.retain(|b| b.manifest.robot_name != robot_name);
+            data.len() != num_bundles
+        });
+    }
+    pub fn loaded_bundle_manifests() -> Vec<OptimaBundleManifest> {
+        return Self::with_data(|data| data.iter().map(|b| b.manifest.clone()).collect());
+    }
+    /// The roots of the mounted bundles, each laid out like the asset folder.
+    pub fn loaded_bundle_roots() -> Vec<OptimaPath> {
+        return Self::with_data(|data| data.iter().map(|b| OptimaPath::VfsPath(b.root.clone())).collect());
+    }
+    fn read_and_verify(bytes: &[u8]) -> Result<(OptimaBundleManifest, VfsPath), OptimaError> {
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(|e| OptimaError::new_generic_error_str(&format!("Could not read bundle: {}", e), file!(), line!()))?;
+
+        let manifest_string = String::from_utf8(Self::read_zip_entry(&mut archive, OPTIMA_BUNDLE_MANIFEST_FILENAME)?)
+            .map_err(|_| OptimaError::new_generic_error_str("Bundle manifest is not valid UTF-8.", file!(), line!()))?;
+        let manifest = OptimaBundleManifest::from_json_string(&manifest_string)?;
+        if manifest.format_version > OPTIMA_BUNDLE_FORMAT_VERSION {
+            return Err(OptimaError::new_generic_error_str(&format!("Bundle has format version {}, but the newest supported version is {}.", manifest.format_version, OPTIMA_BUNDLE_FORMAT_VERSION), file!(), line!()));
+        }
+
+        let robot_prefix = OptimaAssetLocation::Robot { robot_name: manifest.robot_name.clone() }.get_path_wrt_asset_folder().join("/") + "/";
+        for name in archive.file_names() {
+            if name != OPTIMA_BUNDLE_MANIFEST_FILENAME && !name.ends_with('/') && !manifest.files.iter().any(|f| f.path == name) {
+                return Err(OptimaError::new_generic_error_str(&format!("Bundle contains file {} that is not listed in its manifest.", name), file!(), line!()));
+            }
+        }
+
+        let root = VfsPath::new(MemoryFS::new());
+        for entry in &manifest.files {
+            if !entry.path.starts_with(&robot_prefix) || entry.path.split('/').any(|c| c == ".." || c.is_empty()) {
+                return Err(OptimaError::new_generic_error_str(&format!("Bundle file {} is not inside the directory of robot {}.", entry.path, manifest.robot_name), file!(), line!()));
+            }
+            let file_bytes = Self::read_zip_entry(&mut archive, &entry.path)?;
+            if file_bytes.len() as u64 != entry.num_bytes || sha256_hex(&file_bytes) != entry.sha256 {
+                return Err(OptimaError::new_generic_error_str(&format!("Bundle file {} does not match the hash in its manifest.", entry.path), file!(), line!()));
+            }
+            Self::write_vfs_file(&root, &entry.path, &file_bytes)?;
+        }
+
+        Ok((manifest, root))
+    }
+    fn collect_files(robot_dir: &OptimaPath) -> Vec<(Vec<String>, OptimaPath)> {
+        let mut out_vec = vec![];
+        match robot_dir {
+            OptimaPath::Path(root) => {
+                for entry in WalkDir::new(root).into_iter().filter_map(|e| e.ok()) {
+                    if !entry.file_type().is_file() { continue; }
+                    if let Ok(relative) = entry.path().strip_prefix(root) {
+                        let components: Vec<String> = relative.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+                        out_vec.push((components, OptimaPath::Path(entry.path().to_path_buf())));
+                    }
+                }
+            }
+            OptimaPath::VfsPath(root) => {
+                if let Ok(it) = root.walk_dir() {
+                    for entry in it.filter_map(|e| e.ok()) {
+                        if !entry.is_file().unwrap_or(false) { continue; }
+                        if let Some(relative) = entry.as_str().strip_prefix(root.as_str()) {
+                            let components: Vec<String> = relative.split('/').filter(|c| !c.is_empty()).map(|c| c.to_string()).collect();
+                            out_vec.push((components, OptimaPath::VfsPath(entry.clone())));
+                        }
+                    }
+                }
+            }
+        }
+
+        out_vec.retain(|(components, _)| !components.iter().any(|c| c.starts_with('.')));
+        out_vec.sort_by(|a, b| a.0.cmp(&b.0));
+        out_vec
+    }
+    fn write_zip_entry<W: Write + std::io::Seek>(zip: &mut ZipWriter<W>, name: &str, bytes: &[u8], options: FileOptions) -> Result<(), OptimaError> {
+        zip.start_file(name, options).map_err(|e| OptimaError::new_generic_error_str(&format!("Could not add {} to bundle: {}", name, e), file!(), line!()))?;
+        zip.write_all(bytes).map_err(|e| OptimaError::new_generic_error_str(&format!("Could not add {} to bundle: {}", name, e), file!(), line!()))?;
+        Ok(())
+    }
+    fn read_zip_entry(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> Result<Vec<u8>, OptimaError> {
+        let mut file = archive.by_name(name).map_err(|e| OptimaError::new_generic_error_str(&format!("Could not read {} from bundle: {}", name, e), file!(), line!()))?;
+        let mut out_vec = vec![];
+        file.read_to_end(&mut out_vec).map_err(|e| OptimaError::new_generic_error_str(&format!("Could not read {} from bundle: {}", name, e), file!(), line!()))?;
+        Ok(out_vec)
+    }
+    fn write_vfs_file(root: &VfsPath, path: &str, bytes: &[u8]) -> Result<(), OptimaError> {
+        let file_path = root.join(path).map_err(|e| OptimaError::new_generic_error_str(&format!("Invalid bundle path {}: {}", path, e), file!(), line!()))?;
+        file_path.parent().map(|p| p.create_dir_all()).transpose().map_err(|e| OptimaError::new_generic_error_str(&format!("Could not mount bundle file {}: {}", path, e), file!(), line!()))?;
+        let mut file = file_path.create_file().map_err(|e| OptimaError::new_generic_error_str(&format!("Could not mount bundle file {}: {}", path, e), file!(), line!()))?;
+        file.write_all(bytes).map_err(|e| OptimaError::new_generic_error_str(&format!("Could not mount bundle file {}: {}", path, e), file!(), line!()))?;
+        Ok(())
+    }
+    fn with_data<R, F: FnOnce(&mut Vec<LoadedOptimaBundle>) -> R>(f: F) -> R {
+        let mut guard = match OPTIMA_BUNDLE_REGISTRY.lock() {
+            Ok(guard) => { guard }
+            Err(poisoned) => { poisoned.into_inner() }
+        };
+        let data = guard.get_or_insert_with(Vec::new);
+        return f(data);
+    }
+}
+
+/// Python implementations.
+#[cfg(not(target_arch = "wasm32"))]
+#[pymethods]
+impl OptimaBundle {
+    #[staticmethod]
+    pub fn create_from_robot_py(robot_name: &str, destination: &str) -> OptimaBundleManifest {
+        let destination = OptimaPath::Path(PathBuf::from(destination));
+        Self::create_from_robot(robot_name, &destination).expect("error")
+    }
+    #[staticmethod]
+    pub fn load_py(path: &str) -> OptimaBundleManifest {
+        let path = OptimaPath::Path(PathBuf::from(path));
+        Self::load(&path).expect("error")
+    }
+    #[staticmethod]
+    pub fn verify_py(path: &str) -> OptimaBundleManifest {
+        let path = OptimaPath::Path(PathBuf::from(path));
+        Self::verify(&path).expect("error")
+    }
+    #[staticmethod]
+    pub fn unload_py(robot_name: &str) -> bool {
+        Self::unload(robot_name)
+    }
+    #[staticmethod]
+    pub fn loaded_bundle_manifests_py() -> Vec<OptimaBundleManifest> {
+        Self::loaded_bundle_manifests()
+    }
+}
+
+/// The manifest of a bundle: the bundled robot and every file in the bundle (paths relative to the
+/// root of the bundle, which is laid out like the asset folder), each with its size and SHA-256
+/// hash (lowercase hex).
+#[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
+#[cfg_attr(target_arch = "wasm32", derive(Clone, Debug, Serialize, Deserialize))]
+pub struct OptimaBundleManifest {
+    format_version: u32,
+    robot_name: String,
+    files: Vec<OptimaBundleFileEntry>
+}
+impl OptimaBundleManifest {
+    pub fn format_version(&self) -> u32 {
+        self.format_version
+    }
+    pub fn robot_name(&self) -> &str {
+        &self.robot_name
+    }
+    pub fn files(&self) -> &Vec<OptimaBundleFileEntry> {
+        &self.files
+    }
+}
+
+/// Python implementations.
+#[cfg(not(target_arch = "wasm32"))]
+#[pymethods]
+impl OptimaBundleManifest {
+    pub fn robot_name_py(&self) -> String {
+        self.robot_name.clone()
+    }
+    pub fn file_paths_py(&self) -> Vec<String> {
+        self.files.iter().map(|f| f.path.clone()).collect()
+    }
+    pub fn format_version_py(&self) -> u32 {
+        self.format_version
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OptimaBundleFileEntry {
+    path: String,
+    sha256: String,
+    num_bytes: u64
+}
+impl OptimaBundleFileEntry {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+    pub fn sha256(&self) -> &str {
+        &self.sha256
+    }
+    pub fn num_bytes(&self) -> u64 {
+        self.num_bytes
+    }
+}
+
+struct LoadedOptimaBundle {
+    manifest: OptimaBundleManifest,
+    root: VfsPath
+}
@@ -4,12 +4,15 @@ use std::{fs};
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Write};
 use std::path::{PathBuf};
+use std::time::UNIX_EPOCH;
 use serde::de::DeserializeOwned;
 use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
 use urdf_rs::Robot;
 use walkdir::WalkDir;
 use crate::utils::utils_console::{optima_print, PrintColor, PrintMode};
 use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_files::optima_bundle::OptimaBundle;
 
 /// An `OptimaStemCellPath` has the same functionality as an `OptimaPath`, but it
 /// will try to automatically select whether it should use a physical or virtual file path based on
@@ -30,8 +33,10 @@ pub struct OptimaStemCellPath {
     optima_file_paths: Vec<OptimaPath>
 }
 impl OptimaStemCellPath {
+    /// Paths into loaded `.optima` bundles (see `OptimaBundle`) come first, so a robot loaded from a
+    /// bundle takes precedence over a robot of the same name in the asset folder.
     pub fn new_asset_path() -> Result<Self, OptimaError> {
-        let mut optima_file_paths = vec![];
+        let mut optima_file_paths = OptimaBundle::loaded_bundle_roots();
 
         if cfg!(target_arch = "wasm32") || cfg!(feature = "only_use_embedded_assets") {
             let p_res = OptimaPath::new_asset_virtual_path();
@@ -59,6 +64,11 @@ impl OptimaStemCellPath {
         for s in components { out_path.append(s); }
         Ok(out_path)
     }
+    pub fn new_from_optima_path(optima_file_path: OptimaPath) -> Self {
+        Self {
+            optima_file_paths: vec![optima_file_path]
+        }
+    }
     pub fn append(&mut self, s: &str) {
         for p in &mut self.optima_file_paths { p.append(s); }
     }
@@ -75,11 +85,19 @@ impl OptimaStemCellPath {
     pub fn read_file_contents_to_string(&self) -> Result<String, OptimaError> {
         self.try_function_on_all_optima_file_paths(OptimaPath::read_file_contents_to_string, "read_file_contents_to_string")
     }
+    pub fn read_file_contents_to_bytes(&self) -> Result<Vec<u8>, OptimaError> {
+        self.try_function_on_all_optima_file_paths(OptimaPath::read_file_contents_to_bytes, "read_file_contents_to_bytes")
+    }
     pub fn write_string_to_file(&self, s: &String) -> Result<(), OptimaError> {
         self.try_function_on_all_optima_file_paths_with_one_param(OptimaPath::write_string_to_file, s, "write_string_to_file")
     }
     pub fn exists(&self) -> bool {
-        return self.optima_file_paths[0].exists();
+        return self.optima_file_paths.iter().any(|p| p.exists());
+    }
+    /// Fingerprint of the first existing file among the stem cell's paths (see
+    /// `OptimaPath::file_fingerprint`), or None if no such file exists.
+    pub fn file_fingerprint(&self) -> Option<String> {
+        return self.optima_file_paths.iter().find_map(|p| p.file_fingerprint());
     }
     pub fn get_file_for_writing(&self) -> Result<File, OptimaError> {
         self.try_function_on_all_optima_file_paths(OptimaPath::get_file_for_writing, "get_file_for_writing")
@@ -116,6 +134,10 @@ impl OptimaStemCellPath {
     pub fn copy_file_to_destination(&self, destination: &OptimaPath) -> Result<(), OptimaError> {
         self.try_function_on_all_optima_file_paths_with_one_param(OptimaPath::copy_file_to_destination, destination, "copy_file_to_destination")
     }
+    /// See `OptimaPath::quarantine_file`.
+    pub fn quarantine_file(&self) -> Result<OptimaPath, OptimaError> {
+        self.try_function_on_all_optima_file_paths(OptimaPath::quarantine_file, "quarantine_file")
+    }
     pub fn verify_extension(&self, extensions: &Vec<&str>) -> Result<(), OptimaError> {
         self.try_function_on_all_optima_file_paths_with_one_param(OptimaPath::verify_extension, extensions, "verify_extension")
     }
@@ -318,6 +340,19 @@ impl OptimaPath {
             }
         }
     }
+    pub fn read_file_contents_to_bytes(&self) -> Result<Vec<u8>, OptimaError> {
+        return match self {
+            OptimaPath::Path(p) => {
+                fs::read(p).map_err(|e| OptimaError::new_generic_error_str(&format!("Could not read bytes from file {:?}: {}", p, e), file!(), line!()))
+            }
+            OptimaPath::VfsPath(p) => {
+                let mut content = vec![];
+                let mut file = p.open_file().map_err(|e| OptimaError::new_generic_error_str(&format!("Could not read file.  Error is {:?}.", e.to_string()), file!(), line!()))?;
+                file.read_to_end(&mut content).map_err(|e| OptimaError::new_generic_error_str(&format!("Could not read file.  Error is {:?}.", e.to_string()), file!(), line!()))?;
+                Ok(content)
+            }
+        }
+    }
     pub fn write_string_to_file(&self, s: &String) -> Result<(), OptimaError> {
         return match self {
             OptimaPath::Path(p) => {
@@ -353,12 +388,46 @@ impl OptimaPath {
             }
         }
     }
+    pub fn write_bytes_to_file(&self, bytes: &[u8]) -> Result<(), OptimaError> {
+        return match self {
+            OptimaPath::Path(p) => {
+                if let Some(parent) = p.parent() { fs::create_dir_all(parent).expect("error"); }
+                fs::write(p, bytes).map_err(|e| OptimaError::new_generic_error_str(&format!("Could not write bytes to file {:?}: {}", p, e), file!(), line!()))
+            }
+            OptimaPath::VfsPath(_) => {
+                Err(OptimaError::new_unsupported_operation_error("write_bytes_to_file()", "Writing is not supported by VfsPath.  \
+                                                                    Try using a Path variant instead.", file!(), line!()))
+            }
+        }
+    }
     pub fn exists(&self) -> bool {
         return match self {
             OptimaPath::Path(p) => { p.exists() }
             OptimaPath::VfsPath(p) => { p.exists().expect("error") }
         }
     }
+    /// Returns a cheap fingerprint of the file made up of its path, size, and (for physical files)
+    /// modification time, without reading the file's contents.  Returns None if the file does not
+    /// exist.  Embedded files cannot change while the program runs, so their fingerprint is just their
+    /// path and size.
+    pub fn file_fingerprint(&self) -> Option<String> {
+        return match self {
+            OptimaPath::Path(p) => {
+                let metadata = fs::metadata(p).ok()?;
+                if !metadata.is_file() { return None; }
+                let modified = metadata.modified().ok()
+                    .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_nanos())
+                    .unwrap_or(0);
+                Some(format!("{}:{}:{}", p.to_string_lossy(), metadata.len(), modified))
+            }
+            OptimaPath::VfsPath(p) => {
+                let metadata = p.metadata().ok()?;
+                if metadata.file_type != VfsFileType::File { return None; }
+                Some(format!("{}:{}", p.as_str(), metadata.len))
+            }
+        }
+    }
     #[allow(unused_must_use)]
     pub fn get_file_for_writing(&self) -> Result<File, OptimaError> {
         return match self {
@@ -654,6 +723,29 @@ impl OptimaPath {
             }
         }
     }
+    /// Moves a file that could not be loaded out of the way by appending `.corrupted` and a
+    /// timestamp to its name, so that it can be regenerated while the bad copy is kept around for
+    /// inspection.  Returns the quarantined path.
+    pub fn quarantine_file(&self) -> Result<OptimaPath, OptimaError> {
+        if !self.exists() {
+            return Err(OptimaError::new_generic_error_str(&format!("Tried to quarantine file {:?} but it does not exist!", self), file!(), line!()));
+        }
+        return match self {
+            OptimaPath::Path(p) => {
+                let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+                let filename = self.filename().unwrap_or_default();
+                let mut quarantined = p.clone();
+                quarantined.set_file_name(format!("{}.corrupted_{}", filename, timestamp));
+                if let Err(e) = fs::rename(p, &quarantined) {
+                    return Err(OptimaError::new_generic_error_str(&format!("Could not quarantine file {:?}: {}", p, e), file!(), line!()));
+                }
+                Ok(OptimaPath::Path(quarantined))
+            }
+            OptimaPath::VfsPath(_) => {
+                Err(OptimaError::new_unsupported_operation_error("quarantine_file", "VfsPath does not support moving files.", file!(), line!()))
+            }
+        }
+    }
     pub fn verify_extension(&self, extensions: &Vec<&str>) -> Result<(), OptimaError> {
         let ext_option = self.extension();
         match ext_option {
@@ -873,6 +965,12 @@ pub fn load_object_from_json_string<T: DeserializeOwned>(json_str: &str) -> Resu
     }
 }
 
+/// Lowercase hex SHA-256 digest of the given bytes (e.g., file contents read with
+/// `OptimaPath::read_file_contents_to_bytes`).
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    return format!("{:x}", Sha256::digest(bytes));
+}
+
 #[derive(RustEmbed, Debug)]
 #[folder = "../optima_assets"]
 #[exclude = "*/.DS_Store"]
@@ -895,6 +993,7 @@ pub enum OptimaAssetLocation {
     Robot { robot_name: String },
     RobotConfigurations { robot_name: String },
     RobotInputMeshes { robot_name: String },
+    RobotImportOptions { robot_name: String },
     RobotMeshes { robot_name: String  },
     RobotGLBMeshes { robot_name: String  },
     RobotPreprocessedData { robot_name: String },
@@ -908,6 +1007,9 @@ pub enum OptimaAssetLocation {
     SceneMeshFilePreprocessing { name: String },
     SceneMeshFileConvexShape { name: String },
     SceneMeshFileConvexShapeSubcomponents { name: String },
+    SceneDescriptions,
+    ConvexShapeCache,
+    ConvexShapeCacheEntry { mesh_hash: String },
     FileIO
 }
 impl OptimaAssetLocation {
@@ -939,6 +1041,11 @@ impl OptimaAssetLocation {
                 v.push("input_meshes".to_string());
                 v
             }
+            OptimaAssetLocation::RobotImportOptions { robot_name } => {
+                let mut v = Self::Robot { robot_name: robot_name.clone() }.get_path_wrt_asset_folder();
+                v.push("import_options.json".to_string());
+                v
+            }
             OptimaAssetLocation::RobotMeshes { robot_name } => {
                 let mut v = Self::Robot { robot_name: robot_name.clone() }.get_path_wrt_asset_folder();
                 v.push("meshes".to_string());
@@ -1002,6 +1109,19 @@ impl OptimaAssetLocation {
                 v.push("convex_shape_subcomponents".to_string());
                 v
             }
+            OptimaAssetLocation::SceneDescriptions => {
+                let mut v = Self::Scenes.get_path_wrt_asset_folder();
+                v.push("scene_descriptions".to_string());
+                v
+            }
+            OptimaAssetLocation::ConvexShapeCache => {
+                vec!["convex_shape_cache".to_string()]
+            }
+            OptimaAssetLocation::ConvexShapeCacheEntry { mesh_hash } => {
+                let mut v = Self::ConvexShapeCache.get_path_wrt_asset_folder();
+                v.push(mesh_hash.clone());
+                v
+            }
             OptimaAssetLocation::FileIO => {
                 vec!["fileIO".to_string()]
             }
@@ -1041,7 +1161,8 @@ pub enum OptimaPathMatchingStopCondition {
 pub enum RobotModuleJsonType {
     ModelModule,
     ShapeGeometryModule,
-    ShapeGeometryModulePermanent
+    ShapeGeometryModulePermanent,
+    ShapeGeometryPreprocessingReport
 }
 impl RobotModuleJsonType {
     pub fn filename(&self) -> &str {
@@ -1049,6 +1170,7 @@ impl RobotModuleJsonType {
             RobotModuleJsonType::ModelModule => { "robot_model_module.JSON" }
             RobotModuleJsonType::ShapeGeometryModule => { "robot_shape_geometry_module.JSON" }
             RobotModuleJsonType::ShapeGeometryModulePermanent => { "robot_shape_geometry_module_permanent.JSON" }
+            RobotModuleJsonType::ShapeGeometryPreprocessingReport => { "robot_shape_geometry_preprocessing_report.JSON" }
         }
     }
 }
\ No newline at end of file
@@ -1,11 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::ops::Deref;
+use std::sync::Arc;
 use serde_with::{serde_as};
 use serde::de::DeserializeOwned;
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Deserialize, Serializer, Deserializer};
 use crate::utils::utils_errors::OptimaError;
-use crate::utils::utils_files::optima_path::load_object_from_json_string;
 use crate::utils::utils_se3::optima_se3_pose::OptimaSE3Pose;
 use crate::utils::utils_traits::SaveAndLoadable;
 
@@ -83,13 +84,41 @@ impl <T> Array1D <T> where T: Clone + Debug + Serialize + DeserializeOwned + Def
     }
 }
 
+/// How a `SquareArray2D` stores its cells.  All storage types have the same API and behavior.
+/// - `Dense`: all side_length x side_length cells are stored.
+/// - `SymmetricPacked`: only the lower triangle (including the diagonal) is stored, roughly halving
+/// memory.  Only valid for symmetric arrays.
+/// - `Sparse`: only cells that have been written are stored; all other cells hold a shared default
+/// value.  Best when most cells hold the same value (e.g., the skip matrix of a large shape
+/// collection).  For symmetric arrays, only the lower triangle is stored.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SquareArray2DStorageType {
+    Dense,
+    SymmetricPacked,
+    Sparse
+}
+impl Default for SquareArray2DStorageType {
+    fn default() -> Self {
+        Self::Dense
+    }
+}
+
 #[serde_as]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SquareArray2D<T> where T: Clone + Debug + Serialize + DeserializeOwned + Default + Mixable {
+    /// For `Dense` storage, row i holds all columns.  For `SymmetricPacked` storage, row i holds
+    /// columns 0..=i.  Empty for `Sparse` storage.
     #[serde_as(as = "Vec<Vec<_>>")]
     array: Vec<Vec<T>>,
     side_length: usize,
-    symmetric: bool
+    symmetric: bool,
+    #[serde(default)]
+    storage_type: SquareArray2DStorageType,
+    /// Ordered so that serialized arrays are identical across runs.
+    #[serde(default)]
+    sparse_cells: BTreeMap<usize, BTreeMap<usize, T>>,
+    #[serde(default)]
+    sparse_default_value: T
 }
 impl <T> SquareArray2D <T> where T: Clone + Debug + Serialize + DeserializeOwned + Default + Mixable {
     pub fn new(side_length: usize, symmetric: bool, initialization_value: Option<T>) -> Self {
@@ -98,6 +127,45 @@ impl <T> SquareArray2D <T> where T: Clone + Debug + Serialize + DeserializeOwned
             Some(initialization_value) => { Self::new_with_initialization_value(side_length, symmetric, initialization_value) }
         }
     }
+    /// Same as `new`, but with the given storage type.  For `Sparse` storage, every cell initially
+    /// holds the (implicit) initialization value.
+    pub fn new_with_storage_type(side_length: usize, symmetric: bool, initialization_value: Option<T>, storage_type: SquareArray2DStorageType) -> Result<Self, OptimaError> {
+        let initialization_value = match initialization_value {
+            None => { T::default() }
+            Some(initialization_value) => { initialization_value }
+        };
+
+        return match storage_type {
+            SquareArray2DStorageType::Dense => { Ok(Self::new_with_initialization_value(side_length, symmetric, initialization_value)) }
+            SquareArray2DStorageType::SymmetricPacked => {
+                if !symmetric {
+                    return Err(OptimaError::new_generic_error_str("SymmetricPacked storage can only be used for symmetric SquareArray2Ds.", file!(), line!()));
+                }
+                let mut array = vec![];
+                for i in 0..side_length {
+                    array.push(vec![initialization_value.clone(); i + 1]);
+                }
+                Ok(Self {
+                    array,
+                    side_length,
+                    symmetric,
+                    storage_type,
+                    sparse_cells: BTreeMap::new(),
+                    sparse_default_value: T::default()
+                })
+            }
+            SquareArray2DStorageType::Sparse => {
+                Ok(Self {
+                    array: vec![],
+                    side_length,
+                    symmetric,
+                    storage_type,
+                    sparse_cells: BTreeMap::new(),
+                    sparse_default_value: initialization_value
+                })
+            }
+        }
+    }
     /// Concatenation places the second matrix in the lower right corner such that an m x m matrix
     /// concatenated with an n x n matrix will become an m + n x m + n matrix.  Off diagonal terms
     /// will be T::default
@@ -109,13 +177,13 @@ impl <T> SquareArray2D <T> where T: Clone + Debug + Serialize + DeserializeOwned
 
         for i in 0..l1 {
             for j in 0..l1 {
-                out_self.array[i][j] = s1.array[i][j].clone();
+                out_self.array[i][j] = s1.cell(i, j).clone();
             }
         }
 
         for i in 0..l2 {
             for j in 0..l2 {
-                out_self.array[i + l1][j + l1] = s2.array[i][j].clone();
+                out_self.array[i + l1][j + l1] = s2.cell(i, j).clone();
             }
         }
 
@@ -138,14 +206,19 @@ impl <T> SquareArray2D <T> where T: Clone + Debug + Serialize + DeserializeOwned
         Self {
             array,
             side_length,
-            symmetric
+            symmetric,
+            storage_type: SquareArray2DStorageType::Dense,
+            sparse_cells: BTreeMap::new(),
+            sparse_default_value: T::default()
         }
     }
     pub fn replace_data(&mut self, data: T, row_idx: usize, col_idx: usize) -> Result<(), OptimaError> {
         OptimaError::new_check_for_idx_out_of_bound_error(row_idx, self.side_length, file!(), line!())?;
         OptimaError::new_check_for_idx_out_of_bound_error(col_idx, self.side_length, file!(), line!())?;
 
-        if self.symmetric && row_idx != col_idx {
+        if self.storage_type != SquareArray2DStorageType::Dense {
+            *self.cell_mut(row_idx, col_idx) = data;
+        } else if self.symmetric && row_idx != col_idx {
             self.array[row_idx][col_idx] = data.clone();
             self.array[col_idx][row_idx] = data;
         } else {
@@ -156,10 +229,15 @@ impl <T> SquareArray2D <T> where T: Clone + Debug + Serialize + DeserializeOwned
         Ok(())
     }
     pub fn replace_data_on_every_cell(&mut self, data: T) {
-        let l = self.side_length;
-        for row in 0..l {
-            for col in 0..l {
-                self.array[col][row] = data.clone();
+        if self.storage_type == SquareArray2DStorageType::Sparse {
+            self.sparse_cells.clear();
+            self.sparse_default_value = data;
+            return;
+        }
+
+        for row in &mut self.array {
+            for cell in row {
+                *cell = data.clone();
             }
         }
     }
@@ -167,6 +245,11 @@ impl <T> SquareArray2D <T> where T: Clone + Debug + Serialize + DeserializeOwned
         OptimaError::new_check_for_idx_out_of_bound_error(row_idx, self.side_length, file!(), line!())?;
         OptimaError::new_check_for_idx_out_of_bound_error(col_idx, self.side_length, file!(), line!())?;
 
+        if self.storage_type != SquareArray2DStorageType::Dense {
+            adjustment(self.cell_mut(row_idx, col_idx));
+            return Ok(());
+        }
+
         let data = &mut self.array[row_idx][col_idx];
         adjustment(data);
 
@@ -178,43 +261,125 @@ impl <T> SquareArray2D <T> where T: Clone + Debug + Serialize + DeserializeOwned
         Ok(())
     }
     pub fn adjust_data_on_every_cell<F: Fn(&mut T)>(&mut self, adjustment: F) {
-        let l = self.side_length;
-        for row in 0..l {
-            for col in 0..l {
-                let data = &mut self.array[row][col];
-                adjustment(data);
+        if self.storage_type == SquareArray2DStorageType::Sparse {
+            adjustment(&mut self.sparse_default_value);
+            for row in self.sparse_cells.values_mut() {
+                for cell in row.values_mut() { adjustment(cell); }
+            }
+            return;
+        }
+
+        for row in &mut self.array {
+            for cell in row {
+                adjustment(cell);
             }
         }
     }
+    /// Appends a row and column filled with data (or T::default() if data is None).  For `Sparse`
+    /// storage, the new cells are stored explicitly; use `prune_sparse_cells` to drop the ones that
+    /// equal the sparse default value.
     pub fn append_new_row_and_column(&mut self, data: Option<T>) {
-        for col in &mut self.array {
-            match &data {
-                None => { col.push( T::default() ) }
-                Some(data) => { col.push(data.clone()) }
+        match self.storage_type {
+            SquareArray2DStorageType::Dense => {
+                for col in &mut self.array {
+                    match &data {
+                        None => { col.push( T::default() ) }
+                        Some(data) => { col.push(data.clone()) }
+                    }
+                }
+
+                self.side_length += 1;
+
+                let mut new_row = vec![];
+                for _ in 0..self.side_length {
+                    match &data {
+                        None => { new_row.push( T::default() ) }
+                        Some(data) => { new_row.push(data.clone()) }
+                    }
+                }
+                self.array.push(new_row);
+            }
+            SquareArray2DStorageType::SymmetricPacked => {
+                self.side_length += 1;
+                let value = match data { None => { T::default() } Some(data) => { data } };
+                self.array.push(vec![value; self.side_length]);
+            }
+            SquareArray2DStorageType::Sparse => {
+                let new_idx = self.side_length;
+                self.side_length += 1;
+                let value = match data { None => { T::default() } Some(data) => { data } };
+                for i in 0..self.side_length {
+                    *self.cell_mut(new_idx, i) = value.clone();
+                    if !self.symmetric { *self.cell_mut(i, new_idx) = value.clone(); }
+                }
             }
         }
+    }
+    /// Removes the row and column at the given index.  All rows and columns after idx shift down by
+    /// one.
+    pub fn remove_row_and_column(&mut self, idx: usize) -> Result<(), OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(idx, self.side_length, file!(), line!())?;
 
-        self.side_length += 1;
-
-        let mut new_row = vec![];
-        for _ in 0..self.side_length {
-            match &data {
-                None => { new_row.push( T::default() ) }
-                Some(data) => { new_row.push(data.clone()) }
+        match self.storage_type {
+            SquareArray2DStorageType::Dense => {
+                self.array.remove(idx);
+                for row in &mut self.array {
+                    row.remove(idx);
+                }
+            }
+            SquareArray2DStorageType::SymmetricPacked => {
+                self.array.remove(idx);
+                for row in self.array.iter_mut().skip(idx) {
+                    row.remove(idx);
+                }
+            }
+            SquareArray2DStorageType::Sparse => {
+                let shift = |i: usize| if i > idx { i - 1 } else { i };
+                let mut sparse_cells: BTreeMap<usize, BTreeMap<usize, T>> = BTreeMap::new();
+                for (row_idx, row) in std::mem::take(&mut self.sparse_cells) {
+                    if row_idx == idx { continue; }
+                    let mut new_row = BTreeMap::new();
+                    for (col_idx, cell) in row {
+                        if col_idx != idx { new_row.insert(shift(col_idx), cell); }
+                    }
+                    if !new_row.is_empty() { sparse_cells.insert(shift(row_idx), new_row); }
+                }
+                self.sparse_cells = sparse_cells;
             }
         }
-        self.array.push(new_row);
+
+        self.side_length -= 1;
+
+        Ok(())
     }
     pub fn mix(&mut self, other: &Self) -> Result<(), OptimaError> {
         if self.side_length != other.side_length {
             return Err(OptimaError::new_generic_error_str("Cannot combine SquareArray2Ds of different sizes.", file!(), line!()));
         }
 
+        if self.storage_type == SquareArray2DStorageType::Sparse && other.storage_type == SquareArray2DStorageType::Sparse && self.symmetric == other.symmetric {
+            // Cells that are implicit in both arrays stay implicit.
+            let mut idxs = vec![];
+            for (row_idx, row) in self.sparse_cells.iter().chain(other.sparse_cells.iter()) {
+                for col_idx in row.keys() { idxs.push((*row_idx, *col_idx)); }
+            }
+            idxs.sort();
+            idxs.dedup();
+            for (row_idx, col_idx) in idxs {
+                let mixed = self.cell(row_idx, col_idx).mix(other.cell(row_idx, col_idx));
+                *self.cell_mut(row_idx, col_idx) = mixed;
+            }
+            self.sparse_default_value = self.sparse_default_value.mix(&other.sparse_default_value);
+            return Ok(());
+        }
+
         let side_length = self.side_length;
 
         for col in 0..side_length {
             for row in 0..side_length {
-                self.array[col][row] = self.array[col][row].mix(&other.array[col][row]);
+                if self.storage_type != SquareArray2DStorageType::Dense && self.symmetric && row > col { continue; }
+                let mixed = self.cell(col, row).mix(other.cell(col, row));
+                *self.cell_mut(col, row) = mixed;
             }
         }
 
@@ -229,7 +394,8 @@ impl <T> SquareArray2D <T> where T: Clone + Debug + Serialize + DeserializeOwned
         for _ in 0..l2 { self.append_new_row_and_column(off_diagonal_value.clone()); }
         for i in 0..l2 {
             for j in 0..l2 {
-                self.array[i + l1][j + l1] = other.array[i][j].clone();
+                if self.storage_type != SquareArray2DStorageType::Dense && self.symmetric && j > i { continue; }
+                *self.cell_mut(i + l1, j + l1) = other.cell(i, j).clone();
             }
         }
     }
@@ -237,7 +403,7 @@ impl <T> SquareArray2D <T> where T: Clone + Debug + Serialize + DeserializeOwned
         OptimaError::new_check_for_idx_out_of_bound_error(row_idx, self.side_length, file!(), line!())?;
         OptimaError::new_check_for_idx_out_of_bound_error(col_idx, self.side_length, file!(), line!())?;
 
-        Ok(&self.array[row_idx][col_idx])
+        Ok(self.cell(row_idx, col_idx))
     }
     pub fn data_cell_mut(&mut self, row_idx: usize, col_idx: usize) -> Result<&mut T, OptimaError> {
         assert!(!self.symmetric, "cannot get mutable data cell on symmetric grid.  To change cells in this situation, you must use the `adjust_data` function");
@@ -245,19 +411,131 @@ impl <T> SquareArray2D <T> where T: Clone + Debug + Serialize + DeserializeOwned
         OptimaError::new_check_for_idx_out_of_bound_error(row_idx, self.side_length, file!(), line!())?;
         OptimaError::new_check_for_idx_out_of_bound_error(col_idx, self.side_length, file!(), line!())?;
 
-        Ok(&mut self.array[row_idx][col_idx])
+        Ok(self.cell_mut(row_idx, col_idx))
     }
     pub fn side_length(&self) -> usize {
         self.side_length
     }
+    pub fn symmetric(&self) -> bool {
+        self.symmetric
+    }
+    pub fn storage_type(&self) -> &SquareArray2DStorageType {
+        &self.storage_type
+    }
+    /// The number of cells that are explicitly stored.
+    pub fn num_stored_cells(&self) -> usize {
+        return match self.storage_type {
+            SquareArray2DStorageType::Sparse => { self.sparse_cells.values().map(|r| r.len()).sum() }
+            _ => { self.array.iter().map(|r| r.len()).sum() }
+        }
+    }
     pub fn convert_to_memory_cells(&self) -> SquareArray2D<MemoryCell<T>> {
-        let mut out_self = SquareArray2D::new(self.side_length, self.symmetric, None);
+        let mut out_self = SquareArray2D::new_with_storage_type(self.side_length, self.symmetric, Some(MemoryCell::new(self.sparse_default_value.clone())), self.storage_type.clone()).expect("error");
+        self.for_each_stored_cell(|i, j, cell| {
+            out_self.replace_data(MemoryCell::new(cell.clone()), i, j).expect("error");
+        });
+        return out_self;
+    }
+    /// Returns the cell at the given indices without bounds checking.
+    fn cell(&self, row_idx: usize, col_idx: usize) -> &T {
+        let (row_idx, col_idx) = self.stored_idxs(row_idx, col_idx);
+        return match self.storage_type {
+            SquareArray2DStorageType::Sparse => {
+                match self.sparse_cells.get(&row_idx).and_then(|r| r.get(&col_idx)) {
+                    None => { &self.sparse_default_value }
+                    Some(cell) => { cell }
+                }
+            }
+            _ => { &self.array[row_idx][col_idx] }
+        }
+    }
+    /// Returns the stored cell at the given indices without bounds checking, creating it first for
+    /// `Sparse` storage.  For symmetric `Dense` storage, only one of the two mirrored cells is
+    /// returned.
+    fn cell_mut(&mut self, row_idx: usize, col_idx: usize) -> &mut T {
+        let (row_idx, col_idx) = self.stored_idxs(row_idx, col_idx);
+        return match self.storage_type {
+            SquareArray2DStorageType::Sparse => {
+                let default_value = &self.sparse_default_value;
+                self.sparse_cells.entry(row_idx).or_insert_with(BTreeMap::new).entry(col_idx).or_insert_with(|| default_value.clone())
+            }
+            _ => { &mut self.array[row_idx][col_idx] }
+        }
+    }
+    /// Symmetric packed and sparse arrays only store the lower triangle.
+    fn stored_idxs(&self, row_idx: usize, col_idx: usize) -> (usize, usize) {
+        if self.storage_type != SquareArray2DStorageType::Dense && self.symmetric && col_idx > row_idx {
+            return (col_idx, row_idx);
+        }
+        return (row_idx, col_idx);
+    }
+    /// Calls f on every cell that is explicitly stored (once per mirrored pair for symmetric packed
+    /// and sparse arrays).
+    fn for_each_stored_cell<F: FnMut(usize, usize, &T)>(&self, mut f: F) {
+        match self.storage_type {
+            SquareArray2DStorageType::Sparse => {
+                for (row_idx, row) in &self.sparse_cells {
+                    for (col_idx, cell) in row { f(*row_idx, *col_idx, cell); }
+                }
+            }
+            _ => {
+                for (row_idx, row) in self.array.iter().enumerate() {
+                    for (col_idx, cell) in row.iter().enumerate() { f(row_idx, col_idx, cell); }
+                }
+            }
+        }
+    }
+}
+impl <T> SquareArray2D<T> where T: Clone + Debug + Serialize + DeserializeOwned + Default + Mixable + PartialEq {
+    /// Converts the array to the given storage type in place.  For `Sparse` storage, cells equal to
+    /// the sparse default value are not stored; if sparse_default_value is None, the most common
+    /// cell value is used.
+    pub fn convert_storage_type(&mut self, storage_type: SquareArray2DStorageType, sparse_default_value: Option<T>) -> Result<(), OptimaError> {
+        let default_value = match (&storage_type, sparse_default_value) {
+            (SquareArray2DStorageType::Sparse, None) => { self.most_common_value() }
+            (_, Some(sparse_default_value)) => { sparse_default_value }
+            (_, None) => { T::default() }
+        };
+
+        let mut out_self = Self::new_with_storage_type(self.side_length, self.symmetric, Some(default_value.clone()), storage_type.clone())?;
         for i in 0..self.side_length {
             for j in 0..self.side_length {
-                out_self.replace_data(MemoryCell::new(self.array[i][j].clone()), i, j).expect("error");
+                if out_self.storage_type != SquareArray2DStorageType::Dense && self.symmetric && j > i { continue; }
+                let cell = self.cell(i, j);
+                if storage_type == SquareArray2DStorageType::Sparse && cell == &default_value { continue; }
+                *out_self.cell_mut(i, j) = cell.clone();
             }
         }
-        return out_self;
+
+        *self = out_self;
+        Ok(())
+    }
+    /// Drops explicitly stored cells of a `Sparse` array that equal the sparse default value.
+    pub fn prune_sparse_cells(&mut self) {
+        let default_value = &self.sparse_default_value;
+        for row in self.sparse_cells.values_mut() {
+            row.retain(|_, cell| cell != default_value);
+        }
+        self.sparse_cells.retain(|_, row| !row.is_empty());
+    }
+    fn most_common_value(&self) -> T {
+        // Only a bounded number of distinct values are tracked so that arrays with mostly distinct
+        // values (e.g., average distances) stay linear in the number of cells.
+        let mut counts: Vec<(T, usize)> = vec![];
+        self.for_each_stored_cell(|_, _, cell| {
+            match counts.iter_mut().find(|(v, _)| v == cell) {
+                Some((_, count)) => { *count += 1; }
+                None => { if counts.len() < 16 { counts.push((cell.clone(), 1)); } }
+            }
+        });
+        if self.storage_type == SquareArray2DStorageType::Sparse {
+            counts.push((self.sparse_default_value.clone(), usize::MAX));
+        }
+
+        return match counts.iter().max_by_key(|(_, count)| *count) {
+            None => { T::default() }
+            Some((v, _)) => { v.clone() }
+        }
     }
 }
 impl <T> SaveAndLoadable for SquareArray2D<T> where T: Clone + Debug + Serialize + DeserializeOwned + Default + Mixable {
@@ -268,29 +546,25 @@ impl <T> SaveAndLoadable for SquareArray2D<T> where T: Clone + Debug + Serialize
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let load: Self::SaveType = load_object_from_json_string(json_str)?;
+        let load: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
         return Ok(load);
     }
 }
 
 impl <T> SquareArray2D<MemoryCell<T>> where T: Clone + Debug + Serialize + DeserializeOwned + Default + Mixable {
     pub fn convert_to_standard_cells(&self) -> SquareArray2D<T> {
-        let mut out = SquareArray2D::new(self.side_length, self.symmetric, None);
+        let mut out = SquareArray2D::new_with_storage_type(self.side_length, self.symmetric, Some(self.sparse_default_value.curr_value.clone()), self.storage_type.clone()).expect("error");
 
-        let l = self.side_length;
-        for i in 0..l {
-            for j in 0..l {
-                let val = self.array[i][j].curr_value.clone();
-                out.replace_data(val, i, j).expect("error");
-            }
-        }
+        self.for_each_stored_cell(|i, j, cell| {
+            out.replace_data(cell.curr_value.clone(), i, j).expect("error");
+        });
 
         out
     }
 }
 
 #[serde_as]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct MemoryCell <T> where T: Clone + Debug + Serialize + DeserializeOwned + Default + Mixable {
     #[serde_as(as = "_")]
     curr_value: T,
@@ -431,6 +705,8 @@ impl AveragingFloat {
         self.value = self.total_sum / self.counter;
     }
     pub fn value(&self) -> f64 { self.value }
+    /// The number of values added so far.
+    pub fn count(&self) -> f64 { self.counter }
 }
 impl Default for AveragingFloat {
     fn default() -> Self {
@@ -797,3 +1073,80 @@ impl<T: Hash + Debug> HashWrapper for T {
     }
 }
 */
+
+/// A cheaply cloneable, read-only handle to a shared object.  Cloning a `SharedHandle` only clones an
+/// `Arc`, so large read-only objects (e.g., the modules held by `RobotModuleCache`, the robot model
+/// inside a `RobotConfigurationModule`, or the shape list inside a `ShapeCollection`) can be shared
+/// without deep copies.  The handle is `Send` and `Sync` whenever the inner object is.  If a thread needs to modify its copy, `make_mut` clones the
+/// inner object only if it is still shared (copy-on-write).
+#[derive(Debug)]
+pub struct SharedHandle<T> {
+    inner: Arc<T>
+}
+impl <T> SharedHandle<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner: Arc::new(inner)
+        }
+    }
+    pub fn new_from_arc(inner: Arc<T>) -> Self {
+        Self {
+            inner
+        }
+    }
+    pub fn arc(&self) -> &Arc<T> {
+        &self.inner
+    }
+    /// Returns the number of handles currently pointing to the shared object.
+    pub fn num_handles(&self) -> usize {
+        Arc::strong_count(&self.inner)
+    }
+    /// Returns true if both handles point to the same shared object.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+impl <T> SharedHandle<T> where T: Clone {
+    /// Returns a mutable reference to the inner object, cloning it first if other handles to it exist.
+    pub fn make_mut(&mut self) -> &mut T {
+        Arc::make_mut(&mut self.inner)
+    }
+    /// Returns a deep copy of the inner object.
+    pub fn to_owned_inner(&self) -> T {
+        self.inner.as_ref().clone()
+    }
+}
+impl <T> Clone for SharedHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone()
+        }
+    }
+}
+impl <T> Deref for SharedHandle<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+impl <T> AsRef<T> for SharedHandle<T> {
+    fn as_ref(&self) -> &T {
+        &self.inner
+    }
+}
+impl <T> From<T> for SharedHandle<T> {
+    fn from(inner: T) -> Self {
+        Self::new(inner)
+    }
+}
+impl <T> Serialize for SharedHandle<T> where T: Serialize {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        self.inner.as_ref().serialize(serializer)
+    }
+}
+impl <'de, T> Deserialize<'de> for SharedHandle<T> where T: Deserialize<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        Ok(Self::new(T::deserialize(deserializer)?))
+    }
+}
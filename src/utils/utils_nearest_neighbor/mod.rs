@@ -0,0 +1,579 @@
+use std::fmt::Debug;
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+use crate::utils::utils_errors::OptimaError;
+
+/// Distance metric over joint states used by nearest neighbor structures.
+/// - `Euclidean`: standard L2 distance.
+/// - `WeightedEuclidean`: sqrt(sum_i w_i * (a_i - b_i)^2).  Weights must be non-negative and have the
+/// same length as the joint states.
+/// - `LInfinity`: the maximum absolute difference over all axes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum JointStateDistanceMetric {
+    Euclidean,
+    WeightedEuclidean { weights: Vec<f64> },
+    LInfinity
+}
+impl JointStateDistanceMetric {
+    pub fn distance(&self, a: &DVector<f64>, b: &DVector<f64>) -> f64 {
+        return match self {
+            JointStateDistanceMetric::Euclidean => { (a - b).norm() }
+            JointStateDistanceMetric::WeightedEuclidean { weights } => {
+                let mut sum = 0.0;
+                for i in 0..a.len() {
+                    let d = a[i] - b[i];
+                    sum += weights[i] * d * d;
+                }
+                sum.sqrt()
+            }
+            JointStateDistanceMetric::LInfinity => { (a - b).amax() }
+        }
+    }
+    /// A lower bound on the distance between two states that differ by `difference` along the given
+    /// axis.  Used by spatial partitioning structures to prune subtrees.
+    pub fn axis_distance_lower_bound(&self, axis: usize, difference: f64) -> f64 {
+        return match self {
+            JointStateDistanceMetric::Euclidean => { difference.abs() }
+            JointStateDistanceMetric::WeightedEuclidean { weights } => { weights[axis].sqrt() * difference.abs() }
+            JointStateDistanceMetric::LInfinity => { difference.abs() }
+        }
+    }
+    fn check_dimension(&self, dim: usize) -> Result<(), OptimaError> {
+        if let JointStateDistanceMetric::WeightedEuclidean { weights } = self {
+            if weights.len() != dim {
+                return Err(OptimaError::new_generic_error_str(&format!("Metric has {} weights, but states have dimension {}.", weights.len(), dim), file!(), line!()));
+            }
+            for w in weights {
+                if *w < 0.0 { return Err(OptimaError::new_generic_error_str("Metric weights must be non-negative.", file!(), line!())); }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Common interface for structures that store joint states (each paired with a payload of type T)
+/// and answer nearest neighbor queries under a `JointStateDistanceMetric`.  Sampling-based planners
+/// and user-implemented custom planners can use any structure through this trait.
+pub trait NearestNeighborStructure<T: Clone + Debug> {
+    fn insert(&mut self, state: DVector<f64>, data: T) -> Result<usize, OptimaError>;
+    fn nearest(&self, query: &DVector<f64>) -> Option<NearestNeighborResult> {
+        let res = self.k_nearest(query, 1);
+        return res.first().cloned();
+    }
+    /// Returns up to k nearest items, sorted by increasing distance.
+    fn k_nearest(&self, query: &DVector<f64>, k: usize) -> Vec<NearestNeighborResult>;
+    /// Returns all items within the given radius (inclusive), sorted by increasing distance.
+    fn within_radius(&self, query: &DVector<f64>, radius: f64) -> Vec<NearestNeighborResult>;
+    fn state(&self, item_idx: usize) -> Result<&DVector<f64>, OptimaError>;
+    fn data(&self, item_idx: usize) -> Result<&T, OptimaError>;
+    fn len(&self) -> usize;
+    fn metric(&self) -> &JointStateDistanceMetric;
+}
+
+/// The output of a nearest neighbor query.  `item_idx` is the index returned by `insert` for the
+/// matched item.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NearestNeighborResult {
+    item_idx: usize,
+    distance: f64
+}
+impl NearestNeighborResult {
+    pub fn item_idx(&self) -> usize {
+        self.item_idx
+    }
+    pub fn distance(&self) -> f64 {
+        self.distance
+    }
+}
+
+/// Collects the best candidates seen so far during a k-nearest or radius query.
+struct NearestNeighborCandidates {
+    k: Option<usize>,
+    radius: Option<f64>,
+    candidates: Vec<NearestNeighborResult>
+}
+impl NearestNeighborCandidates {
+    fn new_k_nearest(k: usize) -> Self {
+        Self { k: Some(k), radius: None, candidates: vec![] }
+    }
+    fn new_within_radius(radius: f64) -> Self {
+        Self { k: None, radius: Some(radius), candidates: vec![] }
+    }
+    /// Items farther than this distance cannot be added to the candidates.
+    fn pruning_distance(&self) -> f64 {
+        if let Some(radius) = self.radius { return radius; }
+        let k = self.k.unwrap();
+        if k == 0 { return f64::NEG_INFINITY; }
+        return if self.candidates.len() < k { f64::INFINITY } else { self.candidates[self.candidates.len() - 1].distance }
+    }
+    fn add(&mut self, item_idx: usize, distance: f64) {
+        if let Some(radius) = self.radius {
+            if distance <= radius { self.candidates.push(NearestNeighborResult { item_idx, distance }); }
+            return;
+        }
+
+        let k = self.k.unwrap();
+        if k == 0 { return; }
+        if self.candidates.len() < k || distance < self.pruning_distance() {
+            let insert_idx = self.candidates.iter().position(|c| c.distance > distance).unwrap_or(self.candidates.len());
+            self.candidates.insert(insert_idx, NearestNeighborResult { item_idx, distance });
+            if self.candidates.len() > k { self.candidates.pop(); }
+        }
+    }
+    fn finish(mut self) -> Vec<NearestNeighborResult> {
+        self.candidates.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        self.candidates
+    }
+}
+
+/// Linear scan over all stored items.  Exact under any metric and a reasonable choice for small
+/// numbers of items.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BruteForceNearestNeighbor<T: Clone + Debug> {
+    metric: JointStateDistanceMetric,
+    states: Vec<DVector<f64>>,
+    data: Vec<T>
+}
+impl <T: Clone + Debug> BruteForceNearestNeighbor<T> {
+    pub fn new(metric: JointStateDistanceMetric) -> Self {
+        Self {
+            metric,
+            states: vec![],
+            data: vec![]
+        }
+    }
+    fn search(&self, query: &DVector<f64>, candidates: &mut NearestNeighborCandidates) {
+        for (i, state) in self.states.iter().enumerate() {
+            candidates.add(i, self.metric.distance(query, state));
+        }
+    }
+}
+impl <T: Clone + Debug> NearestNeighborStructure<T> for BruteForceNearestNeighbor<T> {
+    fn insert(&mut self, state: DVector<f64>, data: T) -> Result<usize, OptimaError> {
+        check_state_dimension(&self.metric, self.states.first(), &state)?;
+        self.states.push(state);
+        self.data.push(data);
+        Ok(self.states.len() - 1)
+    }
+    fn k_nearest(&self, query: &DVector<f64>, k: usize) -> Vec<NearestNeighborResult> {
+        let mut candidates = NearestNeighborCandidates::new_k_nearest(k);
+        self.search(query, &mut candidates);
+        candidates.finish()
+    }
+    fn within_radius(&self, query: &DVector<f64>, radius: f64) -> Vec<NearestNeighborResult> {
+        let mut candidates = NearestNeighborCandidates::new_within_radius(radius);
+        self.search(query, &mut candidates);
+        candidates.finish()
+    }
+    fn state(&self, item_idx: usize) -> Result<&DVector<f64>, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(item_idx, self.states.len(), file!(), line!())?;
+        Ok(&self.states[item_idx])
+    }
+    fn data(&self, item_idx: usize) -> Result<&T, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(item_idx, self.data.len(), file!(), line!())?;
+        Ok(&self.data[item_idx])
+    }
+    fn len(&self) -> usize {
+        self.states.len()
+    }
+    fn metric(&self) -> &JointStateDistanceMetric {
+        &self.metric
+    }
+}
+
+/// Incrementally built KD-tree.  Split axes cycle through the state dimensions with depth, so the
+/// tree does not need to be rebuilt as items are added (e.g., as an RRT grows).  Queries are exact
+/// for all `JointStateDistanceMetric` variants.  Note that KD-tree pruning becomes ineffective in
+/// high dimensions (more than ~10 DOFs); `BruteForceNearestNeighbor` may be faster in that regime.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KDTreeNearestNeighbor<T: Clone + Debug> {
+    metric: JointStateDistanceMetric,
+    states: Vec<DVector<f64>>,
+    data: Vec<T>,
+    nodes: Vec<KDTreeNode>
+}
+impl <T: Clone + Debug> KDTreeNearestNeighbor<T> {
+    pub fn new(metric: JointStateDistanceMetric) -> Self {
+        Self {
+            metric,
+            states: vec![],
+            data: vec![],
+            nodes: vec![]
+        }
+    }
+    fn search(&self, node_idx: Option<usize>, query: &DVector<f64>, candidates: &mut NearestNeighborCandidates) {
+        let node_idx = match node_idx {
+            None => { return; }
+            Some(node_idx) => { node_idx }
+        };
+        let node = &self.nodes[node_idx];
+        let state = &self.states[node_idx];
+
+        candidates.add(node_idx, self.metric.distance(query, state));
+
+        let difference = query[node.split_axis] - state[node.split_axis];
+        let (near, far) = if difference < 0.0 { (node.left, node.right) } else { (node.right, node.left) };
+
+        self.search(near, query, candidates);
+        if self.metric.axis_distance_lower_bound(node.split_axis, difference) <= candidates.pruning_distance() {
+            self.search(far, query, candidates);
+        }
+    }
+}
+impl <T: Clone + Debug> NearestNeighborStructure<T> for KDTreeNearestNeighbor<T> {
+    fn insert(&mut self, state: DVector<f64>, data: T) -> Result<usize, OptimaError> {
+        check_state_dimension(&self.metric, self.states.first(), &state)?;
+
+        let new_idx = self.states.len();
+        let dim = state.len();
+
+        let mut depth = 0;
+        if !self.nodes.is_empty() {
+            let mut curr_idx = 0;
+            loop {
+                depth += 1;
+                let split_axis = self.nodes[curr_idx].split_axis;
+                let go_left = state[split_axis] < self.states[curr_idx][split_axis];
+                let next = if go_left { self.nodes[curr_idx].left } else { self.nodes[curr_idx].right };
+                match next {
+                    None => {
+                        if go_left { self.nodes[curr_idx].left = Some(new_idx); } else { self.nodes[curr_idx].right = Some(new_idx); }
+                        break;
+                    }
+                    Some(next) => { curr_idx = next; }
+                }
+            }
+        }
+
+        let split_axis = if dim == 0 { 0 } else { depth % dim };
+        self.nodes.push(KDTreeNode { split_axis, left: None, right: None });
+        self.states.push(state);
+        self.data.push(data);
+
+        Ok(new_idx)
+    }
+    fn k_nearest(&self, query: &DVector<f64>, k: usize) -> Vec<NearestNeighborResult> {
+        let mut candidates = NearestNeighborCandidates::new_k_nearest(k);
+        let root = if self.nodes.is_empty() { None } else { Some(0) };
+        self.search(root, query, &mut candidates);
+        candidates.finish()
+    }
+    fn within_radius(&self, query: &DVector<f64>, radius: f64) -> Vec<NearestNeighborResult> {
+        let mut candidates = NearestNeighborCandidates::new_within_radius(radius);
+        let root = if self.nodes.is_empty() { None } else { Some(0) };
+        self.search(root, query, &mut candidates);
+        candidates.finish()
+    }
+    fn state(&self, item_idx: usize) -> Result<&DVector<f64>, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(item_idx, self.states.len(), file!(), line!())?;
+        Ok(&self.states[item_idx])
+    }
+    fn data(&self, item_idx: usize) -> Result<&T, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(item_idx, self.data.len(), file!(), line!())?;
+        Ok(&self.data[item_idx])
+    }
+    fn len(&self) -> usize {
+        self.states.len()
+    }
+    fn metric(&self) -> &JointStateDistanceMetric {
+        &self.metric
+    }
+}
+
+/// Node i of the tree corresponds to item i.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct KDTreeNode {
+    split_axis: usize,
+    left: Option<usize>,
+    right: Option<usize>
+}
+
+/// Geometric Near-neighbor Access Tree (GNAT), a metric tree that only relies on the triangle
+/// inequality of the distance metric rather than on axis-aligned splits.  Unlike a KD-tree, its
+/// pruning remains effective for high-DOF states (e.g., dual arms or mobile manipulators).
+///
+/// Items are stored in leaf buckets.  When a bucket grows beyond `max_leaf_size`, `degree` pivots are
+/// chosen from it (farthest-first), the remaining items are assigned to their closest pivot, and each
+/// internal node records the range of distances from every pivot to every child subtree.  Queries
+/// skip any child subtree whose distance range is inconsistent with the current search radius.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GNATNearestNeighbor<T: Clone + Debug> {
+    metric: JointStateDistanceMetric,
+    degree: usize,
+    max_leaf_size: usize,
+    states: Vec<DVector<f64>>,
+    data: Vec<T>,
+    nodes: Vec<GNATNode>
+}
+impl <T: Clone + Debug> GNATNearestNeighbor<T> {
+    /// degree is clamped to be at least 2, and max_leaf_size is clamped to be at least degree.
+    pub fn new(metric: JointStateDistanceMetric, degree: usize, max_leaf_size: usize) -> Self {
+        let degree = degree.max(2);
+        Self {
+            metric,
+            degree,
+            max_leaf_size: max_leaf_size.max(degree),
+            states: vec![],
+            data: vec![],
+            nodes: vec![GNATNode::new_leaf(vec![])]
+        }
+    }
+    pub fn new_default(metric: JointStateDistanceMetric) -> Self {
+        return Self::new(metric, 8, 50);
+    }
+    pub fn degree(&self) -> usize {
+        self.degree
+    }
+    pub fn max_leaf_size(&self) -> usize {
+        self.max_leaf_size
+    }
+    fn split(&mut self, node_idx: usize) {
+        let bucket = self.nodes[node_idx].bucket.clone();
+
+        // Farthest-first pivot selection.
+        let mut pivots = vec![bucket[0]];
+        let mut min_dis_to_pivots: Vec<f64> = bucket.iter().map(|b| self.metric.distance(&self.states[*b], &self.states[bucket[0]])).collect();
+        while pivots.len() < self.degree {
+            let mut best_idx = 0;
+            let mut best_dis = f64::NEG_INFINITY;
+            for (i, d) in min_dis_to_pivots.iter().enumerate() {
+                if *d > best_dis { best_dis = *d; best_idx = i; }
+            }
+            if best_dis <= 0.0 { break; }
+            let new_pivot = bucket[best_idx];
+            pivots.push(new_pivot);
+            for (i, b) in bucket.iter().enumerate() {
+                let d = self.metric.distance(&self.states[*b], &self.states[new_pivot]);
+                if d < min_dis_to_pivots[i] { min_dis_to_pivots[i] = d; }
+            }
+        }
+
+        let num_pivots = pivots.len();
+        let mut ranges = vec![vec![(f64::INFINITY, f64::NEG_INFINITY); num_pivots]; num_pivots];
+        for i in 0..num_pivots {
+            for j in 0..num_pivots {
+                let d = self.metric.distance(&self.states[pivots[i]], &self.states[pivots[j]]);
+                ranges[i][j] = (d, d);
+            }
+        }
+
+        let mut child_buckets = vec![vec![]; num_pivots];
+        for b in &bucket {
+            if pivots.contains(b) { continue; }
+            let dis_to_pivots: Vec<f64> = pivots.iter().map(|p| self.metric.distance(&self.states[*b], &self.states[*p])).collect();
+            let closest = argmin(&dis_to_pivots);
+            for i in 0..num_pivots {
+                expand_range(&mut ranges[i][closest], dis_to_pivots[i]);
+            }
+            child_buckets[closest].push(*b);
+        }
+
+        let mut children = vec![];
+        for child_bucket in child_buckets {
+            children.push(self.nodes.len());
+            self.nodes.push(GNATNode::new_leaf(child_bucket));
+        }
+
+        let node = &mut self.nodes[node_idx];
+        node.bucket = vec![];
+        node.pivots = pivots;
+        node.children = children;
+        node.ranges = ranges;
+    }
+    fn search(&self, node_idx: usize, query: &DVector<f64>, candidates: &mut NearestNeighborCandidates) {
+        let node = &self.nodes[node_idx];
+        if node.pivots.is_empty() {
+            for b in &node.bucket {
+                candidates.add(*b, self.metric.distance(query, &self.states[*b]));
+            }
+            return;
+        }
+
+        let dis_to_pivots: Vec<f64> = node.pivots.iter().map(|p| self.metric.distance(query, &self.states[*p])).collect();
+        for (i, p) in node.pivots.iter().enumerate() {
+            candidates.add(*p, dis_to_pivots[i]);
+        }
+
+        let mut child_order: Vec<usize> = (0..node.children.len()).collect();
+        child_order.sort_by(|a, b| dis_to_pivots[*a].total_cmp(&dis_to_pivots[*b]));
+
+        for j in child_order {
+            let r = candidates.pruning_distance();
+            let mut prune = false;
+            for i in 0..node.pivots.len() {
+                let (min, max) = node.ranges[i][j];
+                if dis_to_pivots[i] - r > max || dis_to_pivots[i] + r < min { prune = true; break; }
+            }
+            if !prune { self.search(node.children[j], query, candidates); }
+        }
+    }
+}
+impl <T: Clone + Debug> NearestNeighborStructure<T> for GNATNearestNeighbor<T> {
+    fn insert(&mut self, state: DVector<f64>, data: T) -> Result<usize, OptimaError> {
+        check_state_dimension(&self.metric, self.states.first(), &state)?;
+
+        let new_idx = self.states.len();
+        self.states.push(state);
+        self.data.push(data);
+
+        let mut curr_idx = 0;
+        loop {
+            if self.nodes[curr_idx].pivots.is_empty() {
+                self.nodes[curr_idx].bucket.push(new_idx);
+                if self.nodes[curr_idx].bucket.len() > self.max_leaf_size { self.split(curr_idx); }
+                break;
+            }
+
+            let dis_to_pivots: Vec<f64> = self.nodes[curr_idx].pivots.iter().map(|p| self.metric.distance(&self.states[new_idx], &self.states[*p])).collect();
+            let closest = argmin(&dis_to_pivots);
+            let node = &mut self.nodes[curr_idx];
+            for i in 0..node.pivots.len() {
+                expand_range(&mut node.ranges[i][closest], dis_to_pivots[i]);
+            }
+            curr_idx = node.children[closest];
+        }
+
+        Ok(new_idx)
+    }
+    fn k_nearest(&self, query: &DVector<f64>, k: usize) -> Vec<NearestNeighborResult> {
+        let mut candidates = NearestNeighborCandidates::new_k_nearest(k);
+        self.search(0, query, &mut candidates);
+        candidates.finish()
+    }
+    fn within_radius(&self, query: &DVector<f64>, radius: f64) -> Vec<NearestNeighborResult> {
+        let mut candidates = NearestNeighborCandidates::new_within_radius(radius);
+        self.search(0, query, &mut candidates);
+        candidates.finish()
+    }
+    fn state(&self, item_idx: usize) -> Result<&DVector<f64>, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(item_idx, self.states.len(), file!(), line!())?;
+        Ok(&self.states[item_idx])
+    }
+    fn data(&self, item_idx: usize) -> Result<&T, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(item_idx, self.data.len(), file!(), line!())?;
+        Ok(&self.data[item_idx])
+    }
+    fn len(&self) -> usize {
+        self.states.len()
+    }
+    fn metric(&self) -> &JointStateDistanceMetric {
+        &self.metric
+    }
+}
+
+/// A node is a leaf if it has no pivots, in which case its items are stored in bucket.  Otherwise,
+/// ranges\[i]\[j] is the (min, max) distance from pivot i to any item in child j (including pivot j).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct GNATNode {
+    bucket: Vec<usize>,
+    pivots: Vec<usize>,
+    children: Vec<usize>,
+    ranges: Vec<Vec<(f64, f64)>>
+}
+impl GNATNode {
+    fn new_leaf(bucket: Vec<usize>) -> Self {
+        Self {
+            bucket,
+            pivots: vec![],
+            children: vec![],
+            ranges: vec![]
+        }
+    }
+}
+
+/// Selects which nearest neighbor structure to use (e.g., in a planner configuration).  `KDTree` is
+/// typically the best choice for low-DOF systems, while `GNAT` scales better to high-DOF systems.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NearestNeighborStructureType {
+    BruteForce,
+    KDTree,
+    GNAT { degree: usize, max_leaf_size: usize }
+}
+
+/// Wraps any of the nearest neighbor structures so that the structure can be chosen at runtime via a
+/// `NearestNeighborStructureType`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum NearestNeighborStructureObject<T: Clone + Debug> {
+    BruteForce(BruteForceNearestNeighbor<T>),
+    KDTree(KDTreeNearestNeighbor<T>),
+    GNAT(GNATNearestNeighbor<T>)
+}
+impl <T: Clone + Debug> NearestNeighborStructureObject<T> {
+    pub fn new(t: &NearestNeighborStructureType, metric: JointStateDistanceMetric) -> Self {
+        return match t {
+            NearestNeighborStructureType::BruteForce => { Self::BruteForce(BruteForceNearestNeighbor::new(metric)) }
+            NearestNeighborStructureType::KDTree => { Self::KDTree(KDTreeNearestNeighbor::new(metric)) }
+            NearestNeighborStructureType::GNAT { degree, max_leaf_size } => { Self::GNAT(GNATNearestNeighbor::new(metric, *degree, *max_leaf_size)) }
+        }
+    }
+    /// The type of the wrapped structure (with GNAT parameters after clamping).
+    pub fn structure_type(&self) -> NearestNeighborStructureType {
+        return match self {
+            NearestNeighborStructureObject::BruteForce(_) => { NearestNeighborStructureType::BruteForce }
+            NearestNeighborStructureObject::KDTree(_) => { NearestNeighborStructureType::KDTree }
+            NearestNeighborStructureObject::GNAT(s) => { NearestNeighborStructureType::GNAT { degree: s.degree(), max_leaf_size: s.max_leaf_size() } }
+        }
+    }
+    fn inner(&self) -> &dyn NearestNeighborStructure<T> {
+        return match self {
+            NearestNeighborStructureObject::BruteForce(s) => { s }
+            NearestNeighborStructureObject::KDTree(s) => { s }
+            NearestNeighborStructureObject::GNAT(s) => { s }
+        }
+    }
+    fn inner_mut(&mut self) -> &mut dyn NearestNeighborStructure<T> {
+        return match self {
+            NearestNeighborStructureObject::BruteForce(s) => { s }
+            NearestNeighborStructureObject::KDTree(s) => { s }
+            NearestNeighborStructureObject::GNAT(s) => { s }
+        }
+    }
+}
+impl <T: Clone + Debug> NearestNeighborStructure<T> for NearestNeighborStructureObject<T> {
+    fn insert(&mut self, state: DVector<f64>, data: T) -> Result<usize, OptimaError> {
+        self.inner_mut().insert(state, data)
+    }
+    fn k_nearest(&self, query: &DVector<f64>, k: usize) -> Vec<NearestNeighborResult> {
+        self.inner().k_nearest(query, k)
+    }
+    fn within_radius(&self, query: &DVector<f64>, radius: f64) -> Vec<NearestNeighborResult> {
+        self.inner().within_radius(query, radius)
+    }
+    fn state(&self, item_idx: usize) -> Result<&DVector<f64>, OptimaError> {
+        self.inner().state(item_idx)
+    }
+    fn data(&self, item_idx: usize) -> Result<&T, OptimaError> {
+        self.inner().data(item_idx)
+    }
+    fn len(&self) -> usize {
+        self.inner().len()
+    }
+    fn metric(&self) -> &JointStateDistanceMetric {
+        self.inner().metric()
+    }
+}
+
+fn check_state_dimension(metric: &JointStateDistanceMetric, first_state: Option<&DVector<f64>>, state: &DVector<f64>) -> Result<(), OptimaError> {
+    match first_state {
+        None => { metric.check_dimension(state.len())?; }
+        Some(first_state) => {
+            if first_state.len() != state.len() {
+                return Err(OptimaError::new_generic_error_str(&format!("State has dimension {}, but previously inserted states have dimension {}.", state.len(), first_state.len()), file!(), line!()));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn argmin(v: &Vec<f64>) -> usize {
+    let mut out_idx = 0;
+    for (i, x) in v.iter().enumerate() {
+        if *x < v[out_idx] { out_idx = i; }
+    }
+    out_idx
+}
+
+fn expand_range(range: &mut (f64, f64), value: f64) {
+    if value < range.0 { range.0 = value; }
+    if value > range.1 { range.1 = value; }
+}
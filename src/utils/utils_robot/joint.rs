@@ -5,10 +5,12 @@ use pyo3::*;
 use wasm_bindgen::prelude::*;
 
 use nalgebra::{Vector3, Unit};
+use optima_core_kinematics::{CoreJointAxisType, CoreJointComposition};
 use serde::{Serialize, Deserialize};
-use crate::robot_modules::robot_configuration_module::ContiguousChainMobilityMode;
+use crate::robot_modules::robot_configuration_module::{ContiguousChainMobilityMode, JointCouplingInfo};
 use crate::utils::utils_console::{optima_print, optima_print_new_line, PrintColor, PrintMode};
 use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_robot::robot_ids::{JointId, LinkId};
 use crate::utils::utils_robot::urdf_joint::{JointTypeWrapper, URDFJoint};
 use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3PoseAll, OptimaSE3Pose, OptimaSE3PoseType};
 use crate::utils::utils_traits::ToAndFromRonString;
@@ -126,13 +128,22 @@ impl Joint {
     pub fn child_link_idx(&self) -> Option<usize> {
         self.child_link_idx
     }
+    pub fn joint_id(&self) -> JointId {
+        JointId::new(self.joint_idx)
+    }
+    pub fn preceding_link_id(&self) -> Option<LinkId> {
+        self.preceding_link_idx.map(LinkId::new)
+    }
+    pub fn child_link_id(&self) -> Option<LinkId> {
+        self.child_link_idx.map(LinkId::new)
+    }
     pub fn has_origin_offset(&self) -> bool {
         self.has_origin_offset
     }
     pub fn num_dofs(&self) -> usize {
         let mut num_dofs = 0;
         for ja in self.joint_axes() {
-            if !ja.is_fixed() {
+            if ja.is_dof() {
                 num_dofs += 1;
             }
         }
@@ -178,7 +189,10 @@ impl Joint {
                     optima_print(&format!("Fixed at value {}", a.fixed_value.unwrap()), PrintMode::Print, PrintColor::None, false);
                 }
                 false => {
-                    optima_print("Not fixed.", PrintMode::Print, PrintColor::None, false);
+                    match &a.coupling {
+                        None => { optima_print("Not fixed.", PrintMode::Print, PrintColor::None, false); }
+                        Some(coupling) => { optima_print(&format!("Coupled with offset {} to {:?}", coupling.offset, coupling.terms), PrintMode::Print, PrintColor::None, false); }
+                    }
                 }
             };
             if self.joint_axes.len() > 1 && i < self.joint_axes.len()-1 {
@@ -194,6 +208,67 @@ impl Joint {
         self.joint_axes[joint_sub_idx].fixed_value = fixed_value;
         Ok(())
     }
+    /// Sets the linear coupling constraint of the given joint axis (see `JointCouplingInfo`).  A
+    /// coupled joint axis is not a degree of freedom.
+    pub fn set_coupled_joint_sub_dof(&mut self, joint_sub_idx: usize, coupling: Option<JointCouplingInfo>) -> Result<(), OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(joint_sub_idx, self.joint_axes.len(), file!(), line!())?;
+        self.joint_axes[joint_sub_idx].coupling = coupling;
+        Ok(())
+    }
+    /// Sets the lower and upper bounds of all axes of the joint (e.g., to apply calibration results).
+    /// The quaternion components of ball joints keep their (-1, 1) bounds.
+    pub fn set_limits(&mut self, lower: f64, upper: f64) -> Result<(), OptimaError> {
+        if lower > upper {
+            return Err(OptimaError::new_generic_error_str(&format!("Lower limit {} of joint {} is greater than upper limit {}.", lower, self.name, upper), file!(), line!()));
+        }
+
+        self.urdf_joint.set_limits(lower, upper);
+        for a in &mut self.joint_axes {
+            if a.axis_primitive_type != JointAxisPrimitiveType::QuaternionComponent { a.bounds = (lower, upper); }
+        }
+        Ok(())
+    }
+    /// Sets the lower and upper bounds of a single axis of the joint.
+    pub fn set_joint_sub_dof_limits(&mut self, joint_sub_idx: usize, lower: f64, upper: f64) -> Result<(), OptimaError> {
+        if joint_sub_idx >= self.joint_axes.len() {
+            return Err(OptimaError::new_idx_out_of_bound_error(joint_sub_idx, self.joint_axes.len(), file!(), line!()));
+        }
+        if lower > upper {
+            return Err(OptimaError::new_generic_error_str(&format!("Lower limit {} of joint {} is greater than upper limit {}.", lower, self.name, upper), file!(), line!()));
+        }
+
+        self.joint_axes[joint_sub_idx].bounds = (lower, upper);
+        Ok(())
+    }
+    /// Sets the axis of a revolute, continuous, or prismatic joint.  Joints of other types (and chain
+    /// base connector joints) have fixed standard axes, so this returns an error for them.
+    pub fn set_axis(&mut self, axis: Vector3<f64>) -> Result<(), OptimaError> {
+        let single_axis = match self.urdf_joint.joint_type() {
+            JointTypeWrapper::Revolute | JointTypeWrapper::Continuous | JointTypeWrapper::Prismatic => { true }
+            _ => { false }
+        };
+        if !single_axis || self.is_chain_base_connector_joint {
+            return Err(OptimaError::new_unsupported_operation_error("set_axis", &format!("Joint {} does not have a single configurable axis.", self.name), file!(), line!()));
+        }
+        if axis.norm() == 0.0 {
+            return Err(OptimaError::new_generic_error_str(&format!("Axis of joint {} must be non-zero.", self.name), file!(), line!()));
+        }
+
+        self.urdf_joint.set_axis(axis.clone());
+        for a in &mut self.joint_axes {
+            a.axis_as_unit = Unit::new_normalize(axis.clone());
+            a.axis = axis.clone();
+        }
+        self.set_is_joint_with_all_standard_axes();
+        Ok(())
+    }
+    /// Sets the origin offset of the joint relative to its preceding link, given as in a URDF (i.e.,
+    /// a translation and roll, pitch, yaw euler angles).
+    pub fn set_origin(&mut self, origin_xyz: Vector3<f64>, origin_rpy: Vector3<f64>) {
+        self.origin_offset_pose = OptimaSE3PoseAll::new(&OptimaSE3Pose::new_implicit_dual_quaternion_from_euler_angles(origin_rpy[0], origin_rpy[1], origin_rpy[2], origin_xyz[0], origin_xyz[1], origin_xyz[2]));
+        self.has_origin_offset = origin_rpy.norm() != 0.0 || origin_xyz.norm() != 0.0;
+        self.urdf_joint.set_origin(origin_xyz, origin_rpy);
+    }
     pub fn set_present(&mut self, present: bool) {
         self.present = present;
     }
@@ -206,6 +281,23 @@ impl Joint {
     pub fn is_joint_with_all_standard_axes(&self) -> bool {
         self.is_joint_with_all_standard_axes
     }
+    /// Returns the joint sub dof index of the w component of the joint's unit quaternion if the
+    /// joint is a ball joint (i.e., a spherical or floating joint, see
+    /// `JointAxisPrimitiveType::QuaternionComponent`).  The x, y, and z components directly follow it.
+    pub fn quaternion_sub_dof_idx(&self) -> Option<usize> {
+        return self.joint_axes.iter().position(|a| a.axis_primitive_type == JointAxisPrimitiveType::QuaternionComponent);
+    }
+    /// How the joint's axes combine into the joint transform (see
+    /// `CoreJointComposition::joint_transform`).
+    pub fn core_joint_composition(&self) -> CoreJointComposition {
+        return if self.quaternion_sub_dof_idx().is_some() {
+            CoreJointComposition::Ball
+        } else if self.is_joint_with_all_standard_axes {
+            CoreJointComposition::EulerAngles
+        } else {
+            CoreJointComposition::AxisSequence
+        }
+    }
     fn set_dof_axes(&mut self, joint_idx: usize) {
         let joint_type = self.urdf_joint.joint_type();
         let lower_bound = self.urdf_joint.limits_lower();
@@ -226,35 +318,35 @@ impl Joint {
                 /* Do Nothing */
             }
             JointTypeWrapper::Floating => {
-                self.joint_axes.push(JointAxis::new(joint_idx, 0, Vector3::new(1.,0.,0.), JointAxisPrimitiveType::Rotation, (lower_bound, upper_bound)));
-                self.joint_axes.push(JointAxis::new(joint_idx, 1, Vector3::new(0.,1.,0.), JointAxisPrimitiveType::Rotation, (lower_bound, upper_bound)));
-                self.joint_axes.push(JointAxis::new(joint_idx, 2, Vector3::new(0.,0.,1.), JointAxisPrimitiveType::Rotation, (lower_bound, upper_bound)));
+                // Translation first, then rotation about the translated origin as a unit quaternion.
+                self.joint_axes.push(JointAxis::new(joint_idx, 0, Vector3::new(1.,0.,0.), JointAxisPrimitiveType::Translation, (lower_bound, upper_bound)));
+                self.joint_axes.push(JointAxis::new(joint_idx, 1, Vector3::new(0.,1.,0.), JointAxisPrimitiveType::Translation, (lower_bound, upper_bound)));
+                self.joint_axes.push(JointAxis::new(joint_idx, 2, Vector3::new(0.,0.,1.), JointAxisPrimitiveType::Translation, (lower_bound, upper_bound)));
 
-                self.joint_axes.push(JointAxis::new(joint_idx, 3, Vector3::new(1.,0.,0.), JointAxisPrimitiveType::Translation, (lower_bound, upper_bound)));
-                self.joint_axes.push(JointAxis::new(joint_idx, 4, Vector3::new(0.,1.,0.), JointAxisPrimitiveType::Translation, (lower_bound, upper_bound)));
-                self.joint_axes.push(JointAxis::new(joint_idx, 5, Vector3::new(0.,0.,1.), JointAxisPrimitiveType::Translation, (lower_bound, upper_bound)));
+                for component in 0..4 {
+                    self.joint_axes.push(JointAxis::new_quaternion_component(joint_idx, 3 + component, component));
+                }
             }
             JointTypeWrapper::Planar => {
-                /*
-                let mut v = axis.clone();
-
-                let v1 = get_orthogonal_vector(&v)?;
+                // Translation within the plane perpendicular to the axis, then rotation about the axis.
+                let v = if axis.norm() > 0.0 { axis.normalize() } else { Vector3::new(0.,0.,1.) };
+                let helper = if v[0].abs() < 0.9 { Vector3::new(1.,0.,0.) } else { Vector3::new(0.,1.,0.) };
+                let v1 = v.cross(&helper).normalize();
                 let v2 = v.cross(&v1);
 
-                self.dof_translation_axes.push( Vector3::new(v1[0], v1[1], v1[2]) );
-                self.dof_translation_axes.push( Vector3::new(v2[0], v2[1], v2[2]) );
-                */
-                todo!()
+                self.joint_axes.push(JointAxis::new(joint_idx, 0, v1, JointAxisPrimitiveType::Translation, (lower_bound, upper_bound)));
+                self.joint_axes.push(JointAxis::new(joint_idx, 1, v2, JointAxisPrimitiveType::Translation, (lower_bound, upper_bound)));
+                self.joint_axes.push(JointAxis::new(joint_idx, 2, v, JointAxisPrimitiveType::Rotation, (lower_bound, upper_bound)));
             }
             JointTypeWrapper::Spherical => {
-                self.joint_axes.push(JointAxis::new(joint_idx, 0, Vector3::new(1.,0.,0.), JointAxisPrimitiveType::Rotation, (lower_bound, upper_bound)));
-                self.joint_axes.push(JointAxis::new(joint_idx, 1, Vector3::new(0.,1.,0.), JointAxisPrimitiveType::Rotation, (lower_bound, upper_bound)));
-                self.joint_axes.push(JointAxis::new(joint_idx, 2, Vector3::new(0.,0.,1.), JointAxisPrimitiveType::Rotation, (lower_bound, upper_bound)));
+                for component in 0..4 {
+                    self.joint_axes.push(JointAxis::new_quaternion_component(joint_idx, component, component));
+                }
             }
         }
     }
     fn set_is_joint_with_all_standard_axes(&mut self) {
-        let mut out_val = true;
+        let mut out_val = self.quaternion_sub_dof_idx().is_none();
         for a in &self.joint_axes {
             let axis = &a.axis;
             if !(axis == &Vector3::new(1.,0.,0.) || axis == &Vector3::new(0.,1.,0.) || axis == &Vector3::new(0.,0.,1.) ||
@@ -306,6 +398,8 @@ pub struct JointAxis {
     joint_idx: usize,
     joint_sub_dof_idx: usize,
     fixed_value: Option<f64>,
+    #[serde(default)]
+    coupling: Option<JointCouplingInfo>,
     axis_as_unit: Unit<Vector3<f64>>,
     axis: Vector3<f64>,
     axis_primitive_type: JointAxisPrimitiveType,
@@ -317,24 +411,61 @@ impl JointAxis {
             joint_idx,
             joint_sub_dof_idx,
             fixed_value: None,
+            coupling: None,
             axis_as_unit: Unit::new_normalize(axis.clone()),
             axis,
             axis_primitive_type,
             bounds
         }
     }
+    /// Returns one of the four components (0: w, 1: x, 2: y, 3: z) of a ball joint's unit quaternion.
+    /// The axis of the x, y, and z components is the corresponding standard axis, and the axis of the
+    /// w component is the zero vector.
+    fn new_quaternion_component(joint_idx: usize, joint_sub_dof_idx: usize, component: usize) -> Self {
+        let axis = match component {
+            1 => { Vector3::new(1.,0.,0.) }
+            2 => { Vector3::new(0.,1.,0.) }
+            3 => { Vector3::new(0.,0.,1.) }
+            _ => { Vector3::zeros() }
+        };
+
+        Self {
+            joint_idx,
+            joint_sub_dof_idx,
+            fixed_value: None,
+            coupling: None,
+            axis_as_unit: if component == 0 { Vector3::x_axis() } else { Unit::new_normalize(axis.clone()) },
+            axis,
+            axis_primitive_type: JointAxisPrimitiveType::QuaternionComponent,
+            bounds: (-1.0, 1.0)
+        }
+    }
     pub fn is_fixed(&self) -> bool {
         self.fixed_value.is_some()
     }
+    /// Returns true if the joint axis' value is computed from other joint axes (see `JointCouplingInfo`).
+    pub fn is_coupled(&self) -> bool {
+        self.coupling.is_some()
+    }
+    /// Returns true if the joint axis is a degree of freedom, i.e., it is neither fixed nor coupled.
+    pub fn is_dof(&self) -> bool {
+        !self.is_fixed() && !self.is_coupled()
+    }
     pub fn joint_idx(&self) -> usize {
         self.joint_idx
     }
+    pub fn joint_id(&self) -> JointId {
+        JointId::new(self.joint_idx)
+    }
     pub fn joint_sub_dof_idx(&self) -> usize {
         self.joint_sub_dof_idx
     }
     pub fn fixed_value(&self) -> Option<f64> {
         self.fixed_value
     }
+    pub fn coupling(&self) -> &Option<JointCouplingInfo> {
+        &self.coupling
+    }
     pub fn axis_as_unit(&self) -> Unit<Vector3<f64>> {
         self.axis_as_unit
     }
@@ -344,6 +475,13 @@ impl JointAxis {
     pub fn axis_primitive_type(&self) -> &JointAxisPrimitiveType {
         &self.axis_primitive_type
     }
+    pub fn core_joint_axis_type(&self) -> CoreJointAxisType {
+        return match self.axis_primitive_type {
+            JointAxisPrimitiveType::Rotation => { CoreJointAxisType::Rotation }
+            JointAxisPrimitiveType::Translation => { CoreJointAxisType::Translation }
+            JointAxisPrimitiveType::QuaternionComponent => { CoreJointAxisType::QuaternionComponent }
+        }
+    }
     pub fn bounds(&self) -> (f64, f64) {
         self.bounds
     }
@@ -361,6 +499,9 @@ impl JointAxis {
     pub fn fixed_value_py(&self) -> Option<f64> {
         self.fixed_value
     }
+    pub fn is_coupled_py(&self) -> bool {
+        self.is_coupled()
+    }
     pub fn axis_py(&self) -> Vec<f64> {
         let a = &self.axis;
         return vec![a[0], a[1], a[2]];
@@ -374,8 +515,17 @@ impl JointAxis {
 }
 
 /// Specifies the transform type for a JointAxis Object.
+///
+/// Ball joints (spherical joints, and the rotational part of floating joints) are not parameterized
+/// by three Euler angle axes, but by the four components of a unit quaternion (w, x, y, z, in
+/// joint sub dof index order), each of which is a `QuaternionComponent` axis.  The quaternion is
+/// normalized whenever it is used, so joint states only need to be approximately unit length (a
+/// zero quaternion is treated as the identity).
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum JointAxisPrimitiveType {
     Rotation,
-    Translation
-}
\ No newline at end of file
+    Translation,
+    QuaternionComponent
+}
+
+crate::impl_py_pickle!(Joint, JointAxis);
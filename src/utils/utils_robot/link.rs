@@ -6,6 +6,7 @@ use wasm_bindgen::prelude::*;
 
 use serde::{Serialize, Deserialize};
 use crate::utils::utils_console::{optima_print, PrintColor, PrintMode};
+use crate::utils::utils_robot::robot_ids::{JointId, LinkId};
 use crate::utils::utils_robot::urdf_link::URDFLink;
 
 /// A Link holds all necessary information about a robot link (specified by a robot URDF file)
@@ -71,6 +72,18 @@ impl Link {
     pub fn preceding_joint_idx(&self) -> Option<usize> {
         self.preceding_joint_idx
     }
+    pub fn link_id(&self) -> LinkId {
+        LinkId::new(self.link_idx)
+    }
+    pub fn preceding_link_id(&self) -> Option<LinkId> {
+        self.preceding_link_idx.map(LinkId::new)
+    }
+    pub fn children_link_ids(&self) -> Vec<LinkId> {
+        self.children_link_idxs.iter().map(|idx| LinkId::new(*idx)).collect()
+    }
+    pub fn preceding_joint_id(&self) -> Option<JointId> {
+        self.preceding_joint_idx.map(JointId::new)
+    }
     pub fn is_chain_base_link(&self) -> bool {
         self.is_chain_base_link
     }
@@ -141,4 +154,6 @@ impl Link {
 #[wasm_bindgen]
 impl Link {
 
-}
\ No newline at end of file
+}
+
+crate::impl_py_pickle!(Link);
@@ -5,3 +5,13 @@ pub mod joint;
 pub mod robot_module_utils;
 pub mod robot_set_module_utils;
 pub mod robot_set_link_specification;
+pub mod robot_joint_trajectory;
+pub mod robot_state_recorder;
+pub mod robot_joint_state_normalizer;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod robot_dataset_generator;
+#[cfg(all(feature = "parquet_export", not(target_arch = "wasm32")))]
+pub mod robot_state_log_parquet;
+pub mod urdf_validation;
+pub mod robot_import_options;
+pub mod robot_ids;
@@ -0,0 +1,291 @@
+use pyo3::*;
+
+use std::path::PathBuf;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_geometric_shape_module::{RobotGeometricShapeModule, RobotLinkShapeRepresentation, RobotShapeCollectionQuery};
+use crate::robot_modules::robot_joint_state_module::RobotJointStateType;
+use crate::utils::utils_console::{get_default_progress_bar, optima_print, PrintColor, PrintMode};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_files::optima_path::OptimaPath;
+use crate::utils::utils_robot::robot_joint_trajectory::RobotJointTrajectory;
+use crate::utils::utils_se3::optima_se3_pose::OptimaSE3PoseType;
+use crate::utils::utils_shape_geometry::geometric_shape::{LogCondition, StopCondition};
+use crate::utils::utils_traits::{ToAndFromJsonString, ToAndFromRonString};
+
+const DATASET_INFO_FILENAME: &str = "dataset_info.JSON";
+
+/// Generates datasets of (joint state, FK link poses, minimum self-distance, self-collision label)
+/// samples for learning pipelines, the same quantities the preprocessing sampling loop computes
+/// internally.
+///
+/// Samples are generated in shards of `shard_size` samples.  Each shard draws its joint states from
+/// its own random number generator, seeded from the generator seed and the shard index, so a shard's
+/// samples depend only on (seed, shard index) and not on how many threads compute them or which
+/// other shards are generated.  The samples within a shard are computed in parallel.
+///
+/// `generate_to_directory` streams shards to disk as `shard_<index>.jsonl` files, so datasets
+/// larger than memory can be generated, and records completed shards in a `dataset_info.JSON` file
+/// that is written after each shard.  Calling it again on the same directory with the same generator
+/// settings resumes by skipping completed shards.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct RobotDatasetGenerator {
+    robot_geometric_shape_module: RobotGeometricShapeModule,
+    robot_link_shape_representation: RobotLinkShapeRepresentation,
+    seed: u64,
+    shard_size: usize
+}
+impl RobotDatasetGenerator {
+    pub fn new(robot_geometric_shape_module: RobotGeometricShapeModule, robot_link_shape_representation: RobotLinkShapeRepresentation, seed: u64, shard_size: usize) -> Result<Self, OptimaError> {
+        if shard_size == 0 {
+            return Err(OptimaError::new_generic_error_str("Dataset shard size must be greater than 0.", file!(), line!()));
+        }
+        if robot_link_shape_representation != RobotLinkShapeRepresentation::Auto && !robot_geometric_shape_module.robot_link_shape_representation_is_loaded(&robot_link_shape_representation) {
+            return Err(OptimaError::new_generic_error_str(&format!("Robot link shape representation {:?} is not loaded.", robot_link_shape_representation), file!(), line!()));
+        }
+
+        Ok(Self {
+            robot_geometric_shape_module,
+            robot_link_shape_representation,
+            seed,
+            shard_size
+        })
+    }
+    /// Generates the samples of the given shard.
+    pub fn generate_shard(&self, shard_idx: usize) -> Result<Vec<RobotDatasetSample>, OptimaError> {
+        let robot_joint_state_module = self.robot_geometric_shape_module.robot_joint_state_module();
+        let mut rng = self.shard_rng(shard_idx);
+        let joint_states: Vec<_> = (0..self.shard_size).map(|_| robot_joint_state_module.sample_joint_state_with_rng(&RobotJointStateType::DOF, &mut rng)).collect();
+
+        let samples: Result<Vec<RobotDatasetSample>, OptimaError> = joint_states.par_iter().map(|joint_state| {
+            let fk_res = self.robot_geometric_shape_module.robot_kinematics_module().compute_fk(joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+            let mut link_poses = vec![];
+            for link_entry in fk_res.link_entries() {
+                link_poses.push(link_entry.pose().as_ref().map(|pose| {
+                    let isometry = pose.to_nalgebra_isometry();
+                    let t = &isometry.translation.vector;
+                    let q = &isometry.rotation;
+                    [t[0], t[1], t[2], q.i, q.j, q.k, q.w]
+                }));
+            }
+
+            let input = RobotShapeCollectionQuery::Distance { robot_joint_state: joint_state, inclusion_list: &None };
+            let res = self.robot_geometric_shape_module.shape_collection_query(&input, self.robot_link_shape_representation.clone(), StopCondition::None, LogCondition::Intersection, false)?;
+
+            Ok(RobotDatasetSample {
+                joint_state: joint_state.joint_state().iter().cloned().collect(),
+                link_poses,
+                min_self_distance: res.minimum_distance(),
+                in_collision: res.minimum_distance() <= 0.0
+            })
+        }).collect();
+
+        return samples;
+    }
+    /// Generates shards until at least num_samples samples are written to the given directory (the
+    /// last shard is written in full, so the dataset size is rounded up to a multiple of the shard
+    /// size).  Completed shards from a previous call with the same settings are skipped.
+    pub fn generate_to_directory(&self, directory: &OptimaPath, num_samples: usize) -> Result<RobotDatasetInfo, OptimaError> {
+        let num_shards = (num_samples + self.shard_size - 1) / self.shard_size;
+        let mut info = self.load_or_create_dataset_info(directory)?;
+
+        let mut pb = get_default_progress_bar(num_shards);
+        for shard_idx in 0..num_shards {
+            pb.set(shard_idx as u64);
+            if info.completed_shards.contains(&shard_idx) { continue; }
+
+            let samples = self.generate_shard(shard_idx)?;
+            let mut out_string = String::new();
+            for sample in &samples { out_string += &format!("{}\n", sample.to_json_string()); }
+            let mut shard_path = directory.clone();
+            shard_path.append(&RobotDatasetInfo::shard_filename(shard_idx));
+            shard_path.write_string_to_file(&out_string)?;
+
+            info.completed_shards.push(shard_idx);
+            info.completed_shards.sort();
+            Self::save_dataset_info(directory, &info)?;
+        }
+        pb.finish();
+        println!();
+
+        optima_print(&format!("Dataset with {} shards of {} samples written to {}.", num_shards, self.shard_size, directory.to_string()), PrintMode::Println, PrintColor::Blue, true);
+        Ok(info)
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn shard_size(&self) -> usize {
+        self.shard_size
+    }
+    pub fn robot_link_shape_representation(&self) -> &RobotLinkShapeRepresentation {
+        &self.robot_link_shape_representation
+    }
+    fn shard_rng(&self, shard_idx: usize) -> ChaCha8Rng {
+        let mut seed_bytes = [0u8; 32];
+        seed_bytes[..8].copy_from_slice(&self.seed.to_le_bytes());
+        seed_bytes[8..16].copy_from_slice(&(shard_idx as u64).to_le_bytes());
+        return ChaCha8Rng::from_seed(seed_bytes);
+    }
+    fn new_dataset_info(&self) -> RobotDatasetInfo {
+        let robot_joint_state_module = self.robot_geometric_shape_module.robot_joint_state_module();
+        let link_names = self.robot_geometric_shape_module.robot_kinematics_module().robot_configuration_module().robot_model_module().links().iter().map(|l| l.name().to_string()).collect();
+        RobotDatasetInfo {
+            robot_name: robot_joint_state_module.robot_name().to_string(),
+            joint_names: RobotJointTrajectory::dof_joint_axis_names(robot_joint_state_module),
+            link_names,
+            robot_link_shape_representation: self.robot_link_shape_representation.clone(),
+            seed: self.seed,
+            shard_size: self.shard_size,
+            completed_shards: vec![]
+        }
+    }
+    fn load_or_create_dataset_info(&self, directory: &OptimaPath) -> Result<RobotDatasetInfo, OptimaError> {
+        let new_info = self.new_dataset_info();
+        let mut path = directory.clone();
+        path.append(DATASET_INFO_FILENAME);
+        if !path.exists() { return Ok(new_info); }
+
+        let info: RobotDatasetInfo = path.load_object_from_json_file()?;
+        if !info.same_settings(&new_info) {
+            return Err(OptimaError::new_generic_error_str(&format!("Directory {} holds a dataset generated with different settings.  Use an empty directory for a new dataset.", directory.to_string()), file!(), line!()));
+        }
+        Ok(info)
+    }
+    fn save_dataset_info(directory: &OptimaPath, info: &RobotDatasetInfo) -> Result<(), OptimaError> {
+        let mut path = directory.clone();
+        path.append(DATASET_INFO_FILENAME);
+        return path.save_object_to_file_as_json(info);
+    }
+}
+
+/// Python implementations.
+#[pymethods]
+impl RobotDatasetGenerator {
+    #[new]
+    #[args(robot_link_shape_representation = "\"Cubes\"", seed = "0", shard_size = "10000")]
+    pub fn new_py(robot_geometric_shape_module: &RobotGeometricShapeModule, robot_link_shape_representation: &str, seed: u64, shard_size: usize) -> Self {
+        Self::new(robot_geometric_shape_module.clone(), RobotLinkShapeRepresentation::from_ron_string(robot_link_shape_representation).expect("error"), seed, shard_size).expect("error")
+    }
+    pub fn generate_shard_py(&self, shard_idx: usize) -> Vec<RobotDatasetSample> {
+        self.generate_shard(shard_idx).expect("error")
+    }
+    pub fn generate_to_directory_py(&self, directory: &str, num_samples: usize) -> RobotDatasetInfo {
+        let directory = OptimaPath::Path(PathBuf::from(directory));
+        self.generate_to_directory(&directory, num_samples).expect("error")
+    }
+}
+
+/// A single dataset sample.  joint_state is a DOF joint state.  link_poses holds one entry per link
+/// (`None` for links without a pose, e.g., links that are not present in the configuration), each
+/// given as \[x, y, z, qx, qy, qz, qw\]: the translation followed by the unit quaternion.
+/// min_self_distance is the minimum distance over all non-skipped link pairs in the generator's
+/// shape representation (negative for penetrating pairs, infinite if no pair is checked), and
+/// in_collision is true if it is less than or equal to 0.
+#[pyclass]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobotDatasetSample {
+    joint_state: Vec<f64>,
+    link_poses: Vec<Option<[f64; 7]>>,
+    min_self_distance: f64,
+    in_collision: bool
+}
+impl RobotDatasetSample {
+    pub fn joint_state(&self) -> &Vec<f64> {
+        &self.joint_state
+    }
+    pub fn link_poses(&self) -> &Vec<Option<[f64; 7]>> {
+        &self.link_poses
+    }
+    pub fn min_self_distance(&self) -> f64 {
+        self.min_self_distance
+    }
+    pub fn in_collision(&self) -> bool {
+        self.in_collision
+    }
+}
+
+/// Python implementations.
+#[pymethods]
+impl RobotDatasetSample {
+    pub fn joint_state_py(&self) -> Vec<f64> {
+        self.joint_state.clone()
+    }
+    pub fn link_poses_py(&self) -> Vec<Option<[f64; 7]>> {
+        self.link_poses.clone()
+    }
+    pub fn min_self_distance_py(&self) -> f64 {
+        self.min_self_distance
+    }
+    pub fn in_collision_py(&self) -> bool {
+        self.in_collision
+    }
+}
+
+/// Describes a dataset directory written by `RobotDatasetGenerator::generate_to_directory`:
+/// the generator settings, the names of the joint state entries and links of each sample, and the
+/// indices of the shards written so far.
+#[pyclass]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobotDatasetInfo {
+    robot_name: String,
+    joint_names: Vec<String>,
+    link_names: Vec<String>,
+    robot_link_shape_representation: RobotLinkShapeRepresentation,
+    seed: u64,
+    shard_size: usize,
+    completed_shards: Vec<usize>
+}
+impl RobotDatasetInfo {
+    pub fn shard_filename(shard_idx: usize) -> String {
+        return format!("shard_{:05}.jsonl", shard_idx);
+    }
+    pub fn robot_name(&self) -> &str {
+        &self.robot_name
+    }
+    pub fn joint_names(&self) -> &Vec<String> {
+        &self.joint_names
+    }
+    pub fn link_names(&self) -> &Vec<String> {
+        &self.link_names
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn shard_size(&self) -> usize {
+        self.shard_size
+    }
+    pub fn completed_shards(&self) -> &Vec<usize> {
+        &self.completed_shards
+    }
+    fn same_settings(&self, other: &RobotDatasetInfo) -> bool {
+        return self.robot_name == other.robot_name &&
+            self.joint_names == other.joint_names &&
+            self.link_names == other.link_names &&
+            self.robot_link_shape_representation == other.robot_link_shape_representation &&
+            self.seed == other.seed &&
+            self.shard_size == other.shard_size;
+    }
+}
+
+/// Python implementations.
+#[pymethods]
+impl RobotDatasetInfo {
+    pub fn joint_names_py(&self) -> Vec<String> {
+        self.joint_names.clone()
+    }
+    pub fn link_names_py(&self) -> Vec<String> {
+        self.link_names.clone()
+    }
+    pub fn completed_shards_py(&self) -> Vec<usize> {
+        self.completed_shards.clone()
+    }
+    pub fn shard_filenames_py(&self) -> Vec<String> {
+        self.completed_shards.iter().map(|i| Self::shard_filename(*i)).collect()
+    }
+}
+
+crate::impl_py_pickle!(RobotDatasetSample);
+crate::impl_py_pickle!(RobotDatasetInfo);
@@ -0,0 +1,50 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+/// Generates a typed index newtype around a usize.  A newtype is only constructed explicitly from
+/// a raw index (via `new`), so two different kinds of indices cannot be mixed up, and it converts
+/// back to a usize via `idx` or `From`.  Serialized as a bare integer.
+macro_rules! optima_index_newtype {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(usize);
+        impl $name {
+            pub fn new(idx: usize) -> Self {
+                Self(idx)
+            }
+            pub fn idx(&self) -> usize {
+                self.0
+            }
+        }
+        impl From<$name> for usize {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}({})", stringify!($name), self.0)
+            }
+        }
+    };
+}
+
+optima_index_newtype!(
+    /// Index of a link in a `RobotModelModule` (i.e., the link's position in `links()`).
+    LinkId
+);
+optima_index_newtype!(
+    /// Index of a joint in a `RobotModelModule` (i.e., the joint's position in `joints()`).
+    JointId
+);
+optima_index_newtype!(
+    /// Index of a value in a DOF `RobotJointState` (i.e., a position in
+    /// `RobotJointStateModule::ordered_dof_joint_axes()`).
+    DofIdx
+);
+optima_index_newtype!(
+    /// Index of a shape in a `ShapeCollection` (i.e., the shape's position in `shapes()`).
+    ShapeIdx
+);
@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+use nalgebra::{Isometry3, Translation3, UnitQuaternion};
+use serde::{Serialize, Deserialize};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_robot::urdf_joint::URDFJoint;
+use crate::utils::utils_robot::urdf_link::URDFLink;
+
+/// Unit and axis conventions of an imported robot description, used to convert it to Optima's
+/// conventions (meters, radians, z up) on import.  Options are applied to the parsed links and
+/// joints (see `apply`), so they work the same regardless of the source format.
+///
+/// For robots in the optima_assets directory, options are read from an `import_options.json` file
+/// next to the robot's URDF (see `OptimaAssetLocation::RobotImportOptions`) and applied when the
+/// model module is generated from the URDF.  Since the generated model module is saved as
+/// preprocessed data, robots have to be preprocessed again after their import options change.
+/// Options can also be given directly to `RobotModelBuilder::set_import_options`.
+///
+/// Mesh files themselves are not rescaled; length scaling is recorded in the links' mesh scales.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobotImportOptions {
+    /// Multiplies all lengths, e.g., 0.001 for a description in millimeters.
+    #[serde(default = "RobotImportOptions::default_length_unit_scale")]
+    length_unit_scale: f64,
+    #[serde(default)]
+    up_axis: ImportUpAxis,
+    /// Unit of the limits (and velocity limits) of revolute and continuous joints.  Origin
+    /// orientations are always radians, as in URDF.
+    #[serde(default)]
+    angle_unit: ImportAngleUnit
+}
+impl Default for RobotImportOptions {
+    fn default() -> Self {
+        Self {
+            length_unit_scale: 1.0,
+            up_axis: ImportUpAxis::default(),
+            angle_unit: ImportAngleUnit::default()
+        }
+    }
+}
+impl RobotImportOptions {
+    pub fn new(length_unit_scale: f64, up_axis: ImportUpAxis, angle_unit: ImportAngleUnit) -> Result<Self, OptimaError> {
+        if !(length_unit_scale > 0.0) || !length_unit_scale.is_finite() {
+            return Err(OptimaError::new_generic_error_str(&format!("Length unit scale must be positive and finite (given {}).", length_unit_scale), file!(), line!()));
+        }
+        Ok(Self {
+            length_unit_scale,
+            up_axis,
+            angle_unit
+        })
+    }
+    /// Returns true if applying these options would not change anything.
+    pub fn is_identity(&self) -> bool {
+        return self.length_unit_scale == 1.0 && self.up_axis == ImportUpAxis::Z && self.angle_unit == ImportAngleUnit::Radians;
+    }
+    /// Converts the given links and joints in place.  Lengths are scaled first, then angular joint
+    /// values are converted, and finally the model is rotated about its root link(s) such that the
+    /// up axis becomes z.
+    pub fn apply(&self, urdf_links: &mut Vec<URDFLink>, urdf_joints: &mut Vec<URDFJoint>) {
+        if self.length_unit_scale != 1.0 {
+            for l in urdf_links.iter_mut() { l.scale_lengths(self.length_unit_scale); }
+            for j in urdf_joints.iter_mut() { j.scale_lengths(self.length_unit_scale); }
+        }
+
+        if self.angle_unit == ImportAngleUnit::Degrees {
+            for j in urdf_joints.iter_mut() { j.convert_angular_values_from_degrees(); }
+        }
+
+        let rotation = self.up_axis.rotation_to_z_up();
+        if rotation.angle() != 0.0 {
+            let transform = Isometry3::from_parts(Translation3::identity(), rotation);
+            let child_links: HashSet<String> = urdf_joints.iter().map(|j| j.child_link().to_string()).collect();
+            let root_links: HashSet<String> = urdf_links.iter().map(|l| l.name().to_string()).filter(|n| !child_links.contains(n)).collect();
+
+            for l in urdf_links.iter_mut() {
+                if root_links.contains(l.name()) { l.premultiply_origins(&transform); }
+            }
+            for j in urdf_joints.iter_mut() {
+                if root_links.contains(j.parent_link()) { j.premultiply_origin(&transform); }
+            }
+        }
+    }
+    pub fn length_unit_scale(&self) -> f64 {
+        self.length_unit_scale
+    }
+    pub fn up_axis(&self) -> &ImportUpAxis {
+        &self.up_axis
+    }
+    pub fn angle_unit(&self) -> &ImportAngleUnit {
+        &self.angle_unit
+    }
+    fn default_length_unit_scale() -> f64 { 1.0 }
+}
+
+/// The axis that points up in an imported robot description.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportUpAxis {
+    X,
+    Y,
+    Z
+}
+impl Default for ImportUpAxis {
+    fn default() -> Self { Self::Z }
+}
+impl ImportUpAxis {
+    /// The rotation that maps this axis onto z.
+    pub fn rotation_to_z_up(&self) -> UnitQuaternion<f64> {
+        return match self {
+            ImportUpAxis::X => { UnitQuaternion::from_euler_angles(0.0, -std::f64::consts::FRAC_PI_2, 0.0) }
+            ImportUpAxis::Y => { UnitQuaternion::from_euler_angles(std::f64::consts::FRAC_PI_2, 0.0, 0.0) }
+            ImportUpAxis::Z => { UnitQuaternion::identity() }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportAngleUnit {
+    Radians,
+    Degrees
+}
+impl Default for ImportAngleUnit {
+    fn default() -> Self { Self::Radians }
+}
@@ -0,0 +1,214 @@
+#[cfg(not(target_arch = "wasm32"))]
+use pyo3::*;
+
+use std::f64::consts::PI;
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateModule, RobotJointStateType};
+use crate::utils::utils_errors::OptimaError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils::utils_nalgebra::conversions::NalgebraConversions;
+use crate::utils::utils_robot::joint::JointAxisPrimitiveType;
+use crate::utils::utils_robot::robot_joint_trajectory::RobotJointTrajectory;
+use crate::utils::utils_robot::urdf_joint::JointTypeWrapper;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils::utils_traits::ToAndFromRonString;
+
+/// How `RobotJointStateNormalizer` encodes the axes of continuous joints.
+/// - `UnitBox`: like any other axis, mapped linearly from its bounds to \[-1, 1\].  Continuous joints
+/// without meaningful bounds (i.e., upper <= lower, which is how they are read from URDFs that omit
+/// limits) are wrapped to \[-pi, pi) and mapped from there.
+/// - `SinCos`: as the pair (sin(q), cos(q)), so angles that differ by a full turn map to the same
+/// encoding and there is no discontinuity at +/- pi.  Each continuous axis takes two entries.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContinuousJointEncoding {
+    UnitBox,
+    SinCos
+}
+
+/// Maps DOF joint states to and from a normalized encoding suitable as input or output of learned
+/// models: every axis is mapped linearly from its joint limits to \[-1, 1\] (the unit box), with
+/// continuous joints optionally encoded as sin/cos pairs (see `ContinuousJointEncoding`).
+///
+/// Ball joint quaternion components are already in \[-1, 1\], so they are passed through unchanged.
+/// Axes with zero-width bounds map to 0.  States outside the joint limits map outside the unit box
+/// rather than being clamped, so normalization is exactly invertible; use
+/// `RobotJointStateModule::clamp_joint_state_to_bounds` on denormalized states if a model's output
+/// may leave the box.
+#[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
+#[cfg_attr(target_arch = "wasm32", derive(Clone, Debug, Serialize, Deserialize))]
+pub struct RobotJointStateNormalizer {
+    axis_encodings: Vec<NormalizedAxisEncoding>,
+    normalized_names: Vec<String>,
+    continuous_joint_encoding: ContinuousJointEncoding
+}
+impl RobotJointStateNormalizer {
+    pub fn new(robot_joint_state_module: &RobotJointStateModule, continuous_joint_encoding: ContinuousJointEncoding) -> Self {
+        let joints = robot_joint_state_module.robot_configuration_module().robot_model_module().joints();
+        let dof_names = RobotJointTrajectory::dof_joint_axis_names(robot_joint_state_module);
+        let bounds = robot_joint_state_module.get_joint_state_bounds(&RobotJointStateType::DOF);
+
+        let mut axis_encodings = vec![];
+        let mut normalized_names = vec![];
+        for (i, axis) in robot_joint_state_module.ordered_dof_joint_axes().iter().enumerate() {
+            let is_continuous = matches!(joints[axis.joint_idx()].urdf_joint().joint_type(), JointTypeWrapper::Continuous);
+            let (lower, upper) = bounds[i];
+
+            let encoding = if axis.axis_primitive_type() == &JointAxisPrimitiveType::QuaternionComponent {
+                NormalizedAxisEncoding::Passthrough
+            } else if is_continuous && continuous_joint_encoding == ContinuousJointEncoding::SinCos {
+                NormalizedAxisEncoding::SinCos
+            } else if is_continuous && !(upper > lower) {
+                NormalizedAxisEncoding::WrappedAngle
+            } else {
+                NormalizedAxisEncoding::Linear { lower, upper }
+            };
+
+            match &encoding {
+                NormalizedAxisEncoding::SinCos => {
+                    normalized_names.push(format!("{}_sin", dof_names[i]));
+                    normalized_names.push(format!("{}_cos", dof_names[i]));
+                }
+                _ => { normalized_names.push(dof_names[i].clone()); }
+            }
+            axis_encodings.push(encoding);
+        }
+
+        Self {
+            axis_encodings,
+            normalized_names,
+            continuous_joint_encoding
+        }
+    }
+    /// Normalizes a DOF joint state.
+    pub fn normalize(&self, dof_state: &DVector<f64>) -> Result<DVector<f64>, OptimaError> {
+        if dof_state.len() != self.axis_encodings.len() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("normalize", dof_state.len(), self.axis_encodings.len(), file!(), line!()));
+        }
+
+        let mut out_vec = Vec::with_capacity(self.normalized_names.len());
+        for (encoding, q) in self.axis_encodings.iter().zip(dof_state.iter()) {
+            match encoding {
+                NormalizedAxisEncoding::Linear { lower, upper } => {
+                    let width = upper - lower;
+                    out_vec.push(if width > 0.0 && width.is_finite() { 2.0 * (q - lower) / width - 1.0 } else { 0.0 });
+                }
+                NormalizedAxisEncoding::WrappedAngle => {
+                    out_vec.push(Self::wrap_angle(*q) / PI);
+                }
+                NormalizedAxisEncoding::SinCos => {
+                    out_vec.push(q.sin());
+                    out_vec.push(q.cos());
+                }
+                NormalizedAxisEncoding::Passthrough => {
+                    out_vec.push(*q);
+                }
+            }
+        }
+
+        Ok(DVector::from_vec(out_vec))
+    }
+    /// Maps a normalized vector back to a DOF joint state.  Sin/cos pairs need not be unit length
+    /// (e.g., when predicted by a model); the angle is recovered with atan2.  Continuous joint angles
+    /// are returned in \[-pi, pi\].
+    pub fn denormalize(&self, normalized: &DVector<f64>) -> Result<DVector<f64>, OptimaError> {
+        if normalized.len() != self.normalized_names.len() {
+            return Err(OptimaError::new_robot_state_vec_wrong_size_error("denormalize", normalized.len(), self.normalized_names.len(), file!(), line!()));
+        }
+
+        let mut out_vec = Vec::with_capacity(self.axis_encodings.len());
+        let mut idx = 0;
+        for encoding in &self.axis_encodings {
+            match encoding {
+                NormalizedAxisEncoding::Linear { lower, upper } => {
+                    let width = upper - lower;
+                    out_vec.push(if width > 0.0 && width.is_finite() { lower + 0.5 * (normalized[idx] + 1.0) * width } else { *lower });
+                    idx += 1;
+                }
+                NormalizedAxisEncoding::WrappedAngle => {
+                    out_vec.push(normalized[idx] * PI);
+                    idx += 1;
+                }
+                NormalizedAxisEncoding::SinCos => {
+                    out_vec.push(normalized[idx].atan2(normalized[idx + 1]));
+                    idx += 2;
+                }
+                NormalizedAxisEncoding::Passthrough => {
+                    out_vec.push(normalized[idx]);
+                    idx += 1;
+                }
+            }
+        }
+
+        Ok(DVector::from_vec(out_vec))
+    }
+    /// Same as `normalize`, for a joint state of either type (full states are converted to DOF states
+    /// first).
+    pub fn normalize_robot_joint_state(&self, robot_joint_state_module: &RobotJointStateModule, robot_joint_state: &RobotJointState) -> Result<DVector<f64>, OptimaError> {
+        let dof_state = robot_joint_state_module.convert_joint_state_to_dof_state(robot_joint_state)?;
+        return self.normalize(dof_state.joint_state());
+    }
+    /// Same as `denormalize`, returning a DOF `RobotJointState`.
+    pub fn denormalize_to_robot_joint_state(&self, robot_joint_state_module: &RobotJointStateModule, normalized: &DVector<f64>) -> Result<RobotJointState, OptimaError> {
+        let dof_state = self.denormalize(normalized)?;
+        return robot_joint_state_module.spawn_robot_joint_state(dof_state, RobotJointStateType::DOF);
+    }
+    /// Length of normalized vectors.  This is the number of DOFs, plus one for each continuous axis
+    /// encoded as a sin/cos pair.
+    pub fn normalized_dim(&self) -> usize {
+        self.normalized_names.len()
+    }
+    /// Name of each entry of a normalized vector: the DOF axis name (see
+    /// `RobotJointTrajectory::dof_joint_axis_names`), with `_sin` and `_cos` suffixes for sin/cos
+    /// pairs.
+    pub fn normalized_names(&self) -> &Vec<String> {
+        &self.normalized_names
+    }
+    pub fn continuous_joint_encoding(&self) -> &ContinuousJointEncoding {
+        &self.continuous_joint_encoding
+    }
+    fn wrap_angle(q: f64) -> f64 {
+        return (q + PI).rem_euclid(2.0 * PI) - PI;
+    }
+}
+
+/// Python implementations.
+#[cfg(not(target_arch = "wasm32"))]
+#[pymethods]
+impl RobotJointStateNormalizer {
+    #[new]
+    #[args(continuous_joint_encoding = "\"UnitBox\"")]
+    pub fn new_py(robot_joint_state_module: &RobotJointStateModule, continuous_joint_encoding: &str) -> Self {
+        Self::new(robot_joint_state_module, ContinuousJointEncoding::from_ron_string(continuous_joint_encoding).expect("error"))
+    }
+    pub fn normalize_py(&self, dof_state: Vec<f64>) -> Vec<f64> {
+        let res = self.normalize(&NalgebraConversions::vec_to_dvector(&dof_state)).expect("error");
+        NalgebraConversions::dvector_to_vec(&res)
+    }
+    pub fn denormalize_py(&self, normalized: Vec<f64>) -> Vec<f64> {
+        let res = self.denormalize(&NalgebraConversions::vec_to_dvector(&normalized)).expect("error");
+        NalgebraConversions::dvector_to_vec(&res)
+    }
+    pub fn normalize_batch_py(&self, dof_states: Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+        dof_states.into_iter().map(|s| self.normalize_py(s)).collect()
+    }
+    pub fn denormalize_batch_py(&self, normalized: Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+        normalized.into_iter().map(|s| self.denormalize_py(s)).collect()
+    }
+    pub fn normalized_dim_py(&self) -> usize {
+        self.normalized_dim()
+    }
+    pub fn normalized_names_py(&self) -> Vec<String> {
+        self.normalized_names.clone()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum NormalizedAxisEncoding {
+    Linear { lower: f64, upper: f64 },
+    WrappedAngle,
+    SinCos,
+    Passthrough
+}
+
+crate::impl_py_pickle!(RobotJointStateNormalizer);
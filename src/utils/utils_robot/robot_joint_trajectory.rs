@@ -0,0 +1,514 @@
+#[cfg(not(target_arch = "wasm32"))]
+use pyo3::*;
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use std::path::PathBuf;
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateModule, RobotJointStateRemapper, RobotJointStateType};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_files::optima_path::OptimaPath;
+use crate::utils::utils_nalgebra::conversions::NalgebraConversions;
+use crate::utils::utils_traits::{ToAndFromJsonString, ToAndFromRonString};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils::utils_trajectory::time_dilation::{TimeDilationMode, TrajectoryTimeDilation};
+
+/// A time-parameterized sequence of joint positions for a single robot.  The joint positions in each
+/// waypoint are ordered according to `joint_names`, which need not match the library's canonical
+/// DOF ordering (e.g., when a trajectory is imported from another tool).  A `RobotJointTrajectory`
+/// can be converted to and from `RobotJointState` objects via `new_from_robot_joint_states` and
+/// `to_robot_joint_states`.
+///
+/// Trajectories can be imported and exported in the formats listed in `RobotJointTrajectoryFileFormat`.
+/// The native JSON format is the direct serialization of this struct:
+///
+/// ```text
+/// {
+///     "robot_name": "ur5",
+///     "joint_names": ["shoulder_pan_joint", "shoulder_lift_joint", ...],
+///     "waypoints": [
+///         { "time_from_start": 0.0, "positions": [0.0, -1.57, ...] },
+///         { "time_from_start": 0.5, "positions": [0.1, -1.50, ...] }
+///     ]
+/// }
+/// ```
+///
+/// `time_from_start` is given in seconds and must be non-decreasing over the waypoints, and each
+/// `positions` list must have the same length as `joint_names`.  Joint axes of joints with more than
+/// one axis are named `<joint_name>_<joint_sub_dof_idx>`.
+#[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen, derive(Clone, Debug, Serialize, Deserialize))]
+pub struct RobotJointTrajectory {
+    robot_name: String,
+    joint_names: Vec<String>,
+    waypoints: Vec<RobotJointTrajectoryWaypoint>
+}
+impl RobotJointTrajectory {
+    pub fn new(robot_name: &str, joint_names: Vec<String>, waypoints: Vec<RobotJointTrajectoryWaypoint>) -> Result<Self, OptimaError> {
+        let out_self = Self {
+            robot_name: robot_name.to_string(),
+            joint_names,
+            waypoints
+        };
+        out_self.validate()?;
+        Ok(out_self)
+    }
+    /// Creates a trajectory from a list of joint states and the time (in seconds) at which each joint
+    /// state should be reached.  The trajectory is expressed in the canonical DOF ordering of the
+    /// given `RobotJointStateModule`.
+    pub fn new_from_robot_joint_states(robot_joint_state_module: &RobotJointStateModule, robot_joint_states: &Vec<RobotJointState>, times_from_start: &Vec<f64>) -> Result<Self, OptimaError> {
+        if robot_joint_states.len() != times_from_start.len() {
+            return Err(OptimaError::new_generic_error_str(&format!("Number of joint states ({}) does not match number of times ({}).", robot_joint_states.len(), times_from_start.len()), file!(), line!()));
+        }
+
+        let mut waypoints = vec![];
+        for (i, robot_joint_state) in robot_joint_states.iter().enumerate() {
+            let dof_state = robot_joint_state_module.convert_joint_state_to_dof_state(robot_joint_state)?;
+            waypoints.push(RobotJointTrajectoryWaypoint::new(times_from_start[i], NalgebraConversions::dvector_to_vec(dof_state.joint_state())));
+        }
+
+        return Self::new(robot_joint_state_module.robot_name(), Self::dof_joint_axis_names(robot_joint_state_module), waypoints);
+    }
+    /// Converts all waypoints to `RobotJointState` objects of the given type.  If the trajectory's joint
+    /// names do not match the canonical DOF ordering of the given module, the positions are remapped by
+    /// joint name via a `RobotJointStateRemapper`.
+    pub fn to_robot_joint_states(&self, robot_joint_state_module: &RobotJointStateModule, t: &RobotJointStateType) -> Result<Vec<RobotJointState>, OptimaError> {
+        if self.robot_name != robot_joint_state_module.robot_name() {
+            return Err(OptimaError::new_generic_error_str(&format!("Trajectory for robot {} cannot be converted to joint states of robot {}.", self.robot_name, robot_joint_state_module.robot_name()), file!(), line!()));
+        }
+
+        let mut out_vec = vec![];
+
+        if self.joint_names == Self::dof_joint_axis_names(robot_joint_state_module) {
+            for waypoint in &self.waypoints {
+                let dof_state = robot_joint_state_module.spawn_robot_joint_state(NalgebraConversions::vec_to_dvector(&waypoint.positions), RobotJointStateType::DOF)?;
+                let joint_state = match t {
+                    RobotJointStateType::DOF => { dof_state }
+                    RobotJointStateType::Full => { robot_joint_state_module.convert_joint_state_to_full_state(&dof_state)? }
+                };
+                out_vec.push(joint_state);
+            }
+        } else {
+            let remapper = RobotJointStateRemapper::new(robot_joint_state_module, &self.joint_names)?;
+            for waypoint in &self.waypoints {
+                out_vec.push(remapper.external_vector_to_robot_joint_state(&NalgebraConversions::vec_to_dvector(&waypoint.positions), None, t)?);
+            }
+        }
+
+        Ok(out_vec)
+    }
+    /// Returns the trajectory's joint names as they would be given for the canonical DOF ordering of the
+    /// given module.  Names follow `RobotJointStateModule::joint_axis_name`, so they can be resolved by a
+    /// `RobotJointStateRemapper`.
+    pub fn dof_joint_axis_names(robot_joint_state_module: &RobotJointStateModule) -> Vec<String> {
+        let mut out_vec = vec![];
+
+        for (full_state_idx, joint_axis) in robot_joint_state_module.ordered_joint_axes().iter().enumerate() {
+            if joint_axis.is_dof() {
+                out_vec.push(robot_joint_state_module.joint_axis_name(full_state_idx).expect("error"));
+            }
+        }
+
+        out_vec
+    }
+    pub fn to_string_with_format(&self, format: &RobotJointTrajectoryFileFormat) -> Result<String, OptimaError> {
+        return match format {
+            RobotJointTrajectoryFileFormat::NativeJSON => { Ok(self.to_json_string()) }
+            RobotJointTrajectoryFileFormat::MoveItDisplayTrajectoryJSON => { Ok(self.to_moveit_display_trajectory_json_string()) }
+            RobotJointTrajectoryFileFormat::CSV => { Ok(self.to_csv_string()) }
+        }
+    }
+    /// Parses a trajectory from a string in the given format.  The CSV format does not encode a robot
+    /// name, so robot_name must be given for that format; it is otherwise used as a fallback when the
+    /// file does not include a robot name.
+    pub fn from_string_with_format(s: &str, format: &RobotJointTrajectoryFileFormat, robot_name: Option<&str>) -> Result<Self, OptimaError> {
+        return match format {
+            RobotJointTrajectoryFileFormat::NativeJSON => {
+                let out_self = Self::from_json_string(s)?;
+                out_self.validate()?;
+                Ok(out_self)
+            }
+            RobotJointTrajectoryFileFormat::MoveItDisplayTrajectoryJSON => { Self::from_moveit_display_trajectory_json_string(s, robot_name) }
+            RobotJointTrajectoryFileFormat::CSV => {
+                match robot_name {
+                    None => { Err(OptimaError::new_generic_error_str("A robot name must be given when loading a trajectory from CSV.", file!(), line!())) }
+                    Some(robot_name) => { Self::from_csv_string(s, robot_name) }
+                }
+            }
+        }
+    }
+    pub fn save_to_file(&self, path: &OptimaPath, format: &RobotJointTrajectoryFileFormat) -> Result<(), OptimaError> {
+        let s = self.to_string_with_format(format)?;
+        return path.write_string_to_file(&s);
+    }
+    /// Loads a trajectory from a file.  If format is None, the format is inferred from the file
+    /// extension; for `.json` files, the native JSON format is tried first, followed by the MoveIt
+    /// DisplayTrajectory format.
+    pub fn load_from_file(path: &OptimaPath, format: Option<&RobotJointTrajectoryFileFormat>, robot_name: Option<&str>) -> Result<Self, OptimaError> {
+        let s = path.read_file_contents_to_string()?;
+        return match format {
+            Some(format) => { Self::from_string_with_format(&s, format, robot_name) }
+            None => {
+                let extension = path.extension().unwrap_or("".to_string()).to_lowercase();
+                if extension == "csv" {
+                    Self::from_string_with_format(&s, &RobotJointTrajectoryFileFormat::CSV, robot_name)
+                } else if extension == "json" {
+                    let native = Self::from_string_with_format(&s, &RobotJointTrajectoryFileFormat::NativeJSON, robot_name);
+                    match native {
+                        Ok(native) => { Ok(native) }
+                        Err(_) => { Self::from_string_with_format(&s, &RobotJointTrajectoryFileFormat::MoveItDisplayTrajectoryJSON, robot_name) }
+                    }
+                } else {
+                    Err(OptimaError::new_generic_error_str(&format!("Could not infer trajectory file format from extension {:?}.", extension), file!(), line!()))
+                }
+            }
+        }
+    }
+    fn to_csv_string(&self) -> String {
+        let mut out_string = "time".to_string();
+        for joint_name in &self.joint_names { out_string += &format!(",{}", joint_name); }
+        out_string += "\n";
+
+        for waypoint in &self.waypoints {
+            out_string += &format!("{}", waypoint.time_from_start);
+            for position in &waypoint.positions { out_string += &format!(",{}", position); }
+            out_string += "\n";
+        }
+
+        out_string
+    }
+    fn from_csv_string(s: &str, robot_name: &str) -> Result<Self, OptimaError> {
+        let mut lines = s.lines().map(|l| l.trim()).filter(|l| !l.is_empty());
+
+        let header = match lines.next() {
+            None => { return Err(OptimaError::new_generic_error_str("Trajectory CSV string is empty.", file!(), line!())); }
+            Some(header) => { header }
+        };
+        let header_entries: Vec<&str> = header.split(',').map(|e| e.trim()).collect();
+        if header_entries[0] != "time" {
+            return Err(OptimaError::new_generic_error_str("The first column of a trajectory CSV must be named \"time\".", file!(), line!()));
+        }
+        let joint_names: Vec<String> = header_entries[1..].iter().map(|e| e.to_string()).collect();
+
+        let mut waypoints = vec![];
+        for (line_idx, line) in lines.enumerate() {
+            let mut values = vec![];
+            for entry in line.split(',') {
+                let value = entry.trim().parse::<f64>();
+                match value {
+                    Ok(value) => { values.push(value); }
+                    Err(_) => { return Err(OptimaError::new_generic_error_str(&format!("Could not parse value {:?} on row {} of trajectory CSV.", entry, line_idx + 1), file!(), line!())); }
+                }
+            }
+            if values.len() != header_entries.len() {
+                return Err(OptimaError::new_generic_error_str(&format!("Row {} of trajectory CSV has {} values, but the header has {} columns.", line_idx + 1, values.len(), header_entries.len()), file!(), line!()));
+            }
+            waypoints.push(RobotJointTrajectoryWaypoint::new(values[0], values[1..].to_vec()));
+        }
+
+        return Self::new(robot_name, joint_names, waypoints);
+    }
+    fn to_moveit_display_trajectory_json_string(&self) -> String {
+        let mut points = vec![];
+        for waypoint in &self.waypoints {
+            points.push(MoveItJointTrajectoryPoint {
+                positions: waypoint.positions.clone(),
+                velocities: vec![],
+                accelerations: vec![],
+                effort: vec![],
+                time_from_start: MoveItDuration::new_from_seconds(waypoint.time_from_start)
+            });
+        }
+
+        let first_positions = match self.waypoints.first() {
+            None => { vec![] }
+            Some(waypoint) => { waypoint.positions.clone() }
+        };
+
+        let display_trajectory = MoveItDisplayTrajectory {
+            model_id: self.robot_name.clone(),
+            trajectory: vec![MoveItRobotTrajectory {
+                joint_trajectory: MoveItJointTrajectory {
+                    joint_names: self.joint_names.clone(),
+                    points
+                }
+            }],
+            trajectory_start: MoveItRobotState {
+                joint_state: MoveItJointState {
+                    name: self.joint_names.clone(),
+                    position: first_positions
+                }
+            }
+        };
+
+        return display_trajectory.to_json_string();
+    }
+    /// Multiple trajectory segments in a DisplayTrajectory are concatenated, with the times of each
+    /// segment offset by the end time of the previous segment.
+    fn from_moveit_display_trajectory_json_string(s: &str, robot_name: Option<&str>) -> Result<Self, OptimaError> {
+        let display_trajectory = MoveItDisplayTrajectory::from_json_string(s)?;
+
+        let robot_name = if !display_trajectory.model_id.is_empty() { display_trajectory.model_id.clone() } else {
+            match robot_name {
+                None => { return Err(OptimaError::new_generic_error_str("MoveIt DisplayTrajectory has no model_id and no robot name was given.", file!(), line!())); }
+                Some(robot_name) => { robot_name.to_string() }
+            }
+        };
+
+        let mut joint_names: Option<Vec<String>> = None;
+        let mut waypoints = vec![];
+        let mut time_offset = 0.0;
+        for robot_trajectory in &display_trajectory.trajectory {
+            let joint_trajectory = &robot_trajectory.joint_trajectory;
+            match &joint_names {
+                None => { joint_names = Some(joint_trajectory.joint_names.clone()); }
+                Some(joint_names) => {
+                    if joint_names != &joint_trajectory.joint_names {
+                        return Err(OptimaError::new_generic_error_str("All trajectory segments in a MoveIt DisplayTrajectory must have the same joint names.", file!(), line!()));
+                    }
+                }
+            }
+
+            let mut segment_end_time = time_offset;
+            for point in &joint_trajectory.points {
+                let time_from_start = time_offset + point.time_from_start.to_seconds();
+                waypoints.push(RobotJointTrajectoryWaypoint::new(time_from_start, point.positions.clone()));
+                segment_end_time = time_from_start;
+            }
+            time_offset = segment_end_time;
+        }
+
+        return Self::new(&robot_name, joint_names.unwrap_or(vec![]), waypoints);
+    }
+    fn validate(&self) -> Result<(), OptimaError> {
+        let mut prev_time = f64::NEG_INFINITY;
+        for (i, waypoint) in self.waypoints.iter().enumerate() {
+            if waypoint.positions.len() != self.joint_names.len() {
+                return Err(OptimaError::new_generic_error_str(&format!("Waypoint {} has {} positions, but the trajectory has {} joint names.", i, waypoint.positions.len(), self.joint_names.len()), file!(), line!()));
+            }
+            if waypoint.time_from_start < prev_time {
+                return Err(OptimaError::new_generic_error_str(&format!("Waypoint {} has time_from_start {}, which is earlier than the previous waypoint ({}).", i, waypoint.time_from_start, prev_time), file!(), line!()));
+            }
+            prev_time = waypoint.time_from_start;
+        }
+        Ok(())
+    }
+    pub fn robot_name(&self) -> &str {
+        &self.robot_name
+    }
+    pub fn joint_names(&self) -> &Vec<String> {
+        &self.joint_names
+    }
+    pub fn waypoints(&self) -> &Vec<RobotJointTrajectoryWaypoint> {
+        &self.waypoints
+    }
+    pub fn num_waypoints(&self) -> usize {
+        self.waypoints.len()
+    }
+    /// Returns the time of the last waypoint (0.0 for an empty trajectory).
+    pub fn duration(&self) -> f64 {
+        return match self.waypoints.last() {
+            None => { 0.0 }
+            Some(waypoint) => { waypoint.time_from_start }
+        }
+    }
+}
+
+/// Python implementations.
+#[cfg(not(target_arch = "wasm32"))]
+#[pymethods]
+impl RobotJointTrajectory {
+    #[staticmethod]
+    pub fn new_from_joint_states_py(robot_joint_state_module: &RobotJointStateModule, joint_states: Vec<Vec<f64>>, times_from_start: Vec<f64>) -> Self {
+        let mut robot_joint_states = vec![];
+        for joint_state in &joint_states {
+            robot_joint_states.push(robot_joint_state_module.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(joint_state)).expect("error"));
+        }
+        return Self::new_from_robot_joint_states(robot_joint_state_module, &robot_joint_states, &times_from_start).expect("error");
+    }
+    #[args(robot_joint_state_type = "\"DOF\"")]
+    pub fn to_joint_states_py(&self, robot_joint_state_module: &RobotJointStateModule, robot_joint_state_type: &str) -> Vec<Vec<f64>> {
+        let robot_joint_states = self.to_robot_joint_states(robot_joint_state_module, &RobotJointStateType::from_ron_string(robot_joint_state_type).expect("error")).expect("error");
+        let mut out_vec = vec![];
+        for robot_joint_state in &robot_joint_states {
+            out_vec.push(NalgebraConversions::dvector_to_vec(robot_joint_state.joint_state()));
+        }
+        out_vec
+    }
+    #[args(format = "\"NativeJSON\"")]
+    pub fn to_string_with_format_py(&self, format: &str) -> String {
+        self.to_string_with_format(&RobotJointTrajectoryFileFormat::from_ron_string(format).expect("error")).expect("error")
+    }
+    #[staticmethod]
+    #[args(format = "\"NativeJSON\"")]
+    pub fn from_string_with_format_py(s: &str, format: &str, robot_name: Option<&str>) -> Self {
+        Self::from_string_with_format(s, &RobotJointTrajectoryFileFormat::from_ron_string(format).expect("error"), robot_name).expect("error")
+    }
+    #[args(format = "\"NativeJSON\"")]
+    pub fn save_to_file_py(&self, path: &str, format: &str) {
+        let path = OptimaPath::Path(PathBuf::from(path));
+        self.save_to_file(&path, &RobotJointTrajectoryFileFormat::from_ron_string(format).expect("error")).expect("error");
+    }
+    #[staticmethod]
+    pub fn load_from_file_py(path: &str, format: Option<&str>, robot_name: Option<&str>) -> Self {
+        let path = OptimaPath::Path(PathBuf::from(path));
+        let format = match format {
+            None => { None }
+            Some(format) => { Some(RobotJointTrajectoryFileFormat::from_ron_string(format).expect("error")) }
+        };
+        Self::load_from_file(&path, format.as_ref(), robot_name).expect("error")
+    }
+    pub fn joint_names_py(&self) -> Vec<String> {
+        self.joint_names.clone()
+    }
+    pub fn times_from_start_py(&self) -> Vec<f64> {
+        self.waypoints.iter().map(|w| w.time_from_start).collect()
+    }
+    pub fn positions_py(&self) -> Vec<Vec<f64>> {
+        self.waypoints.iter().map(|w| w.positions.clone()).collect()
+    }
+    pub fn duration_py(&self) -> f64 {
+        self.duration()
+    }
+    #[args(mode = "\"Piecewise\"")]
+    pub fn time_dilated_py(&self, velocity_limits: Vec<f64>, acceleration_limits: Option<Vec<f64>>, mode: &str) -> Self {
+        TrajectoryTimeDilation::dilate(self, &velocity_limits, acceleration_limits.as_ref(), &TimeDilationMode::from_ron_string(mode).expect("error")).expect("error")
+    }
+    #[args(mode = "\"Piecewise\"")]
+    pub fn time_dilated_with_robot_limits_py(&self, robot_joint_state_module: &RobotJointStateModule, acceleration_limits: Option<Vec<f64>>, mode: &str) -> Self {
+        TrajectoryTimeDilation::dilate_with_robot_limits(robot_joint_state_module, self, acceleration_limits.as_ref(), &TimeDilationMode::from_ron_string(mode).expect("error")).expect("error")
+    }
+}
+
+/// WASM implementations.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl RobotJointTrajectory {
+    pub fn to_string_with_format_wasm(&self, format: &str) -> String {
+        self.to_string_with_format(&RobotJointTrajectoryFileFormat::from_ron_string(format).expect("error")).expect("error")
+    }
+    pub fn from_string_with_format_wasm(s: &str, format: &str, robot_name: Option<String>) -> Self {
+        Self::from_string_with_format(s, &RobotJointTrajectoryFileFormat::from_ron_string(format).expect("error"), robot_name.as_ref().map(|r| r.as_str())).expect("error")
+    }
+    pub fn duration_wasm(&self) -> f64 {
+        self.duration()
+    }
+}
+
+/// A single waypoint in a `RobotJointTrajectory`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobotJointTrajectoryWaypoint {
+    time_from_start: f64,
+    positions: Vec<f64>
+}
+impl RobotJointTrajectoryWaypoint {
+    pub fn new(time_from_start: f64, positions: Vec<f64>) -> Self {
+        Self {
+            time_from_start,
+            positions
+        }
+    }
+    pub fn time_from_start(&self) -> f64 {
+        self.time_from_start
+    }
+    pub fn positions(&self) -> &Vec<f64> {
+        &self.positions
+    }
+    pub fn positions_as_dvector(&self) -> DVector<f64> {
+        NalgebraConversions::vec_to_dvector(&self.positions)
+    }
+}
+
+/// File formats supported for importing and exporting a `RobotJointTrajectory`.
+/// - `NativeJSON`: the serialization of `RobotJointTrajectory` (see its documentation for the schema).
+/// - `MoveItDisplayTrajectoryJSON`: the JSON encoding (as used by rosbridge) of a
+/// `moveit_msgs/DisplayTrajectory` message.  Only positions and times are imported; velocities,
+/// accelerations, efforts, and multi-dof trajectories are ignored.  Both ROS1 (`secs`/`nsecs`) and
+/// ROS2 (`sec`/`nanosec`) durations are accepted.
+/// - `CSV`: a header row `time,<joint_name_0>,<joint_name_1>,...` followed by one row per waypoint.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RobotJointTrajectoryFileFormat {
+    NativeJSON,
+    MoveItDisplayTrajectoryJSON,
+    CSV
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct MoveItDisplayTrajectory {
+    #[serde(default)]
+    model_id: String,
+    #[serde(default)]
+    trajectory: Vec<MoveItRobotTrajectory>,
+    #[serde(default)]
+    trajectory_start: MoveItRobotState
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct MoveItRobotTrajectory {
+    joint_trajectory: MoveItJointTrajectory
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct MoveItJointTrajectory {
+    #[serde(default)]
+    joint_names: Vec<String>,
+    #[serde(default)]
+    points: Vec<MoveItJointTrajectoryPoint>
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct MoveItJointTrajectoryPoint {
+    #[serde(default)]
+    positions: Vec<f64>,
+    #[serde(default)]
+    velocities: Vec<f64>,
+    #[serde(default)]
+    accelerations: Vec<f64>,
+    #[serde(default)]
+    effort: Vec<f64>,
+    time_from_start: MoveItDuration
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct MoveItDuration {
+    #[serde(alias = "sec")]
+    secs: i64,
+    #[serde(alias = "nanosec")]
+    nsecs: i64
+}
+impl MoveItDuration {
+    fn new_from_seconds(seconds: f64) -> Self {
+        let mut secs = seconds.floor() as i64;
+        let mut nsecs = ((seconds - seconds.floor()) * 1_000_000_000.0).round() as i64;
+        // Rounding can push the fractional part up to a full second.
+        if nsecs >= 1_000_000_000 {
+            secs += nsecs / 1_000_000_000;
+            nsecs %= 1_000_000_000;
+        }
+        Self {
+            secs,
+            nsecs
+        }
+    }
+    fn to_seconds(&self) -> f64 {
+        return self.secs as f64 + self.nsecs as f64 / 1_000_000_000.0;
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct MoveItRobotState {
+    #[serde(default)]
+    joint_state: MoveItJointState
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct MoveItJointState {
+    #[serde(default)]
+    name: Vec<String>,
+    #[serde(default)]
+    position: Vec<f64>
+}
+
+crate::impl_py_pickle!(RobotJointTrajectory);
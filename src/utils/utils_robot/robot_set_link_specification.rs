@@ -8,28 +8,45 @@ use crate::utils::utils_se3::optima_rotation::OptimaRotation;
 pub enum RobotSetLinkSpecification {
     LinkSE3PoseGoal { robot_idx_in_set: usize, link_idx_in_robot: usize, goal: OptimaSE3Pose, weight: Option<f64> },
     LinkPositionGoal { robot_idx_in_set: usize, link_idx_in_robot: usize, goal: Vector3<f64>, weight: Option<f64> },
-    LinkRotationGoal { robot_idx_in_set: usize, link_idx_in_robot: usize, goal: OptimaRotation, weight: Option<f64> }
+    LinkRotationGoal { robot_idx_in_set: usize, link_idx_in_robot: usize, goal: OptimaRotation, weight: Option<f64> },
+    TCPFrameSE3PoseGoal { robot_idx_in_set: usize, tcp_frame_idx_in_robot: usize, goal: OptimaSE3Pose, weight: Option<f64> },
+    TCPFramePositionGoal { robot_idx_in_set: usize, tcp_frame_idx_in_robot: usize, goal: Vector3<f64>, weight: Option<f64> },
+    TCPFrameRotationGoal { robot_idx_in_set: usize, tcp_frame_idx_in_robot: usize, goal: OptimaRotation, weight: Option<f64> }
+}
+impl RobotSetLinkSpecification {
+    /// The weight of the specification's error term (1.0 if no weight is given).
+    pub fn weight(&self) -> f64 {
+        let weight = match self {
+            RobotSetLinkSpecification::LinkSE3PoseGoal { weight, .. } => { weight }
+            RobotSetLinkSpecification::LinkPositionGoal { weight, .. } => { weight }
+            RobotSetLinkSpecification::LinkRotationGoal { weight, .. } => { weight }
+            RobotSetLinkSpecification::TCPFrameSE3PoseGoal { weight, .. } => { weight }
+            RobotSetLinkSpecification::TCPFramePositionGoal { weight, .. } => { weight }
+            RobotSetLinkSpecification::TCPFrameRotationGoal { weight, .. } => { weight }
+        };
+        return weight.unwrap_or(1.0);
+    }
 }
 impl EnumMapToType<RobotSetLinkSpecificationType> for RobotSetLinkSpecification {
     fn map_to_type(&self) -> RobotSetLinkSpecificationType {
         return match self {
             RobotSetLinkSpecification::LinkSE3PoseGoal { robot_idx_in_set, link_idx_in_robot, ..} => {
-                RobotSetLinkSpecificationType {
-                    robot_idx_in_set: *robot_idx_in_set,
-                    link_idx_in_robot: *link_idx_in_robot
-                }
+                RobotSetLinkSpecificationType::new(*robot_idx_in_set, *link_idx_in_robot)
             }
             RobotSetLinkSpecification::LinkPositionGoal { robot_idx_in_set, link_idx_in_robot, ..} => {
-                RobotSetLinkSpecificationType {
-                    robot_idx_in_set: *robot_idx_in_set,
-                    link_idx_in_robot: *link_idx_in_robot
-                }
+                RobotSetLinkSpecificationType::new(*robot_idx_in_set, *link_idx_in_robot)
             }
             RobotSetLinkSpecification::LinkRotationGoal { robot_idx_in_set, link_idx_in_robot, ..} => {
-                RobotSetLinkSpecificationType {
-                    robot_idx_in_set: *robot_idx_in_set,
-                    link_idx_in_robot: *link_idx_in_robot
-                }
+                RobotSetLinkSpecificationType::new(*robot_idx_in_set, *link_idx_in_robot)
+            }
+            RobotSetLinkSpecification::TCPFrameSE3PoseGoal { robot_idx_in_set, tcp_frame_idx_in_robot, ..} => {
+                RobotSetLinkSpecificationType::new_tcp_frame(*robot_idx_in_set, *tcp_frame_idx_in_robot)
+            }
+            RobotSetLinkSpecification::TCPFramePositionGoal { robot_idx_in_set, tcp_frame_idx_in_robot, ..} => {
+                RobotSetLinkSpecificationType::new_tcp_frame(*robot_idx_in_set, *tcp_frame_idx_in_robot)
+            }
+            RobotSetLinkSpecification::TCPFrameRotationGoal { robot_idx_in_set, tcp_frame_idx_in_robot, ..} => {
+                RobotSetLinkSpecificationType::new_tcp_frame(*robot_idx_in_set, *tcp_frame_idx_in_robot)
             }
         }
     }
@@ -38,21 +55,38 @@ impl EnumMapToType<RobotSetLinkSpecificationType> for RobotSetLinkSpecification
 #[derive(Clone, Debug, Serialize, Deserialize, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct RobotSetLinkSpecificationType {
     robot_idx_in_set: usize,
-    link_idx_in_robot: usize
+    link_idx_in_robot: Option<usize>,
+    tcp_frame_idx_in_robot: Option<usize>
 }
 impl RobotSetLinkSpecificationType {
     pub fn new(robot_idx_in_set: usize, link_idx_in_robot: usize) -> Self {
         Self {
             robot_idx_in_set,
-            link_idx_in_robot
+            link_idx_in_robot: Some(link_idx_in_robot),
+            tcp_frame_idx_in_robot: None
+        }
+    }
+    pub fn new_tcp_frame(robot_idx_in_set: usize, tcp_frame_idx_in_robot: usize) -> Self {
+        Self {
+            robot_idx_in_set,
+            link_idx_in_robot: None,
+            tcp_frame_idx_in_robot: Some(tcp_frame_idx_in_robot)
         }
     }
     pub fn robot_idx_in_set(&self) -> usize {
         self.robot_idx_in_set
     }
+    /// Panics for TCP frame specification types (see `link_idx_in_robot_if_link`).
     pub fn link_idx_in_robot(&self) -> usize {
+        self.link_idx_in_robot.expect("a TCP frame specification type has no link idx")
+    }
+    /// The link idx, or None for TCP frame specification types.
+    pub fn link_idx_in_robot_if_link(&self) -> Option<usize> {
         self.link_idx_in_robot
     }
+    pub fn tcp_frame_idx_in_robot(&self) -> Option<usize> {
+        self.tcp_frame_idx_in_robot
+    }
 }
 
 pub struct RobotLinkSpecificationCollection {
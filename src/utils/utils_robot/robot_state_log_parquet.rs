@@ -0,0 +1,141 @@
+use pyo3::*;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Arc;
+use arrow::array::{ArrayRef, Float64Array};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_files::optima_path::OptimaPath;
+use crate::utils::utils_robot::robot_state_recorder::{RobotStateLog, RobotStateRecord};
+
+/// Key of the schema metadata entry that holds the robot name.
+pub const ROBOT_NAME_METADATA_KEY: &str = "optima.robot_name";
+/// Number of records written per row group by `RobotStateParquetWriter` unless set otherwise.
+pub const DEFAULT_PARQUET_ROW_GROUP_SIZE: usize = 8192;
+
+/// Arrow and Parquet export of robot state logs, enabled with the `parquet_export` cargo feature.
+///
+/// Logs are laid out as one row per record, with a `time` column, one column per joint, and one
+/// column per query value named `query:<query_value_name>` (the same column names as the CSV
+/// format of `RobotStateLog`).  All columns are `Float64`; query columns are nullable, since records
+/// may be logged without query values.  The robot name is stored in the schema metadata under
+/// `ROBOT_NAME_METADATA_KEY`.  The resulting files load directly with `pandas.read_parquet` or
+/// `polars.read_parquet`.
+pub struct RobotStateLogArrow;
+impl RobotStateLogArrow {
+    pub fn schema(robot_name: &str, joint_names: &Vec<String>, query_value_names: &Vec<String>) -> SchemaRef {
+        let mut fields = vec![Field::new("time", DataType::Float64, false)];
+        for joint_name in joint_names { fields.push(Field::new(joint_name, DataType::Float64, false)); }
+        for query_value_name in query_value_names { fields.push(Field::new(&format!("query:{}", query_value_name), DataType::Float64, true)); }
+
+        let mut metadata = HashMap::new();
+        metadata.insert(ROBOT_NAME_METADATA_KEY.to_string(), robot_name.to_string());
+
+        Arc::new(Schema::new(fields).with_metadata(metadata))
+    }
+    pub fn log_schema(log: &RobotStateLog) -> SchemaRef {
+        return Self::schema(log.robot_name(), log.joint_names(), log.query_value_names());
+    }
+    /// Converts records to a record batch with the given schema (see `schema`).
+    pub fn records_to_record_batch(schema: &SchemaRef, records: &[RobotStateRecord]) -> Result<RecordBatch, OptimaError> {
+        let num_columns = schema.fields().len();
+        let mut columns: Vec<Vec<Option<f64>>> = vec![Vec::with_capacity(records.len()); num_columns];
+        for (record_idx, record) in records.iter().enumerate() {
+            let num_joints = record.joint_state().len();
+            if 1 + num_joints > num_columns {
+                return Err(OptimaError::new_generic_error_str(&format!("Record {} has {} joint values, but the schema has {} columns.", record_idx, num_joints, num_columns), file!(), line!()));
+            }
+            columns[0].push(Some(record.time()));
+            for (j, value) in record.joint_state().iter().enumerate() { columns[1 + j].push(Some(*value)); }
+            for q in 0..num_columns - 1 - num_joints { columns[1 + num_joints + q].push(record.query_values().get(q).cloned()); }
+        }
+
+        let arrays: Vec<ArrayRef> = columns.into_iter().map(|c| Arc::new(Float64Array::from(c)) as ArrayRef).collect();
+        return RecordBatch::try_new(schema.clone(), arrays).map_err(|e| OptimaError::new_generic_error_str(&format!("Could not build arrow record batch: {}", e), file!(), line!()));
+    }
+    pub fn log_to_record_batch(log: &RobotStateLog) -> Result<RecordBatch, OptimaError> {
+        return Self::records_to_record_batch(&Self::log_schema(log), log.records());
+    }
+    pub fn save_log_to_parquet_file(log: &RobotStateLog, path: &OptimaPath) -> Result<(), OptimaError> {
+        let mut writer = RobotStateParquetWriter::new(path, log.robot_name(), log.joint_names(), log.query_value_names())?;
+        writer.write_records(log.records())?;
+        return writer.close();
+    }
+}
+
+/// Writes robot state records to a Parquet file incrementally, so long experiment logs never have
+/// to be held in memory at once (e.g., periodically write out and clear the records of a
+/// `RobotStateRecorder`).  Records are buffered and written out one row group at a time; `close`
+/// must be called to write the file footer, without which the file cannot be read.
+pub struct RobotStateParquetWriter {
+    writer: Option<ArrowWriter<File>>,
+    schema: SchemaRef,
+    buffer: Vec<RobotStateRecord>,
+    row_group_size: usize
+}
+impl RobotStateParquetWriter {
+    pub fn new(path: &OptimaPath, robot_name: &str, joint_names: &Vec<String>, query_value_names: &Vec<String>) -> Result<Self, OptimaError> {
+        let schema = RobotStateLogArrow::schema(robot_name, joint_names, query_value_names);
+        let file = path.get_file_for_writing()?;
+        let writer = ArrowWriter::try_new(file, schema.clone(), None).map_err(|e| OptimaError::new_generic_error_str(&format!("Could not create parquet writer: {}", e), file!(), line!()))?;
+
+        Ok(Self {
+            writer: Some(writer),
+            schema,
+            buffer: vec![],
+            row_group_size: DEFAULT_PARQUET_ROW_GROUP_SIZE
+        })
+    }
+    pub fn set_row_group_size(&mut self, row_group_size: usize) {
+        self.row_group_size = row_group_size.max(1);
+    }
+    pub fn write_record(&mut self, record: &RobotStateRecord) -> Result<(), OptimaError> {
+        self.buffer.push(record.clone());
+        if self.buffer.len() >= self.row_group_size { self.flush()?; }
+        Ok(())
+    }
+    pub fn write_records(&mut self, records: &[RobotStateRecord]) -> Result<(), OptimaError> {
+        for record in records { self.write_record(record)?; }
+        Ok(())
+    }
+    /// Writes the buffered records to the file as a row group.
+    pub fn flush(&mut self) -> Result<(), OptimaError> {
+        if self.buffer.is_empty() { return Ok(()); }
+        let writer = match &mut self.writer {
+            None => { return Err(OptimaError::new_generic_error_str("Parquet writer is already closed.", file!(), line!())); }
+            Some(writer) => { writer }
+        };
+
+        let batch = RobotStateLogArrow::records_to_record_batch(&self.schema, &self.buffer)?;
+        writer.write(&batch).map_err(|e| OptimaError::new_generic_error_str(&format!("Could not write parquet row group: {}", e), file!(), line!()))?;
+        writer.flush().map_err(|e| OptimaError::new_generic_error_str(&format!("Could not write parquet row group: {}", e), file!(), line!()))?;
+        self.buffer.clear();
+        Ok(())
+    }
+    /// Writes any buffered records and the file footer.
+    pub fn close(&mut self) -> Result<(), OptimaError> {
+        self.flush()?;
+        if let Some(writer) = self.writer.take() {
+            writer.close().map_err(|e| OptimaError::new_generic_error_str(&format!("Could not close parquet file: {}", e), file!(), line!()))?;
+        }
+        Ok(())
+    }
+}
+impl Drop for RobotStateParquetWriter {
+    fn drop(&mut self) {
+        self.close().ok();
+    }
+}
+
+/// Python implementations.
+#[pymethods]
+impl RobotStateLog {
+    pub fn save_to_parquet_file_py(&self, path: &str) {
+        let path = OptimaPath::Path(PathBuf::from(path));
+        RobotStateLogArrow::save_log_to_parquet_file(self, &path).expect("error");
+    }
+}
@@ -0,0 +1,418 @@
+#[cfg(not(target_arch = "wasm32"))]
+use pyo3::*;
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateModule};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_files::optima_path::OptimaPath;
+use crate::utils::utils_nalgebra::conversions::NalgebraConversions;
+use crate::utils::utils_robot::robot_joint_trajectory::{RobotJointTrajectory, RobotJointTrajectoryWaypoint};
+use crate::utils::utils_traits::{ToAndFromJsonString, ToAndFromRonString};
+
+/// Prefix of the CSV columns that hold query values, which separates them from joint columns.
+const QUERY_VALUE_COLUMN_PREFIX: &str = "query:";
+
+/// Records timestamped robot states during execution, along with optional query results (e.g., the
+/// minimum distance to obstacles at each state), so executions can be analyzed with the same tooling
+/// used for planning.  States are stored as DOF joint states.
+///
+/// Records are kept in memory, either all of them or (with a capacity) only the most recent ones as
+/// a ring buffer.  Records can also be streamed to a file as they are recorded (see
+/// `stream_to_file`), which is the way to keep long executions without holding them in memory.
+/// `log` returns the in-memory records as a `RobotStateLog`, which can be saved, loaded, and
+/// replayed into a `RobotJointTrajectory`.
+#[cfg_attr(not(target_arch = "wasm32"), pyclass)]
+pub struct RobotStateRecorder {
+    robot_joint_state_module: RobotJointStateModule,
+    query_value_names: Vec<String>,
+    capacity: Option<usize>,
+    records: VecDeque<RobotStateRecord>,
+    file_sink: Option<(BufWriter<File>, RobotStateLogFormat)>
+}
+impl RobotStateRecorder {
+    /// query_value_names names the query values given with each record (empty if no query results are
+    /// recorded).  With a capacity, only the most recent capacity records are kept in memory.
+    pub fn new(robot_joint_state_module: &RobotJointStateModule, query_value_names: Vec<String>, capacity: Option<usize>) -> Result<Self, OptimaError> {
+        if capacity == Some(0) {
+            return Err(OptimaError::new_generic_error_str("Recorder capacity must be at least 1.", file!(), line!()));
+        }
+
+        Ok(Self {
+            robot_joint_state_module: robot_joint_state_module.clone(),
+            query_value_names,
+            capacity,
+            records: VecDeque::new(),
+            file_sink: None
+        })
+    }
+    /// Writes all subsequent records to the given file in the given format (replacing the file if it
+    /// exists).  Records are buffered, so call `flush` to make sure they reach the file.
+    pub fn stream_to_file(&mut self, path: &OptimaPath, format: &RobotStateLogFormat) -> Result<(), OptimaError> {
+        self.flush()?;
+        let mut writer = BufWriter::new(path.get_file_for_writing()?);
+        let header = self.empty_log().header_string(format);
+        Self::write_to_sink(&mut writer, &header)?;
+        self.file_sink = Some((writer, format.clone()));
+        Ok(())
+    }
+    /// Stops streaming records to a file (flushing any buffered records).
+    pub fn stop_streaming(&mut self) -> Result<(), OptimaError> {
+        self.flush()?;
+        self.file_sink = None;
+        Ok(())
+    }
+    /// Records a state at the given time (in seconds).  Times must be non-decreasing.
+    pub fn record(&mut self, time: f64, robot_joint_state: &RobotJointState, query_values: Option<&Vec<f64>>) -> Result<(), OptimaError> {
+        if let Some(last) = self.records.back() {
+            if time < last.time {
+                return Err(OptimaError::new_generic_error_str(&format!("Record time {} is earlier than the previous record time ({}).", time, last.time), file!(), line!()));
+            }
+        }
+        let query_values = match query_values {
+            None => { vec![] }
+            Some(query_values) => { query_values.clone() }
+        };
+        if !query_values.is_empty() && query_values.len() != self.query_value_names.len() {
+            return Err(OptimaError::new_generic_error_str(&format!("Number of query values ({}) does not match number of query value names ({}).", query_values.len(), self.query_value_names.len()), file!(), line!()));
+        }
+
+        let dof_state = self.robot_joint_state_module.convert_joint_state_to_dof_state(robot_joint_state)?;
+        let record = RobotStateRecord {
+            time,
+            joint_state: NalgebraConversions::dvector_to_vec(dof_state.joint_state()),
+            query_values
+        };
+
+        if let Some((writer, format)) = &mut self.file_sink {
+            let line = record.to_string_with_format(format, self.query_value_names.len());
+            Self::write_to_sink(writer, &line)?;
+        }
+
+        self.records.push_back(record);
+        if let Some(capacity) = self.capacity {
+            while self.records.len() > capacity { self.records.pop_front(); }
+        }
+
+        Ok(())
+    }
+    pub fn flush(&mut self) -> Result<(), OptimaError> {
+        if let Some((writer, _)) = &mut self.file_sink {
+            if let Err(e) = writer.flush() {
+                return Err(OptimaError::new_generic_error_str(&format!("Could not flush recorder file: {}", e), file!(), line!()));
+            }
+        }
+        Ok(())
+    }
+    /// Removes all in-memory records.  Records already streamed to a file are not affected.
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+    /// Returns the in-memory records as a log.
+    pub fn log(&self) -> RobotStateLog {
+        let mut out_log = self.empty_log();
+        out_log.records = self.records.iter().cloned().collect();
+        out_log
+    }
+    pub fn records(&self) -> &VecDeque<RobotStateRecord> {
+        &self.records
+    }
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+    pub fn query_value_names(&self) -> &Vec<String> {
+        &self.query_value_names
+    }
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+    fn empty_log(&self) -> RobotStateLog {
+        RobotStateLog {
+            robot_name: self.robot_joint_state_module.robot_name().to_string(),
+            joint_names: RobotJointTrajectory::dof_joint_axis_names(&self.robot_joint_state_module),
+            query_value_names: self.query_value_names.clone(),
+            records: vec![]
+        }
+    }
+    fn write_to_sink(writer: &mut BufWriter<File>, s: &str) -> Result<(), OptimaError> {
+        if let Err(e) = writer.write_all(s.as_bytes()) {
+            return Err(OptimaError::new_generic_error_str(&format!("Could not write to recorder file: {}", e), file!(), line!()));
+        }
+        Ok(())
+    }
+}
+impl Drop for RobotStateRecorder {
+    fn drop(&mut self) {
+        self.flush().ok();
+    }
+}
+
+/// Python implementations.
+#[cfg(not(target_arch = "wasm32"))]
+#[pymethods]
+impl RobotStateRecorder {
+    #[new]
+    pub fn new_py(robot_joint_state_module: &RobotJointStateModule, query_value_names: Option<Vec<String>>, capacity: Option<usize>) -> Self {
+        Self::new(robot_joint_state_module, query_value_names.unwrap_or(vec![]), capacity).expect("error")
+    }
+    #[args(format = "\"JSONL\"")]
+    pub fn stream_to_file_py(&mut self, path: &str, format: &str) {
+        let path = OptimaPath::Path(PathBuf::from(path));
+        self.stream_to_file(&path, &RobotStateLogFormat::from_ron_string(format).expect("error")).expect("error");
+    }
+    pub fn stop_streaming_py(&mut self) {
+        self.stop_streaming().expect("error");
+    }
+    pub fn record_py(&mut self, time: f64, joint_state: Vec<f64>, query_values: Option<Vec<f64>>) {
+        let robot_joint_state = self.robot_joint_state_module.spawn_robot_joint_state_try_auto_type(NalgebraConversions::vec_to_dvector(&joint_state)).expect("error");
+        self.record(time, &robot_joint_state, query_values.as_ref()).expect("error");
+    }
+    pub fn flush_py(&mut self) {
+        self.flush().expect("error");
+    }
+    pub fn clear_py(&mut self) {
+        self.clear();
+    }
+    pub fn log_py(&self) -> RobotStateLog {
+        self.log()
+    }
+    pub fn len_py(&self) -> usize {
+        self.len()
+    }
+}
+
+/// A single recorded state.  joint_state is a DOF joint state, and query_values is empty if no query
+/// results were recorded with the state.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RobotStateRecord {
+    time: f64,
+    joint_state: Vec<f64>,
+    #[serde(default)]
+    query_values: Vec<f64>
+}
+impl RobotStateRecord {
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+    pub fn joint_state(&self) -> &Vec<f64> {
+        &self.joint_state
+    }
+    pub fn query_values(&self) -> &Vec<f64> {
+        &self.query_values
+    }
+    /// A single line (including the trailing newline) in the given format.  In CSV, missing query
+    /// values are left empty.
+    fn to_string_with_format(&self, format: &RobotStateLogFormat, num_query_values: usize) -> String {
+        return match format {
+            RobotStateLogFormat::JSONL => { format!("{}\n", self.to_json_string()) }
+            RobotStateLogFormat::CSV => {
+                let mut out_string = format!("{}", self.time);
+                for value in &self.joint_state { out_string += &format!(",{}", value); }
+                for i in 0..num_query_values {
+                    match self.query_values.get(i) {
+                        None => { out_string += ","; }
+                        Some(value) => { out_string += &format!(",{}", value); }
+                    }
+                }
+                out_string += "\n";
+                out_string
+            }
+        }
+    }
+}
+
+/// File formats for a `RobotStateLog`.
+/// - `CSV`: a header row `time,<joint_name_0>,...,query:<query_value_name_0>,...` followed by one row
+/// per record.  The CSV format does not encode a robot name.
+/// - `JSONL`: a first line holding `{"robot_name": ..., "joint_names": [...], "query_value_names":
+/// [...]}`, followed by one `{"time": ..., "joint_state": [...], "query_values": [...]}` object per
+/// line.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RobotStateLogFormat {
+    CSV,
+    JSONL
+}
+
+/// A sequence of recorded robot states (see `RobotStateRecorder`).  With the `parquet_export` cargo
+/// feature, logs can also be exported to Arrow and Parquet (see `RobotStateLogArrow`).
+#[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
+#[cfg_attr(target_arch = "wasm32", derive(Clone, Debug, Serialize, Deserialize))]
+pub struct RobotStateLog {
+    robot_name: String,
+    joint_names: Vec<String>,
+    query_value_names: Vec<String>,
+    records: Vec<RobotStateRecord>
+}
+impl RobotStateLog {
+    /// Replays the recorded states into a trajectory, with waypoint times relative to the first
+    /// record.
+    pub fn to_robot_joint_trajectory(&self) -> Result<RobotJointTrajectory, OptimaError> {
+        let start_time = match self.records.first() {
+            None => { 0.0 }
+            Some(record) => { record.time }
+        };
+        let waypoints = self.records.iter().map(|r| RobotJointTrajectoryWaypoint::new(r.time - start_time, r.joint_state.clone())).collect();
+        return RobotJointTrajectory::new(&self.robot_name, self.joint_names.clone(), waypoints);
+    }
+    pub fn to_string_with_format(&self, format: &RobotStateLogFormat) -> String {
+        let mut out_string = self.header_string(format);
+        for record in &self.records {
+            out_string += &record.to_string_with_format(format, self.query_value_names.len());
+        }
+        out_string
+    }
+    /// Parses a log from a string in the given format.  The CSV format does not encode a robot name,
+    /// so robot_name must be given for that format.
+    pub fn from_string_with_format(s: &str, format: &RobotStateLogFormat, robot_name: Option<&str>) -> Result<Self, OptimaError> {
+        let mut lines = s.lines().map(|l| l.trim()).filter(|l| !l.is_empty());
+        let header = match lines.next() {
+            None => { return Err(OptimaError::new_generic_error_str("Robot state log string is empty.", file!(), line!())); }
+            Some(header) => { header }
+        };
+
+        return match format {
+            RobotStateLogFormat::JSONL => {
+                let mut out_self = RobotStateLog::from_json_string(header)?;
+                for line in lines {
+                    out_self.records.push(RobotStateRecord::from_json_string(line)?);
+                }
+                Ok(out_self)
+            }
+            RobotStateLogFormat::CSV => {
+                let robot_name = match robot_name {
+                    None => { return Err(OptimaError::new_generic_error_str("A robot name must be given when loading a robot state log from CSV.", file!(), line!())); }
+                    Some(robot_name) => { robot_name.to_string() }
+                };
+                let header_entries: Vec<&str> = header.split(',').map(|e| e.trim()).collect();
+                if header_entries[0] != "time" {
+                    return Err(OptimaError::new_generic_error_str("The first column of a robot state log CSV must be named \"time\".", file!(), line!()));
+                }
+                let mut joint_names = vec![];
+                let mut query_value_names = vec![];
+                for entry in &header_entries[1..] {
+                    match entry.strip_prefix(QUERY_VALUE_COLUMN_PREFIX) {
+                        None => {
+                            if !query_value_names.is_empty() {
+                                return Err(OptimaError::new_generic_error_str("Joint columns must come before query value columns in a robot state log CSV.", file!(), line!()));
+                            }
+                            joint_names.push(entry.to_string());
+                        }
+                        Some(name) => { query_value_names.push(name.to_string()); }
+                    }
+                }
+
+                let mut records = vec![];
+                for (line_idx, line) in lines.enumerate() {
+                    let entries: Vec<&str> = line.split(',').map(|e| e.trim()).collect();
+                    if entries.len() != header_entries.len() {
+                        return Err(OptimaError::new_generic_error_str(&format!("Row {} of robot state log CSV has {} values, but the header has {} columns.", line_idx + 1, entries.len(), header_entries.len()), file!(), line!()));
+                    }
+                    let mut values = vec![];
+                    for entry in &entries {
+                        if entry.is_empty() { continue; }
+                        match entry.parse::<f64>() {
+                            Ok(value) => { values.push(value); }
+                            Err(_) => { return Err(OptimaError::new_generic_error_str(&format!("Could not parse value {:?} on row {} of robot state log CSV.", entry, line_idx + 1), file!(), line!())); }
+                        }
+                    }
+                    if values.len() < 1 + joint_names.len() {
+                        return Err(OptimaError::new_generic_error_str(&format!("Row {} of robot state log CSV is missing joint values.", line_idx + 1), file!(), line!()));
+                    }
+                    records.push(RobotStateRecord {
+                        time: values[0],
+                        joint_state: values[1..1 + joint_names.len()].to_vec(),
+                        query_values: values[1 + joint_names.len()..].to_vec()
+                    });
+                }
+
+                Ok(Self {
+                    robot_name,
+                    joint_names,
+                    query_value_names,
+                    records
+                })
+            }
+        }
+    }
+    pub fn save_to_file(&self, path: &OptimaPath, format: &RobotStateLogFormat) -> Result<(), OptimaError> {
+        return path.write_string_to_file(&self.to_string_with_format(format));
+    }
+    pub fn load_from_file(path: &OptimaPath, format: &RobotStateLogFormat, robot_name: Option<&str>) -> Result<Self, OptimaError> {
+        let s = path.read_file_contents_to_string()?;
+        return Self::from_string_with_format(&s, format, robot_name);
+    }
+    pub fn robot_name(&self) -> &str {
+        &self.robot_name
+    }
+    pub fn joint_names(&self) -> &Vec<String> {
+        &self.joint_names
+    }
+    pub fn query_value_names(&self) -> &Vec<String> {
+        &self.query_value_names
+    }
+    pub fn records(&self) -> &Vec<RobotStateRecord> {
+        &self.records
+    }
+    /// The header line (including the trailing newline) in the given format.
+    fn header_string(&self, format: &RobotStateLogFormat) -> String {
+        return match format {
+            RobotStateLogFormat::JSONL => {
+                let header = RobotStateLog {
+                    robot_name: self.robot_name.clone(),
+                    joint_names: self.joint_names.clone(),
+                    query_value_names: self.query_value_names.clone(),
+                    records: vec![]
+                };
+                format!("{}\n", header.to_json_string())
+            }
+            RobotStateLogFormat::CSV => {
+                let mut out_string = "time".to_string();
+                for joint_name in &self.joint_names { out_string += &format!(",{}", joint_name); }
+                for query_value_name in &self.query_value_names { out_string += &format!(",{}{}", QUERY_VALUE_COLUMN_PREFIX, query_value_name); }
+                out_string += "\n";
+                out_string
+            }
+        }
+    }
+}
+
+/// Python implementations.
+#[cfg(not(target_arch = "wasm32"))]
+#[pymethods]
+impl RobotStateLog {
+    pub fn to_robot_joint_trajectory_py(&self) -> RobotJointTrajectory {
+        self.to_robot_joint_trajectory().expect("error")
+    }
+    #[args(format = "\"JSONL\"")]
+    pub fn save_to_file_py(&self, path: &str, format: &str) {
+        let path = OptimaPath::Path(PathBuf::from(path));
+        self.save_to_file(&path, &RobotStateLogFormat::from_ron_string(format).expect("error")).expect("error");
+    }
+    #[staticmethod]
+    #[args(format = "\"JSONL\"")]
+    pub fn load_from_file_py(path: &str, format: &str, robot_name: Option<&str>) -> Self {
+        let path = OptimaPath::Path(PathBuf::from(path));
+        Self::load_from_file(&path, &RobotStateLogFormat::from_ron_string(format).expect("error"), robot_name).expect("error")
+    }
+    pub fn times_py(&self) -> Vec<f64> {
+        self.records.iter().map(|r| r.time).collect()
+    }
+    pub fn joint_states_py(&self) -> Vec<Vec<f64>> {
+        self.records.iter().map(|r| r.joint_state.clone()).collect()
+    }
+    pub fn query_values_py(&self) -> Vec<Vec<f64>> {
+        self.records.iter().map(|r| r.query_values.clone()).collect()
+    }
+    pub fn query_value_names_py(&self) -> Vec<String> {
+        self.query_value_names.clone()
+    }
+}
+
+crate::impl_py_pickle!(RobotStateLog);
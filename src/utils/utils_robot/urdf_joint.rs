@@ -5,7 +5,7 @@ use pyo3::*;
 use wasm_bindgen::prelude::*;
 
 use urdf_rs::*;
-use nalgebra::{Vector3};
+use nalgebra::{Isometry3, Translation3, UnitQuaternion, Vector3};
 use serde::{Serialize, Deserialize};
 
 /// This struct holds all information provided by a URDF file on a Joint when parsed by urdf_rs.
@@ -60,6 +60,24 @@ impl URDFJoint {
             safety_k_velocity: if let Some(s) = &joint.safety_controller { Some(s.k_velocity) } else { None }
         }
     }
+    /// Returns a joint that is not read from a URDF file, e.g., for models that are built
+    /// programmatically.  If limits is None, the joint is treated as not including limits.
+    pub fn new(name: &str, joint_type: JointTypeWrapper, parent_link: &str, child_link: &str, axis: Vector3<f64>, origin_xyz: Vector3<f64>, origin_rpy: Vector3<f64>, limits: Option<(f64, f64)>) -> Self {
+        let mut out_self = Self::new_empty();
+        out_self.name = name.to_string();
+        out_self.joint_type = joint_type;
+        out_self.parent_link = parent_link.to_string();
+        out_self.child_link = child_link.to_string();
+        out_self.axis = axis;
+        out_self.origin_xyz = origin_xyz;
+        out_self.origin_rpy = origin_rpy;
+        if let Some((lower, upper)) = limits {
+            out_self.includes_limits = true;
+            out_self.limits_lower = lower;
+            out_self.limits_upper = upper;
+        }
+        out_self
+    }
     pub fn new_empty() -> Self {
         Self {
             name: "".to_string(),
@@ -146,6 +164,66 @@ impl URDFJoint {
     pub fn safety_k_velocity(&self) -> Option<f64> {
         self.safety_k_velocity
     }
+    pub fn set_limits(&mut self, lower: f64, upper: f64) {
+        self.includes_limits = true;
+        self.limits_lower = lower;
+        self.limits_upper = upper;
+    }
+    pub fn set_axis(&mut self, axis: Vector3<f64>) {
+        self.axis = axis;
+    }
+    pub fn set_origin(&mut self, origin_xyz: Vector3<f64>, origin_rpy: Vector3<f64>) {
+        self.origin_xyz = origin_xyz;
+        self.origin_rpy = origin_rpy;
+    }
+    pub fn set_parent_and_child_links(&mut self, parent_link: &str, child_link: &str) {
+        self.parent_link = parent_link.to_string();
+        self.child_link = child_link.to_string();
+    }
+    /// The joint origin (the pose of the joint frame in the parent link's frame) as an isometry.
+    pub fn origin_isometry(&self) -> Isometry3<f64> {
+        return urdf_origin_to_isometry(&self.origin_xyz, &self.origin_rpy);
+    }
+    pub fn set_origin_isometry(&mut self, origin: &Isometry3<f64>) {
+        let (origin_xyz, origin_rpy) = isometry_to_urdf_origin(origin);
+        self.set_origin(origin_xyz, origin_rpy);
+    }
+    /// Replaces the joint origin with transform * origin.
+    pub fn premultiply_origin(&mut self, transform: &Isometry3<f64>) {
+        self.set_origin_isometry(&(transform * self.origin_isometry()));
+    }
+    /// Multiplies all lengths of the joint by the given scale: the origin translation and, for
+    /// prismatic joints, the limits, velocity limit, soft limits, and mimic offset.
+    pub fn scale_lengths(&mut self, scale: f64) {
+        self.origin_xyz *= scale;
+        if let JointTypeWrapper::Prismatic = self.joint_type {
+            self.scale_joint_values(scale);
+        }
+    }
+    /// Converts the limits, velocity limit, soft limits, and mimic offset of revolute and continuous
+    /// joints from degrees to radians.
+    pub fn convert_angular_values_from_degrees(&mut self) {
+        if let JointTypeWrapper::Revolute | JointTypeWrapper::Continuous = self.joint_type {
+            self.scale_joint_values(std::f64::consts::PI / 180.0);
+        }
+    }
+    fn scale_joint_values(&mut self, scale: f64) {
+        self.limits_lower *= scale;
+        self.limits_upper *= scale;
+        self.limits_velocity *= scale;
+        self.safety_soft_lower_limit = self.safety_soft_lower_limit.map(|v| v * scale);
+        self.safety_soft_upper_limit = self.safety_soft_upper_limit.map(|v| v * scale);
+        self.mimic_offset = self.mimic_offset.map(|v| v * scale);
+    }
+}
+
+pub(crate) fn urdf_origin_to_isometry(origin_xyz: &Vector3<f64>, origin_rpy: &Vector3<f64>) -> Isometry3<f64> {
+    return Isometry3::from_parts(Translation3::from(*origin_xyz), UnitQuaternion::from_euler_angles(origin_rpy[0], origin_rpy[1], origin_rpy[2]));
+}
+
+pub(crate) fn isometry_to_urdf_origin(isometry: &Isometry3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+    let (r, p, y) = isometry.rotation.euler_angles();
+    return (isometry.translation.vector, Vector3::new(r, p, y));
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -183,4 +261,6 @@ impl JointTypeWrapper {
             JointType::Spherical => { Self::Spherical }
         }
     }
-}
\ No newline at end of file
+}
+
+crate::impl_py_pickle!(URDFJoint);
@@ -5,7 +5,8 @@ use pyo3::*;
 use wasm_bindgen::prelude::*;
 
 use urdf_rs::*;
-use nalgebra::{Vector3, Matrix3};
+use nalgebra::{Isometry3, Vector3, Matrix3};
+use crate::utils::utils_robot::urdf_joint::{isometry_to_urdf_origin, urdf_origin_to_isometry};
 use serde::{Serialize, Deserialize};
 
 /// This struct holds all information provided by a URDF file on a Link when parsed by urdf_rs.
@@ -88,6 +89,13 @@ impl URDFLink {
             collision_mesh_scale
         }
     }
+    /// Returns a link with the given name and no inertial, visual, or collision information, e.g.,
+    /// for models that are built programmatically.
+    pub fn new_with_name(name: &str) -> Self {
+        let mut out_self = Self::new_empty();
+        out_self.name = name.to_string();
+        out_self
+    }
     pub fn new_empty() -> Self {
         Self {
             name: "".to_string(),
@@ -144,6 +152,50 @@ impl URDFLink {
     pub fn collision_mesh_scale(&self) -> Option<Vector3<f64>> {
         self.collision_mesh_scale
     }
+    pub fn set_inertial_origin(&mut self, origin_xyz: Vector3<f64>, origin_rpy: Vector3<f64>) {
+        self.inertial_origin_xyz = origin_xyz;
+        self.inertial_origin_rpy = origin_rpy;
+    }
+    /// Sets the visual origin.  Does nothing if the link has no visual.
+    pub fn set_visual_origin(&mut self, origin_xyz: Vector3<f64>, origin_rpy: Vector3<f64>) {
+        if self.visual_origin_xyz.is_none() { return; }
+        self.visual_origin_xyz = Some(origin_xyz);
+        self.visual_origin_rpy = Some(origin_rpy);
+    }
+    /// Sets the collision origin.  Does nothing if the link has no collision.
+    pub fn set_collision_origin(&mut self, origin_xyz: Vector3<f64>, origin_rpy: Vector3<f64>) {
+        if self.collision_origin_xyz.is_none() { return; }
+        self.collision_origin_xyz = Some(origin_xyz);
+        self.collision_origin_rpy = Some(origin_rpy);
+    }
+    /// Replaces the inertial, visual, and collision origins with transform * origin, i.e., expresses
+    /// them in a frame whose pose relative to the current link frame is transform^-1.
+    pub fn premultiply_origins(&mut self, transform: &Isometry3<f64>) {
+        let (xyz, rpy) = isometry_to_urdf_origin(&(transform * urdf_origin_to_isometry(&self.inertial_origin_xyz, &self.inertial_origin_rpy)));
+        self.set_inertial_origin(xyz, rpy);
+        if let (Some(xyz), Some(rpy)) = (self.visual_origin_xyz, self.visual_origin_rpy) {
+            let (xyz, rpy) = isometry_to_urdf_origin(&(transform * urdf_origin_to_isometry(&xyz, &rpy)));
+            self.set_visual_origin(xyz, rpy);
+        }
+        if let (Some(xyz), Some(rpy)) = (self.collision_origin_xyz, self.collision_origin_rpy) {
+            let (xyz, rpy) = isometry_to_urdf_origin(&(transform * urdf_origin_to_isometry(&xyz, &rpy)));
+            self.set_collision_origin(xyz, rpy);
+        }
+    }
+    /// Multiplies all lengths of the link by the given scale: origin translations and mesh scales
+    /// scale linearly, and the inertia matrix scales with scale^2 (the mass is unchanged).
+    pub fn scale_lengths(&mut self, scale: f64) {
+        self.inertial_origin_xyz *= scale;
+        self.inertial_matrix *= scale * scale;
+        self.visual_origin_xyz = self.visual_origin_xyz.map(|v| v * scale);
+        self.collision_origin_xyz = self.collision_origin_xyz.map(|v| v * scale);
+        if self.visual_mesh_filename.is_some() {
+            self.visual_mesh_scale = Some(self.visual_mesh_scale.unwrap_or(Vector3::new(1.0, 1.0, 1.0)) * scale);
+        }
+        if self.collision_mesh_filename.is_some() {
+            self.collision_mesh_scale = Some(self.collision_mesh_scale.unwrap_or(Vector3::new(1.0, 1.0, 1.0)) * scale);
+        }
+    }
 }
 
 /// Functions supported in Python.
@@ -163,3 +215,5 @@ impl URDFLink {
 impl URDFLink {
     pub fn name_wasm(&self) -> String { self.name.clone() }
 }
+
+crate::impl_py_pickle!(URDFLink);
@@ -0,0 +1,144 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Serialize, Deserialize};
+use urdf_rs::{JointType, Robot};
+use crate::utils::utils_console::{optima_print, PrintColor, PrintMode};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_files::optima_path::{OptimaAssetLocation, OptimaPath, OptimaPathMatchingPattern, OptimaPathMatchingStopCondition, OptimaStemCellPath};
+
+/// The result of checking a URDF for structural problems before it is used to build robot modules.
+/// Errors are problems that will keep the robot from being modeled correctly (e.g., a joint that
+/// references a missing link, or a link tree with more than one root); warnings are suspicious but
+/// usable (e.g., a revolute joint without limits).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct URDFValidationReport {
+    robot_name: String,
+    errors: Vec<String>,
+    warnings: Vec<String>
+}
+impl URDFValidationReport {
+    pub fn new_from_urdf_robot(robot: &Robot) -> Self {
+        let mut errors = vec![];
+        let mut warnings = vec![];
+
+        if robot.links.is_empty() { errors.push("URDF does not contain any links.".to_string()); }
+
+        let mut link_names = HashSet::new();
+        for link in &robot.links {
+            if !link_names.insert(link.name.as_str()) {
+                errors.push(format!("Link name {:?} is used more than once.", link.name));
+            }
+        }
+        let mut joint_names = HashSet::new();
+        for joint in &robot.joints {
+            if !joint_names.insert(joint.name.as_str()) {
+                errors.push(format!("Joint name {:?} is used more than once.", joint.name));
+            }
+        }
+
+        let mut parent_of_link: HashMap<&str, &str> = HashMap::new();
+        let mut children_of_link: HashMap<&str, Vec<&str>> = HashMap::new();
+        for joint in &robot.joints {
+            let (parent, child) = (joint.parent.link.as_str(), joint.child.link.as_str());
+            if !link_names.contains(parent) {
+                errors.push(format!("Joint {:?} has parent link {:?}, which does not exist.", joint.name, parent));
+            }
+            if !link_names.contains(child) {
+                errors.push(format!("Joint {:?} has child link {:?}, which does not exist.", joint.name, child));
+            }
+            if let Some(other_parent) = parent_of_link.insert(child, parent) {
+                errors.push(format!("Link {:?} is the child of more than one joint (parents {:?} and {:?}).", child, other_parent, parent));
+            }
+            children_of_link.entry(parent).or_insert(vec![]).push(child);
+
+            let has_axis = matches!(joint.joint_type, JointType::Revolute | JointType::Continuous | JointType::Prismatic);
+            if has_axis {
+                let axis = &joint.axis.xyz;
+                let norm = (axis[0] * axis[0] + axis[1] * axis[1] + axis[2] * axis[2]).sqrt();
+                if norm < 1e-9 { errors.push(format!("Joint {:?} has a zero-length axis.", joint.name)); }
+            }
+
+            if matches!(joint.joint_type, JointType::Revolute | JointType::Prismatic) {
+                if joint.limit.lower > joint.limit.upper {
+                    errors.push(format!("Joint {:?} has a lower limit ({}) greater than its upper limit ({}).", joint.name, joint.limit.lower, joint.limit.upper));
+                } else if joint.limit.lower == 0.0 && joint.limit.upper == 0.0 {
+                    warnings.push(format!("Joint {:?} is {:?} but does not specify position limits.", joint.name, joint.joint_type));
+                }
+                if joint.limit.velocity <= 0.0 {
+                    warnings.push(format!("Joint {:?} has a non-positive velocity limit ({}).", joint.name, joint.limit.velocity));
+                }
+            }
+
+            if let Some(mimic) = &joint.mimic {
+                if !robot.joints.iter().any(|j| j.name == mimic.joint) {
+                    errors.push(format!("Joint {:?} mimics joint {:?}, which does not exist.", joint.name, mimic.joint));
+                }
+            }
+        }
+
+        let roots: Vec<&str> = robot.links.iter().map(|l| l.name.as_str()).filter(|n| !parent_of_link.contains_key(n)).collect();
+        if roots.len() > 1 {
+            errors.push(format!("URDF has more than one root link ({:?}).", roots));
+        } else if roots.is_empty() && !robot.links.is_empty() {
+            errors.push("URDF has no root link (the link tree contains a cycle).".to_string());
+        }
+
+        if roots.len() == 1 {
+            let mut visited = HashSet::new();
+            let mut stack = vec![roots[0]];
+            while let Some(link) = stack.pop() {
+                if !visited.insert(link) { continue; }
+                if let Some(children) = children_of_link.get(link) {
+                    for child in children { stack.push(*child); }
+                }
+            }
+            let mut unreachable: Vec<&str> = link_names.iter().filter(|n| !visited.contains(*n)).map(|n| *n).collect();
+            unreachable.sort();
+            if !unreachable.is_empty() {
+                errors.push(format!("Links {:?} are not reachable from root link {:?} (the link tree contains a cycle).", unreachable, roots[0]));
+            }
+        }
+
+        Self {
+            robot_name: robot.name.clone(),
+            errors,
+            warnings
+        }
+    }
+    /// Parses and validates the URDF at the given path.  A URDF that cannot be parsed at all results in
+    /// an error rather than a report.
+    pub fn new_from_path(path: &OptimaPath) -> Result<Self, OptimaError> {
+        let robot = path.load_urdf()?;
+        return Ok(Self::new_from_urdf_robot(&robot));
+    }
+    /// Validates the URDF in the given robot's asset directory.
+    pub fn new_from_robot_name(robot_name: &str) -> Result<Self, OptimaError> {
+        let mut path_to_robot = OptimaStemCellPath::new_asset_path()?;
+        path_to_robot.append_file_location(&OptimaAssetLocation::Robot { robot_name: robot_name.to_string() });
+        let path_to_urdf_vec = path_to_robot.walk_directory_and_match(OptimaPathMatchingPattern::Extension("urdf".to_string()), OptimaPathMatchingStopCondition::First);
+        return match path_to_urdf_vec.first() {
+            None => { Err(OptimaError::new_generic_error_str(&format!("Robot directory for robot {} does not contain a urdf.", robot_name), file!(), line!())) }
+            Some(path_to_urdf) => { Self::new_from_path(path_to_urdf) }
+        }
+    }
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+    pub fn robot_name(&self) -> &str {
+        &self.robot_name
+    }
+    pub fn errors(&self) -> &Vec<String> {
+        &self.errors
+    }
+    pub fn warnings(&self) -> &Vec<String> {
+        &self.warnings
+    }
+    pub fn print_summary(&self) {
+        if self.is_valid() {
+            optima_print(&format!("URDF for robot {:?} is valid ({} warnings).", self.robot_name, self.warnings.len()), PrintMode::Println, PrintColor::Green, true);
+        } else {
+            optima_print(&format!("URDF for robot {:?} has {} errors and {} warnings.", self.robot_name, self.errors.len(), self.warnings.len()), PrintMode::Println, PrintColor::Red, true);
+        }
+        for e in &self.errors { optima_print(&format!("   > error: {}", e), PrintMode::Println, PrintColor::Red, false); }
+        for w in &self.warnings { optima_print(&format!("   > warning: {}", w), PrintMode::Println, PrintColor::Yellow, false); }
+    }
+}
@@ -4,8 +4,12 @@ use rand_distr::{Normal, Distribution};
 pub struct SimpleSamplers;
 impl SimpleSamplers {
     pub fn uniform_samples(bounds: &Vec<(f64, f64)>) -> Vec<f64> {
+        return Self::uniform_samples_with_rng(bounds, &mut rand::thread_rng());
+    }
+    /// Same as `uniform_samples`, drawing from the given random number generator (e.g., a seeded
+    /// `rand_chacha::ChaCha8Rng` for reproducible samples).
+    pub fn uniform_samples_with_rng<R: Rng + ?Sized>(bounds: &Vec<(f64, f64)>, rng: &mut R) -> Vec<f64> {
         let mut out_vec = vec![];
-        let mut rng = rand::thread_rng();
         for b in bounds {
             if b.0 == b.1 {
                 out_vec.push(b.0);
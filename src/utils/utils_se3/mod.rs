@@ -2,4 +2,5 @@ pub mod implicit_dual_quaternion;
 pub mod optima_rotation;
 pub mod optima_se3_pose;
 pub mod homogeneous_matrix;
-pub mod rotation_and_translation;
\ No newline at end of file
+pub mod rotation_and_translation;
+pub mod pose_error_metric;
@@ -475,4 +475,6 @@ impl OptimaRotationPy {
 }
 impl OptimaRotationPy {
     pub fn rotation(&self) -> &OptimaRotation { &self.rotation }
-}
\ No newline at end of file
+}
+
+crate::impl_py_pickle!(OptimaRotationPy);
@@ -80,6 +80,15 @@ impl OptimaSE3Pose {
             }
         }
     }
+    /// The pose of the given type with the same rotation and translation as the given nalgebra
+    /// isometry.
+    pub fn new_from_nalgebra_isometry(isometry: &Isometry3<f64>, t: &OptimaSE3PoseType) -> Self {
+        let translation = &isometry.translation.vector;
+        return match isometry.rotation.axis_angle() {
+            None => { Self::new_from_euler_angles(0.,0.,0., translation[0], translation[1], translation[2], t) }
+            Some((axis, angle)) => { Self::new_from_axis_angle(&axis, angle, translation[0], translation[1], translation[2], t) }
+        }
+    }
     pub fn new_identity() -> Self {
         Self::new_from_euler_angles(0.,0.,0.,0.,0.,0., &OptimaSE3PoseType::ImplicitDualQuaternion)
     }
@@ -805,6 +814,30 @@ impl OptimaSE3PoseWASM {
         }
     }
 
+    /// The translation as a `Float64Array` \[x, y, z\].
+    #[wasm_bindgen(getter = translation)]
+    pub fn translation_wasm(&self) -> Vec<f64> {
+        let t = self.pose.translation();
+        return vec![t[0], t[1], t[2]];
+    }
+    /// The rotation as a `Float64Array` unit quaternion \[x, y, z, w\].
+    #[wasm_bindgen(getter = unit_quaternion)]
+    pub fn unit_quaternion_wasm(&self) -> Vec<f64> {
+        let q = self.pose.to_nalgebra_isometry().rotation;
+        return vec![q.i, q.j, q.k, q.w];
+    }
+    /// The rotation as a `Float64Array` of euler angles \[rx, ry, rz\].
+    #[wasm_bindgen(getter = euler_angles)]
+    pub fn euler_angles_wasm(&self) -> Vec<f64> {
+        let (e, _) = self.pose.to_euler_angles_and_translation();
+        return vec![e[0], e[1], e[2]];
+    }
+    /// The 4x4 homogeneous matrix as a `Float64Array` in column-major order (the layout expected
+    /// by, e.g., three.js `Matrix4.fromArray`).
+    #[wasm_bindgen(getter = homogeneous_matrix)]
+    pub fn homogeneous_matrix_wasm(&self) -> Vec<f64> {
+        return self.pose.to_nalgebra_isometry().to_homogeneous().as_slice().to_vec();
+    }
     pub fn serialized_pose(&self) -> JsValue {
         JsValue::from_serde(&self).unwrap()
     }
@@ -813,5 +846,8 @@ impl OptimaSE3PoseWASM {
     }
 }
 impl OptimaSE3PoseWASM {
+    pub fn new(pose: OptimaSE3Pose) -> Self { Self { pose } }
     pub fn pose(&self) -> &OptimaSE3Pose { &self.pose }
 }
+
+crate::impl_py_pickle!(OptimaSE3PosePy);
@@ -0,0 +1,163 @@
+use nalgebra::{Isometry3, Matrix3, Matrix6, Vector3, Vector6};
+use serde::{Serialize, Deserialize};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_se3::optima_se3_pose::OptimaSE3Pose;
+
+/// How the orientation part of a pose error is computed from the error rotation
+/// target * current^-1.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrientationErrorType {
+    /// The scaled axis (axis * angle) of the error rotation.  Its norm is the rotation angle in
+    /// radians.
+    AxisAngle,
+    /// Twice the vector part of the error quaternion (taken with a non-negative real part).  This
+    /// matches `AxisAngle` for small errors, but its norm is 2 sin(angle / 2), so it saturates for
+    /// large errors, which can make iterative solvers less aggressive far from the goal.
+    Quaternion
+}
+
+/// A configurable error metric between a current and a target pose, used by inverse kinematics
+/// (see `RobotKinematicsModule::compute_ik_damped_least_squares_with_metric`) and anywhere else a
+/// pose has to be driven to or compared against a goal.
+///
+/// The error is a 6-vector \[position; orientation\] expressed in the world frame, where the position
+/// error is target - current and the orientation error is computed according to
+/// `OrientationErrorType`.  Masks select which axes of the *target* frame are constrained: for
+/// example, an orientation mask of \[true, true, false\] leaves rotation about the target's z axis
+/// free, which is the usual setup for a symmetric tool.  Position and orientation weights scale the
+/// two halves of the error (e.g., to trade meters against radians).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PoseErrorMetric {
+    position_weight: f64,
+    orientation_weight: f64,
+    orientation_error_type: OrientationErrorType,
+    position_mask: [bool; 3],
+    orientation_mask: [bool; 3]
+}
+impl PoseErrorMetric {
+    pub fn new(position_weight: f64, orientation_weight: f64, orientation_error_type: OrientationErrorType) -> Result<Self, OptimaError> {
+        if !(position_weight >= 0.0 && orientation_weight >= 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Pose error weights must be non-negative (given {}, {}).", position_weight, orientation_weight), file!(), line!()));
+        }
+        if position_weight == 0.0 && orientation_weight == 0.0 {
+            return Err(OptimaError::new_generic_error_str("At least one pose error weight must be positive.", file!(), line!()));
+        }
+
+        Ok(Self {
+            position_weight,
+            orientation_weight,
+            orientation_error_type,
+            position_mask: [true; 3],
+            orientation_mask: [true; 3]
+        })
+    }
+    /// Only the position is constrained.
+    pub fn new_position_only() -> Self {
+        return Self::new(1.0, 0.0, OrientationErrorType::AxisAngle).expect("error");
+    }
+    /// Only the orientation is constrained.
+    pub fn new_orientation_only() -> Self {
+        return Self::new(0.0, 1.0, OrientationErrorType::AxisAngle).expect("error");
+    }
+    /// Sets which position axes, in the target frame, are constrained.
+    pub fn set_position_mask(&mut self, mask: [bool; 3]) {
+        self.position_mask = mask;
+    }
+    /// Sets which rotation axes, in the target frame, are constrained.
+    pub fn set_orientation_mask(&mut self, mask: [bool; 3]) {
+        self.orientation_mask = mask;
+    }
+    /// Returns true if the position part of the error is used at all.
+    pub fn constrains_position(&self) -> bool {
+        return self.position_weight > 0.0 && self.position_mask.iter().any(|m| *m);
+    }
+    /// Returns true if the orientation part of the error is used at all.
+    pub fn constrains_orientation(&self) -> bool {
+        return self.orientation_weight > 0.0 && self.orientation_mask.iter().any(|m| *m);
+    }
+    /// The masked but unweighted error \[position; orientation\], in the world frame.
+    pub fn error_vector(&self, current: &OptimaSE3Pose, target: &OptimaSE3Pose) -> Vector6<f64> {
+        return self.error_vector_from_isometries(&current.to_nalgebra_isometry(), &target.to_nalgebra_isometry());
+    }
+    pub fn error_vector_from_isometries(&self, current: &Isometry3<f64>, target: &Isometry3<f64>) -> Vector6<f64> {
+        let position_error = target.translation.vector - current.translation.vector;
+        let error_rotation = target.rotation * current.rotation.inverse();
+        let orientation_error = match self.orientation_error_type {
+            OrientationErrorType::AxisAngle => { error_rotation.scaled_axis() }
+            OrientationErrorType::Quaternion => {
+                let v = error_rotation.vector();
+                if error_rotation.w < 0.0 { -2.0 * v } else { 2.0 * v }
+            }
+        };
+
+        let position_error = Self::mask_projection(target, &self.position_mask) * position_error;
+        let orientation_error = Self::mask_projection(target, &self.orientation_mask) * orientation_error;
+
+        return Vector6::new(position_error[0], position_error[1], position_error[2], orientation_error[0], orientation_error[1], orientation_error[2]);
+    }
+    /// The 6 x 6 matrix W such that W * e is the weighted, masked error for an unmasked world frame
+    /// error e.  Multiplying a \[linear; angular\] Jacobian by W gives the Jacobian of the weighted
+    /// error, so masked directions are left free rather than held fixed.
+    pub fn weight_matrix(&self, target: &OptimaSE3Pose) -> Matrix6<f64> {
+        let target = target.to_nalgebra_isometry();
+        let mut out = Matrix6::zeros();
+        out.fixed_slice_mut::<3, 3>(0, 0).copy_from(&(self.position_weight * Self::mask_projection(&target, &self.position_mask)));
+        out.fixed_slice_mut::<3, 3>(3, 3).copy_from(&(self.orientation_weight * Self::mask_projection(&target, &self.orientation_mask)));
+        out
+    }
+    /// The masked and weighted error vector.
+    pub fn weighted_error_vector(&self, current: &OptimaSE3Pose, target: &OptimaSE3Pose) -> Vector6<f64> {
+        let mut e = self.error_vector(current, target);
+        for i in 0..3 { e[i] *= self.position_weight; }
+        for i in 3..6 { e[i] *= self.orientation_weight; }
+        e
+    }
+    /// The norm of the masked and weighted error vector.
+    pub fn error(&self, current: &OptimaSE3Pose, target: &OptimaSE3Pose) -> f64 {
+        return self.weighted_error_vector(current, target).norm();
+    }
+    /// The norm of the masked position error (unweighted, in meters).
+    pub fn position_error(&self, current: &OptimaSE3Pose, target: &OptimaSE3Pose) -> f64 {
+        return self.error_vector(current, target).fixed_rows::<3>(0).norm();
+    }
+    /// The norm of the masked orientation error (unweighted).
+    pub fn orientation_error(&self, current: &OptimaSE3Pose, target: &OptimaSE3Pose) -> f64 {
+        return self.error_vector(current, target).fixed_rows::<3>(3).norm();
+    }
+    /// Returns true if the masked position and orientation errors are within their tolerances.  The
+    /// position (or orientation) check is skipped if the metric does not constrain position (or
+    /// orientation).
+    pub fn is_within_tolerance(&self, current: &OptimaSE3Pose, target: &OptimaSE3Pose, position_tolerance: f64, orientation_tolerance: f64) -> bool {
+        let e = self.error_vector(current, target);
+        if self.constrains_position() && e.fixed_rows::<3>(0).norm() > position_tolerance { return false; }
+        if self.constrains_orientation() && e.fixed_rows::<3>(3).norm() > orientation_tolerance { return false; }
+        return true;
+    }
+    pub fn position_weight(&self) -> f64 {
+        self.position_weight
+    }
+    pub fn orientation_weight(&self) -> f64 {
+        self.orientation_weight
+    }
+    pub fn orientation_error_type(&self) -> &OrientationErrorType {
+        &self.orientation_error_type
+    }
+    pub fn position_mask(&self) -> [bool; 3] {
+        self.position_mask
+    }
+    pub fn orientation_mask(&self) -> [bool; 3] {
+        self.orientation_mask
+    }
+    /// R M R^T, where R is the target rotation and M is the diagonal mask matrix.
+    fn mask_projection(target: &Isometry3<f64>, mask: &[bool; 3]) -> Matrix3<f64> {
+        if mask.iter().all(|m| *m) { return Matrix3::identity(); }
+        let r = target.rotation.to_rotation_matrix().into_inner();
+        let m = Matrix3::from_diagonal(&Vector3::new(mask[0] as u8 as f64, mask[1] as u8 as f64, mask[2] as u8 as f64));
+        return r * m * r.transpose();
+    }
+}
+impl Default for PoseErrorMetric {
+    fn default() -> Self {
+        return Self::new(1.0, 1.0, OrientationErrorType::AxisAngle).expect("error");
+    }
+}
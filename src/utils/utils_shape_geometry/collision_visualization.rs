@@ -0,0 +1,132 @@
+#[cfg(not(target_arch = "wasm32"))]
+use pyo3::*;
+
+use nalgebra::Vector3;
+use serde::{Serialize, Deserialize};
+use crate::utils::utils_shape_geometry::geometric_shape::{ClosestPointsWrapper, GeometricShapeQueryGroupOutput, GeometricShapeQueryRawOutput, GeometricShapeSignature};
+use crate::utils::utils_traits::ToAndFromJsonString;
+
+/// Renderable primitives extracted from the outputs of a group of geometric shape queries, to make
+/// it easy to see why a state is (or is nearly) in collision.  Three kinds of primitives are kept:
+/// - witness segments: the segment between the closest (or deepest) points of a pair of shapes,
+/// - contact normals: the contact normal on each shape of a pair, anchored at its witness point,
+/// - penetrating pairs: the pairs of shapes that intersect, so they can be highlighted.
+///
+/// Only outputs of pair queries carry this information (`Contact`, `ClosestPoints`,
+/// `IntersectionTest`, and `Distance`); other outputs are ignored.  The primitives are plain data,
+/// so they can be drawn with any tool, serialized, or added to a glTF file with
+/// `GltfSceneExporter::add_collision_visualization`.
+#[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
+#[cfg_attr(target_arch = "wasm32", derive(Clone, Debug, Serialize, Deserialize))]
+pub struct CollisionVisualization {
+    witness_segments: Vec<WitnessSegment>,
+    contact_normals: Vec<ContactNormal>,
+    penetrating_pairs: Vec<PenetratingPair>
+}
+impl CollisionVisualization {
+    pub fn new_from_group_output(group_output: &GeometricShapeQueryGroupOutput) -> Self {
+        let mut out_self = Self {
+            witness_segments: vec![],
+            contact_normals: vec![],
+            penetrating_pairs: vec![]
+        };
+
+        for output in group_output.outputs() {
+            let signatures = output.signatures();
+            if signatures.len() < 2 { continue; }
+            let shape_signatures = (signatures[0].clone(), signatures[1].clone());
+
+            match output.raw_output() {
+                GeometricShapeQueryRawOutput::Contact(c) => {
+                    if let Some(c) = c {
+                        out_self.witness_segments.push(WitnessSegment { points: (c.point1, c.point2), distance: c.dist, shape_signatures: shape_signatures.clone() });
+                        out_self.contact_normals.push(ContactNormal { point: c.point1, normal: c.normal1, shape_signature: shape_signatures.0.clone() });
+                        out_self.contact_normals.push(ContactNormal { point: c.point2, normal: c.normal2, shape_signature: shape_signatures.1.clone() });
+                        if c.dist < 0.0 {
+                            out_self.penetrating_pairs.push(PenetratingPair { penetration_depth: Some(-c.dist), shape_signatures });
+                        }
+                    }
+                }
+                GeometricShapeQueryRawOutput::ClosestPoints(c) => {
+                    match c {
+                        ClosestPointsWrapper::Intersecting => {
+                            out_self.penetrating_pairs.push(PenetratingPair { penetration_depth: None, shape_signatures });
+                        }
+                        ClosestPointsWrapper::WithinMargin(a, b) => {
+                            out_self.witness_segments.push(WitnessSegment { points: (*a, *b), distance: (b - a).norm(), shape_signatures });
+                        }
+                        ClosestPointsWrapper::Disjoint => {}
+                    }
+                }
+                GeometricShapeQueryRawOutput::IntersectionTest(intersecting) => {
+                    if *intersecting {
+                        out_self.penetrating_pairs.push(PenetratingPair { penetration_depth: None, shape_signatures });
+                    }
+                }
+                GeometricShapeQueryRawOutput::Distance(d) => {
+                    if *d <= 0.0 {
+                        out_self.penetrating_pairs.push(PenetratingPair { penetration_depth: None, shape_signatures });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        out_self
+    }
+    pub fn witness_segments(&self) -> &Vec<WitnessSegment> {
+        &self.witness_segments
+    }
+    pub fn contact_normals(&self) -> &Vec<ContactNormal> {
+        &self.contact_normals
+    }
+    pub fn penetrating_pairs(&self) -> &Vec<PenetratingPair> {
+        &self.penetrating_pairs
+    }
+    pub fn is_empty(&self) -> bool {
+        self.witness_segments.is_empty() && self.contact_normals.is_empty() && self.penetrating_pairs.is_empty()
+    }
+}
+
+/// Python implementations.
+#[cfg(not(target_arch = "wasm32"))]
+#[pymethods]
+impl CollisionVisualization {
+    pub fn to_json_string_py(&self) -> String {
+        self.to_json_string()
+    }
+    pub fn witness_segments_py(&self) -> Vec<(Vec<f64>, Vec<f64>, f64)> {
+        self.witness_segments.iter().map(|s| (s.points.0.as_slice().to_vec(), s.points.1.as_slice().to_vec(), s.distance)).collect()
+    }
+    pub fn contact_normals_py(&self) -> Vec<(Vec<f64>, Vec<f64>)> {
+        self.contact_normals.iter().map(|n| (n.point.as_slice().to_vec(), n.normal.as_slice().to_vec())).collect()
+    }
+    pub fn num_penetrating_pairs_py(&self) -> usize {
+        self.penetrating_pairs.len()
+    }
+}
+
+/// The segment between the witness points of a pair of shapes.  `distance` is negative if the
+/// shapes penetrate.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WitnessSegment {
+    pub points: (Vector3<f64>, Vector3<f64>),
+    pub distance: f64,
+    pub shape_signatures: (GeometricShapeSignature, GeometricShapeSignature)
+}
+
+/// A unit contact normal of a shape, anchored at the shape's witness point.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContactNormal {
+    pub point: Vector3<f64>,
+    pub normal: Vector3<f64>,
+    pub shape_signature: GeometricShapeSignature
+}
+
+/// A pair of intersecting shapes.  `penetration_depth` is only known when it came from a `Contact`
+/// query.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PenetratingPair {
+    pub penetration_depth: Option<f64>,
+    pub shape_signatures: (GeometricShapeSignature, GeometricShapeSignature)
+}
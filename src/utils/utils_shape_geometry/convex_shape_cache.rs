@@ -0,0 +1,142 @@
+use std::sync::Mutex;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_files::optima_path::{OptimaAssetLocation, OptimaStemCellPath};
+use crate::utils::utils_shape_geometry::trimesh_engine::{ConvexDecompositionResolution, TrimeshEngine};
+
+static CONVEX_SHAPE_CACHE_ENABLED: Mutex<bool> = Mutex::new(true);
+
+const CONVEX_HULL_FILENAME: &str = "convex_hull.stl";
+const ENTRY_INFO_FILENAME: &str = "entry_info.JSON";
+
+/// A cache of convex hulls and convex decompositions shared by all robots and scenes, stored in the
+/// `convex_shape_cache` directory of the asset folder.  Computing these (the decomposition in
+/// particular) dominates preprocessing time, and many robots and configurations use the same meshes,
+/// so results are computed once per mesh and reused from then on.
+///
+/// Entries are keyed by a hash of the mesh content (its vertices and triangle indices; see
+/// `mesh_content_hash`), not by its path, so an entry is reused for the same mesh under any name and
+/// is never reused after a mesh changes.  Each entry directory holds the convex hull
+/// (`convex_hull.stl`) and one `convex_subcomponents_<resolution>` directory per decomposition
+/// resolution.  An entry info file is written after the shapes, so an interrupted write is never
+/// read back as a complete entry.
+///
+/// If the cache cannot be written (e.g., when only embedded assets are available), results are
+/// still computed and returned, just not cached.  Use `set_enabled(false)` to bypass the cache.
+pub struct ConvexShapeCache;
+impl ConvexShapeCache {
+    pub fn get_or_compute_convex_hull(trimesh_engine: &TrimeshEngine) -> Result<TrimeshEngine, OptimaError> {
+        if !Self::enabled() { return Ok(trimesh_engine.compute_convex_hull()); }
+
+        let entry_path = Self::entry_path(trimesh_engine)?;
+        let mut info = Self::load_entry_info(&entry_path);
+        let mut convex_hull_path = entry_path.clone();
+        convex_hull_path.append(CONVEX_HULL_FILENAME);
+        if info.has_convex_hull && convex_hull_path.exists() {
+            if let Ok(convex_hull) = convex_hull_path.load_stl_to_trimesh_engine() { return Ok(convex_hull); }
+        }
+
+        let convex_hull = trimesh_engine.compute_convex_hull();
+        // A failed write only means the result is not cached.
+        if convex_hull_path.save_trimesh_engine_to_stl(&convex_hull).is_ok() {
+            info.has_convex_hull = true;
+            Self::save_entry_info(&entry_path, &info).ok();
+        }
+        Ok(convex_hull)
+    }
+    pub fn get_or_compute_convex_decomposition(trimesh_engine: &TrimeshEngine, resolution: ConvexDecompositionResolution) -> Result<Vec<TrimeshEngine>, OptimaError> {
+        if !Self::enabled() { return Ok(trimesh_engine.compute_convex_decomposition(resolution)); }
+
+        let entry_path = Self::entry_path(trimesh_engine)?;
+        let mut info = Self::load_entry_info(&entry_path);
+        let directory_name = format!("convex_subcomponents_{:?}", resolution);
+        let mut directory_path = entry_path.clone();
+        directory_path.append(&directory_name);
+
+        if let Some((_, num_subcomponents)) = info.convex_decompositions.iter().find(|(d, _)| d == &directory_name) {
+            let mut out_vec = vec![];
+            for i in 0..*num_subcomponents {
+                let mut path = directory_path.clone();
+                path.append(&format!("{}.stl", i));
+                match path.load_stl_to_trimesh_engine() {
+                    Ok(c) => { out_vec.push(c); }
+                    Err(_) => { break; }
+                }
+            }
+            if out_vec.len() == *num_subcomponents { return Ok(out_vec); }
+        }
+
+        let convex_subcomponents = trimesh_engine.compute_convex_decomposition(resolution);
+        let mut saved = true;
+        for (i, c) in convex_subcomponents.iter().enumerate() {
+            let mut path = directory_path.clone();
+            path.append(&format!("{}.stl", i));
+            if path.save_trimesh_engine_to_stl(c).is_err() { saved = false; break; }
+        }
+        // A failed write only means the result is not cached.
+        if saved {
+            info.convex_decompositions.retain(|(d, _)| d != &directory_name);
+            info.convex_decompositions.push((directory_name, convex_subcomponents.len()));
+            Self::save_entry_info(&entry_path, &info).ok();
+        }
+        Ok(convex_subcomponents)
+    }
+    /// Hash (lowercase hex SHA-256) of the mesh's vertices and triangle indices.  Meshes with the same
+    /// geometry have the same hash regardless of their file name or format.
+    pub fn mesh_content_hash(trimesh_engine: &TrimeshEngine) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update((trimesh_engine.vertices().len() as u64).to_le_bytes());
+        for v in trimesh_engine.vertices() {
+            for x in v.iter() { hasher.update(x.to_le_bytes()); }
+        }
+        hasher.update((trimesh_engine.indices().len() as u64).to_le_bytes());
+        for t in trimesh_engine.indices() {
+            for i in t { hasher.update((*i as u64).to_le_bytes()); }
+        }
+        return format!("{:x}", hasher.finalize());
+    }
+    /// Deletes every cache entry.
+    pub fn clear() -> Result<(), OptimaError> {
+        let mut path = OptimaStemCellPath::new_asset_path()?;
+        path.append_file_location(&OptimaAssetLocation::ConvexShapeCache);
+        if !path.exists() { return Ok(()); }
+        return path.delete_all_items_in_directory();
+    }
+    pub fn set_enabled(enabled: bool) {
+        *Self::lock_enabled() = enabled;
+    }
+    pub fn enabled() -> bool {
+        return *Self::lock_enabled();
+    }
+    fn entry_path(trimesh_engine: &TrimeshEngine) -> Result<OptimaStemCellPath, OptimaError> {
+        let mut path = OptimaStemCellPath::new_asset_path()?;
+        path.append_file_location(&OptimaAssetLocation::ConvexShapeCacheEntry { mesh_hash: Self::mesh_content_hash(trimesh_engine) });
+        Ok(path)
+    }
+    fn load_entry_info(entry_path: &OptimaStemCellPath) -> ConvexShapeCacheEntryInfo {
+        let mut path = entry_path.clone();
+        path.append(ENTRY_INFO_FILENAME);
+        if !path.exists() { return ConvexShapeCacheEntryInfo::default(); }
+        return path.load_object_from_json_file().unwrap_or_default();
+    }
+    fn save_entry_info(entry_path: &OptimaStemCellPath, info: &ConvexShapeCacheEntryInfo) -> Result<(), OptimaError> {
+        let mut path = entry_path.clone();
+        path.append(ENTRY_INFO_FILENAME);
+        return path.save_object_to_file_as_json(info);
+    }
+    fn lock_enabled() -> std::sync::MutexGuard<'static, bool> {
+        return match CONVEX_SHAPE_CACHE_ENABLED.lock() {
+            Ok(guard) => { guard }
+            Err(poisoned) => { poisoned.into_inner() }
+        };
+    }
+}
+
+/// Which results a cache entry holds: whether it has a convex hull, and the number of
+/// subcomponents of each cached decomposition (by directory name).
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct ConvexShapeCacheEntryInfo {
+    has_convex_hull: bool,
+    convex_decompositions: Vec<(String, usize)>
+}
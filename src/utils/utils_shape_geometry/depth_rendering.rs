@@ -0,0 +1,184 @@
+use nalgebra::{Point3, Vector3};
+use parry3d_f64::query::Ray;
+use serde::{Serialize, Deserialize};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_se3::optima_se3_pose::OptimaSE3Pose;
+use crate::utils::utils_shape_geometry::geometric_shape::GeometricShapeSignature;
+use crate::utils::utils_shape_geometry::shape_collection::{ShapeCollection, ShapeCollectionInputPoses, ShapeCollectionQueryList};
+
+/// Pinhole camera intrinsics.  The camera frame follows the usual computer vision convention: the
+/// camera looks down its local +z axis, +x points right in the image, and +y points down.  Pixel
+/// (u, v) is column u, row v, and its center is at (u + 0.5, v + 0.5) in image coordinates.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CameraIntrinsics {
+    width: usize,
+    height: usize,
+    fx: f64,
+    fy: f64,
+    cx: f64,
+    cy: f64
+}
+impl CameraIntrinsics {
+    pub fn new(width: usize, height: usize, fx: f64, fy: f64, cx: f64, cy: f64) -> Result<Self, OptimaError> {
+        if width == 0 || height == 0 {
+            return Err(OptimaError::new_generic_error_str(&format!("Image dimensions must be positive (given {} x {}).", width, height), file!(), line!()));
+        }
+        if !(fx > 0.0 && fy > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Focal lengths must be positive (given {}, {}).", fx, fy), file!(), line!()));
+        }
+
+        Ok(Self {
+            width,
+            height,
+            fx,
+            fy,
+            cx,
+            cy
+        })
+    }
+    /// Square pixels, principal point at the image center, and the given vertical field of view
+    /// (in radians).
+    pub fn new_from_vertical_fov(width: usize, height: usize, vertical_fov: f64) -> Result<Self, OptimaError> {
+        if !(vertical_fov > 0.0 && vertical_fov < std::f64::consts::PI) {
+            return Err(OptimaError::new_generic_error_str(&format!("Vertical field of view must be in (0, pi) (given {}).", vertical_fov), file!(), line!()));
+        }
+        let f = (height as f64 / 2.0) / (vertical_fov / 2.0).tan();
+        return Self::new(width, height, f, f, width as f64 / 2.0, height as f64 / 2.0);
+    }
+    /// Direction of the ray through the center of pixel (u, v) in the camera frame, scaled so that
+    /// its z component is 1.
+    pub fn pixel_direction(&self, u: usize, v: usize) -> Vector3<f64> {
+        return Vector3::new((u as f64 + 0.5 - self.cx) / self.fx, (v as f64 + 0.5 - self.cy) / self.fy, 1.0);
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    pub fn fx(&self) -> f64 {
+        self.fx
+    }
+    pub fn fy(&self) -> f64 {
+        self.fy
+    }
+    pub fn cx(&self) -> f64 {
+        self.cx
+    }
+    pub fn cy(&self) -> f64 {
+        self.cy
+    }
+}
+
+/// A simple software depth renderer over a `ShapeCollection`.  One ray is cast through the center
+/// of each pixel (see `ShapeCollection::batch_cast_rays`), so there is no anti-aliasing, but the
+/// output is exact with respect to the collision geometry.  Note that the rendered geometry is the
+/// collision representation (e.g., convex shapes or cubes), not the visual meshes.
+pub struct DepthRenderer;
+impl DepthRenderer {
+    /// camera_pose is the pose of the camera frame in the world frame.  Pixels where nothing is hit
+    /// within max_depth have a depth of f64::INFINITY.
+    pub fn render_depth_image(shape_collection: &ShapeCollection,
+                              poses: &ShapeCollectionInputPoses,
+                              intrinsics: &CameraIntrinsics,
+                              camera_pose: &OptimaSE3Pose,
+                              max_depth: f64,
+                              inclusion_list: &Option<&ShapeCollectionQueryList>) -> DepthImage {
+        let isometry = camera_pose.to_nalgebra_isometry();
+        let origin = Point3::from(isometry.translation.vector);
+
+        let mut rays = vec![];
+        for v in 0..intrinsics.height {
+            for u in 0..intrinsics.width {
+                let direction = isometry.rotation * intrinsics.pixel_direction(u, v);
+                rays.push(Ray::new(origin, direction));
+            }
+        }
+
+        // Since each direction has a z component of 1 in the camera frame, time of impact is depth.
+        let res = shape_collection.batch_cast_rays(poses, &rays, max_depth, true, inclusion_list);
+
+        let mut depths = vec![];
+        let mut signatures = vec![];
+        for hit in res.hits() {
+            match hit {
+                None => {
+                    depths.push(f64::INFINITY);
+                    signatures.push(None);
+                }
+                Some(hit) => {
+                    depths.push(hit.toi());
+                    signatures.push(Some(hit.signature().clone()));
+                }
+            }
+        }
+
+        DepthImage {
+            intrinsics: intrinsics.clone(),
+            camera_pose: camera_pose.clone(),
+            depths,
+            signatures
+        }
+    }
+}
+
+/// A row-major depth image output by `DepthRenderer`, along with the signature of the shape seen at
+/// each pixel (i.e., a segmentation mask).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DepthImage {
+    intrinsics: CameraIntrinsics,
+    camera_pose: OptimaSE3Pose,
+    depths: Vec<f64>,
+    signatures: Vec<Option<GeometricShapeSignature>>
+}
+impl DepthImage {
+    pub fn depth(&self, u: usize, v: usize) -> Result<f64, OptimaError> {
+        let idx = self.pixel_idx(u, v)?;
+        return Ok(self.depths[idx]);
+    }
+    pub fn signature(&self, u: usize, v: usize) -> Result<&Option<GeometricShapeSignature>, OptimaError> {
+        let idx = self.pixel_idx(u, v)?;
+        return Ok(&self.signatures[idx]);
+    }
+    /// Returns the depth image as height rows of width depths.
+    pub fn to_rows(&self) -> Vec<Vec<f64>> {
+        let mut out_vec = vec![];
+        for row in self.depths.chunks(self.intrinsics.width) {
+            out_vec.push(row.to_vec());
+        }
+        out_vec
+    }
+    /// Returns a point for each pixel with a finite depth, in the camera frame.
+    pub fn point_cloud_in_camera_frame(&self) -> Vec<Vector3<f64>> {
+        let mut out_vec = vec![];
+        for v in 0..self.intrinsics.height {
+            for u in 0..self.intrinsics.width {
+                let depth = self.depths[v * self.intrinsics.width + u];
+                if depth.is_finite() { out_vec.push(depth * self.intrinsics.pixel_direction(u, v)); }
+            }
+        }
+        out_vec
+    }
+    /// Returns a point for each pixel with a finite depth, in the world frame.
+    pub fn point_cloud_in_world_frame(&self) -> Vec<Vector3<f64>> {
+        let isometry = self.camera_pose.to_nalgebra_isometry();
+        return self.point_cloud_in_camera_frame().iter().map(|p| isometry.transform_point(&Point3::from(*p)).coords).collect();
+    }
+    pub fn intrinsics(&self) -> &CameraIntrinsics {
+        &self.intrinsics
+    }
+    pub fn camera_pose(&self) -> &OptimaSE3Pose {
+        &self.camera_pose
+    }
+    pub fn depths(&self) -> &Vec<f64> {
+        &self.depths
+    }
+    pub fn signatures(&self) -> &Vec<Option<GeometricShapeSignature>> {
+        &self.signatures
+    }
+    fn pixel_idx(&self, u: usize, v: usize) -> Result<usize, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(u, self.intrinsics.width, file!(), line!())?;
+        OptimaError::new_check_for_idx_out_of_bound_error(v, self.intrinsics.height, file!(), line!())?;
+        return Ok(v * self.intrinsics.width + u);
+    }
+}
@@ -1,20 +1,28 @@
 #[cfg(not(target_arch = "wasm32"))]
 use pyo3::*;
 
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use std::borrow::Borrow;
 use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
 use std::time::{Duration};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use nalgebra::{Isometry3, Point3, Unit, Vector3};
 use parry3d_f64::query::{ClosestPoints, Contact, NonlinearRigidMotion, PointProjection, Ray, RayIntersection};
+use parry3d_f64::query::gjk::{self, CSOPoint, GJKResult, VoronoiSimplex};
 use parry3d_f64::shape::{Ball, ConvexPolyhedron, Cuboid, Shape, TriMesh};
 use crate::utils::utils_console::{optima_print, PrintColor, PrintMode};
 use crate::utils::utils_errors::OptimaError;
-use crate::utils::utils_files::optima_path::{load_object_from_json_string, OptimaStemCellPath};
+use crate::utils::utils_files::optima_path::OptimaStemCellPath;
 use crate::utils::utils_generic_data_structures::EnumMapToType;
 use crate::utils::utils_nalgebra::conversions::NalgebraConversions;
 use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3Pose, OptimaSE3PoseAll, OptimaSE3PoseType};
+use crate::utils::utils_shape_geometry::collision_visualization::CollisionVisualization;
+use crate::utils::utils_shape_geometry::query_output_summary::QueryOutputSummary;
 use crate::utils::utils_shape_geometry::shape_collection::{WitnessPoints, WitnessPointsCollection, WitnessPointsType};
+use crate::utils::utils_shape_geometry::shared_mesh_store::{SharedMeshStore, SharedMeshType};
 use crate::utils::utils_shape_geometry::trimesh_engine::TrimeshEngine;
 use crate::utils::utils_traits::{SaveAndLoadable, ToAndFromJsonString};
 
@@ -113,12 +121,14 @@ impl GeometricShape {
             signature: signature.clone()
         };
 
-        let points: Vec<Point3<f64>> = trimesh_engine.vertices().iter().map(|v| NalgebraConversions::vector3_to_point3(v)).collect();
-        let convex_shape = ConvexPolyhedron::from_convex_hull(&points).expect("error");
-        let f = trimesh_engine.compute_f();
+        let (shape, f) = SharedMeshStore::get_or_insert_with(SharedMeshType::ConvexShape, trimesh_engine.path_string_components(), || {
+            let points: Vec<Point3<f64>> = trimesh_engine.vertices().iter().map(|v| NalgebraConversions::vector3_to_point3(v)).collect();
+            let convex_shape: Arc<dyn Shape> = Arc::new(ConvexPolyhedron::from_convex_hull(&points).expect("error"));
+            (convex_shape, trimesh_engine.compute_f())
+        });
 
         Self {
-            shape: Box::new(Arc::new(convex_shape)),
+            shape: Box::new(shape),
             signature,
             initial_pose_of_shape: None,
             f,
@@ -132,20 +142,44 @@ impl GeometricShape {
             signature: signature.clone()
         };
 
-        let points: Vec<Point3<f64>> = trimesh_engine.vertices().iter().map(|v| NalgebraConversions::vector3_to_point3(v)).collect();
-        let indices: Vec<[u32; 3]> = trimesh_engine.indices().iter().map(|i| [i[0] as u32, i[1] as u32, i[2] as u32] ).collect();
-        let f = trimesh_engine.compute_f();
-
-        let tri_mesh = TriMesh::new(points, indices);
+        let (shape, f) = SharedMeshStore::get_or_insert_with(SharedMeshType::TriangleMesh, trimesh_engine.path_string_components(), || {
+            let points: Vec<Point3<f64>> = trimesh_engine.vertices().iter().map(|v| NalgebraConversions::vector3_to_point3(v)).collect();
+            let indices: Vec<[u32; 3]> = trimesh_engine.indices().iter().map(|i| [i[0] as u32, i[1] as u32, i[2] as u32] ).collect();
+            let tri_mesh: Arc<dyn Shape> = Arc::new(TriMesh::new(points, indices));
+            (tri_mesh, trimesh_engine.compute_f())
+        });
 
         Self {
-            shape: Box::new(Arc::new(tri_mesh)),
+            shape: Box::new(shape),
             signature,
             initial_pose_of_shape: None,
             f,
             spawner
         }
     }
+    /// Spawns a convex shape or triangle mesh from its asset path, reusing the geometry in the
+    /// `SharedMeshStore` (without loading the file) if it is there.
+    fn new_mesh_shape_from_path_string_components(mesh_type: SharedMeshType, path_string_components: &Vec<String>, signature: GeometricShapeSignature) -> Self {
+        if let Some((shape, f)) = SharedMeshStore::get(mesh_type, path_string_components) {
+            let spawner = match mesh_type {
+                SharedMeshType::ConvexShape => { GeometricShapeSpawner::ConvexShape { path_string_components: path_string_components.clone(), trimesh_engine: None, signature: signature.clone() } }
+                SharedMeshType::TriangleMesh => { GeometricShapeSpawner::TriangleMesh { path_string_components: path_string_components.clone(), trimesh_engine: None, signature: signature.clone() } }
+            };
+            return Self {
+                shape: Box::new(shape),
+                signature,
+                initial_pose_of_shape: None,
+                f,
+                spawner
+            };
+        }
+
+        let path = OptimaStemCellPath::new_asset_path_from_string_components(path_string_components).expect("error");
+        return match mesh_type {
+            SharedMeshType::ConvexShape => { Self::new_convex_shape(&path, signature) }
+            SharedMeshType::TriangleMesh => { Self::new_triangle_mesh(&path, signature) }
+        };
+    }
     pub fn to_best_fit_cube(&self) -> Self {
         let aabb = self.shape.compute_aabb(&Isometry3::identity());
         let center = aabb.center();
@@ -184,6 +218,15 @@ impl GeometricShape {
     pub fn cast_ray_and_get_normal(&self, pose: &OptimaSE3Pose, ray: &Ray, max_toi: f64, solid: bool) -> Option<RayIntersection> {
         self.shape.cast_ray_and_get_normal(&self.recover_transformed_pose_wrt_initial_pose(pose).to_nalgebra_isometry(), ray, max_toi, solid)
     }
+    /// Returns the isometry of the underlying shape at the given pose (accounting for the shape's
+    /// initial pose).  This can be computed once and reused with `cast_ray_and_get_normal_at_isometry`
+    /// when many rays are cast against the same posed shape.
+    pub fn isometry_at_pose(&self, pose: &OptimaSE3Pose) -> Isometry3<f64> {
+        self.recover_transformed_pose_wrt_initial_pose(pose).to_nalgebra_isometry()
+    }
+    pub fn cast_ray_and_get_normal_at_isometry(&self, isometry: &Isometry3<f64>, ray: &Ray, max_toi: f64, solid: bool) -> Option<RayIntersection> {
+        self.shape.cast_ray_and_get_normal(isometry, ray, max_toi, solid)
+    }
     fn recover_initial_pose_all_of_shape_from_option(initial_pose_of_shape: Option<OptimaSE3Pose>) -> Option<OptimaSE3PoseAll> {
         return match initial_pose_of_shape {
             None => { None }
@@ -215,8 +258,16 @@ impl GeometricShape {
     }
 }
 impl Clone for GeometricShape {
+    /// Clones share the underlying parry3d geometry.  Shapes are never mutated after construction, so
+    /// there is no need to rebuild (or reload) them from the spawner.
     fn clone(&self) -> Self {
-        self.spawner.spawn()
+        Self {
+            shape: self.shape.clone(),
+            signature: self.signature.clone(),
+            initial_pose_of_shape: self.initial_pose_of_shape.clone(),
+            f: self.f,
+            spawner: self.spawner.clone()
+        }
     }
 }
 impl Serialize for GeometricShape {
@@ -243,7 +294,7 @@ impl SaveAndLoadable for GeometricShape {
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let spawner: Self::SaveType = load_object_from_json_string(json_str)?;
+        let spawner: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
         return Ok(spawner.spawn());
     }
 }
@@ -252,48 +303,109 @@ impl SaveAndLoadable for GeometricShape {
 pub struct GeometricShapeQueries;
 impl GeometricShapeQueries {
     pub fn generic_group_query(inputs: Vec<GeometricShapeQuery>, stop_condition: StopCondition, log_condition: LogCondition, sort_outputs: bool) -> GeometricShapeQueryGroupOutput {
+        return Self::generic_group_query_internal(inputs, None, stop_condition, log_condition, sort_outputs);
+    }
+    /// Same as `generic_group_query`, but the i-th input is computed with the i-th margin (see
+    /// `generic_query_with_margin`).  `margins` must be the same length as `inputs`.
+    pub fn generic_group_query_with_margins(inputs: Vec<GeometricShapeQuery>, margins: &Vec<f64>, stop_condition: StopCondition, log_condition: LogCondition, sort_outputs: bool) -> GeometricShapeQueryGroupOutput {
+        assert_eq!(inputs.len(), margins.len());
+        return Self::generic_group_query_internal(inputs, Some(margins), stop_condition, log_condition, sort_outputs);
+    }
+    fn generic_group_query_internal(inputs: Vec<GeometricShapeQuery>, margins: Option<&Vec<f64>>, stop_condition: StopCondition, log_condition: LogCondition, sort_outputs: bool) -> GeometricShapeQueryGroupOutput {
+        let mut out = GeometricShapeQueryGroupOutput::new_empty();
+        let margins = margins.map(|m| m.as_slice());
+        Self::generic_group_query_into(&inputs, margins, stop_condition, log_condition, sort_outputs, &mut vec![], &mut out);
+        out
+    }
+    /// Same as `generic_group_query_with_margins` (or `generic_group_query` if margins is None), but
+    /// writes into existing buffers so that their allocations can be reused across calls.  Signatures
+    /// are only cloned for outputs that are logged, so nothing is allocated for queries that are not
+    /// logged once the buffers have grown to their working size.
+    pub fn generic_group_query_into(inputs: &[GeometricShapeQuery],
+                                    margins: Option<&[f64]>,
+                                    stop_condition: StopCondition,
+                                    log_condition: LogCondition,
+                                    sort_outputs: bool,
+                                    output_distances: &mut Vec<f64>,
+                                    out: &mut GeometricShapeQueryGroupOutput) {
+        if let Some(margins) = margins { assert_eq!(inputs.len(), margins.len()); }
+
+        Self::generic_group_query_into_with(inputs, |i, input| {
+            match margins {
+                None => { Self::raw_query(input) }
+                Some(margins) => { Self::raw_query_with_margin(input, margins[i]) }
+            }
+        }, stop_condition, log_condition, sort_outputs, output_distances, out);
+    }
+    /// Same as `generic_group_query_into`, but the raw output of the i-th input is computed by
+    /// raw_query_fn(i, input), e.g., to warm start each query from cached state.  The inputs can be
+    /// any iterator over queries (or references to queries), so queries can also be built on the fly
+    /// rather than collected into a vec first.
+    pub fn generic_group_query_into_with<'q, I, F>(inputs: I,
+                                                   mut raw_query_fn: F,
+                                                   stop_condition: StopCondition,
+                                                   log_condition: LogCondition,
+                                                   sort_outputs: bool,
+                                                   output_distances: &mut Vec<f64>,
+                                                   out: &mut GeometricShapeQueryGroupOutput)
+        where I: IntoIterator,
+              I::Item: Borrow<GeometricShapeQuery<'q>>,
+              F: FnMut(usize, &GeometricShapeQuery) -> GeometricShapeQueryRawOutput {
         let start = instant::Instant::now();
-        let mut outputs = vec![];
-        let mut output_distances: Vec<f64> = vec![];
+        out.outputs.clear();
+        output_distances.clear();
         let mut num_queries = 0;
         let mut intersection_found = false;
         let mut minimum_distance = f64::INFINITY;
 
-        for input in &inputs {
-            let output = Self::generic_query(input);
+        for (i, input) in inputs.into_iter().enumerate() {
+            let input = input.borrow();
+            let query_start = instant::Instant::now();
+            let raw_output = raw_query_fn(i, input);
             num_queries += 1;
-            let proxy_dis = output.raw_output.proxy_dis();
+            let proxy_dis = raw_output.proxy_dis();
 
             if proxy_dis <= 0.0 { intersection_found = true; }
             if proxy_dis < minimum_distance { minimum_distance = proxy_dis; }
 
-            let stop = output.raw_output.trigger_stop(&stop_condition);
+            let stop = raw_output.trigger_stop(&stop_condition);
 
-            if output.raw_output.trigger_log(&log_condition) {
+            if raw_output.trigger_log(&log_condition) {
+                let output = GeometricShapeQueryOutput {
+                    raw_output,
+                    duration: query_start.elapsed(),
+                    signatures: input.get_signatures()
+                };
                 if sort_outputs {
                     let binary_search_res = output_distances.binary_search_by(|x| x.partial_cmp(&proxy_dis).unwrap() );
                     let idx = match binary_search_res { Ok(i) => {i} Err(i) => {i} };
                     output_distances.insert(idx, proxy_dis);
-                    outputs.insert(idx, output);
+                    out.outputs.insert(idx, output);
                 } else {
-                    outputs.push(output);
+                    out.outputs.push(output);
                 }
             }
 
             if stop { break; }
         }
 
-        return GeometricShapeQueryGroupOutput {
-            outputs,
-            duration: start.elapsed(),
-            num_queries,
-            intersection_found,
-            minimum_distance
-        }
+        out.duration = start.elapsed();
+        out.num_queries = num_queries;
+        out.intersection_found = intersection_found;
+        out.minimum_distance = minimum_distance;
     }
     pub fn generic_query(input: &GeometricShapeQuery) -> GeometricShapeQueryOutput {
         let start = instant::Instant::now();
-        let raw_output = match input {
+        let raw_output = Self::raw_query(input);
+
+        GeometricShapeQueryOutput {
+            raw_output,
+            duration: start.elapsed(),
+            signatures: input.get_signatures()
+        }
+    }
+    fn raw_query(input: &GeometricShapeQuery) -> GeometricShapeQueryRawOutput {
+        return match input {
             GeometricShapeQuery::ProjectPoint { object, pose, point, solid } => {
                 GeometricShapeQueryRawOutput::ProjectPoint(PointProjectionWrapper::new(&object.project_point(pose, point, *solid)))
             }
@@ -334,6 +446,15 @@ impl GeometricShapeQueries {
                 GeometricShapeQueryRawOutput::CCD(Self::ccd(object1, object1_pose_t1, object1_pose_t2, object2, object2_pose_t1, object2_pose_t2))
             }
         };
+    }
+    /// Same as `generic_query`, but the two shapes in a pairwise query are treated as if they were
+    /// padded by a (non-negative) margin: an `IntersectionTest` succeeds when the shapes are within
+    /// `margin` of each other, a `Distance` output is reduced by `margin`, and a `Contact` query
+    /// reports contacts up to `prediction + margin` apart with their distance reduced by `margin`.
+    /// Single shape queries, `ClosestPoints`, and `CCD` are not affected.
+    pub fn generic_query_with_margin(input: &GeometricShapeQuery, margin: f64) -> GeometricShapeQueryOutput {
+        let start = instant::Instant::now();
+        let raw_output = Self::raw_query_with_margin(input, margin);
 
         GeometricShapeQueryOutput {
             raw_output,
@@ -341,6 +462,46 @@ impl GeometricShapeQueries {
             signatures: input.get_signatures()
         }
     }
+    /// The raw output of `generic_query_with_margin`.
+    pub fn raw_query_with_margin(input: &GeometricShapeQuery, margin: f64) -> GeometricShapeQueryRawOutput {
+        if margin <= 0.0 { return Self::raw_query(input); }
+
+        return match input {
+            GeometricShapeQuery::IntersectionTest { object1, object1_pose, object2, object2_pose } => {
+                GeometricShapeQueryRawOutput::IntersectionTest(Self::distance(object1, object1_pose, object2, object2_pose) <= margin)
+            }
+            GeometricShapeQuery::Distance { object1, object1_pose, object2, object2_pose } => {
+                GeometricShapeQueryRawOutput::Distance(Self::distance(object1, object1_pose, object2, object2_pose) - margin)
+            }
+            GeometricShapeQuery::Contact { object1, object1_pose, object2, object2_pose, prediction } => {
+                let mut out = Self::contact(object1, object1_pose, object2, object2_pose, *prediction + margin);
+                if let Some(c) = &mut out { c.dist -= margin; }
+                GeometricShapeQueryRawOutput::Contact(out)
+            }
+            _ => { Self::raw_query(input) }
+        };
+    }
+    /// Same as `generic_query_with_margin`, but intersection tests, distance queries, and contact
+    /// queries between convex shapes are warm started from separating_direction, which is then
+    /// updated for the next call (see `distance_warm_started`).  Other queries ignore
+    /// separating_direction.
+    pub fn raw_query_warm_started(input: &GeometricShapeQuery, margin: f64, separating_direction: &mut Option<Vector3<f64>>) -> GeometricShapeQueryRawOutput {
+        let margin = margin.max(0.0);
+        return match input {
+            GeometricShapeQuery::IntersectionTest { object1, object1_pose, object2, object2_pose } => {
+                GeometricShapeQueryRawOutput::IntersectionTest(Self::distance_warm_started(object1, object1_pose, object2, object2_pose, separating_direction) <= margin)
+            }
+            GeometricShapeQuery::Distance { object1, object1_pose, object2, object2_pose } => {
+                GeometricShapeQueryRawOutput::Distance(Self::distance_warm_started(object1, object1_pose, object2, object2_pose, separating_direction) - margin)
+            }
+            GeometricShapeQuery::Contact { object1, object1_pose, object2, object2_pose, prediction } => {
+                let mut out = Self::contact_warm_started(object1, object1_pose, object2, object2_pose, *prediction + margin, separating_direction);
+                if let Some(c) = &mut out { c.dist -= margin; }
+                GeometricShapeQueryRawOutput::Contact(out)
+            }
+            _ => { Self::raw_query_with_margin(input, margin) }
+        };
+    }
 
     pub fn intersection_test(object1: &GeometricShape,
                              object1_pose: &OptimaSE3Pose,
@@ -388,6 +549,84 @@ impl GeometricShapeQueries {
             Some(contact) => { Some(ContactWrapper::new(contact)) }
         }
     }
+    /// Same as `distance`, but for temporally coherent queries (e.g., consecutive states along a
+    /// trajectory).  separating_direction is the direction from object2 toward object1, in the local
+    /// frame of object1, found by the previous query on this pair (None if there was none).  GJK is
+    /// started from this direction instead of from the direction between the shape origins, which
+    /// usually lets it converge in one or two iterations when the shapes moved only a little, and
+    /// separating_direction is updated with the new direction.
+    ///
+    /// Only pairs of convex shapes are warm started.  Other pairs (e.g., triangle meshes) use
+    /// `distance` and reset separating_direction to None.
+    pub fn distance_warm_started(object1: &GeometricShape,
+                                 object1_pose: &OptimaSE3Pose,
+                                 object2: &GeometricShape,
+                                 object2_pose: &OptimaSE3Pose,
+                                 separating_direction: &mut Option<Vector3<f64>>) -> f64 {
+        let pos1 = object1.recover_transformed_pose_wrt_initial_pose(object1_pose).to_nalgebra_isometry();
+        let pos2 = object2.recover_transformed_pose_wrt_initial_pose(object2_pose).to_nalgebra_isometry();
+
+        return match Self::gjk_warm_started(&pos1, object1, &pos2, object2, f64::MAX, separating_direction) {
+            Some(GJKResult::ClosestPoints(p1, p2, _)) => { (p2 - p1).norm() }
+            Some(GJKResult::Intersection) => { 0.0 }
+            _ => {
+                *separating_direction = None;
+                parry3d_f64::query::distance(&pos1, &**object1.shape, &pos2, &**object2.shape).expect("error")
+            }
+        }
+    }
+    /// Same as `contact`, but warm started from separating_direction (see `distance_warm_started`).
+    /// Penetrating pairs still fall back to `contact` since their depth is not found by GJK.
+    pub fn contact_warm_started(object1: &GeometricShape,
+                                object1_pose: &OptimaSE3Pose,
+                                object2: &GeometricShape,
+                                object2_pose: &OptimaSE3Pose,
+                                prediction: f64,
+                                separating_direction: &mut Option<Vector3<f64>>) -> Option<ContactWrapper> {
+        let pos1 = object1.recover_transformed_pose_wrt_initial_pose(object1_pose).to_nalgebra_isometry();
+        let pos2 = object2.recover_transformed_pose_wrt_initial_pose(object2_pose).to_nalgebra_isometry();
+
+        match Self::gjk_warm_started(&pos1, object1, &pos2, object2, prediction, separating_direction) {
+            Some(GJKResult::ClosestPoints(p1, p2, _)) => {
+                let dist = (p2 - p1).norm();
+                if dist > prediction { return None; }
+                // Points are in the local frame of object1.
+                if let Some(local_normal1) = Unit::try_new(p2 - p1, 1e-12) {
+                    let normal1 = pos1 * local_normal1;
+                    let contact = Contact::new(pos1 * p1, pos1 * p2, normal1, -normal1, dist);
+                    return Some(ContactWrapper::new(&contact));
+                }
+            }
+            Some(GJKResult::NoIntersection(_)) => { return None; }
+            Some(GJKResult::Intersection) => { }
+            _ => { *separating_direction = None; }
+        }
+
+        return Self::contact(object1, object1_pose, object2, object2_pose, prediction);
+    }
+    /// Runs GJK between the two shapes starting from separating_direction, if both shapes are convex
+    /// (i.e., support maps).  Returns None otherwise.
+    fn gjk_warm_started(pos1: &Isometry3<f64>,
+                        object1: &GeometricShape,
+                        pos2: &Isometry3<f64>,
+                        object2: &GeometricShape,
+                        max_dist: f64,
+                        separating_direction: &mut Option<Vector3<f64>>) -> Option<GJKResult> {
+        let g1 = object1.shape.as_support_map()?;
+        let g2 = object2.shape.as_support_map()?;
+        let pos12 = pos1.inv_mul(pos2);
+
+        let dir = separating_direction.unwrap_or(-pos12.translation.vector);
+        let dir = Unit::try_new(dir, 1e-12).unwrap_or(Vector3::x_axis());
+        let mut simplex = VoronoiSimplex::new();
+        simplex.reset(CSOPoint::from_shapes_toward(&pos12, g1, g2, &dir));
+
+        let res = gjk::closest_points(&pos12, g1, g2, max_dist, true, &mut simplex);
+        if let GJKResult::ClosestPoints(p1, p2, _) = &res {
+            *separating_direction = Some(p1 - p2);
+        }
+        Some(res)
+    }
     /// Continuous collision detection.
     /// Returns None if the objects will never collide.  The CCDResult collision point is provided
     /// in world-space.
@@ -446,7 +685,9 @@ pub enum GeometricShapeSignature {
     None,
     RobotLink { link_idx: usize, shape_idx_in_link: usize },
     RobotSetLink { robot_idx_in_set: usize, link_idx_in_robot: usize, shape_idx_in_link: usize },
-    EnvironmentObject { environment_object_idx: usize, shape_idx_in_object: usize }
+    /// `name` is the name of the environment object in its scene (e.g., "table" or "shelf_2") so that
+    /// query outputs can identify which object was involved.
+    EnvironmentObject { environment_object_idx: usize, shape_idx_in_object: usize, #[serde(default)] name: String }
 }
 impl EnumMapToType<GeometricShapeSignatureType> for GeometricShapeSignature {
     fn map_to_type(&self) -> GeometricShapeSignatureType {
@@ -490,15 +731,13 @@ impl GeometricShapeSpawner {
                 if let Some(trimesh_engine) = trimesh_engine {
                     return GeometricShape::new_convex_shape_from_trimesh_engine(trimesh_engine, signature.clone());
                 }
-                let path = OptimaStemCellPath::new_asset_path_from_string_components(path_string_components).expect("error");
-                GeometricShape::new_convex_shape( &path, signature.clone() )
+                GeometricShape::new_mesh_shape_from_path_string_components(SharedMeshType::ConvexShape, path_string_components, signature.clone())
             }
             GeometricShapeSpawner::TriangleMesh { path_string_components, trimesh_engine, signature } => {
                 if let Some(trimesh_engine) = trimesh_engine {
-                    return GeometricShape::new_convex_shape_from_trimesh_engine(trimesh_engine, signature.clone());
+                    return GeometricShape::new_triangle_mesh_from_trimesh_engine(trimesh_engine, signature.clone());
                 }
-                let path = OptimaStemCellPath::new_asset_path_from_string_components(path_string_components).expect("error");
-                GeometricShape::new_triangle_mesh( &path, signature.clone() )
+                GeometricShape::new_mesh_shape_from_path_string_components(SharedMeshType::TriangleMesh, path_string_components, signature.clone())
             }
         }
     }
@@ -560,6 +799,18 @@ impl <'a> GeometricShapeQuery<'a> {
         }
         out_vec
     }
+    /// Returns references to the two signatures of a pairwise query, or None for a single object
+    /// query.  Unlike `get_signatures`, this does not allocate.
+    pub fn get_pair_signatures(&self) -> Option<(&GeometricShapeSignature, &GeometricShapeSignature)> {
+        return match self {
+            GeometricShapeQuery::IntersectionTest { object1, object2, .. } => { Some((&object1.signature, &object2.signature)) }
+            GeometricShapeQuery::Distance { object1, object2, .. } => { Some((&object1.signature, &object2.signature)) }
+            GeometricShapeQuery::ClosestPoints { object1, object2, .. } => { Some((&object1.signature, &object2.signature)) }
+            GeometricShapeQuery::Contact { object1, object2, .. } => { Some((&object1.signature, &object2.signature)) }
+            GeometricShapeQuery::CCD { object1, object2, .. } => { Some((&object1.signature, &object2.signature)) }
+            _ => { None }
+        }
+    }
 }
 
 /// A raw output from a single `GeometricShapeQuery`.
@@ -770,7 +1021,7 @@ pub struct ContactWrapper {
 impl ContactWrapper {
     pub fn new(contact: &Contact) -> Self {
         let n1 = &contact.normal1;
-        let n2 = &contact.normal1;
+        let n2 = &contact.normal2;
         let p1 = &contact.point1;
         let p2 = &contact.point2;
         Self {
@@ -859,6 +1110,16 @@ pub struct GeometricShapeQueryGroupOutput {
     outputs: Vec<GeometricShapeQueryOutput>
 }
 impl GeometricShapeQueryGroupOutput {
+    /// An output with no queries, e.g., to be filled by `GeometricShapeQueries::generic_group_query_into`.
+    pub fn new_empty() -> Self {
+        Self {
+            duration: Duration::default(),
+            num_queries: 0,
+            intersection_found: false,
+            minimum_distance: f64::INFINITY,
+            outputs: vec![]
+        }
+    }
     pub fn duration(&self) -> Duration {
         self.duration
     }
@@ -904,9 +1165,39 @@ impl GeometricShapeQueryGroupOutput {
             intersection_found: self.intersection_found,
             minimum_distance: self.minimum_distance,
             witness_points_collection: self.output_witness_points_collection(),
+            summary: self.summary(10),
             full_output_json_string
         }
     }
+    pub fn convert_to_js_output(&self) -> GeometricShapeQueryGroupOutputJs {
+        GeometricShapeQueryGroupOutputJs {
+            duration: self.duration.as_secs_f64(),
+            num_queries: self.num_queries,
+            intersection_found: self.intersection_found,
+            minimum_distance: self.minimum_distance,
+            outputs: self.outputs.iter().map(|o| GeometricShapeQueryOutputJs::new(o)).collect()
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    pub fn to_js_object(&self) -> GeometricShapeQueryGroupOutputJsObject {
+        return JsValue::from_serde(&self.convert_to_js_output()).unwrap().unchecked_into();
+    }
+    /// Converts the outputs into renderable primitives (witness segments, contact normals, and
+    /// penetrating pairs) for debugging collisions.
+    pub fn to_collision_visualization(&self) -> CollisionVisualization {
+        return CollisionVisualization::new_from_group_output(self);
+    }
+    /// Summarizes the outputs (counts, deepest penetrations and closest pairs, see
+    /// `QueryOutputSummary`), keeping at most max_pairs pairs per list.  Robot links are labeled by
+    /// index; use the `summarize_query_output` function of the module or scene that ran the query to
+    /// label them by name.
+    pub fn summary(&self, max_pairs: usize) -> QueryOutputSummary {
+        return QueryOutputSummary::new_from_group_output(self, max_pairs, &QueryOutputSummary::default_shape_label);
+    }
+    /// Same as `summary`, with shapes labeled by the given function.
+    pub fn summary_with_shape_labels(&self, max_pairs: usize, shape_label: &dyn Fn(&GeometricShapeSignature) -> String) -> QueryOutputSummary {
+        return QueryOutputSummary::new_from_group_output(self, max_pairs, shape_label);
+    }
     pub fn output_witness_points_collection(&self) -> WitnessPointsCollection {
         let mut witness_points_collection = WitnessPointsCollection::new();
         for output in &self.outputs {
@@ -920,7 +1211,9 @@ impl GeometricShapeQueryGroupOutput {
                 GeometricShapeQueryRawOutput::IntersectionTest(_) => {}
                 GeometricShapeQueryRawOutput::Distance(_) => {}
                 GeometricShapeQueryRawOutput::ClosestPoints(c) => {
-                    todo!()
+                    if let ClosestPointsWrapper::WithinMargin(a, b) = c {
+                        witness_points_collection.insert(WitnessPoints::new((*a, *b), (output.signatures[0].clone(), output.signatures[1].clone()), WitnessPointsType::GroundTruth));
+                    }
                 }
                 GeometricShapeQueryRawOutput::Contact(c) => {
                     match c {
@@ -951,8 +1244,129 @@ pub struct GeometricShapeQueryGroupOutputPy {
     #[pyo3(get)]
     witness_points_collection: WitnessPointsCollection,
     #[pyo3(get)]
+    summary: QueryOutputSummary,
+    #[pyo3(get)]
     full_output_json_string: String
 }
+#[cfg(not(target_arch = "wasm32"))]
+impl GeometricShapeQueryGroupOutputPy {
+    /// Replaces the summary, e.g., with one that labels robot links by name.
+    pub fn set_summary(&mut self, summary: QueryOutputSummary) {
+        self.summary = summary;
+    }
+}
+
+/// A JS-friendly version of `GeometricShapeQueryGroupOutput` that is returned from the wasm query
+/// functions.  Durations are given in seconds and each output is flattened into named fields (see
+/// `GeometricShapeQueryOutputJs`), matching the TypeScript definitions below.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GeometricShapeQueryGroupOutputJs {
+    duration: f64,
+    num_queries: usize,
+    intersection_found: bool,
+    minimum_distance: f64,
+    outputs: Vec<GeometricShapeQueryOutputJs>
+}
+
+/// A JS-friendly version of `GeometricShapeQueryOutput`.  `output_type` is the name of the
+/// `GeometricShapeQueryRawOutput` variant.  `boolean_value` holds the yes/no answer of the query
+/// (e.g., whether the shapes intersect or whether a ray hit), `scalar_value` holds the distance or
+/// time of impact, and `points` and `normals` hold any witness points or normals, each as \[x, y, z\].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GeometricShapeQueryOutputJs {
+    duration: f64,
+    signatures: Vec<GeometricShapeSignature>,
+    output_type: String,
+    boolean_value: Option<bool>,
+    scalar_value: Option<f64>,
+    points: Vec<[f64; 3]>,
+    normals: Vec<[f64; 3]>
+}
+impl GeometricShapeQueryOutputJs {
+    pub fn new(output: &GeometricShapeQueryOutput) -> Self {
+        let v = |x: &Vector3<f64>| [x[0], x[1], x[2]];
+
+        let (output_type, boolean_value, scalar_value, points, normals) = match &output.raw_output {
+            GeometricShapeQueryRawOutput::ProjectPoint(p) => { ("ProjectPoint", Some(p.is_inside), None, vec![v(&p.point)], vec![]) }
+            GeometricShapeQueryRawOutput::ContainsPoint(b) => { ("ContainsPoint", Some(*b), None, vec![], vec![]) }
+            GeometricShapeQueryRawOutput::DistanceToPoint(d) => { ("DistanceToPoint", None, Some(*d), vec![], vec![]) }
+            GeometricShapeQueryRawOutput::IntersectsRay(b) => { ("IntersectsRay", Some(*b), None, vec![], vec![]) }
+            GeometricShapeQueryRawOutput::CastRay(toi) => { ("CastRay", Some(toi.is_some()), *toi, vec![], vec![]) }
+            GeometricShapeQueryRawOutput::CastRayAndGetNormal(r) => {
+                match r {
+                    None => { ("CastRayAndGetNormal", Some(false), None, vec![], vec![]) }
+                    Some(r) => { ("CastRayAndGetNormal", Some(true), Some(r.toi), vec![], vec![v(&r.normal)]) }
+                }
+            }
+            GeometricShapeQueryRawOutput::IntersectionTest(b) => { ("IntersectionTest", Some(*b), None, vec![], vec![]) }
+            GeometricShapeQueryRawOutput::Distance(d) => { ("Distance", Some(*d <= 0.0), Some(*d), vec![], vec![]) }
+            GeometricShapeQueryRawOutput::ClosestPoints(c) => {
+                match c {
+                    ClosestPointsWrapper::Intersecting => { ("ClosestPoints", Some(true), None, vec![], vec![]) }
+                    ClosestPointsWrapper::WithinMargin(p1, p2) => { ("ClosestPoints", Some(false), Some((p1 - p2).norm()), vec![v(p1), v(p2)], vec![]) }
+                    ClosestPointsWrapper::Disjoint => { ("ClosestPoints", Some(false), None, vec![], vec![]) }
+                }
+            }
+            GeometricShapeQueryRawOutput::Contact(c) => {
+                match c {
+                    None => { ("Contact", Some(false), None, vec![], vec![]) }
+                    Some(c) => { ("Contact", Some(c.dist <= 0.0), Some(c.dist), vec![v(&c.point1), v(&c.point2)], vec![v(&c.normal1), v(&c.normal2)]) }
+                }
+            }
+            GeometricShapeQueryRawOutput::CCD(c) => {
+                match c {
+                    None => { ("CCD", Some(false), None, vec![], vec![]) }
+                    Some(c) => { ("CCD", Some(true), Some(c.toi), vec![v(&c.collision_point)], vec![v(&c.normal1.into_inner()), v(&c.normal2.into_inner())]) }
+                }
+            }
+        };
+
+        Self {
+            duration: output.duration.as_secs_f64(),
+            signatures: output.signatures.clone(),
+            output_type: output_type.to_string(),
+            boolean_value,
+            scalar_value,
+            points,
+            normals
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(typescript_custom_section)]
+const GEOMETRIC_SHAPE_QUERY_OUTPUT_TS: &'static str = r#"
+export type GeometricShapeSignature =
+    | "None"
+    | { RobotLink: { link_idx: number, shape_idx_in_link: number } }
+    | { RobotSetLink: { robot_idx_in_set: number, link_idx_in_robot: number, shape_idx_in_link: number } }
+    | { EnvironmentObject: { environment_object_idx: number, shape_idx_in_object: number, name: string } };
+
+export interface GeometricShapeQueryOutputJs {
+    duration: number;
+    signatures: GeometricShapeSignature[];
+    output_type: "ProjectPoint" | "ContainsPoint" | "DistanceToPoint" | "IntersectsRay" | "CastRay" | "CastRayAndGetNormal" | "IntersectionTest" | "Distance" | "ClosestPoints" | "Contact" | "CCD";
+    boolean_value: boolean | null;
+    scalar_value: number | null;
+    points: [number, number, number][];
+    normals: [number, number, number][];
+}
+
+export interface GeometricShapeQueryGroupOutputJs {
+    duration: number;
+    num_queries: number;
+    intersection_found: boolean;
+    minimum_distance: number;
+    outputs: GeometricShapeQueryOutputJs[];
+}
+"#;
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "GeometricShapeQueryGroupOutputJs")]
+    pub type GeometricShapeQueryGroupOutputJsObject;
+}
 
 /// Allows for control over when the `GeometricShapeQueries::generic_group_query` function should
 /// be early terminated.
@@ -1147,3 +1561,5 @@ impl BVHCombinableShape for BVHCombinableShapeSphere {
         todo!()
     }
 }
+
+crate::impl_py_pickle!(GeometricShapeQueryGroupOutputPy);
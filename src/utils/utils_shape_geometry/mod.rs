@@ -1,3 +1,8 @@
 pub mod trimesh_engine;
 pub mod geometric_shape;
 pub mod shape_collection;
+pub mod depth_rendering;
+pub mod shared_mesh_store;
+pub mod collision_visualization;
+pub mod query_output_summary;
+pub mod convex_shape_cache;
@@ -0,0 +1,200 @@
+#[cfg(not(target_arch = "wasm32"))]
+use pyo3::*;
+
+use serde::{Serialize, Deserialize};
+use crate::utils::utils_shape_geometry::geometric_shape::{ClosestPointsWrapper, GeometricShapeQueryGroupOutput, GeometricShapeQueryRawOutput, GeometricShapeSignature};
+use crate::utils::utils_traits::ToAndFromJsonString;
+
+/// A human-readable digest of a `GeometricShapeQueryGroupOutput` for debugging: overall counts, the
+/// outputs grouped by the kinds of shapes involved, the deepest penetrating pairs and the closest
+/// non-penetrating pairs, with shapes given by name rather than by signature.  Shape names come from
+/// the labeling function given to `GeometricShapeQueryGroupOutput::summary_with_shape_labels`;
+/// robot and scene modules provide summaries with link names resolved (e.g.,
+/// `RobotGeometricShapeScene::summarize_query_output`).
+///
+/// Only outputs of pair queries (`IntersectionTest`, `Distance`, `ClosestPoints`, `Contact`, and
+/// `CCD`) are summarized.  The summary can be printed with `to_text` or serialized to json.
+#[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
+#[cfg_attr(target_arch = "wasm32", derive(Clone, Debug, Serialize, Deserialize))]
+pub struct QueryOutputSummary {
+    num_queries: usize,
+    num_outputs: usize,
+    duration: f64,
+    intersection_found: bool,
+    minimum_distance: f64,
+    pair_type_counts: Vec<QueryPairTypeCount>,
+    deepest_penetrations: Vec<QueryPairSummary>,
+    closest_pairs: Vec<QueryPairSummary>
+}
+impl QueryOutputSummary {
+    /// Keeps at most max_pairs pairs in each of the penetration and closest pair lists.
+    pub fn new_from_group_output(group_output: &GeometricShapeQueryGroupOutput, max_pairs: usize, shape_label: &dyn Fn(&GeometricShapeSignature) -> String) -> Self {
+        let mut pair_type_counts: Vec<QueryPairTypeCount> = vec![];
+        let mut penetrations = vec![];
+        let mut non_penetrations = vec![];
+
+        for output in group_output.outputs() {
+            let signatures = output.signatures();
+            if signatures.len() < 2 { continue; }
+            let (intersecting, distance) = match output.raw_output() {
+                GeometricShapeQueryRawOutput::IntersectionTest(b) => { (*b, None) }
+                GeometricShapeQueryRawOutput::Distance(d) => { (*d <= 0.0, Some(*d)) }
+                GeometricShapeQueryRawOutput::ClosestPoints(c) => {
+                    match c {
+                        ClosestPointsWrapper::Intersecting => { (true, None) }
+                        ClosestPointsWrapper::WithinMargin(a, b) => { (false, Some((b - a).norm())) }
+                        ClosestPointsWrapper::Disjoint => { (false, None) }
+                    }
+                }
+                GeometricShapeQueryRawOutput::Contact(c) => {
+                    match c {
+                        None => { (false, None) }
+                        Some(c) => { (c.dist <= 0.0, Some(c.dist)) }
+                    }
+                }
+                GeometricShapeQueryRawOutput::CCD(c) => { (c.is_some(), None) }
+                _ => { continue; }
+            };
+
+            let pair_type = Self::pair_type(&signatures[0], &signatures[1]);
+            match pair_type_counts.iter_mut().find(|c| c.pair_type == pair_type) {
+                Some(count) => {
+                    count.num_outputs += 1;
+                    if intersecting { count.num_intersecting += 1; }
+                }
+                None => {
+                    pair_type_counts.push(QueryPairTypeCount { pair_type, num_outputs: 1, num_intersecting: if intersecting { 1 } else { 0 } });
+                }
+            }
+
+            let pair = QueryPairSummary {
+                shape_a: shape_label(&signatures[0]),
+                shape_b: shape_label(&signatures[1]),
+                intersecting,
+                distance
+            };
+            if intersecting { penetrations.push(pair); } else if distance.is_some() { non_penetrations.push(pair); }
+        }
+
+        // Pairs with a known depth come first, deepest (most negative distance) first.
+        penetrations.sort_by(|a, b| a.distance.unwrap_or(f64::INFINITY).partial_cmp(&b.distance.unwrap_or(f64::INFINITY)).unwrap_or(std::cmp::Ordering::Equal));
+        penetrations.truncate(max_pairs);
+        non_penetrations.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap_or(std::cmp::Ordering::Equal));
+        non_penetrations.truncate(max_pairs);
+        pair_type_counts.sort_by(|a, b| a.pair_type.cmp(&b.pair_type));
+
+        Self {
+            num_queries: group_output.num_queries(),
+            num_outputs: group_output.outputs().len(),
+            duration: group_output.duration().as_secs_f64(),
+            intersection_found: group_output.intersection_found(),
+            minimum_distance: group_output.minimum_distance(),
+            pair_type_counts,
+            deepest_penetrations: penetrations,
+            closest_pairs: non_penetrations
+        }
+    }
+    /// A label that only uses the information in the signature itself (environment object names, and
+    /// link and shape indices for robot links).
+    pub fn default_shape_label(signature: &GeometricShapeSignature) -> String {
+        return match signature {
+            GeometricShapeSignature::None => { "none".to_string() }
+            GeometricShapeSignature::RobotLink { link_idx, shape_idx_in_link } => { format!("link {} (shape {})", link_idx, shape_idx_in_link) }
+            GeometricShapeSignature::RobotSetLink { robot_idx_in_set, link_idx_in_robot, shape_idx_in_link } => { format!("robot {} link {} (shape {})", robot_idx_in_set, link_idx_in_robot, shape_idx_in_link) }
+            GeometricShapeSignature::EnvironmentObject { environment_object_idx, shape_idx_in_object, name } => {
+                if name.is_empty() { format!("environment object {} (shape {})", environment_object_idx, shape_idx_in_object) } else { format!("{} (shape {})", name, shape_idx_in_object) }
+            }
+        }
+    }
+    pub fn to_text(&self) -> String {
+        let mut out = format!("{} queries, {} outputs logged, {:.3} ms\n", self.num_queries, self.num_outputs, self.duration * 1000.0);
+        out += &format!("intersection found: {}, minimum distance: {}\n", self.intersection_found, self.minimum_distance);
+        if !self.pair_type_counts.is_empty() {
+            out += "pair types:\n";
+            for c in &self.pair_type_counts { out += &format!("  {}: {} outputs, {} intersecting\n", c.pair_type, c.num_outputs, c.num_intersecting); }
+        }
+        if !self.deepest_penetrations.is_empty() {
+            out += "deepest penetrations:\n";
+            for p in &self.deepest_penetrations {
+                let depth = match p.distance {
+                    Some(d) if d < 0.0 => { format!("depth {}", -d) }
+                    Some(_) => { "touching".to_string() }
+                    None => { "depth unknown".to_string() }
+                };
+                out += &format!("  {} <-> {}: {}\n", p.shape_a, p.shape_b, depth);
+            }
+        }
+        if !self.closest_pairs.is_empty() {
+            out += "closest pairs:\n";
+            for p in &self.closest_pairs { out += &format!("  {} <-> {}: distance {}\n", p.shape_a, p.shape_b, p.distance.expect("error")); }
+        }
+        out
+    }
+    pub fn num_queries(&self) -> usize {
+        self.num_queries
+    }
+    pub fn num_outputs(&self) -> usize {
+        self.num_outputs
+    }
+    /// The duration of the group query in seconds.
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+    pub fn intersection_found(&self) -> bool {
+        self.intersection_found
+    }
+    pub fn minimum_distance(&self) -> f64 {
+        self.minimum_distance
+    }
+    pub fn pair_type_counts(&self) -> &Vec<QueryPairTypeCount> {
+        &self.pair_type_counts
+    }
+    pub fn deepest_penetrations(&self) -> &Vec<QueryPairSummary> {
+        &self.deepest_penetrations
+    }
+    pub fn closest_pairs(&self) -> &Vec<QueryPairSummary> {
+        &self.closest_pairs
+    }
+    fn pair_type(a: &GeometricShapeSignature, b: &GeometricShapeSignature) -> String {
+        let kind = |s: &GeometricShapeSignature| {
+            match s {
+                GeometricShapeSignature::None => { "none" }
+                GeometricShapeSignature::RobotLink { .. } | GeometricShapeSignature::RobotSetLink { .. } => { "robot link" }
+                GeometricShapeSignature::EnvironmentObject { .. } => { "environment object" }
+            }
+        };
+        let (a, b) = (kind(a), kind(b));
+        return if a <= b { format!("{} / {}", a, b) } else { format!("{} / {}", b, a) };
+    }
+}
+
+/// Python implementations.
+#[cfg(not(target_arch = "wasm32"))]
+#[pymethods]
+impl QueryOutputSummary {
+    pub fn to_text_py(&self) -> String {
+        self.to_text()
+    }
+    pub fn to_json_string_py(&self) -> String {
+        self.to_json_string()
+    }
+}
+
+/// The number of outputs (and intersecting outputs) for one combination of shape kinds, e.g.,
+/// "environment object / robot link".
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueryPairTypeCount {
+    pub pair_type: String,
+    pub num_outputs: usize,
+    pub num_intersecting: usize
+}
+
+/// A pair of shapes in a `QueryOutputSummary`.  `distance` is negative for penetrating pairs whose
+/// depth is known and None if the query does not give a distance.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueryPairSummary {
+    pub shape_a: String,
+    pub shape_b: String,
+    pub intersecting: bool,
+    pub distance: Option<f64>
+}
@@ -1,14 +1,17 @@
 #[cfg(not(target_arch = "wasm32"))]
 use pyo3::*;
+#[cfg(not(target_arch = "wasm32"))]
+use rayon::prelude::*;
 
+use std::collections::HashMap;
 use nalgebra::{Vector3};
 use parry3d_f64::query::{Ray};
 use serde::{Serialize, Deserialize};
 use instant::{Duration};
 use crate::utils::utils_combinations::comb;
 use crate::utils::utils_errors::OptimaError;
-use crate::utils::utils_files::optima_path::{load_object_from_json_string};
-use crate::utils::utils_generic_data_structures::{MemoryCell, Mixable, SquareArray2D};
+use crate::utils::utils_generic_data_structures::{MemoryCell, Mixable, SharedHandle, SquareArray2D, SquareArray2DStorageType};
+use crate::utils::utils_robot::robot_ids::ShapeIdx;
 use crate::utils::utils_sampling::SimpleSamplers;
 use crate::utils::utils_se3::optima_rotation::OptimaRotation;
 use crate::utils::utils_se3::optima_se3_pose::OptimaSE3Pose;
@@ -34,40 +37,120 @@ use crate::utils::utils_traits::{SaveAndLoadable, ToAndFromJsonString};
 /// any pairwise geometric shape query by GeometricShapeQueries::generic_group_query should skip the
 /// computation between shape with index 3 and shape with index 6.  Use the `get_shape_idx_from_signature`
 /// function to map a signature to a shape index.
+///
+/// The `collision_margins` field holds safety margins that are applied to pairwise queries at query
+/// time (see `CollisionMargins`).  These are runtime settings and are not part of the saved
+/// preprocessing data.
+///
+/// The shape list is held in a `SharedHandle`, so cloning a `ShapeCollection` does not copy the
+/// shapes themselves; the list is only copied when a clone adds or removes a shape.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ShapeCollection {
-    shapes: Vec<GeometricShape>,
+    shapes: SharedHandle<Vec<GeometricShape>>,
     skips: SquareArray2D<MemoryCell<bool>>,
     average_distances: SquareArray2D<MemoryCell<f64>>,
     sorted_signatures_with_shape_idxs: Vec<(GeometricShapeSignature, usize)>,
+    #[serde(default)]
+    collision_margins: CollisionMargins,
     /// The id will be updated each time a geometric shape is added.  This will help track whether 
     /// mutable objects given out by the shape collection (intended to be updated throughout runtime)
-    /// are still valid.
+    /// are still valid.  The id is a runtime value and is not serialized, so that saved shape
+    /// collections are identical across runs; a fresh id is drawn on load.
+    #[serde(skip, default = "ShapeCollection::new_id")]
     id: f64 
 }
+
 impl ShapeCollection {
     pub fn new_empty() -> Self {
         Self {
-            shapes: vec![],
+            shapes: SharedHandle::new(vec![]),
             skips: SquareArray2D::new(0, true, None),
             average_distances: SquareArray2D::new(0, true, None),
             sorted_signatures_with_shape_idxs: vec![],
-            id: SimpleSamplers::uniform_sample((-1.0, 1.0))
+            collision_margins: CollisionMargins::default(),
+            id: Self::new_id()
         }
     }
+    fn new_id() -> f64 {
+        return SimpleSamplers::uniform_sample((-1.0, 1.0));
+    }
     pub fn add_geometric_shape(&mut self, geometric_shape: GeometricShape) {
         let add_idx = self.shapes.len();
         let sorted_idx = self.sorted_signatures_with_shape_idxs.binary_search_by(|x| geometric_shape.signature().partial_cmp(&x.0).unwrap() );
         let sorted_idx = match sorted_idx { Ok(idx) => {idx} Err(idx) => {idx} };
         self.sorted_signatures_with_shape_idxs.insert(sorted_idx, (geometric_shape.signature().clone(), add_idx));
-        self.shapes.push(geometric_shape);
+        self.shapes.make_mut().push(geometric_shape);
         self.skips.append_new_row_and_column(Some(MemoryCell::new(false)));
         self.average_distances.append_new_row_and_column(Some(MemoryCell::new(1.0)));
-        self.id = SimpleSamplers::uniform_sample((-1.0, 1.0));
+        self.id = Self::new_id();
+    }
+    /// Same as `add_geometric_shape`, but returns an error if a shape with the same signature is
+    /// already in the collection.  Existing shapes keep their shape indices and all skip and average
+    /// distance entries between them are preserved; the new shape is given the next shape index.
+    /// Returns the new shape index.
+    pub fn insert_geometric_shape(&mut self, geometric_shape: GeometricShape) -> Result<ShapeIdx, OptimaError> {
+        if self.contains_signature(geometric_shape.signature()) {
+            return Err(OptimaError::new_generic_error_str(&format!("Shape with signature {:?} is already in the ShapeCollection.", geometric_shape.signature()), file!(), line!()));
+        }
+        let add_idx = ShapeIdx::new(self.shapes.len());
+        self.add_geometric_shape(geometric_shape);
+        Ok(add_idx)
+    }
+    /// Removes the shape with the given signature and returns it.  All other shapes keep their
+    /// signatures along with their skip, average distance, and collision margin entries, but shapes
+    /// that came after the removed shape have their shape index decreased by one.  Any query lists,
+    /// proxima engines, or BVHs spawned from this collection are invalidated.
+    pub fn remove_geometric_shape(&mut self, signature: &GeometricShapeSignature) -> Result<GeometricShape, OptimaError> {
+        let shape_idx = self.get_shape_id_from_signature(signature)?;
+        return self.remove_geometric_shape_by_idx(shape_idx);
+    }
+    pub fn remove_geometric_shape_by_idx(&mut self, shape_idx: ShapeIdx) -> Result<GeometricShape, OptimaError> {
+        let shape_idx = shape_idx.idx();
+        OptimaError::new_check_for_idx_out_of_bound_error(shape_idx, self.shapes.len(), file!(), line!())?;
+
+        self.skips.remove_row_and_column(shape_idx)?;
+        self.average_distances.remove_row_and_column(shape_idx)?;
+        self.collision_margins.remove_shape(shape_idx);
+
+        self.sorted_signatures_with_shape_idxs.retain(|x| x.1 != shape_idx);
+        for x in &mut self.sorted_signatures_with_shape_idxs {
+            if x.1 > shape_idx { x.1 -= 1; }
+        }
+
+        let removed = self.shapes.make_mut().remove(shape_idx);
+        self.id = Self::new_id();
+
+        Ok(removed)
+    }
+    /// Returns the shape indices of all shapes belonging to the environment object with the given
+    /// name (see `GeometricShapeSignature::EnvironmentObject`), in ascending order.  Returns an error
+    /// if no such shapes exist.
+    pub fn get_shape_idxs_from_env_obj_name(&self, env_obj_name: &str) -> Result<Vec<ShapeIdx>, OptimaError> {
+        let mut out_vec = vec![];
+        for (signature, shape_idx) in &self.sorted_signatures_with_shape_idxs {
+            if let GeometricShapeSignature::EnvironmentObject { name, .. } = signature {
+                if name == env_obj_name { out_vec.push(ShapeIdx::new(*shape_idx)); }
+            }
+        }
+        out_vec.sort();
+
+        if out_vec.is_empty() {
+            return Err(OptimaError::new_generic_error_str(&format!("Environment object with name {:?} not found in ShapeCollection.", env_obj_name), file!(), line!()));
+        }
+
+        return Ok(out_vec);
+    }
+    pub fn contains_signature(&self, signature: &GeometricShapeSignature) -> bool {
+        return self.get_shape_idx_from_signature(signature).is_ok();
     }
     pub fn shapes(&self) -> &Vec<GeometricShape> {
         &self.shapes
     }
+    /// Returns the shape with the given typed index.
+    pub fn shape(&self, shape_idx: ShapeIdx) -> Result<&GeometricShape, OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(shape_idx.idx(), self.shapes.len(), file!(), line!())?;
+        return Ok(&self.shapes[shape_idx.idx()]);
+    }
     pub fn skips(&self) -> &SquareArray2D<MemoryCell<bool>> {
         &self.skips
     }
@@ -118,6 +201,25 @@ impl ShapeCollection {
         self.average_distances = average_distances;
         Ok(())
     }
+    /// Converts the skip and average distance matrices to the given storage types (see
+    /// `SquareArray2DStorageType`).  A sparse matrix uses its most common value as its default.
+    pub fn set_pairwise_storage_types(&mut self, skips_storage_type: SquareArray2DStorageType, average_distances_storage_type: SquareArray2DStorageType) -> Result<(), OptimaError> {
+        self.skips.convert_storage_type(skips_storage_type, None)?;
+        self.average_distances.convert_storage_type(average_distances_storage_type, None)?;
+        Ok(())
+    }
+    pub fn collision_margins(&self) -> &CollisionMargins {
+        &self.collision_margins
+    }
+    pub fn set_global_collision_margin(&mut self, margin: f64) -> Result<(), OptimaError> {
+        return self.collision_margins.set_global_margin(margin);
+    }
+    /// Overrides the global collision margin for the shape with the given index.  Passing None
+    /// removes the override.
+    pub fn set_shape_collision_margin(&mut self, shape_idx: usize, margin: Option<f64>) -> Result<(), OptimaError> {
+        OptimaError::new_check_for_idx_out_of_bound_error(shape_idx, self.shapes.len(), file!(), line!())?;
+        return self.collision_margins.set_shape_margin(shape_idx, margin);
+    }
 
     pub fn get_shape_idx_from_signature(&self, signature: &GeometricShapeSignature) -> Result<usize, OptimaError> {
         let binary_search_res = self.sorted_signatures_with_shape_idxs.binary_search_by(|x| signature.partial_cmp(&x.0).unwrap());
@@ -130,6 +232,10 @@ impl ShapeCollection {
             }
         };
     }
+    /// Same as `get_shape_idx_from_signature`, but returns a typed `ShapeIdx`.
+    pub fn get_shape_id_from_signature(&self, signature: &GeometricShapeSignature) -> Result<ShapeIdx, OptimaError> {
+        return Ok(ShapeIdx::new(self.get_shape_idx_from_signature(signature)?));
+    }
     pub fn get_geometric_shape_query_input_vec<'a>(&'a self, input: &'a ShapeCollectionQuery) -> Result<Vec<GeometricShapeQuery<'a>>, OptimaError> {
         return match input {
             ShapeCollectionQuery::ProjectPoint { .. } => { self.get_single_object_geometric_shape_query_input_vec(input) }
@@ -148,6 +254,22 @@ impl ShapeCollection {
             add it as an arm in the match statement above. ") }
         }
     }
+    fn fill_geometric_shape_query_input_vec<'a>(&'a self, input: &'a ShapeCollectionQuery, out_vec: &mut Vec<GeometricShapeQuery<'a>>) -> Result<(), OptimaError> {
+        return match input {
+            ShapeCollectionQuery::ProjectPoint { .. } => { self.fill_single_object_geometric_shape_query_input_vec(input, out_vec) }
+            ShapeCollectionQuery::ContainsPoint { .. } => { self.fill_single_object_geometric_shape_query_input_vec(input, out_vec) }
+            ShapeCollectionQuery::DistanceToPoint { .. } => { self.fill_single_object_geometric_shape_query_input_vec(input, out_vec) }
+            ShapeCollectionQuery::IntersectsRay { .. } => { self.fill_single_object_geometric_shape_query_input_vec(input, out_vec) }
+            ShapeCollectionQuery::CastRay { .. } => { self.fill_single_object_geometric_shape_query_input_vec(input, out_vec) }
+            ShapeCollectionQuery::CastRayAndGetNormal { .. } => { self.fill_single_object_geometric_shape_query_input_vec(input, out_vec) }
+            ShapeCollectionQuery::IntersectionTest { .. } => { self.fill_pairwise_objects_geometric_shape_query_input_vec(input, out_vec) }
+            ShapeCollectionQuery::Distance { .. } => { self.fill_pairwise_objects_geometric_shape_query_input_vec(input, out_vec) }
+            ShapeCollectionQuery::ClosestPoints { .. } => { self.fill_pairwise_objects_geometric_shape_query_input_vec(input, out_vec) }
+            ShapeCollectionQuery::Contact { .. } => { self.fill_pairwise_objects_geometric_shape_query_input_vec(input, out_vec) }
+            ShapeCollectionQuery::CCD { .. } => { self.fill_pairwise_objects_geometric_shape_query_input_vec(input, out_vec) }
+            _ => { Err(OptimaError::new_unsupported_operation_error("fill_geometric_shape_query_input_vec", "Input is not composed of smaller, atomic queries.", file!(), line!())) }
+        }
+    }
 
     pub fn spawn_query_list(&self) -> ShapeCollectionQueryList {
         return ShapeCollectionQueryList { list: vec![], id: self.id };
@@ -177,6 +299,12 @@ impl ShapeCollection {
             id: self.id
         }
     }
+    pub fn spawn_warm_start_cache(&self) -> ShapeCollectionWarmStartCache {
+        ShapeCollectionWarmStartCache {
+            separating_directions: HashMap::new(),
+            id: self.id
+        }
+    }
     pub fn spawn_bvh<T: BVHCombinableShape>(&self, poses: &ShapeCollectionInputPoses, branch_factor: usize) -> ShapeCollectionBVH<T> {
         let out_bvh = BVH::construct_new(&self.shapes, &poses, branch_factor);
         return ShapeCollectionBVH {
@@ -197,10 +325,274 @@ impl ShapeCollection {
                                       log_condition: LogCondition,
                                       sort_outputs: bool) -> Result<GeometricShapeQueryGroupOutput, OptimaError> {
         let input_vec = self.get_geometric_shape_query_input_vec(input)?;
-        let g = GeometricShapeQueries::generic_group_query(input_vec, stop_condition, log_condition, sort_outputs);
+        if self.collision_margins.is_zero() {
+            let g = GeometricShapeQueries::generic_group_query(input_vec, stop_condition, log_condition, sort_outputs);
+            return Ok(g);
+        }
+
+        let mut margins = vec![];
+        for q in &input_vec {
+            let signatures = q.get_signatures();
+            if signatures.len() == 2 {
+                let idx1 = self.get_shape_idx_from_signature(&signatures[0])?;
+                let idx2 = self.get_shape_idx_from_signature(&signatures[1])?;
+                margins.push(self.collision_margins.pair_margin(idx1, idx2));
+            } else {
+                margins.push(0.0);
+            }
+        }
+
+        let g = GeometricShapeQueries::generic_group_query_with_margins(input_vec, &margins, stop_condition, log_condition, sort_outputs);
         Ok(g)
     }
+    /// Same as `shape_collection_query`, but intersection tests, distance queries, and contact queries
+    /// between pairs of convex shapes are warm started from the separating directions found for
+    /// the same pairs by the previous query with the given cache (see
+    /// `GeometricShapeQueries::distance_warm_started`).  For temporally coherent queries, e.g.,
+    /// consecutive states along a trajectory or in a control loop, this typically cuts the
+    /// narrow-phase cost substantially.  Results are the same as without the cache, up to GJK
+    /// tolerance.
+    pub fn shape_collection_query_warm_started(&self,
+                                               input: &ShapeCollectionQuery,
+                                               stop_condition: StopCondition,
+                                               log_condition: LogCondition,
+                                               sort_outputs: bool,
+                                               cache: &mut ShapeCollectionWarmStartCache) -> Result<GeometricShapeQueryGroupOutput, OptimaError> {
+        assert_eq!(self.id, cache.id, "id must match ShapeCollection.");
+
+        let input_vec = self.get_geometric_shape_query_input_vec(input)?;
+        let mut shape_idx_pairs = vec![];
+        for q in &input_vec {
+            match q.get_pair_signatures() {
+                Some((signature1, signature2)) => {
+                    shape_idx_pairs.push(Some((self.get_shape_idx_from_signature(signature1)?, self.get_shape_idx_from_signature(signature2)?)));
+                }
+                None => { shape_idx_pairs.push(None); }
+            }
+        }
+
+        let mut out = GeometricShapeQueryGroupOutput::new_empty();
+        GeometricShapeQueries::generic_group_query_into_with(&input_vec, |i, q| {
+            match shape_idx_pairs[i] {
+                None => { GeometricShapeQueries::raw_query_warm_started(q, 0.0, &mut None) }
+                Some((idx1, idx2)) => {
+                    let margin = self.collision_margins.pair_margin(idx1, idx2);
+                    let mut separating_direction = cache.separating_directions.get(&(idx1, idx2)).cloned();
+                    let raw_output = GeometricShapeQueries::raw_query_warm_started(q, margin, &mut separating_direction);
+                    match separating_direction {
+                        None => { cache.separating_directions.remove(&(idx1, idx2)); }
+                        Some(separating_direction) => { cache.separating_directions.insert((idx1, idx2), separating_direction); }
+                    }
+                    raw_output
+                }
+            }
+        }, stop_condition, log_condition, sort_outputs, &mut vec![], &mut out);
+
+        Ok(out)
+    }
+    /// Same as `shape_collection_query`, but the intermediate buffers and the output live in the
+    /// given `QueryScratch` and are reused from call to call.  The scratch only stores the indices of
+    /// the shapes (or shape pairs) to query, and each `GeometricShapeQuery` is built from them on the
+    /// fly.  Once the buffers have grown to their working size, queries that are not logged do not
+    /// allocate, but every logged output still allocates a vec of cloned shape signatures (which
+    /// include the shapes' names), so use a selective log condition (e.g.,
+    /// `LogCondition::Intersection`) in control-rate loops.  The returned output is overwritten by the
+    /// next call with the same scratch.
+    pub fn shape_collection_query_with_scratch<'s>(&self,
+                                                   input: &ShapeCollectionQuery,
+                                                   stop_condition: StopCondition,
+                                                   log_condition: LogCondition,
+                                                   sort_outputs: bool,
+                                                   scratch: &'s mut QueryScratch) -> Result<&'s GeometricShapeQueryGroupOutput, OptimaError> {
+        let poses = input.get_primary_input_poses();
+        self.fill_shape_idxs(input, poses, &mut scratch.shape_idxs)?;
+
+        let shape_idxs = &scratch.shape_idxs;
+        let margins_are_zero = self.collision_margins.is_zero();
+        let queries = shape_idxs.iter().filter_map(|(idx1, idx2)| self.geometric_shape_query_at_idxs(input, poses, *idx1, *idx2));
+        GeometricShapeQueries::generic_group_query_into_with(queries, |i, q| {
+            match shape_idxs[i] {
+                (idx1, Some(idx2)) if !margins_are_zero => { GeometricShapeQueries::raw_query_with_margin(q, self.collision_margins.pair_margin(idx1, idx2)) }
+                _ => { GeometricShapeQueries::raw_query_with_margin(q, 0.0) }
+            }
+        }, stop_condition, log_condition, sort_outputs, &mut scratch.output_distances, &mut scratch.output);
+
+        Ok(&scratch.output)
+    }
+    /// Fills out_vec with the index of every shape (for single shape queries) or pair of shapes (for
+    /// pairwise queries) that `fill_geometric_shape_query_input_vec` would output a query for, in the
+    /// same order.
+    fn fill_shape_idxs(&self, input: &ShapeCollectionQuery, poses: &ShapeCollectionInputPoses, out_vec: &mut Vec<(usize, Option<usize>)>) -> Result<(), OptimaError> {
+        out_vec.clear();
+        match input {
+            ShapeCollectionQuery::ProjectPoint { .. } |
+            ShapeCollectionQuery::ContainsPoint { .. } |
+            ShapeCollectionQuery::DistanceToPoint { .. } |
+            ShapeCollectionQuery::IntersectsRay { .. } |
+            ShapeCollectionQuery::CastRay { .. } |
+            ShapeCollectionQuery::CastRayAndGetNormal { .. } => {
+                match input.get_inclusion_list() {
+                    Some(inclusion_list) => {
+                        assert_eq!(inclusion_list.id, self.id, "id must match ShapeCollection.");
+                        for i in &inclusion_list.list {
+                            if poses.poses[*i].is_some() { out_vec.push((*i, None)); }
+                        }
+                    }
+                    None => {
+                        for i in 0..self.shapes.len() {
+                            if poses.poses[i].is_some() { out_vec.push((i, None)); }
+                        }
+                    }
+                }
+            }
+            ShapeCollectionQuery::IntersectionTest { .. } |
+            ShapeCollectionQuery::Distance { .. } |
+            ShapeCollectionQuery::ClosestPoints { .. } |
+            ShapeCollectionQuery::Contact { .. } |
+            ShapeCollectionQuery::CCD { .. } => {
+                let poses_t2 = match input {
+                    ShapeCollectionQuery::CCD { poses_t2, .. } => { Some(*poses_t2) }
+                    _ => { None }
+                };
+                let is_posed = |i: usize| { poses.poses[i].is_some() && poses_t2.map(|p| p.poses[i].is_some()).unwrap_or(true) };
+                match input.get_inclusion_pairs_list() {
+                    Some(inclusion_list) => {
+                        assert_eq!(inclusion_list.id, self.id, "id must match ShapeCollection.");
+                        for (i, j) in &inclusion_list.pairs {
+                            if is_posed(*i) && is_posed(*j) && (inclusion_list.override_all_skips || !*self.skips.data_cell(*i, *j)?.curr_value()) {
+                                out_vec.push((*i, Some(*j)));
+                            }
+                        }
+                    }
+                    None => {
+                        let num_shapes = self.shapes.len();
+                        for i in 0..num_shapes {
+                            if !is_posed(i) { continue; }
+                            for j in (i + 1)..num_shapes {
+                                if is_posed(j) && !*self.skips.data_cell(i, j)?.curr_value() {
+                                    out_vec.push((i, Some(j)));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            _ => { return Err(OptimaError::new_unsupported_operation_error("fill_shape_idxs", "Input is not composed of smaller, atomic queries.", file!(), line!())); }
+        }
+
+        Ok(())
+    }
+    /// The query of the given type on the shape (or pair of shapes) with the given indices, or None if
+    /// one of the shapes does not have a pose.
+    fn geometric_shape_query_at_idxs<'a>(&'a self, input: &'a ShapeCollectionQuery, poses: &ShapeCollectionInputPoses, idx1: usize, idx2: Option<usize>) -> Option<GeometricShapeQuery<'a>> {
+        let object1 = &self.shapes[idx1];
+        let pose1 = poses.poses[idx1].as_ref()?.clone();
+        let out = match (input, idx2) {
+            (ShapeCollectionQuery::ProjectPoint { poses: _, point, solid, inclusion_list: _ }, None) => {
+                GeometricShapeQuery::ProjectPoint { object: object1, pose: pose1, point, solid: *solid }
+            }
+            (ShapeCollectionQuery::ContainsPoint { poses: _, point, inclusion_list: _ }, None) => {
+                GeometricShapeQuery::ContainsPoint { object: object1, pose: pose1, point }
+            }
+            (ShapeCollectionQuery::DistanceToPoint { poses: _, point, solid, inclusion_list: _ }, None) => {
+                GeometricShapeQuery::DistanceToPoint { object: object1, pose: pose1, point, solid: *solid }
+            }
+            (ShapeCollectionQuery::IntersectsRay { poses: _, ray, max_toi, inclusion_list: _ }, None) => {
+                GeometricShapeQuery::IntersectsRay { object: object1, pose: pose1, ray, max_toi: *max_toi }
+            }
+            (ShapeCollectionQuery::CastRay { poses: _, ray, max_toi, solid, inclusion_list: _ }, None) => {
+                GeometricShapeQuery::CastRay { object: object1, pose: pose1, ray, max_toi: *max_toi, solid: *solid }
+            }
+            (ShapeCollectionQuery::CastRayAndGetNormal { poses: _, ray, max_toi, solid, inclusion_list: _ }, None) => {
+                GeometricShapeQuery::CastRayAndGetNormal { object: object1, pose: pose1, ray, max_toi: *max_toi, solid: *solid }
+            }
+            (ShapeCollectionQuery::IntersectionTest { .. }, Some(idx2)) => {
+                GeometricShapeQuery::IntersectionTest { object1, object1_pose: pose1, object2: &self.shapes[idx2], object2_pose: poses.poses[idx2].as_ref()?.clone() }
+            }
+            (ShapeCollectionQuery::Distance { .. }, Some(idx2)) => {
+                GeometricShapeQuery::Distance { object1, object1_pose: pose1, object2: &self.shapes[idx2], object2_pose: poses.poses[idx2].as_ref()?.clone() }
+            }
+            (ShapeCollectionQuery::ClosestPoints { poses: _, max_dis, inclusion_list: _ }, Some(idx2)) => {
+                GeometricShapeQuery::ClosestPoints { object1, object1_pose: pose1, object2: &self.shapes[idx2], object2_pose: poses.poses[idx2].as_ref()?.clone(), max_dis: *max_dis }
+            }
+            (ShapeCollectionQuery::Contact { poses: _, prediction, inclusion_list: _ }, Some(idx2)) => {
+                GeometricShapeQuery::Contact { object1, object1_pose: pose1, object2: &self.shapes[idx2], object2_pose: poses.poses[idx2].as_ref()?.clone(), prediction: *prediction }
+            }
+            (ShapeCollectionQuery::CCD { poses_t1: _, poses_t2, inclusion_list: _ }, Some(idx2)) => {
+                GeometricShapeQuery::CCD {
+                    object1,
+                    object1_pose_t1: pose1,
+                    object1_pose_t2: poses_t2.poses[idx1].as_ref()?.clone(),
+                    object2: &self.shapes[idx2],
+                    object2_pose_t1: poses.poses[idx2].as_ref()?.clone(),
+                    object2_pose_t2: poses_t2.poses[idx2].as_ref()?.clone()
+                }
+            }
+            _ => { return None; }
+        };
+
+        Some(out)
+    }
+
+    /// Casts every ray in `rays` against all posed shapes in the collection (or only the shapes in
+    /// the inclusion list) and returns the closest hit for each ray, if any, within max_toi.  Shapes
+    /// without a pose are ignored.  Rays are processed in parallel, except on WebAssembly.  This is
+    /// much faster than running a `CastRayAndGetNormal` query per ray, and is intended for sensor
+    /// simulation (e.g., a lidar scan pattern) and visibility checks.
+    pub fn batch_cast_rays(&self,
+                           poses: &ShapeCollectionInputPoses,
+                           rays: &Vec<Ray>,
+                           max_toi: f64,
+                           solid: bool,
+                           inclusion_list: &Option<&ShapeCollectionQueryList>) -> BatchRayCastOutput {
+        let start = instant::Instant::now();
+
+        let shape_idxs: Vec<usize> = match inclusion_list {
+            None => { (0..self.shapes.len()).collect() }
+            Some(inclusion_list) => {
+                assert_eq!(inclusion_list.id, self.id, "id must match ShapeCollection.");
+                inclusion_list.list.clone()
+            }
+        };
 
+        let mut posed_shapes = vec![];
+        for shape_idx in shape_idxs {
+            if let Some(pose) = &poses.poses[shape_idx] {
+                let shape = &self.shapes[shape_idx];
+                posed_shapes.push((shape, shape.isometry_at_pose(pose)));
+            }
+        }
+
+        let cast = |ray: &Ray| -> Option<RayCastHit> {
+            let mut out: Option<RayCastHit> = None;
+            let mut curr_max_toi = max_toi;
+            for (shape, isometry) in &posed_shapes {
+                let res = shape.cast_ray_and_get_normal_at_isometry(isometry, ray, curr_max_toi, solid);
+                if let Some(res) = res {
+                    if out.is_none() || res.toi < curr_max_toi {
+                        curr_max_toi = res.toi;
+                        out = Some(RayCastHit {
+                            toi: res.toi,
+                            point: ray.point_at(res.toi).coords,
+                            normal: res.normal,
+                            signature: shape.signature().clone()
+                        });
+                    }
+                }
+            }
+            out
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let hits: Vec<Option<RayCastHit>> = rays.par_iter().map(cast).collect();
+        #[cfg(target_arch = "wasm32")]
+        let hits: Vec<Option<RayCastHit>> = rays.iter().map(cast).collect();
+
+        BatchRayCastOutput {
+            hits,
+            duration: start.elapsed()
+        }
+    }
     pub fn proxima_proximity_query(&self,
                                    poses: &ShapeCollectionInputPoses,
                                    proxima_engine: &mut ProximaEngine,
@@ -377,6 +769,10 @@ impl ShapeCollection {
 
     fn get_single_object_geometric_shape_query_input_vec<'a>(&'a self, input: &'a ShapeCollectionQuery) -> Result<Vec<GeometricShapeQuery<'a>>, OptimaError> {
         let mut out_vec = vec![];
+        self.fill_single_object_geometric_shape_query_input_vec(input, &mut out_vec)?;
+        Ok(out_vec)
+    }
+    fn fill_single_object_geometric_shape_query_input_vec<'a>(&'a self, input: &'a ShapeCollectionQuery, out_vec: &mut Vec<GeometricShapeQuery<'a>>) -> Result<(), OptimaError> {
 
         let poses = &input.get_shape_collection_input_poses()?[0].poses;
         let inclusion_list = input.get_inclusion_list();
@@ -442,7 +838,7 @@ impl ShapeCollection {
                 }
             }
 
-            return Ok(out_vec);
+            return Ok(());
         } else {
             for (i, s) in self.shapes.iter().enumerate() {
                 let pose = &poses[i];
@@ -502,11 +898,15 @@ impl ShapeCollection {
                 }
             }
 
-            return Ok(out_vec);
+            return Ok(());
         }
     }
     fn get_pairwise_objects_geometric_shape_query_input_vec<'a>(&'a self, input: &'a ShapeCollectionQuery) -> Result<Vec<GeometricShapeQuery<'a>>, OptimaError> {
         let mut out_vec = vec![];
+        self.fill_pairwise_objects_geometric_shape_query_input_vec(input, &mut out_vec)?;
+        Ok(out_vec)
+    }
+    fn fill_pairwise_objects_geometric_shape_query_input_vec<'a>(&'a self, input: &'a ShapeCollectionQuery, out_vec: &mut Vec<GeometricShapeQuery<'a>>) -> Result<(), OptimaError> {
 
         let poses = &input.get_shape_collection_input_poses()?[0].poses;
         let inclusion_list = input.get_inclusion_pairs_list();
@@ -578,7 +978,7 @@ impl ShapeCollection {
                 }
             }
 
-            return Ok(out_vec);
+            return Ok(());
         } else {
             for (i, shape1) in self.shapes.iter().enumerate() {
             let pose1 = &poses[i];
@@ -648,7 +1048,7 @@ impl ShapeCollection {
                 }
             }
         }
-            return Ok(out_vec);
+            return Ok(());
         }
     }
 }
@@ -656,29 +1056,79 @@ impl SaveAndLoadable for ShapeCollection {
     type SaveType = (String, String, String, Vec<(GeometricShapeSignature, usize)>);
 
     fn get_save_serialization_object(&self) -> Self::SaveType {
-        (self.shapes.get_serialization_string(),
+        ((*self.shapes).get_serialization_string(),
          self.skips.get_serialization_string(),
          self.average_distances.get_serialization_string(),
          self.sorted_signatures_with_shape_idxs.clone())
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let load: Self::SaveType = load_object_from_json_string(json_str)?;
+        let load: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
         let shapes = Vec::load_from_json_string(&load.0)?;
-        let skips = load_object_from_json_string(&load.1)?;
-        let average_distances = load_object_from_json_string(&load.2)?;
+        let skips = SquareArray2D::load_from_json_string(&load.1)?;
+        let average_distances = SquareArray2D::load_from_json_string(&load.2)?;
         let sorted_signatures_with_shape_idxs = load.3.clone();
 
         Ok(Self {
-            shapes,
+            shapes: SharedHandle::new(shapes),
             skips,
             average_distances,
             sorted_signatures_with_shape_idxs,
-            id: SimpleSamplers::uniform_sample((-1.0,1.0))
+            collision_margins: CollisionMargins::default(),
+            id: Self::new_id()
         })
     }
 }
 
+/// Safety margins applied to pairwise queries in `ShapeCollection::shape_collection_query`.  Each
+/// shape has a margin (the global margin unless overridden for that shape), and a pair of shapes
+/// uses the larger of their two margins.  With a margin of 0.02, for example, two shapes that are
+/// 1 cm apart are reported as intersecting, and distances and contacts are reported 2 cm closer than
+/// they really are (see `GeometricShapeQueries::generic_query_with_margin`).
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CollisionMargins {
+    global_margin: f64,
+    shape_margin_overrides: Vec<Option<f64>>
+}
+impl CollisionMargins {
+    pub fn set_global_margin(&mut self, margin: f64) -> Result<(), OptimaError> {
+        Self::check_margin(margin)?;
+        self.global_margin = margin;
+        Ok(())
+    }
+    pub fn set_shape_margin(&mut self, shape_idx: usize, margin: Option<f64>) -> Result<(), OptimaError> {
+        if let Some(margin) = margin { Self::check_margin(margin)?; }
+        if self.shape_margin_overrides.len() <= shape_idx { self.shape_margin_overrides.resize(shape_idx + 1, None); }
+        self.shape_margin_overrides[shape_idx] = margin;
+        Ok(())
+    }
+    pub fn global_margin(&self) -> f64 {
+        self.global_margin
+    }
+    pub fn shape_margin(&self, shape_idx: usize) -> f64 {
+        return match self.shape_margin_overrides.get(shape_idx) {
+            Some(Some(margin)) => { *margin }
+            _ => { self.global_margin }
+        };
+    }
+    pub fn pair_margin(&self, shape_idx1: usize, shape_idx2: usize) -> f64 {
+        return self.shape_margin(shape_idx1).max(self.shape_margin(shape_idx2));
+    }
+    fn remove_shape(&mut self, shape_idx: usize) {
+        if shape_idx < self.shape_margin_overrides.len() { self.shape_margin_overrides.remove(shape_idx); }
+    }
+    /// Returns true if no margin is applied to any shape.
+    pub fn is_zero(&self) -> bool {
+        return self.global_margin == 0.0 && self.shape_margin_overrides.iter().all(|m| m.unwrap_or(0.0) == 0.0);
+    }
+    fn check_margin(margin: f64) -> Result<(), OptimaError> {
+        if !(margin >= 0.0) || !margin.is_finite() {
+            return Err(OptimaError::new_generic_error_str(&format!("Collision margin must be non-negative and finite (given {}).", margin), file!(), line!()));
+        }
+        Ok(())
+    }
+}
+
 /// An input into the important `ShapeCollection::shape_collection_query` function.
 pub enum ShapeCollectionQuery<'a> {
     ProjectPoint { poses: &'a ShapeCollectionInputPoses, point: &'a Vector3<f64>, solid: bool ,inclusion_list: &'a Option<&'a ShapeCollectionQueryList> },
@@ -696,6 +1146,24 @@ pub enum ShapeCollectionQuery<'a> {
     Proxima { poses: &'a ShapeCollectionInputPoses, prediction: f64, inclusion_list: &'a Option<&'a ShapeCollectionQueryPairsList> }
 }
 impl <'a> ShapeCollectionQuery<'a> {
+    /// The poses of the query (the poses at t1 for CCD queries).  Unlike
+    /// `get_shape_collection_input_poses`, this does not allocate.
+    fn get_primary_input_poses(&self) -> &'a ShapeCollectionInputPoses {
+        return match self {
+            ShapeCollectionQuery::ProjectPoint { poses, .. } => { poses }
+            ShapeCollectionQuery::ContainsPoint { poses, .. } => { poses }
+            ShapeCollectionQuery::DistanceToPoint { poses, .. } => { poses }
+            ShapeCollectionQuery::IntersectsRay { poses, .. } => { poses }
+            ShapeCollectionQuery::CastRay { poses, .. } => { poses }
+            ShapeCollectionQuery::CastRayAndGetNormal { poses, .. } => { poses }
+            ShapeCollectionQuery::IntersectionTest { poses, .. } => { poses }
+            ShapeCollectionQuery::Distance { poses, .. } => { poses }
+            ShapeCollectionQuery::ClosestPoints { poses, .. } => { poses }
+            ShapeCollectionQuery::Contact { poses, .. } => { poses }
+            ShapeCollectionQuery::CCD { poses_t1, .. } => { poses_t1 }
+            ShapeCollectionQuery::Proxima { poses, .. } => { poses }
+        }
+    }
     fn get_shape_collection_input_poses(&self) -> Result<Vec<&'a ShapeCollectionInputPoses>, OptimaError> {
         match self {
             ShapeCollectionQuery::ProjectPoint { poses, .. } => { Ok(vec![poses]) }
@@ -788,6 +1256,13 @@ impl ShapeCollectionInputPoses {
     pub fn poses(&self) -> &Vec<Option<OptimaSE3Pose>> {
         &self.poses
     }
+    /// Sets every pose to `None` without releasing the underlying storage.
+    pub fn clear(&mut self) {
+        for p in &mut self.poses { *p = None; }
+    }
+    pub fn len(&self) -> usize {
+        self.poses.len()
+    }
     /// Returns true if all poses in this object are `Some` and not `None`.
     pub fn is_full(&self) -> bool {
         for p in &self.poses {
@@ -797,6 +1272,66 @@ impl ShapeCollectionInputPoses {
     }
 }
 
+/// The per-pair state used by `ShapeCollection::shape_collection_query_warm_started`: the last
+/// separating direction found for each pair of shapes (in the local frame of the first shape).
+/// Spawn one with `ShapeCollection::spawn_warm_start_cache` per stream of temporally coherent
+/// queries.
+#[derive(Clone, Debug)]
+pub struct ShapeCollectionWarmStartCache {
+    separating_directions: HashMap<(usize, usize), Vector3<f64>>,
+    id: f64
+}
+impl ShapeCollectionWarmStartCache {
+    /// Forgets all cached directions, e.g., after a discontinuous jump in the poses.
+    pub fn clear(&mut self) {
+        self.separating_directions.clear();
+    }
+    /// The number of pairs with a cached direction.
+    pub fn len(&self) -> usize {
+        self.separating_directions.len()
+    }
+}
+
+/// Reusable buffers for `ShapeCollection::shape_collection_query_with_scratch` (and
+/// `RobotGeometricShapeModule::shape_collection_query_with_scratch`).  Create one per control loop
+/// (or per thread) and pass it into every query.  All buffers are owned: the shapes to query are
+/// stored by index, so the scratch does not borrow from any query or shape collection.  The buffers
+/// grow to the size of the largest query and are then reused; see
+/// `ShapeCollection::shape_collection_query_with_scratch` for the allocations that remain.
+pub struct QueryScratch {
+    poses: Option<ShapeCollectionInputPoses>,
+    shape_idxs: Vec<(usize, Option<usize>)>,
+    output_distances: Vec<f64>,
+    output: GeometricShapeQueryGroupOutput
+}
+impl QueryScratch {
+    pub fn new() -> Self {
+        Self {
+            poses: None,
+            shape_idxs: vec![],
+            output_distances: vec![],
+            output: GeometricShapeQueryGroupOutput::new_empty()
+        }
+    }
+    /// The output of the most recent query run with this scratch.
+    pub fn output(&self) -> &GeometricShapeQueryGroupOutput {
+        &self.output
+    }
+    /// Takes the pose buffer out of the scratch (e.g., to fill it from forward kinematics before a
+    /// query).  Hand it back with `return_poses` so that it can be reused.
+    pub fn take_poses(&mut self) -> Option<ShapeCollectionInputPoses> {
+        self.poses.take()
+    }
+    pub fn return_poses(&mut self, poses: ShapeCollectionInputPoses) {
+        self.poses = Some(poses);
+    }
+}
+impl Default for QueryScratch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ShapeCollectionQueryList {
     list: Vec<usize>,
@@ -1055,6 +1590,74 @@ pub struct ProximaSingleComparisonOutput {
     ground_truth_check: bool
 }
 
+/// The output of `ShapeCollection::batch_cast_rays`.  hits\[i\] is the closest hit of the i-th ray,
+/// or None if the ray did not hit anything within max_toi.
+#[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
+#[cfg_attr(target_arch = "wasm32", derive(Clone, Debug, Serialize, Deserialize))]
+pub struct BatchRayCastOutput {
+    hits: Vec<Option<RayCastHit>>,
+    duration: Duration
+}
+impl BatchRayCastOutput {
+    pub fn hits(&self) -> &Vec<Option<RayCastHit>> {
+        &self.hits
+    }
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+    /// Returns the hit distance (time of impact) of each ray, with f64::INFINITY for rays that did
+    /// not hit anything.  For rays with unit length directions, this is a simulated range scan.
+    pub fn distances(&self) -> Vec<f64> {
+        let mut out_vec = vec![];
+        for h in &self.hits {
+            match h {
+                None => { out_vec.push(f64::INFINITY); }
+                Some(h) => { out_vec.push(h.toi); }
+            }
+        }
+        out_vec
+    }
+    pub fn num_hits(&self) -> usize {
+        self.hits.iter().filter(|h| h.is_some()).count()
+    }
+}
+#[cfg(not(target_arch = "wasm32"))]
+#[pymethods]
+impl BatchRayCastOutput {
+    pub fn distances_py(&self) -> Vec<f64> {
+        self.distances()
+    }
+    pub fn num_hits_py(&self) -> usize {
+        self.num_hits()
+    }
+    pub fn to_json_string_py(&self) -> String {
+        self.to_json_string()
+    }
+}
+
+/// A single ray hit in a `BatchRayCastOutput`.  point and normal are in the world frame.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RayCastHit {
+    toi: f64,
+    point: Vector3<f64>,
+    normal: Vector3<f64>,
+    signature: GeometricShapeSignature
+}
+impl RayCastHit {
+    pub fn toi(&self) -> f64 {
+        self.toi
+    }
+    pub fn point(&self) -> &Vector3<f64> {
+        &self.point
+    }
+    pub fn normal(&self) -> &Vector3<f64> {
+        &self.normal
+    }
+    pub fn signature(&self) -> &GeometricShapeSignature {
+        &self.signature
+    }
+}
+
 #[cfg_attr(not(target_arch = "wasm32"), pyclass, derive(Clone, Debug, Serialize, Deserialize))]
 pub struct ProximaProximityOutput {
     output_sum: f64,
@@ -1510,3 +2113,5 @@ impl ShapeCollectionBVHAABBBlenderDrawingUtil {
         self.to_json_string()
     }
 }
+
+crate::impl_py_pickle!(ProximaEngine, BatchRayCastOutput, ProximaProximityOutput, ProximaSceneFilterOutput, WitnessPointsCollection, WitnessPoints, ShapeCollectionBVHAABBBlenderDrawingUtil);
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+use parry3d_f64::shape::Shape;
+
+static SHARED_MESH_STORE: Mutex<Option<SharedMeshStoreData>> = Mutex::new(None);
+
+/// A process-wide store of the parry3d geometry built for mesh based `GeometricShape` objects
+/// (convex hulls and triangle meshes), keyed by the mesh's asset path.  Every shape built from the
+/// same mesh file shares one `Arc` of the underlying geometry, so, e.g., links that reuse a mesh,
+/// several robots of the same kind in a robot set, and every copy of a `RobotGeometricShapeModule`
+/// point to the same convex hulls and triangle meshes instead of each owning a copy.  Spawning a
+/// shape whose mesh is already in the store also skips loading the mesh file.
+///
+/// The store only holds weak references, so geometry is freed as soon as the last shape using it is
+/// dropped.  Meshes that are not backed by a file (i.e., with empty path string components) are not
+/// shared.  Use `set_enabled(false)` to opt out.
+pub struct SharedMeshStore;
+impl SharedMeshStore {
+    /// Returns the shared geometry (along with its f value, see `GeometricShape::f`) for the given
+    /// mesh, if a live shape still uses it.
+    pub fn get(mesh_type: SharedMeshType, path_string_components: &Vec<String>) -> Option<(Arc<dyn Shape>, f64)> {
+        if !Self::enabled() || path_string_components.is_empty() { return None; }
+
+        let key = (mesh_type, path_string_components.clone());
+        return Self::with_data(|data| {
+            let (shape, f) = data.entries.get(&key)?;
+            let shape = shape.upgrade()?;
+            Some((shape, *f))
+        });
+    }
+    /// Returns the shared geometry for the given mesh, building it with `build` (and storing it) if
+    /// it is not already in the store.
+    pub fn get_or_insert_with<F: FnOnce() -> (Arc<dyn Shape>, f64)>(mesh_type: SharedMeshType, path_string_components: &Vec<String>, build: F) -> (Arc<dyn Shape>, f64) {
+        if !Self::enabled() || path_string_components.is_empty() { return build(); }
+
+        if let Some(out) = Self::get(mesh_type, path_string_components) { return out; }
+
+        // Built outside of the lock.  If another thread inserted the same mesh in the meantime, its
+        // geometry is kept and ours is dropped so that there is still only one copy.
+        let (shape, f) = build();
+        let key = (mesh_type, path_string_components.clone());
+        return Self::with_data(|data| {
+            if let Some((existing, existing_f)) = data.entries.get(&key) {
+                if let Some(existing) = existing.upgrade() { return (existing, *existing_f); }
+            }
+            data.entries.insert(key, (Arc::downgrade(&shape), f));
+            (shape, f)
+        });
+    }
+    /// Removes all entries.  Shapes that already use shared geometry keep it.
+    pub fn clear() {
+        Self::with_data(|data| { data.entries.clear(); });
+    }
+    /// Removes entries whose geometry is no longer used by any shape.
+    pub fn prune() {
+        Self::with_data(|data| { data.entries.retain(|_, (shape, _)| shape.strong_count() > 0); });
+    }
+    /// Returns the number of meshes in the store that are still used by at least one shape.
+    pub fn num_live_entries() -> usize {
+        return Self::with_data(|data| data.entries.values().filter(|(shape, _)| shape.strong_count() > 0).count());
+    }
+    pub fn set_enabled(enabled: bool) {
+        Self::with_data(|data| { data.enabled = enabled; });
+    }
+    pub fn enabled() -> bool {
+        return Self::with_data(|data| data.enabled);
+    }
+    fn with_data<R, F: FnOnce(&mut SharedMeshStoreData) -> R>(f: F) -> R {
+        let mut guard = match SHARED_MESH_STORE.lock() {
+            Ok(guard) => { guard }
+            Err(poisoned) => { poisoned.into_inner() }
+        };
+        let data = guard.get_or_insert_with(SharedMeshStoreData::new);
+        return f(data);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SharedMeshType {
+    ConvexShape,
+    TriangleMesh
+}
+
+struct SharedMeshStoreData {
+    enabled: bool,
+    entries: HashMap<(SharedMeshType, Vec<String>), (Weak<dyn Shape>, f64)>
+}
+impl SharedMeshStoreData {
+    fn new() -> Self {
+        Self {
+            enabled: true,
+            entries: HashMap::new()
+        }
+    }
+}
@@ -41,6 +41,10 @@ impl TrimeshEngine {
             path_string_components
         }
     }
+    /// A point cloud with no faces, e.g., as input to `compute_convex_hull`.
+    pub fn new_from_points(points: Vec<Vector3<f64>>) -> Self {
+        return Self::new_from_vertices_and_indices(points, vec![], vec![]);
+    }
     pub fn compute_convex_decomposition(&self, resolution: ConvexDecompositionResolution) -> Vec<TrimeshEngine> {
         let points: Vec<Point3<f64>> = self.vertices.iter().map(|v| NalgebraConversions::vector3_to_point3(v)).collect();
         let indices: Vec<[u32; 3]> = self.indices.iter().map(|i| [i[0] as u32, i[1] as u32, i[2] as u32] ).collect();
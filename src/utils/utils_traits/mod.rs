@@ -1,23 +1,106 @@
 use serde::de::DeserializeOwned;
-use serde::{Serialize};
+use serde::{Serialize, Deserialize};
+use crate::utils::utils_console::{optima_print, PrintColor, PrintMode};
 use crate::utils::utils_errors::OptimaError;
-use crate::utils::utils_files::optima_path::{load_object_from_json_string, OptimaAssetLocation, OptimaStemCellPath};
+use crate::utils::utils_files::optima_path::{OptimaAssetLocation, OptimaStemCellPath};
 
+/// Objects that are saved to and loaded from json, e.g., preprocessed robot module assets.
+///
+/// Every payload is written inside a `VersionedSavePayload` that records `SAVE_FORMAT_VERSION`, so
+/// that payloads written by older versions of the crate can be recognized on load.  Those are passed
+/// through `migrate_save_object` before being deserialized; payloads that cannot be migrated (or
+/// that were written by a newer version) fail with an `IncompatibleSaveFormatError` that says how to
+/// regenerate them, rather than with a bare deserialization error.
 pub trait SaveAndLoadable {
     type SaveType: Serialize + DeserializeOwned;
+    /// Version of this type's save format.  Bump it whenever `SaveType` changes in a way that older
+    /// payloads no longer deserialize, and convert the older versions in `migrate_save_object`.
+    const SAVE_FORMAT_VERSION: u32 = 1;
 
     fn get_save_serialization_object(&self) -> Self::SaveType;
     fn get_serialization_string(&self) -> String {
-        serde_json::to_string(&self.get_save_serialization_object()).expect("error")
+        serde_json::to_string(&VersionedSavePayload::new(Self::SAVE_FORMAT_VERSION, &self.get_save_serialization_object())).expect("error")
     }
     fn save_to_path(&self, path: &OptimaStemCellPath) -> Result<(), OptimaError> {
-        path.save_object_to_file_as_json(&self.get_save_serialization_object())
+        path.save_object_to_file_as_json(&VersionedSavePayload::new(Self::SAVE_FORMAT_VERSION, &self.get_save_serialization_object()))
     }
     fn load_from_path(path: &OptimaStemCellPath) -> Result<Self, OptimaError> where Self: Sized {
         let s = path.read_file_contents_to_string()?;
         return Self::load_from_json_string(&s);
     }
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized;
+    /// Parses the `SaveType` out of a string written by `get_serialization_string` (or by
+    /// `save_to_path`), migrating it if it was written with an older save format version.
+    /// Implementations of `load_from_json_string` should start from this.
+    fn load_save_object_from_json_string(json_str: &str) -> Result<Self::SaveType, OptimaError> {
+        let type_name = std::any::type_name::<Self>();
+        let (version, value) = VersionedSavePayload::parse(json_str, type_name)?;
+        if version > Self::SAVE_FORMAT_VERSION {
+            return Err(OptimaError::new_incompatible_save_format_error(type_name, version, Self::SAVE_FORMAT_VERSION, "It was written by a newer version of optima; update optima to load it", file!(), line!()));
+        }
+        let value = if version == Self::SAVE_FORMAT_VERSION { value } else { Self::migrate_save_object(version, value)? };
+        return match serde_json::from_value(value) {
+            Ok(load) => { Ok(load) }
+            Err(e) => {
+                Err(OptimaError::new_incompatible_save_format_error(type_name, version, Self::SAVE_FORMAT_VERSION, &format!("The payload does not match the expected layout ({}).  If this is a preprocessed robot asset, re-run preprocessing for the robot", e), file!(), line!()))
+            }
+        }
+    }
+    /// Converts a payload written with an older save format version (0 for payloads written before
+    /// versions were embedded) to the current layout.  By default, unversioned payloads are accepted
+    /// as they are while `SAVE_FORMAT_VERSION` is still 1, and everything else is rejected.
+    fn migrate_save_object(from_version: u32, value: serde_json::Value) -> Result<serde_json::Value, OptimaError> {
+        return if from_version == 0 && Self::SAVE_FORMAT_VERSION == 1 {
+            Ok(value)
+        } else {
+            Err(OptimaError::new_incompatible_save_format_error(std::any::type_name::<Self>(), from_version, Self::SAVE_FORMAT_VERSION, "No migration exists for this version.  If this is a preprocessed robot asset, re-run preprocessing for the robot", file!(), line!()))
+        }
+    }
+}
+
+/// The envelope that every `SaveAndLoadable` payload is written in.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VersionedSavePayload {
+    optima_save_format_version: u32,
+    payload: serde_json::Value
+}
+impl VersionedSavePayload {
+    pub fn new<T: Serialize>(version: u32, payload: &T) -> Self {
+        Self {
+            optima_save_format_version: version,
+            payload: serde_json::to_value(payload).expect("error")
+        }
+    }
+    /// Returns the save format version and the payload.  Json that is not wrapped in an envelope
+    /// was written before versions were embedded, and is returned whole as version 0.
+    pub fn parse(json_str: &str, type_name: &str) -> Result<(u32, serde_json::Value), OptimaError> {
+        let value: serde_json::Value = match serde_json::from_str(json_str) {
+            Ok(value) => { value }
+            Err(e) => {
+                return Err(OptimaError::new_generic_error_str(&format!("Could not parse saved {} as json ({}).  The file may be truncated or corrupted.", type_name, e), file!(), line!()));
+            }
+        };
+
+        let is_envelope = match &value {
+            serde_json::Value::Object(map) => { map.len() == 2 && map.contains_key("optima_save_format_version") && map.contains_key("payload") }
+            _ => { false }
+        };
+        if !is_envelope { return Ok((0, value)); }
+
+        let envelope: Self = match serde_json::from_value(value) {
+            Ok(envelope) => { envelope }
+            Err(e) => {
+                return Err(OptimaError::new_generic_error_str(&format!("Saved {} has a malformed version header ({}).", type_name, e), file!(), line!()));
+            }
+        };
+        return Ok((envelope.optima_save_format_version, envelope.payload));
+    }
+    pub fn version(&self) -> u32 {
+        self.optima_save_format_version
+    }
+    pub fn payload(&self) -> &serde_json::Value {
+        &self.payload
+    }
 }
 impl <T> SaveAndLoadable for Vec<T> where T: SaveAndLoadable{
     type SaveType = Vec<String>;
@@ -33,7 +116,7 @@ impl <T> SaveAndLoadable for Vec<T> where T: SaveAndLoadable{
     }
 
     fn load_from_json_string(json_str: &str) -> Result<Self, OptimaError> where Self: Sized {
-        let load: Self::SaveType = load_object_from_json_string(json_str)?;
+        let load: Self::SaveType = Self::load_save_object_from_json_string(json_str)?;
 
         let mut out_vec = vec![];
         for s in &load {
@@ -55,9 +138,35 @@ pub trait AssetSaveAndLoadable: SaveAndLoadable {
         path.append_file_location(&location);
         Self::load_from_path(&path)
     }
+    /// Same as `load_as_asset`, but for assets that can be regenerated (e.g., preprocessed robot
+    /// modules).  If the file exists but cannot be loaded, it is quarantined (see
+    /// `OptimaPath::quarantine_file`) and a warning is printed, so that the caller can regenerate it
+    /// instead of failing.  Returns None if the file does not exist or was quarantined.
+    fn load_as_asset_or_quarantine(location: OptimaAssetLocation) -> Result<Option<Self>, OptimaError> where Self: Sized {
+        let mut path = OptimaStemCellPath::new_asset_path()?;
+        path.append_file_location(&location);
+        if !path.exists() { return Ok(None); }
+
+        return match Self::load_from_path(&path) {
+            Ok(load) => { Ok(Some(load)) }
+            Err(e) => {
+                quarantine_corrupted_file(&path, &e);
+                Ok(None)
+            }
+        }
+    }
 }
 impl <T> AssetSaveAndLoadable for T where T: SaveAndLoadable { }
 
+/// Quarantines a file that failed to load with the given error and prints a warning saying so.
+pub fn quarantine_corrupted_file(path: &OptimaStemCellPath, error: &OptimaError) {
+    let message = match path.quarantine_file() {
+        Ok(quarantined) => { format!("WARNING: Could not load {} ({:?}).  The file was moved to {} and will be regenerated.", path.to_string(), error, quarantined.to_string()) }
+        Err(_) => { format!("WARNING: Could not load {} ({:?}).  It will be regenerated.", path.to_string(), error) }
+    };
+    optima_print(&message, PrintMode::Println, PrintColor::Yellow, true);
+}
+
 pub trait ToAndFromRonString: Serialize + DeserializeOwned {
     fn to_ron_string(&self) -> String {
         ron::to_string(self).expect("error")
@@ -84,9 +193,20 @@ pub trait ToAndFromJsonString: Serialize + DeserializeOwned {
 }
 impl <T> ToAndFromJsonString for T where T: Serialize + DeserializeOwned {  }
 
+/// Note that TOML cannot represent every serde data model type (e.g., externally tagged enum
+/// variants with data, or None inside of arrays), so `to_toml_string` returns an error for types
+/// that use them.
 pub trait ToAndFromTomlString: Serialize + DeserializeOwned {
-    fn to_toml_string(&self) -> String {
-        toml::to_string(self).expect("error")
+    fn to_toml_string(&self) -> Result<String, OptimaError> {
+        // Converting to a toml::Value first ensures that plain values are emitted before tables.
+        let value = match toml::Value::try_from(self) {
+            Ok(value) => { value }
+            Err(e) => { return Err(OptimaError::new_generic_error_str(&format!("Could not convert {} to toml ({}).", std::any::type_name::<Self>(), e), file!(), line!())); }
+        };
+        return match toml::to_string(&value) {
+            Ok(s) => { Ok(s) }
+            Err(e) => { Err(OptimaError::new_generic_error_str(&format!("Could not write {} as a toml string ({}).", std::any::type_name::<Self>(), e), file!(), line!())) }
+        }
     }
     fn from_toml_string(toml_string: &str) -> Result<Self, OptimaError> where Self: Sized {
         let load: Result<Self, _> = toml::from_str(toml_string);
@@ -97,3 +217,43 @@ pub trait ToAndFromTomlString: Serialize + DeserializeOwned {
 }
 impl <T> ToAndFromTomlString for T where T: Serialize + DeserializeOwned {  }
 
+
+pub trait ToAndFromYamlString: Serialize + DeserializeOwned {
+    fn to_yaml_string(&self) -> String {
+        serde_yaml::to_string(self).expect("error")
+    }
+    fn from_yaml_string(yaml_string: &str) -> Result<Self, OptimaError> where Self: Sized {
+        let load: Result<Self, _> = serde_yaml::from_str(yaml_string);
+        return if let Ok(load) = load { Ok(load) } else {
+            Err(OptimaError::new_generic_error_str(&format!("Could not load yaml string {:?} into correct type.", yaml_string), file!(), line!()))
+        }
+    }
+}
+impl <T> ToAndFromYamlString for T where T: Serialize + DeserializeOwned {  }
+
+/// Makes a serializable pyclass picklable (e.g., so that it can be sent across
+/// `multiprocessing` workers or cached with `joblib`).  The object is reduced to its json string
+/// and rebuilt through a generated `_from_pickle_json_string` static method.
+///
+/// Requires pyo3's `multiple-pymethods` feature, as this generates its own `#[pymethods]` block
+/// alongside the type's existing one.
+#[macro_export]
+macro_rules! impl_py_pickle {
+    ($($t: ty),* $(,)?) => {
+        $(
+        #[cfg(not(target_arch = "wasm32"))]
+        #[pyo3::pymethods]
+        impl $t {
+            pub fn __reduce__(&self, py: pyo3::Python) -> pyo3::PyResult<(pyo3::PyObject, (String,))> {
+                let constructor = py.get_type::<$t>().getattr("_from_pickle_json_string")?;
+                let json_string = $crate::utils::utils_traits::ToAndFromJsonString::to_json_string(self);
+                return Ok((pyo3::PyObject::from(constructor), (json_string,)));
+            }
+            #[staticmethod]
+            pub fn _from_pickle_json_string(json_string: &str) -> $t {
+                return <$t as $crate::utils::utils_traits::ToAndFromJsonString>::from_json_string(json_string).expect("error");
+            }
+        }
+        )*
+    };
+}
@@ -0,0 +1,246 @@
+use nalgebra::{DMatrix, DVector};
+use serde::{Serialize, Deserialize};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_robot::robot_joint_trajectory::{RobotJointTrajectory, RobotJointTrajectoryWaypoint};
+use crate::utils::utils_nalgebra::conversions::NalgebraConversions;
+
+/// A B-spline trajectory over joint space.  The curve is parameterized directly by time: it is
+/// defined over \[knots\[degree\], knots\[num_control_points\]\], and each control point is a joint
+/// space vector.  Velocities and accelerations are evaluated analytically via the derivative
+/// B-splines (see `derivative`).
+///
+/// Waypoint trajectories can be converted to B-splines via interpolation (`new_interpolating` and
+/// `new_from_robot_joint_trajectory`) and back via sampling (`to_robot_joint_trajectory`).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BSplineTrajectory {
+    degree: usize,
+    knots: Vec<f64>,
+    control_points: Vec<DVector<f64>>
+}
+impl BSplineTrajectory {
+    /// The number of knots must equal num_control_points + degree + 1, and knots must be
+    /// non-decreasing.
+    pub fn new(degree: usize, knots: Vec<f64>, control_points: Vec<DVector<f64>>) -> Result<Self, OptimaError> {
+        if control_points.len() < degree + 1 {
+            return Err(OptimaError::new_generic_error_str(&format!("A B-spline of degree {} requires at least {} control points (given {}).", degree, degree + 1, control_points.len()), file!(), line!()));
+        }
+        if knots.len() != control_points.len() + degree + 1 {
+            return Err(OptimaError::new_generic_error_str(&format!("B-spline with {} control points and degree {} requires {} knots (given {}).", control_points.len(), degree, control_points.len() + degree + 1, knots.len()), file!(), line!()));
+        }
+        for i in 1..knots.len() {
+            if knots[i] < knots[i - 1] {
+                return Err(OptimaError::new_generic_error_str("B-spline knots must be non-decreasing.", file!(), line!()));
+            }
+        }
+        let dim = control_points[0].len();
+        for c in &control_points {
+            if c.len() != dim {
+                return Err(OptimaError::new_generic_error_str("All B-spline control points must have the same dimension.", file!(), line!()));
+            }
+        }
+
+        Ok(Self {
+            degree,
+            knots,
+            control_points
+        })
+    }
+    /// Creates a clamped B-spline with uniformly spaced interior knots over \[0, duration\].  The curve
+    /// starts at the first control point and ends at the last control point.
+    pub fn new_clamped_uniform(degree: usize, control_points: Vec<DVector<f64>>, duration: f64) -> Result<Self, OptimaError> {
+        if control_points.len() < degree + 1 {
+            return Err(OptimaError::new_generic_error_str(&format!("A B-spline of degree {} requires at least {} control points (given {}).", degree, degree + 1, control_points.len()), file!(), line!()));
+        }
+
+        let num_interior_knots = control_points.len() - degree - 1;
+        let mut knots = vec![0.0; degree + 1];
+        for i in 1..=num_interior_knots {
+            knots.push(duration * i as f64 / (num_interior_knots + 1) as f64);
+        }
+        for _ in 0..degree + 1 { knots.push(duration); }
+
+        return Self::new(degree, knots, control_points);
+    }
+    /// Creates a clamped B-spline that passes through each of the given waypoints at the given times.
+    /// Knots are placed using the averaging method.  If there are too few waypoints for the requested
+    /// degree, the degree is reduced to num_waypoints - 1.
+    pub fn new_interpolating(waypoints: &Vec<DVector<f64>>, times: &Vec<f64>, degree: usize) -> Result<Self, OptimaError> {
+        if waypoints.len() != times.len() {
+            return Err(OptimaError::new_generic_error_str(&format!("Number of waypoints ({}) does not match number of times ({}).", waypoints.len(), times.len()), file!(), line!()));
+        }
+        if waypoints.len() < 2 {
+            return Err(OptimaError::new_generic_error_str("At least two waypoints are required to interpolate a B-spline.", file!(), line!()));
+        }
+        for i in 1..times.len() {
+            if times[i] <= times[i - 1] {
+                return Err(OptimaError::new_generic_error_str("Waypoint times must be strictly increasing to interpolate a B-spline.", file!(), line!()));
+            }
+        }
+
+        let n = waypoints.len();
+        let degree = degree.min(n - 1).max(1);
+
+        let mut knots = vec![times[0]; degree + 1];
+        for j in 1..(n - degree) {
+            let mut sum = 0.0;
+            for i in j..(j + degree) { sum += times[i]; }
+            knots.push(sum / degree as f64);
+        }
+        for _ in 0..degree + 1 { knots.push(times[n - 1]); }
+
+        let dim = waypoints[0].len();
+        let placeholder = Self {
+            degree,
+            knots: knots.clone(),
+            control_points: vec![DVector::zeros(dim); n]
+        };
+
+        let mut a = DMatrix::zeros(n, n);
+        let mut b = DMatrix::zeros(n, dim);
+        for (row, t) in times.iter().enumerate() {
+            let span = placeholder.find_span(*t);
+            let basis = placeholder.basis_functions(span, *t);
+            for (r, value) in basis.iter().enumerate() {
+                a[(row, span - degree + r)] = *value;
+            }
+            for d in 0..dim { b[(row, d)] = waypoints[row][d]; }
+        }
+
+        let solution = a.lu().solve(&b);
+        let solution = match solution {
+            None => { return Err(OptimaError::new_generic_error_str("Could not solve for B-spline control points (singular system).", file!(), line!())); }
+            Some(solution) => { solution }
+        };
+
+        let mut control_points = vec![];
+        for i in 0..n {
+            control_points.push(DVector::from_fn(dim, |d, _| solution[(i, d)]));
+        }
+
+        return Self::new(degree, knots, control_points);
+    }
+    /// Interpolates the waypoints of a `RobotJointTrajectory` (in the trajectory's joint ordering).
+    pub fn new_from_robot_joint_trajectory(robot_joint_trajectory: &RobotJointTrajectory, degree: usize) -> Result<Self, OptimaError> {
+        let waypoints: Vec<DVector<f64>> = robot_joint_trajectory.waypoints().iter().map(|w| w.positions_as_dvector()).collect();
+        let times: Vec<f64> = robot_joint_trajectory.waypoints().iter().map(|w| w.time_from_start()).collect();
+        return Self::new_interpolating(&waypoints, &times, degree);
+    }
+    /// Samples the B-spline at num_samples evenly spaced times (including both end points).
+    pub fn to_robot_joint_trajectory(&self, robot_name: &str, joint_names: Vec<String>, num_samples: usize) -> Result<RobotJointTrajectory, OptimaError> {
+        let num_samples = num_samples.max(2);
+        let (start, end) = (self.start_time(), self.end_time());
+
+        let mut waypoints = vec![];
+        for i in 0..num_samples {
+            let t = start + (end - start) * i as f64 / (num_samples - 1) as f64;
+            waypoints.push(RobotJointTrajectoryWaypoint::new(t, NalgebraConversions::dvector_to_vec(&self.position(t))));
+        }
+
+        return RobotJointTrajectory::new(robot_name, joint_names, waypoints);
+    }
+    /// Evaluates the curve at time t.  t is clamped to \[start_time, end_time\].
+    pub fn position(&self, t: f64) -> DVector<f64> {
+        let t = t.max(self.start_time()).min(self.end_time());
+        let span = self.find_span(t);
+        let basis = self.basis_functions(span, t);
+
+        let mut out = DVector::zeros(self.dim());
+        for (r, value) in basis.iter().enumerate() {
+            out += *value * &self.control_points[span - self.degree + r];
+        }
+        out
+    }
+    pub fn velocity(&self, t: f64) -> DVector<f64> {
+        return self.derivative().position(t);
+    }
+    pub fn acceleration(&self, t: f64) -> DVector<f64> {
+        return self.derivative().derivative().position(t);
+    }
+    /// Returns the derivative of this B-spline as a B-spline of one lower degree.  The derivative of a
+    /// degree 0 B-spline is zero everywhere.
+    pub fn derivative(&self) -> Self {
+        let dim = self.dim();
+        if self.degree == 0 {
+            return Self {
+                degree: 0,
+                knots: self.knots.clone(),
+                control_points: vec![DVector::zeros(dim); self.control_points.len()]
+            }
+        }
+
+        let p = self.degree as f64;
+        let mut control_points = vec![];
+        for i in 0..self.control_points.len() - 1 {
+            let denominator = self.knots[i + self.degree + 1] - self.knots[i + 1];
+            if denominator == 0.0 {
+                control_points.push(DVector::zeros(dim));
+            } else {
+                control_points.push(p * (&self.control_points[i + 1] - &self.control_points[i]) / denominator);
+            }
+        }
+
+        Self {
+            degree: self.degree - 1,
+            knots: self.knots[1..self.knots.len() - 1].to_vec(),
+            control_points
+        }
+    }
+    /// Returns the index of the knot span that contains t.
+    fn find_span(&self, t: f64) -> usize {
+        let n = self.control_points.len() - 1;
+        if t >= self.knots[n + 1] { return n; }
+        if t <= self.knots[self.degree] { return self.degree; }
+
+        let mut low = self.degree;
+        let mut high = n + 1;
+        let mut mid = (low + high) / 2;
+        while t < self.knots[mid] || t >= self.knots[mid + 1] {
+            if t < self.knots[mid] { high = mid; } else { low = mid; }
+            mid = (low + high) / 2;
+        }
+        mid
+    }
+    /// Returns the degree + 1 basis functions that are non-zero in the given span, corresponding to
+    /// control points span - degree through span.
+    fn basis_functions(&self, span: usize, t: f64) -> Vec<f64> {
+        let p = self.degree;
+        let mut n = vec![0.0; p + 1];
+        let mut left = vec![0.0; p + 1];
+        let mut right = vec![0.0; p + 1];
+        n[0] = 1.0;
+        for j in 1..=p {
+            left[j] = t - self.knots[span + 1 - j];
+            right[j] = self.knots[span + j] - t;
+            let mut saved = 0.0;
+            for r in 0..j {
+                let denominator = right[r + 1] + left[j - r];
+                let temp = if denominator == 0.0 { 0.0 } else { n[r] / denominator };
+                n[r] = saved + right[r + 1] * temp;
+                saved = left[j - r] * temp;
+            }
+            n[j] = saved;
+        }
+        n
+    }
+    pub fn degree(&self) -> usize {
+        self.degree
+    }
+    pub fn knots(&self) -> &Vec<f64> {
+        &self.knots
+    }
+    pub fn control_points(&self) -> &Vec<DVector<f64>> {
+        &self.control_points
+    }
+    pub fn dim(&self) -> usize {
+        self.control_points[0].len()
+    }
+    pub fn start_time(&self) -> f64 {
+        self.knots[self.degree]
+    }
+    pub fn end_time(&self) -> f64 {
+        self.knots[self.control_points.len()]
+    }
+    pub fn duration(&self) -> f64 {
+        self.end_time() - self.start_time()
+    }
+}
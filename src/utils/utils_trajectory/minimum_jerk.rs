@@ -0,0 +1,128 @@
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateModule, RobotJointStateType};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_nalgebra::conversions::NalgebraConversions;
+use crate::utils::utils_robot::robot_joint_trajectory::{RobotJointTrajectory, RobotJointTrajectoryWaypoint};
+
+/// Peak of the normalized minimum-jerk velocity profile (reached at the midpoint).
+const MINIMUM_JERK_PEAK_VELOCITY_FACTOR: f64 = 1.875;
+/// Peak of the normalized minimum-jerk acceleration profile, 10 / sqrt(3).
+const MINIMUM_JERK_PEAK_ACCELERATION_FACTOR: f64 = 5.773502691896258;
+
+/// A minimum-jerk point-to-point joint space trajectory from a start state to a goal state, starting
+/// and ending at rest (zero velocity and acceleration).  Each joint follows the quintic profile
+/// start + (goal - start) * (10 s^3 - 15 s^4 + 6 s^5), where s = t / duration.  Intended for short,
+/// smooth motions that do not require full motion planning; no collision checking is performed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MinimumJerkTrajectory {
+    start: DVector<f64>,
+    goal: DVector<f64>,
+    duration: f64
+}
+impl MinimumJerkTrajectory {
+    pub fn new_with_duration(start: DVector<f64>, goal: DVector<f64>, duration: f64) -> Result<Self, OptimaError> {
+        if start.len() != goal.len() {
+            return Err(OptimaError::new_generic_error_str(&format!("Start ({}) and goal ({}) have different lengths.", start.len(), goal.len()), file!(), line!()));
+        }
+        if duration < 0.0 {
+            return Err(OptimaError::new_generic_error_str(&format!("Duration must be non-negative (given {}).", duration), file!(), line!()));
+        }
+
+        Ok(Self {
+            start,
+            goal,
+            duration
+        })
+    }
+    /// Uses the shortest duration for which no joint exceeds its velocity limit or (if given) its
+    /// acceleration limit.  Infinite limits are ignored.
+    pub fn new_from_limits(start: DVector<f64>, goal: DVector<f64>, velocity_limits: &Vec<f64>, acceleration_limits: Option<&Vec<f64>>) -> Result<Self, OptimaError> {
+        if velocity_limits.len() != start.len() {
+            return Err(OptimaError::new_generic_error_str(&format!("Number of velocity limits ({}) does not match state length ({}).", velocity_limits.len(), start.len()), file!(), line!()));
+        }
+        if let Some(acceleration_limits) = acceleration_limits {
+            if acceleration_limits.len() != start.len() {
+                return Err(OptimaError::new_generic_error_str(&format!("Number of acceleration limits ({}) does not match state length ({}).", acceleration_limits.len(), start.len()), file!(), line!()));
+            }
+        }
+
+        let mut duration: f64 = 0.0;
+        for i in 0..start.len().min(goal.len()) {
+            let delta = (goal[i] - start[i]).abs();
+            if delta == 0.0 { continue; }
+
+            let v = velocity_limits[i];
+            if v <= 0.0 {
+                return Err(OptimaError::new_generic_error_str(&format!("Joint {} must move but has a velocity limit of {}.", i, v), file!(), line!()));
+            }
+            if v.is_finite() { duration = duration.max(MINIMUM_JERK_PEAK_VELOCITY_FACTOR * delta / v); }
+
+            if let Some(acceleration_limits) = acceleration_limits {
+                let a = acceleration_limits[i];
+                if a <= 0.0 {
+                    return Err(OptimaError::new_generic_error_str(&format!("Joint {} must move but has an acceleration limit of {}.", i, a), file!(), line!()));
+                }
+                if a.is_finite() { duration = duration.max((MINIMUM_JERK_PEAK_ACCELERATION_FACTOR * delta / a).sqrt()); }
+            }
+        }
+
+        return Self::new_with_duration(start, goal, duration);
+    }
+    /// Creates a trajectory between two robot joint states (in DOF space), with the duration derived
+    /// from the robot's URDF velocity limits and, optionally, the given acceleration limits.
+    pub fn new_from_robot_joint_states(robot_joint_state_module: &RobotJointStateModule, start: &RobotJointState, goal: &RobotJointState, acceleration_limits: Option<&Vec<f64>>) -> Result<Self, OptimaError> {
+        let start = robot_joint_state_module.convert_joint_state_to_dof_state(start)?;
+        let goal = robot_joint_state_module.convert_joint_state_to_dof_state(goal)?;
+        let velocity_limits = robot_joint_state_module.get_joint_state_velocity_limits(&RobotJointStateType::DOF);
+        return Self::new_from_limits(start.joint_state().clone(), goal.joint_state().clone(), &velocity_limits, acceleration_limits);
+    }
+    fn normalized_time(&self, t: f64) -> f64 {
+        if self.duration == 0.0 { return 1.0; }
+        return (t / self.duration).max(0.0).min(1.0);
+    }
+    pub fn position(&self, t: f64) -> DVector<f64> {
+        let s = self.normalized_time(t);
+        let blend = 10.0 * s.powi(3) - 15.0 * s.powi(4) + 6.0 * s.powi(5);
+        return &self.start + blend * (&self.goal - &self.start);
+    }
+    pub fn velocity(&self, t: f64) -> DVector<f64> {
+        if self.duration == 0.0 || t < 0.0 || t > self.duration { return DVector::zeros(self.start.len()); }
+        let s = self.normalized_time(t);
+        let blend = (30.0 * s.powi(2) - 60.0 * s.powi(3) + 30.0 * s.powi(4)) / self.duration;
+        return blend * (&self.goal - &self.start);
+    }
+    pub fn acceleration(&self, t: f64) -> DVector<f64> {
+        if self.duration == 0.0 || t < 0.0 || t > self.duration { return DVector::zeros(self.start.len()); }
+        let s = self.normalized_time(t);
+        let blend = (60.0 * s - 180.0 * s.powi(2) + 120.0 * s.powi(3)) / self.duration.powi(2);
+        return blend * (&self.goal - &self.start);
+    }
+    pub fn jerk(&self, t: f64) -> DVector<f64> {
+        if self.duration == 0.0 || t < 0.0 || t > self.duration { return DVector::zeros(self.start.len()); }
+        let s = self.normalized_time(t);
+        let blend = (60.0 - 360.0 * s + 360.0 * s.powi(2)) / self.duration.powi(3);
+        return blend * (&self.goal - &self.start);
+    }
+    /// Samples the trajectory at num_samples evenly spaced times (including both end points).
+    pub fn to_robot_joint_trajectory(&self, robot_name: &str, joint_names: Vec<String>, num_samples: usize) -> Result<RobotJointTrajectory, OptimaError> {
+        let num_samples = num_samples.max(2);
+
+        let mut waypoints = vec![];
+        for i in 0..num_samples {
+            let t = self.duration * i as f64 / (num_samples - 1) as f64;
+            waypoints.push(RobotJointTrajectoryWaypoint::new(t, NalgebraConversions::dvector_to_vec(&self.position(t))));
+        }
+
+        return RobotJointTrajectory::new(robot_name, joint_names, waypoints);
+    }
+    pub fn start(&self) -> &DVector<f64> {
+        &self.start
+    }
+    pub fn goal(&self) -> &DVector<f64> {
+        &self.goal
+    }
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+}
@@ -0,0 +1,4 @@
+pub mod bspline;
+pub mod minimum_jerk;
+pub mod online_trajectory_generation;
+pub mod time_dilation;
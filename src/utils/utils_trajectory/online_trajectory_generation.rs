@@ -0,0 +1,223 @@
+use nalgebra::DVector;
+use serde::{Serialize, Deserialize};
+use crate::utils::utils_errors::OptimaError;
+
+/// Real-time capable, jerk-limited online trajectory generator (OTG).  Given the current position,
+/// velocity, and acceleration of each DOF and a (possibly changing) target, `update` computes the
+/// next setpoint one control cycle ahead such that velocity, acceleration, and jerk limits are
+/// respected.  This is intended to sit between offline plans and servo loops: a new target can be
+/// given at any cycle and the motion is smoothly re-planned from the current state.
+///
+/// Each DOF is handled independently by a tracking law, not by solving for a time-optimal profile.
+/// The law follows a velocity reference that is limited by the braking distance to the target
+/// (accounting for the acceleration ramp-up time under the jerk limit), and picks the jerk that
+/// moves the acceleration toward the one needed to reach that reference.  Motions are therefore
+/// slower than those of a time-optimal solver such as Ruckig (typically by the jerk ramp times at
+/// the start and end of each motion), and DOFs are not time-synchronized.  The position, velocity,
+/// and acceleration of each setpoint are integrated consistently from the applied (clamped) jerk.
+/// In the cycle in which a DOF reaches its velocity limit, its acceleration is reduced to land
+/// exactly on the limit, which can exceed the jerk limit for that cycle.
+/// `update` performs a constant amount of work per DOF and does not allocate beyond the returned
+/// state.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JerkLimitedOnlineTrajectoryGenerator {
+    cycle_time: f64,
+    max_velocities: Vec<f64>,
+    max_accelerations: Vec<f64>,
+    max_jerks: Vec<f64>,
+    position_tolerance: f64,
+    velocity_tolerance: f64
+}
+impl JerkLimitedOnlineTrajectoryGenerator {
+    pub fn new(cycle_time: f64, max_velocities: Vec<f64>, max_accelerations: Vec<f64>, max_jerks: Vec<f64>) -> Result<Self, OptimaError> {
+        if cycle_time <= 0.0 {
+            return Err(OptimaError::new_generic_error_str(&format!("Cycle time must be positive (given {}).", cycle_time), file!(), line!()));
+        }
+        if max_velocities.len() != max_accelerations.len() || max_velocities.len() != max_jerks.len() {
+            return Err(OptimaError::new_generic_error_str(&format!("Limit vectors have different lengths ({}, {}, {}).", max_velocities.len(), max_accelerations.len(), max_jerks.len()), file!(), line!()));
+        }
+        for i in 0..max_velocities.len() {
+            if !(max_velocities[i] > 0.0 && max_accelerations[i] > 0.0 && max_jerks[i] > 0.0) {
+                return Err(OptimaError::new_generic_error_str(&format!("All limits for DOF {} must be positive.", i), file!(), line!()));
+            }
+        }
+
+        Ok(Self {
+            cycle_time,
+            max_velocities,
+            max_accelerations,
+            max_jerks,
+            position_tolerance: 1e-6,
+            velocity_tolerance: 1e-5
+        })
+    }
+    /// Sets the tolerances used to decide when the target has been reached.
+    pub fn set_tolerances(&mut self, position_tolerance: f64, velocity_tolerance: f64) {
+        self.position_tolerance = position_tolerance;
+        self.velocity_tolerance = velocity_tolerance;
+    }
+    /// Computes the state one cycle ahead of current_state while moving toward target.
+    pub fn update(&self, current_state: &OTGState, target: &OTGTarget) -> Result<OTGOutput, OptimaError> {
+        let n = self.num_dofs();
+        current_state.check_dimension(n)?;
+        target.check_dimension(n)?;
+
+        let dt = self.cycle_time;
+        let mut new_state = current_state.clone();
+        let mut jerk = DVector::zeros(n);
+        let mut finished = true;
+
+        for i in 0..n {
+            let (p, v, a) = (current_state.position[i], current_state.velocity[i], current_state.acceleration[i]);
+            let (p_target, v_target) = (target.position[i], target.velocity[i]);
+            let (v_max, a_max, j_max) = (self.max_velocities[i], self.max_accelerations[i], self.max_jerks[i]);
+
+            let e = p_target - p;
+            if e.abs() <= self.position_tolerance && (v - v_target).abs() <= self.velocity_tolerance && a.abs() <= a_max * 1e-3 {
+                new_state.position[i] = p_target;
+                new_state.velocity[i] = v_target;
+                new_state.acceleration[i] = 0.0;
+                continue;
+            }
+            finished = false;
+
+            // Time to ramp the acceleration between zero and its limit under the jerk limit.
+            let t_j = a_max / j_max;
+
+            let d_eff = (e.abs() - v.abs() * t_j).max(0.0);
+            let v_mag = v_max.min((2.0 * a_max * d_eff).sqrt()).min(e.abs() / t_j);
+            let v_ref = (e.signum() * v_mag + v_target).max(-v_max).min(v_max);
+
+            let a_ref = ((v_ref - v) / t_j).max(-a_max).min(a_max);
+            let j = ((a_ref - a) / dt).max(-j_max).min(j_max);
+
+            // The jerk actually applied is the one that keeps the acceleration within its limits.
+            let mut a_new = (a + j * dt).max(-a_max).min(a_max);
+            let mut j_applied = (a_new - a) / dt;
+            let mut v_new = v + a * dt + 0.5 * j_applied * dt * dt;
+            if v_new.abs() > v_max {
+                // Pick the jerk under which the velocity lands exactly on its limit at the
+                // end of the cycle.
+                v_new = v_new.max(-v_max).min(v_max);
+                a_new = 2.0 * (v_new - v) / dt - a;
+                j_applied = (a_new - a) / dt;
+            }
+            let p_new = p + v * dt + 0.5 * a * dt * dt + j_applied * dt * dt * dt / 6.0;
+
+            new_state.position[i] = p_new;
+            new_state.velocity[i] = v_new;
+            new_state.acceleration[i] = a_new;
+            jerk[i] = j_applied;
+        }
+
+        Ok(OTGOutput {
+            new_state,
+            jerk,
+            finished
+        })
+    }
+    pub fn num_dofs(&self) -> usize {
+        self.max_velocities.len()
+    }
+    pub fn cycle_time(&self) -> f64 {
+        self.cycle_time
+    }
+    pub fn max_velocities(&self) -> &Vec<f64> {
+        &self.max_velocities
+    }
+    pub fn max_accelerations(&self) -> &Vec<f64> {
+        &self.max_accelerations
+    }
+    pub fn max_jerks(&self) -> &Vec<f64> {
+        &self.max_jerks
+    }
+}
+
+/// Kinematic state of all DOFs at a single control cycle.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OTGState {
+    position: DVector<f64>,
+    velocity: DVector<f64>,
+    acceleration: DVector<f64>
+}
+impl OTGState {
+    pub fn new(position: DVector<f64>, velocity: DVector<f64>, acceleration: DVector<f64>) -> Self {
+        Self {
+            position,
+            velocity,
+            acceleration
+        }
+    }
+    /// A state at rest at the given position.
+    pub fn new_at_rest(position: DVector<f64>) -> Self {
+        let n = position.len();
+        return Self::new(position, DVector::zeros(n), DVector::zeros(n));
+    }
+    fn check_dimension(&self, n: usize) -> Result<(), OptimaError> {
+        if self.position.len() != n || self.velocity.len() != n || self.acceleration.len() != n {
+            return Err(OptimaError::new_generic_error_str(&format!("OTG state must have dimension {}.", n), file!(), line!()));
+        }
+        Ok(())
+    }
+    pub fn position(&self) -> &DVector<f64> {
+        &self.position
+    }
+    pub fn velocity(&self) -> &DVector<f64> {
+        &self.velocity
+    }
+    pub fn acceleration(&self) -> &DVector<f64> {
+        &self.acceleration
+    }
+}
+
+/// Target position and velocity for all DOFs.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OTGTarget {
+    position: DVector<f64>,
+    velocity: DVector<f64>
+}
+impl OTGTarget {
+    pub fn new(position: DVector<f64>, velocity: DVector<f64>) -> Self {
+        Self {
+            position,
+            velocity
+        }
+    }
+    /// A target at rest at the given position.
+    pub fn new_at_rest(position: DVector<f64>) -> Self {
+        let n = position.len();
+        return Self::new(position, DVector::zeros(n));
+    }
+    fn check_dimension(&self, n: usize) -> Result<(), OptimaError> {
+        if self.position.len() != n || self.velocity.len() != n {
+            return Err(OptimaError::new_generic_error_str(&format!("OTG target must have dimension {}.", n), file!(), line!()));
+        }
+        Ok(())
+    }
+    pub fn position(&self) -> &DVector<f64> {
+        &self.position
+    }
+    pub fn velocity(&self) -> &DVector<f64> {
+        &self.velocity
+    }
+}
+
+/// The output of a single `JerkLimitedOnlineTrajectoryGenerator::update` call.  finished is true when all DOFs
+/// have reached the target (in which case new_state equals the target).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OTGOutput {
+    new_state: OTGState,
+    jerk: DVector<f64>,
+    finished: bool
+}
+impl OTGOutput {
+    pub fn new_state(&self) -> &OTGState {
+        &self.new_state
+    }
+    pub fn jerk(&self) -> &DVector<f64> {
+        &self.jerk
+    }
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+}
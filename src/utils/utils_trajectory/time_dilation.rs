@@ -0,0 +1,180 @@
+use serde::{Serialize, Deserialize};
+use crate::robot_modules::robot_joint_state_module::{RobotJointStateModule, RobotJointStateType};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_robot::robot_joint_trajectory::{RobotJointTrajectory, RobotJointTrajectoryWaypoint};
+
+/// Maximum number of sweeps over the waypoints when resolving acceleration violations with
+/// `TimeDilationMode::Piecewise`.  Any violation that remains afterwards is removed with a final
+/// uniform dilation.
+pub const MAX_PIECEWISE_DILATION_SWEEPS: usize = 100;
+/// Relative amount by which an acceleration may exceed its limit before it counts as a violation
+/// (guards against re-dilating segments because of round-off).
+const ACCELERATION_RATIO_TOLERANCE: f64 = 1e-9;
+
+/// How `TrajectoryTimeDilation` stretches a trajectory in time.
+/// - `Uniform`: every segment is stretched by the same factor, so the timing profile keeps its shape
+/// and only the overall speed changes.
+/// - `Piecewise`: each segment between consecutive waypoints gets its own factor, so only the parts
+/// of the trajectory that violate a limit are slowed down.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeDilationMode {
+    Uniform,
+    Piecewise
+}
+
+/// Retimes an existing `RobotJointTrajectory` so that it satisfies new velocity and (optionally)
+/// acceleration limits.  The waypoint positions are kept as they are, so the geometric path is not
+/// replanned; only the times between waypoints are stretched.  Segments are never shortened, so a
+/// trajectory that already satisfies the limits is returned unchanged.
+///
+/// Velocities and accelerations are estimated with finite differences over the waypoints: the
+/// velocity of a segment is its displacement over its duration, and the acceleration at an interior
+/// waypoint is the change in velocity between its two segments over the mean of their durations.
+/// No acceleration is assumed at the first and last waypoints.
+pub struct TrajectoryTimeDilation;
+impl TrajectoryTimeDilation {
+    /// Limits are given per joint in the order of the trajectory's `joint_names`.  Infinite limits are
+    /// ignored.
+    pub fn dilate(trajectory: &RobotJointTrajectory, velocity_limits: &Vec<f64>, acceleration_limits: Option<&Vec<f64>>, mode: &TimeDilationMode) -> Result<RobotJointTrajectory, OptimaError> {
+        let factors = Self::segment_dilation_factors(trajectory, velocity_limits, acceleration_limits, mode)?;
+        return Self::apply_segment_dilation_factors(trajectory, &factors);
+    }
+    /// Same as `dilate`, with the velocity limits taken from the robot's URDF.  Trajectory joints are
+    /// matched to the robot's DOF axes by name, so the trajectory need not be in the canonical DOF
+    /// ordering.
+    pub fn dilate_with_robot_limits(robot_joint_state_module: &RobotJointStateModule, trajectory: &RobotJointTrajectory, acceleration_limits: Option<&Vec<f64>>, mode: &TimeDilationMode) -> Result<RobotJointTrajectory, OptimaError> {
+        let dof_joint_axis_names = RobotJointTrajectory::dof_joint_axis_names(robot_joint_state_module);
+        let dof_velocity_limits = robot_joint_state_module.get_joint_state_velocity_limits(&RobotJointStateType::DOF);
+
+        let mut velocity_limits = vec![];
+        for joint_name in trajectory.joint_names() {
+            match dof_joint_axis_names.iter().position(|n| n == joint_name) {
+                None => { return Err(OptimaError::new_generic_error_str(&format!("Trajectory joint {} is not a DOF of robot {}.", joint_name, robot_joint_state_module.robot_name()), file!(), line!())); }
+                Some(idx) => { velocity_limits.push(dof_velocity_limits[idx]); }
+            }
+        }
+
+        return Self::dilate(trajectory, &velocity_limits, acceleration_limits, mode);
+    }
+    /// Returns the factor by which each segment (between waypoints i and i + 1) must be stretched for
+    /// the trajectory to satisfy the given limits.  All factors are at least 1.
+    pub fn segment_dilation_factors(trajectory: &RobotJointTrajectory, velocity_limits: &Vec<f64>, acceleration_limits: Option<&Vec<f64>>, mode: &TimeDilationMode) -> Result<Vec<f64>, OptimaError> {
+        let num_joints = trajectory.joint_names().len();
+        if velocity_limits.len() != num_joints {
+            return Err(OptimaError::new_generic_error_str(&format!("Number of velocity limits ({}) does not match number of trajectory joints ({}).", velocity_limits.len(), num_joints), file!(), line!()));
+        }
+        if let Some(acceleration_limits) = acceleration_limits {
+            if acceleration_limits.len() != num_joints {
+                return Err(OptimaError::new_generic_error_str(&format!("Number of acceleration limits ({}) does not match number of trajectory joints ({}).", acceleration_limits.len(), num_joints), file!(), line!()));
+            }
+        }
+
+        let waypoints = trajectory.waypoints();
+        if waypoints.len() < 2 { return Ok(vec![]); }
+
+        let durations: Vec<f64> = (0..waypoints.len() - 1).map(|i| waypoints[i + 1].time_from_start() - waypoints[i].time_from_start()).collect();
+
+        let mut factors = vec![];
+        for i in 0..durations.len() {
+            factors.push(Self::segment_velocity_ratio(&waypoints[i], &waypoints[i + 1], durations[i], i, velocity_limits)?.max(1.0));
+        }
+
+        if mode == &TimeDilationMode::Uniform {
+            let max_factor = factors.iter().fold(1.0_f64, |a, b| a.max(*b));
+            factors = vec![max_factor; durations.len()];
+        }
+
+        let acceleration_limits = match acceleration_limits {
+            None => { return Ok(factors); }
+            Some(acceleration_limits) => { acceleration_limits }
+        };
+
+        if mode == &TimeDilationMode::Piecewise {
+            for _ in 0..MAX_PIECEWISE_DILATION_SWEEPS {
+                let mut violated = false;
+                for i in 1..waypoints.len() - 1 {
+                    let ratio = Self::waypoint_acceleration_ratio(waypoints, &durations, &factors, i, acceleration_limits)?;
+                    if ratio > 1.0 + ACCELERATION_RATIO_TOLERANCE {
+                        // Stretching both adjacent segments by s scales the acceleration by 1 / s^2.
+                        let s = ratio.sqrt();
+                        factors[i - 1] *= s;
+                        factors[i] *= s;
+                        violated = true;
+                    }
+                }
+                if !violated { break; }
+            }
+        }
+
+        // Stretching every segment by s also scales every acceleration by 1 / s^2, so this resolves
+        // the acceleration limits in uniform mode and any violations left over in piecewise mode.
+        let mut max_ratio: f64 = 0.0;
+        for i in 1..waypoints.len() - 1 {
+            max_ratio = max_ratio.max(Self::waypoint_acceleration_ratio(waypoints, &durations, &factors, i, acceleration_limits)?);
+        }
+        if max_ratio > 1.0 {
+            let s = max_ratio.sqrt();
+            for factor in &mut factors { *factor *= s; }
+        }
+
+        Ok(factors)
+    }
+    /// Stretches segment i of the trajectory by factors[i].  The time of the first waypoint is kept.
+    pub fn apply_segment_dilation_factors(trajectory: &RobotJointTrajectory, factors: &Vec<f64>) -> Result<RobotJointTrajectory, OptimaError> {
+        let waypoints = trajectory.waypoints();
+        if factors.len() != waypoints.len().max(1) - 1 {
+            return Err(OptimaError::new_generic_error_str(&format!("Number of dilation factors ({}) does not match number of trajectory segments ({}).", factors.len(), waypoints.len().max(1) - 1), file!(), line!()));
+        }
+
+        let mut out_waypoints = vec![];
+        let mut time = 0.0;
+        for (i, waypoint) in waypoints.iter().enumerate() {
+            if i == 0 {
+                time = waypoint.time_from_start();
+            } else {
+                time += factors[i - 1] * (waypoint.time_from_start() - waypoints[i - 1].time_from_start());
+            }
+            out_waypoints.push(RobotJointTrajectoryWaypoint::new(time, waypoint.positions().clone()));
+        }
+
+        return RobotJointTrajectory::new(trajectory.robot_name(), trajectory.joint_names().clone(), out_waypoints);
+    }
+    /// Largest ratio of a joint's segment velocity to its velocity limit.
+    fn segment_velocity_ratio(a: &RobotJointTrajectoryWaypoint, b: &RobotJointTrajectoryWaypoint, duration: f64, segment_idx: usize, velocity_limits: &Vec<f64>) -> Result<f64, OptimaError> {
+        let mut out_ratio: f64 = 0.0;
+        for (j, v) in velocity_limits.iter().enumerate() {
+            let delta = (b.positions()[j] - a.positions()[j]).abs();
+            if delta == 0.0 || v.is_infinite() { continue; }
+            if *v <= 0.0 {
+                return Err(OptimaError::new_generic_error_str(&format!("Joint {} moves in segment {} but has a velocity limit of {}.", j, segment_idx, v), file!(), line!()));
+            }
+            if duration <= 0.0 {
+                return Err(OptimaError::new_generic_error_str(&format!("Segment {} has zero duration but a non-zero displacement, so it cannot be dilated.", segment_idx), file!(), line!()));
+            }
+            out_ratio = out_ratio.max(delta / (duration * v));
+        }
+        Ok(out_ratio)
+    }
+    /// Largest ratio of a joint's acceleration at interior waypoint i to its acceleration limit, with
+    /// segment k stretched by factors[k].
+    fn waypoint_acceleration_ratio(waypoints: &Vec<RobotJointTrajectoryWaypoint>, durations: &Vec<f64>, factors: &Vec<f64>, i: usize, acceleration_limits: &Vec<f64>) -> Result<f64, OptimaError> {
+        let d0 = factors[i - 1] * durations[i - 1];
+        let d1 = factors[i] * durations[i];
+        let mean_duration = 0.5 * (d0 + d1);
+        if mean_duration <= 0.0 { return Ok(0.0); }
+
+        let mut out_ratio: f64 = 0.0;
+        for (j, a) in acceleration_limits.iter().enumerate() {
+            if a.is_infinite() { continue; }
+            let v0 = if d0 > 0.0 { (waypoints[i].positions()[j] - waypoints[i - 1].positions()[j]) / d0 } else { 0.0 };
+            let v1 = if d1 > 0.0 { (waypoints[i + 1].positions()[j] - waypoints[i].positions()[j]) / d1 } else { 0.0 };
+            let acceleration = ((v1 - v0) / mean_duration).abs();
+            if acceleration == 0.0 { continue; }
+            if *a <= 0.0 {
+                return Err(OptimaError::new_generic_error_str(&format!("Joint {} accelerates at waypoint {} but has an acceleration limit of {}.", j, i, a), file!(), line!()));
+            }
+            out_ratio = out_ratio.max(acceleration / a);
+        }
+        Ok(out_ratio)
+    }
+}
@@ -17,4 +17,18 @@ impl JsMatrix {
     pub fn matrix(&self) -> &Vec<Vec<f64>> {
         &self.matrix
     }
-}
\ No newline at end of file
+}
+#[cfg(target_arch = "wasm32")]
+impl JsMatrix {
+    pub fn to_js_object(&self) -> JsMatrixObject {
+        return JsValue::from_serde(self).unwrap().unchecked_into();
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    /// A `JsMatrix` as a plain JS object, with the matrix given as a list of rows.
+    #[wasm_bindgen(typescript_type = "{ matrix: number[][] }")]
+    pub type JsMatrixObject;
+}
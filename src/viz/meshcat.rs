@@ -0,0 +1,257 @@
+use pyo3::*;
+
+use std::net::TcpStream;
+use nalgebra::{DVector, Isometry3};
+use serde_json::{json, Value};
+use tungstenite::{Message, WebSocket};
+use tungstenite::stream::MaybeTlsStream;
+use crate::robot_modules::robot_joint_state_module::{RobotJointState, RobotJointStateType};
+use crate::robot_modules::robot_kinematics_module::{RobotFKResult, RobotKinematicsModule};
+use crate::robot_modules::robot_mesh_file_manager_module::RobotMeshFileManagerModule;
+use crate::robot_set_modules::robot_set_joint_state_module::RobotSetJointState;
+use crate::scenes::robot_geometric_shape_scene::{RobotGeometricShapeScene, RobotGeometricShapeScenePy};
+use crate::utils::utils_errors::OptimaError;
+use crate::utils::utils_files::optima_path::OptimaStemCellPath;
+use crate::utils::utils_robot::robot_joint_trajectory::RobotJointTrajectory;
+use crate::utils::utils_se3::optima_se3_pose::{OptimaSE3Pose, OptimaSE3PoseType};
+use crate::utils::utils_shape_geometry::geometric_shape::{GeometricShape, GeometricShapeSpawner};
+use crate::utils::utils_shape_geometry::trimesh_engine::TrimeshEngine;
+
+const ROBOT_COLOR: u32 = 0xaaaaaa;
+const ENVIRONMENT_COLOR: u32 = 0x4477cc;
+
+/// Publishes robots, their forward kinematics poses, environment shapes, and trajectories to a
+/// [meshcat](https://github.com/rdeits/meshcat) server over a websocket.  Start a server (e.g.,
+/// `meshcat-server` from the `meshcat` python package), open the url it prints in a browser, and
+/// connect to its websocket url (by default `ws://127.0.0.1:7000`).
+///
+/// Robot links are published under `/meshcat/<robot_name>/<link_name>` and environment objects under
+/// `/meshcat/environment/env_obj_<i>/shape_<j>`, so they can be hidden or deleted by path from the
+/// meshcat controls.  Geometry only needs to be sent once (`publish_robot_model`); afterwards,
+/// `publish_robot_state` just sends the link transforms, which is cheap enough to call every frame.
+#[pyclass]
+pub struct MeshcatVisualizer {
+    socket: WebSocket<MaybeTlsStream<TcpStream>>
+}
+impl MeshcatVisualizer {
+    pub fn connect(url: &str) -> Result<Self, OptimaError> {
+        let (socket, _) = tungstenite::connect(url).map_err(|e| OptimaError::new_generic_error_str(&format!("Could not connect to meshcat server at {}: {}", url, e), file!(), line!()))?;
+        Ok(Self { socket })
+    }
+    pub fn connect_default() -> Result<Self, OptimaError> {
+        return Self::connect("ws://127.0.0.1:7000");
+    }
+    /// Sets the object at the given path.  The object is given in the three.js json object format.
+    pub fn set_object(&mut self, path: &str, object: Value) -> Result<(), OptimaError> {
+        return self.send(json!({ "type": "set_object", "path": path, "object": object }));
+    }
+    pub fn set_transform(&mut self, path: &str, pose: &OptimaSE3Pose) -> Result<(), OptimaError> {
+        return self.set_transform_from_isometry(path, &pose.to_nalgebra_isometry());
+    }
+    pub fn set_transform_from_isometry(&mut self, path: &str, isometry: &Isometry3<f64>) -> Result<(), OptimaError> {
+        // three.js matrices are column major, the same as nalgebra's storage order.
+        let matrix: Vec<f64> = isometry.to_homogeneous().as_slice().to_vec();
+        return self.send(json!({ "type": "set_transform", "path": path, "matrix": matrix }));
+    }
+    /// Deletes the object at the given path, along with all of its children.
+    pub fn delete(&mut self, path: &str) -> Result<(), OptimaError> {
+        return self.send(json!({ "type": "delete", "path": path }));
+    }
+    /// Sends the mesh of every link that has one.  Links are placed at the identity pose until a
+    /// state is published.
+    pub fn publish_robot_model(&mut self, robot_name: &str) -> Result<(), OptimaError> {
+        let robot_mesh_file_manager_module = RobotMeshFileManagerModule::new_from_name(robot_name)?;
+        let paths = robot_mesh_file_manager_module.get_paths_to_meshes()?;
+        let links = robot_mesh_file_manager_module.links();
+        for (link_idx, path) in paths.iter().enumerate() {
+            if let Some(path) = path {
+                let trimesh_engine = path.load_file_to_trimesh_engine()?;
+                let object_path = Self::link_path(robot_name, links[link_idx].name());
+                self.set_object(&object_path, Self::mesh_object_json(&object_path, &trimesh_engine, ROBOT_COLOR, 1.0))?;
+            }
+        }
+        Ok(())
+    }
+    pub fn publish_robot_fk_result(&mut self, robot_name: &str, fk_res: &RobotFKResult) -> Result<(), OptimaError> {
+        for link_entry in fk_res.link_entries() {
+            if let Some(pose) = link_entry.pose() {
+                self.set_transform(&Self::link_path(robot_name, link_entry.link_name()), pose)?;
+            }
+        }
+        Ok(())
+    }
+    pub fn publish_robot_state(&mut self, robot_kinematics_module: &RobotKinematicsModule, robot_joint_state: &RobotJointState) -> Result<(), OptimaError> {
+        let fk_res = robot_kinematics_module.compute_fk(robot_joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+        let robot_name = robot_kinematics_module.robot_configuration_module().robot_name().to_string();
+        return self.publish_robot_fk_result(&robot_name, &fk_res);
+    }
+    /// Sends the shapes of every environment object in the scene, posed at the given robot set
+    /// state (which matters for objects attached to robot links).  Robot link shapes in the scene
+    /// are not sent; use `publish_robot_model` for those.
+    pub fn publish_environment(&mut self, scene: &RobotGeometricShapeScene, robot_set_joint_state: &RobotSetJointState) -> Result<(), OptimaError> {
+        let poses = scene.recover_poses(robot_set_joint_state, None)?;
+        let shapes = scene.shape_collection().shapes();
+        for env_obj_idx in 0..scene.num_env_objs() {
+            let shape_idxs = scene.get_shape_idxs_from_env_obj_idx(env_obj_idx)?;
+            for (i, shape_idx) in shape_idxs.iter().enumerate() {
+                let pose = match &poses.poses()[*shape_idx] {
+                    None => { continue; }
+                    Some(pose) => { pose }
+                };
+                let shape = &shapes[*shape_idx];
+                let object_path = format!("/meshcat/environment/env_obj_{}/shape_{}", env_obj_idx, i);
+                self.set_object(&object_path, Self::geometric_shape_object_json(&object_path, shape)?)?;
+                self.set_transform_from_isometry(&object_path, &shape.isometry_at_pose(pose))?;
+            }
+        }
+        Ok(())
+    }
+    /// Sends the trajectory as a meshcat animation, sampled at the given number of frames per
+    /// second.  The animation plays once and can be replayed or scrubbed from the meshcat controls.
+    /// The robot model should already be published.
+    pub fn publish_trajectory(&mut self, robot_kinematics_module: &RobotKinematicsModule, trajectory: &RobotJointTrajectory, fps: f64) -> Result<(), OptimaError> {
+        if !(fps > 0.0) {
+            return Err(OptimaError::new_generic_error_str(&format!("Animation fps must be positive (given {}).", fps), file!(), line!()));
+        }
+        let robot_name = robot_kinematics_module.robot_configuration_module().robot_name().to_string();
+        let robot_joint_states = trajectory.to_robot_joint_states(robot_kinematics_module.robot_joint_state_module(), &RobotJointStateType::DOF)?;
+
+        let links = robot_kinematics_module.robot_configuration_module().robot_model_module().links();
+        let mut position_keys: Vec<Vec<Value>> = vec![vec![]; links.len()];
+        let mut quaternion_keys: Vec<Vec<Value>> = vec![vec![]; links.len()];
+        for (waypoint, robot_joint_state) in trajectory.waypoints().iter().zip(robot_joint_states.iter()) {
+            let frame = waypoint.time_from_start() * fps;
+            let fk_res = robot_kinematics_module.compute_fk(robot_joint_state, &OptimaSE3PoseType::ImplicitDualQuaternion)?;
+            for link_entry in fk_res.link_entries() {
+                if let Some(pose) = link_entry.pose() {
+                    let isometry = pose.to_nalgebra_isometry();
+                    let t = &isometry.translation.vector;
+                    let q = &isometry.rotation;
+                    position_keys[link_entry.link_idx()].push(json!({ "time": frame, "value": [t[0], t[1], t[2]] }));
+                    quaternion_keys[link_entry.link_idx()].push(json!({ "time": frame, "value": [q.i, q.j, q.k, q.w] }));
+                }
+            }
+        }
+
+        let mut animations = vec![];
+        for (link_idx, link) in links.iter().enumerate() {
+            if position_keys[link_idx].is_empty() { continue; }
+            animations.push(json!({
+                "path": Self::link_path(&robot_name, link.name()),
+                "clip": {
+                    "fps": fps,
+                    "name": "default",
+                    "tracks": [
+                        { "name": ".position", "type": "vector3", "keys": position_keys[link_idx] },
+                        { "name": ".quaternion", "type": "quaternion", "keys": quaternion_keys[link_idx] }
+                    ]
+                }
+            }));
+        }
+
+        return self.send(json!({
+            "type": "set_animation",
+            "path": "",
+            "animations": animations,
+            "options": { "play": true, "repetitions": 1 }
+        }));
+    }
+    fn send(&mut self, command: Value) -> Result<(), OptimaError> {
+        let bytes = rmp_serde::to_vec_named(&command).map_err(|e| OptimaError::new_generic_error_str(&format!("Could not encode meshcat command: {}", e), file!(), line!()))?;
+        self.socket.write_message(Message::Binary(bytes)).map_err(|e| OptimaError::new_generic_error_str(&format!("Could not send meshcat command: {}", e), file!(), line!()))?;
+        Ok(())
+    }
+    fn link_path(robot_name: &str, link_name: &str) -> String {
+        return format!("/meshcat/{}/{}", robot_name, link_name);
+    }
+    fn geometric_shape_object_json(object_path: &str, shape: &GeometricShape) -> Result<Value, OptimaError> {
+        let geometry = match shape.spawner() {
+            GeometricShapeSpawner::Cube { half_extent_x, half_extent_y, half_extent_z, .. } => {
+                json!({ "uuid": format!("{}/geometry", object_path), "type": "BoxGeometry", "width": 2.0 * half_extent_x, "height": 2.0 * half_extent_y, "depth": 2.0 * half_extent_z })
+            }
+            GeometricShapeSpawner::Sphere { radius, .. } => {
+                json!({ "uuid": format!("{}/geometry", object_path), "type": "SphereGeometry", "radius": radius, "widthSegments": 20, "heightSegments": 20 })
+            }
+            GeometricShapeSpawner::ConvexShape { path_string_components, trimesh_engine, .. } |
+            GeometricShapeSpawner::TriangleMesh { path_string_components, trimesh_engine, .. } => {
+                match trimesh_engine {
+                    Some(trimesh_engine) => { Self::mesh_geometry_json(object_path, trimesh_engine) }
+                    None => {
+                        let path = OptimaStemCellPath::new_asset_path_from_string_components(path_string_components)?;
+                        Self::mesh_geometry_json(object_path, &path.load_file_to_trimesh_engine()?)
+                    }
+                }
+            }
+        };
+        return Ok(Self::object_json(object_path, geometry, ENVIRONMENT_COLOR, 0.6));
+    }
+    fn mesh_object_json(object_path: &str, trimesh_engine: &TrimeshEngine, color: u32, opacity: f64) -> Value {
+        return Self::object_json(object_path, Self::mesh_geometry_json(object_path, trimesh_engine), color, opacity);
+    }
+    fn mesh_geometry_json(object_path: &str, trimesh_engine: &TrimeshEngine) -> Value {
+        let mut positions = vec![];
+        for v in trimesh_engine.vertices() {
+            positions.push(v[0]);
+            positions.push(v[1]);
+            positions.push(v[2]);
+        }
+        let mut indices = vec![];
+        for face in trimesh_engine.indices() {
+            indices.push(face[0]);
+            indices.push(face[1]);
+            indices.push(face[2]);
+        }
+        return json!({
+            "uuid": format!("{}/geometry", object_path),
+            "type": "BufferGeometry",
+            "data": {
+                "attributes": { "position": { "itemSize": 3, "type": "Float32Array", "array": positions, "normalized": false } },
+                "index": { "itemSize": 1, "type": "Uint32Array", "array": indices, "normalized": false }
+            }
+        });
+    }
+    fn object_json(object_path: &str, geometry: Value, color: u32, opacity: f64) -> Value {
+        let geometry_uuid = format!("{}/geometry", object_path);
+        let material_uuid = format!("{}/material", object_path);
+        return json!({
+            "metadata": { "version": 4.5, "type": "Object" },
+            "geometries": [ geometry ],
+            "materials": [ { "uuid": material_uuid, "type": "MeshLambertMaterial", "color": color, "transparent": opacity < 1.0, "opacity": opacity } ],
+            "object": {
+                "uuid": object_path,
+                "type": "Mesh",
+                "geometry": geometry_uuid,
+                "material": material_uuid,
+                "matrix": [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0]
+            }
+        });
+    }
+}
+
+#[pymethods]
+impl MeshcatVisualizer {
+    #[new]
+    #[args(url = "\"ws://127.0.0.1:7000\"")]
+    pub fn new_py(url: &str) -> Self {
+        return Self::connect(url).expect("error");
+    }
+    pub fn delete_py(&mut self, path: &str) {
+        self.delete(path).expect("error");
+    }
+    pub fn publish_robot_model_py(&mut self, robot_name: &str) {
+        self.publish_robot_model(robot_name).expect("error");
+    }
+    pub fn publish_robot_state_py(&mut self, robot_kinematics_module: &RobotKinematicsModule, joint_state: Vec<f64>) {
+        let robot_joint_state = robot_kinematics_module.robot_joint_state_module().spawn_robot_joint_state_try_auto_type(DVector::from_vec(joint_state)).expect("error");
+        self.publish_robot_state(robot_kinematics_module, &robot_joint_state).expect("error");
+    }
+    pub fn publish_environment_py(&mut self, scene: &RobotGeometricShapeScenePy, robot_set_joint_state: Vec<f64>) {
+        let scene = scene.robot_geometric_shape_scene();
+        let robot_set_joint_state = scene.robot_set().robot_set_joint_state_module().spawn_robot_set_joint_state_try_auto_type(DVector::from_vec(robot_set_joint_state)).expect("error");
+        self.publish_environment(scene, &robot_set_joint_state).expect("error");
+    }
+    #[args(fps = "30.0")]
+    pub fn publish_trajectory_py(&mut self, robot_kinematics_module: &RobotKinematicsModule, trajectory: &RobotJointTrajectory, fps: f64) {
+        self.publish_trajectory(robot_kinematics_module, trajectory, fps).expect("error");
+    }
+}
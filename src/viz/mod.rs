@@ -0,0 +1,5 @@
+//! Optional visualization backends, enabled with the `viz` cargo feature.  Currently the only
+//! backend is [meshcat](https://github.com/rdeits/meshcat), which renders the robot and its
+//! environment in a web browser.
+
+pub mod meshcat;